@@ -57,11 +57,83 @@ fn bench_normalize_path_complexity(c: &mut Criterion) {
     group.finish();
 }
 
+/// Synthetic `formula.json`-shaped payload (~7000 formulae, like homebrew/core)
+fn synthetic_formulae_json() -> Vec<u8> {
+    let formulae: Vec<serde_json::Value> = (0..7000)
+        .map(|i| {
+            serde_json::json!({
+                "name": format!("formula{}", i),
+                "full_name": format!("formula{}", i),
+                "desc": "A synthetic formula used for benchmarking",
+                "homepage": "https://example.com",
+                "license": "MIT",
+                "versions": {"stable": "1.2.3", "head": null, "bottle": true},
+                "dependencies": [format!("formula{}", i / 2), "openssl@3"],
+                "build_dependencies": ["pkgconf"],
+                "bottle": {"stable": {"rebuild": 0, "files": {
+                    "arm64_sequoia": {"cellar": ":any", "url": "https://ghcr.io/v2/homebrew/core/x/blobs/sha256:0", "sha256": "0"},
+                    "arm64_sonoma": {"cellar": ":any", "url": "https://ghcr.io/v2/homebrew/core/x/blobs/sha256:1", "sha256": "1"},
+                    "x86_64_linux": {"cellar": ":any", "url": "https://ghcr.io/v2/homebrew/core/x/blobs/sha256:2", "sha256": "2"}
+                }}},
+                "keg_only": false
+            })
+        })
+        .collect();
+    serde_json::to_vec(&formulae).unwrap()
+}
+
+fn bench_uses_lookup(c: &mut Criterion) {
+    use kombrucha::{Formula, FormulaBrief};
+
+    let full = synthetic_formulae_json();
+    let briefs: Vec<FormulaBrief> = serde_json::from_slice(&full).unwrap();
+    let index = serde_json::to_vec(&briefs).unwrap();
+    let depends_on = |deps: &[String]| deps.iter().any(|d| d == "openssl@3");
+
+    let mut group = c.benchmark_group("uses");
+
+    // Before: materialize every Formula just to read dependencies
+    group.bench_function("full_formulae", |b| {
+        b.iter(|| {
+            let formulae: Vec<Formula> = serde_json::from_slice(black_box(&full)).unwrap();
+            formulae
+                .iter()
+                .filter(|f| depends_on(&f.dependencies))
+                .count()
+        })
+    });
+
+    // Cold index: parse the full cache into briefs, skipping unused fields
+    group.bench_function("brief_from_full_cache", |b| {
+        b.iter(|| {
+            let formulae: Vec<FormulaBrief> = serde_json::from_slice(black_box(&full)).unwrap();
+            formulae
+                .iter()
+                .filter(|f| depends_on(&f.dependencies))
+                .count()
+        })
+    });
+
+    // Warm index: load formulae-brief.json
+    group.bench_function("brief_index", |b| {
+        b.iter(|| {
+            let formulae: Vec<FormulaBrief> = serde_json::from_slice(black_box(&index)).unwrap();
+            formulae
+                .iter()
+                .filter(|f| depends_on(&f.dependencies))
+                .count()
+        })
+    });
+
+    group.finish();
+}
+
 criterion_group!(
     benches,
     bench_normalize_path,
     bench_normalize_path_single,
     bench_normalize_path_complexity,
-    bench_list_installed
+    bench_list_installed,
+    bench_uses_lookup
 );
 criterion_main!(benches);
@@ -0,0 +1,42 @@
+//! Embeds build information shown by `bru config`: the git commit, build date, and
+//! target triple.
+
+use std::path::Path;
+use std::process::Command;
+
+fn main() {
+    let git_sha = Command::new("git")
+        .args(["rev-parse", "--short=10", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .and_then(|output| String::from_utf8(output.stdout).ok())
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // SOURCE_DATE_EPOCH keeps reproducible builds reproducible
+    let build_date = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse().ok())
+        .and_then(|epoch| chrono::DateTime::from_timestamp(epoch, 0))
+        .unwrap_or_else(chrono::Utc::now)
+        .format("%Y-%m-%d")
+        .to_string();
+
+    println!("cargo:rustc-env=BRU_GIT_SHA={}", git_sha);
+    println!("cargo:rustc-env=BRU_BUILD_DATE={}", build_date);
+    println!(
+        "cargo:rustc-env=BRU_TARGET={}",
+        std::env::var("TARGET").unwrap_or_default()
+    );
+
+    // Only rerun when the checked-out commit changes (a missing path would force a
+    // rerun on every build, e.g. in a crates.io tarball)
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    for path in [".git/HEAD", ".git/index"] {
+        if Path::new(path).exists() {
+            println!("cargo:rerun-if-changed={}", path);
+        }
+    }
+}
@@ -97,6 +97,7 @@ async fn main() -> anyhow::Result<()> {
             bottle_rebuild: 0,
             pkg_version: "0.0.0".to_string(),
             declared_directly: idx == 0,
+            extra: Default::default(),
         })
         .collect();
 
@@ -48,21 +48,21 @@ async fn main() -> anyhow::Result<()> {
     println!("TEST 1: cleanup(dry_run: true)");
     println!("─────────────────────────────────────────────────────────────");
 
-    match pm.cleanup(true) {
+    match pm.cleanup(&[], true) {
         Ok(result) => {
             println!("✓ Dry-run completed without errors");
             println!("\nResults:");
             println!("  Versions that would be removed: {}", result.removed.len());
             println!(
                 "  Space that would be freed: {:.2} MB",
-                result.space_freed_mb
+                result.total_freed_bytes as f64 / 1_048_576.0
             );
             println!("  Errors during scan: {}", result.errors.len());
 
             if !result.removed.is_empty() {
                 println!("\nWould remove:");
                 for item in result.removed.iter().take(5) {
-                    println!("    • {}", item);
+                    println!("    • {}/{}", item.name, item.version);
                 }
                 if result.removed.len() > 5 {
                     println!("    ... and {} more", result.removed.len() - 5);
@@ -94,18 +94,21 @@ async fn main() -> anyhow::Result<()> {
     println!("TEST 2: cleanup(dry_run: false) - Actual Cleanup");
     println!("─────────────────────────────────────────────────────────────");
 
-    match pm.cleanup(false) {
+    match pm.cleanup(&[], false) {
         Ok(result) => {
             println!("✓ Cleanup completed");
             println!("\nResults:");
             println!("  Versions actually removed: {}", result.removed.len());
-            println!("  Space actually freed: {:.2} MB", result.space_freed_mb);
+            println!(
+                "  Space actually freed: {:.2} MB",
+                result.total_freed_bytes as f64 / 1_048_576.0
+            );
             println!("  Errors during cleanup: {}", result.errors.len());
 
             if !result.removed.is_empty() {
                 println!("\nRemoved:");
                 for item in result.removed.iter().take(5) {
-                    println!("    • {}", item);
+                    println!("    • {}/{}", item.name, item.version);
                 }
                 if result.removed.len() > 5 {
                     println!("    ... and {} more", result.removed.len() - 5);
@@ -158,14 +158,14 @@ async fn main() -> anyhow::Result<()> {
     println!("─────────────────────────────────────────────────────────────");
 
     let cleanup_start = Instant::now();
-    let cleanup_result = pm.cleanup(true)?;
+    let cleanup_result = pm.cleanup(&[], true)?;
     let cleanup_time = cleanup_start.elapsed();
 
     println!("Cleanup dry-run results:");
     println!("  Versions to remove: {}", cleanup_result.removed.len());
     println!(
         "  Space that would be freed: {:.2} MB",
-        cleanup_result.space_freed_mb
+        cleanup_result.total_freed_bytes as f64 / 1_048_576.0
     );
     println!("  Errors encountered: {}", cleanup_result.errors.len());
     println!(
@@ -176,7 +176,7 @@ async fn main() -> anyhow::Result<()> {
     if !cleanup_result.removed.is_empty() {
         println!("\nWould remove:");
         for item in cleanup_result.removed.iter().take(5) {
-            println!("    • {}", item);
+            println!("    • {}/{}", item.name, item.version);
         }
         if cleanup_result.removed.len() > 5 {
             println!("    ... and {} more", cleanup_result.removed.len() - 5);
@@ -228,8 +228,11 @@ async fn main() -> anyhow::Result<()> {
     assert!(health_check.homebrew_available);
     assert!(health_check.cellar_exists);
 
-    let cleanup = pm.cleanup(true)?;
-    assert!(cleanup.space_freed_mb >= 0.0);
+    let cleanup = pm.cleanup(&[], true)?;
+    assert_eq!(
+        cleanup.total_freed_bytes,
+        cleanup.freed_bytes.values().sum::<u64>()
+    );
 
     let outdated_list = pm.outdated().await?;
     if !outdated_list.is_empty() {
@@ -256,7 +259,7 @@ async fn main() -> anyhow::Result<()> {
     println!("  ✓ dependencies() - Dependency resolution");
     println!(
         "  ✓ cleanup() - Would free {:.2} MB",
-        cleanup_result.space_freed_mb
+        cleanup_result.total_freed_bytes as f64 / 1_048_576.0
     );
     println!("  ✓ uses() - Reverse dependency lookup");
     println!("  ✓ Type safety - All result types verified");
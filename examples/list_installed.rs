@@ -71,7 +71,7 @@ fn main() -> anyhow::Result<()> {
                     println!("  {} {}", marker, pkg.version);
 
                     // Show installation details if receipt is available
-                    if let Some(receipt) = &pkg.receipt {
+                    if let Some(receipt) = pkg.receipt() {
                         if receipt.installed_on_request {
                             print!(" (installed on request)");
                         }
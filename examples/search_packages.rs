@@ -33,7 +33,7 @@ async fn main() -> anyhow::Result<()> {
     let api = BrewApi::new()?;
     println!("Fetching from API (this may take a moment on first run)...\n");
 
-    match api.search(&query).await {
+    match api.search(&query, Default::default()).await {
         Ok(results) => {
             let total = results.formulae.len() + results.casks.len();
 
@@ -58,6 +58,8 @@ async fn main() -> anyhow::Result<()> {
             println!("  From:        {}", result.from_version);
             println!("  To:          {}", result.to_version);
             println!("  Path:        {}", result.path.display());
+            println!("  Linked:      {} files", result.linked_files);
+            println!("  Freed:       {} bytes", result.freed_bytes);
             println!("  Time:        {:.2}ms\n", result.time_ms as f64);
 
             // Verify new version is installed
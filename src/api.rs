@@ -34,7 +34,7 @@
 //!     println!("Latest version: {}", formula.versions.stable.unwrap_or_default());
 //!
 //!     // Search across all packages
-//!     let results = api.search("python").await?;
+//!     let results = api.search("python", Default::default()).await?;
 //!     println!("Found {} formulae", results.formulae.len());
 //!
 //!     Ok(())
@@ -42,12 +42,191 @@
 //! ```
 
 use crate::error::Result;
+use futures::future::BoxFuture;
 use serde::{Deserialize, Serialize};
+use std::path::Path;
 use std::sync::Arc;
 use std::time::Duration;
 
 const HOMEBREW_API_BASE: &str = "https://formulae.brew.sh/api";
-const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+const GITHUB_API_BASE: &str = "https://api.github.com";
+
+/// Longest formula or cask name accepted by [`validate_name`]
+const MAX_NAME_LEN: usize = 128;
+pub(crate) const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many times a 429 response is retried before it's returned to the caller
+const MAX_THROTTLE_RETRIES: u32 = 3;
+/// Longest `Retry-After` wait honored; longer requests fail instead of stalling
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(30);
+
+/// Limits on how hard a [`BrewApi`] hits the Homebrew API.
+///
+/// Every API request waits for a free slot, so a wide dependency level resolved with
+/// `join_all` never opens more than `max_concurrent` connections at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    /// Requests allowed in flight at the same time
+    pub max_concurrent: usize,
+    /// Optional cap on requests started per second
+    pub requests_per_second: Option<f64>,
+}
+
+impl Default for RateLimit {
+    /// 8 concurrent requests with no per-second cap
+    fn default() -> Self {
+        Self {
+            max_concurrent: 8,
+            requests_per_second: None,
+        }
+    }
+}
+
+/// Shared state behind [`RateLimit`]: a semaphore plus the next allowed start time
+struct Limiter {
+    permits: tokio::sync::Semaphore,
+    min_interval: Option<Duration>,
+    next_start: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl Limiter {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            permits: tokio::sync::Semaphore::new(limit.max_concurrent.max(1)),
+            min_interval: limit
+                .requests_per_second
+                .filter(|rps| *rps > 0.0)
+                .map(|rps| Duration::from_secs_f64(1.0 / rps)),
+            next_start: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    /// Wait for a concurrency slot and, with a per-second cap, for the next start time
+    async fn acquire(&self) -> tokio::sync::SemaphorePermit<'_> {
+        let permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("rate limiter semaphore is never closed");
+        if let Some(interval) = self.min_interval {
+            let start = {
+                let mut next = self.next_start.lock().await;
+                let start = (*next).max(tokio::time::Instant::now());
+                *next = start + interval;
+                start
+            };
+            tokio::time::sleep_until(start).await;
+        }
+        permit
+    }
+}
+
+/// A response from [`BrewApi::api_get`], which keeps its rate-limiter slot until the
+/// body has been read
+struct ApiResponse<'a> {
+    response: reqwest::Response,
+    _permit: tokio::sync::SemaphorePermit<'a>,
+}
+
+impl ApiResponse<'_> {
+    fn status(&self) -> reqwest::StatusCode {
+        self.response.status()
+    }
+
+    fn error_for_status(self) -> Result<Self> {
+        Ok(Self {
+            response: self.response.error_for_status()?,
+            _permit: self._permit,
+        })
+    }
+
+    async fn json<T: serde::de::DeserializeOwned>(self) -> Result<T> {
+        Ok(self.response.json().await?)
+    }
+
+    async fn text(self) -> Result<String> {
+        Ok(self.response.text().await?)
+    }
+}
+
+/// How long a 429 response asks us to wait (`Retry-After` in seconds, default 1s)
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Duration {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u64>().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(Duration::from_secs(1))
+}
+
+/// Build the HTTP client used for API requests and bottle downloads.
+///
+/// `timeout` bounds connecting and each read rather than the whole request, so large
+/// bottles aren't cut off mid-download while stalled connections still fail fast.
+///
+/// # Proxies
+///
+/// Proxies are resolved in this order (first match wins):
+/// 1. `proxy` - set programmatically, used for every scheme
+/// 2. `HOMEBREW_HTTPS_PROXY`, `HOMEBREW_HTTP_PROXY`, `HOMEBREW_ALL_PROXY`
+/// 3. `HTTPS_PROXY`, `HTTP_PROXY`, `ALL_PROXY` (or their lowercase forms)
+///
+/// Hosts listed in `HOMEBREW_NO_PROXY` (falling back to `NO_PROXY`/`no_proxy`) bypass
+/// the proxy in every case.
+pub(crate) fn build_http_client(timeout: Duration, proxy: Option<&str>) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(timeout)
+        .read_timeout(timeout)
+        .pool_idle_timeout(Duration::from_secs(90)) // HTTP keep-alive standard
+        .pool_max_idle_per_host(10) // Reuse connections during parallel resolution
+        .tcp_keepalive(Duration::from_secs(60))
+        .user_agent(format!("bru/{}", env!("CARGO_PKG_VERSION")));
+
+    for proxy in proxies(proxy, |key| std::env::var(key).ok())? {
+        builder = builder.proxy(proxy);
+    }
+
+    Ok(builder.build()?)
+}
+
+/// Resolve proxies from an explicit URL and the environment.
+///
+/// Returns an empty list when nothing is configured, leaving reqwest's own system
+/// proxy detection in place.
+fn proxies(
+    explicit: Option<&str>,
+    env: impl Fn(&str) -> Option<String>,
+) -> Result<Vec<reqwest::Proxy>> {
+    // First non-empty value among the given variables
+    let lookup = |keys: &[&str]| {
+        keys.iter()
+            .filter_map(|key| env(key))
+            .find(|value| !value.is_empty())
+    };
+
+    let no_proxy = lookup(&["HOMEBREW_NO_PROXY", "NO_PROXY", "no_proxy"])
+        .and_then(|hosts| reqwest::NoProxy::from_string(&hosts));
+
+    let mut proxies = Vec::new();
+    if let Some(url) = explicit {
+        proxies.push(reqwest::Proxy::all(url)?);
+    } else {
+        if let Some(url) = lookup(&["HOMEBREW_HTTPS_PROXY", "HTTPS_PROXY", "https_proxy"]) {
+            proxies.push(reqwest::Proxy::https(&url)?);
+        }
+        if let Some(url) = lookup(&["HOMEBREW_HTTP_PROXY", "HTTP_PROXY", "http_proxy"]) {
+            proxies.push(reqwest::Proxy::http(&url)?);
+        }
+        if let Some(url) = lookup(&["HOMEBREW_ALL_PROXY", "ALL_PROXY", "all_proxy"]) {
+            proxies.push(reqwest::Proxy::all(&url)?);
+        }
+    }
+
+    Ok(proxies
+        .into_iter()
+        .map(|proxy| proxy.no_proxy(no_proxy.clone()))
+        .collect())
+}
 
 /// Keg-only reason metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -68,17 +247,405 @@ pub struct Formula {
     #[serde(default)]
     pub homepage: Option<String>,
     #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
     pub versions: Versions,
+    /// Bumped when the formula changes without a new upstream version; kegs are named
+    /// `<version>_<revision>` once it's non-zero
+    #[serde(default)]
+    pub revision: u32,
     #[serde(default)]
     pub dependencies: Vec<String>,
     #[serde(default)]
     pub build_dependencies: Vec<String>,
+    /// Installed by default; skipped with `--without-<dep>`
+    #[serde(default)]
+    pub recommended_dependencies: Vec<String>,
+    /// Skipped by default; installed with `--with-<dep>`
+    #[serde(default)]
+    pub optional_dependencies: Vec<String>,
+    /// Only needed to run the formula's `test do` block
+    #[serde(default)]
+    pub test_dependencies: Vec<String>,
+    /// Non-formula requirements such as a minimum macOS or Xcode version
+    #[serde(default)]
+    pub requirements: Vec<Requirement>,
+    /// Formulae that install the same files, so they can't be linked together
+    #[serde(default)]
+    pub conflicts_with: Vec<String>,
+    /// Why each `conflicts_with` entry conflicts, in the same order
+    #[serde(default)]
+    pub conflicts_with_reasons: Vec<Option<String>>,
     #[serde(default)]
     pub bottle: Option<Bottle>,
     #[serde(default)]
     pub keg_only: bool,
     #[serde(default)]
     pub keg_only_reason: Option<KegOnlyReason>,
+    /// Post-install notes; render with [`cellar::render_caveats`](crate::cellar::render_caveats)
+    #[serde(default)]
+    pub caveats: Option<String>,
+}
+
+/// A non-formula requirement (`depends_on macos: :sonoma`, `depends_on xcode: "15"`, ...)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Requirement {
+    /// Requirement kind: `macos`, `maximum_macos`, `xcode`, `arch`, `linux`, ...
+    pub name: String,
+    /// Version bound for `macos`/`maximum_macos`/`xcode`, or the CPU for `arch`
+    #[serde(default, deserialize_with = "lenient_string")]
+    pub version: Option<String>,
+    /// When the requirement applies, e.g. `["build"]`; empty means always
+    #[serde(default)]
+    pub contexts: Vec<String>,
+}
+
+impl Requirement {
+    /// Whether this bounds the macOS release (`macos` or `maximum_macos`)
+    pub fn is_macos(&self) -> bool {
+        matches!(self.name.as_str(), "macos" | "maximum_macos")
+    }
+
+    /// Whether the requirement only matters when building from source
+    pub fn build_only(&self) -> bool {
+        !self.contexts.is_empty() && self.contexts.iter().all(|c| c == "build" || c == "test")
+    }
+
+    /// Check the requirement against the running system.
+    ///
+    /// `macos_version` is the macOS product version, or `None` when not on macOS.
+    /// Returns `None` for requirements that can't be checked here (like Xcode).
+    ///
+    /// As in Homebrew, a versioned `macos` requirement only bounds macOS releases and
+    /// is met off macOS; a bare `depends_on :macos` is not.
+    pub fn satisfied(&self, macos_version: Option<&str>, arch: &str) -> Option<bool> {
+        match self.name.as_str() {
+            "macos" => Some(match (macos_version, &self.version) {
+                (Some(current), Some(min)) => compare_macos_versions(current, min).is_ge(),
+                (Some(_), None) => true,
+                (None, version) => version.is_some(),
+            }),
+            "maximum_macos" => Some(match (macos_version, &self.version) {
+                (Some(current), Some(max)) => compare_macos_versions(current, max).is_le(),
+                _ => true,
+            }),
+            "linux" => Some(macos_version.is_none()),
+            "arch" => {
+                let arch = if arch == "aarch64" { "arm64" } else { arch };
+                let wanted = self.version.as_deref()?;
+                let wanted = if wanted == "arm" { "arm64" } else { wanted };
+                Some(wanted == arch)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Requirement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version = self.version.as_deref();
+        match (self.name.as_str(), version) {
+            ("macos", Some(v)) => write!(f, "macOS >= {}", v)?,
+            ("macos", None) => f.write_str("macOS")?,
+            ("maximum_macos", Some(v)) => write!(f, "macOS <= {}", v)?,
+            ("xcode", Some(v)) => write!(f, "Xcode >= {}", v)?,
+            ("xcode", None) => f.write_str("Xcode")?,
+            ("arch", Some(v)) => write!(f, "{} architecture", v)?,
+            ("linux", _) => f.write_str("Linux")?,
+            (name, Some(v)) => write!(f, "{} {}", name, v)?,
+            (name, None) => f.write_str(name)?,
+        }
+        if !self.contexts.is_empty() {
+            write!(f, " [{}]", self.contexts.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Accept a string or a number (the API emits both for versions); anything else is `None`
+fn lenient_string<'de, D>(deserializer: D) -> std::result::Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::String(s) => Some(s),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    })
+}
+
+/// How a formula declares one of its dependencies (`brew deps --annotate` tags)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyTag {
+    Runtime,
+    Recommended,
+    Optional,
+    Build,
+    Test,
+}
+
+impl std::fmt::Display for DependencyTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Runtime => "runtime",
+            Self::Recommended => "recommended",
+            Self::Optional => "optional",
+            Self::Build => "build",
+            Self::Test => "test",
+        })
+    }
+}
+
+impl Formula {
+    /// Tap the formula comes from: the tap part of a qualified `full_name`
+    /// (`user/repo/name`), otherwise `homebrew/core`
+    pub fn tap(&self) -> &str {
+        match self.full_name.rsplit_once('/') {
+            Some((tap, _)) if tap.contains('/') => tap,
+            _ => "homebrew/core",
+        }
+    }
+
+    /// The version an install pours: the stable version.
+    ///
+    /// Bottles are only built for stable versions, so a HEAD-only formula can't be
+    /// installed from a bottle; `brew install --HEAD` builds it from source instead.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`BruError::NoStableVersion`](crate::error::BruError::NoStableVersion) if
+    /// the formula has no stable version.
+    pub fn install_version(&self) -> Result<&str> {
+        self.versions
+            .stable
+            .as_deref()
+            .ok_or_else(|| crate::error::BruError::NoStableVersion(self.name.clone()))
+    }
+
+    /// The stable version with its revision, as kegs are named (`14.1.0_1`).
+    ///
+    /// `None` for HEAD-only formulae.
+    pub fn pkg_version(&self) -> Option<String> {
+        let stable = self.versions.stable.as_deref()?;
+        Some(match self.revision {
+            0 => stable.to_string(),
+            revision => format!("{}_{}", stable, revision),
+        })
+    }
+
+    /// Every stable bottle the formula ships, sorted by platform tag.
+    ///
+    /// Empty for formulae without bottles. Sizes are left as `None`; fill them in with
+    /// [`download::bottle_file_size`](crate::download::bottle_file_size) if needed.
+    pub fn bottle_tags_with_meta(&self) -> Vec<BottleFileInfo> {
+        let Some(data) = self.bottle.as_ref().and_then(|b| b.stable.as_ref()) else {
+            return Vec::new();
+        };
+        let mut files: Vec<_> = data
+            .files
+            .iter()
+            .map(|(tag, file)| BottleFileInfo {
+                tag: tag.clone(),
+                cellar: file.cellar.clone(),
+                url: file.url.clone(),
+                sha256: file.sha256.clone(),
+                size: None,
+            })
+            .collect();
+        files.sort_by(|a, b| a.tag.cmp(&b.tag));
+        files
+    }
+
+    /// Each conflicting formula with the reason given for it, if any
+    pub fn conflicts(&self) -> Vec<(&str, Option<&str>)> {
+        self.conflicts_with
+            .iter()
+            .enumerate()
+            .map(|(i, name)| {
+                let reason = self
+                    .conflicts_with_reasons
+                    .get(i)
+                    .and_then(|r| r.as_deref());
+                (name.as_str(), reason)
+            })
+            .collect()
+    }
+
+    /// Every declared dependency once, with all the ways it is declared.
+    ///
+    /// A dependency listed in several sections (say `dependencies` and
+    /// `build_dependencies`) appears a single time carrying both tags. Order follows
+    /// first declaration: runtime, recommended, optional, build, then test.
+    pub fn tagged_dependencies(&self) -> Vec<(&str, Vec<DependencyTag>)> {
+        let sections = [
+            (DependencyTag::Runtime, &self.dependencies),
+            (DependencyTag::Recommended, &self.recommended_dependencies),
+            (DependencyTag::Optional, &self.optional_dependencies),
+            (DependencyTag::Build, &self.build_dependencies),
+            (DependencyTag::Test, &self.test_dependencies),
+        ];
+
+        let mut tagged: Vec<(&str, Vec<DependencyTag>)> = Vec::new();
+        for (tag, deps) in sections {
+            for dep in deps {
+                match tagged.iter_mut().find(|(name, _)| name == dep) {
+                    Some((_, tags)) if !tags.contains(&tag) => tags.push(tag),
+                    Some(_) => {}
+                    None => tagged.push((dep, vec![tag])),
+                }
+            }
+        }
+        tagged
+    }
+}
+
+/// Lightweight formula summary with only the fields listings and reverse-dependency
+/// lookups need.
+///
+/// Deserializing into this skips every other field (bottles, versions, caveats, ...)
+/// instead of materializing it, which keeps `uses`/`formulae` fast and small.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormulaBrief {
+    pub name: String,
+    #[serde(default)]
+    pub desc: Option<String>,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    #[serde(default)]
+    pub build_dependencies: Vec<String>,
+}
+
+/// Which recommended and optional dependencies an install pulls in.
+///
+/// The default matches Homebrew: every `:recommended` dependency is installed and every
+/// `:optional` one is skipped. `with`/`without` name individual dependencies and apply to
+/// each formula in the dependency tree that declares them.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyPolicy {
+    /// Skip all recommended dependencies (`--ignore-recommended`)
+    pub ignore_recommended: bool,
+    /// Install all optional dependencies (`--include-optional`)
+    pub include_optional: bool,
+    /// Optional dependencies to install (`--with-<dep>`)
+    pub with: Vec<String>,
+    /// Recommended dependencies to skip (`--without-<dep>`)
+    pub without: Vec<String>,
+}
+
+impl DependencyPolicy {
+    /// Runtime dependencies of `formula` under this policy: the required ones plus the
+    /// selected recommended and optional ones.
+    pub fn dependencies<'a>(&self, formula: &'a Formula) -> Vec<&'a String> {
+        let recommended = formula
+            .recommended_dependencies
+            .iter()
+            .filter(|dep| !self.ignore_recommended && !self.without.contains(dep));
+        let optional = formula
+            .optional_dependencies
+            .iter()
+            .filter(|dep| self.include_optional || self.with.contains(dep));
+
+        let mut deps: Vec<&String> = formula.dependencies.iter().collect();
+        for dep in recommended.chain(optional) {
+            if !deps.contains(&dep) {
+                deps.push(dep);
+            }
+        }
+        deps
+    }
+
+    /// Fold the selected recommended and optional dependencies into
+    /// `formula.dependencies`, so resolution and receipts treat them as required.
+    pub fn apply(&self, formula: &mut Formula) {
+        formula.dependencies = self.dependencies(formula).into_iter().cloned().collect();
+    }
+
+    /// Homebrew-style `(used_options, unused_options)` for the receipt, e.g.
+    /// `--without-foo` for a skipped recommended dependency.
+    pub fn options(&self, formula: &Formula) -> (Vec<String>, Vec<String>) {
+        let selected = self.dependencies(formula);
+        let mut used = Vec::new();
+        let mut unused = Vec::new();
+
+        for dep in &formula.recommended_dependencies {
+            let option = format!("--without-{}", dep);
+            if selected.contains(&dep) {
+                unused.push(option);
+            } else {
+                used.push(option);
+            }
+        }
+        for dep in &formula.optional_dependencies {
+            let option = format!("--with-{}", dep);
+            if selected.contains(&dep) {
+                used.push(option);
+            } else {
+                unused.push(option);
+            }
+        }
+
+        (used, unused)
+    }
+
+    /// Add the `--with-<dep>`/`--without-<dep>` options a receipt recorded in its
+    /// `used_options`, so a reinstall or upgrade keeps the original selection.
+    ///
+    /// Other options are ignored.
+    pub fn add_options(&mut self, options: &[String]) {
+        for option in options {
+            let (list, dep) = if let Some(dep) = option.strip_prefix("--without-") {
+                (&mut self.without, dep)
+            } else if let Some(dep) = option.strip_prefix("--with-") {
+                (&mut self.with, dep)
+            } else {
+                continue;
+            };
+            if !list.iter().any(|d| d == dep) {
+                list.push(dep.to_string());
+            }
+        }
+    }
+}
+
+impl From<Formula> for FormulaBrief {
+    fn from(formula: Formula) -> Self {
+        Self {
+            name: formula.name,
+            desc: formula.desc,
+            dependencies: formula.dependencies,
+            build_dependencies: formula.build_dependencies,
+        }
+    }
+}
+
+/// The executables one formula installs, as listed in Homebrew's executables index.
+///
+/// See [`BrewApi::fetch_executables_index`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FormulaExecutables {
+    pub name: String,
+    #[serde(default)]
+    pub executables: Vec<String>,
+}
+
+/// Parse Homebrew's `executables.txt`: one `name(version):exe exe ...` line per formula.
+///
+/// The version is optional; blank and malformed lines are skipped.
+pub fn parse_executables_index(text: &str) -> Vec<FormulaExecutables> {
+    text.lines()
+        .filter_map(|line| {
+            let (formula, executables) = line.split_once(':')?;
+            let name = formula
+                .split_once('(')
+                .map_or(formula, |(name, _)| name)
+                .trim();
+            (!name.is_empty()).then(|| FormulaExecutables {
+                name: name.to_string(),
+                executables: executables.split_whitespace().map(String::from).collect(),
+            })
+        })
+        .collect()
 }
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -108,6 +675,46 @@ pub struct BottleData {
     pub files: std::collections::HashMap<String, BottleFile>,
 }
 
+impl BottleData {
+    /// Get the bottle file for a platform tag, falling back to the universal `all` bottle.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error listing the available tags when neither exists.
+    pub fn file_for_tag(&self, tag: &str) -> anyhow::Result<&BottleFile> {
+        // Matches Homebrew's fallback logic: exact platform first, then universal
+        self.files
+            .get(tag)
+            .or_else(|| self.files.get("all"))
+            .ok_or_else(|| {
+                let mut tags: Vec<_> = self.files.keys().map(String::as_str).collect();
+                tags.sort_unstable();
+                anyhow::anyhow!(
+                    "No bottle for platform: {} (available: {})",
+                    tag,
+                    if tags.is_empty() {
+                        "none".to_string()
+                    } else {
+                        tags.join(", ")
+                    }
+                )
+            })
+    }
+}
+
+/// One platform's bottle, as listed by `info --variations`
+#[derive(Debug, Clone, Serialize)]
+pub struct BottleFileInfo {
+    /// Platform tag, e.g. `arm64_sequoia` or `all`
+    pub tag: String,
+    /// Cellar the bottle was built for (`:any`, `:any_skip_relocation`, or a path)
+    pub cellar: String,
+    pub url: String,
+    pub sha256: String,
+    /// Download size in bytes; the API doesn't report it, so it starts out unknown
+    pub size: Option<u64>,
+}
+
 /// Bottle information from API
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Bottle {
@@ -134,20 +741,358 @@ pub struct Cask {
     #[serde(default)]
     pub sha256: Option<String>,
     #[serde(default)]
-    pub artifacts: Vec<serde_json::Value>,
+    pub artifacts: Vec<Artifact>,
+    #[serde(default)]
+    pub depends_on: CaskDependsOn,
+    #[serde(default)]
+    pub conflicts_with: Option<CaskConflicts>,
+}
+
+impl Cask {
+    /// SHA256 the downloaded artifact must match.
+    ///
+    /// Returns `None` when the cask explicitly opts out of verification with
+    /// `sha256 :no_check`, which is what `version :latest` casks use since their
+    /// download changes with every release.
+    pub fn checksum(&self) -> Option<&str> {
+        self.sha256.as_deref().filter(|sha| *sha != "no_check")
+    }
+
+    /// Split the version into the release and its build suffix.
+    ///
+    /// Casks that need a build number to form their download URL write it after a
+    /// comma (`version "3.5.10,20241001"`); everything after the first comma is the
+    /// build. Returns `None` when the cask has no version.
+    pub fn version_and_build(&self) -> Option<(&str, Option<&str>)> {
+        let version = self.version.as_deref()?;
+        Some(match version.split_once(',') {
+            Some((release, build)) => (release, Some(build)),
+            None => (version, None),
+        })
+    }
+}
+
+/// One artifact stanza of a cask (`app "Foo.app"`, `zap trash: [...]`, ...)
+///
+/// The API writes each stanza as a single-key object mapping the stanza name to its
+/// arguments, e.g. `{"app": ["Firefox.app"]}` or `{"zap": [{"trash": [...]}]}`.
+/// Stanzas bru doesn't model (`preflight`, `installer`, ...) are kept verbatim as
+/// [`Artifact::Other`] so the cask still round-trips through `info --json`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Artifact {
+    /// App bundle moved to /Applications
+    App(ArtifactPath),
+    /// Installer package run with `installer`
+    Pkg(ArtifactPath),
+    /// Executable linked into the prefix's `bin`
+    Binary(ArtifactPath),
+    /// Font moved to ~/Library/Fonts
+    Font(ArtifactPath),
+    /// Manual page linked into the prefix's `share/man`
+    Manpage(ArtifactPath),
+    /// Directory of apps moved to /Applications
+    Suite(ArtifactPath),
+    /// Any other file moved to an explicit target (the `artifact` stanza)
+    #[serde(rename = "artifact")]
+    Generic(ArtifactPath),
+    /// What to clean up when the cask is uninstalled
+    Uninstall(Vec<UninstallDirectives>),
+    /// What to clean up on `uninstall --zap`: everything `uninstall` removes plus the
+    /// app's settings, caches and support files
+    Zap(Vec<UninstallDirectives>),
+    /// A stanza bru doesn't model
+    #[serde(untagged)]
+    Other(serde_json::Value),
+}
+
+/// Arguments of a stanza that moves or links a single file: its path inside the
+/// download, followed by options such as `target:`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "Vec<serde_json::Value>", into = "Vec<serde_json::Value>")]
+pub struct ArtifactPath {
+    pub path: String,
+    pub options: serde_json::Map<String, serde_json::Value>,
+}
+
+impl ArtifactPath {
+    /// Where the artifact is installed to, if the cask renames it (`target:`)
+    pub fn target(&self) -> Option<&str> {
+        self.options.get("target").and_then(|t| t.as_str())
+    }
+
+    /// File name the artifact is installed under: the target's, or else the path's
+    pub fn installed_name(&self) -> &str {
+        let name = self.target().unwrap_or(&self.path);
+        name.rsplit('/').next().unwrap_or(name)
+    }
+}
+
+impl TryFrom<Vec<serde_json::Value>> for ArtifactPath {
+    type Error = String;
+
+    fn try_from(args: Vec<serde_json::Value>) -> std::result::Result<Self, Self::Error> {
+        let mut args = args.into_iter();
+        let path = match args.next() {
+            Some(serde_json::Value::String(path)) => path,
+            other => return Err(format!("expected an artifact path, found {:?}", other)),
+        };
+        let options = match args.next() {
+            None => serde_json::Map::new(),
+            Some(serde_json::Value::Object(options)) => options,
+            Some(other) => return Err(format!("expected artifact options, found {}", other)),
+        };
+        match args.next() {
+            None => Ok(Self { path, options }),
+            Some(extra) => Err(format!("unexpected artifact argument: {}", extra)),
+        }
+    }
+}
+
+impl From<ArtifactPath> for Vec<serde_json::Value> {
+    fn from(artifact: ArtifactPath) -> Self {
+        let mut args = vec![serde_json::Value::String(artifact.path)];
+        if !artifact.options.is_empty() {
+            args.push(serde_json::Value::Object(artifact.options));
+        }
+        args
+    }
+}
+
+/// Arguments of an `uninstall` or `zap` stanza
+///
+/// Each directive may be written as a single string or a list; both parse to a list.
+/// Directives bru doesn't act on (`signal`, `script`, `login_item`, ...) are kept in
+/// `other`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UninstallDirectives {
+    /// Bundle IDs of apps to quit before removing anything
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub quit: Vec<String>,
+    /// launchd job labels to unload
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub launchctl: Vec<String>,
+    /// Package receipt IDs to forget (`pkgutil --forget`)
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub pkgutil: Vec<String>,
+    /// Paths to delete outright
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub delete: Vec<String>,
+    /// Paths to move to the Trash
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub trash: Vec<String>,
+    /// Directories to remove once they're empty
+    #[serde(
+        deserialize_with = "one_or_many",
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub rmdir: Vec<String>,
+    #[serde(flatten)]
+    pub other: serde_json::Map<String, serde_json::Value>,
+}
+
+/// Deserialize a directive written either as one string or as a list of strings
+fn one_or_many<'de, D>(deserializer: D) -> std::result::Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+
+    Ok(match OneOrMany::deserialize(deserializer)? {
+        OneOrMany::One(value) => vec![value],
+        OneOrMany::Many(values) => values,
+    })
+}
+
+/// Cask dependencies (`depends_on` in the cask definition)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaskDependsOn {
+    /// Formulae the cask requires
+    #[serde(default)]
+    pub formula: Vec<String>,
+    /// Other casks the cask requires
+    #[serde(default)]
+    pub cask: Vec<String>,
+    /// macOS release requirement, as comparator to versions (e.g. `{">=": ["12"]}`)
+    #[serde(default)]
+    pub macos: Option<std::collections::HashMap<String, Vec<String>>>,
+}
+
+impl CaskDependsOn {
+    /// Check the `depends_on macos:` requirement against a macOS product version.
+    ///
+    /// Casks without a requirement accept any version.
+    pub fn macos_satisfied(&self, current: &str) -> bool {
+        let Some(requirements) = &self.macos else {
+            return true;
+        };
+
+        requirements.iter().all(|(op, versions)| {
+            let mut matches = versions.iter().map(|required| {
+                let ordering = compare_macos_versions(current, required);
+                match op.as_str() {
+                    ">=" => ordering.is_ge(),
+                    ">" => ordering.is_gt(),
+                    "<=" => ordering.is_le(),
+                    "<" => ordering.is_lt(),
+                    _ => ordering.is_eq(),
+                }
+            });
+            // `==` lists alternatives; comparisons carry a single bound
+            if op == "==" {
+                matches.any(|m| m)
+            } else {
+                matches.all(|m| m)
+            }
+        })
+    }
+}
+
+/// Compare macOS versions numerically, only as precisely as `required` is written
+/// (so `14.7.1` satisfies `== 14`)
+fn compare_macos_versions(current: &str, required: &str) -> std::cmp::Ordering {
+    let parse = |v: &str| -> Vec<u32> { v.split('.').filter_map(|p| p.parse().ok()).collect() };
+    let required = parse(required);
+    let mut current = parse(current);
+    current.resize(required.len(), 0);
+    current.cmp(&required)
+}
+
+/// Casks and formulae a cask cannot be installed alongside (`conflicts_with`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CaskConflicts {
+    #[serde(default)]
+    pub cask: Vec<String>,
+    #[serde(default)]
+    pub formula: Vec<String>,
+}
+
+/// Order casks for installation.
+///
+/// Returns the formulae the casks depend on (deduplicated, in first-seen order), which
+/// must be installed first, and the casks sorted so that every cask comes after the
+/// casks it depends on. Dependencies outside `casks` are assumed to be installed already.
+pub fn cask_install_order(casks: Vec<Cask>) -> (Vec<String>, Vec<Cask>) {
+    let mut formulae: Vec<String> = Vec::new();
+    for cask in &casks {
+        for formula in &cask.depends_on.formula {
+            if !formulae.contains(formula) {
+                formulae.push(formula.clone());
+            }
+        }
+    }
+
+    // Depth-first topological sort; `visiting` guards against dependency cycles
+    fn visit(
+        index: usize,
+        casks: &[Cask],
+        done: &mut Vec<bool>,
+        visiting: &mut Vec<bool>,
+        order: &mut Vec<usize>,
+    ) {
+        if done[index] || visiting[index] {
+            return;
+        }
+        visiting[index] = true;
+        for dep in &casks[index].depends_on.cask {
+            if let Some(dep_index) = casks.iter().position(|c| &c.token == dep) {
+                visit(dep_index, casks, done, visiting, order);
+            }
+        }
+        visiting[index] = false;
+        done[index] = true;
+        order.push(index);
+    }
+
+    let mut done = vec![false; casks.len()];
+    let mut visiting = vec![false; casks.len()];
+    let mut order = Vec::with_capacity(casks.len());
+    for index in 0..casks.len() {
+        visit(index, &casks, &mut done, &mut visiting, &mut order);
+    }
+
+    let mut slots: Vec<Option<Cask>> = casks.into_iter().map(Some).collect();
+    let ordered = order.into_iter().filter_map(|i| slots[i].take()).collect();
+
+    (formulae, ordered)
+}
+
+/// Repository metadata from the GitHub API, used to enrich `info` output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GitHubRepo {
+    pub full_name: String,
+    pub stars: u64,
+    #[serde(default)]
+    pub last_release: Option<String>,
+}
+
+/// Extract `(owner, repo)` from a GitHub URL such as a formula homepage.
+///
+/// Returns `None` for non-GitHub URLs and for GitHub URLs that don't point at a repository.
+pub fn github_repo_slug(url: &str) -> Option<(String, String)> {
+    let rest = url
+        .strip_prefix("https://github.com/")
+        .or_else(|| url.strip_prefix("http://github.com/"))
+        .or_else(|| url.strip_prefix("https://www.github.com/"))?;
+
+    let mut parts = rest.split(['/', '#', '?']);
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    let repo = parts.next().filter(|s| !s.is_empty())?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+
+    Some((owner.to_string(), repo.to_string()))
 }
 
 /// Homebrew API client with in-memory caching
 #[derive(Clone)]
 pub struct BrewApi {
     client: reqwest::Client,
+    limiter: Arc<Limiter>,
     formula_cache: moka::future::Cache<String, Formula>,
     cask_cache: moka::future::Cache<String, Cask>,
+    search_cache: moka::future::Cache<(String, SearchScope), CachedSearch>,
+}
+
+/// Search results, with the versions of the cached datasets they were computed from
+#[derive(Clone)]
+struct CachedSearch {
+    results: SearchResults,
+    stamp: SearchStamp,
 }
 
+/// When the formulae and casks lists a search read were cached (`None` for datasets
+/// outside its scope)
+type SearchStamp = (Option<std::time::SystemTime>, Option<std::time::SystemTime>);
+
+/// Recent searches kept per [`BrewApi`]
+const SEARCH_CACHE_SIZE: u64 = 64;
+
 impl BrewApi {
     /// Create a new Homebrew API client with in-memory caching.
     ///
+    /// Proxy environment variables (`HOMEBREW_HTTPS_PROXY`, `HTTPS_PROXY`, ...) are honored.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -160,23 +1105,89 @@ impl BrewApi {
     /// }
     /// ```
     pub fn new() -> Result<Self> {
-        let client = reqwest::Client::builder()
-            .timeout(REQUEST_TIMEOUT)
-            .pool_idle_timeout(Duration::from_secs(90)) // HTTP keep-alive standard
-            .pool_max_idle_per_host(10) // Reuse connections during parallel resolution
-            .user_agent(format!("bru/{}", env!("CARGO_PKG_VERSION")))
-            .build()?;
+        Ok(Self::with_client(build_http_client(REQUEST_TIMEOUT, None)?))
+    }
 
+    /// Create a Homebrew API client that sends requests through an existing HTTP client.
+    ///
+    /// Use this to share one connection pool between API lookups and bottle downloads.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::BrewApi;
+    ///
+    /// let client = reqwest::Client::new();
+    /// let api = BrewApi::with_client(client);
+    /// ```
+    pub fn with_client(client: reqwest::Client) -> Self {
         // In-memory cache for formula/cask lookups (lasts for command duration)
         // Cache up to 1000 formulae and 500 casks to avoid redundant API calls
         let formula_cache = moka::future::Cache::new(1000);
         let cask_cache = moka::future::Cache::new(500);
+        let search_cache = moka::future::Cache::new(SEARCH_CACHE_SIZE);
 
-        Ok(Self {
+        Self {
             client,
+            limiter: Arc::new(Limiter::new(RateLimit::default())),
             formula_cache,
             cask_cache,
-        })
+            search_cache,
+        }
+    }
+
+    /// Replace the default [`RateLimit`] for requests to the Homebrew API.
+    ///
+    /// Clones made afterwards share the limit; clones made before keep the old one.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::{BrewApi, RateLimit};
+    ///
+    /// let api = BrewApi::new()?.with_rate_limit(RateLimit {
+    ///     max_concurrent: 4,
+    ///     requests_per_second: Some(10.0),
+    /// });
+    /// # Ok::<(), kombrucha::BruError>(())
+    /// ```
+    #[allow(dead_code)]
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.limiter = Arc::new(Limiter::new(limit));
+        self
+    }
+
+    /// GET a Homebrew API URL through the rate limiter.
+    ///
+    /// A 429 response is retried after its `Retry-After` delay (up to
+    /// [`MAX_THROTTLE_RETRIES`] times, each wait capped at [`MAX_RETRY_AFTER`]).
+    /// The request counts against the limit until its body has been read.
+    async fn api_get(&self, url: &str) -> Result<ApiResponse<'_>> {
+        let permit = self.limiter.acquire().await;
+        let mut retries = 0;
+        loop {
+            let response = self.client.get(url).send().await?;
+            if response.status() != reqwest::StatusCode::TOO_MANY_REQUESTS
+                || retries == MAX_THROTTLE_RETRIES
+                || retry_after(response.headers()) > MAX_RETRY_AFTER
+            {
+                return Ok(ApiResponse {
+                    response,
+                    _permit: permit,
+                });
+            }
+            let wait = retry_after(response.headers());
+            retries += 1;
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Get the HTTP client used for API requests.
+    ///
+    /// Bottle and cask downloads go through this client too, so every request in a
+    /// command shares one connection pool.
+    pub fn client(&self) -> &reqwest::Client {
+        &self.client
     }
 
     /// Fetch all formulae from Homebrew (with local disk caching for 24 hours).
@@ -197,40 +1208,128 @@ impl BrewApi {
     ///     Ok(())
     /// }
     /// ```
-    ///
-    /// # Performance
-    ///
-    /// - First call: ~2-3 seconds (downloads ~25 MB)
-    /// - Subsequent calls: <100 ms (loads from cache)
-    pub async fn fetch_all_formulae(&self) -> Result<Vec<Formula>> {
-        // Try cache first
-        if let Some(cached) = crate::cache::get_cached_formulae() {
+    ///
+    /// # Performance
+    ///
+    /// - First call: ~2-3 seconds (downloads ~25 MB)
+    /// - Subsequent calls: <100 ms (loads from cache)
+    pub async fn fetch_all_formulae(&self) -> Result<Vec<Formula>> {
+        self.fetch_all_formulae_in(&crate::cache::cache_dir()).await
+    }
+
+    /// [`fetch_all_formulae`](Self::fetch_all_formulae) through the cache in `cache_dir`
+    async fn fetch_all_formulae_in(&self, cache_dir: &Path) -> Result<Vec<Formula>> {
+        // Try cache first
+        if let Some(cached) = crate::cache::get_cached_formulae_in(cache_dir) {
+            return Ok(cached);
+        }
+
+        // Fetch fresh from API
+        let url = format!("{}/formula.json", HOMEBREW_API_BASE);
+        let formulae: Vec<Formula> = self.api_get(&url).await?.json().await?;
+
+        // Store in cache (ignore errors)
+        let _ = crate::cache::store_formulae_in(cache_dir, &formulae);
+
+        Ok(formulae)
+    }
+
+    /// Fetch the brief index (name, description, dependencies) of every formula.
+    ///
+    /// Use this instead of [`fetch_all_formulae`](Self::fetch_all_formulae) when only
+    /// names or dependency edges are needed (e.g. `uses`, `formulae`). Sources, fastest first:
+    /// 1. The cached brief index (`formulae-brief.json`)
+    /// 2. The full formulae cache, parsed into [`FormulaBrief`]s (then indexed)
+    /// 3. A fresh fetch of all formulae (which caches both)
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::BrewApi;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let api = BrewApi::new()?;
+    ///     let formulae = api.fetch_all_formulae_brief().await?;
+    ///     let users: Vec<_> = formulae
+    ///         .iter()
+    ///         .filter(|f| f.dependencies.iter().any(|d| d == "openssl@3"))
+    ///         .collect();
+    ///     println!("{} formulae depend on openssl@3", users.len());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn fetch_all_formulae_brief(&self) -> Result<Vec<FormulaBrief>> {
+        if let Some(index) = crate::cache::get_cached_brief_index() {
+            return Ok(index);
+        }
+
+        if let Some(brief) = crate::cache::get_cached_formulae_brief() {
+            // Store in cache (ignore errors)
+            let _ = crate::cache::store_brief_index(&brief);
+            return Ok(brief);
+        }
+
+        let formulae = self.fetch_all_formulae().await?;
+        Ok(formulae.into_iter().map(FormulaBrief::from).collect())
+    }
+
+    /// Fetch the index of which executables each formula installs (cached locally for
+    /// 24 hours).
+    ///
+    /// Homebrew publishes this command-to-formula mapping for `which-formula` and the
+    /// command-not-found handler; it's the only way to learn which *uninstalled* formula
+    /// provides a command, since executable names often differ from formula names
+    /// (`rg` comes from `ripgrep`).
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::BrewApi;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let api = BrewApi::new()?;
+    ///     let index = api.fetch_executables_index().await?;
+    ///     for formula in index.iter().filter(|f| f.executables.iter().any(|e| e == "rg")) {
+    ///         println!("rg is provided by {}", formula.name);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn fetch_executables_index(&self) -> Result<Vec<FormulaExecutables>> {
+        if let Some(cached) = crate::cache::get_cached_executables() {
             return Ok(cached);
         }
 
-        // Fetch fresh from API
-        let url = format!("{}/formula.json", HOMEBREW_API_BASE);
-        let formulae = self.client.get(&url).send().await?.json().await?;
+        let url = format!("{}/internal/executables.txt", HOMEBREW_API_BASE);
+        let response = self.api_get(&url).await?.error_for_status()?;
+        let index = parse_executables_index(&response.text().await?);
 
         // Store in cache (ignore errors)
-        let _ = crate::cache::store_formulae(&formulae);
+        let _ = crate::cache::store_executables(&index);
 
-        Ok(formulae)
+        Ok(index)
     }
 
     /// Fetch all casks (cached locally for 24 hours)
     pub async fn fetch_all_casks(&self) -> Result<Vec<Cask>> {
+        self.fetch_all_casks_in(&crate::cache::cache_dir()).await
+    }
+
+    /// [`fetch_all_casks`](Self::fetch_all_casks) through the cache in `cache_dir`
+    async fn fetch_all_casks_in(&self, cache_dir: &Path) -> Result<Vec<Cask>> {
         // Try cache first
-        if let Some(cached) = crate::cache::get_cached_casks() {
+        if let Some(cached) = crate::cache::get_cached_casks_in(cache_dir) {
             return Ok(cached);
         }
 
         // Fetch fresh from API
         let url = format!("{}/cask.json", HOMEBREW_API_BASE);
-        let casks = self.client.get(&url).send().await?.json().await?;
+        let casks: Vec<Cask> = self.api_get(&url).await?.json().await?;
 
         // Store in cache (ignore errors)
-        let _ = crate::cache::store_casks(&casks);
+        let _ = crate::cache::store_casks_in(cache_dir, &casks);
 
         Ok(casks)
     }
@@ -243,7 +1342,9 @@ impl BrewApi {
     /// # Errors
     ///
     /// Returns [`BruError::FormulaNotFound`](crate::error::BruError::FormulaNotFound) if
-    /// the formula doesn't exist in Homebrew.
+    /// the formula doesn't exist in Homebrew, or
+    /// [`BruError::InvalidName`](crate::error::BruError::InvalidName) without a request if
+    /// `name` can't be a formula name.
     ///
     /// # Examples
     ///
@@ -264,6 +1365,10 @@ impl BrewApi {
     /// }
     /// ```
     pub async fn fetch_formula(&self, name: &str) -> Result<Formula> {
+        if !validate_name(name) {
+            return Err(crate::error::BruError::InvalidName(name.to_string()));
+        }
+
         // Check cache first
         if let Some(cached) = self.formula_cache.get(name).await {
             return Ok(cached);
@@ -271,7 +1376,7 @@ impl BrewApi {
 
         // Fetch from API
         let url = format!("{}/formula/{}.json", HOMEBREW_API_BASE, name);
-        let response = self.client.get(&url).send().await?;
+        let response = self.api_get(&url).await?;
 
         if response.status() == 404 {
             return Err(crate::error::BruError::FormulaNotFound(name.to_string()));
@@ -287,8 +1392,119 @@ impl BrewApi {
         Ok(formula)
     }
 
+    /// Fetch star count and latest release date for a GitHub-hosted project.
+    ///
+    /// Uses `HOMEBREW_GITHUB_API_TOKEN` or `GITHUB_TOKEN` when set; otherwise the request
+    /// is unauthenticated and subject to GitHub's much lower rate limit. Returns `Ok(None)`
+    /// when `url` isn't a GitHub repository or GitHub refuses the request (rate limited,
+    /// private, or missing), so callers can treat enrichment as best-effort.
+    pub async fn fetch_github_repo(&self, url: &str) -> Result<Option<GitHubRepo>> {
+        #[derive(Deserialize)]
+        struct RepoResponse {
+            full_name: String,
+            stargazers_count: u64,
+        }
+
+        #[derive(Deserialize)]
+        struct ReleaseResponse {
+            published_at: Option<String>,
+        }
+
+        let Some((owner, repo)) = github_repo_slug(url) else {
+            return Ok(None);
+        };
+
+        let token = github_token();
+        let request = |path: String| {
+            let mut builder = self
+                .client
+                .get(format!("{}{}", GITHUB_API_BASE, path))
+                .header(reqwest::header::ACCEPT, "application/vnd.github+json");
+            if let Some(token) = &token {
+                builder = builder.bearer_auth(token);
+            }
+            builder
+        };
+
+        let response = request(format!("/repos/{}/{}", owner, repo)).send().await?;
+        if !response.status().is_success() {
+            return Ok(None);
+        }
+        let repo_info: RepoResponse = response.json().await?;
+
+        // Many projects don't publish GitHub releases; a 404 here just means no date
+        let response = request(format!("/repos/{}/{}/releases/latest", owner, repo))
+            .send()
+            .await?;
+        let last_release = if response.status().is_success() {
+            response
+                .json::<ReleaseResponse>()
+                .await
+                .ok()
+                .and_then(|r| r.published_at)
+        } else {
+            None
+        };
+
+        Ok(Some(GitHubRepo {
+            full_name: repo_info.full_name,
+            stars: repo_info.stargazers_count,
+            last_release,
+        }))
+    }
+
+    /// Upload files to a new secret GitHub gist and return its URL.
+    ///
+    /// `files` are `(filename, content)` pairs. Requires `HOMEBREW_GITHUB_API_TOKEN` or
+    /// `GITHUB_TOKEN` with the `gist` scope.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if no token is set, or GitHub rejects the upload (bad token,
+    /// missing scope, rate limit).
+    pub async fn create_gist(&self, description: &str, files: &[(&str, String)]) -> Result<String> {
+        #[derive(Deserialize)]
+        struct GistResponse {
+            html_url: String,
+        }
+
+        let token = github_token().ok_or_else(|| {
+            anyhow::anyhow!("Set HOMEBREW_GITHUB_API_TOKEN or GITHUB_TOKEN to create gists")
+        })?;
+        let files: serde_json::Map<String, serde_json::Value> = files
+            .iter()
+            .map(|(name, content)| (name.to_string(), serde_json::json!({ "content": content })))
+            .collect();
+
+        let response = self
+            .client
+            .post(format!("{}/gists", GITHUB_API_BASE))
+            .header(reqwest::header::ACCEPT, "application/vnd.github+json")
+            .bearer_auth(token)
+            .json(&serde_json::json!({
+                "description": description,
+                "public": false,
+                "files": files,
+            }))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "GitHub refused to create the gist: {}",
+                response.status()
+            )
+            .into());
+        }
+
+        Ok(response.json::<GistResponse>().await?.html_url)
+    }
+
     /// Fetch specific cask by token (with in-memory caching)
     pub async fn fetch_cask(&self, token: &str) -> Result<Cask> {
+        if !validate_name(token) {
+            return Err(crate::error::BruError::InvalidName(token.to_string()));
+        }
+
         // Check cache first
         if let Some(cached) = self.cask_cache.get(token).await {
             return Ok(cached);
@@ -296,7 +1512,7 @@ impl BrewApi {
 
         // Fetch from API
         let url = format!("{}/cask/{}.json", HOMEBREW_API_BASE, token);
-        let response = self.client.get(&url).send().await?;
+        let response = self.api_get(&url).await?;
 
         if response.status() == 404 {
             return Err(crate::error::BruError::CaskNotFound(token.to_string()));
@@ -314,18 +1530,25 @@ impl BrewApi {
 
     /// Search for formulae and casks matching a query.
     ///
-    /// Performs a case-insensitive search across both formula names/descriptions
-    /// and cask tokens/names. Results are returned separately for filtering.
+    /// Performs a case-insensitive search across formula names and cask tokens.
+    /// Results are returned separately for filtering. Only the datasets selected by
+    /// `scope` are loaded, so a formula-only search never touches cask data.
+    ///
+    /// The last 64 distinct queries are remembered for the life of the client, so
+    /// repeating one (say, on every keystroke of an interactive search) returns without
+    /// reloading anything. A remembered result is dropped once the cached formulae or
+    /// casks list it came from is refreshed; [`clear_search_cache`](Self::clear_search_cache)
+    /// drops them all.
     ///
     /// # Examples
     ///
     /// ```no_run
-    /// use kombrucha::BrewApi;
+    /// use kombrucha::{BrewApi, SearchScope};
     ///
     /// #[tokio::main]
     /// async fn main() -> anyhow::Result<()> {
     ///     let api = BrewApi::new()?;
-    ///     let results = api.search("python").await?;
+    ///     let results = api.search("python", SearchScope::default()).await?;
     ///
     ///     println!("Found {} formulae", results.formulae.len());
     ///     println!("Found {} casks", results.casks.len());
@@ -337,12 +1560,44 @@ impl BrewApi {
     ///     Ok(())
     /// }
     /// ```
-    pub async fn search(&self, query: &str) -> Result<SearchResults> {
+    pub async fn search(&self, query: &str, scope: SearchScope) -> Result<SearchResults> {
+        self.search_in(&crate::cache::cache_dir(), query, scope)
+            .await
+    }
+
+    /// [`search`](Self::search) through the cache in `cache_dir`
+    async fn search_in(
+        &self,
+        cache_dir: &Path,
+        query: &str,
+        scope: SearchScope,
+    ) -> Result<SearchResults> {
         let query_lower = query.to_lowercase();
+        let key = (query_lower.clone(), scope);
 
-        // Fetch both formulae and casks in parallel
-        let (formulae_result, casks_result) =
-            tokio::join!(self.fetch_all_formulae(), self.fetch_all_casks());
+        if let Some(cached) = self.search_cache.get(&key).await
+            && cached.stamp == search_stamp(cache_dir, scope)
+        {
+            return Ok(cached.results);
+        }
+
+        // Fetch the requested datasets in parallel
+        let (formulae_result, casks_result) = tokio::join!(
+            async {
+                if scope.formulae {
+                    self.fetch_all_formulae_in(cache_dir).await
+                } else {
+                    Ok(Vec::new())
+                }
+            },
+            async {
+                if scope.casks {
+                    self.fetch_all_casks_in(cache_dir).await
+                } else {
+                    Ok(Vec::new())
+                }
+            }
+        );
 
         let formulae = formulae_result?;
         let casks = casks_result?;
@@ -350,34 +1605,13 @@ impl BrewApi {
         // Share query string between parallel tasks using Arc to avoid cloning
         let query = std::sync::Arc::new(query_lower);
 
-        // Match for formulae: substring or high Jaro-Winkler similarity
-        fn matches_formula_name(name: &str, query: &str) -> bool {
-            let name_lower = name.to_lowercase();
-            // Direct substring match
-            if name_lower.contains(query) {
-                return true;
-            }
-            // Jaro-Winkler similarity >= 0.85 for fuzzy matches
-            if query.len() >= 3 {
-                let similarity = strsim::jaro_winkler(&name_lower, query);
-                return similarity >= 0.85;
-            }
-            false
-        }
-
-        // Match for casks: substring, query contains name, or high Jaro-Winkler similarity
-        fn matches_cask_name(name: &str, query: &str) -> bool {
-            let name_lower = name.to_lowercase();
-            // Direct substring match (either direction)
-            if name_lower.contains(query) || query.contains(&name_lower) {
-                return true;
-            }
-            // Jaro-Winkler similarity >= 0.85 for fuzzy matches
-            if query.len() >= 3 {
-                let similarity = strsim::jaro_winkler(&name_lower, query);
-                return similarity >= 0.85;
-            }
-            false
+        // Match for casks: also when the query contains the name
+        fn cask_name_score(name: &str, query: &str) -> Option<f64> {
+            fuzzy_score(name, query).or_else(|| {
+                query
+                    .contains(&name.to_lowercase())
+                    .then_some(FUZZY_THRESHOLD)
+            })
         }
 
         // Filter results in parallel (name-only matching like brew search)
@@ -387,7 +1621,7 @@ impl BrewApi {
                 move || {
                     formulae
                         .into_iter()
-                        .filter(|f| matches_formula_name(&f.name, &query))
+                        .filter_map(|f| Some(Scored::new(fuzzy_score(&f.name, &query)?, f)))
                         .collect::<Vec<_>>()
                 }
             }),
@@ -396,17 +1630,144 @@ impl BrewApi {
                 move || {
                     casks
                         .into_iter()
-                        .filter(|c| matches_cask_name(&c.token, &query))
+                        .filter_map(|c| Some(Scored::new(cask_name_score(&c.token, &query)?, c)))
                         .collect::<Vec<_>>()
                 }
             })
         );
 
-        Ok(SearchResults {
+        let results = SearchResults {
             formulae: matching_formulae.unwrap(),
             casks: matching_casks.unwrap(),
+        };
+
+        // Only results read from the disk cache can be checked for staleness later
+        let stamp = search_stamp(cache_dir, scope);
+        if (!scope.formulae || stamp.0.is_some()) && (!scope.casks || stamp.1.is_some()) {
+            let cached = CachedSearch {
+                results: results.clone(),
+                stamp,
+            };
+            self.search_cache.insert(key, cached).await;
+        }
+
+        Ok(results)
+    }
+
+    /// Forget every remembered [`search`](Self::search) result.
+    #[allow(dead_code)]
+    pub fn clear_search_cache(&self) {
+        self.search_cache.invalidate_all();
+    }
+}
+
+/// Versions of the cached datasets a search in `scope` reads
+fn search_stamp(cache_dir: &Path, scope: SearchScope) -> SearchStamp {
+    (
+        scope
+            .formulae
+            .then(|| crate::cache::formulae_modified_in(cache_dir))
+            .flatten(),
+        scope
+            .casks
+            .then(|| crate::cache::casks_modified_in(cache_dir))
+            .flatten(),
+    )
+}
+
+/// GitHub token from `HOMEBREW_GITHUB_API_TOKEN` or `GITHUB_TOKEN`, ignoring empty values
+pub fn github_token() -> Option<String> {
+    std::env::var("HOMEBREW_GITHUB_API_TOKEN")
+        .or_else(|_| std::env::var("GITHUB_TOKEN"))
+        .ok()
+        .filter(|t| !t.is_empty())
+}
+
+/// Whether `name` is a plausible formula name or cask token.
+///
+/// Homebrew names are ASCII letters and digits plus `+ - _ . @`, starting with a letter
+/// or digit. Tap-qualified names (`user/repo/name`) aren't accepted; strip the tap first.
+/// Lets typos like `rip grep` fail immediately instead of after an API round-trip.
+pub fn validate_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    let Some(first) = chars.next() else {
+        return false;
+    };
+    name.len() <= MAX_NAME_LEN
+        && first.is_ascii_alphanumeric()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '_' | '.' | '@'))
+}
+
+/// Suggest up to `n` known names close to `name`, best match first.
+///
+/// Candidates are the formula names and cask tokens in the local cache (see
+/// [`cache::get_cached_names_any_age`](crate::cache::get_cached_names_any_age)), so this
+/// works offline and returns nothing until an online command has filled the cache.
+/// Meant for "Did you mean" hints after a [`BruError::FormulaNotFound`](crate::error::BruError::FormulaNotFound).
+pub fn suggest(name: &str, n: usize) -> Vec<String> {
+    closest_names(name, crate::cache::get_cached_names_any_age(), n)
+}
+
+/// Rank `candidates` by edit distance to `name`, keeping the `n` closest.
+///
+/// A candidate qualifies when it's within about one edit per three characters of `name`
+/// (at least one). Ties break on Jaro-Winkler similarity, then alphabetically.
+fn closest_names<I, S>(name: &str, candidates: I, n: usize) -> Vec<String>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let name = name.to_lowercase();
+    let max_distance = (name.chars().count() / 3).max(1);
+
+    let mut scored: Vec<(usize, f64, String)> = candidates
+        .into_iter()
+        .map(Into::into)
+        .filter(|candidate| candidate.to_lowercase() != name)
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let distance = strsim::damerau_levenshtein(&name, &lower);
+            (distance <= max_distance)
+                .then(|| (distance, strsim::jaro_winkler(&name, &lower), candidate))
         })
+        .collect();
+
+    scored.sort_by(|a, b| {
+        a.0.cmp(&b.0)
+            .then(b.1.total_cmp(&a.1))
+            .then_with(|| a.2.cmp(&b.2))
+    });
+    scored.dedup_by(|a, b| a.2 == b.2);
+    scored
+        .into_iter()
+        .take(n)
+        .map(|(_, _, name)| name)
+        .collect()
+}
+
+/// Jaro-Winkler similarity a name needs to match a query it doesn't contain
+const FUZZY_THRESHOLD: f64 = 0.85;
+
+/// Name matching used by `search`: substring or high Jaro-Winkler similarity.
+///
+/// `query` must already be lowercase.
+pub fn fuzzy_matches(name: &str, query: &str) -> bool {
+    fuzzy_score(name, query).is_some()
+}
+
+/// How closely `name` matches `query`, or `None` if [`fuzzy_matches`] rejects it
+///
+/// The score is the Jaro-Winkler similarity, from the threshold up to 1.0 (an exact
+/// match). Names containing the query score at least the threshold, however different
+/// their lengths. `query` must already be lowercase.
+pub fn fuzzy_score(name: &str, query: &str) -> Option<f64> {
+    let name_lower = name.to_lowercase();
+    let similarity = strsim::jaro_winkler(&name_lower, query);
+    if name_lower.contains(query) {
+        return Some(similarity.max(FUZZY_THRESHOLD));
     }
+    // Typos only count once the query is long enough to tell them apart
+    (query.len() >= 3 && similarity >= FUZZY_THRESHOLD).then_some(similarity)
 }
 
 impl Default for BrewApi {
@@ -415,10 +1776,109 @@ impl Default for BrewApi {
     }
 }
 
-#[derive(Debug)]
+/// Source of formula and cask metadata.
+///
+/// Dependency resolution and install planning only need to look packages up by name,
+/// so they take a `&dyn FormulaSource` rather than a [`BrewApi`]. That lets tests (or
+/// embedders with their own index) plan installs without network access.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::{BrewApi, FormulaSource};
+///
+/// async fn describe(source: &dyn FormulaSource, name: &str) -> kombrucha::Result<()> {
+///     let formula = source.formula(name).await?;
+///     println!("{}: {:?}", formula.name, formula.dependencies);
+///     Ok(())
+/// }
+///
+/// # async fn run() -> kombrucha::Result<()> {
+/// describe(&BrewApi::new()?, "ripgrep").await
+/// # }
+/// ```
+pub trait FormulaSource: Sync {
+    /// Look up a formula by name.
+    ///
+    /// Returns [`BruError::FormulaNotFound`](crate::error::BruError::FormulaNotFound)
+    /// when no such formula exists.
+    fn formula<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Formula>>;
+
+    /// Look up a cask by token.
+    ///
+    /// Returns [`BruError::CaskNotFound`](crate::error::BruError::CaskNotFound) when no
+    /// such cask exists.
+    fn cask<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Cask>>;
+}
+
+impl FormulaSource for BrewApi {
+    fn formula<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Formula>> {
+        Box::pin(self.fetch_formula(name))
+    }
+
+    fn cask<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Cask>> {
+        Box::pin(self.fetch_cask(token))
+    }
+}
+
+/// Which datasets [`BrewApi::search`] looks in. The default searches both.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SearchScope {
+    pub formulae: bool,
+    pub casks: bool,
+}
+
+impl SearchScope {
+    /// Scope for `--formula`/`--cask` style flags: one flag restricts the search to
+    /// that kind, none (or both) searches everything
+    pub fn from_flags(formula_only: bool, cask_only: bool) -> Self {
+        if formula_only == cask_only {
+            Self::default()
+        } else {
+            Self {
+                formulae: formula_only,
+                casks: cask_only,
+            }
+        }
+    }
+}
+
+impl Default for SearchScope {
+    fn default() -> Self {
+        Self {
+            formulae: true,
+            casks: true,
+        }
+    }
+}
+
+/// A search match together with its [`fuzzy_score`]
+///
+/// Derefs to the matched item, so `result.name` reads the formula's name.
+#[derive(Debug, Clone)]
+pub struct Scored<T> {
+    pub item: T,
+    pub score: f64,
+}
+
+impl<T> Scored<T> {
+    pub fn new(score: f64, item: T) -> Self {
+        Self { item, score }
+    }
+}
+
+impl<T> std::ops::Deref for Scored<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.item
+    }
+}
+
+#[derive(Debug, Clone)]
 pub struct SearchResults {
-    pub formulae: Vec<Formula>,
-    pub casks: Vec<Cask>,
+    pub formulae: Vec<Scored<Formula>>,
+    pub casks: Vec<Scored<Cask>>,
 }
 
 impl SearchResults {
@@ -426,3 +1886,792 @@ impl SearchResults {
         self.formulae.is_empty() && self.casks.is_empty()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_head_only_formula_has_no_install_version() {
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "nightly-tool",
+            "full_name": "nightly-tool",
+            "versions": {"stable": null, "head": "HEAD", "bottle": false},
+            "bottle": {},
+        }))
+        .unwrap();
+
+        assert_eq!(formula.versions.head.as_deref(), Some("HEAD"));
+        assert!(matches!(
+            formula.install_version(),
+            Err(crate::error::BruError::NoStableVersion(name)) if name == "nightly-tool"
+        ));
+
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "jq",
+            "versions": {"stable": "1.7.1"},
+        }))
+        .unwrap();
+        assert_eq!(formula.install_version().unwrap(), "1.7.1");
+        assert_eq!(formula.pkg_version().as_deref(), Some("1.7.1"));
+    }
+
+    #[test]
+    fn test_parse_executables_index() {
+        let index = parse_executables_index(
+            "ripgrep(14.1.1):rg\n\nbinutils(2.43):addr2line ar as\nnoversion:tool\nbroken line\n",
+        );
+        let names: Vec<&str> = index.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, ["ripgrep", "binutils", "noversion"]);
+        assert_eq!(index[0].executables, ["rg"]);
+        assert_eq!(index[1].executables, ["addr2line", "ar", "as"]);
+        assert_eq!(index[2].executables, ["tool"]);
+    }
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_proxies_none_configured() {
+        assert!(proxies(None, env(&[])).unwrap().is_empty());
+        assert!(
+            proxies(None, env(&[("HTTPS_PROXY", "")]))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_proxies_homebrew_vars_take_precedence() {
+        let vars = env(&[
+            ("HOMEBREW_HTTPS_PROXY", "http://brew-proxy:3128"),
+            ("HTTPS_PROXY", "http://std-proxy:3128"),
+            ("http_proxy", "http://plain-proxy:8080"),
+        ]);
+        let resolved = format!("{:?}", proxies(None, vars).unwrap());
+
+        assert!(resolved.contains("Https") && resolved.contains("brew-proxy"));
+        assert!(resolved.contains("Http(") && resolved.contains("plain-proxy"));
+        assert!(!resolved.contains("std-proxy"));
+    }
+
+    #[test]
+    fn test_proxies_explicit_overrides_env() {
+        let vars = env(&[
+            ("HOMEBREW_ALL_PROXY", "http://brew-proxy:3128"),
+            ("NO_PROXY", "localhost"),
+        ]);
+        let resolved = proxies(Some("http://explicit:3128"), vars).unwrap();
+
+        assert_eq!(resolved.len(), 1);
+        let resolved = format!("{:?}", resolved);
+        assert!(resolved.contains("explicit") && !resolved.contains("brew-proxy"));
+        assert!(resolved.contains("localhost"));
+    }
+
+    #[test]
+    fn test_proxies_invalid_url() {
+        assert!(proxies(Some("not a url"), env(&[])).is_err());
+    }
+
+    #[test]
+    fn test_bottle_file_for_tag() {
+        let file = |sha: &str| BottleFile {
+            cellar: ":any".into(),
+            url: format!("https://ghcr.io/v2/homebrew/core/jq/blobs/sha256:{sha}"),
+            sha256: sha.into(),
+        };
+        let bottle = BottleData {
+            rebuild: 0,
+            root_url: None,
+            files: HashMap::from([
+                ("arm64_sequoia".to_string(), file("a")),
+                ("x86_64_linux".to_string(), file("b")),
+            ]),
+        };
+
+        assert_eq!(bottle.file_for_tag("arm64_sequoia").unwrap().sha256, "a");
+
+        let err = bottle
+            .file_for_tag("arm64_ventura")
+            .unwrap_err()
+            .to_string();
+        assert!(err.contains("arm64_ventura"), "{err}");
+        assert!(
+            err.contains("available: arm64_sequoia, x86_64_linux"),
+            "{err}"
+        );
+
+        // A universal bottle covers any tag
+        let universal = BottleData {
+            files: HashMap::from([("all".to_string(), file("c"))]),
+            ..bottle
+        };
+        assert_eq!(universal.file_for_tag("arm64_ventura").unwrap().sha256, "c");
+    }
+
+    #[test]
+    fn test_fuzzy_matches() {
+        assert!(fuzzy_matches("ripgrep", "grep"));
+        assert!(fuzzy_matches("RipGrep", "ripgrep"));
+        assert!(fuzzy_matches("ripgrep", "ripgre"));
+        // Typos only match fuzzily once the query is long enough
+        assert!(fuzzy_matches("ripgrep", "ripgrpe"));
+        assert!(!fuzzy_matches("ripgrep", "rq"));
+        assert!(!fuzzy_matches("ripgrep", "python"));
+
+        assert_eq!(fuzzy_score("RipGrep", "ripgrep"), Some(1.0));
+        assert_eq!(fuzzy_score("ripgrep", "python"), None);
+        // Contained queries score at least as well as any accepted typo
+        assert!(fuzzy_score("ripgrep", "grep").unwrap() >= FUZZY_THRESHOLD);
+        assert!(
+            fuzzy_score("ripgrep", "ripgrpe").unwrap() > fuzzy_score("ripgrep", "grep").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_validate_name() {
+        for name in [
+            "ripgrep",
+            "python@3.12",
+            "gtk+3",
+            "font-fira-code",
+            "7zip",
+            "c++_lib",
+        ] {
+            assert!(validate_name(name), "{name} should be valid");
+        }
+        for name in [
+            "",
+            "rip grep",
+            "../etc",
+            "-rf",
+            ".hidden",
+            "user/tap/wget",
+            "wget?",
+        ] {
+            assert!(!validate_name(name), "{name:?} should be invalid");
+        }
+        assert!(!validate_name(&"a".repeat(MAX_NAME_LEN + 1)));
+    }
+
+    #[test]
+    fn test_closest_names() {
+        let names = [
+            "ripgrep",
+            "ripgrep-all",
+            "grep",
+            "ripmime",
+            "wget",
+            "ripgrep",
+        ];
+
+        // A dropped letter suggests the intended formula first, once
+        assert_eq!(closest_names("rigrep", names, 3), ["ripgrep", "grep"]);
+        assert_eq!(closest_names("rigrep", names, 1), ["ripgrep"]);
+        assert_eq!(closest_names("RipGrpe", names, 3), ["ripgrep"]);
+        assert_eq!(closest_names("wgte", names, 1), ["wget"]);
+        assert!(closest_names("python", names, 3).is_empty());
+        assert!(closest_names("ripgrep", names, 3).is_empty());
+    }
+
+    #[test]
+    fn test_search_scope_from_flags() {
+        let all = SearchScope::default();
+        assert_eq!(SearchScope::from_flags(false, false), all);
+        assert_eq!(SearchScope::from_flags(true, true), all);
+        assert_eq!(
+            SearchScope::from_flags(true, false),
+            SearchScope {
+                formulae: true,
+                casks: false
+            }
+        );
+        assert_eq!(
+            SearchScope::from_flags(false, true),
+            SearchScope {
+                formulae: false,
+                casks: true
+            }
+        );
+    }
+
+    fn formula(json: serde_json::Value) -> Formula {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_formula_conflicts_pair_reasons() {
+        let f = formula(serde_json::json!({
+            "name": "gnu-tar",
+            "conflicts_with": ["libarchive", "gtar"],
+            "conflicts_with_reasons": ["both install `tar` binaries", null]
+        }));
+        assert_eq!(
+            f.conflicts(),
+            vec![
+                ("libarchive", Some("both install `tar` binaries")),
+                ("gtar", None)
+            ]
+        );
+
+        let f = formula(serde_json::json!({"name": "jq", "conflicts_with": ["jq-legacy"]}));
+        assert_eq!(f.conflicts(), vec![("jq-legacy", None)]);
+    }
+
+    #[test]
+    fn test_formula_requirements_parse() {
+        let f = formula(serde_json::json!({
+            "name": "widget",
+            "requirements": [
+                {"name": "macos", "cask": null, "download": null, "version": "13",
+                 "contexts": [], "specs": ["stable"]},
+                {"name": "xcode", "cask": null, "download": null, "version": "15.0",
+                 "contexts": ["build"], "specs": ["stable", "head"]},
+                {"name": "arch", "version": "arm64", "contexts": []},
+                {"name": "linux", "version": null, "contexts": []}
+            ]
+        }));
+
+        let [macos, xcode, arch, linux] = f.requirements.as_slice() else {
+            panic!("expected four requirements");
+        };
+        assert_eq!(macos.to_string(), "macOS >= 13");
+        assert_eq!(macos.satisfied(Some("14.5"), "aarch64"), Some(true));
+        assert_eq!(macos.satisfied(Some("12.7"), "aarch64"), Some(false));
+        // A minimum macOS version doesn't keep Linux from installing it
+        assert_eq!(macos.satisfied(None, "x86_64"), Some(true));
+        let bare_macos = Requirement {
+            version: None,
+            ..macos.clone()
+        };
+        assert_eq!(bare_macos.satisfied(None, "x86_64"), Some(false));
+        assert_eq!(bare_macos.satisfied(Some("12.7"), "aarch64"), Some(true));
+
+        assert_eq!(xcode.to_string(), "Xcode >= 15.0 [build]");
+        assert!(xcode.build_only());
+        assert_eq!(xcode.satisfied(Some("14.5"), "aarch64"), None);
+
+        assert_eq!(arch.satisfied(Some("14.5"), "aarch64"), Some(true));
+        assert_eq!(arch.satisfied(Some("14.5"), "x86_64"), Some(false));
+        assert_eq!(linux.satisfied(Some("14.5"), "aarch64"), Some(false));
+    }
+
+    #[test]
+    fn test_macos_requirements_against_versions() {
+        let requirement = |name: &str, version: &str| Requirement {
+            name: name.into(),
+            version: Some(version.into()),
+            contexts: Vec::new(),
+        };
+        let minimum = requirement("macos", "13");
+        let maximum = requirement("maximum_macos", "14");
+        let minimum_point = requirement("macos", "10.15");
+
+        for (current, min_ok, max_ok) in [
+            ("10.15.7", false, true),
+            ("12.7.6", false, true),
+            ("13.0", true, true),
+            ("13.6.1", true, true),
+            ("14.7.1", true, true),
+            ("15.1", true, false),
+        ] {
+            assert_eq!(
+                minimum.satisfied(Some(current), "arm64"),
+                Some(min_ok),
+                "{current}"
+            );
+            assert_eq!(
+                maximum.satisfied(Some(current), "arm64"),
+                Some(max_ok),
+                "{current}"
+            );
+        }
+        assert_eq!(
+            minimum_point.satisfied(Some("10.14.6"), "x86_64"),
+            Some(false)
+        );
+        assert_eq!(minimum_point.satisfied(Some("10.15"), "x86_64"), Some(true));
+        assert_eq!(minimum_point.satisfied(Some("11.0"), "x86_64"), Some(true));
+        // An upper bound means nothing off macOS
+        assert_eq!(maximum.satisfied(None, "x86_64"), Some(true));
+    }
+
+    #[test]
+    fn test_bottle_tags_with_meta_sorted() {
+        let file = |tag: &str| {
+            serde_json::json!({
+                "cellar": ":any",
+                "url": format!("https://ghcr.io/v2/homebrew/core/jq/blobs/sha256:{tag}"),
+                "sha256": tag,
+            })
+        };
+        let f = formula(serde_json::json!({
+            "name": "jq",
+            "bottle": {"stable": {"rebuild": 0, "files": {
+                "x86_64_linux": file("x86_64_linux"),
+                "arm64_sequoia": file("arm64_sequoia"),
+                "sonoma": file("sonoma"),
+            }}}
+        }));
+
+        let tags: Vec<_> = f
+            .bottle_tags_with_meta()
+            .into_iter()
+            .map(|b| (b.tag, b.sha256, b.size))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("arm64_sequoia".into(), "arm64_sequoia".into(), None),
+                ("sonoma".into(), "sonoma".into(), None),
+                ("x86_64_linux".into(), "x86_64_linux".into(), None),
+            ]
+        );
+        assert!(
+            formula(serde_json::json!({"name": "x"}))
+                .bottle_tags_with_meta()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_tagged_dependencies_merges_sections() {
+        let f = formula(serde_json::json!({
+            "name": "widget",
+            "dependencies": ["openssl@3", "zlib"],
+            "optional_dependencies": ["rav1e"],
+            "build_dependencies": ["pkgconf", "openssl@3"],
+            "test_dependencies": ["pkgconf"]
+        }));
+
+        use DependencyTag::*;
+        assert_eq!(
+            f.tagged_dependencies(),
+            vec![
+                ("openssl@3", vec![Runtime, Build]),
+                ("zlib", vec![Runtime]),
+                ("rav1e", vec![Optional]),
+                ("pkgconf", vec![Build, Test]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_recommended_dependencies_installed_by_default() {
+        let mut f = formula(serde_json::json!({
+            "name": "ffmpeg",
+            "dependencies": ["x264"],
+            "recommended_dependencies": ["lame"],
+            "optional_dependencies": ["rav1e"]
+        }));
+
+        let policy = DependencyPolicy::default();
+        assert_eq!(policy.dependencies(&f), vec!["x264", "lame"]);
+        assert_eq!(
+            policy.options(&f),
+            (vec![], vec!["--without-lame".into(), "--with-rav1e".into()])
+        );
+
+        policy.apply(&mut f);
+        assert_eq!(f.dependencies, vec!["x264", "lame"]);
+    }
+
+    #[test]
+    fn test_dependency_policy_with_and_without() {
+        let f = formula(serde_json::json!({
+            "name": "ffmpeg",
+            "dependencies": ["x264"],
+            "recommended_dependencies": ["lame"],
+            "optional_dependencies": ["rav1e", "srt"]
+        }));
+
+        let policy = DependencyPolicy {
+            with: vec!["rav1e".into()],
+            without: vec!["lame".into()],
+            ..Default::default()
+        };
+        assert_eq!(policy.dependencies(&f), vec!["x264", "rav1e"]);
+        assert_eq!(
+            policy.options(&f),
+            (
+                vec!["--without-lame".into(), "--with-rav1e".into()],
+                vec!["--with-srt".into()]
+            )
+        );
+
+        let policy = DependencyPolicy {
+            ignore_recommended: true,
+            include_optional: true,
+            ..Default::default()
+        };
+        assert_eq!(policy.dependencies(&f), vec!["x264", "rav1e", "srt"]);
+    }
+
+    #[test]
+    fn test_dependency_policy_from_recorded_options() {
+        let f = formula(serde_json::json!({
+            "name": "ffmpeg",
+            "dependencies": ["x264"],
+            "recommended_dependencies": ["lame"],
+            "optional_dependencies": ["rav1e", "srt"]
+        }));
+        let recorded = DependencyPolicy {
+            with: vec!["rav1e".into()],
+            without: vec!["lame".into()],
+            ..Default::default()
+        };
+        let (used, _) = recorded.options(&f);
+
+        let mut policy = DependencyPolicy::default();
+        policy.add_options(&used);
+        policy.add_options(&used);
+        assert_eq!(policy.with, vec!["rav1e"]);
+        assert_eq!(policy.without, vec!["lame"]);
+        assert_eq!(policy.dependencies(&f), vec!["x264", "rav1e"]);
+    }
+
+    fn cask(json: serde_json::Value) -> Cask {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn test_cask_checksum_opt_out_is_explicit() {
+        let pinned =
+            cask(serde_json::json!({"token": "firefox", "version": "131.0", "sha256": "abc123"}));
+        assert_eq!(pinned.checksum(), Some("abc123"));
+
+        let latest = cask(serde_json::json!({
+            "token": "google-chrome",
+            "version": "latest",
+            "sha256": "no_check"
+        }));
+        assert_eq!(latest.checksum(), None);
+    }
+
+    #[test]
+    fn test_cask_version_and_build() {
+        let build = cask(serde_json::json!({"token": "iterm2", "version": "3.5.10,20241001"}));
+        assert_eq!(
+            build.version_and_build(),
+            Some(("3.5.10", Some("20241001")))
+        );
+
+        let plain = cask(serde_json::json!({"token": "firefox", "version": "131.0"}));
+        assert_eq!(plain.version_and_build(), Some(("131.0", None)));
+
+        let none = cask(serde_json::json!({"token": "firefox"}));
+        assert_eq!(none.version_and_build(), None);
+    }
+
+    #[test]
+    fn test_cask_artifacts_app_binary_zap() {
+        // visual-studio-code, abridged
+        let parsed = cask(serde_json::json!({
+            "token": "visual-studio-code",
+            "artifacts": [
+                {"uninstall": [{
+                    "launchctl": "com.microsoft.VSCode.ShipIt",
+                    "quit": "com.microsoft.VSCode"
+                }]},
+                {"app": ["Visual Studio Code.app"]},
+                {"binary": [
+                    "$APPDIR/Visual Studio Code.app/Contents/Resources/app/bin/code",
+                    {"target": "code"}
+                ]},
+                {"zap": [{
+                    "trash": [
+                        "~/.vscode",
+                        "~/Library/Application Support/Code",
+                        "~/Library/Caches/com.microsoft.VSCode"
+                    ]
+                }]}
+            ]
+        }));
+
+        let [uninstall, app, binary, zap] = parsed.artifacts.as_slice() else {
+            panic!("expected 4 artifacts, got {:?}", parsed.artifacts);
+        };
+        assert_eq!(
+            uninstall,
+            &Artifact::Uninstall(vec![UninstallDirectives {
+                quit: vec!["com.microsoft.VSCode".to_string()],
+                launchctl: vec!["com.microsoft.VSCode.ShipIt".to_string()],
+                ..Default::default()
+            }])
+        );
+        let Artifact::App(app) = app else {
+            panic!("expected app, got {:?}", app);
+        };
+        assert_eq!(app.path, "Visual Studio Code.app");
+        assert_eq!(app.installed_name(), "Visual Studio Code.app");
+        let Artifact::Binary(binary) = binary else {
+            panic!("expected binary, got {:?}", binary);
+        };
+        assert_eq!(binary.target(), Some("code"));
+        assert_eq!(binary.installed_name(), "code");
+        let Artifact::Zap(zap) = zap else {
+            panic!("expected zap, got {:?}", zap);
+        };
+        assert_eq!(zap[0].trash.len(), 3);
+        assert!(zap[0].delete.is_empty());
+    }
+
+    #[test]
+    fn test_cask_artifacts_pkg_and_unmodeled_stanzas() {
+        // zoom, abridged: a pkg with hooks and directives bru doesn't act on
+        let artifacts = serde_json::json!([
+            {"pkg": ["zoomusInstallerFull.pkg"]},
+            {"postflight": null},
+            {"uninstall": [{
+                "signal": ["KILL", "us.zoom.xos"],
+                "launchctl": ["us.zoom.ZoomDaemon"],
+                "pkgutil": "us.zoom.pkg.videomeeting",
+                "delete": [
+                    "/Applications/zoom.us.app",
+                    "/Library/PrivilegedHelperTools/us.zoom.ZoomDaemon"
+                ]
+            }]},
+            {"zap": [{
+                "trash": "~/Library/Preferences/us.zoom.xos.plist",
+                "rmdir": "~/Library/Application Support/zoom.us"
+            }]},
+            {"installer": [{"manual": "Install Zoom.app"}]}
+        ]);
+        let parsed: Vec<Artifact> = serde_json::from_value(artifacts.clone()).unwrap();
+
+        let Artifact::Pkg(pkg) = &parsed[0] else {
+            panic!("expected pkg, got {:?}", parsed[0]);
+        };
+        assert_eq!(pkg.path, "zoomusInstallerFull.pkg");
+        assert_eq!(
+            parsed[1],
+            Artifact::Other(serde_json::json!({"postflight": null}))
+        );
+        let Artifact::Uninstall(uninstall) = &parsed[2] else {
+            panic!("expected uninstall, got {:?}", parsed[2]);
+        };
+        assert_eq!(uninstall[0].pkgutil, vec!["us.zoom.pkg.videomeeting"]);
+        assert_eq!(uninstall[0].delete.len(), 2);
+        assert_eq!(
+            uninstall[0].other.get("signal"),
+            Some(&serde_json::json!(["KILL", "us.zoom.xos"]))
+        );
+        let Artifact::Zap(zap) = &parsed[3] else {
+            panic!("expected zap, got {:?}", parsed[3]);
+        };
+        assert_eq!(
+            zap[0].trash,
+            vec!["~/Library/Preferences/us.zoom.xos.plist"]
+        );
+        assert_eq!(zap[0].rmdir, vec!["~/Library/Application Support/zoom.us"]);
+        assert!(matches!(parsed[4], Artifact::Other(_)));
+
+        // Serializes back to the API's shape, with single strings normalized to lists
+        let round_trip = serde_json::to_value(&parsed).unwrap();
+        assert_eq!(round_trip[0], artifacts[0]);
+        assert_eq!(round_trip[1], artifacts[1]);
+        assert_eq!(
+            round_trip[2]["uninstall"][0]["pkgutil"],
+            serde_json::json!(["us.zoom.pkg.videomeeting"])
+        );
+        assert_eq!(round_trip[4], artifacts[4]);
+        assert_eq!(
+            serde_json::from_value::<Vec<Artifact>>(round_trip).unwrap(),
+            parsed
+        );
+    }
+
+    #[test]
+    fn test_cask_requirements_parse() {
+        let parsed = cask(serde_json::json!({
+            "token": "wireshark",
+            "depends_on": {"formula": ["libpcap"], "macos": {">=": ["12"]}},
+            "conflicts_with": {"cask": ["wireshark-chmodbpf"]}
+        }));
+        assert_eq!(parsed.depends_on.formula, vec!["libpcap"]);
+        assert_eq!(
+            parsed.conflicts_with.unwrap().cask,
+            vec!["wireshark-chmodbpf"]
+        );
+
+        // The API sends `null` for casks without conflicts
+        let parsed = cask(serde_json::json!({"token": "firefox", "conflicts_with": null}));
+        assert!(parsed.conflicts_with.is_none());
+        assert!(parsed.depends_on.macos.is_none());
+    }
+
+    #[test]
+    fn test_cask_macos_requirement() {
+        let requires = |op: &str, versions: &[&str]| CaskDependsOn {
+            macos: Some(HashMap::from([(
+                op.to_string(),
+                versions.iter().map(|v| v.to_string()).collect(),
+            )])),
+            ..Default::default()
+        };
+
+        assert!(CaskDependsOn::default().macos_satisfied("11.0"));
+        assert!(requires(">=", &["12"]).macos_satisfied("15.1"));
+        assert!(requires(">=", &["12"]).macos_satisfied("12.0.1"));
+        assert!(!requires(">=", &["12"]).macos_satisfied("11.7"));
+        assert!(requires(">=", &["10.15"]).macos_satisfied("10.15.7"));
+        assert!(!requires("<", &["14"]).macos_satisfied("14.7.1"));
+        assert!(requires("==", &["13", "14"]).macos_satisfied("14.7.1"));
+        assert!(!requires("==", &["13", "14"]).macos_satisfied("15.0"));
+    }
+
+    #[test]
+    fn test_cask_install_order_puts_dependencies_first() {
+        let casks = vec![
+            cask(serde_json::json!({
+                "token": "app",
+                "depends_on": {"cask": ["runtime"], "formula": ["libpcap", "openssl@3"]}
+            })),
+            cask(serde_json::json!({"token": "standalone"})),
+            cask(serde_json::json!({
+                "token": "runtime",
+                "depends_on": {"cask": ["base"], "formula": ["openssl@3"]}
+            })),
+            cask(
+                serde_json::json!({"token": "base", "depends_on": {"cask": ["installed-elsewhere"]}}),
+            ),
+        ];
+
+        let (formulae, ordered) = cask_install_order(casks);
+        let tokens: Vec<_> = ordered.iter().map(|c| c.token.as_str()).collect();
+
+        assert_eq!(formulae, vec!["libpcap", "openssl@3"]);
+        assert_eq!(tokens, vec!["base", "runtime", "app", "standalone"]);
+    }
+
+    #[test]
+    fn test_cask_install_order_tolerates_cycles() {
+        let casks = vec![
+            cask(serde_json::json!({"token": "a", "depends_on": {"cask": ["b"]}})),
+            cask(serde_json::json!({"token": "b", "depends_on": {"cask": ["a"]}})),
+        ];
+
+        let (_, ordered) = cask_install_order(casks);
+        assert_eq!(ordered.len(), 2);
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = reqwest::header::HeaderMap::new();
+        assert_eq!(retry_after(&headers), Duration::from_secs(1));
+
+        headers.insert(reqwest::header::RETRY_AFTER, "5".parse().unwrap());
+        assert_eq!(retry_after(&headers), Duration::from_secs(5));
+
+        // HTTP-date values aren't parsed; fall back to the default
+        headers.insert(
+            reqwest::header::RETRY_AFTER,
+            "Wed, 21 Oct 2026 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_spaces_requests() {
+        let limiter = Limiter::new(RateLimit {
+            max_concurrent: 4,
+            requests_per_second: Some(20.0),
+        });
+        let start = tokio::time::Instant::now();
+        for _ in 0..3 {
+            drop(limiter.acquire().await);
+        }
+        // First request starts immediately, the next two wait 50ms each
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+
+    #[tokio::test]
+    async fn test_limiter_caps_concurrency() {
+        let limiter = Limiter::new(RateLimit {
+            max_concurrent: 1,
+            requests_per_second: None,
+        });
+        let held = limiter.acquire().await;
+        assert!(limiter.permits.try_acquire().is_err());
+        drop(held);
+        assert!(limiter.permits.try_acquire().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_api_get_holds_permit_until_body_is_read() {
+        let (port, _server) = crate::test_support::serve_once(b"[]".to_vec());
+        let url = format!("http://127.0.0.1:{}/formula.json", port);
+
+        let api = BrewApi::with_client(reqwest::Client::new()).with_rate_limit(RateLimit {
+            max_concurrent: 1,
+            requests_per_second: None,
+        });
+        let response = api.api_get(&url).await.unwrap();
+        assert!(api.limiter.permits.try_acquire().is_err());
+        let formulae: Vec<Formula> = response.json().await.unwrap();
+        assert!(formulae.is_empty());
+        assert!(api.limiter.permits.try_acquire().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_search_cache_follows_formulae_cache() {
+        let cache_dir = tempfile::TempDir::new().unwrap();
+        let cache_dir = cache_dir.path();
+        let formulae_path = cache_dir.join("formulae.json");
+        let write = |names: &[&str], modified: std::time::SystemTime| {
+            let formulae: Vec<_> = names
+                .iter()
+                .map(|name| serde_json::json!({ "name": name }))
+                .collect();
+            std::fs::write(&formulae_path, serde_json::to_string(&formulae).unwrap()).unwrap();
+            std::fs::File::options()
+                .write(true)
+                .open(&formulae_path)
+                .unwrap()
+                .set_modified(modified)
+                .unwrap();
+        };
+        let names = |results: SearchResults| -> Vec<String> {
+            results.formulae.into_iter().map(|f| f.item.name).collect()
+        };
+
+        let api = BrewApi::with_client(reqwest::Client::new());
+        let scope = SearchScope::from_flags(true, false);
+        let written = std::time::SystemTime::now() - Duration::from_secs(60);
+
+        write(&["ripgrep"], written);
+        assert_eq!(
+            names(api.search_in(cache_dir, "rip", scope).await.unwrap()),
+            ["ripgrep"]
+        );
+
+        // Same cached list (by modification time): the remembered result is returned
+        write(&["ripgrep", "ripsecrets"], written);
+        assert_eq!(
+            names(api.search_in(cache_dir, "RIP", scope).await.unwrap()),
+            ["ripgrep"]
+        );
+
+        // A refreshed list invalidates it
+        let refreshed = written + Duration::from_secs(30);
+        write(&["ripgrep", "ripsecrets"], refreshed);
+        assert_eq!(
+            names(api.search_in(cache_dir, "rip", scope).await.unwrap()),
+            ["ripgrep", "ripsecrets"]
+        );
+
+        write(&["ripgrep"], refreshed);
+        api.clear_search_cache();
+        assert_eq!(
+            names(api.search_in(cache_dir, "rip", scope).await.unwrap()),
+            ["ripgrep"]
+        );
+    }
+}
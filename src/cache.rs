@@ -6,11 +6,12 @@
 //!
 //! # Architecture
 //!
-//! Two main cache files:
+//! Main cache files:
 //! ```text
 //! ~/.cache/bru/
 //!   formulae.json    # All formula metadata from API
 //!   casks.json       # All cask metadata from API
+//!   executables.json # Which executables each formula installs
 //! ```
 //!
 //! Each file is refreshed automatically when:
@@ -41,9 +42,9 @@
 //! }
 //! ```
 
-use crate::api::{Cask, Formula};
+use crate::api::{Cask, Formula, FormulaBrief, FormulaExecutables};
 use crate::error::Result;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, SystemTime};
 
 const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60); // 24 hours
@@ -71,6 +72,7 @@ const CACHE_TTL: Duration = Duration::from_secs(24 * 60 * 60); // 24 hours
 ///
 /// The directory contains:
 /// - `formulae.json` - Cached list of all Homebrew formulae (24-hour TTL)
+/// - `formulae-brief.json` - Name/description/dependency index of the formulae (24-hour TTL)
 /// - `casks.json` - Cached list of all Homebrew casks (24-hour TTL)
 /// - `downloads/` - Cached downloaded bottles
 pub fn cache_dir() -> PathBuf {
@@ -107,6 +109,42 @@ pub fn is_cache_fresh(path: &PathBuf) -> bool {
     age < CACHE_TTL
 }
 
+/// When a fresh cache file was last written, or `None` if it's missing or stale.
+///
+/// Identifies one version of the cached data, since it changes on every refresh.
+fn fresh_modified(path: &PathBuf) -> Option<SystemTime> {
+    if !is_cache_fresh(path) {
+        return None;
+    }
+    std::fs::metadata(path).ok()?.modified().ok()
+}
+
+/// When the fresh formulae list was cached, or `None` if it's missing or stale.
+///
+/// Results derived from the list can be kept for as long as this stays the same.
+#[allow(dead_code)]
+pub fn formulae_modified() -> Option<SystemTime> {
+    formulae_modified_in(&cache_dir())
+}
+
+/// [`formulae_modified`] for the cache in an explicit directory
+pub fn formulae_modified_in(dir: &Path) -> Option<SystemTime> {
+    fresh_modified(&dir.join("formulae.json"))
+}
+
+/// When the fresh casks list was cached, or `None` if it's missing or stale.
+///
+/// See [`formulae_modified`].
+#[allow(dead_code)]
+pub fn casks_modified() -> Option<SystemTime> {
+    casks_modified_in(&cache_dir())
+}
+
+/// [`casks_modified`] for the cache in an explicit directory
+pub fn casks_modified_in(dir: &Path) -> Option<SystemTime> {
+    fresh_modified(&dir.join("casks.json"))
+}
+
 /// Get cached formulae list if it's still fresh (less than 24 hours old).
 ///
 /// Returns the cached list of all Homebrew formulae if the cache exists and is fresh.
@@ -129,7 +167,12 @@ pub fn is_cache_fresh(path: &PathBuf) -> bool {
 /// }
 /// ```
 pub fn get_cached_formulae() -> Option<Vec<Formula>> {
-    let cache_path = cache_dir().join("formulae.json");
+    get_cached_formulae_in(&cache_dir())
+}
+
+/// [`get_cached_formulae`] from the cache in an explicit directory
+pub fn get_cached_formulae_in(dir: &Path) -> Option<Vec<Formula>> {
+    let cache_path = dir.join("formulae.json");
 
     if !is_cache_fresh(&cache_path) {
         return None;
@@ -139,9 +182,123 @@ pub fn get_cached_formulae() -> Option<Vec<Formula>> {
     serde_json::from_str(&content).ok()
 }
 
+/// Get the cached formulae list regardless of its age.
+///
+/// For offline operations (e.g. `bru reinstall --from-cache`), where stale metadata is
+/// better than none. Returns `None` only if the cache is missing or corrupted.
+pub fn get_cached_formulae_any_age() -> Option<Vec<Formula>> {
+    let content = std::fs::read_to_string(cache_dir().join("formulae.json")).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Get a brief summary of the cached formulae list if it's still fresh.
+///
+/// Same cache as [`get_cached_formulae`], but streams the file into [`FormulaBrief`]s
+/// (name, description, dependencies) without reading it into memory first or
+/// allocating the fields it skips.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::cache;
+///
+/// if let Some(formulae) = cache::get_cached_formulae_brief() {
+///     println!("Cache hit: {} formulae cached", formulae.len());
+/// }
+/// ```
+pub fn get_cached_formulae_brief() -> Option<Vec<FormulaBrief>> {
+    let cache_path = cache_dir().join("formulae.json");
+
+    if !is_cache_fresh(&cache_path) {
+        return None;
+    }
+
+    let file = std::fs::File::open(&cache_path).ok()?;
+    serde_json::from_reader(std::io::BufReader::new(file)).ok()
+}
+
+/// Get the cached brief formula index if it's still fresh.
+///
+/// The index (`formulae-brief.json`) holds only names, descriptions, and dependencies,
+/// so it is a fraction of the size of the full formulae cache and much faster to load.
+/// Returns `None` if it is missing, stale, or corrupted.
+pub fn get_cached_brief_index() -> Option<Vec<FormulaBrief>> {
+    let cache_path = cache_dir().join("formulae-brief.json");
+
+    if !is_cache_fresh(&cache_path) {
+        return None;
+    }
+
+    let content = std::fs::read(&cache_path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Get formula names and descriptions from whichever formula cache exists, at any age.
+///
+/// For offline lookups (e.g. `bru search --installed`): prefers the small brief index
+/// and falls back to the full formulae cache. Returns `None` if neither is readable.
+pub fn get_cached_formulae_brief_any_age() -> Option<Vec<FormulaBrief>> {
+    ["formulae-brief.json", "formulae.json"]
+        .iter()
+        .find_map(|file| {
+            let content = std::fs::read(cache_dir().join(file)).ok()?;
+            serde_json::from_slice(&content).ok()
+        })
+}
+
+/// Get every cached formula name and cask token, at any age.
+///
+/// Backs "did you mean" suggestions, so it never touches the network: names come from
+/// whichever formula cache exists and from `casks.json` (tokens only, the rest of each
+/// cask is skipped). Returns an empty list if nothing has been cached yet.
+pub fn get_cached_names_any_age() -> Vec<String> {
+    #[derive(serde::Deserialize)]
+    struct CaskToken {
+        token: String,
+    }
+
+    let mut names: Vec<String> = get_cached_formulae_brief_any_age()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|f| f.name)
+        .collect();
+    if let Some(casks) = std::fs::read(cache_dir().join("casks.json"))
+        .ok()
+        .and_then(|content| serde_json::from_slice::<Vec<CaskToken>>(&content).ok())
+    {
+        names.extend(casks.into_iter().map(|c| c.token));
+    }
+    names
+}
+
+/// Store the brief formula index to cache.
+///
+/// # Errors
+///
+/// Returns an error if the cache directory cannot be created or the file cannot be written.
+pub fn store_brief_index(formulae: &[FormulaBrief]) -> Result<()> {
+    store_brief_index_in(&cache_dir(), formulae)
+}
+
+/// [`store_brief_index`] into the cache in an explicit directory
+pub fn store_brief_index_in(dir: &Path, formulae: &[FormulaBrief]) -> Result<()> {
+    let cache_path = dir.join("formulae-brief.json");
+
+    // Create cache directory if needed
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string(formulae)?;
+    std::fs::write(&cache_path, json)?;
+
+    Ok(())
+}
+
 /// Store the complete formulae list to cache.
 ///
-/// Saves the formulae list for fast lookup on subsequent runs.
+/// Saves the formulae list for fast lookup on subsequent runs, and refreshes the
+/// brief index alongside it so the two never disagree.
 /// Cache is automatically refreshed after 24 hours.
 ///
 /// # Errors
@@ -163,8 +320,14 @@ pub fn get_cached_formulae() -> Option<Vec<Formula>> {
 ///     Ok(())
 /// }
 /// ```
-pub fn store_formulae(formulae: &Vec<Formula>) -> Result<()> {
-    let cache_path = cache_dir().join("formulae.json");
+#[allow(dead_code)]
+pub fn store_formulae(formulae: &[Formula]) -> Result<()> {
+    store_formulae_in(&cache_dir(), formulae)
+}
+
+/// [`store_formulae`] into the cache in an explicit directory
+pub fn store_formulae_in(dir: &Path, formulae: &[Formula]) -> Result<()> {
+    let cache_path = dir.join("formulae.json");
 
     // Create cache directory if needed
     if let Some(parent) = cache_path.parent() {
@@ -174,6 +337,51 @@ pub fn store_formulae(formulae: &Vec<Formula>) -> Result<()> {
     let json = serde_json::to_string(formulae)?;
     std::fs::write(&cache_path, json)?;
 
+    let brief: Vec<FormulaBrief> = formulae.iter().cloned().map(FormulaBrief::from).collect();
+    store_brief_index_in(dir, &brief)?;
+
+    Ok(())
+}
+
+/// Get the cached executables index if it's still fresh.
+///
+/// Returns `None` if it is missing, stale, or corrupted.
+pub fn get_cached_executables() -> Option<Vec<FormulaExecutables>> {
+    let cache_path = cache_dir().join("executables.json");
+
+    if !is_cache_fresh(&cache_path) {
+        return None;
+    }
+
+    let content = std::fs::read(&cache_path).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Get the cached executables index regardless of its age.
+///
+/// Lets command lookups keep working offline. Returns `None` only if the cache is
+/// missing or corrupted.
+pub fn get_cached_executables_any_age() -> Option<Vec<FormulaExecutables>> {
+    let content = std::fs::read(cache_dir().join("executables.json")).ok()?;
+    serde_json::from_slice(&content).ok()
+}
+
+/// Store the executables index to cache.
+///
+/// # Errors
+///
+/// Returns an error if the cache directory cannot be created or the file cannot be written.
+pub fn store_executables(index: &[FormulaExecutables]) -> Result<()> {
+    let cache_path = cache_dir().join("executables.json");
+
+    // Create cache directory if needed
+    if let Some(parent) = cache_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let json = serde_json::to_string(index)?;
+    std::fs::write(&cache_path, json)?;
+
     Ok(())
 }
 
@@ -199,7 +407,12 @@ pub fn store_formulae(formulae: &Vec<Formula>) -> Result<()> {
 /// }
 /// ```
 pub fn get_cached_casks() -> Option<Vec<Cask>> {
-    let cache_path = cache_dir().join("casks.json");
+    get_cached_casks_in(&cache_dir())
+}
+
+/// [`get_cached_casks`] from the cache in an explicit directory
+pub fn get_cached_casks_in(dir: &Path) -> Option<Vec<Cask>> {
+    let cache_path = dir.join("casks.json");
 
     if !is_cache_fresh(&cache_path) {
         return None;
@@ -233,8 +446,14 @@ pub fn get_cached_casks() -> Option<Vec<Cask>> {
 ///     Ok(())
 /// }
 /// ```
-pub fn store_casks(casks: &Vec<Cask>) -> Result<()> {
-    let cache_path = cache_dir().join("casks.json");
+#[allow(dead_code)]
+pub fn store_casks(casks: &[Cask]) -> Result<()> {
+    store_casks_in(&cache_dir(), casks)
+}
+
+/// [`store_casks`] into the cache in an explicit directory
+pub fn store_casks_in(dir: &Path, casks: &[Cask]) -> Result<()> {
+    let cache_path = dir.join("casks.json");
 
     // Create cache directory if needed
     if let Some(parent) = cache_path.parent() {
@@ -1,9 +1,70 @@
+use crate::api::{Artifact, ArtifactPath, UninstallDirectives};
 use crate::cellar;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::RwLock;
 
 type Result<T> = anyhow::Result<T>;
 
+/// Where apps are installed by default
+const DEFAULT_APPDIR: &str = "/Applications";
+
+/// Directory chosen with `--appdir`, remembered by [`set_appdir`]
+static APPDIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// Directory apps are installed to
+///
+/// The `--appdir` given on the command line (see [`set_appdir`]), else the `--appdir`
+/// in `HOMEBREW_CASK_OPTS`, else /Applications. Users without admin rights typically
+/// pick `~/Applications`.
+pub fn appdir() -> PathBuf {
+    if let Some(appdir) = appdir_override() {
+        return appdir;
+    }
+
+    std::env::var("HOMEBREW_CASK_OPTS")
+        .ok()
+        .and_then(|opts| appdir_from_cask_opts(&opts))
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_APPDIR))
+}
+
+/// The `--appdir` given on the command line, if any (see [`set_appdir`])
+pub fn appdir_override() -> Option<PathBuf> {
+    APPDIR
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .clone()
+}
+
+/// Make [`appdir`] return `appdir` from now on (`--appdir`), or with `None`, go back to
+/// the environment and default
+pub fn set_appdir(appdir: Option<PathBuf>) {
+    *APPDIR
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = appdir;
+}
+
+/// The `--appdir` in a `HOMEBREW_CASK_OPTS` value, written `--appdir=DIR` or
+/// `--appdir DIR`, with a leading `~` expanded
+///
+/// Options are split on whitespace, so the directory can't contain spaces.
+fn appdir_from_cask_opts(opts: &str) -> Option<PathBuf> {
+    let mut words = opts.split_whitespace();
+    while let Some(word) = words.next() {
+        let dir = match word.strip_prefix("--appdir") {
+            Some("") => words.next()?,
+            Some(value) => match value.strip_prefix('=') {
+                Some(dir) => dir,
+                None => continue,
+            },
+            None => continue,
+        };
+        let dir = dir.trim_matches(|c| c == '"' || c == '\'');
+        return (!dir.is_empty()).then(|| expand_cask_path(dir));
+    }
+    None
+}
+
 /// Get Caskroom directory
 pub fn caskroom_dir() -> PathBuf {
     cellar::detect_prefix().join("Caskroom")
@@ -14,86 +75,170 @@ pub fn cask_install_dir(token: &str, version: &str) -> PathBuf {
     caskroom_dir().join(token).join(version)
 }
 
-/// Download a cask artifact
-pub async fn download_cask(url: &str, token: &str) -> Result<PathBuf> {
+/// Download a cask artifact, verifying it against `sha256` (see `Cask::checksum`)
+///
+/// With no checksum the file is downloaded unverified. A file that fails verification
+/// is deleted rather than returned, so it is never mounted.
+pub async fn download_cask(
+    client: &reqwest::Client,
+    url: &str,
+    token: &str,
+    sha256: Option<&str>,
+) -> Result<PathBuf> {
     let cache_dir = crate::download::cache_dir();
-    std::fs::create_dir_all(&cache_dir)?;
 
     // Extract filename from URL
     let filename = url.split('/').next_back().unwrap_or(token);
     let dest_path = cache_dir.join(filename);
 
-    // Skip if already downloaded
-    if dest_path.exists() {
-        return Ok(dest_path);
+    crate::download::download_verified(client, url, &dest_path, sha256).await
+}
+
+/// How many times `hdiutil attach` is tried before giving up
+const DMG_ATTACH_ATTEMPTS: u32 = 3;
+
+/// A mounted DMG, detached when dropped
+///
+/// Holding the mount in a guard means every early exit from an install (a failed copy,
+/// an error bubbling up with `?`) still detaches the image. Dropping detaches quietly,
+/// falling back to `hdiutil detach -force`; call [`MountedDmg::unmount`] to hear about
+/// failures.
+pub struct MountedDmg {
+    volumes: Vec<PathBuf>,
+    detach: fn(&Path, bool) -> Result<()>,
+}
+
+impl MountedDmg {
+    fn new(volumes: Vec<PathBuf>, detach: fn(&Path, bool) -> Result<()>) -> Self {
+        Self { volumes, detach }
     }
 
-    // Download file
-    let client = reqwest::Client::new();
-    let response = client.get(url).send().await?;
+    /// Mount points of the image's volumes; most DMGs have one
+    pub fn volumes(&self) -> &[PathBuf] {
+        &self.volumes
+    }
 
-    if !response.status().is_success() {
-        anyhow::bail!("Failed to download cask: HTTP {}", response.status());
+    /// Path of `relative` on the first volume that has it
+    pub fn find(&self, relative: &str) -> Option<PathBuf> {
+        self.volumes
+            .iter()
+            .map(|volume| volume.join(relative))
+            .find(|path| path.exists())
     }
 
-    let bytes = response.bytes().await?;
-    std::fs::write(&dest_path, bytes)?;
+    /// Detach every volume, forcing the ones that refuse
+    ///
+    /// # Errors
+    ///
+    /// Returns the first volume that couldn't be detached even with `-force`.
+    pub fn unmount(mut self) -> Result<()> {
+        let mut result = Ok(());
+        for volume in std::mem::take(&mut self.volumes) {
+            if let Err(e) = self.detach_volume(&volume)
+                && result.is_ok()
+            {
+                result = Err(e);
+            }
+        }
+        result
+    }
 
-    Ok(dest_path)
+    fn detach_volume(&self, volume: &Path) -> Result<()> {
+        (self.detach)(volume, false).or_else(|_| (self.detach)(volume, true))
+    }
+}
+
+impl Drop for MountedDmg {
+    fn drop(&mut self) {
+        for volume in std::mem::take(&mut self.volumes) {
+            let _ = self.detach_volume(&volume);
+        }
+    }
+}
+
+/// Mount a DMG file without showing it in Finder
+///
+/// Images with a license agreement wait for it to be accepted on stdin, so the
+/// agreement is answered the way brew does (`q` to leave the pager, `y` to agree).
+/// Attaching is retried a few times, since a just-downloaded image is sometimes still
+/// busy being scanned.
+pub fn mount_dmg(dmg_path: &Path) -> Result<MountedDmg> {
+    let mut attempt = 1;
+    loop {
+        match attach_dmg(dmg_path) {
+            Ok(volumes) => return Ok(MountedDmg::new(volumes, detach_dmg)),
+            Err(e) if attempt >= DMG_ATTACH_ATTEMPTS => return Err(e),
+            Err(_) => {
+                std::thread::sleep(std::time::Duration::from_secs(u64::from(attempt)));
+                attempt += 1;
+            }
+        }
+    }
 }
 
-/// Mount a DMG file and return the mount point
-pub fn mount_dmg(dmg_path: &PathBuf) -> Result<PathBuf> {
-    let output = Command::new("hdiutil")
-        .args(["attach", "-nobrowse"])
+fn attach_dmg(dmg_path: &Path) -> Result<Vec<PathBuf>> {
+    use std::io::Write;
+
+    let mut child = Command::new("hdiutil")
+        .args(["attach", "-nobrowse", "-readonly"])
         .arg(dmg_path)
-        .output()?;
+        .stdin(std::process::Stdio::piped())
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // Images without an agreement never read this, so a broken pipe is fine
+        let _ = stdin.write_all(b"qy\n");
+    }
+    let output = child.wait_with_output()?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to mount DMG: {}", stderr);
-    }
-
-    // Parse output to find mount point
-    // Output format: /dev/diskX<tab>filesystem<tab>/Volumes/Name
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    for line in stdout.lines() {
-        // Look for lines with /Volumes/
-        if line.contains("/Volumes/") {
-            // Split by tabs and whitespace
-            let parts: Vec<&str> = line
-                .split(|c: char| c == '\t' || c.is_whitespace())
-                .collect();
-            for part in parts {
-                let trimmed = part.trim();
-                if trimmed.starts_with("/Volumes/") && !trimmed.is_empty() {
-                    return Ok(PathBuf::from(trimmed));
-                }
-            }
-        }
+        anyhow::bail!("Failed to mount DMG: {}", stderr.trim());
     }
 
-    anyhow::bail!("Could not find mount point in hdiutil output")
+    let volumes = parse_mount_points(&String::from_utf8_lossy(&output.stdout));
+    if volumes.is_empty() {
+        anyhow::bail!("Could not find mount point in hdiutil output");
+    }
+    Ok(volumes)
 }
 
-/// Unmount a DMG
-pub fn unmount_dmg(mount_point: &PathBuf) -> Result<()> {
-    let output = Command::new("hdiutil")
-        .args(["detach", "-quiet"])
-        .arg(mount_point)
-        .output()?;
+/// Mount points in `hdiutil attach` output
+///
+/// Each attached device gets a tab-separated `device<tab>type<tab>mount point` line;
+/// only the lines of mounted volumes have a mount point, which may contain spaces.
+fn parse_mount_points(output: &str) -> Vec<PathBuf> {
+    output
+        .lines()
+        .filter_map(|line| line.rsplit('\t').next())
+        .map(str::trim)
+        .filter(|field| field.starts_with("/Volumes/"))
+        .map(PathBuf::from)
+        .collect()
+}
+
+/// Detach a mounted DMG volume, with `-force` if `force` is set
+pub fn detach_dmg(mount_point: &Path, force: bool) -> Result<()> {
+    let mut command = Command::new("hdiutil");
+    command.args(["detach", "-quiet"]);
+    if force {
+        command.arg("-force");
+    }
+    let output = command.arg(mount_point).output()?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to unmount DMG: {}", stderr);
+        anyhow::bail!("Failed to unmount DMG: {}", stderr.trim());
     }
 
     Ok(())
 }
 
-/// Copy an app bundle to /Applications
-pub fn install_app(app_path: &PathBuf, app_name: &str) -> Result<PathBuf> {
-    let target = PathBuf::from("/Applications").join(app_name);
+/// Copy an app bundle into `appdir` (see [`appdir`]) as `app_name`
+pub fn install_app(app_path: &Path, appdir: &Path, app_name: &str) -> Result<PathBuf> {
+    std::fs::create_dir_all(appdir)?;
+    let target = appdir.join(app_name);
 
     // Remove existing app if present
     if target.exists() {
@@ -109,14 +254,21 @@ pub fn install_app(app_path: &PathBuf, app_name: &str) -> Result<PathBuf> {
         .status()?;
 
     if !status.success() {
-        anyhow::bail!("Failed to copy app to /Applications");
+        anyhow::bail!("Failed to copy app to {}", appdir.display());
+    }
+
+    // cp can exit cleanly after copying only part of a bundle (e.g. the volume went away)
+    let info_plist = Path::new("Contents/Info.plist");
+    if !target.exists() || (app_path.join(info_plist).exists() && !target.join(info_plist).exists())
+    {
+        anyhow::bail!("App was not copied completely to {}", target.display());
     }
 
     Ok(target)
 }
 
 /// Install a PKG file
-pub fn install_pkg(pkg_path: &PathBuf) -> Result<()> {
+pub fn install_pkg(pkg_path: &Path) -> Result<()> {
     println!("  Installing PKG (requires sudo)...");
 
     let status = Command::new("sudo")
@@ -133,24 +285,268 @@ pub fn install_pkg(pkg_path: &PathBuf) -> Result<()> {
     Ok(())
 }
 
-/// Extract artifacts from cask JSON
-pub fn extract_app_artifacts(artifacts: &[serde_json::Value]) -> Vec<String> {
-    let mut apps = Vec::new();
+/// App bundles a cask installs to /Applications, in stanza order
+pub fn extract_app_artifacts(artifacts: &[Artifact]) -> Vec<&ArtifactPath> {
+    artifacts
+        .iter()
+        .filter_map(|artifact| match artifact {
+            Artifact::App(app) => Some(app),
+            _ => None,
+        })
+        .collect()
+}
 
-    for artifact in artifacts {
-        if let Some(obj) = artifact.as_object()
-            && let Some(app_array) = obj.get("app")
-            && let Some(arr) = app_array.as_array()
-        {
-            for item in arr {
-                if let Some(app_name) = item.as_str() {
-                    apps.push(app_name.to_string());
-                }
-            }
+/// Installer packages a cask runs, in stanza order
+pub fn extract_pkg_artifacts(artifacts: &[Artifact]) -> Vec<&ArtifactPath> {
+    artifacts
+        .iter()
+        .filter_map(|artifact| match artifact {
+            Artifact::Pkg(pkg) => Some(pkg),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Command-line tools a cask links into the prefix's `bin`, in stanza order
+pub fn extract_binary_artifacts(artifacts: &[Artifact]) -> Vec<&ArtifactPath> {
+    artifacts
+        .iter()
+        .filter_map(|artifact| match artifact {
+            Artifact::Binary(binary) => Some(binary),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Whether a `binary` artifact lives inside an installed app (`$APPDIR/...`) rather
+/// than in the cask's download
+pub fn binary_in_app(binary: &ArtifactPath) -> bool {
+    binary.path.starts_with("$APPDIR/")
+}
+
+/// File a `binary` artifact links to
+///
+/// `$APPDIR` paths point into the directory apps were installed to; any other path is
+/// relative to the cask's staged directory (`Caskroom/<token>/<version>`), where the
+/// download was unpacked.
+pub fn binary_source(binary: &ArtifactPath, staged: &Path, appdir: &Path) -> PathBuf {
+    match binary.path.strip_prefix("$APPDIR/") {
+        Some(in_app) => appdir.join(in_app),
+        None => staged.join(&binary.path),
+    }
+}
+
+/// Copy a binary out of the download into the cask's staged directory, at its stanza
+/// path, so it outlasts the mounted DMG or the cached extraction
+pub fn stage_binary(source: &Path, binary: &ArtifactPath, staged: &Path) -> Result<PathBuf> {
+    let dest = staged.join(&binary.path);
+    if let Some(parent) = dest.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::copy(source, &dest)?;
+    Ok(dest)
+}
+
+/// Symlink a cask binary into `bin_dir` as `name`, making the source executable
+///
+/// A link already pointing at `source` is left alone; anything else at the destination
+/// (a formula's link, a user's file) is an error rather than being overwritten.
+pub fn link_binary(source: &Path, bin_dir: &Path, name: &str) -> Result<PathBuf> {
+    use std::os::unix::fs::PermissionsExt;
+
+    if !source.is_file() {
+        anyhow::bail!("Binary not found: {}", source.display());
+    }
+
+    let mut permissions = std::fs::metadata(source)?.permissions();
+    if permissions.mode() & 0o111 != 0o111 {
+        permissions.set_mode(permissions.mode() | 0o755);
+        std::fs::set_permissions(source, permissions)?;
+    }
+
+    let link = bin_dir.join(name);
+    if link.symlink_metadata().is_ok() {
+        if std::fs::read_link(&link).is_ok_and(|target| target == source) {
+            return Ok(link);
         }
+        anyhow::bail!("{} already exists", link.display());
     }
 
-    apps
+    std::fs::create_dir_all(bin_dir)?;
+    std::os::unix::fs::symlink(source, &link)?;
+    Ok(link)
+}
+
+/// Remove a cask binary's link from `bin_dir`
+///
+/// Only symlinks into one of `owners` (the cask's Caskroom directory, its appdir)
+/// are removed, so a formula that has since taken over the name keeps its link.
+/// Returns whether a link was removed.
+pub fn unlink_binary(bin_dir: &Path, name: &str, owners: &[PathBuf]) -> Result<bool> {
+    let link = bin_dir.join(name);
+    let Ok(target) = std::fs::read_link(&link) else {
+        return Ok(false);
+    };
+
+    if !owners.iter().any(|owner| target.starts_with(owner)) {
+        return Ok(false);
+    }
+
+    std::fs::remove_file(&link)?;
+    Ok(true)
+}
+
+/// Uninstall directives of a cask, followed by its zap directives when `zap` is set
+pub fn uninstall_directives(artifacts: &[Artifact], zap: bool) -> Vec<&UninstallDirectives> {
+    let uninstall = artifacts.iter().filter_map(|artifact| match artifact {
+        Artifact::Uninstall(directives) => Some(directives),
+        _ => None,
+    });
+    let zap_stanzas = artifacts.iter().filter_map(|artifact| match artifact {
+        Artifact::Zap(directives) if zap => Some(directives),
+        _ => None,
+    });
+    uninstall.chain(zap_stanzas).flatten().collect()
+}
+
+/// Expand the leading `~` casks use for paths in the user's home directory
+pub fn expand_cask_path(path: &str) -> PathBuf {
+    match path.strip_prefix("~/") {
+        Some(rest) => std::env::var_os("HOME")
+            .map(|home| PathBuf::from(home).join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    }
+}
+
+/// System and home directories an `uninstall`/`zap` stanza may never remove
+///
+/// The same roots brew refuses to delete; paths under them are fine.
+const UNDELETABLE_PATHS: &[&str] = &[
+    "/",
+    "/Applications",
+    "/Library",
+    "/Library/Application Support",
+    "/Library/Caches",
+    "/Library/Fonts",
+    "/Library/LaunchAgents",
+    "/Library/LaunchDaemons",
+    "/Library/Preferences",
+    "/Library/PrivilegedHelperTools",
+    "/System",
+    "/Users",
+    "/Volumes",
+    "/bin",
+    "/cores",
+    "/dev",
+    "/etc",
+    "/home",
+    "/opt",
+    "/private",
+    "/sbin",
+    "/tmp",
+    "/usr",
+    "/usr/local",
+    "/var",
+    "~",
+    "~/Applications",
+    "~/Desktop",
+    "~/Documents",
+    "~/Downloads",
+    "~/Library",
+    "~/Library/Application Support",
+    "~/Library/Caches",
+    "~/Library/Containers",
+    "~/Library/Fonts",
+    "~/Library/Group Containers",
+    "~/Library/LaunchAgents",
+    "~/Library/Logs",
+    "~/Library/Preferences",
+    "~/Library/Saved Application State",
+    "~/Movies",
+    "~/Music",
+    "~/Pictures",
+    "~/Public",
+];
+
+/// Whether a path from a cask's `uninstall`/`zap` stanza must be left alone
+///
+/// Refuses the roots in [`UNDELETABLE_PATHS`], the prefix and its top-level
+/// directories, and anything relative or climbing with `..`, which a cask has no
+/// business writing.
+pub fn is_undeletable(path: &Path) -> bool {
+    let home = std::env::var_os("HOME").map(PathBuf::from);
+    let expand = |root: &str| match root.strip_prefix('~') {
+        Some(rest) => home
+            .as_ref()
+            .map(|home| home.join(rest.trim_start_matches('/'))),
+        None => Some(PathBuf::from(root)),
+    };
+
+    if !path.is_absolute()
+        || path
+            .components()
+            .any(|c| c == std::path::Component::ParentDir)
+    {
+        return true;
+    }
+
+    let prefix = cellar::detect_prefix();
+    path == prefix
+        || path.parent() == Some(prefix.as_path())
+        || UNDELETABLE_PATHS
+            .iter()
+            .filter_map(|root| expand(root))
+            .any(|root| path == root)
+}
+
+/// Forget a package receipt left behind by a `pkg` artifact
+pub fn forget_pkg(package_id: &str) -> Result<()> {
+    let status = Command::new("sudo")
+        .args(["pkgutil", "--forget", package_id])
+        .status()?;
+
+    if !status.success() {
+        anyhow::bail!("Failed to forget package {}", package_id);
+    }
+
+    Ok(())
+}
+
+/// Unload a launchd job a cask registered
+pub fn unload_launchctl(label: &str) -> Result<()> {
+    let output = Command::new("launchctl").args(["remove", label]).output()?;
+
+    // Ignore errors if the job isn't loaded
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if !stderr.contains("No such process") {
+            anyhow::bail!("Failed to unload {}: {}", label, stderr.trim());
+        }
+    }
+
+    Ok(())
+}
+
+/// Move a file or directory to the user's Trash
+pub fn trash_path(path: &Path) -> Result<()> {
+    let trash =
+        PathBuf::from(std::env::var_os("HOME").ok_or_else(|| anyhow::anyhow!("HOME is not set"))?)
+            .join(".Trash");
+    std::fs::create_dir_all(&trash)?;
+
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid path: {}", path.display()))?;
+    let mut dest = trash.join(name);
+    // Don't clobber an earlier trashed copy
+    let mut n = 1;
+    while dest.exists() {
+        dest = trash.join(format!("{} {}", name.to_string_lossy(), n));
+        n += 1;
+    }
+    std::fs::rename(path, dest)?;
+    Ok(())
 }
 
 /// Check if a cask is installed
@@ -161,39 +557,62 @@ pub fn is_cask_installed(token: &str) -> bool {
 
 /// Get installed cask version
 pub fn get_installed_cask_version(token: &str) -> Option<String> {
-    let caskroom = caskroom_dir().join(token);
-    if !caskroom.exists() {
-        return None;
-    }
+    installed_cask_version_in(&caskroom_dir(), token)
+}
 
-    // Get the first (and typically only) version directory
-    if let Ok(entries) = std::fs::read_dir(&caskroom) {
-        for entry in entries.flatten() {
-            if entry.path().is_dir()
-                && let Some(version) = entry.file_name().to_str()
-            {
-                return Some(version.to_string());
-            }
-        }
-    }
+/// Installed version of a cask under an explicit Caskroom
+///
+/// Homebrew keeps each cask in `Caskroom/<token>/<version>/` and records the install
+/// under `.metadata/<version>/<timestamp>/`. Casks whose artifacts are moved out
+/// entirely (fonts, some pkgs) may only have the `.metadata` entry, so that is the
+/// fallback. When several versions are present the newest one wins.
+pub fn installed_cask_version_in(caskroom: &Path, token: &str) -> Option<String> {
+    let cask_dir = caskroom.join(token);
+    let version_dirs = |dir: &Path| -> Vec<String> {
+        std::fs::read_dir(dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().to_str().map(String::from))
+            .filter(|name| !name.starts_with('.'))
+            .collect()
+    };
 
-    None
+    let mut versions = version_dirs(&cask_dir);
+    if versions.is_empty() {
+        versions = version_dirs(&cask_dir.join(".metadata"));
+    }
+    versions
+        .into_iter()
+        .max_by(|a, b| cellar::compare_versions(a, b).then_with(|| a.cmp(b)))
 }
 
 /// List all installed casks
 pub fn list_installed_casks() -> Result<Vec<(String, String)>> {
+    list_installed_casks_in(&caskroom_dir())
+}
+
+/// List the casks in an explicit Caskroom as `(token, version)` pairs, sorted by token
+///
+/// Reads the directory layout only, so casks installed by `brew` are found as well as
+/// those installed by bru (which also writes a `.metadata.json` into the version
+/// directory).
+pub fn list_installed_casks_in(caskroom: &Path) -> Result<Vec<(String, String)>> {
     let mut casks = Vec::new();
-    let caskroom = caskroom_dir();
 
     if !caskroom.exists() {
         return Ok(casks);
     }
 
-    for entry in std::fs::read_dir(&caskroom)? {
+    for entry in std::fs::read_dir(caskroom)? {
         let entry = entry?;
         let token = entry.file_name().to_string_lossy().to_string();
+        if token.starts_with('.') || !entry.path().is_dir() {
+            continue;
+        }
 
-        if let Some(version) = get_installed_cask_version(&token) {
+        if let Some(version) = installed_cask_version_in(caskroom, &token) {
             casks.push((token, version));
         }
     }
@@ -202,13 +621,96 @@ pub fn list_installed_casks() -> Result<Vec<(String, String)>> {
     Ok(casks)
 }
 
-/// Extract a ZIP file and return the extraction directory
-pub fn extract_zip(zip_path: &PathBuf) -> Result<PathBuf> {
+/// Cask definitions recorded for an installed cask, as raw JSON
+///
+/// Reads the `.metadata.json` bru writes into each version directory, plus the cask
+/// definition Homebrew keeps under `.metadata/<version>/<timestamp>/Casks/<token>.json`.
+fn installed_cask_definitions(caskroom: &Path, token: &str) -> Vec<serde_json::Value> {
+    let cask_dir = caskroom.join(token);
+    let mut definitions = Vec::new();
+
+    for entry in std::fs::read_dir(&cask_dir).into_iter().flatten().flatten() {
+        let path = entry.path();
+        if entry.file_name() == ".metadata" {
+            // .metadata/<version>/<timestamp>/Casks/<token>.json
+            for version in std::fs::read_dir(&path).into_iter().flatten().flatten() {
+                for stamp in std::fs::read_dir(version.path())
+                    .into_iter()
+                    .flatten()
+                    .flatten()
+                {
+                    definitions.push(stamp.path().join("Casks").join(format!("{}.json", token)));
+                }
+            }
+        } else if path.is_dir() {
+            definitions.push(path.join(".metadata.json"));
+        }
+    }
+
+    definitions
+        .iter()
+        .filter_map(|path| std::fs::read_to_string(path).ok())
+        .filter_map(|contents| serde_json::from_str(&contents).ok())
+        .collect()
+}
+
+/// Formulae an installed cask depends on (`depends_on formula:`)
+///
+/// Tap-qualified names (`homebrew/core/libpcap`) are reduced to the formula name.
+pub fn installed_cask_formula_dependencies(token: &str) -> Vec<String> {
+    let mut formulae: Vec<String> = installed_cask_definitions(&caskroom_dir(), token)
+        .iter()
+        .filter_map(|json| {
+            serde_json::from_value::<crate::api::CaskDependsOn>(json.get("depends_on")?.clone())
+                .ok()
+        })
+        .flat_map(|depends_on| depends_on.formula)
+        .map(|name| name.rsplit('/').next().unwrap_or(&name).to_string())
+        .collect();
+    formulae.sort();
+    formulae.dedup();
+    formulae
+}
+
+/// Artifacts recorded for an installed cask under an explicit Caskroom
+///
+/// Uses the first recorded definition with artifacts; casks bru installed before it
+/// recorded them have none.
+pub fn installed_cask_artifacts_in(caskroom: &Path, token: &str) -> Vec<Artifact> {
+    installed_cask_definitions(caskroom, token)
+        .into_iter()
+        .filter_map(|mut json| {
+            serde_json::from_value::<Vec<Artifact>>(json.get_mut("artifacts")?.take()).ok()
+        })
+        .find(|artifacts| !artifacts.is_empty())
+        .unwrap_or_default()
+}
+
+/// Appdir an installed cask's apps went to, as bru recorded it under an explicit Caskroom
+pub fn installed_cask_appdir_in(caskroom: &Path, token: &str) -> Option<PathBuf> {
+    installed_cask_definitions(caskroom, token)
+        .iter()
+        .find_map(|json| json.get("appdir")?.as_str().map(PathBuf::from))
+}
+
+/// Whether a download is a tar archive (`.tar.gz`, `.tgz`, `.tar.xz`, ...)
+pub fn is_tar_archive(filename: &str) -> bool {
+    let filename = filename.to_lowercase();
+    [
+        ".tar", ".tar.gz", ".tgz", ".tar.bz2", ".tbz", ".tbz2", ".tar.xz", ".txz",
+    ]
+    .iter()
+    .any(|ext| filename.ends_with(ext))
+}
+
+/// Extract a ZIP or tar archive and return the extraction directory
+pub fn extract_archive(archive: &Path) -> Result<PathBuf> {
     let cache_dir = crate::download::cache_dir();
-    let extract_dir = cache_dir.join(format!(
-        "{}_extracted",
-        zip_path.file_stem().unwrap().to_string_lossy()
-    ));
+    let file_name = archive
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Invalid archive path: {}", archive.display()))?
+        .to_string_lossy();
+    let extract_dir = cache_dir.join(format!("{}_extracted", file_name));
 
     // Remove existing extraction directory if present
     if extract_dir.exists() {
@@ -217,23 +719,31 @@ pub fn extract_zip(zip_path: &PathBuf) -> Result<PathBuf> {
 
     std::fs::create_dir_all(&extract_dir)?;
 
-    // Extract ZIP file
-    let status = Command::new("unzip")
-        .args(["-q", "-o"]) // quiet, overwrite
-        .arg(zip_path)
-        .arg("-d")
-        .arg(&extract_dir)
-        .status()?;
+    let status = if is_tar_archive(&file_name) {
+        // tar picks the compression from the file itself
+        Command::new("tar")
+            .arg("-xf")
+            .arg(archive)
+            .arg("-C")
+            .arg(&extract_dir)
+            .status()?
+    } else {
+        Command::new("unzip")
+            .args(["-q", "-o"]) // quiet, overwrite
+            .arg(archive)
+            .arg("-d")
+            .arg(&extract_dir)
+            .status()?
+    };
 
     if !status.success() {
-        anyhow::bail!("Failed to extract ZIP file");
+        anyhow::bail!("Failed to extract {}", file_name);
     }
 
     Ok(extract_dir)
 }
 
 /// Quit an application before uninstalling
-#[allow(dead_code)]
 pub fn quit_app(bundle_id: &str) -> Result<()> {
     let output = Command::new("osascript")
         .args(["-e", &format!("quit app id \"{}\"", bundle_id)])
@@ -249,3 +759,352 @@ pub fn quit_app(bundle_id: &str) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_installed_casks_from_brew_layout() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let caskroom = temp.path();
+
+        // Installed by brew: version dir plus .metadata record
+        std::fs::create_dir_all(caskroom.join("firefox/131.0")).unwrap();
+        std::fs::create_dir_all(caskroom.join("firefox/.metadata/131.0/20241001120000.000/Casks"))
+            .unwrap();
+        // An upgrade that left the previous version behind
+        std::fs::create_dir_all(caskroom.join("iterm2/3.5.9")).unwrap();
+        std::fs::create_dir_all(caskroom.join("iterm2/3.5.10")).unwrap();
+        // A font: nothing left but the .metadata record
+        std::fs::create_dir_all(
+            caskroom.join("font-hack-nerd-font/.metadata/3.2.1/20240101000000.000"),
+        )
+        .unwrap();
+        // Installed by bru
+        std::fs::create_dir_all(caskroom.join("rectangle/0.85")).unwrap();
+        std::fs::write(
+            caskroom.join("rectangle/0.85/.metadata.json"),
+            r#"{"token": "rectangle", "version": "0.85"}"#,
+        )
+        .unwrap();
+        // Junk
+        std::fs::write(caskroom.join(".DS_Store"), "").unwrap();
+        std::fs::create_dir_all(caskroom.join("empty")).unwrap();
+
+        let casks = list_installed_casks_in(caskroom).unwrap();
+        assert_eq!(
+            casks,
+            vec![
+                ("firefox".to_string(), "131.0".to_string()),
+                ("font-hack-nerd-font".to_string(), "3.2.1".to_string()),
+                ("iterm2".to_string(), "3.5.10".to_string()),
+                ("rectangle".to_string(), "0.85".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_installed_cask_artifacts_from_either_record() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let caskroom = temp.path();
+
+        // Installed by brew: the cask definition under .metadata
+        let casks = caskroom.join("rectangle/.metadata/0.85/20241001120000.000/Casks");
+        std::fs::create_dir_all(&casks).unwrap();
+        std::fs::create_dir_all(caskroom.join("rectangle/0.85")).unwrap();
+        std::fs::write(
+            casks.join("rectangle.json"),
+            r#"{"token": "rectangle", "artifacts": [
+                {"app": ["Rectangle.app"]},
+                {"uninstall": [{"quit": "com.knollsoft.Rectangle"}]},
+                {"zap": [{"trash": ["~/Library/Preferences/com.knollsoft.Rectangle.plist"]}]}
+            ]}"#,
+        )
+        .unwrap();
+        // Installed by bru, before it recorded artifacts
+        std::fs::create_dir_all(caskroom.join("firefox/131.0")).unwrap();
+        std::fs::write(
+            caskroom.join("firefox/131.0/.metadata.json"),
+            r#"{"token": "firefox", "installed_apps": ["Firefox.app"], "appdir": "/Users/me/Applications"}"#,
+        )
+        .unwrap();
+
+        let artifacts = installed_cask_artifacts_in(caskroom, "rectangle");
+        let apps: Vec<&str> = extract_app_artifacts(&artifacts)
+            .iter()
+            .map(|app| app.installed_name())
+            .collect();
+        assert_eq!(apps, vec!["Rectangle.app"]);
+
+        let uninstall = uninstall_directives(&artifacts, false);
+        assert_eq!(uninstall.len(), 1);
+        assert_eq!(uninstall[0].quit, vec!["com.knollsoft.Rectangle"]);
+        let zap = uninstall_directives(&artifacts, true);
+        assert_eq!(zap.len(), 2);
+        assert_eq!(zap[1].trash.len(), 1);
+
+        assert!(installed_cask_artifacts_in(caskroom, "firefox").is_empty());
+
+        assert_eq!(
+            installed_cask_appdir_in(caskroom, "firefox"),
+            Some(PathBuf::from("/Users/me/Applications"))
+        );
+        assert_eq!(installed_cask_appdir_in(caskroom, "rectangle"), None);
+    }
+
+    #[test]
+    fn test_binary_cask_links_and_unlinks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let download = temp.path().join("download");
+        let staged = temp.path().join("Caskroom/ngrok/3.18.4");
+        let bin_dir = temp.path().join("bin");
+
+        // ngrok ships as a zip holding the bare executable
+        let cask: crate::api::Cask = serde_json::from_value(serde_json::json!({
+            "token": "ngrok",
+            "version": "3.18.4",
+            "artifacts": [
+                {"binary": ["ngrok"]},
+                {"binary": ["$APPDIR/Ngrok.app/Contents/MacOS/agent", {"target": "ngrok-agent"}]}
+            ]
+        }))
+        .unwrap();
+        let binaries = extract_binary_artifacts(&cask.artifacts);
+        assert_eq!(binaries.len(), 2);
+        assert!(!binary_in_app(binaries[0]));
+        assert!(binary_in_app(binaries[1]));
+        assert_eq!(binaries[1].installed_name(), "ngrok-agent");
+        let appdir = Path::new("/Applications");
+        assert_eq!(
+            binary_source(binaries[1], &staged, appdir),
+            PathBuf::from("/Applications/Ngrok.app/Contents/MacOS/agent")
+        );
+
+        std::fs::create_dir_all(&download).unwrap();
+        std::fs::write(download.join("ngrok"), "#!/bin/sh\n").unwrap();
+        std::fs::write(download.join("README.md"), "").unwrap();
+        let staged_binary = stage_binary(&download.join("ngrok"), binaries[0], &staged).unwrap();
+        assert_eq!(staged_binary, staged.join("ngrok"));
+        // Only the binary is staged, not the rest of the download
+        assert!(!staged.join("README.md").exists());
+        assert!(is_tar_archive("ngrok-v3-stable-darwin-arm64.TGZ"));
+        assert!(is_tar_archive("tool.tar.xz"));
+        assert!(!is_tar_archive("tool.zip"));
+
+        let source = binary_source(binaries[0], &staged, appdir);
+        assert_eq!(source, staged.join("ngrok"));
+        let link = link_binary(&source, &bin_dir, binaries[0].installed_name()).unwrap();
+        assert_eq!(link, bin_dir.join("ngrok"));
+        assert_eq!(std::fs::read_link(&link).unwrap(), source);
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&source).unwrap().permissions().mode();
+            assert_eq!(mode & 0o111, 0o111);
+        }
+
+        // Relinking is a no-op; someone else's file is never replaced
+        assert!(link_binary(&source, &bin_dir, "ngrok").is_ok());
+        std::fs::write(bin_dir.join("taken"), "").unwrap();
+        assert!(link_binary(&source, &bin_dir, "taken").is_err());
+        // The app isn't installed, so its binary has nothing to link to
+        assert!(
+            link_binary(
+                &binary_source(binaries[1], &staged, appdir),
+                &bin_dir,
+                "ngrok-agent"
+            )
+            .is_err()
+        );
+
+        // Only links into the cask's own directories are removed
+        let other_cask = [temp.path().join("Caskroom/other")];
+        assert!(!unlink_binary(&bin_dir, "ngrok", &other_cask).unwrap());
+        assert!(link.symlink_metadata().is_ok());
+        let owners = [temp.path().join("Caskroom/ngrok")];
+        assert!(unlink_binary(&bin_dir, "ngrok", &owners).unwrap());
+        assert!(link.symlink_metadata().is_err());
+        assert!(!unlink_binary(&bin_dir, "taken", &owners).unwrap());
+        assert!(bin_dir.join("taken").exists());
+    }
+
+    #[test]
+    fn test_appdir_from_cask_opts() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        assert_eq!(
+            appdir_from_cask_opts("--appdir=~/Applications"),
+            Some(home.join("Applications"))
+        );
+        assert_eq!(
+            appdir_from_cask_opts("--no-quarantine --appdir /opt/apps"),
+            Some(PathBuf::from("/opt/apps"))
+        );
+        assert_eq!(
+            appdir_from_cask_opts("--appdir=\"/Users/me/Apps\" --fontdir=/Library/Fonts"),
+            Some(PathBuf::from("/Users/me/Apps"))
+        );
+        assert_eq!(appdir_from_cask_opts("--appdirs=/nope --fontdir=/x"), None);
+        assert_eq!(appdir_from_cask_opts("--appdir"), None);
+        assert_eq!(appdir_from_cask_opts(""), None);
+    }
+
+    #[test]
+    fn test_install_app_into_appdir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let app = temp.path().join("volume/Visual Studio Code.app");
+        std::fs::create_dir_all(app.join("Contents/MacOS")).unwrap();
+        std::fs::write(app.join("Contents/MacOS/Electron"), "").unwrap();
+        // ~/Applications often doesn't exist yet
+        let appdir = temp.path().join("home/Applications");
+
+        let target = install_app(&app, &appdir, "Code.app").unwrap();
+        assert_eq!(target, appdir.join("Code.app"));
+        assert!(target.join("Contents/MacOS/Electron").exists());
+
+        // Reinstalling replaces the previous copy
+        std::fs::write(target.join("stale"), "").unwrap();
+        install_app(&app, &appdir, "Code.app").unwrap();
+        assert!(!target.join("stale").exists());
+        assert!(target.join("Contents/MacOS/Electron").exists());
+    }
+
+    #[test]
+    fn test_parse_mount_points() {
+        // A DMG with two volumes; the partition map lines have no mount point
+        let output = "/dev/disk4          \tGUID_partition_scheme          \t\n\
+                      /dev/disk4s1        \tApple_HFS                      \t/Volumes/Visual Studio Code\n\
+                      /dev/disk5          \tGUID_partition_scheme          \t\n\
+                      /dev/disk5s1        \tApple_APFS                     \t/Volumes/Extras 2\n";
+        assert_eq!(
+            parse_mount_points(output),
+            vec![
+                PathBuf::from("/Volumes/Visual Studio Code"),
+                PathBuf::from("/Volumes/Extras 2"),
+            ]
+        );
+        assert!(parse_mount_points("").is_empty());
+    }
+
+    thread_local! {
+        static DETACHED: std::cell::RefCell<Vec<(PathBuf, bool)>> =
+            const { std::cell::RefCell::new(Vec::new()) };
+    }
+
+    /// Records detach calls; volumes named `busy` refuse to detach without `-force`,
+    /// and `stuck` ones refuse even then
+    fn fake_detach(volume: &Path, force: bool) -> Result<()> {
+        DETACHED.with(|calls| calls.borrow_mut().push((volume.to_path_buf(), force)));
+        let name = volume.file_name().unwrap().to_string_lossy();
+        if name == "stuck" || (name == "busy" && !force) {
+            anyhow::bail!("resource busy");
+        }
+        Ok(())
+    }
+
+    fn take_detached() -> Vec<(PathBuf, bool)> {
+        DETACHED.with(|calls| std::mem::take(&mut *calls.borrow_mut()))
+    }
+
+    #[test]
+    fn test_mounted_dmg_detaches_on_drop() {
+        let volumes = vec![
+            PathBuf::from("/Volumes/App"),
+            PathBuf::from("/Volumes/busy"),
+        ];
+
+        // An install that bails out early still detaches every volume
+        let result: Result<()> = (|| {
+            let _dmg = MountedDmg::new(volumes.clone(), fake_detach);
+            anyhow::bail!("copy failed")
+        })();
+        assert!(result.is_err());
+        assert_eq!(
+            take_detached(),
+            vec![
+                (PathBuf::from("/Volumes/App"), false),
+                (PathBuf::from("/Volumes/busy"), false),
+                (PathBuf::from("/Volumes/busy"), true),
+            ]
+        );
+
+        // Unmounting explicitly detaches once; the drop afterwards has nothing left
+        let dmg = MountedDmg::new(volumes, fake_detach);
+        assert!(dmg.unmount().is_ok());
+        assert_eq!(take_detached().len(), 3);
+
+        let dmg = MountedDmg::new(
+            vec![
+                PathBuf::from("/Volumes/stuck"),
+                PathBuf::from("/Volumes/App"),
+            ],
+            fake_detach,
+        );
+        assert!(dmg.unmount().is_err());
+        assert_eq!(
+            take_detached(),
+            vec![
+                (PathBuf::from("/Volumes/stuck"), false),
+                (PathBuf::from("/Volumes/stuck"), true),
+                (PathBuf::from("/Volumes/App"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_mounted_dmg_finds_app_on_any_volume() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let first = temp.path().join("Installer");
+        let second = temp.path().join("Apps");
+        std::fs::create_dir_all(first.join("Read Me.rtfd")).unwrap();
+        std::fs::create_dir_all(second.join("Foo.app")).unwrap();
+
+        let dmg = MountedDmg::new(vec![first, second.clone()], fake_detach);
+        assert_eq!(dmg.find("Foo.app"), Some(second.join("Foo.app")));
+        assert_eq!(dmg.find("Bar.app"), None);
+        drop(dmg);
+        assert_eq!(take_detached().len(), 2);
+    }
+
+    #[test]
+    fn test_undeletable_paths() {
+        let home = PathBuf::from(std::env::var_os("HOME").unwrap());
+        for path in [
+            PathBuf::from("/"),
+            PathBuf::from("/Applications"),
+            PathBuf::from("/Applications/"),
+            PathBuf::from("/Library/LaunchDaemons"),
+            home.clone(),
+            home.join("Library"),
+            home.join("Library/Application Support"),
+            cellar::detect_prefix(),
+            cellar::detect_prefix().join("bin"),
+            // Relative or escaping paths are never trusted
+            PathBuf::from("~"),
+            PathBuf::from("Library/Caches"),
+            PathBuf::from("/Applications/Foo.app/../.."),
+        ] {
+            assert!(
+                is_undeletable(&path),
+                "{} should be protected",
+                path.display()
+            );
+        }
+
+        for path in [
+            PathBuf::from("/Applications/Foo.app"),
+            PathBuf::from("/Library/LaunchDaemons/com.foo.helper.plist"),
+            home.join("Library/Application Support/Foo"),
+            home.join(".foo"),
+        ] {
+            assert!(
+                !is_undeletable(&path),
+                "{} should be removable",
+                path.display()
+            );
+        }
+        assert!(is_undeletable(&expand_cask_path("~/Library/Caches")));
+        assert!(!is_undeletable(&expand_cask_path(
+            "~/Library/Caches/com.foo"
+        )));
+    }
+}
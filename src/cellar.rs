@@ -53,9 +53,11 @@
 //! ```
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock};
 
 /// Detect the Homebrew prefix on this system.
 ///
@@ -68,6 +70,9 @@ use std::path::{Path, PathBuf};
 /// 1. `HOMEBREW_PREFIX` environment variable (if set)
 /// 2. Architecture-based detection (aarch64 → `/opt/homebrew`, x86_64 → `/usr/local`)
 ///
+/// The result is remembered for the rest of the process, so a later change to
+/// `HOMEBREW_PREFIX` has no effect until [`set_prefix`] is called.
+///
 /// # Examples
 ///
 /// ```no_run
@@ -78,8 +83,43 @@ use std::path::{Path, PathBuf};
 /// // Output: "/opt/homebrew" (on Apple Silicon)
 /// // Output: "/usr/local" (on Intel)
 /// ```
-#[inline]
 pub fn detect_prefix() -> PathBuf {
+    if let Some(prefix) = PREFIX
+        .read()
+        .unwrap_or_else(std::sync::PoisonError::into_inner)
+        .as_ref()
+    {
+        return prefix.clone();
+    }
+
+    let prefix = detect_prefix_uncached();
+    *PREFIX
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = Some(prefix.clone());
+    prefix
+}
+
+/// Make [`detect_prefix`] return `prefix` from now on (`--prefix`), or with `None`,
+/// forget the remembered prefix so the next call detects it again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::cellar;
+///
+/// cellar::set_prefix(Some("/tmp/brew".into()));
+/// assert_eq!(cellar::cellar_path(), std::path::Path::new("/tmp/brew/Cellar"));
+/// ```
+pub fn set_prefix(prefix: Option<PathBuf>) {
+    *PREFIX
+        .write()
+        .unwrap_or_else(std::sync::PoisonError::into_inner) = prefix;
+}
+
+/// Prefix remembered by [`detect_prefix`]
+static PREFIX: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+fn detect_prefix_uncached() -> PathBuf {
     // First check environment variable
     if let Ok(prefix) = std::env::var("HOMEBREW_PREFIX") {
         return PathBuf::from(prefix);
@@ -142,12 +182,17 @@ pub fn cellar_path() -> PathBuf {
 pub struct RuntimeDependency {
     pub full_name: String,
     pub version: String,
+    #[serde(default)]
     pub revision: u32,
     #[serde(default)]
     pub bottle_rebuild: u32,
+    #[serde(default)]
     pub pkg_version: String,
     #[serde(default)]
     pub declared_directly: bool,
+    /// Fields bru doesn't model, kept so receipts round-trip unchanged
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
 /// Source information from install receipt
@@ -192,21 +237,20 @@ pub struct InstalledPackage {
     pub version: String,
     #[allow(dead_code)]
     pub path: PathBuf,
-    #[allow(dead_code)]
-    pub receipt: Option<InstallReceipt>,
+    /// INSTALL_RECEIPT.json, parsed on first access
+    receipt: OnceLock<Option<InstallReceipt>>,
 }
 
 impl InstalledPackage {
     /// Create from a Cellar version directory.
     ///
-    /// Reads the package metadata and INSTALL_RECEIPT.json if available.
+    /// INSTALL_RECEIPT.json is not read until one of the receipt accessors needs it.
     pub fn from_path(name: String, version: String, path: PathBuf) -> Self {
-        let receipt = Self::read_receipt(&path).ok();
         Self {
             name,
             version,
             path,
-            receipt,
+            receipt: OnceLock::new(),
         }
     }
 
@@ -220,11 +264,23 @@ impl InstalledPackage {
         Ok(receipt)
     }
 
+    /// The keg's install receipt, or `None` if it is missing or unreadable.
+    ///
+    /// The receipt is parsed the first time it is needed and cached afterwards.
+    pub fn receipt(&self) -> Option<&InstallReceipt> {
+        self.receipt
+            .get_or_init(|| Self::read_receipt(&self.path).ok())
+            .as_ref()
+    }
+
     /// Check if this was installed on request (vs as dependency).
     ///
     /// Returns `true` if the user explicitly requested this package installation,
     /// `false` if it was installed as a dependency of another package.
     ///
+    /// This is not simply the inverse of [`installed_as_dependency`](Self::installed_as_dependency):
+    /// a dependency the user later installs explicitly has both flags set.
+    ///
     /// # Examples
     ///
     /// ```no_run
@@ -242,12 +298,75 @@ impl InstalledPackage {
     /// ```
     #[allow(dead_code)]
     pub fn installed_on_request(&self) -> bool {
-        self.receipt
-            .as_ref()
+        self.receipt()
             .map(|r| r.installed_on_request)
             .unwrap_or(false)
     }
 
+    /// Check if this was first installed to satisfy another formula's dependency.
+    ///
+    /// The flag records how the keg originally arrived and is never cleared, so it stays
+    /// `true` after the user requests the formula directly. Use
+    /// [`installed_on_request`](Self::installed_on_request) to decide whether a package
+    /// is a leaf the user wants to keep.
+    #[allow(dead_code)]
+    pub fn installed_as_dependency(&self) -> bool {
+        self.receipt()
+            .map(|r| r.installed_as_dependency)
+            .unwrap_or(false)
+    }
+
+    /// The tap this package was installed from (e.g. `homebrew/core`), if recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::cellar;
+    ///
+    /// fn main() -> anyhow::Result<()> {
+    ///     for pkg in cellar::list_installed()? {
+    ///         println!("{} from {}", pkg.name, pkg.tap().unwrap_or("unknown tap"));
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(dead_code)]
+    pub fn tap(&self) -> Option<&str> {
+        self.receipt()
+            .and_then(|r| r.source.as_ref())
+            .and_then(|s| s.tap.as_deref())
+    }
+
+    /// Check if this package was poured from a bottle rather than built from source.
+    #[allow(dead_code)]
+    pub fn poured_from_bottle(&self) -> bool {
+        self.receipt()
+            .map(|r| r.poured_from_bottle)
+            .unwrap_or(false)
+    }
+
+    /// When this package was installed, from the receipt's `time` field.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::cellar;
+    ///
+    /// fn main() -> anyhow::Result<()> {
+    ///     for pkg in cellar::list_installed()? {
+    ///         if let Some(time) = pkg.installed_time() {
+    ///             println!("{} installed {}", pkg.name, time.format("%Y-%m-%d"));
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn installed_time(&self) -> Option<DateTime<Utc>> {
+        self.receipt()
+            .and_then(|r| r.time)
+            .and_then(|secs| DateTime::from_timestamp(secs, 0))
+    }
+
     /// Get runtime dependencies of this installed package.
     ///
     /// Returns a list of packages this package depends on at runtime. This is useful
@@ -271,11 +390,24 @@ impl InstalledPackage {
     /// ```
     #[allow(dead_code)]
     pub fn runtime_dependencies(&self) -> Vec<RuntimeDependency> {
-        self.receipt
-            .as_ref()
+        self.receipt()
             .map(|r| r.runtime_dependencies.clone())
             .unwrap_or_default()
     }
+
+    /// Check whether this package is keg-only, using the formula file shipped in the keg.
+    ///
+    /// Bottles include the formula source at `.brew/<name>.rb`. Returns `None` when that
+    /// file is missing, so callers can fall back to API metadata.
+    pub fn keg_only(&self) -> Option<bool> {
+        let formula_file = self.path.join(".brew").join(format!("{}.rb", self.name));
+        let contents = fs::read_to_string(formula_file).ok()?;
+        Some(
+            contents
+                .lines()
+                .any(|line| line.trim_start().starts_with("keg_only")),
+        )
+    }
 }
 
 /// Read all installed packages from the Cellar.
@@ -315,22 +447,27 @@ impl InstalledPackage {
 /// O(n) where n is the total number of installed package versions.
 /// On a typical system with 200+ packages, this takes 10-50ms.
 pub fn list_installed() -> Result<Vec<InstalledPackage>> {
-    let cellar = cellar_path();
+    list_installed_in(&cellar_path())
+}
 
+/// List all installed packages in an explicit Cellar directory.
+///
+/// Same as [`list_installed`], but reads `cellar` instead of the detected Homebrew Cellar.
+pub fn list_installed_in(cellar: &Path) -> Result<Vec<InstalledPackage>> {
     if !cellar.exists() {
         return Ok(vec![]);
     }
 
     let mut packages = Vec::new();
 
-    for entry in fs::read_dir(&cellar)
+    for entry in fs::read_dir(cellar)
         .with_context(|| format!("Failed to read Cellar: {}", cellar.display()))?
     {
         let entry = entry?;
         let formula_name = entry.file_name().to_string_lossy().to_string();
 
-        // Skip hidden files
-        if formula_name.starts_with('.') {
+        // Skip hidden files and anything that isn't a formula directory
+        if formula_name.starts_with('.') || !entry.path().is_dir() {
             continue;
         }
 
@@ -339,8 +476,8 @@ pub fn list_installed() -> Result<Vec<InstalledPackage>> {
             let version_entry = version_entry?;
             let version = version_entry.file_name().to_string_lossy().to_string();
 
-            // Skip hidden files
-            if version.starts_with('.') {
+            // Skip hidden files, stray files, and leftovers that aren't kegs
+            if !is_keg_dir(&version_entry.path(), &version) {
                 continue;
             }
 
@@ -353,6 +490,59 @@ pub fn list_installed() -> Result<Vec<InstalledPackage>> {
     Ok(packages)
 }
 
+/// List the current version of each installed formula.
+///
+/// When a formula has several versions in the Cellar, the highest one by
+/// [`compare_versions`] is current. Directory modification times are deliberately not
+/// used: reinstalling an old version touches it without making it newer. Versions with
+/// no numeric part (e.g. `HEAD-1a2b3c`) can't be ordered, so for those the linked
+/// version wins.
+///
+/// # Errors
+///
+/// Returns an error if the Cellar cannot be read.
+pub fn list_current() -> Result<Vec<InstalledPackage>> {
+    list_current_in(&detect_prefix())
+}
+
+/// List the current version of each installed formula under an explicit prefix.
+///
+/// Same as [`list_current`], but reads `prefix` instead of the detected Homebrew prefix.
+pub fn list_current_in(prefix: &Path) -> Result<Vec<InstalledPackage>> {
+    let mut by_name: std::collections::BTreeMap<String, Vec<InstalledPackage>> =
+        std::collections::BTreeMap::new();
+    for pkg in list_installed_in(&prefix.join("Cellar"))? {
+        by_name.entry(pkg.name.clone()).or_default().push(pkg);
+    }
+
+    let mut current = Vec::with_capacity(by_name.len());
+    for (name, mut versions) in by_name {
+        if versions.len() > 1 {
+            let orderable = versions.iter().all(|pkg| {
+                pkg.version
+                    .split('.')
+                    .any(|part| part.parse::<u32>().is_ok())
+            });
+            let linked = if orderable {
+                None
+            } else {
+                crate::symlink::get_linked_version_in(prefix, &name)
+                    .ok()
+                    .flatten()
+                    .and_then(|linked| versions.iter().position(|pkg| pkg.version == linked))
+            };
+            let index = linked.unwrap_or_else(|| {
+                (0..versions.len())
+                    .max_by(|&a, &b| compare_versions(&versions[a].version, &versions[b].version))
+                    .unwrap_or(0)
+            });
+            versions.swap(0, index);
+        }
+        current.extend(versions.into_iter().next());
+    }
+    Ok(current)
+}
+
 /// Get all versions of a specific formula, sorted by version (newest first).
 ///
 /// Returns a list of all installed versions of a formula, with the newest version first.
@@ -399,7 +589,15 @@ pub fn list_installed() -> Result<Vec<InstalledPackage>> {
 /// Versions are sorted semantically (e.g., 1.10.0 > 1.9.0). This ensures that accessing
 /// the first element always gives you the newest installed version.
 pub fn get_installed_versions(formula: &str) -> Result<Vec<InstalledPackage>> {
-    let formula_path = cellar_path().join(formula);
+    get_installed_versions_in(&cellar_path(), formula)
+}
+
+/// Get all installed versions of a formula from an explicit Cellar directory.
+///
+/// Same as [`get_installed_versions`], but reads `cellar` instead of the detected
+/// Homebrew Cellar.
+pub fn get_installed_versions_in(cellar: &Path, formula: &str) -> Result<Vec<InstalledPackage>> {
+    let formula_path = cellar.join(formula);
 
     if !formula_path.exists() {
         return Ok(vec![]);
@@ -411,7 +609,7 @@ pub fn get_installed_versions(formula: &str) -> Result<Vec<InstalledPackage>> {
         let entry = entry?;
         let version = entry.file_name().to_string_lossy().to_string();
 
-        if version.starts_with('.') {
+        if !is_keg_dir(&entry.path(), &version) {
             continue;
         }
 
@@ -427,6 +625,315 @@ pub fn get_installed_versions(formula: &str) -> Result<Vec<InstalledPackage>> {
     Ok(packages)
 }
 
+/// Top-level entries that only appear in a keg (a bottle's contents or Homebrew metadata)
+const KEG_MARKERS: &[&str] = &[
+    "INSTALL_RECEIPT.json",
+    ".brew",
+    "bin",
+    "sbin",
+    "lib",
+    "libexec",
+    "include",
+    "share",
+    "etc",
+    "Frameworks",
+];
+
+/// Check whether a Cellar version entry is a real keg.
+///
+/// Finder and interrupted installs leave `.DS_Store`, `.metadata`, and partially
+/// extracted directories next to real kegs. An entry counts as a keg when it is a
+/// directory whose name follows the version grammar and it has an install receipt or
+/// keg contents.
+fn is_keg_dir(path: &Path, version: &str) -> bool {
+    is_version_name(version)
+        && path.is_dir()
+        && KEG_MARKERS.iter().any(|marker| path.join(marker).exists())
+}
+
+/// Check whether a directory name looks like a Homebrew version (`1.7.1`, `3.12.0_1`,
+/// `2024a`, `HEAD-abc1234`).
+fn is_version_name(name: &str) -> bool {
+    let starts_alnum = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_alphanumeric());
+    let valid_chars = name
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-' | '+' | '~'));
+    let has_number = name.chars().any(|c| c.is_ascii_digit()) || name.starts_with("HEAD");
+
+    starts_alnum && valid_chars && has_number
+}
+
+/// List the files installed in a keg, relative to the keg directory.
+///
+/// Walks `keg_path` (e.g. `/opt/homebrew/Cellar/jq/1.7.1`) and returns every file and
+/// symlink it contains, sorted. Homebrew's own bookkeeping (`INSTALL_RECEIPT.json` and
+/// top-level dotfiles such as `.brew/`) is skipped, matching `brew list <formula>`.
+///
+/// # Errors
+///
+/// Returns an error if the keg cannot be walked (missing directory, permission denied).
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::cellar;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let keg = cellar::cellar_path().join("jq").join("1.7.1");
+///     for file in cellar::keg_files(&keg)? {
+///         println!("{}", file.display());
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn keg_files(keg_path: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+
+    let walker = walkdir::WalkDir::new(keg_path)
+        .follow_links(false)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|e| !(e.depth() == 1 && e.file_name().to_string_lossy().starts_with('.')));
+
+    for entry in walker {
+        let entry = entry.with_context(|| format!("Failed to read keg: {}", keg_path.display()))?;
+        if entry.file_type().is_dir() {
+            continue;
+        }
+
+        let relative = entry
+            .path()
+            .strip_prefix(keg_path)
+            .with_context(|| format!("Path outside keg: {}", entry.path().display()))?;
+        if relative == Path::new("INSTALL_RECEIPT.json") {
+            continue;
+        }
+
+        files.push(relative.to_path_buf());
+    }
+
+    files.sort();
+    Ok(files)
+}
+
+/// Total size in bytes of the files in a keg.
+///
+/// Symlinks count as their own size rather than their target's, so links into other
+/// kegs aren't double-counted. Unlike [`keg_files`], the receipt and `.brew/` are
+/// included since they take up disk space too.
+///
+/// # Errors
+///
+/// Returns an error if the keg cannot be walked (missing directory, permission denied).
+pub fn keg_size(keg_path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in walkdir::WalkDir::new(keg_path).follow_links(false) {
+        let entry = entry.with_context(|| format!("Failed to read keg: {}", keg_path.display()))?;
+        if !entry.file_type().is_dir() {
+            size += entry
+                .metadata()
+                .with_context(|| format!("Failed to stat {}", entry.path().display()))?
+                .len();
+        }
+    }
+    Ok(size)
+}
+
+/// Environment settings for building against a keg-only formula.
+///
+/// Keg-only formulae aren't linked into the prefix, so compilers, `pkg-config`, and the
+/// shell won't find them on their own. Paths point at `opt/<name>`, which survives
+/// upgrades, and are only included for directories the keg actually has.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BuildEnvHints {
+    /// Formula name, used in the printed instructions
+    pub name: String,
+    /// `bin`/`sbin` directories to put first in `PATH`
+    pub path: Vec<PathBuf>,
+    /// `-L<opt>/lib`
+    pub ldflags: Option<String>,
+    /// `-I<opt>/include`
+    pub cppflags: Option<String>,
+    /// `<opt>/lib/pkgconfig`
+    pub pkg_config_path: Option<PathBuf>,
+}
+
+impl BuildEnvHints {
+    /// True when the keg has nothing to add to `PATH` or the build flags
+    pub fn is_empty(&self) -> bool {
+        self.path.is_empty()
+            && self.ldflags.is_none()
+            && self.cppflags.is_none()
+            && self.pkg_config_path.is_none()
+    }
+}
+
+impl std::fmt::Display for BuildEnvHints {
+    /// The same instructions `brew` prints in a keg-only formula's caveats
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut sections = Vec::new();
+        if !self.path.is_empty() {
+            let dirs: Vec<String> = self.path.iter().map(|p| p.display().to_string()).collect();
+            sections.push(format!(
+                "If you need to have {} first in your PATH, run:\n  export PATH=\"{}:$PATH\"",
+                self.name,
+                dirs.join(":")
+            ));
+        }
+        if self.ldflags.is_some() || self.cppflags.is_some() {
+            let mut section = format!("For compilers to find {} you may need to set:", self.name);
+            if let Some(ldflags) = &self.ldflags {
+                section.push_str(&format!("\n  export LDFLAGS=\"{}\"", ldflags));
+            }
+            if let Some(cppflags) = &self.cppflags {
+                section.push_str(&format!("\n  export CPPFLAGS=\"{}\"", cppflags));
+            }
+            sections.push(section);
+        }
+        if let Some(pkg_config_path) = &self.pkg_config_path {
+            sections.push(format!(
+                "For pkg-config to find {} you may need to set:\n  export PKG_CONFIG_PATH=\"{}\"",
+                self.name,
+                pkg_config_path.display()
+            ));
+        }
+        write!(f, "{}", sections.join("\n\n"))
+    }
+}
+
+/// Render the path interpolations in a formula's caveats or keg-only explanation.
+///
+/// Formulae write these as Ruby interpolations (`#{HOMEBREW_PREFIX}`, `#{prefix}`,
+/// `#{opt_prefix}`, `#{etc}`, ...), and the JSON API ships the prefix pre-rendered as
+/// `$HOMEBREW_PREFIX`. Both are replaced with paths under the detected prefix and the
+/// keg of `name` at `version`; anything unrecognized is left as written.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::cellar;
+///
+/// let caveats = cellar::render_caveats("Config: #{etc}/redis.conf", "redis", "7.2.4");
+/// println!("{}", caveats); // Config: /opt/homebrew/etc/redis.conf
+/// ```
+pub fn render_caveats(template: &str, name: &str, version: &str) -> String {
+    render_caveats_in(&detect_prefix(), template, name, version)
+}
+
+/// Render caveats against an explicit prefix.
+///
+/// Same as [`render_caveats`], but paths are under `prefix` instead of the detected
+/// Homebrew prefix.
+pub fn render_caveats_in(prefix: &Path, template: &str, name: &str, version: &str) -> String {
+    let keg = prefix.join("Cellar").join(name).join(version);
+    let opt = prefix.join("opt").join(name);
+
+    let lookup = |key: &str| -> Option<String> {
+        let path = match key {
+            "name" => return Some(name.to_string()),
+            "version" => return Some(version.to_string()),
+            "HOMEBREW_PREFIX" => prefix.to_path_buf(),
+            "HOMEBREW_CELLAR" => prefix.join("Cellar"),
+            "prefix" => keg.clone(),
+            "opt_prefix" => opt.clone(),
+            "etc" => prefix.join("etc"),
+            "var" => prefix.join("var"),
+            "pkgetc" => prefix.join("etc").join(name),
+            "pkgshare" => keg.join("share").join(name),
+            "opt_pkgshare" => opt.join("share").join(name),
+            _ => {
+                let (base, dir) = match key.strip_prefix("opt_") {
+                    Some(dir) => (&opt, dir),
+                    None => (&keg, key),
+                };
+                let dir = match dir {
+                    "bin" | "sbin" | "lib" | "include" | "share" | "libexec" => dir,
+                    "frameworks" => "Frameworks",
+                    "man" => "share/man",
+                    _ => return None,
+                };
+                base.join(dir)
+            }
+        };
+        Some(path.display().to_string())
+    };
+
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("#{") {
+        rendered.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after
+            .find('}')
+            .and_then(|end| Some((lookup(after[..end].trim())?, end)))
+        {
+            Some((value, end)) => {
+                rendered.push_str(&value);
+                rest = &after[end + 1..];
+            }
+            None => {
+                rendered.push_str("#{");
+                rest = after;
+            }
+        }
+    }
+    rendered.push_str(rest);
+
+    let prefix_str = prefix.display().to_string();
+    rendered
+        .replace("$HOMEBREW_CELLAR", &format!("{}/Cellar", prefix_str))
+        .replace("$HOMEBREW_PREFIX", &prefix_str)
+}
+
+/// Build environment hints for an installed keg (see [`BuildEnvHints`]).
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::cellar;
+///
+/// let hints = cellar::build_env_hints("openssl@3", "3.4.0");
+/// if !hints.is_empty() {
+///     println!("{}", hints);
+/// }
+/// ```
+pub fn build_env_hints(name: &str, version: &str) -> BuildEnvHints {
+    build_env_hints_in(&detect_prefix(), name, version)
+}
+
+/// Build environment hints for a keg under an explicit prefix.
+///
+/// Same as [`build_env_hints`], but looks in `prefix` instead of the detected
+/// Homebrew prefix.
+pub fn build_env_hints_in(prefix: &Path, name: &str, version: &str) -> BuildEnvHints {
+    let keg = prefix.join("Cellar").join(name).join(version);
+    let opt = prefix.join("opt").join(name);
+
+    BuildEnvHints {
+        name: name.to_string(),
+        path: ["bin", "sbin"]
+            .iter()
+            .filter(|dir| keg.join(dir).is_dir())
+            .map(|dir| opt.join(dir))
+            .collect(),
+        ldflags: keg
+            .join("lib")
+            .is_dir()
+            .then(|| format!("-L{}", opt.join("lib").display())),
+        cppflags: keg
+            .join("include")
+            .is_dir()
+            .then(|| format!("-I{}", opt.join("include").display())),
+        pkg_config_path: keg
+            .join("lib/pkgconfig")
+            .is_dir()
+            .then(|| opt.join("lib/pkgconfig")),
+    }
+}
+
 /// Compare two version strings semantically
 pub fn compare_versions(a: &str, b: &str) -> std::cmp::Ordering {
     // Parse as semantic version numbers
@@ -465,4 +972,248 @@ mod tests {
         let cellar = cellar_path();
         assert!(cellar.ends_with("Cellar"));
     }
+
+    #[test]
+    fn test_keg_only_from_formula_file() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let keg = temp.path().join("openssl@3").join("3.4.0");
+        let pkg = InstalledPackage::from_path("openssl@3".into(), "3.4.0".into(), keg.clone());
+        assert_eq!(pkg.keg_only(), None);
+
+        fs::create_dir_all(keg.join(".brew")).unwrap();
+        fs::write(
+            keg.join(".brew/openssl@3.rb"),
+            "class OpensslAT3 < Formula\n  keg_only :shadowed_by_macos\nend\n",
+        )
+        .unwrap();
+        assert_eq!(pkg.keg_only(), Some(true));
+
+        fs::write(
+            keg.join(".brew/openssl@3.rb"),
+            "class OpensslAT3 < Formula\nend\n",
+        )
+        .unwrap();
+        assert_eq!(pkg.keg_only(), Some(false));
+    }
+
+    #[test]
+    fn test_list_current_prefers_highest_version_over_mtime() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path();
+        for version in ["1.6", "1.7.1"] {
+            fs::create_dir_all(prefix.join("Cellar/jq").join(version).join("bin")).unwrap();
+        }
+        // The old version was reinstalled most recently
+        fs::File::open(prefix.join("Cellar/jq/1.7.1"))
+            .unwrap()
+            .set_modified(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+        fs::File::open(prefix.join("Cellar/jq/1.6"))
+            .unwrap()
+            .set_modified(std::time::SystemTime::now())
+            .unwrap();
+        fs::create_dir_all(prefix.join("Cellar/ripgrep/14.1.0/bin")).unwrap();
+
+        let current = list_current_in(prefix).unwrap();
+        let versions: Vec<_> = current
+            .iter()
+            .map(|pkg| (pkg.name.as_str(), pkg.version.as_str()))
+            .collect();
+        assert_eq!(versions, [("jq", "1.7.1"), ("ripgrep", "14.1.0")]);
+    }
+
+    #[test]
+    fn test_list_current_uses_linked_version_for_head_builds() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path();
+        for version in ["HEAD-aaa111", "HEAD-fff999"] {
+            fs::create_dir_all(prefix.join("Cellar/neovim").join(version).join("bin")).unwrap();
+        }
+        fs::create_dir_all(prefix.join("opt")).unwrap();
+        std::os::unix::fs::symlink("../Cellar/neovim/HEAD-aaa111", prefix.join("opt/neovim"))
+            .unwrap();
+
+        let current = list_current_in(prefix).unwrap();
+        assert_eq!(current.len(), 1);
+        assert_eq!(current[0].version, "HEAD-aaa111");
+    }
+
+    #[test]
+    fn test_keg_files_skips_metadata() {
+        let keg = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(keg.path().join("bin")).unwrap();
+        fs::create_dir_all(keg.path().join("share/man/man1")).unwrap();
+        fs::create_dir_all(keg.path().join(".brew")).unwrap();
+        fs::write(keg.path().join("bin/jq"), "").unwrap();
+        fs::write(keg.path().join("share/man/man1/jq.1"), "").unwrap();
+        fs::write(keg.path().join(".brew/jq.rb"), "").unwrap();
+        fs::write(keg.path().join("INSTALL_RECEIPT.json"), "{}").unwrap();
+
+        let files = keg_files(keg.path()).unwrap();
+        assert_eq!(
+            files,
+            vec![
+                PathBuf::from("bin/jq"),
+                PathBuf::from("share/man/man1/jq.1")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_keg_size_counts_files_not_link_targets() {
+        let keg = tempfile::TempDir::new().unwrap();
+        fs::create_dir_all(keg.path().join("bin")).unwrap();
+        fs::write(keg.path().join("bin/jq"), vec![0u8; 1000]).unwrap();
+        fs::write(keg.path().join("INSTALL_RECEIPT.json"), "{}").unwrap();
+        std::os::unix::fs::symlink("jq", keg.path().join("bin/jq-link")).unwrap();
+
+        // 1000 + 2 + the link's own length ("jq")
+        assert_eq!(keg_size(keg.path()).unwrap(), 1004);
+    }
+
+    #[test]
+    fn test_installed_versions_skip_junk_entries() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let cellar = temp.path();
+        let jq = cellar.join("jq");
+
+        // Real kegs: one with a receipt, one with only bottle contents
+        fs::create_dir_all(jq.join("1.7.1")).unwrap();
+        fs::write(jq.join("1.7.1/INSTALL_RECEIPT.json"), "{}").unwrap();
+        fs::create_dir_all(jq.join("1.6_1/bin")).unwrap();
+
+        // Junk: Finder metadata, hidden dirs, an empty partial dir, a non-version name
+        fs::write(jq.join(".DS_Store"), "").unwrap();
+        fs::create_dir_all(jq.join(".metadata")).unwrap();
+        fs::create_dir_all(jq.join("1.8.0")).unwrap();
+        fs::create_dir_all(jq.join("backup copy/bin")).unwrap();
+        fs::write(cellar.join(".DS_Store"), "").unwrap();
+        fs::write(cellar.join("notes.txt"), "").unwrap();
+
+        let versions: Vec<_> = get_installed_versions_in(cellar, "jq")
+            .unwrap()
+            .into_iter()
+            .map(|p| p.version)
+            .collect();
+        assert_eq!(versions, vec!["1.7.1", "1.6_1"]);
+
+        let mut listed: Vec<_> = list_installed_in(cellar)
+            .unwrap()
+            .into_iter()
+            .map(|p| format!("{}/{}", p.name, p.version))
+            .collect();
+        listed.sort();
+        assert_eq!(listed, vec!["jq/1.6_1", "jq/1.7.1"]);
+    }
+
+    #[test]
+    fn test_receipt_accessors() {
+        let keg = tempfile::TempDir::new().unwrap();
+        let pkg =
+            InstalledPackage::from_path("wget".into(), "1.25.0".into(), keg.path().to_path_buf());
+
+        // Written after construction: the receipt is only read on first access. Marked
+        // as a dependency the user later asked for directly
+        let mut receipt: serde_json::Value =
+            serde_json::from_str(crate::test_support::BREW_RECEIPT).unwrap();
+        receipt["installed_as_dependency"] = true.into();
+        fs::write(
+            keg.path().join("INSTALL_RECEIPT.json"),
+            serde_json::to_string(&receipt).unwrap(),
+        )
+        .unwrap();
+
+        assert_eq!(pkg.tap(), Some("homebrew/core"));
+        assert!(pkg.poured_from_bottle());
+        assert_eq!(
+            pkg.installed_time().map(|t| t.to_rfc3339()),
+            Some("2024-11-13T01:40:18+00:00".to_string())
+        );
+        // Such a keg keeps both flags
+        assert!(pkg.installed_as_dependency());
+        assert!(pkg.installed_on_request());
+        assert_eq!(pkg.runtime_dependencies()[0].full_name, "libunistring");
+
+        let missing =
+            InstalledPackage::from_path("fd".into(), "10.2.0".into(), keg.path().join("x"));
+        assert!(missing.receipt().is_none());
+        assert_eq!(missing.tap(), None);
+        assert!(!missing.poured_from_bottle());
+        assert_eq!(missing.installed_time(), None);
+    }
+
+    #[test]
+    fn test_build_env_hints_follow_keg_dirs() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path();
+        let keg = prefix.join("Cellar/openssl@3/3.4.0");
+        fs::create_dir_all(keg.join("bin")).unwrap();
+        fs::create_dir_all(keg.join("include/openssl")).unwrap();
+        fs::create_dir_all(keg.join("lib/pkgconfig")).unwrap();
+
+        let hints = build_env_hints_in(prefix, "openssl@3", "3.4.0");
+        let opt = prefix.join("opt/openssl@3");
+        assert_eq!(hints.path, vec![opt.join("bin")]);
+        assert_eq!(
+            hints.ldflags,
+            Some(format!("-L{}", opt.join("lib").display()))
+        );
+        assert_eq!(
+            hints.cppflags,
+            Some(format!("-I{}", opt.join("include").display()))
+        );
+        assert_eq!(hints.pkg_config_path, Some(opt.join("lib/pkgconfig")));
+
+        let text = hints.to_string();
+        assert!(text.contains(&format!(
+            "export LDFLAGS=\"-L{}\"",
+            opt.join("lib").display()
+        )));
+        assert!(text.contains("For pkg-config to find openssl@3"));
+
+        // A keg with nothing to build against
+        fs::create_dir_all(prefix.join("Cellar/ca-certificates/2024/share")).unwrap();
+        let hints = build_env_hints_in(prefix, "ca-certificates", "2024");
+        assert!(hints.is_empty());
+        assert_eq!(hints.to_string(), "");
+    }
+
+    #[test]
+    fn test_version_name_grammar() {
+        for valid in ["1.7.1", "3.12.0_1", "2024a", "HEAD-abc1234", "1.0.0-rc1"] {
+            assert!(is_version_name(valid), "{valid} should be a version");
+        }
+        for invalid in ["", ".DS_Store", "backup copy", "latest", "-1.0"] {
+            assert!(
+                !is_version_name(invalid),
+                "{invalid} should not be a version"
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_caveats_substitutes_paths() {
+        let prefix = Path::new("/opt/homebrew");
+        let render = |template: &str| render_caveats_in(prefix, template, "redis", "7.2.4");
+
+        assert_eq!(
+            render("#{HOMEBREW_PREFIX}/bin/redis-server #{etc}/redis.conf"),
+            "/opt/homebrew/bin/redis-server /opt/homebrew/etc/redis.conf"
+        );
+        assert_eq!(
+            render("#{prefix}/lib and #{opt_prefix}/lib"),
+            "/opt/homebrew/Cellar/redis/7.2.4/lib and /opt/homebrew/opt/redis/lib"
+        );
+        assert_eq!(
+            render("#{opt_bin}, #{pkgshare}, #{var}/db"),
+            "/opt/homebrew/opt/redis/bin, /opt/homebrew/Cellar/redis/7.2.4/share/redis, /opt/homebrew/var/db"
+        );
+        assert_eq!(
+            render("$HOMEBREW_PREFIX/etc and $HOMEBREW_CELLAR"),
+            "/opt/homebrew/etc and /opt/homebrew/Cellar"
+        );
+        // Unknown or unterminated interpolations are kept as written
+        assert_eq!(render("#{unknown} #{name}"), "#{unknown} redis");
+        assert_eq!(render("open #{etc"), "open #{etc");
+    }
 }
@@ -0,0 +1,285 @@
+//! Removal of old formula and cask versions.
+//!
+//! Upgrades leave the previous keg in the Cellar (and the previous version in the
+//! Caskroom) so a broken upgrade can be rolled back. Cleanup removes those leftovers:
+//! - **Formulae**: the newest version is kept, plus the linked one if it's older
+//! - **Casks**: the installed version (the newest one, as `bru list --cask` reports it)
+//!   is kept
+//!
+//! Results are returned as a [`CleanupResult`] so callers can report them however they
+//! like; nothing here prints.
+
+use crate::cask;
+use crate::cellar::{self, InstalledPackage};
+use crate::error::Result;
+use crate::symlink;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A formula or cask version removed by cleanup
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CleanedVersion {
+    /// Formula name or cask token
+    pub name: String,
+    /// Removed version
+    pub version: String,
+    /// Removed directory (Cellar keg or Caskroom version)
+    pub path: PathBuf,
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Result of a cleanup operation
+///
+/// In a dry run, everything is reported as if it had been removed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CleanupResult {
+    /// Removed formula versions
+    pub removed: Vec<CleanedVersion>,
+    /// Removed cask versions
+    pub removed_casks: Vec<CleanedVersion>,
+    /// Bytes freed per formula or cask
+    pub freed_bytes: BTreeMap<String, u64>,
+    /// Total bytes freed
+    pub total_freed_bytes: u64,
+    /// Errors encountered (name/version, error message)
+    pub errors: Vec<(String, String)>,
+}
+
+impl CleanupResult {
+    fn record(&mut self, version: CleanedVersion, cask: bool) {
+        *self.freed_bytes.entry(version.name.clone()).or_default() += version.size;
+        self.total_freed_bytes += version.size;
+        if cask {
+            self.removed_casks.push(version);
+        } else {
+            self.removed.push(version);
+        }
+    }
+}
+
+/// Remove old formula and cask versions under `prefix`.
+///
+/// `names` limits cleanup to those formulae and casks; an empty slice cleans everything.
+/// With `dry_run`, nothing is removed but the result is the same.
+///
+/// # Errors
+///
+/// Returns an error if the Cellar or Caskroom can't be read, or if an old keg can't be
+/// unlinked. Failures to delete individual versions are collected in
+/// [`CleanupResult::errors`] instead.
+pub fn cleanup_in(prefix: &Path, names: &[String], dry_run: bool) -> Result<CleanupResult> {
+    let mut result = CleanupResult::default();
+    cleanup_kegs(prefix, names, dry_run, &mut result)?;
+    cleanup_cask_versions(prefix, names, dry_run, &mut result)?;
+    Ok(result)
+}
+
+/// Remove old cask versions under `prefix`, leaving the Cellar untouched.
+///
+/// Same as [`cleanup_in`] otherwise.
+pub fn cleanup_casks_in(prefix: &Path, names: &[String], dry_run: bool) -> Result<CleanupResult> {
+    let mut result = CleanupResult::default();
+    cleanup_cask_versions(prefix, names, dry_run, &mut result)?;
+    Ok(result)
+}
+
+fn cleanup_kegs(
+    prefix: &Path,
+    names: &[String],
+    dry_run: bool,
+    result: &mut CleanupResult,
+) -> Result<()> {
+    let cellar = prefix.join("Cellar");
+
+    let mut by_formula: BTreeMap<String, Vec<InstalledPackage>> = BTreeMap::new();
+    for pkg in cellar::list_installed_in(&cellar)? {
+        if names.is_empty() || names.contains(&pkg.name) {
+            by_formula.entry(pkg.name.clone()).or_default().push(pkg);
+        }
+    }
+
+    let mut stale = Vec::new();
+    for (formula_name, mut versions) in by_formula {
+        if versions.len() <= 1 {
+            continue;
+        }
+
+        // Newest first; the linked version stays even when a newer one is installed
+        versions.sort_by(|a, b| cellar::compare_versions(&b.version, &a.version));
+        let linked = symlink::get_linked_version_in(prefix, &formula_name)
+            .ok()
+            .flatten();
+
+        for pkg in &versions[1..] {
+            if linked.as_deref() == Some(pkg.version.as_str()) {
+                continue;
+            }
+            let path = cellar.join(&formula_name).join(&pkg.version);
+            stale.push(CleanedVersion {
+                name: formula_name.clone(),
+                version: pkg.version.clone(),
+                size: cellar::keg_size(&path)?,
+                path,
+            });
+        }
+    }
+
+    if dry_run {
+        for keg in stale {
+            result.record(keg, false);
+        }
+        return Ok(());
+    }
+
+    // Unlinking touches shared directories, so only the deletions run in parallel
+    for keg in &stale {
+        symlink::unlink_formula_in(prefix, &keg.name, &keg.version)?;
+    }
+    let outcomes: Vec<_> = std::thread::scope(|scope| {
+        let handles: Vec<_> = stale
+            .iter()
+            .map(|keg| scope.spawn(|| fs::remove_dir_all(&keg.path)))
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("keg removal thread panicked"))
+            .collect()
+    });
+
+    for (keg, outcome) in stale.into_iter().zip(outcomes) {
+        match outcome {
+            Ok(()) => result.record(keg, false),
+            Err(e) => result
+                .errors
+                .push((format!("{}/{}", keg.name, keg.version), e.to_string())),
+        }
+    }
+    Ok(())
+}
+
+fn cleanup_cask_versions(
+    prefix: &Path,
+    names: &[String],
+    dry_run: bool,
+    result: &mut CleanupResult,
+) -> Result<()> {
+    let caskroom = prefix.join("Caskroom");
+    if !caskroom.is_dir() {
+        return Ok(());
+    }
+
+    let mut tokens: Vec<String> = fs::read_dir(&caskroom)?
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .map(|e| e.file_name().to_string_lossy().into_owned())
+        .filter(|token| !token.starts_with('.'))
+        .filter(|token| names.is_empty() || names.contains(token))
+        .collect();
+    tokens.sort();
+
+    for token in tokens {
+        let versions: Vec<_> = fs::read_dir(caskroom.join(&token))?
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().is_dir() && !e.file_name().to_string_lossy().starts_with('.'))
+            .collect();
+        if versions.len() <= 1 {
+            continue;
+        }
+
+        // Keep the version the cask counts as installed everywhere else
+        let Some(installed) = cask::installed_cask_version_in(&caskroom, &token) else {
+            continue;
+        };
+        for old in versions
+            .iter()
+            .filter(|e| e.file_name() != installed.as_str())
+        {
+            let path = old.path();
+            let version = CleanedVersion {
+                name: token.clone(),
+                version: old.file_name().to_string_lossy().into_owned(),
+                size: cellar::keg_size(&path)?,
+                path,
+            };
+            if !dry_run && let Err(e) = fs::remove_dir_all(&version.path) {
+                result.errors.push((
+                    format!("{}/{}", version.name, version.version),
+                    e.to_string(),
+                ));
+                continue;
+            }
+            result.record(version, true);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keg(prefix: &Path, name: &str, version: &str, contents: &[u8]) {
+        let bin = prefix.join("Cellar").join(name).join(version).join("bin");
+        fs::create_dir_all(&bin).unwrap();
+        fs::write(bin.join(name), contents).unwrap();
+    }
+
+    #[test]
+    fn test_cleanup_keeps_newest_and_linked_versions() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path();
+        keg(prefix, "jq", "1.6", b"old");
+        keg(prefix, "jq", "1.7", b"linked");
+        keg(prefix, "jq", "1.7.1", b"newest");
+        keg(prefix, "ripgrep", "14.1.0", b"only");
+        fs::create_dir_all(prefix.join("opt")).unwrap();
+        std::os::unix::fs::symlink("../Cellar/jq/1.7", prefix.join("opt/jq")).unwrap();
+
+        let result = cleanup_in(prefix, &[], true).unwrap();
+        let removed: Vec<_> = result
+            .removed
+            .iter()
+            .map(|v| format!("{}/{}", v.name, v.version))
+            .collect();
+        assert_eq!(removed, ["jq/1.6"]);
+        assert_eq!(result.freed_bytes["jq"], 3);
+        assert_eq!(result.total_freed_bytes, 3);
+        assert!(
+            prefix.join("Cellar/jq/1.6").exists(),
+            "dry run removed a keg"
+        );
+
+        let result = cleanup_in(prefix, &[], false).unwrap();
+        assert_eq!(result.removed.len(), 1);
+        assert!(result.errors.is_empty());
+        assert!(!prefix.join("Cellar/jq/1.6").exists());
+        assert!(prefix.join("Cellar/jq/1.7").exists());
+        assert!(prefix.join("Cellar/jq/1.7.1").exists());
+    }
+
+    #[test]
+    fn test_cleanup_casks_only_touches_named_casks() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path();
+        keg(prefix, "jq", "1.6", b"old");
+        keg(prefix, "jq", "1.7.1", b"newest");
+        for (token, version) in [("firefox", "120.0"), ("zed", "0.1"), ("zed", "0.2")] {
+            fs::create_dir_all(prefix.join("Caskroom").join(token).join(version)).unwrap();
+        }
+        // The newest version stays even when an older one was modified later
+        fs::File::open(prefix.join("Caskroom/zed/0.2"))
+            .unwrap()
+            .set_modified(std::time::SystemTime::UNIX_EPOCH)
+            .unwrap();
+
+        let result = cleanup_casks_in(prefix, &["zed".to_string()], false).unwrap();
+        assert!(result.removed.is_empty());
+        assert_eq!(result.removed_casks.len(), 1);
+        assert_eq!(result.removed_casks[0].version, "0.1");
+        assert!(prefix.join("Caskroom/zed/0.2").exists());
+        assert!(prefix.join("Cellar/jq/1.6").exists());
+    }
+}
@@ -4,12 +4,16 @@
 /// CLICOLOR conventions for disabling terminal colors.
 ///
 /// **Environment Variables**:
-/// - `NO_COLOR`: If set (to any value), disable colors
+/// - `NO_COLOR`: If set to a non-empty value, disable colors
 /// - `CLICOLOR`: If set to 0, disable colors
 /// - `CLICOLOR_FORCE`: If set to non-zero, force colors even when not a TTY
 ///
 /// **TTY Detection**: Colors are automatically disabled if stdout is not a terminal,
 /// unless CLICOLOR_FORCE is set.
+///
+/// The decision is applied to both `colored` (all `.green()`/`.cyan()` text) and
+/// `console` (indicatif spinner and progress bar styles), so nothing else needs to
+/// check the environment.
 use colored::control;
 
 /// Initialize color support by checking environment variables and TTY status
@@ -17,28 +21,69 @@ use colored::control;
 /// This function implements the NO_COLOR standard and CLICOLOR conventions.
 /// Call this early in main() to configure color output for the entire program.
 pub fn init_colors() {
+    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    let enabled = colors_enabled(|key| std::env::var(key).ok(), is_tty);
+
+    control::set_override(enabled);
+    // console decides per stream and ignores NO_COLOR, so only ever turn it off
+    // (or on when forced); otherwise keep its own stderr TTY check for spinners
+    if !enabled {
+        console::set_colors_enabled(false);
+        console::set_colors_enabled_stderr(false);
+    } else if !is_tty {
+        console::set_colors_enabled(true);
+        console::set_colors_enabled_stderr(true);
+    }
+}
+
+/// Whether output should be colored, given an environment lookup and TTY status
+fn colors_enabled(var: impl Fn(&str) -> Option<String>, is_tty: bool) -> bool {
     // NO_COLOR takes precedence over everything (https://no-color.org/)
-    if std::env::var("NO_COLOR").is_ok() {
-        control::set_override(false);
-        return;
+    if var("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+        return false;
     }
 
     // CLICOLOR_FORCE overrides both CLICOLOR and TTY detection
-    if std::env::var("CLICOLOR_FORCE")
-        .map(|v| v != "0")
-        .unwrap_or(false)
-    {
-        control::set_override(true);
-        return;
+    if var("CLICOLOR_FORCE").is_some_and(|v| v != "0") {
+        return true;
     }
 
     // CLICOLOR=0 disables colors
-    if std::env::var("CLICOLOR").map(|v| v == "0").unwrap_or(false) {
-        control::set_override(false);
-        return;
+    if var("CLICOLOR").is_some_and(|v| v == "0") {
+        return false;
     }
 
     // Default: use colors only if stdout is a TTY
-    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
-    control::set_override(is_tty);
+    is_tty
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn env(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |key| vars.get(key).cloned()
+    }
+
+    #[test]
+    fn test_colors_enabled() {
+        assert!(colors_enabled(env(&[]), true));
+        assert!(!colors_enabled(env(&[]), false));
+
+        // NO_COLOR beats CLICOLOR_FORCE, but an empty NO_COLOR is ignored
+        assert!(!colors_enabled(
+            env(&[("NO_COLOR", "1"), ("CLICOLOR_FORCE", "1")]),
+            true
+        ));
+        assert!(colors_enabled(env(&[("NO_COLOR", "")]), true));
+
+        assert!(colors_enabled(env(&[("CLICOLOR_FORCE", "1")]), false));
+        assert!(!colors_enabled(env(&[("CLICOLOR_FORCE", "0")]), false));
+        assert!(!colors_enabled(env(&[("CLICOLOR", "0")]), true));
+    }
 }
@@ -7,6 +7,7 @@ use crate::api::BrewApi;
 use crate::cellar;
 use crate::error::Result;
 use colored::Colorize;
+use std::collections::HashMap;
 
 use super::install::install;
 
@@ -19,8 +20,9 @@ use super::install::install;
 /// # Arguments
 /// * `api` - The Homebrew API client
 /// * `dump` - If true, generate a Brewfile from installed packages
+/// * `describe` - With `dump`, append each entry's description as a trailing comment
 /// * `file` - The Brewfile path (defaults to "Brewfile")
-pub async fn bundle(api: &BrewApi, dump: bool, file: Option<&str>) -> Result<()> {
+pub async fn bundle(api: &BrewApi, dump: bool, describe: bool, file: Option<&str>) -> Result<()> {
     let brewfile_path = file.unwrap_or("Brewfile");
 
     if dump {
@@ -47,19 +49,25 @@ pub async fn bundle(api: &BrewApi, dump: bool, file: Option<&str>) -> Result<()>
             .collect();
         formulae_names.sort();
 
-        for name in &formulae_names {
-            content.push_str(&format!("brew \"{}\"\n", name));
-        }
-
         // Get all installed casks
         let casks = crate::cask::list_installed_casks()?;
-        let mut cask_tokens: Vec<_> = casks.iter().map(|(token, _)| token).collect();
+        let mut cask_tokens: Vec<_> = casks.iter().map(|(token, _)| token.as_str()).collect();
         cask_tokens.sort();
 
+        let (formula_descs, cask_descs) = if describe {
+            descriptions(api, &formulae_names, &cask_tokens).await
+        } else {
+            Default::default()
+        };
+
+        for name in &formulae_names {
+            content.push_str(&brewfile_entry("brew", name, formula_descs.get(*name)));
+        }
+
         if !cask_tokens.is_empty() {
             content.push('\n');
             for token in &cask_tokens {
-                content.push_str(&format!("cask \"{}\"\n", token));
+                content.push_str(&brewfile_entry("cask", token, cask_descs.get(*token)));
             }
         }
 
@@ -91,7 +99,7 @@ pub async fn bundle(api: &BrewApi, dump: bool, file: Option<&str>) -> Result<()>
 
         // Parse Brewfile
         for line in content.lines() {
-            let line = line.trim();
+            let line = strip_trailing_comment(line).trim();
 
             // Skip comments and empty lines
             if line.is_empty() || line.starts_with('#') {
@@ -152,7 +160,14 @@ pub async fn bundle(api: &BrewApi, dump: bool, file: Option<&str>) -> Result<()>
         // Install formulae
         if !formulae_to_install.is_empty() {
             println!("Installing formulae...");
-            match install(api, &formulae_to_install, false, false, false).await {
+            // `brew "name"` lines are formulae even when a cask shares the name
+            match install(
+                api,
+                &formulae_to_install,
+                &super::install::InstallFlags::unattended(false),
+            )
+            .await
+            {
                 Ok(_) => {}
                 Err(e) => {
                     println!("{} Failed to install some formulae: {}", "".yellow(), e);
@@ -177,6 +192,70 @@ pub async fn bundle(api: &BrewApi, dump: bool, file: Option<&str>) -> Result<()>
     Ok(())
 }
 
+/// Fetch formula and cask descriptions for `bundle --dump --describe`
+///
+/// Formula descriptions come from the cached index when available; anything missing
+/// (and every cask) is fetched in parallel. Entries whose description can't be found
+/// are simply left out.
+async fn descriptions(
+    api: &BrewApi,
+    formulae: &[&str],
+    casks: &[&str],
+) -> (HashMap<String, String>, HashMap<String, String>) {
+    let mut formula_descs: HashMap<String, String> =
+        crate::cache::get_cached_formulae_brief_any_age()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|f| formulae.contains(&f.name.as_str()))
+            .filter_map(|f| f.desc.map(|desc| (f.name, desc)))
+            .collect();
+
+    let missing: Vec<&str> = formulae
+        .iter()
+        .copied()
+        .filter(|name| !formula_descs.contains_key(*name))
+        .collect();
+    let fetched = futures::future::join_all(missing.iter().map(|name| async move {
+        let desc = api.fetch_formula(name).await.ok()?.desc?;
+        Some((name.to_string(), desc))
+    }))
+    .await;
+    formula_descs.extend(fetched.into_iter().flatten());
+
+    let cask_descs = futures::future::join_all(casks.iter().map(|token| async move {
+        let desc = api.fetch_cask(token).await.ok()?.desc?;
+        Some((token.to_string(), desc))
+    }))
+    .await
+    .into_iter()
+    .flatten()
+    .collect();
+
+    (formula_descs, cask_descs)
+}
+
+/// Format a Brewfile line such as `brew "jq" # Command-line JSON processor`
+fn brewfile_entry(kind: &str, name: &str, desc: Option<&String>) -> String {
+    match desc.map(|desc| desc.split_whitespace().collect::<Vec<_>>().join(" ")) {
+        Some(desc) if !desc.is_empty() => format!("{} \"{}\" # {}\n", kind, name, desc),
+        _ => format!("{} \"{}\"\n", kind, name),
+    }
+}
+
+/// Drop a trailing `# comment` that isn't inside quotes
+fn strip_trailing_comment(line: &str) -> &str {
+    let mut quote = None;
+    for (i, c) in line.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(open)) if c == open => quote = None,
+            ('#', None) => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
 /// Extract string from quotes: "string" or 'string'
 fn extract_quoted_string(s: &str) -> Option<&str> {
     let s = s.trim();
@@ -186,3 +265,30 @@ fn extract_quoted_string(s: &str) -> Option<&str> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_described_entries_parse_back() {
+        let desc = "Lightweight and flexible\ncommand-line JSON processor".to_string();
+        let line = brewfile_entry("brew", "jq", Some(&desc));
+        assert_eq!(
+            line,
+            "brew \"jq\" # Lightweight and flexible command-line JSON processor\n"
+        );
+        assert_eq!(brewfile_entry("cask", "iterm2", None), "cask \"iterm2\"\n");
+
+        let entry = strip_trailing_comment(line.trim_end()).trim();
+        assert_eq!(
+            extract_quoted_string(entry.strip_prefix("brew").unwrap()),
+            Some("jq")
+        );
+        assert_eq!(
+            strip_trailing_comment("tap \"user/repo#1\" # pinned"),
+            "tap \"user/repo#1\" "
+        );
+        assert_eq!(strip_trailing_comment("# only a comment"), "");
+    }
+}
@@ -9,7 +9,6 @@
 //! - **upgrade_cask**: Upgrade outdated casks to their latest versions
 //! - **reinstall_cask**: Uninstall and reinstall casks (useful for fixing corrupted installations)
 //! - **uninstall_cask**: Remove casks and clean up their files
-//! - **cleanup_cask**: Remove old versions of casks to free up disk space
 //! - **uses_cask**: Show information about cask dependencies (typically none)
 //! - **abv_cask**: Display abbreviated cask information
 
@@ -22,8 +21,30 @@ use std::path::PathBuf;
 /// Install one or more casks from Homebrew
 ///
 /// Downloads cask metadata in parallel, then sequentially downloads and installs each cask.
-/// Supports .dmg, .pkg, and .zip file formats. Creates tracking metadata in Caskroom.
+/// Supports .dmg, .pkg, .zip and tar archive downloads. Apps go to [`crate::cask::appdir`], which
+/// is recorded along with the other tracking metadata in Caskroom.
 pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
+    install_cask_to(api, cask_names, &crate::cask::appdir()).await
+}
+
+/// Reinstall an installed cask where its apps already are: the recorded appdir,
+/// unless `--appdir` says otherwise
+async fn install_cask_again(api: &BrewApi, cask_name: &str) -> Result<()> {
+    let appdir = crate::cask::appdir_override()
+        .or_else(|| crate::cask::installed_cask_appdir_in(&crate::cask::caskroom_dir(), cask_name))
+        .unwrap_or_else(crate::cask::appdir);
+
+    // Uninstall the existing version, then install a fresh copy
+    uninstall_cask(&[cask_name.to_string()], false)?;
+    install_cask_to(api, &[cask_name.to_string()], &appdir).await
+}
+
+/// [`install_cask`] with apps going to `appdir`
+async fn install_cask_to(
+    api: &BrewApi,
+    cask_names: &[String],
+    appdir: &std::path::Path,
+) -> Result<()> {
     if cask_names.is_empty() {
         println!("{} No casks specified", "".red());
         return Ok(());
@@ -33,6 +54,7 @@ pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
         "Installing {} casks...",
         cask_names.len().to_string().bold()
     );
+    let appdir = appdir.to_path_buf();
 
     // Fetch all cask metadata in parallel first
     let fetch_futures: Vec<_> = cask_names
@@ -54,21 +76,154 @@ pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
 
     let metadata_results = futures::future::join_all(fetch_futures).await;
 
-    // Process each cask sequentially (downloads and installs must be sequential)
+    let mut to_install = Vec::new();
     for (cask_name, result) in metadata_results {
-        println!("Installing cask: {}", cask_name.cyan());
-
-        let cask = match result {
-            Ok(c) => c,
+        match result {
+            Ok(cask) => to_install.push(cask),
             Err(msg) => {
+                println!("Installing cask: {}", cask_name.cyan());
                 if msg.starts_with("Already installed") {
                     println!("  {} {}", "".green(), msg);
                 } else {
                     println!("  {} {}", "".red(), msg);
                 }
+            }
+        }
+    }
+
+    // Pull in casks required via `depends_on cask:` that aren't installed or requested
+    let needs_fetch = |token: &String, known: &[crate::api::Cask], pending: &[String]| {
+        !crate::cask::is_cask_installed(token)
+            && !known.iter().any(|c| &c.token == token)
+            && !pending.contains(token)
+    };
+    let mut pending: Vec<String> = Vec::new();
+    for cask in &to_install {
+        for dep in &cask.depends_on.cask {
+            if needs_fetch(dep, &to_install, &pending) {
+                pending.push(dep.clone());
+            }
+        }
+    }
+    while !pending.is_empty() {
+        let fetched =
+            futures::future::join_all(pending.iter().map(|name| api.fetch_cask(name))).await;
+        let fetched_names = std::mem::take(&mut pending);
+        for (name, result) in fetched_names.iter().zip(fetched) {
+            match result {
+                Ok(cask) => {
+                    for dep in &cask.depends_on.cask {
+                        if needs_fetch(dep, &to_install, &pending) && !fetched_names.contains(dep) {
+                            pending.push(dep.clone());
+                        }
+                    }
+                    to_install.push(cask);
+                }
+                Err(e) => println!(
+                    "{} Failed to fetch cask dependency {}: {}",
+                    "".red(),
+                    name.bold(),
+                    e
+                ),
+            }
+        }
+    }
+
+    // Dependencies come first: formulae, then casks in dependency order
+    let (formula_deps, ordered) = crate::api::cask_install_order(to_install);
+    let formula_installed =
+        |name: &str| crate::cellar::get_installed_versions(name).is_ok_and(|v| !v.is_empty());
+
+    let missing_formulae: Vec<String> = formula_deps
+        .into_iter()
+        .filter(|f| !formula_installed(f))
+        .collect();
+    if !missing_formulae.is_empty() {
+        println!(
+            "Installing formula dependencies: {}",
+            missing_formulae.join(", ").cyan()
+        );
+        if let Err(e) = super::install::install(
+            api,
+            &missing_formulae,
+            &super::install::InstallFlags::unattended(false),
+        )
+        .await
+        {
+            println!(
+                "  {} Failed to install formula dependencies: {}",
+                "".red(),
+                e
+            );
+        }
+    }
+
+    let macos_version = crate::platform::current_macos_version();
+
+    // Process each cask sequentially (downloads and installs must be sequential)
+    for cask in ordered {
+        let cask_name = cask.token.clone();
+        println!("Installing cask: {}", cask_name.cyan());
+
+        // Validate requirements before downloading anything
+        if let Some(current) = &macos_version
+            && !cask.depends_on.macos_satisfied(current)
+        {
+            let required: Vec<String> = cask
+                .depends_on
+                .macos
+                .iter()
+                .flatten()
+                .map(|(op, versions)| format!("{} {}", op, versions.join(", ")))
+                .collect();
+            println!(
+                "  {} Requires macOS {} (running {})",
+                "".red(),
+                required.join(" and "),
+                current
+            );
+            continue;
+        }
+
+        let missing: Vec<&str> = cask
+            .depends_on
+            .formula
+            .iter()
+            .filter(|f| !formula_installed(f))
+            .chain(
+                cask.depends_on
+                    .cask
+                    .iter()
+                    .filter(|c| !crate::cask::is_cask_installed(c)),
+            )
+            .map(String::as_str)
+            .collect();
+        if !missing.is_empty() {
+            println!(
+                "  {} Missing dependencies: {}",
+                "".red(),
+                missing.join(", ").bold()
+            );
+            continue;
+        }
+
+        if let Some(conflicts) = &cask.conflicts_with {
+            let installed: Vec<&str> = conflicts
+                .cask
+                .iter()
+                .filter(|c| crate::cask::is_cask_installed(c))
+                .chain(conflicts.formula.iter().filter(|f| formula_installed(f)))
+                .map(String::as_str)
+                .collect();
+            if !installed.is_empty() {
+                println!(
+                    "  {} Conflicts with installed: {}",
+                    "".red(),
+                    installed.join(", ").bold()
+                );
                 continue;
             }
-        };
+        }
 
         let version = cask
             .version
@@ -79,27 +234,57 @@ pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
             .as_ref()
             .ok_or_else(|| anyhow::anyhow!("No download URL"))?;
 
-        println!("  {}: {}", "Version".dimmed(), version.cyan());
+        match cask.version_and_build() {
+            Some((release, Some(build))) => println!(
+                "  {}: {} {}",
+                "Version".dimmed(),
+                release.cyan(),
+                format!("(build {})", build).dimmed()
+            ),
+            _ => println!("  {}: {}", "Version".dimmed(), version.cyan()),
+        }
         println!("  {}: {}", "URL".dimmed(), url.dimmed());
 
         // Extract app artifacts from cask metadata
         let apps = crate::cask::extract_app_artifacts(&cask.artifacts);
-        if apps.is_empty() {
+        let pkgs = crate::cask::extract_pkg_artifacts(&cask.artifacts);
+        let binaries = crate::cask::extract_binary_artifacts(&cask.artifacts);
+        if apps.is_empty() && pkgs.is_empty() && binaries.is_empty() {
             println!("  {} No app artifacts found", "".yellow());
             continue;
         }
+        let app_names: Vec<&str> = apps.iter().map(|app| app.installed_name()).collect();
 
-        println!("  {}: {}", "Apps".dimmed(), apps.join(", ").cyan());
+        if !app_names.is_empty() {
+            println!("  {}: {}", "Apps".dimmed(), app_names.join(", ").cyan());
+        }
+        if !binaries.is_empty() {
+            let names: Vec<&str> = binaries.iter().map(|b| b.installed_name()).collect();
+            println!("  {}: {}", "Binaries".dimmed(), names.join(", ").cyan());
+        }
 
-        // Download cask to cache directory
+        // Download cask to cache directory, verified against its sha256
+        let checksum = cask.checksum();
+        if checksum.is_none() {
+            println!(
+                "  {} No checksum ({}); skipping verification",
+                "".yellow(),
+                if version == "latest" {
+                    "version :latest"
+                } else {
+                    "sha256 :no_check"
+                }
+            );
+        }
         println!("  Downloading...");
-        let download_path = match crate::cask::download_cask(url, &cask_name).await {
-            Ok(p) => p,
-            Err(e) => {
-                println!("  {} Failed to download: {}", "".red(), e);
-                continue;
-            }
-        };
+        let download_path =
+            match crate::cask::download_cask(api.client(), url, &cask_name, checksum).await {
+                Ok(p) => p,
+                Err(e) => {
+                    println!("  {} Failed to download: {}", "".red(), e);
+                    continue;
+                }
+            };
 
         println!(
             "    {} Downloaded to {}",
@@ -107,57 +292,47 @@ pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
             download_path.display().to_string().dimmed()
         );
 
-        // Handle different file types - DMG, PKG, or ZIP
-        let filename = download_path
+        let cask_dir = crate::cask::cask_install_dir(&cask_name, version);
+
+        // Handle different file types - DMG, PKG, ZIP or tar archive, or a bare binary
+        let original_name = download_path
             .file_name()
             .ok_or_else(|| anyhow::anyhow!("Invalid download path: no filename"))?
             .to_string_lossy()
-            .to_lowercase();
+            .to_string();
+        let filename = original_name.to_lowercase();
 
+        // Apps and packages installed; binaries are counted once they're linked
+        let mut installed = 0;
         if filename.ends_with(".dmg") {
-            // Mount DMG and extract apps
+            // Mount DMG and extract apps; the mount is detached however this branch exits
             println!("  Mounting DMG...");
-            let mount_point = match crate::cask::mount_dmg(&download_path) {
-                Ok(p) => p,
+            let dmg = match crate::cask::mount_dmg(&download_path) {
+                Ok(dmg) => dmg,
                 Err(e) => {
                     println!("  {} Failed to mount: {}", "".red(), e);
                     continue;
                 }
             };
 
+            let volumes: Vec<String> = dmg
+                .volumes()
+                .iter()
+                .map(|volume| volume.display().to_string())
+                .collect();
             println!(
                 "    {} Mounted at {}",
                 "".green(),
-                mount_point.display().to_string().dimmed()
+                volumes.join(", ").dimmed()
             );
 
-            // Install each app from the mounted DMG
-            for app_name in &apps {
-                let app_path = mount_point.join(app_name);
-
-                if !app_path.exists() {
-                    println!("    {} App not found: {}", "".yellow(), app_name);
-                    continue;
-                }
-
-                println!("  Installing {}...", app_name.cyan());
-                match crate::cask::install_app(&app_path, app_name) {
-                    Ok(_) => {
-                        println!(
-                            "    └ {} Installed to /Applications/{}",
-                            "".green(),
-                            app_name.bold()
-                        );
-                    }
-                    Err(e) => {
-                        println!("    └ {} Failed to install: {}", "".red(), e);
-                    }
-                }
-            }
+            installed += install_apps(&apps, &appdir, |path| dmg.find(path));
+            installed += install_pkgs(&pkgs, |path| dmg.find(path));
+            stage_binaries(&binaries, &cask_dir, |path| dmg.find(path));
 
             // Unmount DMG after installation
             println!("  Unmounting DMG...");
-            if let Err(e) = crate::cask::unmount_dmg(&mount_point) {
+            if let Err(e) = dmg.unmount() {
                 println!("    {} Failed to unmount: {}", "".yellow(), e);
             }
         } else if filename.ends_with(".pkg") {
@@ -166,16 +341,17 @@ pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
             match crate::cask::install_pkg(&download_path) {
                 Ok(_) => {
                     println!("    └ {} Installed successfully", "".green());
+                    installed += 1;
                 }
                 Err(e) => {
                     println!("  {} Failed to install: {}", "".red(), e);
                     continue;
                 }
             }
-        } else if filename.ends_with(".zip") {
-            // Extract ZIP and install apps
-            println!("  Extracting ZIP...");
-            let extract_dir = match crate::cask::extract_zip(&download_path) {
+        } else if filename.ends_with(".zip") || crate::cask::is_tar_archive(&filename) {
+            // Extract the archive and install from it
+            println!("  Extracting {}...", original_name);
+            let extract_dir = match crate::cask::extract_archive(&download_path) {
                 Ok(dir) => {
                     println!(
                         "    └ {} Extracted to {}",
@@ -190,45 +366,62 @@ pub async fn install_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
                 }
             };
 
-            // Install apps from extracted directory
-            for app in &apps {
-                println!("  Installing {}...", app.cyan());
-                let app_path = extract_dir.join(app);
+            let find = |path: &str| Some(extract_dir.join(path)).filter(|found| found.exists());
+            installed += install_apps(&apps, &appdir, find);
+            installed += install_pkgs(&pkgs, find);
+            stage_binaries(&binaries, &cask_dir, find);
+        } else if binaries.iter().any(|binary| binary.path == original_name) {
+            // A bare executable: the download itself is the binary
+            stage_binaries(&binaries, &cask_dir, |path| {
+                (path == original_name).then(|| download_path.clone())
+            });
+        } else {
+            println!("  {} Unsupported file type: {}", "".yellow(), filename);
+            continue;
+        }
 
-                if !app_path.exists() {
-                    println!("    └ {} App not found in ZIP: {}", "".yellow(), app);
-                    continue;
+        // Link binaries into the prefix
+        let bin_dir = crate::cellar::detect_prefix().join("bin");
+        let mut binary_names = Vec::new();
+        for binary in &binaries {
+            let name = binary.installed_name();
+            let source = crate::cask::binary_source(binary, &cask_dir, &appdir);
+            match crate::cask::link_binary(&source, &bin_dir, name) {
+                Ok(link) => {
+                    println!(
+                        "  {} Linked {}",
+                        "".green(),
+                        link.display().to_string().bold()
+                    );
+                    binary_names.push(name);
                 }
+                Err(e) => println!("  {} Failed to link {}: {}", "".red(), name, e),
+            }
+        }
 
-                match crate::cask::install_app(&app_path, app) {
-                    Ok(target) => {
-                        println!(
-                            "    └ {} Installed to {}",
-                            "".green(),
-                            target.display().to_string().bold()
-                        );
-                    }
-                    Err(e) => {
-                        println!("  {} Failed to install: {}", "".red(), e);
-                        continue;
-                    }
-                }
+        // A cask that installed nothing isn't recorded, so it isn't listed as installed
+        if installed == 0 && binary_names.is_empty() {
+            println!("  {} Nothing was installed", "".red());
+            let _ = std::fs::remove_dir_all(&cask_dir);
+            if let Some(token_dir) = cask_dir.parent() {
+                let _ = std::fs::remove_dir(token_dir);
             }
-        } else {
-            println!("  {} Unsupported file type: {}", "".yellow(), filename);
             continue;
         }
 
         // Create Caskroom directory to track installation
-        let cask_dir = crate::cask::cask_install_dir(&cask_name, version);
         std::fs::create_dir_all(&cask_dir)?;
 
         // Write metadata file for future reference
         let metadata = serde_json::json!({
             "token": cask_name,
             "version": version,
-            "installed_apps": apps,
+            "installed_apps": app_names,
+            "installed_binaries": binary_names,
+            "appdir": appdir,
             "install_time": chrono::Utc::now().timestamp(),
+            "depends_on": cask.depends_on,
+            "artifacts": cask.artifacts,
         });
         let metadata_path = cask_dir.join(".metadata.json");
         std::fs::write(&metadata_path, serde_json::to_string_pretty(&metadata)?)?;
@@ -268,11 +461,7 @@ pub async fn reinstall_cask(api: &BrewApi, cask_names: &[String]) -> Result<()>
 
         println!("  Reinstalling {}...", cask_name.cyan());
 
-        // Uninstall the existing version
-        uninstall_cask(std::slice::from_ref(cask_name))?;
-
-        // Install fresh copy
-        install_cask(api, std::slice::from_ref(cask_name)).await?;
+        install_cask_again(api, cask_name).await?;
 
         println!("  {} Reinstalled {}", "".green(), cask_name.bold().green());
     }
@@ -281,139 +470,29 @@ pub async fn reinstall_cask(api: &BrewApi, cask_names: &[String]) -> Result<()>
     Ok(())
 }
 
-/// Clean up old versions of casks to free disk space
-///
-/// Removes all but the most recent version of each cask. Can be run in dry-run mode
-/// to preview what would be removed without actually deleting files.
-pub fn cleanup_cask(cask_names: &[String], dry_run: bool) -> Result<()> {
-    let caskroom = crate::cask::caskroom_dir();
-
-    if !caskroom.exists() {
-        println!("No casks installed");
-        return Ok(());
-    }
-
-    // Get list of casks to clean (all or specified)
-    let to_clean: Vec<String> = if cask_names.is_empty() {
-        // Clean all installed casks - collect directory names
-        std::fs::read_dir(&caskroom)?
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().is_dir())
-            .filter_map(|e| e.file_name().to_str().map(String::from))
-            .collect()
-    } else {
-        cask_names.to_vec()
-    };
-
-    let mut total_removed = 0;
-    let mut total_space_freed = 0u64;
-
-    if dry_run {
-        println!("Dry run - no files will be removed");
-    } else {
-        println!("Cleaning up old cask versions...");
-    }
-
-    for token in &to_clean {
-        let cask_dir = caskroom.join(token);
-
-        if !cask_dir.exists() {
-            if !cask_names.is_empty() {
-                println!("  {} {} not installed", "".yellow(), token.bold());
-            }
-            continue;
-        }
-
-        // Get all version directories for this cask
-        let mut versions: Vec<_> = std::fs::read_dir(&cask_dir)?
-            .filter_map(|e| e.ok())
-            .filter(|e| e.path().is_dir())
-            .collect();
-
-        // Need at least 2 versions to have something to clean
-        if versions.len() <= 1 {
-            continue;
-        }
-
-        // Sort by modification time (newest first)
-        versions.sort_by_key(|e| {
-            e.metadata()
-                .and_then(|m| m.modified())
-                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-        });
-        versions.reverse();
-
-        let latest = &versions[0];
-        let old_versions = &versions[1..];
-
-        // Remove all old versions
-        for old in old_versions {
-            let version_path = old.path();
-            let version_name = old.file_name();
-
-            // Calculate directory size for reporting
-            let size = calculate_dir_size(&version_path)?;
-            total_space_freed += size;
-
-            if dry_run {
-                println!(
-                    "  Would remove {} {} ({})",
-                    token.cyan(),
-                    version_name.to_string_lossy().dimmed(),
-                    format_size(size).dimmed()
-                );
-            } else {
-                println!(
-                    "  Removing {} {} ({})",
-                    token.cyan(),
-                    version_name.to_string_lossy().dimmed(),
-                    format_size(size).dimmed()
-                );
-
-                // Remove the old version directory
-                if version_path.exists() {
-                    std::fs::remove_dir_all(&version_path)?;
-                }
-            }
-
-            total_removed += 1;
-        }
-
-        println!(
-            "    {} Keeping {} {}",
-            "".green(),
-            token.bold(),
-            latest.file_name().to_string_lossy().dimmed()
-        );
-    }
-
-    // Print summary
-    if total_removed == 0 {
-        println!("{} No old cask versions to remove", "".green());
-    } else if dry_run {
-        println!(
-            "{} Would remove {} old cask versions ({})",
-            "".dimmed(),
-            total_removed.to_string().bold(),
-            format_size(total_space_freed).bold()
-        );
-    } else {
-        println!(
-            "{} Removed {} old cask versions, freed {}",
-            "".green().bold(),
-            total_removed.to_string().bold(),
-            format_size(total_space_freed).bold()
-        );
-    }
-
-    Ok(())
-}
-
 /// Upgrade outdated casks to their latest versions
 ///
 /// If no casks are specified, checks all installed casks for updates and upgrades
-/// any that are outdated. Otherwise upgrades only the specified casks.
+/// any that are outdated. Otherwise upgrades only the specified casks. Pinned casks
+/// are skipped either way.
 pub async fn upgrade_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
+    // Pinned casks (`bru pin --cask`) stay at their installed version
+    let pinned = super::utils::read_pinned(true)?;
+    let hold_pinned = |casks: Vec<String>| -> Vec<String> {
+        let (held, rest): (Vec<_>, Vec<_>) =
+            casks.into_iter().partition(|token| pinned.contains(token));
+        if !held.is_empty() {
+            println!(
+                "{} Not upgrading {} pinned cask{}: {}",
+                "".yellow(),
+                held.len(),
+                if held.len() == 1 { "" } else { "s" },
+                held.join(", ").bold()
+            );
+        }
+        rest
+    };
+
     // Determine which casks to upgrade
     let to_upgrade = if cask_names.is_empty() {
         // Upgrade all outdated casks - check in parallel
@@ -457,7 +536,7 @@ pub async fn upgrade_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
         let results = futures::future::join_all(fetch_futures).await;
         spinner.finish_and_clear();
 
-        let outdated: Vec<_> = results.into_iter().flatten().collect();
+        let outdated = hold_pinned(results.into_iter().flatten().collect());
 
         if outdated.is_empty() {
             println!("{} All casks are up to date", "".green());
@@ -471,20 +550,20 @@ pub async fn upgrade_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
         );
         outdated
     } else {
-        cask_names.to_vec()
+        hold_pinned(cask_names.to_vec())
     };
 
+    if to_upgrade.is_empty() {
+        return Ok(());
+    }
+
     println!("Upgrading {} casks...", to_upgrade.len());
 
     // Upgrade each cask sequentially
     for cask_name in &to_upgrade {
         println!("  Upgrading {}...", cask_name.cyan());
 
-        // First uninstall the old version
-        uninstall_cask(std::slice::from_ref(cask_name))?;
-
-        // Then install the new version
-        install_cask(api, std::slice::from_ref(cask_name)).await?;
+        install_cask_again(api, cask_name).await?;
 
         println!("  {} Upgraded {}", "".green(), cask_name.bold().green());
     }
@@ -495,9 +574,12 @@ pub async fn upgrade_cask(api: &BrewApi, cask_names: &[String]) -> Result<()> {
 
 /// Uninstall one or more casks
 ///
-/// Removes the application from /Applications and cleans up tracking metadata
-/// from the Caskroom directory.
-pub fn uninstall_cask(cask_names: &[String]) -> Result<()> {
+/// Quits the cask's apps, removes them from their appdir along with the binaries it
+/// linked into the prefix, runs the cask's `uninstall` stanza (forgetting pkg receipts,
+/// deleting the files it lists) and cleans up tracking metadata from the Caskroom
+/// directory. With `zap`, also removes what the cask's `zap` stanza lists: settings,
+/// caches and other files the app leaves in the home directory.
+pub fn uninstall_cask(cask_names: &[String], zap: bool) -> Result<()> {
     if cask_names.is_empty() {
         println!("{} No casks specified", "".red());
         return Ok(());
@@ -520,22 +602,53 @@ pub fn uninstall_cask(cask_names: &[String]) -> Result<()> {
         let version = crate::cask::get_installed_cask_version(cask_name)
             .ok_or_else(|| anyhow::anyhow!("Could not determine version"))?;
 
+        // The cask definition recorded at install time says what to clean up
+        let artifacts =
+            crate::cask::installed_cask_artifacts_in(&crate::cask::caskroom_dir(), cask_name);
+        let directives = crate::cask::uninstall_directives(&artifacts, zap);
+        if zap
+            && !artifacts
+                .iter()
+                .any(|artifact| matches!(artifact, crate::api::Artifact::Zap(_)))
+        {
+            println!("  {} No zap stanza recorded for {}", "".yellow(), cask_name);
+        }
+
         // Read metadata to find installed apps
         let cask_dir = crate::cask::cask_install_dir(cask_name, &version);
         let metadata_path = cask_dir.join(".metadata.json");
+        let recorded_apps: Vec<String> = crate::cask::extract_app_artifacts(&artifacts)
+            .iter()
+            .map(|app| app.installed_name().to_string())
+            .collect();
 
-        let apps = if metadata_path.exists() {
+        let metadata: Option<serde_json::Value> = if metadata_path.exists() {
             let metadata_str = std::fs::read_to_string(&metadata_path)?;
-            let metadata: serde_json::Value = serde_json::from_str(&metadata_str)?;
-
-            if let Some(apps_array) = metadata.get("installed_apps").and_then(|v| v.as_array()) {
-                apps_array
+            Some(serde_json::from_str(&metadata_str)?)
+        } else {
+            None
+        };
+        let installed = |key: &str| -> Option<Vec<String>> {
+            let names = metadata.as_ref()?.get(key)?.as_array()?;
+            Some(
+                names
                     .iter()
                     .filter_map(|v| v.as_str().map(String::from))
-                    .collect()
-            } else {
-                Vec::new()
-            }
+                    .collect(),
+            )
+        };
+
+        // Apps are where the install put them, whatever the current --appdir
+        let appdir = metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get("appdir")?.as_str())
+            .map(PathBuf::from)
+            .unwrap_or_else(crate::cask::appdir);
+
+        let apps = if let Some(apps) = installed("installed_apps") {
+            apps
+        } else if metadata.is_some() || !recorded_apps.is_empty() {
+            recorded_apps
         } else {
             // Fallback: guess app name from cask name (capitalize first letter)
             let mut chars = cask_name.chars();
@@ -549,9 +662,23 @@ pub fn uninstall_cask(cask_names: &[String]) -> Result<()> {
             }
         };
 
-        // Remove apps from /Applications
+        // Stop the app and its background jobs before removing their files
+        for directives in &directives {
+            for bundle_id in &directives.quit {
+                if let Err(e) = crate::cask::quit_app(bundle_id) {
+                    println!("  {} Failed to quit {}: {}", "".yellow(), bundle_id, e);
+                }
+            }
+            for label in &directives.launchctl {
+                if let Err(e) = crate::cask::unload_launchctl(label) {
+                    println!("  {} {}", "".yellow(), e);
+                }
+            }
+        }
+
+        // Remove apps from the appdir
         for app_name in &apps {
-            let app_path = PathBuf::from("/Applications").join(app_name);
+            let app_path = appdir.join(app_name);
 
             if app_path.exists() {
                 println!("  Removing {}...", app_name.cyan());
@@ -573,6 +700,25 @@ pub fn uninstall_cask(cask_names: &[String]) -> Result<()> {
             }
         }
 
+        // Remove binary links; brew links the same names, so its records work too
+        let binaries = installed("installed_binaries").unwrap_or_else(|| {
+            crate::cask::extract_binary_artifacts(&artifacts)
+                .iter()
+                .map(|binary| binary.installed_name().to_string())
+                .collect()
+        });
+        let bin_dir = crate::cellar::detect_prefix().join("bin");
+        let owners = [crate::cask::caskroom_dir().join(cask_name), appdir.clone()];
+        for name in &binaries {
+            match crate::cask::unlink_binary(&bin_dir, name, &owners) {
+                Ok(true) => println!("  {} Unlinked {}", "".green(), name.bold()),
+                Ok(false) => {}
+                Err(e) => println!("  {} Failed to unlink {}: {}", "".red(), name, e),
+            }
+        }
+
+        remove_listed_files(&directives);
+
         // Remove Caskroom directory
         let caskroom_path = crate::cask::caskroom_dir().join(cask_name);
         if caskroom_path.exists() {
@@ -591,6 +737,139 @@ pub fn uninstall_cask(cask_names: &[String]) -> Result<()> {
     Ok(())
 }
 
+/// Copy the cask's apps out of its download, located with `find`, into `appdir`
+///
+/// Returns how many were installed.
+fn install_apps(
+    apps: &[&crate::api::ArtifactPath],
+    appdir: &std::path::Path,
+    find: impl Fn(&str) -> Option<PathBuf>,
+) -> usize {
+    let mut installed = 0;
+    for app in apps {
+        let app_name = app.installed_name();
+        let Some(app_path) = find(&app.path) else {
+            println!("    {} App not found: {}", "".yellow(), app.path);
+            continue;
+        };
+
+        println!("  Installing {}...", app_name.cyan());
+        match crate::cask::install_app(&app_path, appdir, app_name) {
+            Ok(target) => {
+                println!(
+                    "    └ {} Installed to {}",
+                    "".green(),
+                    target.display().to_string().bold()
+                );
+                installed += 1;
+            }
+            Err(e) => println!("    └ {} Failed to install: {}", "".red(), e),
+        }
+    }
+    installed
+}
+
+/// Copy the binaries that live in the download (rather than in an app), located with
+/// `find`, into the cask's Caskroom directory, where they outlast the mounted DMG or
+/// the cached extraction
+fn stage_binaries(
+    binaries: &[&crate::api::ArtifactPath],
+    cask_dir: &std::path::Path,
+    find: impl Fn(&str) -> Option<PathBuf>,
+) {
+    for binary in binaries {
+        if crate::cask::binary_in_app(binary) {
+            continue;
+        }
+        let staged = find(&binary.path)
+            .ok_or_else(|| anyhow::anyhow!("not found in the download"))
+            .and_then(|source| crate::cask::stage_binary(&source, binary, cask_dir));
+        if let Err(e) = staged {
+            println!("    {} Failed to stage {}: {}", "".red(), binary.path, e);
+        }
+    }
+}
+
+/// Run the installer packages a cask ships inside its download, located with `find`
+///
+/// Returns how many were installed.
+fn install_pkgs(
+    pkgs: &[&crate::api::ArtifactPath],
+    find: impl Fn(&str) -> Option<PathBuf>,
+) -> usize {
+    let mut installed = 0;
+    for pkg in pkgs {
+        let Some(pkg_path) = find(&pkg.path) else {
+            println!("    {} Package not found: {}", "".yellow(), pkg.path);
+            continue;
+        };
+        println!("  Installing {}...", pkg.installed_name().cyan());
+        match crate::cask::install_pkg(&pkg_path) {
+            Ok(()) => {
+                println!("    └ {} Installed successfully", "".green());
+                installed += 1;
+            }
+            Err(e) => println!("    └ {} Failed to install: {}", "".red(), e),
+        }
+    }
+    installed
+}
+
+/// Carry out the file removals of `uninstall`/`zap` directives
+///
+/// Paths that don't exist are skipped quietly: zap stanzas list every place an app
+/// might leave files, and most installs only create a few of them. Glob patterns are
+/// not expanded, so they only match a file literally named that way.
+fn remove_listed_files(directives: &[&crate::api::UninstallDirectives]) {
+    for directives in directives {
+        for package_id in &directives.pkgutil {
+            println!("  Forgetting package {}...", package_id.cyan());
+            if let Err(e) = crate::cask::forget_pkg(package_id) {
+                println!("    {} {}", "".yellow(), e);
+            }
+        }
+
+        let deleted = directives.delete.iter().map(|path| (path, false));
+        let trashed = directives.trash.iter().map(|path| (path, true));
+        for (path, trash) in deleted.chain(trashed) {
+            let expanded = crate::cask::expand_cask_path(path);
+            if crate::cask::is_undeletable(&expanded) {
+                println!("  {} Refusing to remove {}", "".yellow(), path.bold());
+                continue;
+            }
+            if expanded.symlink_metadata().is_err() {
+                continue;
+            }
+
+            let removed = if trash {
+                crate::cask::trash_path(&expanded)
+            } else if expanded.is_dir() {
+                std::fs::remove_dir_all(&expanded).map_err(Into::into)
+            } else {
+                std::fs::remove_file(&expanded).map_err(Into::into)
+            };
+            match removed {
+                Ok(()) => println!("  {} Removed {}", "".green(), path.dimmed()),
+                Err(e) => {
+                    println!("  {} Failed to remove {}: {}", "".red(), path, e);
+                    println!(
+                        "    Try: {}",
+                        format!("sudo rm -rf {:?}", expanded.display().to_string()).cyan()
+                    );
+                }
+            }
+        }
+
+        // Only empty directories go; anything still in them belongs to something else
+        for path in &directives.rmdir {
+            let expanded = crate::cask::expand_cask_path(path);
+            if !crate::cask::is_undeletable(&expanded) && std::fs::remove_dir(&expanded).is_ok() {
+                println!("  {} Removed {}", "".green(), path.dimmed());
+            }
+        }
+    }
+}
+
 /// Show what depends on a cask (typically nothing)
 ///
 /// Unlike formulae which can have dependents, casks are GUI applications that
@@ -638,49 +917,3 @@ pub async fn abv_cask(api: &BrewApi, cask_name: &str) -> anyhow::Result<()> {
 
     Ok(())
 }
-
-// Helper functions
-
-/// Calculate the total size of a directory recursively
-///
-/// Uses walkdir to traverse the directory tree and sum up all file sizes.
-/// Returns 0 if the directory doesn't exist.
-fn calculate_dir_size(path: &std::path::Path) -> Result<u64> {
-    let mut total = 0u64;
-
-    if !path.exists() {
-        return Ok(0);
-    }
-
-    // Use walkdir with limits to prevent resource exhaustion
-    for entry in walkdir::WalkDir::new(path).follow_links(false).max_open(64) {
-        let entry = entry.map_err(|e| anyhow::anyhow!("Failed to read directory: {}", e))?;
-        if entry.file_type().is_file() {
-            total += entry
-                .metadata()
-                .map_err(|e| anyhow::anyhow!("Failed to read metadata: {}", e))?
-                .len();
-        }
-    }
-
-    Ok(total)
-}
-
-/// Format byte size as human-readable string
-///
-/// Converts bytes to KB, MB, or GB as appropriate.
-fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} bytes", bytes)
-    }
-}
@@ -8,6 +8,7 @@ use crate::api::BrewApi;
 use crate::cellar;
 use crate::error::Result;
 use colored::Colorize;
+use rayon::prelude::*;
 
 /// Open a formula in the default editor
 ///
@@ -682,20 +683,160 @@ pub fn migrate(formula_name: &str, new_tap: Option<&str>) -> Result<()> {
     Ok(())
 }
 
+/// Libraries the Mach-O files of one keg load, as reported by `bru linkage`
+///
+/// Serialized as-is for `bru linkage --json`, so field names are part of the output
+/// format.
+#[derive(Debug, Default, serde::Serialize)]
+pub struct LinkageReport {
+    pub name: String,
+    pub version: String,
+    /// Mach-O binaries and libraries inspected
+    pub files: usize,
+    /// Libraries shipped with macOS (`/usr/lib`, `/System`)
+    pub system: Vec<String>,
+    /// Libraries found in the Homebrew prefix or another existing path
+    pub homebrew: Vec<String>,
+    /// `@rpath` references, which depend on the run paths of whatever loads them
+    pub variable: Vec<String>,
+    /// References that resolve to no file
+    pub missing: Vec<MissingLibrary>,
+}
+
+/// A library reference that resolves to no file
+#[derive(Debug, serde::Serialize)]
+pub struct MissingLibrary {
+    /// The install name as recorded in the file
+    pub library: String,
+    /// A file in the keg that loads it
+    pub file: std::path::PathBuf,
+}
+
+/// Where a library reference points
+#[derive(Debug, PartialEq, Eq)]
+enum LibraryKind {
+    System,
+    Homebrew,
+    Variable,
+    Missing,
+}
+
+impl LinkageReport {
+    /// Inspect every Mach-O file in `keg` with `otool -L`
+    fn inspect(name: &str, version: &str, keg: &std::path::Path, prefix: &std::path::Path) -> Self {
+        let mut report = Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            ..Self::default()
+        };
+        let mut seen = std::collections::HashSet::new();
+
+        for file in crate::relocate::mach_o_files(keg) {
+            let Ok(output) = std::process::Command::new("otool")
+                .arg("-L")
+                .arg(&file)
+                .output()
+            else {
+                continue;
+            };
+            report.files += 1;
+
+            for library in parse_otool_libraries(&String::from_utf8_lossy(&output.stdout)) {
+                if !seen.insert(library.clone()) {
+                    continue;
+                }
+                match classify_library(&library, &file, prefix) {
+                    LibraryKind::System => report.system.push(library),
+                    LibraryKind::Homebrew => report.homebrew.push(library),
+                    LibraryKind::Variable => report.variable.push(library),
+                    LibraryKind::Missing => report.missing.push(MissingLibrary {
+                        library,
+                        file: file.strip_prefix(keg).unwrap_or(&file).to_path_buf(),
+                    }),
+                }
+            }
+        }
+
+        report.system.sort();
+        report.homebrew.sort();
+        report.variable.sort();
+        report
+    }
+}
+
+/// The install names listed in `otool -L` output
+///
+/// Header lines (`file:` or `file (architecture arm64):` for universal binaries) are
+/// skipped, as is the version suffix of each entry.
+fn parse_otool_libraries(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.ends_with(':'))
+        .map(|line| line.split(" (").next().unwrap_or(line).to_string())
+        .collect()
+}
+
+/// Resolve a library reference made by `file`
+///
+/// System libraries live in the dyld shared cache rather than on disk, so they are
+/// trusted by path. `@loader_path` and `@executable_path` resolve against the
+/// file's own directory.
+fn classify_library(
+    library: &str,
+    file: &std::path::Path,
+    prefix: &std::path::Path,
+) -> LibraryKind {
+    if library.starts_with("/usr/lib/") || library.starts_with("/System/") {
+        return LibraryKind::System;
+    }
+
+    let resolved = if let Some(rest) = library
+        .strip_prefix("@loader_path/")
+        .or_else(|| library.strip_prefix("@executable_path/"))
+    {
+        match file.parent() {
+            Some(dir) => dir.join(rest),
+            None => return LibraryKind::Missing,
+        }
+    } else if library.starts_with('/') {
+        std::path::PathBuf::from(library)
+    } else {
+        return LibraryKind::Variable;
+    };
+
+    if !resolved.exists() {
+        LibraryKind::Missing
+    } else if resolved.starts_with(prefix) || !library.starts_with('/') {
+        LibraryKind::Homebrew
+    } else {
+        LibraryKind::System
+    }
+}
+
 /// Check library linkages for installed formulae
 ///
-/// Inspects executables and libraries to verify they link to the correct
-/// dependencies and detect broken links. Uses otool on macOS.
+/// Runs `otool -L` on every Mach-O file in each keg and resolves the libraries it
+/// references against the prefix and the system. With `rebuild`, formulae that
+/// reference missing libraries are reinstalled to repair them; without it, any
+/// missing library fails the check.
 ///
 /// # Arguments
+/// * `api` - The Homebrew API client, used by `rebuild`
 /// * `formula_names` - The formulae to check (empty checks all)
-/// * `show_all` - If true, show all linkages (not just broken ones)
-pub fn linkage(formula_names: &[String], show_all: bool) -> Result<()> {
-    println!("Checking library linkages...");
-
+/// * `show_all` - If true, list every library (not just missing ones)
+/// * `rebuild` - If true, reinstall formulae with missing libraries
+/// * `json` - Print a [`LinkageReport`] per formula as JSON instead
+pub async fn linkage(
+    api: &BrewApi,
+    formula_names: &[String],
+    show_all: bool,
+    rebuild: bool,
+    json: bool,
+) -> Result<super::Outcome> {
     let formulae_to_check: Vec<String> = if formula_names.is_empty() {
         // Check all installed formulae
-        cellar::list_installed()?
+        cellar::list_current()?
             .into_iter()
             .map(|p| p.name)
             .collect()
@@ -703,103 +844,118 @@ pub fn linkage(formula_names: &[String], show_all: bool) -> Result<()> {
         formula_names.to_vec()
     };
 
+    if !json {
+        println!("Checking library linkages...");
+    }
     if formulae_to_check.is_empty() {
-        println!("No formulae to check");
-        return Ok(());
+        if json {
+            crate::output::print_json(&Vec::<LinkageReport>::new())?;
+        } else {
+            println!("No formulae to check");
+        }
+        return Ok(super::Outcome::Done);
     }
 
-    for formula_name in &formulae_to_check {
-        println!("{}", formula_name.cyan());
+    let prefix = cellar::detect_prefix();
+    let mut reports = Vec::new();
 
+    for formula_name in &formulae_to_check {
         let versions = cellar::get_installed_versions(formula_name)?;
-        if versions.is_empty() {
-            println!("  {} Not installed", "".yellow());
+        let Some(installed) = versions.first() else {
+            if !json {
+                println!("{}", formula_name.cyan());
+                println!("  {} Not installed", "".yellow());
+            }
             continue;
+        };
+
+        let report =
+            LinkageReport::inspect(formula_name, &installed.version, &installed.path, &prefix);
+        if !json {
+            print_linkage_report(&report, show_all);
         }
+        reports.push(report);
+    }
 
-        let version = &versions[0].version;
-        let formula_path = cellar::cellar_path().join(formula_name).join(version);
-
-        // Find all executables and libraries
-        let mut checked_files = 0;
-        let mut broken_links = 0;
-
-        // Check bin/ directory
-        let bin_dir = formula_path.join("bin");
-        if bin_dir.exists()
-            && let Ok(entries) = std::fs::read_dir(&bin_dir)
-        {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_file() {
-                    checked_files += 1;
-
-                    // Use otool to check linkages on macOS
-                    let output = std::process::Command::new("otool")
-                        .arg("-L")
-                        .arg(&path)
-                        .output();
-
-                    if let Ok(output) = output {
-                        let stdout = String::from_utf8_lossy(&output.stdout);
-
-                        if show_all && let Some(name) = path.file_name() {
-                            println!("  {}:", name.to_string_lossy());
-                            for line in stdout.lines().skip(1) {
-                                let trimmed = line.trim();
-                                if !trimmed.is_empty() {
-                                    println!("    {}", trimmed.dimmed());
-                                }
-                            }
-                        }
+    let broken: Vec<String> = reports
+        .iter()
+        .filter(|r| !r.missing.is_empty())
+        .map(|r| r.name.clone())
+        .collect();
 
-                        // Check for broken links (simplified)
-                        if stdout.contains("dyld:") || stdout.contains("not found") {
-                            broken_links += 1;
-                        }
-                    }
-                }
-            }
-        }
+    if json {
+        crate::output::print_json(&reports)?;
+        return Ok(super::Outcome::from_failures(broken.len()));
+    }
+    if broken.is_empty() {
+        return Ok(super::Outcome::Done);
+    }
+
+    if !rebuild {
+        println!(
+            "\nRun {} to reinstall {}",
+            "bru linkage --rebuild".cyan(),
+            broken.join(", ").bold()
+        );
+        return Ok(super::Outcome::PartialFailure);
+    }
+
+    println!(
+        "\nReinstalling {} formulae with missing libraries...",
+        broken.len().to_string().bold()
+    );
+    super::install::reinstall(api, &broken, false, false, false).await
+}
 
-        // Check lib/ directory
-        let lib_dir = formula_path.join("lib");
-        if lib_dir.exists()
-            && let Ok(entries) = std::fs::read_dir(&lib_dir)
-        {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_file() && (path.extension().and_then(|s| s.to_str()) == Some("dylib")) {
-                    checked_files += 1;
+/// Print one formula's linkage in the style of `brew linkage`
+fn print_linkage_report(report: &LinkageReport, show_all: bool) {
+    println!("{}", report.name.cyan());
+
+    if show_all {
+        for (heading, libraries) in [
+            ("System libraries", &report.system),
+            ("Homebrew libraries", &report.homebrew),
+            ("Variable-referenced libraries", &report.variable),
+        ] {
+            if !libraries.is_empty() {
+                println!("  {}:", heading);
+                for library in libraries {
+                    println!("    {}", library.dimmed());
                 }
             }
         }
+    }
 
-        if checked_files == 0 {
-            println!("  No linkable files found");
-        } else if broken_links > 0 {
-            println!(
-                "  {} {} files checked, {} broken links",
-                "".yellow(),
-                checked_files,
-                broken_links
-            );
-        } else {
+    if report.files == 0 {
+        println!("  No linkable files found");
+    } else if report.missing.is_empty() {
+        println!(
+            "  {} {} files checked, all links valid",
+            "".green(),
+            report.files
+        );
+    } else {
+        println!(
+            "  {} {} files checked, {} missing libraries:",
+            "".yellow(),
+            report.files,
+            report.missing.len()
+        );
+        for missing in &report.missing {
             println!(
-                "  {} {} files checked, all links valid",
-                "".green(),
-                checked_files
+                "    {} {}",
+                missing.library,
+                format!("(from {})", missing.file.display()).dimmed()
             );
         }
     }
-
-    Ok(())
 }
 
 /// Read and validate all formulae in a tap
 ///
-/// Checks that all formula files in a tap are readable and syntactically valid.
-/// Useful for verifying tap integrity.
+/// Parses every formula file in a tap and reports the ones missing a class
+/// declaration, source URL, or version. Fails if any formula has problems, so it
+/// can be used as a lint step for a tap.
 ///
 /// # Arguments
 /// * `tap_name` - The tap to check (defaults to homebrew/core)
@@ -825,55 +981,104 @@ pub fn readall(tap_name: Option<&str>) -> Result<()> {
         return Ok(());
     }
 
-    // Count formula files recursively
-    fn count_formulae(dir: &std::path::Path, depth: usize) -> (usize, usize) {
-        const MAX_DEPTH: usize = 10;
-        if depth > MAX_DEPTH {
-            return (0, 0);
-        }
-
-        let mut total = 0;
-        let mut valid = 0;
-
-        if let Ok(entries) = std::fs::read_dir(dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("rb") {
-                    total += 1;
-                    // Basic validation: check if file is readable (metadata check, not reading content)
-                    if std::fs::metadata(&path).is_ok() {
-                        valid += 1;
-                    }
-                } else if path.is_dir() {
-                    let (sub_total, sub_valid) = count_formulae(&path, depth + 1);
-                    total += sub_total;
-                    valid += sub_valid;
-                }
+    // Parse every formula in parallel, keeping the ones with problems
+    let files = crate::tap::rb_files(&formula_dir);
+    let total = files.len();
+    let mut failures: Vec<(String, Vec<String>)> = files
+        .par_iter()
+        .filter_map(|path| {
+            let name = path.file_stem()?.to_string_lossy().to_string();
+            let problems = match crate::tap::parse_formula_info(path, &name) {
+                Ok(info) => info.problems(),
+                Err(e) => vec![e.to_string()],
+            };
+            if problems.is_empty() {
+                return None;
             }
+            let relative = path.strip_prefix(&tap_dir).unwrap_or(path);
+            Some((relative.display().to_string(), problems))
+        })
+        .collect();
+    failures.sort();
+
+    for (file, problems) in &failures {
+        println!("{} {}", "".red(), file.bold());
+        for problem in problems {
+            println!("    {}", problem);
         }
-
-        (total, valid)
     }
 
-    let (total, valid) = count_formulae(&formula_dir, 0);
-
     if total == 0 {
         println!("{} No formulae found in tap", "".yellow());
-    } else if valid == total {
+    } else if failures.is_empty() {
         println!(
-            "{} All {} formulae are readable",
+            "{} All {} formulae read successfully",
             "".green().bold(),
             total.to_string().bold()
         );
     } else {
-        println!(
-            "{} {} of {} formulae are readable",
-            "".yellow(),
-            valid,
-            total
+        println!();
+        return Err(
+            anyhow::anyhow!("{} of {} formulae failed to read", failures.len(), total).into(),
         );
-        println!("  {} {} formulae have issues", "".red(), total - valid);
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_otool_libraries() {
+        let output = "\
+/opt/homebrew/Cellar/jq/1.7.1/bin/jq (architecture arm64):
+\t/opt/homebrew/opt/oniguruma/lib/libonig.5.dylib (compatibility version 6.0.0, current version 6.9.0)
+\t@rpath/libjq.1.dylib (compatibility version 2.0.0, current version 2.0.0)
+\t/usr/lib/libSystem.B.dylib (compatibility version 1.0.0, current version 1351.0.0)
+";
+        assert_eq!(
+            parse_otool_libraries(output),
+            [
+                "/opt/homebrew/opt/oniguruma/lib/libonig.5.dylib",
+                "@rpath/libjq.1.dylib",
+                "/usr/lib/libSystem.B.dylib",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_classify_library() {
+        let prefix = tempfile::TempDir::new().unwrap();
+        let keg = prefix.path().join("Cellar/jq/1.7.1");
+        std::fs::create_dir_all(keg.join("bin")).unwrap();
+        std::fs::create_dir_all(keg.join("lib")).unwrap();
+        std::fs::write(keg.join("lib/libjq.1.dylib"), "").unwrap();
+        let lib = keg.join("lib/libjq.1.dylib");
+        let bin = keg.join("bin/jq");
+
+        let classify = |library: &str| classify_library(library, &bin, prefix.path());
+        assert_eq!(classify("/usr/lib/libSystem.B.dylib"), LibraryKind::System);
+        assert_eq!(classify(&lib.display().to_string()), LibraryKind::Homebrew);
+        assert_eq!(
+            classify("@loader_path/../lib/libjq.1.dylib"),
+            LibraryKind::Homebrew
+        );
+        assert_eq!(classify("@rpath/libjq.1.dylib"), LibraryKind::Variable);
+        assert_eq!(
+            classify(
+                &prefix
+                    .path()
+                    .join("opt/icu4c/lib/libicuuc.74.dylib")
+                    .display()
+                    .to_string()
+            ),
+            LibraryKind::Missing
+        );
+        assert_eq!(
+            classify("@executable_path/libgone.dylib"),
+            LibraryKind::Missing
+        );
+    }
+}
@@ -7,6 +7,7 @@ use crate::api::BrewApi;
 use crate::cellar;
 use crate::error::Result;
 use colored::Colorize;
+use std::path::Path;
 
 use super::utils::read_pinned;
 
@@ -138,27 +139,46 @@ pub fn log(formula_name: &str) -> Result<()> {
 /// debugging issues or sharing with maintainers. Includes system
 /// info, installed packages, taps, and formula-specific details.
 ///
+/// The report goes to `output` when given. Otherwise it's uploaded to a secret
+/// gist if a GitHub token is set, and printed to the terminal if not (or if the
+/// upload fails).
+///
 /// # Arguments
 /// * `api` - The Homebrew API client
 /// * `formula` - Optional formula name to include specific details for
-pub async fn gist_logs(api: &BrewApi, formula: Option<&str>) -> Result<()> {
+/// * `output_file` - Optional file to write the report to instead of uploading it
+pub async fn gist_logs(
+    api: &BrewApi,
+    formula: Option<&str>,
+    output_file: Option<&Path>,
+) -> Result<()> {
     println!("Generating diagnostic information...");
     println!();
 
     let mut output = String::new();
 
+    let config = super::SystemConfig::collect()?;
+
     // System information
     output.push_str("=== System Information ===\n");
-    output.push_str(&format!("bru version: {}\n", env!("CARGO_PKG_VERSION")));
-    output.push_str(&format!("OS: {}\n", std::env::consts::OS));
-    output.push_str(&format!("Architecture: {}\n", std::env::consts::ARCH));
-    output.push_str(&format!("Prefix: {}\n", cellar::detect_prefix().display()));
+    output.push_str(&format!(
+        "bru version: {} ({}, built {})\n",
+        config.version, config.git_sha, config.build_date
+    ));
+    output.push_str(&format!("Target: {}\n", config.target));
+    output.push_str(&format!(
+        "Bottle tag: {}\n",
+        config.bottle_tag.as_deref().unwrap_or("none")
+    ));
+    output.push_str(&format!("OS: {}\n", config.os));
+    output.push_str(&format!("Architecture: {}\n", config.arch));
+    output.push_str(&format!("Prefix: {}\n", config.prefix.display()));
     output.push('\n');
 
     // Installed packages
     output.push_str("=== Installed Packages ===\n");
     let packages = cellar::list_installed()?;
-    output.push_str(&format!("Total: {}\n", packages.len()));
+    output.push_str(&format!("Total: {}\n", config.installed_packages));
     for pkg in packages.iter().take(20) {
         output.push_str(&format!("{} {}\n", pkg.name, pkg.version));
     }
@@ -217,24 +237,19 @@ pub async fn gist_logs(api: &BrewApi, formula: Option<&str>) -> Result<()> {
 
     // Config
     output.push_str("=== Configuration ===\n");
-    output.push_str(&format!("Cellar: {}\n", cellar::cellar_path().display()));
-    output.push_str(&format!(
-        "Cache: {}\n",
-        crate::download::cache_dir().display()
-    ));
+    output.push_str(&format!("Cellar: {}\n", config.cellar.display()));
+    output.push_str(&format!("Cache: {}\n", config.cache.display()));
 
     // Check for pinned formulae
-    let pinned = read_pinned()?;
+    let pinned = read_pinned(false)?;
     if !pinned.is_empty() {
         output.push_str(&format!("Pinned: {}\n", pinned.join(", ")));
     }
 
     // Doctor check summary
     output.push_str("\n=== Health Check ===\n");
-    let prefix = cellar::detect_prefix();
-    let cellar = cellar::cellar_path();
-    output.push_str(&format!("Prefix exists: {}\n", prefix.exists()));
-    output.push_str(&format!("Cellar exists: {}\n", cellar.exists()));
+    output.push_str(&format!("Prefix exists: {}\n", config.prefix.exists()));
+    output.push_str(&format!("Cellar exists: {}\n", config.cellar.exists()));
     output.push_str(&format!(
         "Git available: {}\n",
         std::process::Command::new("git")
@@ -244,10 +259,46 @@ pub async fn gist_logs(api: &BrewApi, formula: Option<&str>) -> Result<()> {
             .unwrap_or(false)
     ));
 
+    if let Some(path) = output_file {
+        std::fs::write(path, &output)?;
+        println!(
+            "{} Diagnostic information written to {}",
+            "".green(),
+            path.display().to_string().cyan()
+        );
+        return Ok(());
+    }
+
+    if crate::api::github_token().is_some() {
+        let description = match formula {
+            Some(name) => format!("bru gist-logs {}", name),
+            None => "bru gist-logs".to_string(),
+        };
+        let files = [
+            ("bru-gist-logs.txt", output.clone()),
+            ("config.json", serde_json::to_string_pretty(&config)?),
+        ];
+        match api.create_gist(&description, &files).await {
+            Ok(url) => {
+                println!("{} Diagnostic information uploaded", "".green());
+                println!("{}", url.cyan());
+                return Ok(());
+            }
+            Err(e) => {
+                println!("{} Gist upload failed: {}", "".yellow(), e);
+                println!();
+            }
+        }
+    }
+
     println!("{}", output);
     println!();
     println!("{} Diagnostic information generated", "".green());
-    println!("Copy the above output to share for debugging");
+    println!(
+        "Copy the above output to share for debugging (set {} to upload a gist, or use {})",
+        "GITHUB_TOKEN".cyan(),
+        "--output <file>".cyan()
+    );
 
     Ok(())
 }
@@ -3,7 +3,7 @@
 //! This module contains all commands related to installing, upgrading, reinstalling,
 //! and uninstalling formulae, including dependency resolution and bottle management.
 
-use crate::api::{BrewApi, Formula};
+use crate::api::{BrewApi, DependencyPolicy, Formula, FormulaSource};
 use crate::cellar::{self, RuntimeDependency};
 use crate::error::Result;
 use crate::{download, extract, receipt, symlink};
@@ -14,24 +14,48 @@ use std::collections::{HashMap, HashSet, VecDeque};
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Duration;
 
-fn pinned_file_path() -> std::path::PathBuf {
-    cellar::detect_prefix().join("var/homebrew/pinned_formulae")
-}
+use super::Outcome;
+use super::interrupt::{self, InterruptGuard};
 
-fn read_pinned() -> Result<Vec<String>> {
-    let path = pinned_file_path();
-    if !path.exists() {
-        return Ok(Vec::new());
+/// Resolve all dependencies recursively, parallelizing each level
+///
+/// Recommended and optional dependencies selected by `policy` are folded into each
+/// formula's `dependencies`, so they are ordered and recorded like required ones.
+pub(crate) async fn resolve_dependencies(
+    source: &dyn FormulaSource,
+    root_formulae: &[String],
+    policy: &DependencyPolicy,
+) -> Result<(HashMap<String, Formula>, Vec<String>)> {
+    let (all_formulae, dep_order) = resolve_dependency_graph(source, root_formulae, policy).await?;
+
+    // Only print summary if not in quiet mode
+    if std::env::var("BRU_QUIET").is_err() {
+        println!("{} dependencies resolved", all_formulae.len());
     }
 
-    let content = std::fs::read_to_string(&path)?;
-    Ok(content.lines().map(String::from).collect())
+    Ok((all_formulae, dep_order))
 }
 
-/// Resolve all dependencies recursively, parallelizing each level
-pub(crate) async fn resolve_dependencies(
-    api: &BrewApi,
+/// The order `names` and all their dependencies would be installed in, dependencies first
+///
+/// This is the order `install` works through, before it skips what is already
+/// installed. Recommended dependencies are included and optional ones aren't, as
+/// for an install without `--with`/`--without`. Formulae that don't depend on each
+/// other are ordered by name, so the result is the same on every run.
+///
+/// # Errors
+///
+/// Returns an error if the dependency graph has a cycle.
+pub async fn install_order(source: &dyn FormulaSource, names: &[String]) -> Result<Vec<String>> {
+    let (_, order) = resolve_dependency_graph(source, names, &DependencyPolicy::default()).await?;
+    Ok(order)
+}
+
+/// [`resolve_dependencies`] without the summary line
+async fn resolve_dependency_graph(
+    source: &dyn FormulaSource,
     root_formulae: &[String],
+    policy: &DependencyPolicy,
 ) -> Result<(HashMap<String, Formula>, Vec<String>)> {
     // Typical dependency depth is 10-20, so estimate total as root_count * 10
     let estimated_capacity = root_formulae.len() * 10;
@@ -69,14 +93,16 @@ pub(crate) async fn resolve_dependencies(
         // Fetch all formulae at this level in parallel
         let fetch_futures: Vec<_> = current_level
             .iter()
-            .map(|name| async move { api.fetch_formula(name).await.ok() })
+            .map(|name| async move { source.formula(name).await.ok() })
             .collect();
 
         let results = futures::future::join_all(fetch_futures).await;
 
         // Collect next level dependencies
         let mut next_level = Vec::new();
-        for (formula, name) in results.into_iter().flatten().zip(current_level.iter()) {
+        for (mut formula, name) in results.into_iter().flatten().zip(current_level.iter()) {
+            policy.apply(&mut formula);
+
             // Add dependencies to next level
             for dep in &formula.dependencies {
                 if !processed.contains(dep) && !all_formulae.contains_key(dep) {
@@ -98,11 +124,6 @@ pub(crate) async fn resolve_dependencies(
 
     spinner.finish_and_clear();
 
-    // Only print summary if not in quiet mode
-    if std::env::var("BRU_QUIET").is_err() {
-        println!("{} dependencies resolved", all_formulae.len());
-    }
-
     Ok((all_formulae, dep_order))
 }
 
@@ -116,30 +137,42 @@ fn topological_sort(formulae: &HashMap<String, Formula>) -> anyhow::Result<Vec<S
     for (name, formula) in formulae {
         in_degree.entry(name.as_str()).or_insert(0);
         for dep in &formula.dependencies {
-            graph.entry(dep.as_str()).or_default().push(name.as_str());
+            // Tap formulae name their tap dependencies qualified (`user/repo/dep`), but
+            // are keyed by their short name
+            let dep = match dep.rsplit_once('/') {
+                Some((_, short)) if !formulae.contains_key(dep) => short,
+                _ => dep.as_str(),
+            };
+            graph.entry(dep).or_default().push(name.as_str());
             *in_degree.entry(name.as_str()).or_insert(0) += 1;
         }
     }
 
-    // Kahn's algorithm with VecDeque for efficient queue operations
-    let mut queue: VecDeque<&str> = in_degree
+    // Kahn's algorithm with VecDeque for efficient queue operations. Ready formulae
+    // are queued by name, so the order doesn't depend on hash map iteration
+    let mut ready: Vec<&str> = in_degree
         .iter()
         .filter_map(|(&name, &count)| if count == 0 { Some(name) } else { None })
         .collect();
+    ready.sort_unstable();
+    let mut queue: VecDeque<&str> = ready.into();
     let mut result = Vec::with_capacity(capacity);
 
     while let Some(node) = queue.pop_front() {
         result.push(node.to_string());
 
         if let Some(dependents) = graph.get(node) {
+            let mut ready = Vec::new();
             for &dependent in dependents {
                 if let Some(count) = in_degree.get_mut(dependent) {
                     *count -= 1;
                     if *count == 0 {
-                        queue.push_back(dependent);
+                        ready.push(dependent);
                     }
                 }
             }
+            ready.sort_unstable();
+            queue.extend(ready);
         }
     }
 
@@ -150,6 +183,93 @@ fn topological_sort(formulae: &HashMap<String, Formula>) -> anyhow::Result<Vec<S
     Ok(result)
 }
 
+/// Resolve each name on its own, so one whose dependencies can't be fetched doesn't
+/// stop the rest (`--keep-going`)
+///
+/// Returns the merged formula map and install order, and the names that failed.
+async fn resolve_dependencies_each(
+    source: &dyn FormulaSource,
+    root_formulae: &[String],
+    policy: &DependencyPolicy,
+) -> (
+    HashMap<String, Formula>,
+    Vec<String>,
+    Vec<(String, crate::error::BruError)>,
+) {
+    let mut all_formulae = HashMap::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut failed = Vec::new();
+
+    for name in root_formulae {
+        match resolve_dependencies(source, std::slice::from_ref(name), policy).await {
+            Ok((formulae, dep_order)) => {
+                // Each order lists dependencies first, so keeping first occurrences
+                // keeps the merged order valid
+                for formula in dep_order {
+                    if !order.contains(&formula) {
+                        order.push(formula);
+                    }
+                }
+                all_formulae.extend(formulae);
+            }
+            Err(e) => failed.push((name.clone(), e)),
+        }
+    }
+
+    (all_formulae, order, failed)
+}
+
+/// Look up each requested name as a formula, checking for a cask of the same name
+///
+/// Yields `Ok((name, also_a_cask))` for formulae and `Err((name, CaskNotFound))` for
+/// names that only exist as casks (those are handed to brew); any other error is a
+/// failed lookup.
+async fn validate_requested(
+    source: &dyn FormulaSource,
+    names: &[String],
+    formula_only: bool,
+) -> Vec<std::result::Result<(String, bool), (String, crate::error::BruError)>> {
+    let lookups: Vec<_> = names
+        .iter()
+        .map(|name| async move {
+            // Unless --formula was given, also look for a cask with the same name
+            let (formula, cask) = if formula_only {
+                (source.formula(name).await, None)
+            } else {
+                let (formula, cask) = tokio::join!(source.formula(name), source.cask(name));
+                (formula, Some(cask))
+            };
+            match formula {
+                Ok(_) => Ok((name.clone(), matches!(cask, Some(Ok(_))))),
+                Err(crate::error::BruError::FormulaNotFound(_)) => {
+                    // Formula not found, check if it's a cask
+                    let cask = match cask {
+                        Some(cask) => cask,
+                        None => source.cask(name).await,
+                    };
+                    match cask {
+                        Ok(_) => Err((
+                            name.clone(),
+                            crate::error::BruError::CaskNotFound(name.clone()),
+                        )),
+                        Err(crate::error::BruError::CaskNotFound(_)) => {
+                            // Neither formula nor cask exists
+                            Err((
+                                name.clone(),
+                                crate::error::BruError::FormulaNotFound(name.clone()),
+                            ))
+                        }
+                        Err(e) => Err((name.clone(), e)),
+                    }
+                }
+                Err(e) => Err((name.clone(), e)),
+            }
+        })
+        .collect();
+
+    futures::future::join_all(lookups).await
+}
+
 /// Build runtime dependencies list for receipt
 fn build_runtime_deps(
     dep_names: &[String],
@@ -166,6 +286,7 @@ fn build_runtime_deps(
                     bottle_rebuild: 0,
                     pkg_version: v.clone(),
                     declared_directly: true,
+                    extra: Default::default(),
                 })
             })
         })
@@ -176,9 +297,25 @@ struct UpgradeCandidate {
     name: String,
     old_version: String,
     formula: crate::api::Formula,
+    /// The installed keg was built from source, so brew upgrades it the same way
+    from_source: bool,
 }
 
-pub async fn fetch(api: &BrewApi, formula_names: &[String]) -> Result<()> {
+/// Download bottles into the cache without installing them
+///
+/// With `verify`, every bottle (freshly downloaded or already cached) is checked
+/// against its SHA256 and reported as passed or failed; formulae without a bottle
+/// count as failures too. Any failure makes the command exit non-zero.
+///
+/// Without `keep_going`, a formula that can't be looked up or has no bottle stops the
+/// fetch before anything is downloaded. With it, those formulae are skipped, every
+/// other bottle is still downloaded, and all failures are listed together at the end.
+pub async fn fetch(
+    api: &BrewApi,
+    formula_names: &[String],
+    verify: bool,
+    keep_going: bool,
+) -> Result<Outcome> {
     let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
 
     let spinner = if is_tty {
@@ -200,47 +337,71 @@ pub async fn fetch(api: &BrewApi, formula_names: &[String]) -> Result<()> {
         .iter()
         .map(|name| async move {
             match api.fetch_formula(name).await {
-                Ok(formula) => {
-                    // Check if bottle exists
-                    if formula.bottle.is_none()
-                        || formula
-                            .bottle
-                            .as_ref()
-                            .and_then(|b| b.stable.as_ref())
-                            .is_none()
-                    {
-                        println!("{}: No bottle available", name.bold().yellow());
-                        return None;
-                    }
-                    Some(formula)
-                }
-                Err(e) => {
-                    println!("{}: Failed to fetch formula: {}", name.bold().red(), e);
-                    None
+                Ok(formula)
+                    if formula
+                        .bottle
+                        .as_ref()
+                        .and_then(|b| b.stable.as_ref())
+                        .is_none() =>
+                {
+                    Err((name.clone(), anyhow::anyhow!("No bottle available")))
                 }
+                Ok(formula) => Ok(formula),
+                Err(e) => Err((
+                    name.clone(),
+                    anyhow::anyhow!("Failed to fetch formula: {}", e),
+                )),
             }
         })
         .collect();
 
     let results = futures::future::join_all(fetch_futures).await;
-    let formulae: Vec<_> = results.into_iter().flatten().collect();
-
     spinner.finish_and_clear();
 
-    if formulae.is_empty() {
-        println!("No formulae to download");
-        return Ok(());
+    let mut formulae = Vec::new();
+    let mut failed = Vec::new();
+    for result in results {
+        match result {
+            Ok(formula) => formulae.push(formula),
+            Err(failure) => failed.push(failure),
+        }
+    }
+    if !keep_going && let Some((name, err)) = failed.first() {
+        return Err(anyhow::anyhow!("{}: {}", name, err).into());
+    }
+
+    if verify {
+        let mut failures: Vec<String> = Vec::new();
+        let verified = verify_bottles(api, &formulae, &mut failures).await;
+        if keep_going {
+            if !failed.is_empty() {
+                report_failures("Failed to fetch:", &failed);
+            }
+            failures.extend(failed.into_iter().map(|(name, _)| name));
+            return Ok(super::utils::batch_outcome("verify", &failures, true));
+        }
+        let failed = formula_names.len().saturating_sub(verified);
+        if failed > 0 {
+            return Err(anyhow::anyhow!(
+                "{} of {} bottles failed verification",
+                failed,
+                formula_names.len()
+            )
+            .into());
+        }
+        return Ok(Outcome::Done);
     }
 
-    // Download bottles in parallel
-    match download::download_bottles(api, &formulae).await {
-        Ok(results) => {
+    if !formulae.is_empty() {
+        // Download bottles in parallel; one failure doesn't stop the others
+        let batch = download::download_bottles(api, &formulae).await;
+        if !batch.downloaded.is_empty() {
             println!(
                 "Downloaded {} bottles to {}",
-                results.len().to_string().bold().green(),
+                batch.downloaded.len().to_string().bold().green(),
                 download::cache_dir().display().to_string().dimmed()
             );
-            for (name, path) in results {
+            for (name, path) in &batch.downloaded {
                 println!(
                     "  {} {}",
                     name.bold().green(),
@@ -248,26 +409,233 @@ pub async fn fetch(api: &BrewApi, formula_names: &[String]) -> Result<()> {
                 );
             }
         }
-        Err(e) => {
-            println!("{}: {}", "Download failed".red().bold(), e);
-            return Err(e.into());
+        failed.extend(batch.failed);
+    } else if failed.is_empty() {
+        println!("No formulae to download");
+    }
+
+    if failed.is_empty() {
+        return Ok(Outcome::Done);
+    }
+    report_failures("Failed to fetch:", &failed);
+    if !keep_going {
+        return Err(anyhow::anyhow!(
+            "{} of {} bottles failed to download",
+            failed.len(),
+            formulae.len()
+        )
+        .into());
+    }
+    let failures: Vec<String> = failed.into_iter().map(|(name, _)| name).collect();
+    Ok(super::utils::batch_outcome("fetch", &failures, keep_going))
+}
+
+/// `fetch --verify`: download or re-read each bottle and report its checksum result
+///
+/// Formulae whose bottle failed are added to `failures`. Returns how many bottles
+/// passed; the caller counts formulae that had no bottle to verify as failures too.
+async fn verify_bottles(api: &BrewApi, formulae: &[Formula], failures: &mut Vec<String>) -> usize {
+    // download_bottles only hands out files that match their SHA256: a cached bottle
+    // that doesn't match is deleted and downloaded again, and only a fresh download
+    // that still doesn't match is reported as a failure
+    let batch = download::download_bottles(api, formulae).await;
+    println!("{}", "Verifying bottles:".bold());
+    report_verified(formulae, &batch, failures)
+}
+
+/// Print a line per bottle in `batch` and return how many verified. A bottle whose
+/// formula lists no checksum can't have been verified, so it counts as a failure.
+fn report_verified(
+    formulae: &[Formula],
+    batch: &download::BatchResult,
+    failures: &mut Vec<String>,
+) -> usize {
+    let by_name: HashMap<_, _> = formulae.iter().map(|f| (f.name.as_str(), f)).collect();
+
+    let mut verified = 0;
+    for (name, _) in &batch.downloaded {
+        let sha256 = by_name
+            .get(name.as_str())
+            .and_then(|f| download::bottle_sha256(f).ok())
+            .filter(|sha256| !sha256.is_empty());
+        match sha256 {
+            Some(sha256) => {
+                println!(
+                    "  {} {} {}",
+                    "✓".green(),
+                    name.bold(),
+                    format!("sha256:{}", sha256).dimmed()
+                );
+                verified += 1;
+            }
+            None => {
+                println!(
+                    "  {} {}: no checksum to verify",
+                    "✗".red(),
+                    name.bold().red()
+                );
+                failures.push(name.clone());
+            }
         }
     }
+    for (name, err) in &batch.failed {
+        println!("  {} {}: {}", "✗".red(), name.bold().red(), err);
+        failures.push(name.clone());
+    }
+
+    println!("Verified {} bottles", verified.to_string().bold().green());
+    verified
+}
+
+/// `(formula, requirement)` for each `:macos`/`:maximum_macos` requirement the running
+/// macOS version doesn't meet. Build-only requirements don't apply to bottles.
+fn macos_incompatible<'a>(
+    formulae: &'a [Formula],
+    macos_version: Option<&str>,
+) -> Vec<(&'a str, &'a crate::api::Requirement)> {
+    formulae
+        .iter()
+        .flat_map(|f| f.requirements.iter().map(move |r| (f.name.as_str(), r)))
+        .filter(|(_, r)| {
+            r.is_macos()
+                && !r.build_only()
+                && r.satisfied(macos_version, std::env::consts::ARCH) == Some(false)
+        })
+        .collect()
+}
+
+/// Warn about formulae whose runtime requirements (e.g. a minimum macOS) this system
+/// doesn't meet. Build-only requirements don't matter for bottles and are skipped.
+fn warn_unsatisfied_requirements(formulae: &[Formula]) {
+    let macos_version = crate::platform::current_macos_version();
+    for formula in formulae {
+        for requirement in &formula.requirements {
+            if !requirement.build_only()
+                && requirement.satisfied(macos_version.as_deref(), std::env::consts::ARCH)
+                    == Some(false)
+            {
+                eprintln!(
+                    "Warning: {} requires {}; it may not work on this system",
+                    formula.name, requirement
+                );
+            }
+        }
+    }
+}
+
+/// List the bottles that couldn't be fetched, after the ones that could
+fn report_failures(heading: &str, failed: &[(String, anyhow::Error)]) {
+    println!("{}", heading.red().bold());
+    for (name, err) in failed {
+        println!("  {}: {}", name.bold().red(), err);
+    }
+}
 
+/// Refuse to start pouring when the Cellar's filesystem can't hold every bottle
+/// unpacked, rather than failing mid-extract with a half-written keg
+///
+/// Bottles whose size can't be read are left out of the estimate; pouring them fails
+/// on its own, for that package only.
+fn ensure_disk_space<'a>(bottles: impl IntoIterator<Item = &'a std::path::PathBuf>) -> Result<()> {
+    let cellar = cellar::cellar_path();
+    let needed: u64 = bottles
+        .into_iter()
+        .filter_map(|bottle| extract::unpacked_size(bottle).ok())
+        .sum();
+    let available = extract::available_space(&cellar)?;
+
+    if needed > available {
+        return Err(anyhow::anyhow!(
+            "Not enough disk space in {}: need {}, have {}",
+            cellar.display(),
+            super::utils::format_size(needed),
+            super::utils::format_size(available)
+        )
+        .into());
+    }
     Ok(())
 }
 
+/// Plans with more formulae than this ask for confirmation before downloading
+const LARGE_INSTALL_THRESHOLD: usize = 10;
+
+/// Options for [`install`], mirroring `bru install`'s flags
+#[derive(Debug, Clone, Default)]
+pub struct InstallFlags {
+    /// Show what would be installed without installing anything
+    pub dry_run: bool,
+    /// Install even if already installed
+    pub force: bool,
+    /// Treat names as formulae even when a cask has the same name
+    pub formula_only: bool,
+    /// Don't ask for confirmation before large installs
+    pub yes: bool,
+    /// Always ask for confirmation before downloading
+    pub interactive: bool,
+    /// Which recommended and optional dependencies to install
+    pub dependency_policy: DependencyPolicy,
+    /// Install the rest when some formulae fail, and report every failure at the end
+    pub keep_going: bool,
+}
+
+impl InstallFlags {
+    /// Flags for installing formulae another command needs (dependencies of a cask,
+    /// an upgrade or a Brewfile): names are formulae, and nothing asks for confirmation
+    pub fn unattended(keep_going: bool) -> Self {
+        Self {
+            formula_only: true,
+            yes: true,
+            keep_going,
+            ..Self::default()
+        }
+    }
+}
+
 pub async fn install(
     api: &BrewApi,
     formula_names: &[String],
-    _only_dependencies: bool,
-    dry_run: bool,
-    force: bool,
-) -> Result<()> {
+    flags: &InstallFlags,
+) -> Result<Outcome> {
+    let InstallFlags {
+        dry_run,
+        force,
+        formula_only,
+        yes,
+        interactive,
+        ref dependency_policy,
+        keep_going,
+        ..
+    } = *flags;
+
     if dry_run {
         println!("Dry run mode - no packages will be installed");
     }
 
+    // Packages that failed while others may still succeed (reported as a partial failure)
+    let mut failures: Vec<String> = Vec::new();
+    let finish = |failures: &[String]| super::utils::batch_outcome("install", failures, keep_going);
+
+    // Bottle and formula files (or bottle URLs) are installed without the API
+    let (local_sources, formula_names): (Vec<_>, Vec<_>) = formula_names
+        .iter()
+        .cloned()
+        .partition(|name| name.ends_with(".tar.gz") || name.ends_with(".rb"));
+    for source in &local_sources {
+        let result = if source.ends_with(".rb") {
+            install_local_formula(api, source, flags).await
+        } else {
+            install_local_bottle(api, source, flags).await
+        };
+        if let Err(e) = result {
+            println!("  {}: Failed to install: {}", source.bold().red(), e);
+            failures.push(source.clone());
+        }
+    }
+    if formula_names.is_empty() {
+        return Ok(finish(&failures));
+    }
+    let formula_names = formula_names.as_slice();
+
     println!(
         "Installing {} formulae...",
         formula_names.len().to_string().bold()
@@ -290,68 +658,78 @@ pub async fn install(
         }
     }
 
-    // Handle tap formulas by delegating to brew
-    if !tap_formulae.is_empty() {
-        for tap_formula in &tap_formulae {
-            match super::utils::fallback_to_brew_with_reason(
+    // Formulae from locally cloned taps are read from the tap's Ruby file; any other
+    // tap is left to brew, which can tap it first
+    for tap_formula in &tap_formulae {
+        let local = crate::tap::split_qualified(tap_formula).and_then(|(tap, name)| {
+            let path = crate::tap::formula_file_in(&cellar::detect_prefix(), &tap, name)?;
+            Some((tap, name, path))
+        });
+        let result = match local {
+            Some((tap, name, path)) => {
+                match install_tap_dependencies(api, tap_formula, flags).await {
+                    Ok(()) => {
+                        install_formula_file(api, tap_formula, &path, name, Some(&tap), flags)
+                            .await
+                            .map_err(Into::into)
+                    }
+                    Err(e) => Err(e),
+                }
+            }
+            None => super::utils::fallback_to_brew_with_reason(
                 "install",
                 tap_formula,
                 Some(&format!("{} (custom tap)", tap_formula.bold())),
-            ) {
-                Ok(_) => {
-                    println!("  {} installed successfully", tap_formula.bold().green());
-                }
-                Err(e) => {
-                    println!("  {}: Failed to install: {}", tap_formula.bold().red(), e);
-                }
-            }
+            )
+            .map(|_| println!("  {} installed successfully", tap_formula.bold().green())),
+        };
+        if let Err(e) = result {
+            println!("  {}: Failed to install: {}", tap_formula.bold().red(), e);
+            failures.push(tap_formula.clone());
         }
     }
 
     // If only tap formulas were requested, we're done
     if core_formulae.is_empty() {
-        return Ok(());
+        if !keep_going && tap_formulae.iter().all(|name| failures.contains(name)) {
+            return Err(crate::error::BruError::Other(anyhow::anyhow!(
+                "All formulae failed to install"
+            )));
+        }
+        return Ok(finish(&failures));
     }
 
     // Step 1: Validate core formulae in parallel, check for casks if formula not found
     println!("Resolving dependencies...");
 
-    let validation_futures: Vec<_> = core_formulae
-        .iter()
-        .map(|name| async move {
-            match api.fetch_formula(name).await {
-                Ok(_) => Ok(name.clone()),
-                Err(crate::error::BruError::FormulaNotFound(_)) => {
-                    // Formula not found, check if it's a cask
-                    match api.fetch_cask(name).await {
-                        Ok(_) => Err((
-                            name.clone(),
-                            crate::error::BruError::CaskNotFound(name.clone()),
-                        )),
-                        Err(crate::error::BruError::CaskNotFound(_)) => {
-                            // Neither formula nor cask exists
-                            Err((
-                                name.clone(),
-                                crate::error::BruError::FormulaNotFound(name.clone()),
-                            ))
-                        }
-                        Err(e) => Err((name.clone(), e)),
-                    }
-                }
-                Err(e) => Err((name.clone(), e)),
-            }
-        })
-        .collect();
-
-    let validation_results = futures::future::join_all(validation_futures).await;
+    let validation_results = validate_requested(api, &core_formulae, formula_only).await;
 
     let mut errors = Vec::new();
     let mut casks = Vec::new();
     let mut valid_formulae = Vec::new();
+    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
 
     for result in validation_results {
         match result {
-            Ok(name) => valid_formulae.push(name),
+            Ok((name, false)) => valid_formulae.push(name),
+            Ok((name, true)) => {
+                // Same name as a cask (e.g. docker): make the user pick interactively,
+                // keep installing the formula in scripts
+                if is_tty {
+                    errors.push((
+                        name.clone(),
+                        crate::error::BruError::Other(anyhow::anyhow!(
+                            "exists as both a formula and a cask; use --formula or --cask"
+                        )),
+                    ));
+                } else {
+                    eprintln!(
+                        "Warning: {} exists as both a formula and a cask; installing the formula (use --cask for the cask)",
+                        name
+                    );
+                    valid_formulae.push(name);
+                }
+            }
             Err((name, crate::error::BruError::CaskNotFound(_))) => {
                 // Mark as cask for fallback to brew
                 casks.push(name);
@@ -373,20 +751,25 @@ pub async fn install(
                 }
                 Err(e) => {
                     println!("  {}: Failed to install: {}", cask_name.bold().red(), e);
+                    failures.push(cask_name.clone());
                 }
             }
         }
     }
 
     // Report any other errors
+    failures.extend(errors.iter().map(|(name, _)| name.clone()));
     if !errors.is_empty() {
         for (name, err) in &errors {
             println!("{}: {}", name.red().bold(), err);
+            if matches!(err, crate::error::BruError::FormulaNotFound(_)) {
+                super::utils::print_suggestions(name);
+            }
         }
     }
 
     // If no valid formulae and no casks handled, fail
-    if valid_formulae.is_empty() && casks.is_empty() {
+    if !keep_going && valid_formulae.is_empty() && casks.is_empty() {
         return Err(crate::error::BruError::Other(anyhow::anyhow!(
             "All formulae failed to install"
         )));
@@ -394,7 +777,7 @@ pub async fn install(
 
     // If only casks were requested, we're done
     if valid_formulae.is_empty() {
-        return Ok(());
+        return Ok(finish(&failures));
     }
 
     // Report any non-cask errors but continue with valid formulae
@@ -404,13 +787,23 @@ pub async fn install(
 
     // Resolve dependencies for valid formulae only
     println!("Resolving dependencies...");
-    let (all_formulae, dep_order) = resolve_dependencies(api, &valid_formulae).await?;
+    let (all_formulae, dep_order) = if keep_going {
+        let (all_formulae, dep_order, unresolved) =
+            resolve_dependencies_each(api, &valid_formulae, dependency_policy).await;
+        for (name, err) in unresolved {
+            println!("{}: {}", name.red().bold(), err);
+            failures.push(name);
+        }
+        (all_formulae, dep_order)
+    } else {
+        resolve_dependencies(api, &valid_formulae, dependency_policy).await?
+    };
 
     // Filter installed packages (unless --force)
     let installed = cellar::list_installed()?;
     let installed_names: HashSet<_> = installed.iter().map(|p| p.name.as_str()).collect();
 
-    let to_install: Vec<_> = if force {
+    let mut to_install: Vec<_> = if force {
         // With --force, install all formulae even if already installed
         all_formulae.values().cloned().collect()
     } else {
@@ -446,7 +839,7 @@ pub async fn install(
         if force {
             println!("  Use {} to reinstall", "--force".dimmed());
         }
-        return Ok(());
+        return Ok(finish(&failures));
     }
 
     println!(
@@ -460,27 +853,80 @@ pub async fn install(
             .cyan()
     );
 
+    // Bottles built for a newer (or older) macOS crash at runtime, so refuse them
+    // unless --force
+    let mut blocked: HashSet<String> = HashSet::new();
+    if !force {
+        let macos_version = crate::platform::current_macos_version();
+        for (formula, requirement) in macos_incompatible(&to_install, macos_version.as_deref()) {
+            println!(
+                "  {}: Requires {} (running {}); use {} to install anyway",
+                formula.bold().red(),
+                requirement,
+                macos_version.as_deref().unwrap_or(std::env::consts::OS),
+                "--force".dimmed()
+            );
+            blocked.insert(formula.to_string());
+        }
+    }
+    to_install.retain(|f| !blocked.contains(&f.name));
+    if to_install.is_empty() {
+        failures.extend(blocked);
+        return Ok(finish(&failures));
+    }
+
+    warn_unsatisfied_requirements(&to_install);
+
     // If dry-run, stop here
     if dry_run {
         println!(
             "{}",
             "Dry run complete - no packages were installed".green()
         );
-        return Ok(());
+        return Ok(finish(&failures));
+    }
+
+    // Give a chance to back out of large installs (or any install with --interactive)
+    if !yes && (interactive || to_install.len() > LARGE_INSTALL_THRESHOLD) {
+        let size = match download::download_size(api, &to_install).await {
+            Ok(bytes) => format!(", {} to download", super::utils::format_size(bytes)),
+            Err(_) => String::new(),
+        };
+        let question = format!("Install {} formulae{}?", to_install.len(), size);
+        if !super::utils::confirm(&question)? {
+            println!("Installation cancelled");
+            return Ok(finish(&failures));
+        }
     }
 
     // Step 2: Download all bottles in parallel
     println!("Downloading bottles...");
-    let downloaded = download::download_bottles(api, &to_install).await?;
-    let download_map: HashMap<_, _> = downloaded.into_iter().collect();
+    let batch = download::download_bottles(api, &to_install).await;
+    if !batch.failed.is_empty() {
+        report_failures("Failed to download:", &batch.failed);
+    }
+    // Formulae that can't be installed (failed downloads and refused requirements);
+    // their dependents are skipped too
+    let mut unavailable: HashSet<String> = batch.failed.into_iter().map(|(name, _)| name).collect();
+    unavailable.extend(blocked);
+    let download_map: HashMap<_, _> = batch.downloaded.into_iter().collect();
+    ensure_disk_space(download_map.values())?;
 
     // Step 3: Install in dependency order
+    // From here on Ctrl-C finishes or rolls back the current keg instead of exiting mid-way
+    let _interrupt_guard = InterruptGuard::acquire();
     let total_to_install = to_install.len();
     let mut installed_count = 0;
     println!("Installing packages...");
     let requested_set: HashSet<_> = formula_names.iter().map(|s| s.as_str()).collect();
+    // Kegs poured below; a fatal error removes them all so no half-installed tree remains
+    let mut transaction = InstallTransaction::new(cellar::detect_prefix());
 
     for formula_name in &dep_order {
+        if interrupt::interrupted() {
+            break;
+        }
+
         let formula = match all_formulae.get(formula_name.as_str()) {
             Some(f) => f,
             None => continue,
@@ -491,12 +937,31 @@ pub async fn install(
             continue;
         }
 
+        if unavailable.contains(&formula.name) {
+            failures.push(formula.name.clone());
+            continue;
+        }
+        if let Some(dep) = formula
+            .dependencies
+            .iter()
+            .find(|dep| unavailable.contains(*dep))
+        {
+            println!(
+                "  {}: Skipped ({} could not be installed)",
+                formula.name.bold().red(),
+                dep.bold()
+            );
+            unavailable.insert(formula.name.clone());
+            failures.push(formula.name.clone());
+            continue;
+        }
+
         // Get downloaded bottle path
         let bottle_path = match download_map.get(&formula.name) {
             Some(path) => path,
             None => {
                 // No bottle available - fall back to brew for source build
-                match super::utils::fallback_to_brew_with_reason(
+                match super::utils::fallback_to_brew_from_source(
                     "install",
                     &formula.name,
                     Some(&format!(
@@ -505,11 +970,32 @@ pub async fn install(
                     )),
                 ) {
                     Ok(_) => {
-                        // Successfully installed via brew, continue to next package
+                        // brew records every install as requested; fix up the receipt of
+                        // the keg it just built (the version, or a revision of it), not
+                        // whichever version happens to be installed too
+                        let requested = requested_set.contains(formula.name.as_str());
+                        let built = formula.install_version().ok().and_then(|version| {
+                            let revision = format!("{}_", version);
+                            cellar::get_installed_versions(&formula.name)
+                                .ok()?
+                                .into_iter()
+                                .find(|keg| {
+                                    keg.version == version || keg.version.starts_with(&revision)
+                                })
+                        });
+                        if let Some(keg) = built {
+                            if let Err(e) = receipt::InstallReceipt::mark_built_from_source(
+                                &keg.path, requested,
+                            ) {
+                                println!("    Warning: failed to update receipt: {}", e);
+                            }
+                            transaction.record(&formula.name, keg.path);
+                        }
                         continue;
                     }
                     Err(e) => {
                         println!("  {}: Failed to install: {}", formula.name.bold().red(), e);
+                        failures.push(formula.name.clone());
                         continue;
                     }
                 }
@@ -517,11 +1003,7 @@ pub async fn install(
         };
 
         // Determine version
-        let version = formula
-            .versions
-            .stable
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No stable version for {}", formula.name))?;
+        let version = formula.install_version()?;
 
         installed_count += 1;
         println!(
@@ -531,79 +1013,752 @@ pub async fn install(
             total_to_install
         );
 
-        // Extract bottle
-        let extracted_path = extract::extract_bottle(bottle_path, &formula.name, version)?;
-
-        // Get actual installed version (may have bottle revision suffix like 25.1.0_1)
-        let actual_version = extracted_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| {
-                anyhow::anyhow!("Invalid extracted path: {}", extracted_path.display())
-            })?;
-
-        // Relocate bottle (fix install names)
-        crate::relocate::relocate_bottle(&extracted_path, &crate::cellar::detect_prefix())?;
-
-        // Create symlinks (use actual_version which includes bottle revision if present)
-        // Skip linking if formula is keg-only (matches Homebrew behavior)
-        if !formula.keg_only {
-            let linked = symlink::link_formula(&formula.name, actual_version)?;
-            println!("    ├ Linked {} files", linked.len().to_string().dimmed());
-
-            // Create version-agnostic symlinks (opt/ and var/homebrew/linked/)
-            symlink::optlink(&formula.name, actual_version)?;
-        } else {
-            println!(
-                "    ├ {} is keg-only (not linked to prefix)",
-                formula.name.dimmed()
-            );
-        }
-
-        // Generate install receipt
-        let runtime_deps = build_runtime_deps(&formula.dependencies, &all_formulae);
         let is_requested = requested_set.contains(formula.name.as_str());
-        let receipt_data = receipt::InstallReceipt::new_bottle(formula, runtime_deps, is_requested);
-        receipt_data.write(&extracted_path)?;
+        let poured_before = transaction.kegs.len();
+        match pour_bottle(
+            formula,
+            bottle_path,
+            version,
+            &all_formulae,
+            is_requested,
+            dependency_policy,
+            &mut transaction,
+        ) {
+            Ok(true) => {
+                println!(
+                    "    └ Installed {} {}",
+                    formula.name.bold().green(),
+                    version.dimmed()
+                );
+            }
+            Ok(false) => {
+                // Ctrl-C: the unfinished keg is already rolled back, finished ones stay
+                installed_count -= 1;
+                break;
+            }
+            Err(e) => {
+                println!("  {}: Failed to install: {}", formula.name.bold().red(), e);
+                if keep_going {
+                    // Only this keg goes; its dependents are skipped, everything else
+                    // carries on
+                    if transaction.kegs.len() > poured_before {
+                        transaction.undo_last();
+                    }
+                    installed_count -= 1;
+                    unavailable.insert(formula.name.clone());
+                    failures.push(formula.name.clone());
+                    continue;
+                }
+                transaction.rollback();
+                return Err(e.into());
+            }
+        }
+    }
 
+    if interrupt::interrupted() {
         println!(
-            "    └ Installed {} {}",
-            formula.name.bold().green(),
-            version.dimmed()
+            "{} Installed {} of {} packages before Ctrl-C",
+            "Interrupted:".yellow().bold(),
+            installed_count.to_string().bold(),
+            total_to_install
         );
+        return Ok(Outcome::Interrupted);
     }
 
     // Summary
-    let installed_count = to_install.len();
     println!(
         "Installed {} packages",
         installed_count.to_string().bold().green()
     );
 
-    Ok(())
+    Ok(finish(&failures))
 }
 
-pub async fn upgrade(
+/// Install a bottle from a local path or URL without consulting the API
+///
+/// Name, version and platform come from the filename; keg-only status and runtime
+/// dependencies from the formula source the bottle ships. The receipt records no tap.
+async fn install_local_bottle(
     api: &BrewApi,
-    names: &[String],
-    cask: bool,
-    dry_run: bool,
-    force: bool,
-) -> Result<()> {
-    if cask {
-        return super::cask::upgrade_cask(api, names).await;
-    }
-
-    if dry_run {
-        println!(
-            "{} Dry run mode - no packages will be upgraded",
-            " ℹ".blue()
+    source: &str,
+    flags: &InstallFlags,
+) -> anyhow::Result<()> {
+    let filename = source.rsplit('/').next().unwrap_or(source);
+    let bottle = download::BottleFilename::parse(filename)?;
+    let tag = crate::platform::bottle_tag()?;
+    if !crate::platform::bottle_runs_on(&bottle.tag, &tag) {
+        anyhow::bail!(
+            "{} is a bottle for {}, not {} (use --bottle-tag {} to install it anyway)",
+            filename,
+            bottle.tag,
+            tag,
+            bottle.tag
         );
     }
+    if skip_local_install(&bottle.name, &bottle.version, source, flags)? {
+        return Ok(());
+    }
 
-    let formula_names = names;
+    let path = if source.starts_with("https://") || source.starts_with("http://") {
+        let dest = download::cache_dir().join(filename);
+        download::download_verified(api.client(), source, &dest, None).await?
+    } else {
+        let path = std::path::PathBuf::from(source);
+        if !path.is_file() {
+            anyhow::bail!("No such bottle file: {}", source);
+        }
+        path
+    };
 
-    // Determine which formulae to upgrade
+    let formula_source = extract::bottle_formula_source(&path, &bottle.name)?.unwrap_or_default();
+    pour_local_bottle(&path, &bottle.name, &bottle.version, &formula_source, None)
+}
+
+/// Install a formula from a local `.rb` file that isn't in any tap
+async fn install_local_formula(
+    api: &BrewApi,
+    source: &str,
+    flags: &InstallFlags,
+) -> anyhow::Result<()> {
+    let path = std::path::Path::new(source);
+    if !path.is_file() {
+        anyhow::bail!("No such formula file: {}", source);
+    }
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .filter(|name| crate::api::validate_name(name))
+        .ok_or_else(|| anyhow::anyhow!("'{}' is not a valid formula file name", source))?;
+    install_formula_file(api, source, path, name, None, flags).await
+}
+
+/// Install a formula from its Ruby file, a tap's (recorded in the receipt) or a
+/// standalone one given as `source`
+///
+/// The bottle for this platform comes from the formula's `bottle do` block; formulae
+/// without one are built from source by brew.
+async fn install_formula_file(
+    api: &BrewApi,
+    source: &str,
+    path: &std::path::Path,
+    name: &str,
+    tap: Option<&crate::tap::TapName>,
+    flags: &InstallFlags,
+) -> anyhow::Result<()> {
+    let info = crate::tap::parse_formula_info(path, name)?;
+    let problems = info.problems();
+    if !problems.is_empty() {
+        anyhow::bail!("{} can't be installed: {}", source, problems.join(", "));
+    }
+    if info.head_only() {
+        anyhow::bail!(
+            "{} is head-only; install it with `brew install --HEAD {}`",
+            source,
+            source
+        );
+    }
+    // Kegs and bottles are named after the version including its revision
+    let version = info.pkg_version().unwrap_or_default();
+    if skip_local_install(name, &version, source, flags)? {
+        return Ok(());
+    }
+
+    let tag = crate::platform::bottle_tag()?;
+    let bottle = info.bottle.as_ref().and_then(|bottle| {
+        let (tag, sha256) = bottle
+            .sha256
+            .get_key_value(&tag)
+            .or_else(|| bottle.sha256.get_key_value("all"))?;
+        Some((bottle, tag.as_str(), sha256.as_str()))
+    });
+    let Some((bottle, tag, sha256)) = bottle else {
+        super::utils::fallback_to_brew_from_source(
+            "install",
+            source,
+            Some(&format!(
+                "{} has no bottle for {} and must be built from source",
+                name.bold(),
+                tag
+            )),
+        )?;
+        return Ok(());
+    };
+
+    let url = formula_file_bottle_url(name, &version, tag, bottle, sha256);
+    let bottle_path = if url.starts_with("https://ghcr.io/v2/") {
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "versions": {"stable": version, "bottle": true},
+            "bottle": {"stable": {"rebuild": bottle.rebuild, "files": {tag: {
+                "cellar": ":any",
+                "url": url,
+                "sha256": sha256,
+            }}}},
+        }))?;
+        download::download_bottle(&formula, None, api.client()).await?
+    } else {
+        let filename = url.rsplit('/').next().unwrap_or(&url);
+        let dest = download::cache_dir().join(filename);
+        download::download_verified(api.client(), &url, &dest, Some(sha256)).await?
+    };
+
+    let full_name = match tap {
+        Some(tap) => format!("{}/{}", tap, name),
+        None => name.to_string(),
+    };
+    let formula_source = std::fs::read_to_string(path)?;
+    let formula_file = tap.is_none().then_some(path);
+    pour_local_bottle(
+        &bottle_path,
+        &full_name,
+        &version,
+        &formula_source,
+        formula_file,
+    )
+}
+
+/// Where a formula file's bottle for `tag` is downloaded from, the way brew builds it
+///
+/// Bottles on GHCR are blobs addressed by checksum, in an image named after the formula
+/// (`@` becomes `/`, `+` becomes `x`). Under any other `root_url` they're plain files
+/// named `<name>--<pkg_version>.<tag>.bottle[.<rebuild>].tar.gz`.
+fn formula_file_bottle_url(
+    name: &str,
+    pkg_version: &str,
+    tag: &str,
+    bottle: &crate::tap::TapBottleInfo,
+    sha256: &str,
+) -> String {
+    let root_url = bottle
+        .root_url
+        .as_deref()
+        .unwrap_or("https://ghcr.io/v2/homebrew/core")
+        .trim_end_matches('/');
+    if root_url.starts_with("https://ghcr.io/v2/") {
+        let image = name.replace('@', "/").replace('+', "x");
+        return format!("{}/{}/blobs/sha256:{}", root_url, image, sha256);
+    }
+
+    let rebuild = match bottle.rebuild {
+        0 => String::new(),
+        rebuild => format!(".{}", rebuild),
+    };
+    format!(
+        "{}/{}--{}.{}.bottle{}.tar.gz",
+        root_url, name, pkg_version, tag, rebuild
+    )
+}
+
+/// Install what a formula from a locally cloned tap depends on, before its bottle is
+/// poured
+///
+/// Dependencies are resolved through the tap's Ruby files and the API. Missing core
+/// formulae go through a regular install; missing formulae from taps are installed
+/// from their Ruby files, dependencies first.
+async fn install_tap_dependencies(
+    api: &BrewApi,
+    tap_formula: &str,
+    flags: &InstallFlags,
+) -> Result<()> {
+    let source = crate::tap::TapSource::new(api);
+    let (formulae, order) = resolve_dependencies(
+        &source,
+        &[tap_formula.to_string()],
+        &flags.dependency_policy,
+    )
+    .await?;
+
+    let root = tap_formula.rsplit('/').next().unwrap_or(tap_formula);
+    let mut core = Vec::new();
+    let mut tapped = Vec::new();
+    for formula in order
+        .iter()
+        .filter(|name| name.as_str() != root)
+        .filter_map(|name| formulae.get(name))
+    {
+        if !cellar::get_installed_versions(&formula.name)?.is_empty() {
+            continue;
+        }
+        match crate::tap::split_qualified(&formula.full_name) {
+            Some((tap, name)) if !tap.is_core() => tapped.push((tap, name)),
+            _ => core.push(formula.name.clone()),
+        }
+    }
+
+    if !core.is_empty() {
+        // Boxed: install is what called us
+        let flags = InstallFlags {
+            keep_going: false,
+            ..flags.clone()
+        };
+        let outcome = Box::pin(install(api, &core, &flags)).await?;
+        if outcome != Outcome::Done {
+            return Err(
+                anyhow::anyhow!("Failed to install dependencies of {}", tap_formula).into(),
+            );
+        }
+    }
+
+    for (tap, name) in tapped {
+        let full_name = format!("{}/{}", tap, name);
+        let path = crate::tap::formula_file_in(&cellar::detect_prefix(), &tap, name)
+            .ok_or_else(|| crate::error::BruError::FormulaNotFound(full_name.clone()))?;
+        install_formula_file(api, &full_name, &path, name, Some(&tap), flags).await?;
+    }
+    Ok(())
+}
+
+/// Whether a local bottle or formula file needs no further work: the formula is
+/// already installed (and this isn't `--force`), or this is a dry run (both reported
+/// here)
+fn skip_local_install(
+    name: &str,
+    version: &str,
+    source: &str,
+    flags: &InstallFlags,
+) -> anyhow::Result<bool> {
+    if !flags.force
+        && let Some(keg) = cellar::get_installed_versions(name)?.first()
+    {
+        println!(
+            "  {} {} is already installed",
+            name.cyan(),
+            keg.version.dimmed()
+        );
+        return Ok(true);
+    }
+    if flags.dry_run {
+        println!(
+            "  Would install {} {} from {}",
+            name.cyan(),
+            version.dimmed(),
+            source
+        );
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// Pour a bottle that didn't come from the API
+///
+/// Keg-only status and runtime dependencies are read from the formula's Ruby source.
+/// Dependencies aren't installed, only reported when missing. Unless `full_name` is
+/// tap-qualified, the receipt records no tap, and `formula_file` as the source if the
+/// bottle came from a standalone formula file.
+fn pour_local_bottle(
+    bottle_path: &std::path::Path,
+    full_name: &str,
+    version: &str,
+    formula_source: &str,
+    formula_file: Option<&std::path::Path>,
+) -> anyhow::Result<()> {
+    ensure_disk_space([&bottle_path.to_path_buf()])?;
+
+    let name = full_name.rsplit('/').next().unwrap_or(full_name);
+
+    let dependencies = crate::tap::runtime_dependencies(formula_source);
+    let keg_only = formula_source
+        .lines()
+        .any(|line| line.trim_start().starts_with("keg_only"));
+    let formula: Formula = serde_json::from_value(serde_json::json!({
+        "name": name,
+        "full_name": full_name,
+        "versions": {"stable": version, "bottle": true},
+        "dependencies": dependencies,
+        "keg_only": keg_only,
+    }))?;
+
+    // Installed dependencies go into the receipt like they would for an API install
+    let mut installed_deps = HashMap::new();
+    let mut missing = Vec::new();
+    for dep in &formula.dependencies {
+        let short_name = dep.rsplit('/').next().unwrap_or(dep);
+        match cellar::get_installed_versions(short_name)?
+            .into_iter()
+            .next()
+        {
+            Some(keg) => {
+                let dep_formula: Formula = serde_json::from_value(serde_json::json!({
+                    "name": dep,
+                    "versions": {"stable": keg.version},
+                }))?;
+                installed_deps.insert(dep.clone(), dep_formula);
+            }
+            None => missing.push(dep.as_str()),
+        }
+    }
+
+    println!(
+        "  Installing {} {} from {}...",
+        name.cyan(),
+        version.dimmed(),
+        bottle_path.display().to_string().dimmed()
+    );
+    let _interrupt_guard = InterruptGuard::acquire();
+    let mut transaction = InstallTransaction::new(cellar::detect_prefix());
+    match pour_bottle(
+        &formula,
+        bottle_path,
+        version,
+        &installed_deps,
+        true,
+        &DependencyPolicy::default(),
+        &mut transaction,
+    ) {
+        Ok(true) => {
+            if !full_name.contains('/')
+                && let Some((_, keg)) = transaction.kegs.last()
+                && let Err(e) = receipt::InstallReceipt::mark_untapped(keg, formula_file)
+            {
+                transaction.rollback();
+                return Err(e);
+            }
+            println!(
+                "    └ Installed {} {}",
+                name.bold().green(),
+                version.dimmed()
+            );
+        }
+        Ok(false) => anyhow::bail!("Interrupted"),
+        Err(e) => {
+            transaction.rollback();
+            return Err(e);
+        }
+    }
+
+    if !missing.is_empty() {
+        println!(
+            "  {} {} needs {}, which {} not installed",
+            "Warning:".yellow(),
+            name,
+            missing.join(", ").bold(),
+            if missing.len() == 1 { "is" } else { "are" }
+        );
+    }
+    Ok(())
+}
+
+/// Extract, relocate, link and write the receipt for one bottle
+///
+/// The keg is recorded in `transaction` as soon as it exists, so a caller that gets an
+/// error can roll it back with the rest of the run. Returns `Ok(false)` if Ctrl-C
+/// arrived mid-way; the unfinished keg has then already been rolled back.
+fn pour_bottle(
+    formula: &Formula,
+    bottle_path: &std::path::Path,
+    version: &str,
+    all_formulae: &HashMap<String, Formula>,
+    is_requested: bool,
+    dependency_policy: &DependencyPolicy,
+    transaction: &mut InstallTransaction,
+) -> anyhow::Result<bool> {
+    let prefix = transaction.prefix.clone();
+    let cellar = prefix.join("Cellar");
+
+    // Extract bottle
+    let (extracted_path, manifest) =
+        extract::extract_bottle_with_manifest_in(&cellar, bottle_path, &formula.name, version)
+            .inspect_err(|_| {
+                // The formula had no keg before this run, so whatever is there now is a
+                // partial unpack
+                let _ = std::fs::remove_dir_all(cellar.join(&formula.name));
+                let _ = std::fs::remove_dir_all(cellar.join(format!(".{}.extract", formula.name)));
+            })?;
+    transaction.record(&formula.name, extracted_path.clone());
+    if interrupt::interrupted() {
+        transaction.undo_last();
+        return Ok(false);
+    }
+
+    // Get actual installed version (may have bottle revision suffix like 25.1.0_1)
+    let actual_version = extracted_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid extracted path: {}", extracted_path.display()))?;
+
+    // Relocate bottle (fix install names)
+    crate::relocate::relocate_bottle_with_manifest(&extracted_path, &prefix, &manifest)?;
+    if interrupt::interrupted() {
+        transaction.undo_last();
+        return Ok(false);
+    }
+
+    // Create symlinks (use actual_version which includes bottle revision if present)
+    // Skip linking if formula is keg-only (matches Homebrew behavior)
+    if !formula.keg_only {
+        let linked = symlink::link_formula_with_manifest_in(
+            &prefix,
+            &formula.name,
+            actual_version,
+            &manifest,
+        )?;
+        println!("    ├ Linked {} files", linked.len().to_string().dimmed());
+
+        // Create version-agnostic symlinks (opt/ and var/homebrew/linked/)
+        symlink::optlink_in(&prefix, &formula.name, actual_version)?;
+    } else {
+        println!(
+            "    ├ {} is keg-only (not linked to prefix)",
+            formula.name.dimmed()
+        );
+        symlink::install_config_files_in(&prefix, &formula.name, actual_version)?;
+        let hints = cellar::build_env_hints_in(&prefix, &formula.name, actual_version);
+        for line in hints.to_string().lines() {
+            println!("    │ {}", line);
+        }
+    }
+    if interrupt::interrupted() {
+        transaction.undo_last();
+        return Ok(false);
+    }
+
+    // Generate install receipt
+    let runtime_deps = build_runtime_deps(&formula.dependencies, all_formulae);
+    let mut receipt_data = receipt::InstallReceipt::new_bottle(formula, runtime_deps, is_requested);
+    (receipt_data.used_options, receipt_data.unused_options) = dependency_policy.options(formula);
+    receipt_data.write(&extracted_path)?;
+
+    Ok(true)
+}
+
+/// Kegs poured by one `install` run, in install order
+///
+/// Only formulae that had no keg before the run are poured, so rolling back restores
+/// the Cellar and prefix to how they were before `install` started.
+struct InstallTransaction {
+    prefix: std::path::PathBuf,
+    kegs: Vec<(String, std::path::PathBuf)>,
+}
+
+impl InstallTransaction {
+    fn new(prefix: std::path::PathBuf) -> Self {
+        Self {
+            prefix,
+            kegs: Vec::new(),
+        }
+    }
+
+    /// Remember a keg this run created
+    fn record(&mut self, formula_name: &str, keg: std::path::PathBuf) {
+        self.kegs.push((formula_name.to_string(), keg));
+    }
+
+    /// Roll back only the most recently recorded keg (an install cut short by Ctrl-C)
+    fn undo_last(&mut self) {
+        if let Some((formula_name, keg)) = self.kegs.pop() {
+            rollback_keg(&self.prefix, &formula_name, &keg);
+        }
+    }
+
+    /// Roll back every keg this run created, dependents before their dependencies
+    fn rollback(mut self) {
+        if self.kegs.is_empty() {
+            return;
+        }
+        println!(
+            "Rolling back {} packages installed by this run...",
+            self.kegs.len().to_string().bold()
+        );
+        while !self.kegs.is_empty() {
+            self.undo_last();
+        }
+    }
+}
+
+/// Undo a keg that didn't finish installing (or whose install run failed): remove its
+/// links, its `opt/` record and the keg itself
+fn rollback_keg(prefix: &std::path::Path, formula_name: &str, keg: &std::path::Path) {
+    println!("    └ Rolling back {}", formula_name.yellow());
+    if let Some(version) = keg.file_name().and_then(|n| n.to_str()) {
+        let _ = symlink::unlink_formula_in(prefix, formula_name, version);
+    }
+    let _ = symlink::unoptlink_in(prefix, formula_name);
+    let _ = std::fs::remove_dir_all(keg);
+    // Only succeeds once no other version is left
+    if let Some(formula_dir) = keg.parent() {
+        let _ = std::fs::remove_dir(formula_dir);
+    }
+}
+
+/// Split the dependencies of the newest `names` into installed and missing ones
+///
+/// Both lists are in install order, dependencies first, and leave out `names`
+/// themselves. Installed dependencies may still be current; the caller checks them
+/// like any other upgrade candidate. Missing ones are usually new dependencies of the
+/// new version.
+async fn upgrade_dependencies(
+    source: &dyn FormulaSource,
+    cellar: &std::path::Path,
+    names: &[String],
+    policy: &DependencyPolicy,
+) -> Result<(Vec<String>, Vec<String>)> {
+    let roots: HashSet<String> = names
+        .iter()
+        .map(|name| crate::tap::extract_formula_name(name))
+        .collect();
+    let (_, order) = resolve_dependency_graph(source, names, policy).await?;
+
+    Ok(order
+        .into_iter()
+        .filter(|name| !roots.contains(name))
+        .partition(|name| {
+            cellar::get_installed_versions_in(cellar, name).is_ok_and(|v| !v.is_empty())
+        }))
+}
+
+/// The `--with`/`--without` selection `names` were installed with, from the
+/// `used_options` in their receipts
+///
+/// The options of all of `names` are merged, as if they were installed together.
+fn recorded_dependency_policy(cellar: &std::path::Path, names: &[String]) -> DependencyPolicy {
+    let mut policy = DependencyPolicy::default();
+    for name in names {
+        let name = crate::tap::extract_formula_name(name);
+        if let Ok(versions) = cellar::get_installed_versions_in(cellar, &name)
+            && let Some(newest) = versions.first()
+            && let Ok(receipt) = receipt::InstallReceipt::read(&newest.path)
+        {
+            policy.add_options(&receipt.used_options);
+        }
+    }
+    policy
+}
+
+/// Installed formulae whose receipts list any of `upgraded` as a runtime dependency
+///
+/// These were linked against the old kegs, so `upgrade --dependents` reinstalls them.
+/// The result is sorted and leaves out `upgraded` themselves.
+fn installed_dependents(packages: &[cellar::InstalledPackage], upgraded: &[String]) -> Vec<String> {
+    let mut dependents: Vec<String> = packages
+        .iter()
+        .filter(|pkg| !upgraded.contains(&pkg.name))
+        .filter(|pkg| {
+            pkg.runtime_dependencies()
+                .iter()
+                .any(|dep| upgraded.contains(&dep.full_name))
+        })
+        .map(|pkg| pkg.name.clone())
+        .collect();
+    dependents.sort();
+    dependents.dedup();
+    dependents
+}
+
+/// Find which of `names` have a newer homebrew/core version than the installed keg
+///
+/// Formulae that aren't installed, come from a tap, or are already current are left
+/// out. Only metadata is fetched; nothing is downloaded.
+async fn collect_upgrade_candidates(api: &BrewApi, names: &[String]) -> Vec<UpgradeCandidate> {
+    let fetch_futures: Vec<_> = names
+        .iter()
+        .map(|formula_name| async move {
+            // Extract actual formula name (strip tap prefix if present)
+            let pkg_name = crate::tap::extract_formula_name(formula_name);
+
+            // Check if installed
+            let installed_versions = cellar::get_installed_versions(&pkg_name).ok()?;
+            if installed_versions.is_empty() {
+                return None; // Will install separately
+            }
+
+            // Use the linked version as the "old" version (matches Homebrew's linked_keg behavior)
+            // This correctly handles interrupted upgrades where multiple versions may exist
+            let old_version = if let Ok(Some(linked_ver)) = symlink::get_linked_version(&pkg_name) {
+                // Find the matching installed version to get its path
+                let matching = installed_versions.iter().find(|v| v.version == linked_ver);
+                if let Some(ver) = matching {
+                    ver.version.clone()
+                } else {
+                    // Linked version doesn't exist in Cellar (broken state), use newest
+                    installed_versions[0].version.clone()
+                }
+            } else {
+                // Not linked, use newest version
+                installed_versions[0].version.clone()
+            };
+
+            let cellar_path = installed_versions
+                .iter()
+                .find(|v| v.version == old_version)
+                .map(|v| &v.path)
+                .unwrap_or(&installed_versions[0].path);
+
+            // Check if this is a tap formula - if so, we'll upgrade via brew
+            if let Ok(Some(_tap_info)) = crate::tap::get_package_tap_info(cellar_path) {
+                // For tap formulae, fall back to brew since we don't have bottles
+                return None;
+            }
+
+            let from_source =
+                receipt::InstallReceipt::read(cellar_path).is_ok_and(|r| r.built_from_source());
+
+            // Fetch latest version from API (homebrew/core only)
+            let formula = api.fetch_formula(formula_name).await.ok()?;
+            let new_version = formula.versions.stable.as_ref()?.clone();
+
+            // Compare full versions INCLUDING bottle revisions
+            // A version change from 1.76.0 to 1.76.0_1 IS an upgrade
+            if old_version == new_version {
+                return None; // Already at latest version
+            }
+
+            Some(UpgradeCandidate {
+                name: formula_name.clone(),
+                old_version,
+                formula,
+                from_source,
+            })
+        })
+        .collect();
+
+    futures::future::join_all(fetch_futures)
+        .await
+        .into_iter()
+        .flatten()
+        .collect()
+}
+
+/// Upgrade outdated formulae (or just `names`), pouring new bottles in parallel
+///
+/// With `dry_run` and `json` (main only allows `--json` with `--dry-run`), prints
+/// `[{"name", "old_version", "new_version", "pinned"}]` for every formula that has a
+/// newer version, without downloading anything. Dependencies the new versions would
+/// install are listed too, with a null `old_version`.
+///
+/// Upgrading named formulae also upgrades their outdated dependencies and installs
+/// the ones the new versions added, unless `ignore_dependencies` is set. With
+/// `dependents`, installed formulae that depend on an upgraded one are reinstalled
+/// afterwards so they link against the new version.
+#[allow(clippy::too_many_arguments)]
+pub async fn upgrade(
+    api: &BrewApi,
+    names: &[String],
+    cask: bool,
+    dry_run: bool,
+    force: bool,
+    json: bool,
+    keep_going: bool,
+    ignore_dependencies: bool,
+    dependents: bool,
+) -> Result<Outcome> {
+    if cask {
+        return super::cask::upgrade_cask(api, names)
+            .await
+            .map(|()| Outcome::Done);
+    }
+
+    if dry_run && !json {
+        println!(
+            "{} Dry run mode - no packages will be upgraded",
+            " ℹ".blue()
+        );
+    }
+
+    let formula_names = names;
+    // Dependencies the upgrade installs for the first time
+    let mut new_dependencies: Vec<String> = Vec::new();
+
+    // Determine which formulae to upgrade
     let to_upgrade = if formula_names.is_empty() {
         // Upgrade all outdated
         use indicatif::{ProgressBar, ProgressStyle};
@@ -616,32 +1771,8 @@ pub async fn upgrade(
         spinner.set_message("Checking for outdated packages...");
         spinner.enable_steady_tick(std::time::Duration::from_millis(100));
 
-        let all_packages = cellar::list_installed()?;
-
-        // Deduplicate multiple versions - keep only the most recent for each formula
-        let estimated_capacity = all_packages.len() / 2; // ~50% typical dedup rate
-        let mut package_map: std::collections::HashMap<String, cellar::InstalledPackage> =
-            std::collections::HashMap::with_capacity(estimated_capacity);
-
-        for pkg in all_packages {
-            package_map
-                .entry(pkg.name.clone())
-                .and_modify(|existing| {
-                    // Compare modification times - keep the more recent one
-                    if let (Ok(existing_meta), Ok(pkg_meta)) = (
-                        std::fs::metadata(&existing.path),
-                        std::fs::metadata(&pkg.path),
-                    ) && let (Ok(existing_time), Ok(pkg_time)) =
-                        (existing_meta.modified(), pkg_meta.modified())
-                        && pkg_time > existing_time
-                    {
-                        *existing = pkg.clone();
-                    }
-                })
-                .or_insert(pkg);
-        }
-
-        let packages: Vec<_> = package_map.into_values().collect();
+        // Keep only the current (highest) version of each formula
+        let packages = cellar::list_current()?;
 
         // Fetch all formulae in parallel for better performance
         // Handle both homebrew/core (via API) and tap formulae (via file reading)
@@ -702,95 +1833,138 @@ pub async fn upgrade(
 
         spinner.finish_and_clear();
 
-        if outdated.is_empty() {
+        if json {
+            outdated
+        } else if outdated.is_empty() {
             println!("{}", "All packages are up to date".green());
-            return Ok(());
+            return Ok(Outcome::Done);
+        } else {
+            println!(
+                "Found {} outdated packages: {}",
+                outdated.len().to_string().bold(),
+                outdated.join(", ").cyan()
+            );
+            outdated
         }
-
-        println!(
-            "Found {} outdated packages: {}",
-            outdated.len().to_string().bold(),
-            outdated.join(", ").cyan()
-        );
-        outdated
-    } else {
+    } else if ignore_dependencies {
         formula_names.to_vec()
+    } else {
+        // The new versions may depend on newer (or new) formulae, so bring the
+        // dependency closure along as `brew upgrade` does. Names that aren't
+        // installed are left to the usual checks below rather than installed here
+        let installed: Vec<String> = formula_names
+            .iter()
+            .filter(|name| {
+                cellar::get_installed_versions(&crate::tap::extract_formula_name(name))
+                    .is_ok_and(|v| !v.is_empty())
+            })
+            .cloned()
+            .collect();
+        let policy = recorded_dependency_policy(&cellar::cellar_path(), &installed);
+        // Tap formulae resolve through their tap's Ruby files, under the tap their
+        // receipt records
+        let qualified: Vec<String> = installed
+            .iter()
+            .map(|name| {
+                cellar::get_installed_versions(name)
+                    .ok()
+                    .and_then(|versions| versions.into_iter().next())
+                    .and_then(|keg| crate::tap::get_package_tap_info(&keg.path).ok().flatten())
+                    .map_or_else(|| name.clone(), |(tap, _, _)| format!("{}/{}", tap, name))
+            })
+            .collect();
+        let source = crate::tap::TapSource::new(api);
+        let (installed_deps, missing_deps) =
+            upgrade_dependencies(&source, &cellar::cellar_path(), &qualified, &policy).await?;
+
+        if !missing_deps.is_empty() && !json {
+            println!(
+                "{} {} new dependencies: {}",
+                if dry_run {
+                    "Would install"
+                } else {
+                    "Installing"
+                },
+                missing_deps.len().to_string().bold(),
+                missing_deps.join(", ").cyan()
+            );
+            if !dry_run {
+                // Installing the named formulae pours only what's missing, and records
+                // it as installed as a dependency
+                let flags = InstallFlags {
+                    dependency_policy: policy,
+                    ..InstallFlags::unattended(keep_going)
+                };
+                install(api, &installed, &flags).await?;
+            }
+        }
+        new_dependencies = missing_deps;
+
+        installed_deps
+            .into_iter()
+            .chain(formula_names.iter().cloned())
+            .collect()
     };
 
+    // Check for pinned formulae
+    let pinned = super::utils::read_pinned(false)?;
+
+    if dry_run && json {
+        #[derive(serde::Serialize)]
+        struct PlannedUpgrade<'a> {
+            name: &'a str,
+            old_version: Option<&'a str>,
+            new_version: &'a str,
+            pinned: bool,
+        }
+
+        let mut candidates = collect_upgrade_candidates(api, &to_upgrade).await;
+        candidates.sort_by(|a, b| a.name.cmp(&b.name));
+        let mut additions = Vec::with_capacity(new_dependencies.len());
+        for name in &new_dependencies {
+            additions.push(api.fetch_formula(name).await?);
+        }
+
+        let mut planned: Vec<_> = candidates
+            .iter()
+            .filter_map(|c| {
+                Some(PlannedUpgrade {
+                    name: &c.name,
+                    old_version: Some(&c.old_version),
+                    new_version: c.formula.versions.stable.as_deref()?,
+                    pinned: pinned.contains(&c.name),
+                })
+            })
+            .collect();
+        planned.extend(additions.iter().filter_map(|formula| {
+            Some(PlannedUpgrade {
+                name: &formula.name,
+                old_version: None,
+                new_version: formula.versions.stable.as_deref()?,
+                pinned: false,
+            })
+        }));
+        crate::output::print_json(&planned)?;
+        return Ok(Outcome::Done);
+    }
+
     // If dry-run, stop after showing what would be upgraded
     if dry_run {
         println!("{}", "Dry run complete - no packages were upgraded".green());
-        return Ok(());
+        return Ok(Outcome::Done);
     }
 
-    // Check for pinned formulae
-    let pinned = read_pinned()?;
-
     // Phase 1: Collect all upgrade candidates in parallel
-    let fetch_futures: Vec<_> = to_upgrade
+    let unpinned: Vec<String> = to_upgrade
         .iter()
         .filter(|name| !pinned.contains(*name))
-        .map(|formula_name| async move {
-            // Extract actual formula name (strip tap prefix if present)
-            let pkg_name = crate::tap::extract_formula_name(formula_name);
-
-            // Check if installed
-            let installed_versions = cellar::get_installed_versions(&pkg_name).ok()?;
-            if installed_versions.is_empty() {
-                return None; // Will install separately
-            }
-
-            // Use the linked version as the "old" version (matches Homebrew's linked_keg behavior)
-            // This correctly handles interrupted upgrades where multiple versions may exist
-            let old_version = if let Ok(Some(linked_ver)) = symlink::get_linked_version(&pkg_name) {
-                // Find the matching installed version to get its path
-                let matching = installed_versions.iter().find(|v| v.version == linked_ver);
-                if let Some(ver) = matching {
-                    ver.version.clone()
-                } else {
-                    // Linked version doesn't exist in Cellar (broken state), use newest
-                    installed_versions[0].version.clone()
-                }
-            } else {
-                // Not linked, use newest version
-                installed_versions[0].version.clone()
-            };
-
-            let cellar_path = installed_versions
-                .iter()
-                .find(|v| v.version == old_version)
-                .map(|v| &v.path)
-                .unwrap_or(&installed_versions[0].path);
-
-            // Check if this is a tap formula - if so, we'll upgrade via brew
-            if let Ok(Some(_tap_info)) = crate::tap::get_package_tap_info(cellar_path) {
-                // For tap formulae, fall back to brew since we don't have bottles
-                return None;
-            }
-
-            // Fetch latest version from API (homebrew/core only)
-            let formula = api.fetch_formula(formula_name).await.ok()?;
-            let new_version = formula.versions.stable.as_ref()?.clone();
-
-            // Compare full versions INCLUDING bottle revisions
-            // A version change from 1.76.0 to 1.76.0_1 IS an upgrade
-            if old_version == new_version {
-                return None; // Already at latest version
-            }
-
-            Some(UpgradeCandidate {
-                name: formula_name.clone(),
-                old_version,
-                formula,
-            })
-        })
-        .collect();
-
-    let candidates: Vec<_> = futures::future::join_all(fetch_futures)
-        .await
-        .into_iter()
-        .flatten()
+        .cloned()
         .collect();
+    let mut candidates = collect_upgrade_candidates(api, &unpinned).await;
+    // Formulae that didn't upgrade, listed at the end under --keep-going
+    let mut failures: Vec<String> = Vec::new();
+    // Formulae that did, whose dependents --dependents reinstalls
+    let mut upgraded: Vec<String> = Vec::new();
 
     // Separate out tap packages that need to be upgraded via brew
     // Store as (formula_name, tap_name) so we can construct full tap/formula name
@@ -815,7 +1989,7 @@ pub async fn upgrade(
 
     if !have_updates && !names.is_empty() {
         println!("{}", "All packages are up to date".green());
-        return Ok(());
+        return Ok(Outcome::Done);
     }
 
     if have_updates {
@@ -840,32 +2014,52 @@ pub async fn upgrade(
         // Phase 2: Resolve dependencies for all candidates to build complete formula map
         // This is critical for generating correct receipts with runtime_dependencies
         let candidate_names: Vec<String> = candidates.iter().map(|c| c.name.clone()).collect();
-        let (all_formulae, _) = if !candidate_names.is_empty() {
-            resolve_dependencies(api, &candidate_names).await?
-        } else {
+        // Keep the --with/--without selection the old kegs were installed with
+        let policy = recorded_dependency_policy(&cellar::cellar_path(), &candidate_names);
+        let (all_formulae, _) = if candidate_names.is_empty() {
             (HashMap::new(), vec![])
+        } else if keep_going {
+            let (all_formulae, order, unresolved) =
+                resolve_dependencies_each(api, &candidate_names, &policy).await;
+            for (name, err) in unresolved {
+                println!("  {}: {}", name.bold().red(), err);
+                candidates.retain(|c| c.name != name);
+                failures.push(name);
+            }
+            (all_formulae, order)
+        } else {
+            resolve_dependencies(api, &candidate_names, &policy).await?
         };
+        for candidate in &mut candidates {
+            policy.apply(&mut candidate.formula);
+        }
 
         // Phase 3: Download all bottles in parallel
-        println!("Downloading {} bottles...", candidates.len());
-        let formulae: Vec<_> = candidates.iter().map(|c| c.formula.clone()).collect();
-        let downloaded = download::download_bottles(api, &formulae).await?;
-        let download_map: HashMap<_, _> = downloaded.into_iter().collect();
+        let formulae: Vec<_> = candidates
+            .iter()
+            .filter(|c| !c.from_source)
+            .map(|c| c.formula.clone())
+            .collect();
+        println!("Downloading {} bottles...", formulae.len());
+        let batch = download::download_bottles(api, &formulae).await;
+        if !batch.failed.is_empty() {
+            report_failures("Failed to download:", &batch.failed);
+        }
+        let failed_downloads: HashSet<_> = batch.failed.iter().map(|(name, _)| name).collect();
+        failures.extend(failed_downloads.iter().map(|name| name.to_string()));
+        let download_map: HashMap<_, _> = batch.downloaded.into_iter().collect();
+        ensure_disk_space(download_map.values())?;
 
         // Phase 4: Install with parallel extraction/relocation, sequential linking
-        // Separate packages that need fallback (no bottles) from those with bottles
+        // Separate packages that need fallback (no bottles, or built from source) from
+        // those with bottles; packages whose bottle failed to download are left at their
+        // current version
         let (with_bottles, without_bottles): (Vec<_>, Vec<_>) = candidates
             .iter()
-            .partition(|c| download_map.contains_key(&c.name));
+            .filter(|c| !failed_downloads.contains(&c.name))
+            .partition(|c| !c.from_source && download_map.contains_key(&c.name));
 
-        // Result type for parallel phase (extract + relocate only)
-        struct ExtractedPackage {
-            name: String,
-            old_version: String,
-            new_version: String,
-            extracted_path: std::path::PathBuf,
-            formula: Formula,
-        }
+        let prefix = cellar::detect_prefix();
 
         // Progress bar for parallel extraction/relocation (I/O and CPU bound)
         let progress = ProgressBar::new(with_bottles.len() as u64);
@@ -879,196 +2073,86 @@ pub async fn upgrade(
 
         let completed = AtomicUsize::new(0);
 
-        // PARALLEL PHASE: Extract and relocate bottles (safe - unique directories)
-        let extraction_results: Vec<std::result::Result<ExtractedPackage, String>> = with_bottles
+        // PARALLEL PHASE: Pour the new kegs beside the old ones (safe - unique directories)
+        let poured: Vec<_> = with_bottles
             .par_iter()
             .map(|candidate| {
-                let formula_name = &candidate.name;
-                let old_version = &candidate.old_version;
-                let formula = &candidate.formula;
-                let new_version = match formula.versions.stable.as_ref() {
-                    Some(v) => v.clone(),
-                    None => return Err(format!("{}: no stable version", formula_name)),
-                };
-
-                let bottle_path = download_map.get(formula_name).unwrap();
-
-                // Extract new version (I/O bound - benefits from parallelism)
-                let extracted_path =
-                    match extract::extract_bottle(bottle_path, formula_name, &new_version) {
-                        Ok(path) => path,
-                        Err(e) => {
-                            return Err(format!("{}: failed to extract: {}", formula_name, e));
-                        }
-                    };
-
-                // Get actual installed version (may have bottle revision suffix like 25.1.0_1)
-                let actual_new_version = match extracted_path.file_name().and_then(|n| n.to_str()) {
-                    Some(v) => v.to_string(),
-                    None => {
-                        return Err(format!(
-                            "{}: invalid extracted path: {}",
-                            formula_name,
-                            extracted_path.display()
-                        ));
-                    }
-                };
-
-                // Relocate bottle (CPU bound - benefits from parallelism)
-                if let Err(e) = crate::relocate::relocate_bottle(
-                    &extracted_path,
-                    &crate::cellar::detect_prefix(),
-                ) {
-                    // Clean up extracted bottle to avoid orphans in Cellar
-                    let _ = std::fs::remove_dir_all(&extracted_path);
-                    return Err(format!("{}: failed to relocate: {}", formula_name, e));
-                }
+                let bottle_path = download_map.get(&candidate.name).unwrap();
+                let keg = crate::keg::pour_keg_in(&prefix, &candidate.formula, bottle_path);
 
                 // Update progress (Relaxed ordering is sufficient - this is only for UI updates
                 // and doesn't require synchronization with other memory operations)
                 let done = completed.fetch_add(1, Ordering::Relaxed) + 1;
                 progress.set_position(done as u64);
-
-                Ok(ExtractedPackage {
-                    name: formula_name.clone(),
-                    old_version: old_version.clone(),
-                    new_version: actual_new_version,
-                    extracted_path,
-                    formula: formula.clone(),
-                })
+                (*candidate, keg)
             })
             .collect();
 
         progress.finish_with_message(format!("Extracted {} bottles", with_bottles.len()));
 
-        // SEQUENTIAL PHASE: Link and cleanup (touches shared directories - no race conditions)
+        // SEQUENTIAL PHASE: Swap the kegs (touches shared directories - no race conditions)
         println!("Linking packages...");
         let mut successful_upgrades = 0;
 
-        for result in extraction_results {
-            match result {
-                Ok(pkg) => {
-                    // Unlink old version (sequential - touches shared /opt/homebrew/bin/)
-                    if let Err(e) = symlink::unlink_formula(&pkg.name, &pkg.old_version) {
-                        println!(
-                            "  {}: failed to unlink old version symlink: {}",
-                            pkg.name.bold().red(),
-                            e
-                        );
-                    }
-
-                    // Create symlinks (sequential - touches shared directories)
-                    let mut linking_failed = false;
-                    let linked_count = if !pkg.formula.keg_only {
-                        let linked = match symlink::link_formula(&pkg.name, &pkg.new_version) {
-                            Ok(l) => l,
-                            Err(e) => {
-                                println!("  {}: failed to link: {}", pkg.name.bold().red(), e);
-                                continue;
-                            }
-                        };
-
-                        // Create version-agnostic symlinks (opt/ and var/homebrew/linked/)
-                        if let Err(e) = symlink::optlink(&pkg.name, &pkg.new_version) {
-                            println!(
-                                "  {}: failed to create opt link: {}",
-                                pkg.name.bold().red(),
-                                e
-                            );
-                            linking_failed = true;
-                        }
-
-                        linked.len()
-                    } else {
-                        0
-                    };
-
-                    // Generate receipt
-                    let runtime_deps = build_runtime_deps(&pkg.formula.dependencies, &all_formulae);
-
-                    // Read old receipt to preserve installed_on_request status
-                    let old_path = cellar::cellar_path().join(&pkg.name).join(&pkg.old_version);
-                    let installed_on_request =
-                        if let Ok(old_receipt) = receipt::InstallReceipt::read(&old_path) {
-                            old_receipt.installed_on_request
-                        } else {
-                            true
-                        };
-
-                    let receipt_data = receipt::InstallReceipt::new_bottle(
-                        &pkg.formula,
-                        runtime_deps,
-                        installed_on_request,
-                    );
-                    let mut receipt_failed = false;
-                    if let Err(e) = receipt_data.write(&pkg.extracted_path) {
-                        println!(
-                            "  {}: failed to write receipt: {}",
-                            pkg.name.bold().red(),
-                            e
-                        );
-                        receipt_failed = true;
-                    }
-
-                    // Remove old version directory (always cleanup to avoid inconsistent state)
-                    let old_removed = if old_path.exists() {
-                        match std::fs::remove_dir_all(&old_path) {
-                            Ok(_) => true,
-                            Err(e) => {
-                                println!(
-                                    "  {}: failed to remove old version: {}",
-                                    pkg.name.bold().red(),
-                                    e
-                                );
-                                // Continue anyway - new version is installed
-                                false
-                            }
-                        }
-                    } else {
-                        false
-                    };
-
-                    // Report success
-                    if linked_count > 0 {
-                        println!("    ├ Linked {} files", linked_count.to_string().dimmed());
-                    }
-                    if pkg.formula.keg_only {
-                        println!(
-                            "    ├ {} is keg-only (not linked to prefix)",
-                            pkg.name.dimmed()
-                        );
-                    }
-                    if old_removed {
-                        println!("    ├ Removed old version {}", pkg.old_version.dimmed());
-                    }
-
-                    if linking_failed || receipt_failed {
-                        println!(
-                            "    └ Upgraded {} to {} (with warnings)",
-                            pkg.name.bold().yellow(),
-                            pkg.new_version.dimmed()
-                        );
-                    } else {
-                        println!(
-                            "    └ Upgraded {} to {}",
-                            pkg.name.bold().green(),
-                            pkg.new_version.dimmed()
-                        );
-                        successful_upgrades += 1;
-                    }
+        for (candidate, keg) in poured {
+            let pkg = &candidate.formula;
+            let options = {
+                let (used_options, unused_options) = policy.options(pkg);
+                crate::keg::ReceiptOptions {
+                    runtime_dependencies: Some(build_runtime_deps(
+                        &pkg.dependencies,
+                        &all_formulae,
+                    )),
+                    used_options,
+                    unused_options,
                 }
-                Err(err) => {
-                    println!("  {}", err.to_string().red());
+            };
+            let result = keg.and_then(|keg| {
+                crate::keg::upgrade_keg_in(&prefix, pkg, keg, &candidate.old_version, &options)
+            });
+            let result = match result {
+                Ok(result) => result,
+                Err(e) => {
+                    println!("  {}: {}", candidate.name.bold().red(), e);
+                    failures.push(candidate.name.clone());
+                    continue;
                 }
+            };
+
+            if pkg.keg_only {
+                println!(
+                    "    ├ {} is keg-only (not linked to prefix)",
+                    result.name.dimmed()
+                );
+            } else {
+                println!(
+                    "    ├ Linked {} files",
+                    result.linked_files.to_string().dimmed()
+                );
+            }
+            if result.freed_bytes > 0 {
+                println!(
+                    "    ├ Removed old version {} ({})",
+                    result.from_version.dimmed(),
+                    super::utils::format_size(result.freed_bytes).dimmed()
+                );
             }
+            println!(
+                "    └ Upgraded {} to {}",
+                result.name.bold().green(),
+                result.to_version.dimmed()
+            );
+            successful_upgrades += 1;
+            upgraded.push(result.name);
         }
 
         // Handle packages without bottles sequentially (fallback to brew)
         for candidate in &without_bottles {
             let formula_name = &candidate.name;
-            let new_version = candidate.formula.versions.stable.as_ref().unwrap();
+            // brew builds a HEAD-only formula from its HEAD spec
+            let new_version = candidate.formula.install_version().unwrap_or("HEAD");
 
-            match super::utils::fallback_to_brew("upgrade", formula_name) {
+            match super::utils::fallback_to_brew_from_source("upgrade", formula_name, None) {
                 Ok(_) => {
                     println!(
                         "    └ Upgraded {} to {} (via brew)",
@@ -1076,9 +2160,11 @@ pub async fn upgrade(
                         new_version.dimmed()
                     );
                     successful_upgrades += 1;
+                    upgraded.push(formula_name.clone());
                 }
                 Err(e) => {
                     println!("  {}: Failed to upgrade: {}", formula_name.bold().red(), e);
+                    failures.push(formula_name.clone());
                 }
             }
         }
@@ -1116,8 +2202,13 @@ pub async fn upgrade(
                         }
                         println!("  Upgraded {}", formula_name.bold().green());
                         tap_upgrades += 1;
+                        // Dependents' receipts name tap formulae by their full name
+                        upgraded.push(full_name);
+                    }
+                    Err(e) => {
+                        println!("  {}: Failed to upgrade: {}", formula_name.bold().red(), e);
+                        failures.push(formula_name.clone());
                     }
-                    Err(e) => println!("  {}: Failed to upgrade: {}", formula_name.bold().red(), e),
                 }
             }
         }
@@ -1138,18 +2229,45 @@ pub async fn upgrade(
         }
     }
 
-    Ok(())
+    // Reinstall what was linked against the old kegs
+    let mut dependents_outcome = Outcome::Done;
+    if dependents && !upgraded.is_empty() {
+        let to_reinstall = installed_dependents(&cellar::list_current()?, &upgraded);
+        if !to_reinstall.is_empty() {
+            println!(
+                "\nReinstalling {} dependents of upgraded formulae: {}",
+                to_reinstall.len().to_string().bold(),
+                to_reinstall.join(", ").cyan()
+            );
+            dependents_outcome = reinstall(api, &to_reinstall, false, false, keep_going).await?;
+        }
+    }
+
+    // Failed upgrades never stop the others, but always fail the run
+    Ok(if failures.is_empty() {
+        dependents_outcome
+    } else {
+        super::utils::batch_outcome("upgrade", &failures, keep_going)
+    })
 }
 
-pub async fn reinstall(api: &BrewApi, names: &[String], cask: bool) -> Result<()> {
+pub async fn reinstall(
+    api: &BrewApi,
+    names: &[String],
+    cask: bool,
+    from_cache: bool,
+    keep_going: bool,
+) -> Result<Outcome> {
     if cask {
-        return super::cask::reinstall_cask(api, names).await;
+        return super::cask::reinstall_cask(api, names)
+            .await
+            .map(|()| Outcome::Done);
     }
 
     let formula_names = names;
     if formula_names.is_empty() {
         println!("{}", "No formulae specified".red());
-        return Ok(());
+        return Ok(Outcome::Done);
     }
 
     println!(
@@ -1158,18 +2276,48 @@ pub async fn reinstall(api: &BrewApi, names: &[String], cask: bool) -> Result<()
     );
 
     // Check for pinned formulae
-    let pinned = read_pinned()?;
+    let pinned = super::utils::read_pinned(false)?;
+    // Formulae that weren't reinstalled, listed at the end under --keep-going
+    let mut failures: Vec<String> = Vec::new();
+
+    // Keep the --with/--without selection the kegs were installed with
+    let policy = recorded_dependency_policy(&cellar::cellar_path(), formula_names);
 
     // Resolve dependencies for all formulas to build complete formula map
     // This is critical for generating correct receipts with runtime_dependencies
-    let (all_formulae, _) = resolve_dependencies(api, formula_names).await?;
+    let all_formulae = if from_cache {
+        // Offline: the cached formula index (at any age) stands in for the API
+        let cached = crate::cache::get_cached_formulae_any_age().ok_or_else(|| {
+            anyhow::anyhow!("No cached formula metadata; run `bru update` while online first")
+        })?;
+        cached
+            .into_iter()
+            .map(|mut f| {
+                policy.apply(&mut f);
+                (f.name.clone(), f)
+            })
+            .collect()
+    } else if keep_going {
+        let (all_formulae, _, unresolved) =
+            resolve_dependencies_each(api, formula_names, &policy).await;
+        for (name, err) in unresolved {
+            println!("  {}: Failed to reinstall: {}", name.bold().red(), err);
+            failures.push(name);
+        }
+        all_formulae
+    } else {
+        resolve_dependencies(api, formula_names, &policy).await?.0
+    };
 
     let mut actually_reinstalled = 0;
 
-    // Create shared HTTP client for all downloads
-    let client = reqwest::Client::new();
+    // Reuse the API's HTTP client for all downloads
+    let client = api.client();
 
     for formula_name in formula_names {
+        if failures.contains(formula_name) {
+            continue;
+        }
         // Skip pinned packages
         if pinned.contains(formula_name) {
             println!(
@@ -1230,95 +2378,180 @@ pub async fn reinstall(api: &BrewApi, names: &[String], cask: bool) -> Result<()
                         formula_name.bold().red(),
                         e
                     );
+                    failures.push(formula_name.clone());
                     continue;
                 }
             }
         }
 
-        println!(
-            "  Reinstalling {} {}",
-            formula_name.cyan(),
-            old_version.dimmed()
-        );
-
-        // Unlink
-        symlink::unlink_formula(formula_name, &old_version)?;
-
-        // Remove from Cellar
-        let cellar_path = cellar::cellar_path().join(formula_name).join(old_version);
-        if cellar_path.exists() {
-            std::fs::remove_dir_all(&cellar_path)?;
+        // Keep a source build a source build: brew rebuilds it instead of pouring a bottle
+        if receipt::InstallReceipt::read(cellar_path).is_ok_and(|r| r.built_from_source()) {
+            println!(
+                "  Reinstalling {} {} (built from source)",
+                formula_name.cyan(),
+                old_version.dimmed()
+            );
+            match super::utils::fallback_to_brew_from_source("reinstall", formula_name, None) {
+                Ok(_) => {
+                    actually_reinstalled += 1;
+                    println!("  Reinstalled {}", formula_name.bold().green());
+                }
+                Err(e) => {
+                    println!(
+                        "  {}: Failed to reinstall: {}",
+                        formula_name.bold().red(),
+                        e
+                    );
+                    failures.push(formula_name.clone());
+                }
+            }
+            continue;
         }
 
         // Fetch formula metadata to get NEW version
-        let formula = api.fetch_formula(formula_name).await?;
-        let new_version = formula
-            .versions
-            .stable
-            .as_ref()
-            .ok_or_else(|| anyhow::anyhow!("No stable version for {}", formula.name))?;
-
-        // Download bottle
-        let bottle_path = match download::download_bottle(&formula, None, &client).await {
-            Ok(path) => path,
-            Err(_) => {
-                // No bottle available - fall back to brew for source build
-                match super::utils::fallback_to_brew("reinstall", formula_name) {
-                    Ok(_) => {
-                        // Successfully reinstalled via brew, continue to next package
-                        actually_reinstalled += 1;
-                        continue;
-                    }
-                    Err(e) => {
-                        println!(
-                            "  {}: Failed to reinstall: {}",
-                            formula_name.bold().red(),
-                            e
-                        );
-                        continue;
-                    }
+        let mut formula = if from_cache {
+            match all_formulae.get(formula_name) {
+                Some(formula) => formula.clone(),
+                None => {
+                    println!(
+                        "  {}: not in the cached formula metadata",
+                        formula_name.bold().yellow()
+                    );
+                    continue;
+                }
+            }
+        } else {
+            match api.fetch_formula(formula_name).await {
+                Ok(formula) => formula,
+                Err(e) if keep_going => {
+                    println!(
+                        "  {}: Failed to reinstall: {}",
+                        formula_name.bold().red(),
+                        e
+                    );
+                    failures.push(formula_name.clone());
+                    continue;
                 }
+                Err(e) => return Err(e),
+            }
+        };
+        policy.apply(&mut formula);
+        let new_version = match formula.install_version() {
+            Ok(version) => version,
+            Err(e) => {
+                if !keep_going {
+                    return Err(e);
+                }
+                println!(
+                    "  {}: Failed to reinstall: {}",
+                    formula_name.bold().red(),
+                    e
+                );
+                failures.push(formula_name.clone());
+                continue;
             }
         };
 
-        // Install with NEW version
-        let extracted_path = extract::extract_bottle(&bottle_path, formula_name, new_version)?;
-
-        // Get actual installed version (may have bottle revision suffix like 25.1.0_1)
-        let actual_new_version = extracted_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or_else(|| {
-                anyhow::anyhow!("Invalid extracted path: {}", extracted_path.display())
-            })?;
-
-        // Relocate bottle (fix install names)
-        crate::relocate::relocate_bottle(&extracted_path, &crate::cellar::detect_prefix())?;
-
-        // Create symlinks - skip if formula is keg-only (matches Homebrew behavior)
-        if !formula.keg_only {
-            let linked = symlink::link_formula(formula_name, actual_new_version)?;
+        // Get the bottle before touching the existing keg, so a failure leaves it intact
+        let bottle_path = if from_cache {
+            match download::cached_bottle(&formula).await {
+                Ok(Some(path)) => path,
+                Ok(None) | Err(_) => {
+                    println!(
+                        "  {}: no verified bottle for {} in the download cache",
+                        formula_name.bold().yellow(),
+                        new_version
+                    );
+                    continue;
+                }
+            }
+        } else {
+            match download::download_bottle(&formula, None, client).await {
+                Ok(path) => path,
+                Err(_) => {
+                    // No bottle available - fall back to brew for source build
+                    match super::utils::fallback_to_brew_from_source(
+                        "reinstall",
+                        formula_name,
+                        None,
+                    ) {
+                        Ok(_) => {
+                            // Successfully reinstalled via brew, continue to next package
+                            actually_reinstalled += 1;
+                            continue;
+                        }
+                        Err(e) => {
+                            println!(
+                                "  {}: Failed to reinstall: {}",
+                                formula_name.bold().red(),
+                                e
+                            );
+                            failures.push(formula_name.clone());
+                            continue;
+                        }
+                    }
+                }
+            }
+        };
 
-            // Create version-agnostic symlinks (opt/ and var/homebrew/linked/)
-            symlink::optlink(formula_name, actual_new_version)?;
+        println!(
+            "  Reinstalling {} {}{}",
+            formula_name.cyan(),
+            old_version.dimmed(),
+            if from_cache {
+                " (from cache)".dimmed().to_string()
+            } else {
+                String::new()
+            }
+        );
 
-            println!("    ├ Linked {} files", linked.len().to_string().dimmed());
-        } else {
+        let options = {
+            let (used_options, unused_options) = policy.options(&formula);
+            crate::keg::ReceiptOptions {
+                runtime_dependencies: Some(build_runtime_deps(
+                    &formula.dependencies,
+                    &all_formulae,
+                )),
+                used_options,
+                unused_options,
+            }
+        };
+        let result = match crate::keg::reinstall_keg_in(
+            &cellar::detect_prefix(),
+            &formula,
+            &bottle_path,
+            &old_version,
+            &options,
+        ) {
+            Ok(result) => result,
+            Err(e) => {
+                if !keep_going {
+                    return Err(e);
+                }
+                println!(
+                    "  {}: Failed to reinstall: {}",
+                    formula_name.bold().red(),
+                    e
+                );
+                failures.push(formula_name.clone());
+                continue;
+            }
+        };
+        if formula.keg_only {
             println!(
                 "    ├ {} is keg-only (not linked to prefix)",
                 formula_name.dimmed()
             );
+        } else {
+            println!(
+                "    ├ Linked {} files",
+                result.linked_files.to_string().dimmed()
+            );
         }
-
-        // Generate receipt
-        // Use complete all_formulae map so runtime_dependencies are populated correctly
-        let runtime_deps = build_runtime_deps(&formula.dependencies, &all_formulae);
-        let receipt_data = receipt::InstallReceipt::new_bottle(&formula, runtime_deps, true);
-        receipt_data.write(&extracted_path)?;
         println!(
             "    └ Reinstalled {} {}",
-            formula_name.bold().green(),
-            new_version.dimmed()
+            result.name.bold().green(),
+            result.version.dimmed()
         );
         actually_reinstalled += 1;
     }
@@ -1333,7 +2566,12 @@ pub async fn reinstall(api: &BrewApi, names: &[String], cask: bool) -> Result<()
         println!("No packages were reinstalled");
     }
 
-    Ok(())
+    // Failures that didn't stop the run (e.g. a failed brew fallback) still fail it
+    Ok(super::utils::batch_outcome(
+        "reinstall",
+        &failures,
+        keep_going,
+    ))
 }
 
 pub async fn uninstall(_api: &BrewApi, formula_names: &[String], force: bool) -> Result<()> {
@@ -1346,6 +2584,23 @@ pub async fn uninstall(_api: &BrewApi, formula_names: &[String], force: bool) ->
     let all_installed = cellar::list_installed()?;
     let mut actually_uninstalled = 0;
 
+    // Installed casks with `depends_on formula:` (cached metadata only, no network)
+    let installed_casks: Vec<_> = crate::cask::list_installed_casks()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(token, _)| token)
+        .collect();
+    let cask_dependents: Vec<(String, Vec<String>)> = if installed_casks.is_empty() {
+        Vec::new()
+    } else {
+        crate::cache::get_cached_casks()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|c| installed_casks.contains(&c.token) && !c.depends_on.formula.is_empty())
+            .map(|c| (c.token, c.depends_on.formula))
+            .collect()
+    };
+
     for formula_name in formula_names {
         // Check if installed
         let installed_versions = cellar::get_installed_versions(formula_name)?;
@@ -1353,7 +2608,7 @@ pub async fn uninstall(_api: &BrewApi, formula_names: &[String], force: bool) ->
             // Check if it's a cask
             if crate::cask::is_cask_installed(formula_name) {
                 // Delegate to cask uninstall
-                if let Err(e) = super::cask::uninstall_cask(&[formula_name.to_string()]) {
+                if let Err(e) = super::cask::uninstall_cask(&[formula_name.to_string()], false) {
                     println!(
                         "  {}: Failed to uninstall cask: {}",
                         formula_name.bold().red(),
@@ -1403,6 +2658,20 @@ pub async fn uninstall(_api: &BrewApi, formula_names: &[String], force: bool) ->
             }
         }
 
+        // Casks don't block removal, but they may stop working without the formula
+        let casks: Vec<_> = cask_dependents
+            .iter()
+            .filter(|(_, formulae)| formulae.contains(formula_name))
+            .map(|(token, _)| token.as_str())
+            .collect();
+        if !casks.is_empty() {
+            println!(
+                "  {}: Warning - installed casks depend on it: {}",
+                formula_name.bold().yellow(),
+                casks.join(", ").cyan()
+            );
+        }
+
         println!(
             "  Uninstalling {} {}",
             formula_name.cyan(),
@@ -1504,3 +2773,475 @@ fn calculate_dir_size(path: &std::path::Path) -> Result<u64> {
 
     Ok(total)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockSource;
+    use std::path::Path;
+
+    fn write_bottle(path: &Path, name: &str, version: &str) {
+        let entry = format!("{}/{}/bin/{}", name, version, name);
+        let bottle = crate::test_support::bottle(&[(&entry, b"#!/bin/sh\n", 0o755)]);
+        std::fs::write(path, bottle).unwrap();
+    }
+
+    fn formula(name: &str, dependencies: &[&str]) -> Formula {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "versions": {"stable": "1.0", "bottle": true},
+            "dependencies": dependencies,
+        }))
+        .unwrap()
+    }
+
+    fn bottled(name: &str, sha256: &str) -> Formula {
+        serde_json::from_value(serde_json::json!({
+            "name": name,
+            "versions": {"stable": "1.0", "bottle": true},
+            "bottle": {"stable": {"rebuild": 0, "files": {"all": {
+                "cellar": ":any",
+                "url": format!("https://ghcr.io/v2/homebrew/core/{}/blobs/sha256:{}", name, sha256),
+                "sha256": sha256,
+            }}}},
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_report_verified_counts_mismatches_and_missing_checksums_as_failures() {
+        let formulae = vec![bottled("good", "abc123"), bottled("unsigned", "")];
+        let batch = download::BatchResult {
+            downloaded: vec![
+                ("good".to_string(), "good.tar.gz".into()),
+                ("unsigned".to_string(), "unsigned.tar.gz".into()),
+            ],
+            failed: vec![(
+                "corrupt".to_string(),
+                anyhow::anyhow!("Checksum verification failed for corrupt"),
+            )],
+        };
+
+        let mut failures = Vec::new();
+        assert_eq!(report_verified(&formulae, &batch, &mut failures), 1);
+        assert_eq!(failures, ["unsigned", "corrupt"]);
+    }
+
+    #[tokio::test]
+    async fn test_resolve_dependencies_orders_dependencies_first() {
+        let mut git = formula("git", &["pcre2", "gettext"]);
+        git.recommended_dependencies = vec!["openssl@3".to_string()];
+        git.optional_dependencies = vec!["tcl-tk".to_string()];
+        let source = MockSource::new(
+            vec![
+                git,
+                formula("pcre2", &[]),
+                formula("gettext", &["libunistring"]),
+                formula("libunistring", &[]),
+                formula("openssl@3", &["ca-certificates"]),
+                formula("ca-certificates", &[]),
+                formula("tcl-tk", &[]),
+            ],
+            &[],
+        );
+
+        let (formulae, order) =
+            resolve_dependencies(&source, &["git".to_string()], &DependencyPolicy::default())
+                .await
+                .unwrap();
+
+        // Recommended dependencies are followed by default, optional ones aren't
+        assert_eq!(formulae.len(), 6);
+        assert!(!formulae.contains_key("tcl-tk"));
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+        assert_eq!(order.last().map(String::as_str), Some("git"));
+        assert!(position("libunistring") < position("gettext"));
+        assert!(position("ca-certificates") < position("openssl@3"));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_tap_formula_dependencies() {
+        let prefix = tempfile::TempDir::new().unwrap();
+        let formula_dir = prefix
+            .path()
+            .join("Library/Taps/acme/homebrew-tools/Formula");
+        std::fs::create_dir_all(&formula_dir).unwrap();
+        std::fs::write(
+            formula_dir.join("foo.rb"),
+            "class Foo < Formula\n  url \"https://example.com/foo-1.0.tar.gz\"\n  \
+             depends_on \"acme/tools/bar\"\n  depends_on \"openssl@3\"\nend\n",
+        )
+        .unwrap();
+        std::fs::write(
+            formula_dir.join("bar.rb"),
+            "class Bar < Formula\n  url \"https://example.com/bar-2.0.tar.gz\"\n  \
+             depends_on \"zlib\"\nend\n",
+        )
+        .unwrap();
+        let api = MockSource::new(
+            vec![
+                formula("openssl@3", &["ca-certificates"]),
+                formula("ca-certificates", &[]),
+                formula("zlib", &[]),
+            ],
+            &[],
+        );
+        let source = crate::tap::TapSource::with_prefix(&api, prefix.path().to_path_buf());
+
+        let (formulae, order) = resolve_dependencies(
+            &source,
+            &["acme/tools/foo".to_string()],
+            &DependencyPolicy::default(),
+        )
+        .await
+        .unwrap();
+
+        // Qualified tap dependencies are ordered like core ones
+        assert_eq!(formulae["bar"].full_name, "acme/tools/bar");
+        let position = |name: &str| order.iter().position(|n| n == name).unwrap();
+        assert_eq!(order.last().map(String::as_str), Some("foo"));
+        assert!(position("zlib") < position("bar"));
+        assert!(position("ca-certificates") < position("openssl@3"));
+        assert_eq!(order.len(), 5);
+    }
+
+    #[tokio::test]
+    async fn test_install_order_is_deterministic() {
+        let source = MockSource::new(
+            vec![
+                formula("wget", &["openssl@3", "libidn2", "gettext"]),
+                formula("openssl@3", &["ca-certificates"]),
+                formula("ca-certificates", &[]),
+                formula("libidn2", &["libunistring", "gettext"]),
+                formula("libunistring", &[]),
+                formula("gettext", &["libunistring"]),
+            ],
+            &[],
+        );
+
+        // Formulae that become ready together are taken by name
+        let order = install_order(&source, &["wget".to_string()]).await.unwrap();
+        assert_eq!(
+            order,
+            [
+                "ca-certificates",
+                "libunistring",
+                "openssl@3",
+                "gettext",
+                "libidn2",
+                "wget"
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolve_dependencies_each_isolates_cycles() {
+        let source = MockSource::new(
+            vec![
+                formula("jq", &["oniguruma"]),
+                formula("oniguruma", &[]),
+                formula("egg", &["chicken"]),
+                formula("chicken", &["egg"]),
+            ],
+            &[],
+        );
+        let names = ["egg".to_string(), "jq".to_string()];
+
+        assert!(
+            resolve_dependencies(&source, &names, &DependencyPolicy::default())
+                .await
+                .is_err()
+        );
+
+        let (formulae, order, failed) =
+            resolve_dependencies_each(&source, &names, &DependencyPolicy::default()).await;
+        assert_eq!(order, ["oniguruma", "jq"]);
+        assert_eq!(formulae.len(), 2);
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].0, "egg");
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_dependencies_includes_new_dependency() {
+        // The new curl depends on libssh2, which the installed version didn't
+        let source = MockSource::new(
+            vec![
+                formula("curl", &["openssl@3", "libssh2"]),
+                formula("libssh2", &["openssl@3"]),
+                formula("openssl@3", &[]),
+            ],
+            &[],
+        );
+        let temp = tempfile::TempDir::new().unwrap();
+        for keg in ["curl/8.9.0", "openssl@3/3.3.0"] {
+            std::fs::create_dir_all(temp.path().join(keg).join("bin")).unwrap();
+        }
+
+        let (installed, missing) = upgrade_dependencies(
+            &source,
+            temp.path(),
+            &["curl".into()],
+            &DependencyPolicy::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(installed, ["openssl@3"]);
+        assert_eq!(missing, ["libssh2"]);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_dependencies_of_tap_formula() {
+        let prefix = tempfile::TempDir::new().unwrap();
+        let formula_dir = prefix
+            .path()
+            .join("Library/Taps/acme/homebrew-tools/Formula");
+        std::fs::create_dir_all(&formula_dir).unwrap();
+        std::fs::write(
+            formula_dir.join("foo.rb"),
+            "class Foo < Formula\n  url \"https://example.com/foo-1.1.tar.gz\"\n  \
+             depends_on \"openssl@3\"\n  depends_on \"zlib\"\nend\n",
+        )
+        .unwrap();
+        let cellar = prefix.path().join("Cellar");
+        for keg in ["foo/1.0", "openssl@3/3.3.0"] {
+            std::fs::create_dir_all(cellar.join(keg).join("bin")).unwrap();
+        }
+        let api = MockSource::new(vec![formula("openssl@3", &[]), formula("zlib", &[])], &[]);
+        let source = crate::tap::TapSource::with_prefix(&api, prefix.path().to_path_buf());
+
+        let (installed, missing) = upgrade_dependencies(
+            &source,
+            &cellar,
+            &["acme/tools/foo".into()],
+            &DependencyPolicy::default(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(installed, ["openssl@3"]);
+        assert_eq!(missing, ["zlib"]);
+    }
+
+    #[tokio::test]
+    async fn test_upgrade_keeps_recorded_dependency_options() {
+        // curl was installed --without-brotli, which its receipt recorded
+        let mut curl = formula("curl", &["openssl@3"]);
+        curl.recommended_dependencies = vec!["brotli".to_string()];
+        let source = MockSource::new(
+            vec![
+                curl.clone(),
+                formula("brotli", &[]),
+                formula("openssl@3", &[]),
+            ],
+            &[],
+        );
+        let temp = tempfile::TempDir::new().unwrap();
+        for keg in ["curl/8.9.0", "openssl@3/3.3.0"] {
+            std::fs::create_dir_all(temp.path().join(keg).join("bin")).unwrap();
+        }
+        let mut receipt = receipt::InstallReceipt::new_bottle(&curl, vec![], true);
+        receipt.used_options = vec!["--without-brotli".to_string()];
+        receipt.write(&temp.path().join("curl/8.9.0")).unwrap();
+
+        let policy = recorded_dependency_policy(temp.path(), &["curl".into()]);
+        assert_eq!(policy.without, ["brotli"]);
+        let (installed, missing) =
+            upgrade_dependencies(&source, temp.path(), &["curl".into()], &policy)
+                .await
+                .unwrap();
+        assert_eq!(installed, ["openssl@3"]);
+        assert!(missing.is_empty());
+    }
+
+    #[test]
+    fn test_macos_incompatible_on_linux_and_macos() {
+        let mut sonoma: Formula = serde_json::from_value(serde_json::json!({
+            "name": "needs-sonoma",
+            "requirements": [{"name": "macos", "version": "14", "contexts": []}],
+        }))
+        .unwrap();
+        let bare: Formula = serde_json::from_value(serde_json::json!({
+            "name": "mac-only",
+            "requirements": [{"name": "macos", "version": null, "contexts": []}],
+        }))
+        .unwrap();
+        let formulae = [sonoma.clone(), bare];
+        let names = |macos_version| -> Vec<&str> {
+            macos_incompatible(&formulae, macos_version)
+                .into_iter()
+                .map(|(name, _)| name)
+                .collect()
+        };
+
+        // On Linux only the bare `depends_on :macos` refuses to install
+        assert_eq!(names(None), ["mac-only"]);
+        assert_eq!(names(Some("13.6")), ["needs-sonoma"]);
+        assert!(names(Some("14.5")).is_empty());
+
+        // Build-only requirements don't stop a bottle
+        sonoma.requirements[0].contexts = vec!["build".to_string()];
+        assert!(macos_incompatible(&[sonoma], Some("13.6")).is_empty());
+    }
+
+    #[test]
+    fn test_disk_space_check_skips_unreadable_bottles() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let truncated = temp.path().join("truncated--1.0.all.bottle.tar.gz");
+        std::fs::write(&truncated, b"\x1f\x8b").unwrap();
+        let missing = temp.path().join("missing--1.0.all.bottle.tar.gz");
+
+        assert!(ensure_disk_space([&truncated, &missing]).is_ok());
+    }
+
+    #[test]
+    fn test_formula_file_bottle_url() {
+        let mut bottle = crate::tap::TapBottleInfo {
+            root_url: Some("https://example.com/bottles/".to_string()),
+            rebuild: 2,
+            ..Default::default()
+        };
+        assert_eq!(
+            formula_file_bottle_url("widget", "1.2.0_1", "arm64_sonoma", &bottle, "abc"),
+            "https://example.com/bottles/widget--1.2.0_1.arm64_sonoma.bottle.2.tar.gz"
+        );
+        bottle.rebuild = 0;
+        assert_eq!(
+            formula_file_bottle_url("widget", "1.2.0", "all", &bottle, "abc"),
+            "https://example.com/bottles/widget--1.2.0.all.bottle.tar.gz"
+        );
+
+        // GHCR images swap characters that aren't allowed in repository names
+        bottle.root_url = None;
+        assert_eq!(
+            formula_file_bottle_url("libsigc++@2", "2.12.1", "all", &bottle, "abc"),
+            "https://ghcr.io/v2/homebrew/core/libsigcxx/2/blobs/sha256:abc"
+        );
+    }
+
+    #[test]
+    fn test_installed_dependents_reads_receipts() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let keg = |name: &str, deps: &[&str]| {
+            let path = temp.path().join(name).join("1.0");
+            std::fs::create_dir_all(&path).unwrap();
+            let deps: Vec<_> = deps
+                .iter()
+                .map(|d| serde_json::json!({"full_name": d, "version": "1.0"}))
+                .collect();
+            std::fs::write(
+                path.join("INSTALL_RECEIPT.json"),
+                serde_json::json!({"homebrew_version": "4.4.0", "runtime_dependencies": deps})
+                    .to_string(),
+            )
+            .unwrap();
+            cellar::InstalledPackage::from_path(name.into(), "1.0".into(), path)
+        };
+        let packages = vec![
+            keg("icu4c", &[]),
+            keg("node", &["icu4c", "openssl@3"]),
+            keg("boost", &["icu4c"]),
+            keg("jq", &["oniguruma"]),
+            keg("curl", &["openssl@3"]),
+            keg("bar", &["user/repo/foo"]),
+        ];
+
+        assert_eq!(
+            installed_dependents(&packages, &["icu4c".into()]),
+            ["boost", "node"]
+        );
+        assert_eq!(
+            installed_dependents(&packages, &["icu4c".into(), "openssl@3".into()]),
+            ["boost", "curl", "node"]
+        );
+        assert!(installed_dependents(&packages, &["node".into()]).is_empty());
+        // Tap formulae are recorded under their full name
+        assert_eq!(
+            installed_dependents(&packages, &["user/repo/foo".into()]),
+            ["bar"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_validate_requested_separates_formulae_and_casks() {
+        let source = MockSource::new(
+            vec![formula("docker", &[]), formula("jq", &[])],
+            &["docker", "firefox"],
+        );
+        let names: Vec<String> = ["jq", "docker", "firefox", "nope"]
+            .iter()
+            .map(|n| n.to_string())
+            .collect();
+
+        let results = validate_requested(&source, &names, false).await;
+        assert!(matches!(&results[0], Ok((name, false)) if name == "jq"));
+        assert!(matches!(&results[1], Ok((name, true)) if name == "docker"));
+        assert!(matches!(
+            &results[2],
+            Err((_, crate::error::BruError::CaskNotFound(name))) if name == "firefox"
+        ));
+        assert!(matches!(
+            &results[3],
+            Err((_, crate::error::BruError::FormulaNotFound(name))) if name == "nope"
+        ));
+
+        // --formula doesn't look for a same-named cask
+        let results = validate_requested(&source, &names[1..2], true).await;
+        assert!(matches!(&results[0], Ok((_, false))));
+    }
+
+    #[test]
+    fn test_failed_dependency_rolls_back_run() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path().join("prefix");
+
+        // Installed before the run; must survive the rollback
+        let existing = prefix.join("Cellar/zlib/1.3");
+        std::fs::create_dir_all(existing.join("lib")).unwrap();
+        std::fs::write(existing.join("INSTALL_RECEIPT.json"), "{}").unwrap();
+
+        let good = temp.path().join("libfoo--1.0.bottle.tar.gz");
+        write_bottle(&good, "libfoo", "1.0");
+        let bad = temp.path().join("libbar--1.0.bottle.tar.gz");
+        std::fs::write(&bad, "not a bottle").unwrap();
+
+        let all_formulae: HashMap<String, Formula> = [
+            ("libfoo".to_string(), formula("libfoo", &[])),
+            ("libbar".to_string(), formula("libbar", &["libfoo"])),
+        ]
+        .into();
+        let policy = DependencyPolicy::default();
+        let mut transaction = InstallTransaction::new(prefix.clone());
+
+        let poured = pour_bottle(
+            &all_formulae["libfoo"],
+            &good,
+            "1.0",
+            &all_formulae,
+            false,
+            &policy,
+            &mut transaction,
+        )
+        .unwrap();
+        assert!(poured);
+        assert!(prefix.join("bin/libfoo").is_symlink());
+        assert!(prefix.join("opt/libfoo").is_symlink());
+
+        // The second dependency's bottle is corrupt
+        let result = pour_bottle(
+            &all_formulae["libbar"],
+            &bad,
+            "1.0",
+            &all_formulae,
+            true,
+            &policy,
+            &mut transaction,
+        );
+        assert!(result.is_err());
+        transaction.rollback();
+
+        assert!(!prefix.join("Cellar/libfoo").exists());
+        assert!(!prefix.join("Cellar/libbar").exists());
+        assert!(!prefix.join("bin/libfoo").is_symlink());
+        assert!(!prefix.join("opt/libfoo").is_symlink());
+        assert!(existing.join("INSTALL_RECEIPT.json").exists());
+    }
+}
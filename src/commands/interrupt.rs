@@ -0,0 +1,54 @@
+//! Ctrl-C handling for commands that modify the Cellar
+//!
+//! Ctrl-C normally exits right away. While an [`InterruptGuard`] is held, the first
+//! Ctrl-C only raises a flag: the command checks [`interrupted`] between steps, stops
+//! starting new work, and rolls back whatever it had in flight. A second Ctrl-C exits
+//! immediately.
+
+use colored::Colorize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Exit status of a process stopped by SIGINT (128 + 2, as shells report it)
+pub const INTERRUPTED_EXIT_CODE: i32 = 130;
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+static GUARDS: AtomicUsize = AtomicUsize::new(0);
+
+/// Listen for Ctrl-C for the rest of the process
+///
+/// Must be called from within the Tokio runtime.
+pub fn install_handler() {
+    tokio::spawn(async {
+        while tokio::signal::ctrl_c().await.is_ok() {
+            if GUARDS.load(Ordering::SeqCst) == 0 || INTERRUPTED.swap(true, Ordering::SeqCst) {
+                eprintln!();
+                std::process::exit(INTERRUPTED_EXIT_CODE);
+            }
+            eprintln!(
+                "\n{} Finishing cleanup (press Ctrl-C again to quit now)",
+                "Interrupted:".yellow().bold()
+            );
+        }
+    });
+}
+
+/// Whether Ctrl-C was pressed while a guard was held
+pub fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// Defers Ctrl-C to [`interrupted`] checkpoints for as long as it is alive
+pub struct InterruptGuard(());
+
+impl InterruptGuard {
+    pub fn acquire() -> Self {
+        GUARDS.fetch_add(1, Ordering::SeqCst);
+        Self(())
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        GUARDS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
@@ -3,8 +3,8 @@
 //! This module handles symlink management and version pinning for installed formulae:
 //! - `link`: Create symlinks from Cellar to system directories (bin, lib, etc.)
 //! - `unlink`: Remove symlinks for a formula
-//! - `pin`: Prevent a formula from being upgraded
-//! - `unpin`: Allow a formula to be upgraded again
+//! - `pin`: Prevent a formula (or cask) from being upgraded
+//! - `unpin`: Allow a formula (or cask) to be upgraded again
 //! - `postinstall`: Run post-install hooks (stub for Phase 5 Ruby interop)
 
 use crate::api::BrewApi;
@@ -12,28 +12,12 @@ use crate::cellar;
 use crate::error::Result;
 use crate::symlink;
 use colored::Colorize;
-use std::path::PathBuf;
 
-/// Returns the path to the pinned formulae tracking file
-fn pinned_file_path() -> PathBuf {
-    cellar::detect_prefix().join("var/homebrew/pinned_formulae")
-}
-
-/// Read the list of pinned formulae from the tracking file
-fn read_pinned() -> Result<Vec<String>> {
-    let path = pinned_file_path();
-    if !path.exists() {
-        return Ok(Vec::new());
-    }
-
-    let content = std::fs::read_to_string(&path)?;
-    // Convert lines to owned strings
-    Ok(content.lines().map(String::from).collect())
-}
+use super::utils::{pinned_file_path, read_pinned};
 
-/// Write the list of pinned formulae to the tracking file
-fn write_pinned(pinned: &[String]) -> Result<()> {
-    let path = pinned_file_path();
+/// Write the list of pinned formulae (or casks) to the tracking file
+fn write_pinned(pinned: &[String], cask: bool) -> Result<()> {
+    let path = pinned_file_path(cask);
 
     // Ensure parent directory exists
     if let Some(parent) = path.parent() {
@@ -48,20 +32,25 @@ fn write_pinned(pinned: &[String]) -> Result<()> {
 ///
 /// Pinned formulae are excluded from `bru upgrade` operations. This is useful
 /// when you need to keep a specific version installed (e.g., for compatibility).
-pub fn pin(formula_names: &[String]) -> Result<()> {
+/// With `cask`, the names are casks and are held back from `bru upgrade --cask`.
+pub fn pin(formula_names: &[String], cask: bool) -> Result<()> {
     if formula_names.is_empty() {
         println!("{} No formulae specified", "".red());
         return Ok(());
     }
 
-    println!("Pinning formulae...");
+    println!("Pinning {}...", if cask { "casks" } else { "formulae" });
 
-    let mut pinned = read_pinned()?;
+    let mut pinned = read_pinned(cask)?;
 
     for formula in formula_names {
-        // Verify formula is installed before pinning
-        let versions = cellar::get_installed_versions(formula)?;
-        if versions.is_empty() {
+        // Verify formula (or cask) is installed before pinning
+        let installed = if cask {
+            crate::cask::is_cask_installed(formula)
+        } else {
+            !cellar::get_installed_versions(formula)?.is_empty()
+        };
+        if !installed {
             println!("  {} {} is not installed", "".yellow(), formula.bold());
             continue;
         }
@@ -74,23 +63,24 @@ pub fn pin(formula_names: &[String]) -> Result<()> {
         }
     }
 
-    write_pinned(&pinned)?;
+    write_pinned(&pinned, cask)?;
 
     Ok(())
 }
 
 /// Unpin formulae to allow them to be upgraded
 ///
-/// Removes the pin from formulae, allowing them to be upgraded by `bru upgrade`.
-pub fn unpin(formula_names: &[String]) -> Result<()> {
+/// Removes the pin from formulae (or, with `cask`, casks), allowing them to be upgraded
+/// by `bru upgrade`.
+pub fn unpin(formula_names: &[String], cask: bool) -> Result<()> {
     if formula_names.is_empty() {
         println!("{} No formulae specified", "".red());
         return Ok(());
     }
 
-    println!("Unpinning formulae...");
+    println!("Unpinning {}...", if cask { "casks" } else { "formulae" });
 
-    let mut pinned = read_pinned()?;
+    let mut pinned = read_pinned(cask)?;
 
     for formula in formula_names {
         // Find and remove formula from pinned list
@@ -102,7 +92,7 @@ pub fn unpin(formula_names: &[String]) -> Result<()> {
         }
     }
 
-    write_pinned(&pinned)?;
+    write_pinned(&pinned, cask)?;
 
     Ok(())
 }
@@ -113,14 +103,27 @@ pub fn unpin(formula_names: &[String]) -> Result<()> {
 /// locations like `/usr/local/bin`. This makes formula executables and libraries
 /// available in your PATH.
 ///
-/// Keg-only formulae cannot be linked as they are designed to be isolated.
-pub async fn link(api: &BrewApi, formula_names: &[String]) -> Result<()> {
+/// Keg-only formulae are skipped unless `force` is set. Existing files that are not
+/// symlinks are left in place, and a formula whose links would replace another keg's
+/// symlinks is not linked, unless `overwrite` is set. With `dry_run`, prints the
+/// links that would be created (and files that would be removed) without changing anything.
+pub async fn link(
+    api: &BrewApi,
+    formula_names: &[String],
+    overwrite: bool,
+    dry_run: bool,
+    force: bool,
+) -> Result<()> {
     if formula_names.is_empty() {
         println!("{} No formulae specified", "".red());
         return Ok(());
     }
 
-    println!("Linking formulae...");
+    if dry_run {
+        println!("Dry run - no files will be linked");
+    } else {
+        println!("Linking formulae...");
+    }
 
     for formula_name in formula_names {
         // Check if formula is installed
@@ -145,22 +148,109 @@ pub async fn link(api: &BrewApi, formula_names: &[String]) -> Result<()> {
 
         // Keg-only formulae are not linkable by design
         // They are typically system-conflicting packages (e.g., openssl, curl)
-        if formula.keg_only {
+        if formula.keg_only && !force {
             println!(
                 "  {} {} is keg-only and cannot be linked",
                 "".yellow(),
                 formula_name.bold()
             );
             if let Some(reason) = &formula.keg_only_reason {
-                println!("    {} {}", "".dimmed(), reason.explanation);
+                let explanation =
+                    cellar::render_caveats(&reason.explanation, formula_name, &versions[0].version);
+                println!("    {} {}", "".dimmed(), explanation);
             }
+            println!(
+                "    {} Use {} to link it anyway",
+                "→".dimmed(),
+                "--force".cyan()
+            );
             continue;
         }
 
         // Link the most recent installed version
         let version = &versions[0].version;
+
+        // Real files in the way are skipped by linking and other kegs' symlinks stop it;
+        // --overwrite removes both first
+        let targets = symlink::link_targets(formula_name, version)?;
+        let conflicts = symlink::link_conflicts(formula_name, version)?;
+        let owned: Vec<&symlink::LinkConflict> =
+            conflicts.iter().filter(|c| c.owner.is_some()).collect();
+
+        if !overwrite && !owned.is_empty() {
+            println!(
+                "  {} {} {} {}",
+                "".red(),
+                if dry_run {
+                    "Would not link"
+                } else {
+                    "Cannot link"
+                },
+                formula_name.bold(),
+                version.dimmed()
+            );
+            for conflict in &owned {
+                println!(
+                    "    {} is a symlink belonging to {}",
+                    conflict.target.display(),
+                    conflict.owner.as_deref().unwrap_or_default().cyan()
+                );
+            }
+            println!(
+                "    {} Unlink the other formula first, or use {} to replace its links",
+                "→".dimmed(),
+                "--overwrite".cyan()
+            );
+            continue;
+        }
+
+        if dry_run {
+            println!("  Would link {} {}", formula_name.cyan(), version.dimmed());
+            if overwrite && !conflicts.is_empty() {
+                println!("    Would remove:");
+                for conflict in &conflicts {
+                    println!("      {}", conflict.target.display());
+                }
+            }
+            println!("    Would link:");
+            for (_, target) in &targets {
+                if !overwrite && conflicts.iter().any(|c| &c.target == target) {
+                    println!(
+                        "      {} {}",
+                        target.display(),
+                        "(conflict, skipped)".yellow()
+                    );
+                } else {
+                    println!("      {}", target.display());
+                }
+            }
+            continue;
+        }
+
         println!("  Linking {} {}", formula_name.cyan(), version.dimmed());
 
+        if overwrite {
+            for conflict in &conflicts {
+                std::fs::remove_file(&conflict.target).map_err(|e| {
+                    anyhow::anyhow!("Failed to remove {}: {}", conflict.target.display(), e)
+                })?;
+            }
+            if !conflicts.is_empty() {
+                println!(
+                    "    {} Removed {} conflicting files",
+                    "".green(),
+                    conflicts.len().to_string().dimmed()
+                );
+            }
+        } else if !conflicts.is_empty() {
+            println!(
+                "    {} {} existing files were not replaced (use {} to replace them)",
+                "".yellow(),
+                conflicts.len(),
+                "--overwrite".cyan()
+            );
+        }
+
         let linked = symlink::link_formula(formula_name, version)?;
 
         // Create version-agnostic symlinks (opt/ and var/homebrew/linked/)
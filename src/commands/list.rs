@@ -7,11 +7,14 @@
 use crate::api::BrewApi;
 use crate::cellar;
 use crate::error::Result;
+use crate::output;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
 use std::collections::{HashMap, HashSet};
 use std::io::IsTerminal;
 
+use super::Outcome;
+
 /// Format names into column layout for terminal output
 fn format_columns(names: &[String]) -> String {
     if names.is_empty() {
@@ -56,6 +59,51 @@ fn format_columns(names: &[String]) -> String {
     result
 }
 
+/// Order for `list` output
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ListSort {
+    /// Alphabetical
+    #[default]
+    Name,
+    /// Most recently installed first
+    Date,
+    /// Largest on disk first
+    Size,
+}
+
+/// Formula names from `by_name` in `sort` order
+///
+/// Ties (and formulae without a receipt time) fall back to alphabetical order.
+fn sorted_formula_names(
+    by_name: &HashMap<String, Vec<cellar::InstalledPackage>>,
+    sort: ListSort,
+) -> Vec<String> {
+    use std::cmp::Reverse;
+
+    let mut names: Vec<_> = by_name.keys().cloned().collect();
+    names.sort();
+    match sort {
+        ListSort::Name => {}
+        ListSort::Date => names.sort_by_cached_key(|name| {
+            Reverse(
+                by_name[name]
+                    .iter()
+                    .filter_map(|pkg| pkg.installed_time())
+                    .max(),
+            )
+        }),
+        ListSort::Size => names.sort_by_cached_key(|name| {
+            Reverse(
+                by_name[name]
+                    .iter()
+                    .map(|pkg| cellar::keg_size(&pkg.path).unwrap_or(0))
+                    .sum::<u64>(),
+            )
+        }),
+    }
+    names
+}
+
 /// List installed formulae or casks with various output formats
 ///
 /// Supports multiple output modes:
@@ -63,14 +111,24 @@ fn format_columns(names: &[String]) -> String {
 /// - Single column (piped output or --versions)
 /// - JSON format
 /// - Quiet mode (names only)
+///
+/// With `json` and `detailed`, each formula also gets its disk usage, link state,
+/// request state and dependencies. That means walking every keg and reading every
+/// receipt, so it's noticeably slower on large installs (work is spread across cores).
+///
+/// `sort` orders formulae by name, install date, or disk usage; casks are always listed
+/// by name. Sorting by date prints each formula's install date in single-column output.
+#[allow(clippy::too_many_arguments)]
 pub async fn list(
     _api: &BrewApi,
     show_versions: bool,
     json: bool,
+    detailed: bool,
     formula: bool,
     cask: bool,
     quiet: bool,
     columns: bool,
+    sort: ListSort,
 ) -> Result<()> {
     // Detect if stdout is a TTY (for pipe-aware behavior)
     let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
@@ -109,91 +167,71 @@ pub async fn list(
     let show_casks = cask || !formula;
     let show_headers = show_formulae && show_casks;
 
+    if json {
+        return list_json(show_formulae, show_casks, detailed, sort);
+    }
+
     if show_formulae {
         // List installed formulae
         let packages = cellar::list_installed()?;
 
-        if json && !show_casks {
-            // Output as JSON (only if not showing casks too, or prioritize formulae)
-            // ... (existing JSON logic)
-            #[derive(serde::Serialize)]
-            struct PackageInfo {
-                name: String,
-                versions: Vec<String>,
-            }
+        if show_headers && is_tty {
+            println!("{}", "==> Formulae".bold().green());
+        }
 
+        if packages.is_empty() {
+            if is_tty && !show_casks {
+                println!("No packages installed");
+            }
+        } else {
+            // ... (existing formulae display logic)
             // Group by formula name
-            let mut by_name: HashMap<String, Vec<String>> = HashMap::new();
+            let mut by_name: HashMap<String, Vec<cellar::InstalledPackage>> =
+                HashMap::with_capacity(packages.len());
             for pkg in packages {
-                by_name
-                    .entry(pkg.name.clone())
-                    .or_default()
-                    .push(pkg.version.clone());
-            }
-
-            let mut package_list: Vec<PackageInfo> = by_name
-                .into_iter()
-                .map(|(name, versions)| PackageInfo { name, versions })
-                .collect();
-
-            package_list.sort_by(|a, b| a.name.cmp(&b.name));
-
-            let json_str = serde_json::to_string_pretty(&package_list)?;
-            println!("{}", json_str);
-            return Ok(());
-        }
-
-        if !json {
-            if show_headers && is_tty {
-                println!("{}", "==> Formulae".bold().green());
+                by_name.entry(pkg.name.clone()).or_default().push(pkg);
             }
 
-            if packages.is_empty() {
-                if is_tty && !show_casks {
-                    println!("No packages installed");
+            let names = sorted_formula_names(&by_name, sort);
+
+            if use_columns {
+                if show_versions {
+                    // Columns with versions: "name version" in columns
+                    let formatted: Vec<String> = names
+                        .iter()
+                        .map(|name| {
+                            let versions = &by_name[name];
+                            let pkg = &versions[0]; // Show first version in column mode
+                            format!("{} {}", name, pkg.version)
+                        })
+                        .collect();
+                    print!("{}", format_columns(&formatted));
+                } else {
+                    // Columns with names only
+                    print!("{}", format_columns(&names));
                 }
             } else {
-                // ... (existing formulae display logic)
-                // Group by formula name
-                let mut by_name: HashMap<String, Vec<cellar::InstalledPackage>> =
-                    HashMap::with_capacity(packages.len());
-                for pkg in packages {
-                    by_name.entry(pkg.name.clone()).or_default().push(pkg);
-                }
+                for name in names {
+                    let versions = &by_name[&name];
 
-                let mut names: Vec<_> = by_name.keys().cloned().collect();
-                names.sort();
-
-                if use_columns {
+                    let mut line = name.bold().green().to_string();
                     if show_versions {
-                        // Columns with versions: "name version" in columns
-                        let formatted: Vec<String> = names
-                            .iter()
-                            .map(|name| {
-                                let versions = &by_name[name];
-                                let pkg = &versions[0]; // Show first version in column mode
-                                format!("{} {}", name, pkg.version)
-                            })
-                            .collect();
-                        print!("{}", format_columns(&formatted));
-                    } else {
-                        // Columns with names only
-                        print!("{}", format_columns(&names));
+                        // Show all versions on one line (brew behavior)
+                        let version_str: Vec<String> =
+                            versions.iter().map(|pkg| pkg.version.clone()).collect();
+                        line = format!("{} {}", line, version_str.join(" ").dimmed());
                     }
-                } else {
-                    for name in names {
-                        let versions = &by_name[&name];
-
-                        if show_versions {
-                            // Show all versions on one line (brew behavior)
-                            let version_str: Vec<String> =
-                                versions.iter().map(|pkg| pkg.version.clone()).collect();
-                            println!("{} {}", name.bold().green(), version_str.join(" ").dimmed());
-                        } else {
-                            // No versions requested: names only
-                            println!("{}", name.bold().green());
-                        }
+                    // Names-only output (-1 or piped) stays parseable
+                    if sort == ListSort::Date && !use_quiet {
+                        let installed = versions
+                            .iter()
+                            .filter_map(|pkg| pkg.installed_time())
+                            .max()
+                            .map(|time| time.format("%Y-%m-%d").to_string())
+                            .unwrap_or_else(|| "unknown".to_string());
+                        line = format!("{} {}", line, installed.dimmed());
                     }
+                    println!("{}", line);
                 }
             }
         }
@@ -203,290 +241,488 @@ pub async fn list(
         // List installed casks
         let casks = crate::cask::list_installed_casks()?;
 
-        if json && !show_formulae {
-            // Output as JSON (only if not showing formulae too)
-            // ... (existing JSON logic)
-            #[derive(serde::Serialize)]
-            struct CaskInfo {
-                token: String,
-                version: String,
+        if show_headers && is_tty {
+            if show_formulae {
+                println!(); // Spacing
             }
-
-            let cask_list: Vec<CaskInfo> = casks
-                .into_iter()
-                .map(|(token, version)| CaskInfo { token, version })
-                .collect();
-
-            let json_str = serde_json::to_string_pretty(&cask_list)?;
-            println!("{}", json_str);
-            return Ok(());
+            println!("{}", "==> Casks".bold().green());
         }
 
-        if !json {
-            if show_headers && is_tty {
-                if show_formulae {
-                    println!(); // Spacing
-                }
-                println!("{}", "==> Casks".bold().green());
+        if casks.is_empty() {
+            if is_tty && !show_formulae {
+                println!("No casks installed");
             }
-
-            if casks.is_empty() {
-                if is_tty && !show_formulae {
-                    println!("No casks installed");
+        } else {
+            // ... (existing casks display logic)
+            if use_columns {
+                if show_versions {
+                    // Columns with versions: "name version" in columns
+                    let formatted: Vec<String> = casks
+                        .iter()
+                        .map(|(token, version)| format!("{} {}", token, version))
+                        .collect();
+                    print!("{}", format_columns(&formatted));
+                } else {
+                    // Columns with names only
+                    let names: Vec<String> = casks.iter().map(|(token, _)| token.clone()).collect();
+                    print!("{}", format_columns(&names));
+                }
+            } else if show_versions {
+                // Show versions
+                for (token, version) in &casks {
+                    println!("{} {}", token.bold().green(), version.dimmed());
                 }
             } else {
-                // ... (existing casks display logic)
-                if use_columns {
-                    if show_versions {
-                        // Columns with versions: "name version" in columns
-                        let formatted: Vec<String> = casks
+                // Names only
+                for (token, _version) in &casks {
+                    println!("{}", token.bold().green());
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// `list --json`: an array of formulae or casks, or both under `formulae`/`casks`
+fn list_json(show_formulae: bool, show_casks: bool, detailed: bool, sort: ListSort) -> Result<()> {
+    use rayon::prelude::*;
+
+    #[derive(serde::Serialize)]
+    struct PackageInfo {
+        name: String,
+        versions: Vec<String>,
+        #[serde(flatten)]
+        details: Option<PackageDetails>,
+    }
+
+    /// Extra fields for `--detailed`
+    #[derive(serde::Serialize)]
+    struct PackageDetails {
+        /// Disk usage of all installed versions
+        size_bytes: u64,
+        linked: bool,
+        installed_on_request: bool,
+        /// Direct runtime dependencies from the receipt of the linked (or newest) version
+        dependencies: Vec<String>,
+    }
+
+    #[derive(serde::Serialize)]
+    struct CaskInfo {
+        token: String,
+        version: String,
+    }
+
+    let mut package_list = Vec::new();
+    if show_formulae {
+        // Group by formula name
+        let mut by_name: HashMap<String, Vec<cellar::InstalledPackage>> = HashMap::new();
+        for pkg in cellar::list_installed()? {
+            by_name.entry(pkg.name.clone()).or_default().push(pkg);
+        }
+        let order: HashMap<String, usize> = sorted_formula_names(&by_name, sort)
+            .into_iter()
+            .enumerate()
+            .map(|(position, name)| (name, position))
+            .collect();
+        package_list = by_name
+            .into_par_iter()
+            .map(|(name, kegs)| {
+                let details = detailed.then(|| {
+                    let linked = crate::symlink::get_linked_version(&name).ok().flatten();
+                    let current = linked
+                        .as_ref()
+                        .and_then(|v| kegs.iter().find(|pkg| &pkg.version == v))
+                        .or_else(|| {
+                            kegs.iter()
+                                .max_by(|a, b| cellar::compare_versions(&a.version, &b.version))
+                        });
+                    PackageDetails {
+                        size_bytes: kegs
                             .iter()
-                            .map(|(token, version)| format!("{} {}", token, version))
-                            .collect();
-                        print!("{}", format_columns(&formatted));
-                    } else {
-                        // Columns with names only
-                        let names: Vec<String> =
-                            casks.iter().map(|(token, _)| token.clone()).collect();
-                        print!("{}", format_columns(&names));
-                    }
-                } else if show_versions {
-                    // Show versions
-                    for (token, version) in &casks {
-                        println!("{} {}", token.bold().green(), version.dimmed());
+                            .map(|pkg| cellar::keg_size(&pkg.path).unwrap_or(0))
+                            .sum(),
+                        linked: linked.is_some(),
+                        installed_on_request: current.is_some_and(|pkg| pkg.installed_on_request()),
+                        dependencies: current
+                            .map(|pkg| {
+                                pkg.runtime_dependencies()
+                                    .into_iter()
+                                    .filter(|dep| dep.declared_directly)
+                                    .map(|dep| dep.full_name)
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
                     }
+                });
+                PackageInfo {
+                    name,
+                    versions: kegs.into_iter().map(|pkg| pkg.version).collect(),
+                    details,
+                }
+            })
+            .collect();
+        package_list.sort_by_key(|package| order[&package.name]);
+    }
+
+    let mut cask_list = Vec::new();
+    if show_casks {
+        cask_list = crate::cask::list_installed_casks()?
+            .into_iter()
+            .map(|(token, version)| CaskInfo { token, version })
+            .collect();
+    }
+
+    match (show_formulae, show_casks) {
+        (true, false) => output::print_json(&package_list)?,
+        (false, true) => output::print_json(&cask_list)?,
+        _ => output::print_json(&serde_json::json!({
+            "formulae": package_list,
+            "casks": cask_list,
+        }))?,
+    }
+    Ok(())
+}
+
+/// List the files installed by specific formulae
+///
+/// Matches `brew list <formula>`: files are shown at their location under the
+/// prefix when the keg is linked, otherwise at their location inside the keg.
+pub fn list_files(formula_names: &[String], json: bool) -> Result<()> {
+    #[derive(serde::Serialize)]
+    struct FormulaFiles {
+        name: String,
+        version: String,
+        linked: bool,
+        files: Vec<String>,
+    }
+
+    let prefix = cellar::detect_prefix();
+    let mut results = Vec::with_capacity(formula_names.len());
+
+    for name in formula_names {
+        let versions = cellar::get_installed_versions(name)?;
+        if versions.is_empty() {
+            return Err(anyhow::anyhow!(
+                "No such keg: {}",
+                cellar::cellar_path().join(name).display()
+            )
+            .into());
+        }
+
+        // Prefer the linked keg so paths match what is actually on PATH
+        let linked_version = crate::symlink::get_linked_version(name).ok().flatten();
+        let keg = linked_version
+            .as_ref()
+            .and_then(|v| versions.iter().find(|pkg| &pkg.version == v))
+            .unwrap_or(&versions[0]);
+        let is_linked = linked_version.as_deref() == Some(keg.version.as_str());
+
+        let files = cellar::keg_files(&keg.path)?
+            .into_iter()
+            .map(|relative| {
+                let linked_path = prefix.join(&relative);
+                if is_linked && linked_path.exists() {
+                    linked_path
                 } else {
-                    // Names only
-                    for (token, _version) in &casks {
-                        println!("{}", token.bold().green());
-                    }
+                    keg.path.join(&relative)
                 }
-            }
+            })
+            .map(|path| path.display().to_string())
+            .collect();
+
+        results.push(FormulaFiles {
+            name: name.clone(),
+            version: keg.version.clone(),
+            linked: is_linked,
+            files,
+        });
+    }
+
+    if json {
+        output::print_json(&results)?;
+        return Ok(());
+    }
+
+    for entry in &results {
+        for file in &entry.files {
+            println!("{}", file);
         }
     }
 
     Ok(())
 }
 
-/// Check for outdated formulae or casks
+/// Check for outdated formulae and/or casks
 ///
 /// Compares installed versions against latest available versions from the API.
-/// Shows version differences in TTY mode, names only when piped or with --quiet.
-pub async fn outdated(api: &BrewApi, cask: bool, quiet: bool) -> Result<()> {
-    // Detect if stdout is a TTY (for brew-compatible behavior)
-    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+/// Checks both formulae and casks (in parallel) unless `--formula` or `--cask` narrows it.
+/// Shows version differences and section headers in TTY mode, names only when piped
+/// or with --quiet. With `json`, prints `{"formulae": [...], "casks": [...]}` like
+/// `brew outdated --json=v2`.
+///
+/// Returns [`Outcome::UpdatesAvailable`] if anything other than a pinned formula is outdated.
+pub async fn outdated(
+    api: &BrewApi,
+    formula: bool,
+    cask: bool,
+    quiet: bool,
+    json: bool,
+) -> Result<Outcome> {
+    // Detect if stdout is a TTY (for brew-compatible behavior); no spinner under --json
+    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout()) && !json;
 
     // Show version info in TTY, suppress when piped (brew behavior)
     // --quiet forces names-only even in TTY
     let show_versions = is_tty && !quiet;
 
-    if cask {
-        // Check outdated casks
-        let installed_casks = crate::cask::list_installed_casks()?;
+    // If neither specified, check both (brew behavior)
+    let check_formulae = formula || !cask;
+    let check_casks = cask || !formula;
+    let show_headers = check_formulae && check_casks && show_versions;
+
+    // Show spinner in TTY mode
+    let spinner = if is_tty {
+        let pb = ProgressBar::new_spinner();
+        pb.set_style(
+            ProgressStyle::default_spinner()
+                .template("{spinner:.cyan} {msg}")
+                .unwrap(),
+        );
+        pb.set_message(match (check_formulae, check_casks) {
+            (true, false) => "Checking for outdated packages...",
+            (false, true) => "Checking for outdated casks...",
+            _ => "Checking for outdated packages and casks...",
+        });
+        pb.enable_steady_tick(std::time::Duration::from_millis(100));
+        pb
+    } else {
+        ProgressBar::hidden()
+    };
 
-        if installed_casks.is_empty() {
-            return Ok(());
+    // Fetch formulae and casks concurrently
+    let (outdated_packages, outdated_casks) = tokio::join!(
+        async {
+            if check_formulae {
+                outdated_formulae(api).await
+            } else {
+                Ok(Vec::new())
+            }
+        },
+        async {
+            if check_casks {
+                outdated_casks(api).await
+            } else {
+                Ok(Vec::new())
+            }
         }
+    );
 
-        // Show spinner in TTY mode
-        let spinner = if is_tty {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap(),
-            );
-            pb.set_message("Checking for outdated casks...");
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            pb
-        } else {
-            ProgressBar::hidden()
-        };
+    spinner.finish_and_clear();
 
-        // Fetch all cask versions in parallel
-        let fetch_futures: Vec<_> = installed_casks
-            .iter()
-            .map(|(token, installed_version)| {
-                let token = token.clone();
-                let installed_version = installed_version.clone();
-                async move {
-                    // Check if cask has a newer version available
-                    if let Ok(cask) = api.fetch_cask(&token).await
-                        && let Some(latest) = &cask.version
-                        && latest != &installed_version
-                    {
-                        return Some((token, installed_version, latest.clone()));
-                    }
-                    None
-                }
-            })
-            .collect();
+    // Pinned formulae and casks are still listed, but they're held on purpose so they
+    // don't count as available updates
+    let pinned = if check_formulae {
+        super::utils::read_pinned(false)?
+    } else {
+        Vec::new()
+    };
+    let pinned_casks = if check_casks {
+        super::utils::read_pinned(true)?
+    } else {
+        Vec::new()
+    };
+    let (outdated_packages, outdated_casks) = (outdated_packages?, outdated_casks?);
+    let outcome = outdated_outcome(&outdated_packages, &pinned, &outdated_casks, &pinned_casks);
 
-        let results = futures::future::join_all(fetch_futures).await;
-        let outdated_casks: Vec<_> = results.into_iter().flatten().collect();
+    if json {
+        let entries = |outdated: Vec<(String, String, String)>, pinned: &[String]| {
+            outdated
+                .into_iter()
+                .map(|(name, installed, latest)| {
+                    let is_pinned = pinned.contains(&name);
+                    serde_json::json!({
+                        "name": name,
+                        "installed_versions": [installed],
+                        "current_version": latest,
+                        "pinned": is_pinned,
+                        "pinned_version": is_pinned.then_some(installed),
+                    })
+                })
+                .collect::<Vec<_>>()
+        };
+        let (formulae, casks) = (
+            entries(outdated_packages, &pinned),
+            entries(outdated_casks, &pinned_casks),
+        );
+        output::print_json(&serde_json::json!({
+            "formulae": formulae,
+            "casks": casks,
+        }))?;
+        return Ok(outcome);
+    }
 
-        spinner.finish_and_clear();
+    let sections = [
+        (
+            "Formulae",
+            "package",
+            "packages",
+            outdated_packages,
+            &pinned,
+        ),
+        ("Casks", "cask", "casks", outdated_casks, &pinned_casks),
+    ];
+    let mut printed_section = false;
+
+    for (header, singular, plural, outdated, pinned) in &sections {
+        if outdated.is_empty() {
+            continue;
+        }
 
-        if outdated_casks.is_empty() {
-            return Ok(());
+        if show_headers {
+            if printed_section {
+                println!(); // Spacing
+            }
+            println!("{}", format!("==> {}", header).bold().green());
         }
+        printed_section = true;
 
-        for (token, installed, latest) in &outdated_casks {
+        for (name, installed, latest) in outdated {
             if show_versions {
                 // TTY mode: show versions in brew format
+                let pin = if pinned.contains(name) {
+                    format!(" [pinned at {}]", installed).yellow().to_string()
+                } else {
+                    String::new()
+                };
                 println!(
-                    "{} ({}) < {}",
-                    token.bold().green(),
+                    "{} ({}) < {}{}",
+                    name.bold().green(),
                     installed.dimmed(),
-                    latest.cyan()
+                    latest.cyan(),
+                    pin
                 );
             } else {
                 // Piped/quiet mode: just names (brew behavior)
-                println!("{}", token);
+                println!("{}", name);
             }
         }
 
         // Show summary in TTY mode
         if show_versions {
-            let count = outdated_casks.len();
+            let count = outdated.len();
             println!(
                 "{} outdated {} found",
                 count.to_string().bold(),
-                if count == 1 { "cask" } else { "casks" }
+                if count == 1 { singular } else { plural }
             );
         }
-    } else {
-        // Check outdated formulae
-        let all_packages = cellar::list_installed()?;
+    }
 
-        if all_packages.is_empty() {
-            return Ok(());
-        }
+    Ok(outcome)
+}
 
-        // Deduplicate multiple versions - keep only the most recent for each formula
-        let mut package_map: HashMap<String, cellar::InstalledPackage> =
-            HashMap::with_capacity(all_packages.len());
-
-        for pkg in all_packages {
-            package_map
-                .entry(pkg.name.clone())
-                .and_modify(|existing| {
-                    // Compare modification times - keep the more recent one
-                    if let (Ok(existing_meta), Ok(pkg_meta)) = (
-                        std::fs::metadata(&existing.path),
-                        std::fs::metadata(&pkg.path),
-                    ) && let (Ok(existing_time), Ok(pkg_time)) =
-                        (existing_meta.modified(), pkg_meta.modified())
-                        && pkg_time > existing_time
-                    {
-                        *existing = pkg.clone();
-                    }
-                })
-                .or_insert(pkg);
-        }
+/// [`Outcome::UpdatesAvailable`] unless every outdated formula and cask is pinned
+fn outdated_outcome(
+    formulae: &[(String, String, String)],
+    pinned: &[String],
+    casks: &[(String, String, String)],
+    pinned_casks: &[String],
+) -> Outcome {
+    let held = |outdated: &[(String, String, String)], pinned: &[String]| {
+        outdated.iter().all(|(name, _, _)| pinned.contains(name))
+    };
+    if held(formulae, pinned) && held(casks, pinned_casks) {
+        Outcome::Done
+    } else {
+        Outcome::UpdatesAvailable
+    }
+}
 
-        let packages: Vec<_> = package_map.into_values().collect();
+/// Find outdated formulae as `(name, installed, latest)`
+async fn outdated_formulae(api: &BrewApi) -> Result<Vec<(String, String, String)>> {
+    // Keep only the current (highest) version of each formula
+    let packages = cellar::list_current()?;
 
-        // Show spinner in TTY mode
-        let spinner = if is_tty {
-            let pb = ProgressBar::new_spinner();
-            pb.set_style(
-                ProgressStyle::default_spinner()
-                    .template("{spinner:.cyan} {msg}")
-                    .unwrap(),
-            );
-            pb.set_message("Checking for outdated packages...");
-            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-            pb
-        } else {
-            ProgressBar::hidden()
-        };
+    if packages.is_empty() {
+        return Ok(Vec::new());
+    }
 
-        // Fetch all formula versions in parallel
-        let fetch_futures: Vec<_> = packages
-            .iter()
-            .map(|pkg| async move {
-                // Hybrid approach: check tap for freshness, use API for accuracy
-                // Tap parsing may be incomplete for complex formulas (e.g., bash with patches)
-                // but is always up-to-date. API is complete but may lag.
-
-                // Try API first (complete and accurate)
-                if let Ok(formula) = api.fetch_formula(&pkg.name).await
-                    && let Some(api_version) = &formula.versions.stable
-                {
-                    // Strip bottle revisions for comparison (e.g., "6.9.3_1" -> "6.9.3")
-                    // Bottle revisions indicate rebuilds, not version upgrades
-                    let installed_base = pkg.version.split('_').next().unwrap_or(&pkg.version);
-                    let api_base = api_version.split('_').next().unwrap_or(api_version);
-
-                    // Only flag as outdated if the base version changed
-                    if installed_base != api_base {
-                        return Some((pkg.clone(), api_version.clone()));
-                    }
-                    return None;
+    // Fetch all formula versions in parallel
+    let fetch_futures: Vec<_> = packages
+        .iter()
+        .map(|pkg| async move {
+            // Hybrid approach: check tap for freshness, use API for accuracy
+            // Tap parsing may be incomplete for complex formulas (e.g., bash with patches)
+            // but is always up-to-date. API is complete but may lag.
+
+            // Try API first (complete and accurate)
+            if let Ok(formula) = api.fetch_formula(&pkg.name).await
+                && let Some(api_version) = &formula.versions.stable
+            {
+                // Strip bottle revisions for comparison (e.g., "6.9.3_1" -> "6.9.3")
+                // Bottle revisions indicate rebuilds, not version upgrades
+                let installed_base = pkg.version.split('_').next().unwrap_or(&pkg.version);
+                let api_base = api_version.split('_').next().unwrap_or(api_version);
+
+                // Only flag as outdated if the base version changed
+                if installed_base != api_base {
+                    return Some((pkg.name.clone(), pkg.version.clone(), api_version.clone()));
                 }
+                return None;
+            }
 
-                // API unavailable - fall back to tap parsing
-                // This ensures we still work when offline or if API is down
-                if let Ok(Some(tap_ver)) = crate::tap::get_core_formula_version(&pkg.name) {
-                    // Strip bottle revisions for comparison
-                    let installed_base = pkg.version.split('_').next().unwrap_or(&pkg.version);
-                    let tap_base = tap_ver.split('_').next().unwrap_or(&tap_ver);
+            // API unavailable - fall back to tap parsing
+            // This ensures we still work when offline or if API is down
+            if let Ok(Some(tap_ver)) = crate::tap::get_core_formula_version(&pkg.name) {
+                // Strip bottle revisions for comparison
+                let installed_base = pkg.version.split('_').next().unwrap_or(&pkg.version);
+                let tap_base = tap_ver.split('_').next().unwrap_or(&tap_ver);
 
-                    if installed_base != tap_base {
-                        return Some((pkg.clone(), tap_ver));
-                    }
+                if installed_base != tap_base {
+                    return Some((pkg.name.clone(), pkg.version.clone(), tap_ver));
                 }
+            }
 
-                None
-            })
-            .collect();
-
-        let results = futures::future::join_all(fetch_futures).await;
-        let outdated_packages: Vec<_> = results.into_iter().flatten().collect();
-
-        spinner.finish_and_clear();
+            None
+        })
+        .collect();
 
-        if outdated_packages.is_empty() {
-            return Ok(());
-        }
+    let results = futures::future::join_all(fetch_futures).await;
+    Ok(results.into_iter().flatten().collect())
+}
 
-        for (pkg, latest) in &outdated_packages {
-            if show_versions {
-                // TTY mode: show versions in brew format
-                println!(
-                    "{} ({}) < {}",
-                    pkg.name.bold().green(),
-                    pkg.version.dimmed(),
-                    latest.cyan()
-                );
-            } else {
-                // Piped/quiet mode: just names (brew behavior)
-                println!("{}", pkg.name);
+/// Find outdated casks as `(token, installed, latest)`
+async fn outdated_casks(api: &BrewApi) -> Result<Vec<(String, String, String)>> {
+    let installed_casks = crate::cask::list_installed_casks()?;
+
+    // Fetch all cask versions in parallel
+    let fetch_futures: Vec<_> = installed_casks
+        .into_iter()
+        .map(|(token, installed_version)| async move {
+            // Check if cask has a newer version available
+            if let Ok(cask) = api.fetch_cask(&token).await
+                && let Some(latest) = &cask.version
+                && latest != &installed_version
+            {
+                return Some((token, installed_version, latest.clone()));
             }
-        }
-
-        // Show summary in TTY mode
-        if show_versions {
-            let count = outdated_packages.len();
-            println!(
-                "{} outdated {} found",
-                count.to_string().bold(),
-                if count == 1 { "package" } else { "packages" }
-            );
-        }
-    }
+            None
+        })
+        .collect();
 
-    Ok(())
+    let results = futures::future::join_all(fetch_futures).await;
+    Ok(results.into_iter().flatten().collect())
 }
 
 /// Find leaf packages (packages not required by any other packages)
 ///
 /// Useful for identifying packages that can be safely removed without
-/// breaking dependencies.
-pub fn leaves() -> Result<()> {
+/// breaking dependencies. With `json`, prints an array of
+/// `{"name", "version", "installed_on_request"}` objects for the kept keg of each leaf.
+pub fn leaves(json: bool) -> Result<()> {
     // Detect if stdout is a TTY (for brew-compatible behavior)
-    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout()) && !json;
 
     if is_tty {
         println!("{}", "==> Leaf Packages".bold().green());
@@ -494,31 +730,8 @@ pub fn leaves() -> Result<()> {
         println!();
     }
 
-    let all_packages = cellar::list_installed()?;
-
-    // Deduplicate by package name - keep only the most recent version of each
-    let mut package_map: HashMap<String, cellar::InstalledPackage> =
-        HashMap::with_capacity(all_packages.len());
-
-    for pkg in all_packages {
-        package_map
-            .entry(pkg.name.clone())
-            .and_modify(|existing| {
-                // Compare modification times - keep the more recent one
-                if let (Ok(existing_meta), Ok(pkg_meta)) = (
-                    std::fs::metadata(&existing.path),
-                    std::fs::metadata(&pkg.path),
-                ) && let (Ok(existing_time), Ok(pkg_time)) =
-                    (existing_meta.modified(), pkg_meta.modified())
-                    && pkg_time > existing_time
-                {
-                    *existing = pkg.clone();
-                }
-            })
-            .or_insert(pkg);
-    }
-
-    let unique_packages: Vec<_> = package_map.into_values().collect();
+    // Keep only the current (highest) version of each formula
+    let unique_packages = cellar::list_current()?;
 
     // Build a set of all packages that are dependencies of others
     let mut required_by_others = HashSet::with_capacity(unique_packages.len());
@@ -536,6 +749,26 @@ pub fn leaves() -> Result<()> {
 
     leaves.sort_by(|a, b| a.name.cmp(&b.name));
 
+    if json {
+        #[derive(serde::Serialize)]
+        struct Leaf<'a> {
+            name: &'a str,
+            version: &'a str,
+            installed_on_request: bool,
+        }
+
+        let leaves: Vec<_> = leaves
+            .iter()
+            .map(|pkg| Leaf {
+                name: &pkg.name,
+                version: &pkg.version,
+                installed_on_request: pkg.installed_on_request(),
+            })
+            .collect();
+        output::print_json(&leaves)?;
+        return Ok(());
+    }
+
     if leaves.is_empty() {
         if is_tty {
             println!("No leaf packages found");
@@ -630,3 +863,35 @@ pub fn missing(formula_names: &[String]) -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outdated(names: &[&str]) -> Vec<(String, String, String)> {
+        names
+            .iter()
+            .map(|name| (name.to_string(), "1.0".to_string(), "2.0".to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_pinned_formulae_dont_count_as_updates() {
+        let pinned = vec!["node".to_string()];
+
+        assert_eq!(
+            outdated_outcome(&outdated(&["node"]), &pinned, &[], &[]),
+            Outcome::Done
+        );
+        assert_eq!(
+            outdated_outcome(&outdated(&["node", "jq"]), &pinned, &[], &[]),
+            Outcome::UpdatesAvailable
+        );
+        // A formula pin doesn't hold back a cask of the same name
+        assert_eq!(
+            outdated_outcome(&[], &pinned, &outdated(&["node"]), &[]),
+            Outcome::UpdatesAvailable
+        );
+        assert_eq!(outdated_outcome(&[], &[], &[], &[]), Outcome::Done);
+    }
+}
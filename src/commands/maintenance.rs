@@ -10,21 +10,28 @@
 //! - `update_report`: Show recent tap changes
 //! - `update_if_needed`: Conditionally update if stale
 
+use super::utils::format_size;
 use crate::cellar;
+use crate::cleanup;
 use crate::download;
 use crate::error::Result;
 use crate::symlink;
 use colored::Colorize;
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::collections::{HashSet, VecDeque};
+use std::path::{Path, PathBuf};
 
 /// Remove unused dependencies that were installed automatically
 ///
 /// Performs a breadth-first traversal of the dependency graph to identify
 /// packages that were installed as dependencies but are no longer needed.
-pub fn autoremove(dry_run: bool) -> Result<()> {
-    if dry_run {
+/// Formulae an installed cask depends on (`depends_on formula:`) are kept.
+///
+/// With `json` (only offered together with `dry_run`), prints the candidates with their
+/// disk usage instead of the human-readable report.
+pub fn autoremove(dry_run: bool, json: bool) -> Result<()> {
+    if dry_run && !json {
         println!("Dry run - no packages will be removed");
-    } else {
+    } else if !dry_run {
         println!("Removing unused dependencies...");
     }
 
@@ -43,6 +50,14 @@ pub fn autoremove(dry_run: bool) -> Result<()> {
     let mut to_check: VecDeque<String> = on_request.iter().cloned().collect();
     let mut checked = HashSet::new();
 
+    // Casks aren't in the Cellar, but the formulae they depend on are still in use
+    for (token, _) in crate::cask::list_installed_casks().unwrap_or_default() {
+        for formula in crate::cask::installed_cask_formula_dependencies(&token) {
+            required.insert(formula.clone());
+            to_check.push_back(formula);
+        }
+    }
+
     // Traverse dependency graph using receipts only (matches Homebrew behavior)
     // NO network calls - instant operation
     while let Some(name) = to_check.pop_front() {
@@ -67,13 +82,33 @@ pub fn autoremove(dry_run: bool) -> Result<()> {
         .filter(|pkg| !pkg.installed_on_request() && !required.contains(&pkg.name))
         .collect();
 
+    to_remove.sort_by(|a, b| a.name.cmp(&b.name));
+
+    if json {
+        #[derive(serde::Serialize)]
+        struct Candidate<'a> {
+            name: &'a str,
+            version: &'a str,
+            size_bytes: u64,
+        }
+
+        let candidates: Vec<_> = to_remove
+            .iter()
+            .map(|pkg| Candidate {
+                name: &pkg.name,
+                version: &pkg.version,
+                size_bytes: cellar::keg_size(&pkg.path).unwrap_or(0),
+            })
+            .collect();
+        crate::output::print_json(&candidates)?;
+        return Ok(());
+    }
+
     if to_remove.is_empty() {
         println!("{} No unused dependencies to remove", "".green());
         return Ok(());
     }
 
-    to_remove.sort_by(|a, b| a.name.cmp(&b.name));
-
     println!(
         "Found {} unused dependencies:",
         to_remove.len().to_string().bold()
@@ -172,268 +207,158 @@ pub fn autoremove(dry_run: bool) -> Result<()> {
 /// Remove old versions of installed packages
 ///
 /// Keeps the linked version and the newest version, removes everything else.
-/// This matches Homebrew's cleanup behavior.
-pub fn cleanup(formula_names: &[String], dry_run: bool, cask: bool) -> Result<()> {
-    if cask {
-        return super::cask::cleanup_cask(formula_names, dry_run);
-    }
-
-    let all_packages = cellar::list_installed()?;
-
-    // Group packages by formula name
-    let mut by_formula: HashMap<String, Vec<&cellar::InstalledPackage>> = HashMap::new();
-    for pkg in &all_packages {
-        by_formula.entry(pkg.name.clone()).or_default().push(pkg);
+/// This matches Homebrew's cleanup behavior. The work itself is done by
+/// [`cleanup::cleanup_in`]; this only reports it.
+pub fn cleanup(
+    formula_names: &[String],
+    dry_run: bool,
+    cask: bool,
+    prune_prefix: bool,
+    scrub: bool,
+) -> Result<()> {
+    if prune_prefix {
+        return prune_prefix_links_and_dirs(dry_run);
     }
 
-    // Filter to specified formulae if provided
-    let to_clean: Vec<_> = if formula_names.is_empty() {
-        by_formula.keys().cloned().collect()
-    } else {
-        formula_names.to_vec()
-    };
-
-    let mut total_removed = 0;
-    let mut total_space_freed = 0u64;
-
     if dry_run {
         println!("Dry run - no files will be removed");
+    } else if cask {
+        println!("Cleaning up old cask versions...");
     } else {
         println!("Cleaning up old versions...");
     }
 
-    for formula in &to_clean {
-        let versions = match by_formula.get(formula) {
-            Some(v) => v,
-            None => {
-                if !formula_names.is_empty() {
-                    println!("  {} {} not installed", "".yellow(), formula.bold());
-                }
-                continue;
-            }
-        };
-
-        if versions.len() <= 1 {
-            continue;
-        }
-
-        // Determine which versions to keep:
-        // 1. Always keep the linked version (active installation)
-        // 2. Keep the newest version (may be same as linked)
-        // This matches Homebrew's behavior of preserving the installed version
-
-        let linked_version = symlink::get_linked_version(formula).ok().flatten();
-
-        // Sort by version to find the newest
-        let mut sorted_versions = versions.to_vec();
-        sorted_versions.sort_by(|a, b| {
-            // Try to parse as semantic version numbers
-            let a_parts: Vec<u32> = a
-                .version
-                .split('.')
-                .filter_map(|s| s.parse::<u32>().ok())
-                .collect();
-            let b_parts: Vec<u32> = b
-                .version
-                .split('.')
-                .filter_map(|s| s.parse::<u32>().ok())
-                .collect();
-
-            // Compare version parts numerically
-            for i in 0..a_parts.len().max(b_parts.len()) {
-                let a_part = a_parts.get(i).unwrap_or(&0);
-                let b_part = b_parts.get(i).unwrap_or(&0);
-                match a_part.cmp(b_part) {
-                    std::cmp::Ordering::Equal => continue,
-                    other => return other,
-                }
-            }
-
-            // If numeric comparison fails, fall back to lexicographic
-            a.version.cmp(&b.version)
-        });
-        sorted_versions.reverse(); // Highest version first
-
-        let newest_version = sorted_versions[0];
-
-        // Collect versions to keep
-        let mut versions_to_keep = vec![newest_version];
-        if let Some(ref linked_ver) = linked_version
-            && let Some(linked_pkg) = sorted_versions.iter().find(|v| &v.version == linked_ver)
-            && linked_pkg.version != newest_version.version
+    let prefix = cellar::detect_prefix();
+    for name in formula_names {
+        if !prefix.join("Cellar").join(name).is_dir()
+            && !prefix.join("Caskroom").join(name).is_dir()
         {
-            versions_to_keep.push(linked_pkg);
+            println!("  {} {} not installed", "".yellow(), name.bold());
         }
+    }
 
-        // Everything else can be removed
-        let old_versions: Vec<_> = sorted_versions
-            .iter()
-            .filter(|v| {
-                !versions_to_keep
-                    .iter()
-                    .any(|keep| keep.version == v.version)
-            })
-            .copied()
-            .collect();
-
-        // Skip if no old versions to remove
-        if old_versions.is_empty() {
-            continue;
-        }
+    let result = if cask {
+        cleanup::cleanup_casks_in(&prefix, formula_names, dry_run)?
+    } else {
+        cleanup::cleanup_in(&prefix, formula_names, dry_run)?
+    };
 
-        // Show which versions we're keeping
-        if dry_run {
-            for keep in &versions_to_keep {
-                let marker = if Some(&keep.version) == linked_version.as_ref() {
-                    "(linked)"
-                } else {
-                    "(newest)"
-                };
-                println!(
-                    "  Keeping {} {} {}",
-                    keep.name.cyan().bold(),
-                    keep.version.green(),
-                    marker.dimmed()
-                );
-            }
+    if !cask {
+        print_cleaned_versions(&result.removed, "old versions", dry_run);
+    }
+    // Cask results are only worth a section when asked for or when something was found
+    if cask || formula_names.is_empty() || !result.removed_casks.is_empty() {
+        if !cask {
+            println!();
         }
+        print_cleaned_versions(&result.removed_casks, "old cask versions", dry_run);
+    }
 
-        // Collect metadata for all versions to remove
-        let mut removal_tasks: Vec<(String, String, std::path::PathBuf, u64)> = Vec::new();
+    for (version, error) in &result.errors {
+        println!(
+            "  {} Failed to remove {}: {}",
+            "".red(),
+            version.bold(),
+            error
+        );
+    }
 
-        for old in old_versions {
-            let version_path = cellar::cellar_path().join(&old.name).join(&old.version);
-            let size = calculate_dir_size(&version_path)?;
-            total_space_freed += size;
+    if scrub {
+        scrub_download_cache(dry_run, result.total_freed_bytes)?;
+    }
 
-            removal_tasks.push((old.name.clone(), old.version.clone(), version_path, size));
-        }
+    if !result.errors.is_empty() {
+        return Err(
+            anyhow::anyhow!("Failed to remove {} old versions", result.errors.len()).into(),
+        );
+    }
+    Ok(())
+}
 
+/// Print the versions cleanup removed (or would remove) followed by a total
+fn print_cleaned_versions(versions: &[cleanup::CleanedVersion], what: &str, dry_run: bool) {
+    for version in versions {
         if dry_run {
-            // Dry run: just show what would be removed
-            for (name, version, _, size) in &removal_tasks {
-                println!(
-                    "  Would remove {} {} ({})",
-                    name.cyan(),
-                    version.dimmed(),
-                    format_size(*size).dimmed()
-                );
-                total_removed += 1;
-            }
+            println!(
+                "  Would remove {} {} ({})",
+                version.name.cyan(),
+                version.version.dimmed(),
+                format_size(version.size).dimmed()
+            );
         } else {
-            // Phase 1: Unlink all symlinks sequentially (touches shared directories)
-            for (name, version, _, _) in &removal_tasks {
-                let unlinked = symlink::unlink_formula(name, version)?;
-                if !unlinked.is_empty() {
-                    println!(
-                        "  {} Unlinked {} symlinks from {} {}",
-                        "".green(),
-                        unlinked.len().to_string().dimmed(),
-                        name.cyan(),
-                        version.dimmed()
-                    );
-                }
-            }
-
-            // Phase 2: Delete directories in parallel
-            use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-            use std::sync::Arc;
-            use std::sync::atomic::{AtomicUsize, Ordering};
-
-            let multi_progress = MultiProgress::new();
-            let completed = Arc::new(AtomicUsize::new(0));
-            let total = removal_tasks.len();
-
-            // Create deletion tasks
-            let handles: Vec<_> = removal_tasks
-                .into_iter()
-                .map(|(name, version, version_path, size)| {
-                    let multi_progress = multi_progress.clone();
-                    let completed = Arc::clone(&completed);
-
-                    std::thread::spawn(move || {
-                        // Show spinner for deletions > 10 MB
-                        let show_spinner = size > 10 * 1024 * 1024;
-                        let spinner = if show_spinner {
-                            let pb = multi_progress.add(ProgressBar::new_spinner());
-                            pb.set_style(
-                                ProgressStyle::default_spinner()
-                                    .template(&format!(
-                                        "  {{spinner:.cyan}} Removing {} {} ({})",
-                                        name,
-                                        version,
-                                        format_size(size)
-                                    ))
-                                    .unwrap(),
-                            );
-                            pb.enable_steady_tick(std::time::Duration::from_millis(100));
-                            pb
-                        } else {
-                            ProgressBar::hidden()
-                        };
-
-                        // Remove directory
-                        let result = if version_path.exists() {
-                            std::fs::remove_dir_all(&version_path)
-                        } else {
-                            Ok(())
-                        };
+            println!(
+                "  {} Removed {} {} ({})",
+                "".green(),
+                version.name.cyan(),
+                version.version.dimmed(),
+                format_size(version.size).dimmed()
+            );
+        }
+    }
 
-                        if show_spinner {
-                            spinner.finish_and_clear();
-                        }
+    let freed: u64 = versions.iter().map(|version| version.size).sum();
+    if versions.is_empty() {
+        println!("{} No {} to remove", "".green(), what);
+    } else if dry_run {
+        println!(
+            "{} Would remove {} {} ({})",
+            "".dimmed(),
+            versions.len().to_string().bold(),
+            what,
+            format_size(freed).bold()
+        );
+    } else {
+        println!(
+            "{} Removed {} {}, freed {}",
+            "".green().bold(),
+            versions.len().to_string().bold(),
+            what,
+            format_size(freed).bold()
+        );
+    }
+}
 
-                        let count = completed.fetch_add(1, Ordering::Relaxed) + 1;
-                        println!(
-                            "  {} Removed {} {} ({}) [{}/{}]",
-                            "".green(),
-                            name.cyan(),
-                            version.dimmed(),
-                            format_size(size).dimmed(),
-                            count,
-                            total
-                        );
-
-                        result
-                    })
-                })
-                .collect();
+/// Remove dangling Homebrew symlinks and empty directories from the prefix
+///
+/// Only links into the Cellar/Caskroom/opt and empty directories under the linkable
+/// directories are touched (see `symlink::prune_broken` and `symlink::prune_empty_dirs`).
+fn prune_prefix_links_and_dirs(dry_run: bool) -> Result<()> {
+    if dry_run {
+        println!("Dry run - no files will be removed");
+    } else {
+        println!("Pruning prefix...");
+    }
 
-            // Wait for all deletions to complete
-            for handle in handles {
-                handle.join().unwrap()?;
-            }
+    let links = symlink::prune_broken(dry_run)?;
+    let dirs = symlink::prune_empty_dirs(dry_run)?;
 
-            total_removed = total;
+    if dry_run {
+        for link in &links {
+            println!("  Would remove broken symlink {}", link.display());
+        }
+        for dir in &dirs {
+            println!("  Would remove empty directory {}", dir.display());
         }
     }
 
-    if total_removed == 0 {
-        println!("{} No old versions to remove", "".green());
+    if links.is_empty() && dirs.is_empty() {
+        println!("{} Nothing to prune", "".green());
     } else if dry_run {
         println!(
-            "{} Would remove {} old versions ({})",
+            "{} Would remove {} broken symlinks and {} empty directories",
             "".dimmed(),
-            total_removed.to_string().bold(),
-            format_size(total_space_freed).bold()
+            links.len().to_string().bold(),
+            dirs.len().to_string().bold()
         );
     } else {
         println!(
-            "{} Removed {} old versions, freed {}",
+            "{} Removed {} broken symlinks and {} empty directories",
             "".green().bold(),
-            total_removed.to_string().bold(),
-            format_size(total_space_freed).bold()
+            links.len().to_string().bold(),
+            dirs.len().to_string().bold()
         );
     }
 
-    // If cleaning everything (no specific args), also clean casks
-    if formula_names.is_empty() {
-        println!();
-        if let Err(e) = super::cask::cleanup_cask(&[], dry_run) {
-            println!("{} Failed to clean casks: {}", "".yellow(), e);
-        }
-    }
-
     Ok(())
 }
 
@@ -449,20 +374,8 @@ pub fn cache(clean: bool) -> Result<()> {
             return Ok(());
         }
 
-        // Calculate size before cleaning
-        let total_size = calculate_dir_size(&cache_dir)?;
-
         // Remove all bottles from cache
-        let mut removed_count = 0;
-        for entry in std::fs::read_dir(&cache_dir)? {
-            let entry = entry?;
-            let path = entry.path();
-
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("gz") {
-                std::fs::remove_file(&path)?;
-                removed_count += 1;
-            }
-        }
+        let (removed_count, total_size) = remove_cached_bottles(false)?;
 
         println!(
             "{} Removed {} bottles, freed {}",
@@ -513,6 +426,132 @@ pub fn cache(clean: bool) -> Result<()> {
     Ok(())
 }
 
+/// Remove downloaded bottles from the cache, returning how many and their total size
+///
+/// With `dry_run`, only counts what would be removed.
+fn remove_cached_bottles(dry_run: bool) -> Result<(usize, u64)> {
+    let cache_dir = download::cache_dir();
+    if !cache_dir.exists() {
+        return Ok((0, 0));
+    }
+
+    let mut removed_count = 0;
+    let mut removed_size = 0u64;
+    for entry in std::fs::read_dir(&cache_dir)? {
+        let entry = entry?;
+        let path = entry.path();
+
+        if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("gz") {
+            removed_size += std::fs::metadata(&path)?.len();
+            if !dry_run {
+                std::fs::remove_file(&path)?;
+            }
+            removed_count += 1;
+        }
+    }
+
+    Ok((removed_count, removed_size))
+}
+
+/// Scrub the download cache as part of `cleanup --scrub`, reporting the combined total
+fn scrub_download_cache(dry_run: bool, kegs_freed: u64) -> Result<()> {
+    println!();
+    let (count, size) = remove_cached_bottles(dry_run)?;
+
+    if count == 0 {
+        println!("{} Download cache is already empty", "".green());
+    } else if dry_run {
+        println!(
+            "{} Would remove {} cached bottles ({})",
+            "".dimmed(),
+            count.to_string().bold(),
+            format_size(size).bold()
+        );
+    } else {
+        println!(
+            "{} Removed {} cached bottles, freed {}",
+            "".green().bold(),
+            count.to_string().bold(),
+            format_size(size).bold()
+        );
+    }
+
+    let total = kegs_freed + size;
+    if total > 0 {
+        println!(
+            "{} {} {} in total",
+            "".green().bold(),
+            if dry_run { "Would free" } else { "Freed" },
+            format_size(total).bold()
+        );
+    }
+
+    Ok(())
+}
+
+/// `.pc` files in a keg's `lib/pkgconfig` that `<prefix>/lib/pkgconfig` doesn't
+/// resolve to this keg's copy (missing, broken, or owned by another formula)
+fn unlinked_pkgconfig_files(prefix: &Path, keg: &Path) -> Vec<String> {
+    let linked_dir = prefix.join("lib/pkgconfig");
+    let mut missing: Vec<String> = std::fs::read_dir(keg.join("lib/pkgconfig"))
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "pc"))
+        .filter(|entry| {
+            let linked = std::fs::canonicalize(linked_dir.join(entry.file_name()));
+            linked.ok() != std::fs::canonicalize(entry.path()).ok()
+        })
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect();
+    missing.sort();
+    missing
+}
+
+/// Directories under the prefix that Homebrew manages and must own
+///
+/// The prefix itself is left out: `/usr/local` on Intel Macs is owned by root by
+/// design, and only these subdirectories need to belong to the user.
+fn homebrew_owned_dirs(prefix: &Path) -> Vec<PathBuf> {
+    [
+        "Cellar",
+        "Caskroom",
+        "var/homebrew",
+        "bin",
+        "lib",
+        "share",
+        "etc",
+        "opt",
+    ]
+    .iter()
+    .map(|dir| prefix.join(dir))
+    .collect()
+}
+
+/// Directories not owned by `uid`, with their current owner; missing ones are skipped
+fn foreign_owned_dirs(dirs: &[PathBuf], uid: u32) -> Vec<(PathBuf, u32)> {
+    use std::os::unix::fs::MetadataExt;
+
+    dirs.iter()
+        .filter_map(|dir| {
+            let owner = std::fs::metadata(dir).ok()?.uid();
+            (owner != uid).then(|| (dir.to_path_buf(), owner))
+        })
+        .collect()
+}
+
+/// Whether the current user can create files in `dir`
+fn dir_is_writable(dir: &Path) -> bool {
+    let probe = dir.join(format!(".bru-doctor-{}", std::process::id()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+            true
+        }
+        Err(_) => false,
+    }
+}
+
 /// Check system health and configuration
 pub fn doctor() -> Result<()> {
     println!("{}", "==> System Health Check".bold().green());
@@ -598,6 +637,124 @@ pub fn doctor() -> Result<()> {
         );
     }
 
+    // Directories owned by another user (e.g. after `sudo bru install`) make
+    // every later install fail partway through
+    // SAFETY: getuid takes no arguments, cannot fail and has no side effects.
+    let uid = unsafe { libc::getuid() };
+    let var_homebrew = prefix.join("var/homebrew");
+    let foreign = foreign_owned_dirs(&homebrew_owned_dirs(&prefix), uid);
+    if foreign.is_empty() {
+        println!(
+            "  {} Prefix directories are owned by the current user",
+            "".green()
+        );
+    } else {
+        for (dir, owner) in &foreign {
+            println!(
+                "  {} {} is owned by uid {}, not the current user (uid {})",
+                "".red(),
+                dir.display(),
+                owner,
+                uid
+            );
+        }
+        let paths: Vec<String> = foreign
+            .iter()
+            .map(|(dir, _)| dir.display().to_string())
+            .collect();
+        println!(
+            "    {} Run: {}",
+            "→".dimmed(),
+            format!("sudo chown -R \"$(whoami)\" {}", paths.join(" ")).cyan()
+        );
+        issues += 1;
+    }
+
+    // var/homebrew holds pinned and linked-keg records
+    if !var_homebrew.exists() {
+        println!(
+            "  {} {} does not exist (created on first install)",
+            "".yellow(),
+            var_homebrew.display()
+        );
+    } else if !dir_is_writable(&var_homebrew) {
+        println!("  {} {} is not writable", "".red(), var_homebrew.display());
+        println!(
+            "    {} Run: {}",
+            "→".dimmed(),
+            format!("sudo chown -R \"$(whoami)\" {}", var_homebrew.display()).cyan()
+        );
+        issues += 1;
+    } else {
+        println!("  {} {} is writable", "".green(), var_homebrew.display());
+    }
+
+    println!();
+    println!("{}", "Checking shell environment...".bold());
+
+    let path_dirs: Vec<_> = std::env::var_os("PATH")
+        .map(|path| std::env::split_paths(&path).collect())
+        .unwrap_or_default();
+    let (shellenv_line, rc_file) = shellenv_hint();
+
+    match path_dirs.iter().position(|dir| same_dir(dir, &bin_dir)) {
+        None => {
+            println!("  {} {} is not in your PATH", "".red(), bin_dir.display());
+            println!("    Installed commands won't be found by your shell");
+            println!(
+                "    {} Add to {}: {}",
+                "→".dimmed(),
+                rc_file,
+                shellenv_line.cyan()
+            );
+            issues += 1;
+        }
+        Some(index) => {
+            // Installed tools that a system directory earlier in PATH would shadow
+            let earlier_system_dirs: Vec<_> = path_dirs[..index]
+                .iter()
+                .filter(|dir| {
+                    SYSTEM_BIN_DIRS
+                        .iter()
+                        .any(|sys| same_dir(dir, Path::new(sys)))
+                })
+                .collect();
+            let mut shadowed: Vec<String> = std::fs::read_dir(&bin_dir)
+                .into_iter()
+                .flatten()
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .filter(|name| {
+                    earlier_system_dirs
+                        .iter()
+                        .any(|dir| dir.join(name).exists())
+                })
+                .collect();
+            shadowed.sort();
+
+            if shadowed.is_empty() {
+                println!("  {} {} is in your PATH", "".green(), bin_dir.display());
+            } else {
+                println!(
+                    "  {} {} comes after system directories in your PATH",
+                    "".yellow(),
+                    bin_dir.display()
+                );
+                println!(
+                    "    System versions of these commands will run instead: {}",
+                    shadowed.join(", ").dimmed()
+                );
+                println!(
+                    "    {} Add to the end of {}: {}",
+                    "→".dimmed(),
+                    rc_file,
+                    shellenv_line.cyan()
+                );
+                warnings += 1;
+            }
+        }
+    }
+
     println!();
     println!("{}", "Checking dependencies...".bold());
 
@@ -668,8 +825,94 @@ pub fn doctor() -> Result<()> {
         warnings += 1;
     }
 
-    // Check for outdated packages
+    // Check for installed formulae that aren't linked (e.g. after a failed upgrade)
     let packages = cellar::list_installed()?;
+    let cached_formulae = crate::cache::get_cached_formulae().unwrap_or_default();
+    // Keg-only formulae are intentionally left unlinked
+    let is_keg_only = |name: &str| {
+        packages
+            .iter()
+            .filter(|pkg| pkg.name == name)
+            .find_map(|pkg| pkg.keg_only())
+            .or_else(|| {
+                cached_formulae
+                    .iter()
+                    .find(|f| f.name == name)
+                    .map(|f| f.keg_only)
+            })
+            .unwrap_or(false)
+    };
+    let mut unlinked: Vec<&str> = packages
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter(|name| matches!(symlink::linked_keg_version_in(&prefix, name), Ok(None)))
+        .filter(|name| !is_keg_only(name))
+        .collect();
+    unlinked.sort_unstable();
+
+    if unlinked.is_empty() {
+        println!("  {} All installed formulae are linked", "".green());
+    } else {
+        println!(
+            "  {} Found {} installed but unlinked formulae:",
+            "".yellow(),
+            unlinked.len()
+        );
+        for name in &unlinked {
+            println!(
+                "    - {} {} Run: {}",
+                name,
+                "→".dimmed(),
+                format!("bru link {}", name).cyan()
+            );
+        }
+        warnings += 1;
+    }
+
+    // Linked formulae whose pkg-config files aren't visible in lib/pkgconfig; keg-only
+    // ones are found through their opt/ path instead
+    let mut linked_names: Vec<&str> = packages
+        .iter()
+        .map(|pkg| pkg.name.as_str())
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .filter(|name| !is_keg_only(name))
+        .collect();
+    linked_names.sort_unstable();
+    let missing_pc: Vec<(&str, Vec<String>)> = linked_names
+        .into_iter()
+        .filter_map(|name| {
+            let version = symlink::linked_keg_version_in(&prefix, name)
+                .ok()
+                .flatten()?;
+            let missing = unlinked_pkgconfig_files(&prefix, &cellar.join(name).join(version));
+            (!missing.is_empty()).then_some((name, missing))
+        })
+        .collect();
+
+    if missing_pc.is_empty() {
+        println!("  {} All pkg-config files are linked", "".green());
+    } else {
+        println!(
+            "  {} Found {} formulae with unlinked pkg-config files:",
+            "".yellow(),
+            missing_pc.len()
+        );
+        for (name, files) in &missing_pc {
+            println!(
+                "    - {} ({}) {} Run: {}",
+                name,
+                files.join(", ").dimmed(),
+                "→".dimmed(),
+                format!("bru link --overwrite {}", name).cyan()
+            );
+        }
+        println!("    pkg-config won't find these libraries when building against them");
+        warnings += 1;
+    }
+
     println!("  {} packages installed", packages.len());
 
     println!();
@@ -693,6 +936,27 @@ pub fn doctor() -> Result<()> {
     Ok(())
 }
 
+/// System binary directories that can shadow commands installed into the prefix
+const SYSTEM_BIN_DIRS: &[&str] = &["/usr/bin", "/bin", "/usr/sbin", "/sbin"];
+
+/// Compare two directories, resolving symlinks when both exist
+fn same_dir(a: &Path, b: &Path) -> bool {
+    match (a.canonicalize(), b.canonicalize()) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// The `shellenv` line for the user's shell and the startup file it belongs in
+fn shellenv_hint() -> (&'static str, &'static str) {
+    let shell = std::env::var("SHELL").unwrap_or_default();
+    match Path::new(&shell).file_name().and_then(|name| name.to_str()) {
+        Some("zsh") => ("eval \"$(bru shellenv)\"", "~/.zshrc"),
+        Some("fish") => ("bru shellenv | source", "~/.config/fish/config.fish"),
+        _ => ("eval \"$(bru shellenv)\"", "~/.bashrc"),
+    }
+}
+
 /// Update all taps in parallel
 pub fn update() -> Result<()> {
     // Clear cached formula/cask data to ensure fresh results
@@ -1003,19 +1267,40 @@ fn calculate_dir_size(path: &std::path::Path) -> Result<u64> {
     Ok(total)
 }
 
-/// Format byte size as human-readable string
-fn format_size(bytes: u64) -> String {
-    const KB: u64 = 1024;
-    const MB: u64 = KB * 1024;
-    const GB: u64 = MB * 1024;
-
-    if bytes >= GB {
-        format!("{:.2} GB", bytes as f64 / GB as f64)
-    } else if bytes >= MB {
-        format!("{:.2} MB", bytes as f64 / MB as f64)
-    } else if bytes >= KB {
-        format!("{:.2} KB", bytes as f64 / KB as f64)
-    } else {
-        format!("{} bytes", bytes)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_foreign_owned_dirs_skips_prefix_root() {
+        use std::os::unix::fs::MetadataExt;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path();
+        for dir in ["Cellar", "var/homebrew", "bin", "opt"] {
+            std::fs::create_dir_all(prefix.join(dir)).unwrap();
+        }
+        let uid = std::fs::metadata(prefix).unwrap().uid();
+
+        assert!(foreign_owned_dirs(&homebrew_owned_dirs(prefix), uid).is_empty());
+
+        // Seen by another user, every existing managed directory is foreign, but the
+        // prefix itself (root-owned /usr/local on Intel Macs) is never reported
+        let foreign: Vec<PathBuf> = foreign_owned_dirs(&homebrew_owned_dirs(prefix), uid + 1)
+            .into_iter()
+            .map(|(dir, owner)| {
+                assert_eq!(owner, uid);
+                dir
+            })
+            .collect();
+        assert_eq!(
+            foreign,
+            vec![
+                prefix.join("Cellar"),
+                prefix.join("var/homebrew"),
+                prefix.join("bin"),
+                prefix.join("opt"),
+            ]
+        );
     }
 }
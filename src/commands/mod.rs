@@ -27,6 +27,7 @@ pub mod developer;
 pub mod development;
 pub mod git;
 pub mod install;
+pub mod interrupt;
 pub mod linking;
 pub mod list;
 pub mod maintenance;
@@ -37,22 +38,53 @@ pub mod tap;
 pub mod utilities;
 pub(crate) mod utils;
 
+/// What a command achieved, beyond plain success or an error
+///
+/// `main` maps this to the process exit code, so scripts can tell "not found" or
+/// "some packages failed" apart from success.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// Everything requested was done
+    Done,
+    /// The requested formula or cask doesn't exist
+    NotFound,
+    /// Some of the requested packages failed, the rest succeeded
+    PartialFailure,
+    /// `outdated` found packages with newer versions
+    UpdatesAvailable,
+    /// Ctrl-C stopped the command after it cleaned up
+    Interrupted,
+}
+
+impl Outcome {
+    /// [`Done`](Outcome::Done) if nothing failed, otherwise
+    /// [`PartialFailure`](Outcome::PartialFailure)
+    pub fn from_failures(failed: usize) -> Self {
+        if failed == 0 {
+            Self::Done
+        } else {
+            Self::PartialFailure
+        }
+    }
+}
+
 // Re-export commonly used commands for convenience
 // This allows using `commands::search()` instead of `commands::query::search()`
 
 // Query commands
 pub use query::formula_info;
 pub use query::{
-    casks, cat, deps, desc, formula, formulae, info, options, search, unbottled, uses,
+    casks, cat, deps, desc, formula, formulae, info, info_json_array, options, search,
+    search_installed, unbottled, uses,
 };
 
 // Install commands
-pub use install::{fetch, install, reinstall, uninstall, upgrade};
+pub use install::{InstallFlags, fetch, install, install_order, reinstall, uninstall, upgrade};
 
 // Cask commands
 
 // List commands
-pub use list::{leaves, list, missing, outdated};
+pub use list::{ListSort, leaves, list, list_files, missing, outdated};
 
 // Maintenance commands
 
@@ -62,7 +94,7 @@ pub use list::{leaves, list, missing, outdated};
 pub use linking::{link, pin, postinstall, unlink, unpin};
 
 // Paths commands
-pub use paths::{cellar_cmd, config, env, prefix, repository, shellenv};
+pub use paths::{SystemConfig, cellar_cmd, config, env, prefix, repository, shellenv};
 
 // Services
 pub use services::services;
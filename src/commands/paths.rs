@@ -10,6 +10,7 @@
 use crate::cellar;
 use crate::error::Result;
 use colored::Colorize;
+use std::path::PathBuf;
 
 /// Display the Homebrew installation prefix.
 ///
@@ -75,67 +76,113 @@ pub fn repository(tap_name: Option<&str>) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Snapshot of the installation and environment, shared by `config` and `gist-logs`.
+///
+/// Serialized as-is for `bru config --json`, so field names are part of the output format.
+#[derive(Debug, serde::Serialize)]
+pub struct SystemConfig {
+    pub version: String,
+    /// Commit bru was built from (`unknown` outside a git checkout)
+    pub git_sha: String,
+    /// Date bru was built (`SOURCE_DATE_EPOCH` when set)
+    pub build_date: String,
+    /// Rust target triple bru was compiled for
+    pub target: String,
+    /// Bottle tag bru installs, or `None` on platforms without bottles
+    pub bottle_tag: Option<String>,
+    pub os: String,
+    pub arch: String,
+    pub prefix: PathBuf,
+    pub cellar: PathBuf,
+    pub taps: PathBuf,
+    pub cache: PathBuf,
+    /// Installed formula kegs (each installed version counts)
+    pub installed_packages: usize,
+    pub installed_taps: usize,
+}
+
+impl SystemConfig {
+    /// Gather paths, counts and platform details for the current prefix
+    pub fn collect() -> Result<Self> {
+        Ok(Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_sha: env!("BRU_GIT_SHA").to_string(),
+            build_date: env!("BRU_BUILD_DATE").to_string(),
+            target: env!("BRU_TARGET").to_string(),
+            bottle_tag: crate::platform::bottle_tag().ok(),
+            os: std::env::consts::OS.to_string(),
+            arch: std::env::consts::ARCH.to_string(),
+            prefix: cellar::detect_prefix(),
+            cellar: cellar::cellar_path(),
+            taps: crate::tap::taps_path(),
+            cache: crate::download::cache_dir(),
+            installed_packages: cellar::list_installed()?.len(),
+            installed_taps: crate::tap::list_taps()?.len(),
+        })
+    }
+}
+
 /// Display comprehensive system configuration information.
 ///
 /// Shows installation paths, statistics about installed packages and taps,
-/// and system information like version and architecture.
-pub fn config() -> Result<()> {
+/// and system information like version and architecture. With `json`, prints
+/// the [`SystemConfig`] object instead of the table.
+pub fn config(json: bool) -> Result<()> {
+    let config = SystemConfig::collect()?;
+    if json {
+        crate::output::print_json(&config)?;
+        return Ok(());
+    }
+
     println!("{}", "==> System Configuration".bold().green());
     println!();
 
-    let prefix = cellar::detect_prefix();
-    let cellar = cellar::cellar_path();
-    let taps = crate::tap::taps_path();
-
     // Display key installation paths
     println!("{}", "Paths:".bold());
-    println!(
-        "  {}: {}",
-        "Prefix".dimmed(),
-        prefix.display().to_string().cyan()
-    );
-    println!(
-        "  {}: {}",
-        "Cellar".dimmed(),
-        cellar.display().to_string().cyan()
-    );
-    println!(
-        "  {}: {}",
-        "Taps".dimmed(),
-        taps.display().to_string().cyan()
-    );
+    for (label, path) in [
+        ("Prefix", &config.prefix),
+        ("Cellar", &config.cellar),
+        ("Taps", &config.taps),
+        ("Cache", &config.cache),
+    ] {
+        println!(
+            "  {}: {}",
+            label.dimmed(),
+            path.display().to_string().cyan()
+        );
+    }
     println!();
 
-    // Get installation statistics
-    let packages = cellar::list_installed()?;
-    let installed_taps = crate::tap::list_taps()?;
-
     println!("{}", "Statistics:".bold());
     println!(
         "  {}: {}",
         "Installed packages".dimmed(),
-        packages.len().to_string().cyan()
+        config.installed_packages.to_string().cyan()
     );
     println!(
         "  {}: {}",
         "Installed taps".dimmed(),
-        installed_taps.len().to_string().cyan()
+        config.installed_taps.to_string().cyan()
     );
     println!();
 
     // Display system information
     println!("{}", "System:".bold());
+    println!("  {}: {}", "Version".dimmed(), config.version.cyan());
     println!(
-        "  {}: {}",
-        "Version".dimmed(),
-        env!("CARGO_PKG_VERSION").cyan()
+        "  {}: {} ({})",
+        "Build".dimmed(),
+        config.git_sha.cyan(),
+        config.build_date
     );
+    println!("  {}: {}", "Target".dimmed(), config.target.cyan());
+    println!("  {}: {}", "Architecture".dimmed(), config.arch.cyan());
+    println!("  {}: {}", "OS".dimmed(), config.os.cyan());
     println!(
         "  {}: {}",
-        "Architecture".dimmed(),
-        std::env::consts::ARCH.cyan()
+        "Bottle tag".dimmed(),
+        config.bottle_tag.as_deref().unwrap_or("none").cyan()
     );
-    println!("  {}: {}", "OS".dimmed(), std::env::consts::OS.cyan());
 
     Ok(())
 }
@@ -144,29 +191,145 @@ pub fn config() -> Result<()> {
 ///
 /// Outputs environment variables that can be sourced by the shell or used
 /// by other tools. This includes paths to prefix, cellar, cache, and logs.
-pub fn env() -> Result<()> {
+///
+/// By default each line is `NAME="value"`. With `shell` (bash, zsh or fish), the lines
+/// are quoted `export`/`set -gx` commands for `eval "$(bru env --shell fish)"`; with
+/// `plain`, they're bare `NAME=value` pairs for tools that split on the first `=`.
+pub fn env(shell: Option<&str>, plain: bool) -> Result<()> {
     let prefix = cellar::detect_prefix();
-    let cellar = cellar::cellar_path();
-    let cache = crate::download::cache_dir();
-    let taps = crate::tap::taps_path();
-    let logs = prefix.join("var/log");
-    let caskroom = crate::cask::caskroom_dir();
-
-    // Output environment variables in shell-compatible format
-    println!("HOMEBREW_PREFIX=\"{}\"", prefix.display());
-    println!("HOMEBREW_CELLAR=\"{}\"", cellar.display());
-    println!("HOMEBREW_REPOSITORY=\"{}\"", prefix.display());
-    println!("HOMEBREW_CACHE=\"{}\"", cache.display());
-    println!("HOMEBREW_TAPS=\"{}\"", taps.display());
-    println!("HOMEBREW_LOGS=\"{}\"", logs.display());
-    println!("HOMEBREW_CASKROOM=\"{}\"", caskroom.display());
-    println!("HOMEBREW_ARCH=\"{}\"", std::env::consts::ARCH);
-    println!("HOMEBREW_OS=\"{}\"", std::env::consts::OS);
-    println!("HOMEBREW_VERSION=\"{}\"", env!("CARGO_PKG_VERSION"));
+    let vars = [
+        ("HOMEBREW_PREFIX", prefix.display().to_string()),
+        (
+            "HOMEBREW_CELLAR",
+            cellar::cellar_path().display().to_string(),
+        ),
+        ("HOMEBREW_REPOSITORY", prefix.display().to_string()),
+        (
+            "HOMEBREW_CACHE",
+            crate::download::cache_dir().display().to_string(),
+        ),
+        (
+            "HOMEBREW_TAPS",
+            crate::tap::taps_path().display().to_string(),
+        ),
+        (
+            "HOMEBREW_LOGS",
+            prefix.join("var/log").display().to_string(),
+        ),
+        (
+            "HOMEBREW_CASKROOM",
+            crate::cask::caskroom_dir().display().to_string(),
+        ),
+        ("HOMEBREW_ARCH", std::env::consts::ARCH.to_string()),
+        ("HOMEBREW_OS", std::env::consts::OS.to_string()),
+        ("HOMEBREW_VERSION", env!("CARGO_PKG_VERSION").to_string()),
+    ];
+
+    for (name, value) in &vars {
+        let line = match shell {
+            Some(shell) => export_line(shell, name, value).ok_or_else(|| {
+                anyhow::anyhow!("Unsupported shell: {} (supported: bash, zsh, fish)", shell)
+            })?,
+            None if plain => format!("{}={}", name, value),
+            None => format!("{}=\"{}\"", name, value),
+        };
+        println!("{}", line);
+    }
 
     Ok(())
 }
 
+/// Shell to generate commands for: `shell` if given, else the one in `$SHELL`, else bash
+pub(crate) fn resolve_shell(shell: Option<&str>) -> String {
+    match shell {
+        Some(s) => String::from(s),
+        None => std::env::var("SHELL")
+            .ok()
+            .and_then(|s| {
+                let path = std::path::PathBuf::from(s);
+                path.file_name().and_then(|f| f.to_str()).map(String::from)
+            })
+            .unwrap_or_else(|| String::from("bash")),
+    }
+}
+
+/// Shells bru generates scripts for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Shell {
+    /// bash, or a plain POSIX `sh`
+    Bash,
+    Zsh,
+    Fish,
+}
+
+impl Shell {
+    /// The shell called `name`, or `None` if bru doesn't support it
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "bash" | "sh" => Some(Self::Bash),
+            "zsh" => Some(Self::Zsh),
+            "fish" => Some(Self::Fish),
+            _ => None,
+        }
+    }
+
+    /// Characters that must be backslash-escaped inside a double-quoted string
+    pub(crate) fn quoted_specials(self) -> &'static [char] {
+        match self {
+            Self::Bash | Self::Zsh => &['\\', '"', '$', '`'],
+            Self::Fish => &['\\', '"', '$'],
+        }
+    }
+}
+
+/// Characters that must be backslash-escaped inside a double-quoted string in `shell`
+fn quoted_specials(shell: &str) -> Option<&'static [char]> {
+    Shell::from_name(shell).map(Shell::quoted_specials)
+}
+
+/// `value` with every character in `special` backslash-escaped, ready to sit between
+/// double quotes
+pub(crate) fn escape(value: &str, special: &[char]) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if special.contains(&c) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// The command that exports `name` as `value` in `shell` (bash, sh, zsh or fish), with
+/// the value double-quoted and escaped so `eval` reproduces it exactly
+///
+/// Returns `None` for other shells.
+fn export_line(shell: &str, name: &str, value: &str) -> Option<String> {
+    let value = escape(value, quoted_specials(shell)?);
+    match shell {
+        "fish" => Some(format!("set -gx {} \"{}\";", name, value)),
+        _ => Some(format!("export {}=\"{}\";", name, value)),
+    }
+}
+
+/// The command that puts `dir` in front of the search path `name` unless it is already
+/// one of its entries, so sourcing the snippet again leaves the variable unchanged
+///
+/// For POSIX shells `tail` is appended after `dir` and is expected to re-expand the
+/// current value of `name`. Fish uses `fish_add_path` for `PATH` instead, which is
+/// already idempotent. Returns `None` for other shells.
+fn prepend_line(shell: &str, name: &str, dir: &str, tail: &str) -> Option<String> {
+    let dir = escape(dir, quoted_specials(shell)?);
+    match shell {
+        "fish" => Some(format!(
+            "contains -- \"{dir}\" ${name}; or set -gx {name} \"{dir}\" ${name};"
+        )),
+        _ => Some(format!(
+            "case \":${{{name}}}:\" in *:\"{dir}\":*) ;; *) export {name}=\"{dir}{tail}\";; esac"
+        )),
+    }
+}
+
 /// Generate shell configuration for Homebrew integration.
 ///
 /// Outputs shell-specific commands to set up PATH, MANPATH, INFOPATH, and
@@ -176,77 +339,50 @@ pub fn env() -> Result<()> {
 /// Supports bash, zsh, and fish shells. Auto-detects from $SHELL if not specified.
 pub fn shellenv(shell: Option<&str>) -> Result<()> {
     let prefix = cellar::detect_prefix();
+    let shell_type = resolve_shell(shell);
 
-    // Auto-detect shell from $SHELL environment variable if not explicitly provided
-    let shell_type = match shell {
-        Some(s) => String::from(s),
-        None => std::env::var("SHELL")
-            .ok()
-            .and_then(|s| {
-                let path = std::path::PathBuf::from(s);
-                path.file_name().and_then(|f| f.to_str()).map(String::from)
-            })
-            .unwrap_or_else(|| String::from("bash")),
-    };
+    // The Homebrew variables are quoted the same way `env --shell` quotes them
+    let cellar = prefix.join("Cellar");
+    let vars = [
+        ("HOMEBREW_PREFIX", &prefix),
+        ("HOMEBREW_CELLAR", &cellar),
+        ("HOMEBREW_REPOSITORY", &prefix),
+    ];
+    for (name, value) in vars {
+        if let Some(line) = export_line(&shell_type, name, &value.display().to_string()) {
+            println!("{}", line);
+        }
+    }
 
-    // Generate shell-specific configuration
+    // Each directory is only prepended when it is missing, so sourcing the snippet
+    // from several rc files doesn't grow the search paths
+    let dir = |sub: &str| prefix.join(sub).display().to_string();
     match shell_type.as_str() {
-        "bash" | "sh" => {
-            // POSIX-compatible shell configuration
-            println!("export HOMEBREW_PREFIX=\"{}\";", prefix.display());
-            println!("export HOMEBREW_CELLAR=\"{}/Cellar\";", prefix.display());
-            println!("export HOMEBREW_REPOSITORY=\"{}\";", prefix.display());
-            println!(
-                "export PATH=\"{}/bin:{}/sbin:$PATH\";",
-                prefix.display(),
-                prefix.display()
-            );
-            println!(
-                "export MANPATH=\"{}/share/man:$MANPATH\";",
-                prefix.display()
-            );
-            println!(
-                "export INFOPATH=\"{}/share/info:$INFOPATH\";",
-                prefix.display()
-            );
-        }
-        "zsh" => {
-            // Zsh-specific configuration with parameter expansion
-            println!("export HOMEBREW_PREFIX=\"{}\";", prefix.display());
-            println!("export HOMEBREW_CELLAR=\"{}/Cellar\";", prefix.display());
-            println!("export HOMEBREW_REPOSITORY=\"{}\";", prefix.display());
-            println!(
-                "export PATH=\"{}/bin:{}/sbin${{PATH+:$PATH}}\";",
-                prefix.display(),
-                prefix.display()
-            );
-            println!(
-                "export MANPATH=\"{}/share/man${{MANPATH+:$MANPATH}}:\";",
-                prefix.display()
-            );
-            println!(
-                "export INFOPATH=\"{}/share/info:${{INFOPATH:-}}\";",
-                prefix.display()
-            );
+        "bash" | "sh" | "zsh" => {
+            // sbin goes first so that bin ends up in front of it
+            for (name, sub, tail) in [
+                ("PATH", "sbin", "${PATH+:$PATH}"),
+                ("PATH", "bin", "${PATH+:$PATH}"),
+                ("MANPATH", "share/man", "${MANPATH+:$MANPATH}:"),
+                ("INFOPATH", "share/info", ":${INFOPATH:-}"),
+            ] {
+                if let Some(line) = prepend_line(&shell_type, name, &dir(sub), tail) {
+                    println!("{}", line);
+                }
+            }
         }
         "fish" => {
-            // Fish shell configuration using fish-specific commands
-            println!("set -gx HOMEBREW_PREFIX \"{}\";", prefix.display());
-            println!("set -gx HOMEBREW_CELLAR \"{}/Cellar\";", prefix.display());
-            println!("set -gx HOMEBREW_REPOSITORY \"{}\";", prefix.display());
-            println!(
-                "fish_add_path -gP \"{}/bin\" \"{}/sbin\";",
-                prefix.display(),
-                prefix.display()
-            );
-            println!(
-                "set -gx MANPATH \"{}/share/man\" $MANPATH;",
-                prefix.display()
-            );
+            let fish_quote = |value: &str| escape(value, &['\\', '"', '$']);
             println!(
-                "set -gx INFOPATH \"{}/share/info\" $INFOPATH;",
-                prefix.display()
+                "fish_add_path -gP \"{}\" \"{}\";",
+                fish_quote(&dir("bin")),
+                fish_quote(&dir("sbin"))
             );
+            for (name, sub) in [("MANPATH", "share/man"), ("INFOPATH", "share/info")] {
+                if let Some(line) = prepend_line(&shell_type, name, &dir(sub), "") {
+                    println!("{}", line);
+                }
+            }
         }
         other => {
             // Unsupported shell - display error and supported options
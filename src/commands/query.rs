@@ -3,20 +3,33 @@
 //! This module contains read-only commands that fetch and display information
 //! about formulae and casks from the Homebrew API.
 
-use crate::api::BrewApi;
+use crate::api::{BottleFileInfo, BrewApi, DependencyTag, FormulaSource};
 use crate::cellar;
-use crate::error::Result;
+use crate::error::{BruError, Result};
+use crate::output;
 use colored::Colorize;
 use indicatif::{ProgressBar, ProgressStyle};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::process::Command;
 
+use super::Outcome;
+
 /// Search for formulae and casks matching a query string
-pub async fn search(api: &BrewApi, query: &str, formula_only: bool, cask_only: bool) -> Result<()> {
+///
+/// With `json`, prints `{"formulae": [{"name", "desc", "score"}], "casks": [{"token",
+/// "desc", "score"}]}`, each list best match first, where `score` is the
+/// [`fuzzy_score`](crate::api::fuzzy_score) the name matched the query with.
+pub async fn search(
+    api: &BrewApi,
+    query: &str,
+    formula_only: bool,
+    cask_only: bool,
+    json: bool,
+) -> Result<()> {
     // Detect if stdout is a TTY (for brew-compatible behavior)
     let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
 
-    let spinner = if is_tty {
+    let spinner = if is_tty && !json {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -30,9 +43,50 @@ pub async fn search(api: &BrewApi, query: &str, formula_only: bool, cask_only: b
         ProgressBar::hidden()
     };
 
-    let results = api.search(query).await?;
+    let scope = crate::api::SearchScope::from_flags(formula_only, cask_only);
+    let results = api.search(query, scope).await?;
     spinner.finish_and_clear();
 
+    if json {
+        #[derive(serde::Serialize)]
+        struct FormulaMatch<'a> {
+            name: &'a str,
+            desc: Option<&'a str>,
+            score: f64,
+        }
+
+        #[derive(serde::Serialize)]
+        struct CaskMatch<'a> {
+            token: &'a str,
+            desc: Option<&'a str>,
+            score: f64,
+        }
+
+        let mut formulae: Vec<_> = results
+            .formulae
+            .iter()
+            .map(|f| FormulaMatch {
+                name: &f.name,
+                desc: f.desc.as_deref(),
+                score: f.score,
+            })
+            .collect();
+        let mut casks: Vec<_> = results
+            .casks
+            .iter()
+            .map(|c| CaskMatch {
+                token: &c.token,
+                desc: c.desc.as_deref(),
+                score: c.score,
+            })
+            .collect();
+        // Stable, so equal scores keep the index's alphabetical order
+        formulae.sort_by(|a, b| b.score.total_cmp(&a.score));
+        casks.sort_by(|a, b| b.score.total_cmp(&a.score));
+        output::print_json(&serde_json::json!({ "formulae": formulae, "casks": casks }))?;
+        return Ok(());
+    }
+
     if results.is_empty() {
         if is_tty {
             println!(
@@ -44,26 +98,8 @@ pub async fn search(api: &BrewApi, query: &str, formula_only: bool, cask_only: b
         return Ok(());
     }
 
-    // Determine what to display based on flags
-    let show_formulae = !cask_only;
-    let show_casks = !formula_only;
-
-    // Count total results to show
-    let total_to_show = (if show_formulae {
-        results.formulae.len()
-    } else {
-        0
-    }) + (if show_casks { results.casks.len() } else { 0 });
-
-    if total_to_show == 0 {
-        if is_tty {
-            println!("{} No results found with the specified filter", "".red());
-        }
-        return Ok(());
-    }
-
     // Display formulae
-    if show_formulae && !results.formulae.is_empty() {
+    if !results.formulae.is_empty() {
         if is_tty {
             println!("{}", "==> Formulae".bold().green());
         }
@@ -84,7 +120,7 @@ pub async fn search(api: &BrewApi, query: &str, formula_only: bool, cask_only: b
     }
 
     // Display casks
-    if show_casks && !results.casks.is_empty() {
+    if !results.casks.is_empty() {
         if is_tty {
             println!("{}", "==> Casks".bold().cyan());
         }
@@ -102,12 +138,290 @@ pub async fn search(api: &BrewApi, query: &str, formula_only: bool, cask_only: b
     Ok(())
 }
 
+/// Search installed formulae and casks without touching the network
+///
+/// Names are matched like `search`; formula descriptions are matched too when a
+/// formula index has been cached by an earlier online command (at any age).
+pub fn search_installed(query: &str, formula_only: bool, cask_only: bool) -> Result<()> {
+    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    let query_lower = query.to_lowercase();
+
+    let descriptions: HashMap<String, String> = crate::cache::get_cached_formulae_brief_any_age()
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|f| f.desc.map(|desc| (f.name, desc)))
+        .collect();
+
+    let mut formulae: Vec<(String, String)> = Vec::new();
+    if !cask_only {
+        for pkg in cellar::list_installed()? {
+            let desc_matches = descriptions
+                .get(&pkg.name)
+                .is_some_and(|desc| desc.to_lowercase().contains(&query_lower));
+            if !crate::api::fuzzy_matches(&pkg.name, &query_lower) && !desc_matches {
+                continue;
+            }
+            // list_installed yields one entry per version; keep the newest
+            match formulae.iter_mut().find(|(name, _)| *name == pkg.name) {
+                Some(entry) => {
+                    if cellar::compare_versions(&pkg.version, &entry.1).is_gt() {
+                        entry.1 = pkg.version;
+                    }
+                }
+                None => formulae.push((pkg.name, pkg.version)),
+            }
+        }
+        formulae.sort();
+    }
+
+    let casks: Vec<(String, String)> = if formula_only {
+        Vec::new()
+    } else {
+        crate::cask::list_installed_casks()?
+            .into_iter()
+            .filter(|(token, _)| crate::api::fuzzy_matches(token, &query_lower))
+            .collect()
+    };
+
+    if formulae.is_empty() && casks.is_empty() {
+        if is_tty {
+            println!(
+                "{} No installed formulae or casks found matching '{}'",
+                "".red(),
+                query
+            );
+        }
+        return Ok(());
+    }
+
+    if !formulae.is_empty() {
+        if is_tty {
+            println!("{}", "==> Installed Formulae".bold().green());
+        }
+        for (name, version) in &formulae {
+            if is_tty {
+                match descriptions.get(name) {
+                    Some(desc) => println!(
+                        "{} {} {}",
+                        name.bold().green(),
+                        version.dimmed(),
+                        format!("- {}", desc).dimmed()
+                    ),
+                    None => println!("{} {}", name.bold().green(), version.dimmed()),
+                }
+            } else {
+                println!("{}", name);
+            }
+        }
+
+        if is_tty && !casks.is_empty() {
+            println!();
+        }
+    }
+
+    if !casks.is_empty() {
+        if is_tty {
+            println!("{}", "==> Installed Casks".bold().cyan());
+        }
+        for (token, version) in &casks {
+            if is_tty {
+                println!("{} {}", token.bold().cyan(), version.dimmed());
+            } else {
+                println!("{}", token);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Render JSON for `info`, attaching GitHub metadata when `--github` was requested
+fn info_json<T: serde::Serialize>(
+    value: &T,
+    github: Option<&Option<crate::api::GitHubRepo>>,
+    status: Option<&InstallStatus>,
+) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(value)?;
+    if let Some(obj) = value.as_object_mut() {
+        if let Some(repo) = github {
+            obj.insert("github".to_string(), serde_json::to_value(repo)?);
+        }
+        // Same field names as `brew info --json`
+        if let Some(status) = status {
+            let installed: Vec<_> = status
+                .versions
+                .iter()
+                .map(|v| serde_json::json!({ "version": v }))
+                .collect();
+            obj.insert("installed".to_string(), installed.into());
+            obj.insert("linked_keg".to_string(), status.linked.clone().into());
+            obj.insert("outdated".to_string(), status.outdated.into());
+        }
+    }
+    Ok(value)
+}
+
+/// Every bottle the formula ships, with sizes looked up from GHCR in parallel.
+///
+/// A size that can't be determined (offline, registry error) stays `None`.
+async fn bottle_variations(api: &BrewApi, formula: &crate::api::Formula) -> Vec<BottleFileInfo> {
+    let mut bottles = formula.bottle_tags_with_meta();
+    let sizes = futures::future::join_all(
+        bottles
+            .iter()
+            .map(|b| crate::download::bottle_file_size(api.client(), &b.url)),
+    )
+    .await;
+    for (bottle, size) in bottles.iter_mut().zip(sizes) {
+        bottle.size = size.ok();
+    }
+    bottles
+}
+
+/// Print the `info --variations` section, marking this system's bottle
+fn print_variations(bottles: &[BottleFileInfo]) {
+    if bottles.is_empty() {
+        println!("{}: {}", "Bottles".bold(), "none".dimmed());
+        return;
+    }
+
+    let current = crate::platform::bottle_tag().ok();
+    let width = bottles.iter().map(|b| b.tag.len()).max().unwrap_or(0);
+    println!("{}:", "Bottles".bold());
+    for bottle in bottles {
+        let size = bottle
+            .size
+            .map(super::utils::format_size)
+            .unwrap_or_else(|| "?".to_string());
+        let marker = if current.as_deref() == Some(bottle.tag.as_str()) {
+            " (this system)".green().to_string()
+        } else {
+            String::new()
+        };
+        println!(
+            "  {}  {:>9}  {}{}",
+            format!("{:<width$}", bottle.tag).cyan(),
+            size,
+            bottle.sha256.dimmed(),
+            marker
+        );
+    }
+}
+
+/// Local install state of a formula, shown next to the API's latest version
+struct InstallStatus {
+    /// Installed versions, newest first (empty if not installed)
+    versions: Vec<String>,
+    /// Version the `opt/` link points at
+    linked: Option<String>,
+    /// True if installed but none of the installed versions is `latest`
+    outdated: bool,
+}
+
+impl InstallStatus {
+    /// `latest` is the formula's [`pkg_version`](crate::api::Formula::pkg_version), so
+    /// a revision bump (`14.1.0` to `14.1.0_1`) counts as an update like it does in brew
+    fn read(name: &str, latest: Option<&str>) -> Self {
+        let versions: Vec<String> = cellar::get_installed_versions(name)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| v.version)
+            .collect();
+        let linked = crate::symlink::get_linked_version(name).ok().flatten();
+        let outdated = is_outdated(&versions, latest);
+
+        Self {
+            versions,
+            linked,
+            outdated,
+        }
+    }
+
+    /// "14.1.0 (linked), 13.0.0"
+    fn display(&self) -> String {
+        self.versions
+            .iter()
+            .map(|v| {
+                if self.linked.as_deref() == Some(v.as_str()) {
+                    format!("{} {}", v, "(linked)".green())
+                } else {
+                    v.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+/// Whether a formula installed at `versions` is behind `latest`, revision included
+fn is_outdated(versions: &[String], latest: Option<&str>) -> bool {
+    latest.is_some_and(|latest| !versions.is_empty() && !versions.iter().any(|v| v == latest))
+}
+
+/// Print the GitHub enrichment line for `info --github`
+fn print_github_info(repo: &Option<crate::api::GitHubRepo>) {
+    match repo {
+        Some(repo) => {
+            let release = repo
+                .last_release
+                .as_deref()
+                .and_then(|date| date.split('T').next())
+                .unwrap_or("none");
+            println!(
+                "{}: {} ({} stars, last release {})",
+                "GitHub".bold(),
+                repo.full_name,
+                repo.stars,
+                release
+            );
+        }
+        // Unauthenticated requests are the usual reason GitHub refuses the lookup
+        None if crate::api::github_token().is_none() => {
+            println!(
+                "{}: {}",
+                "GitHub".bold(),
+                "unavailable (set HOMEBREW_GITHUB_API_TOKEN to avoid rate limits)".dimmed()
+            );
+        }
+        None => println!("{}: {}", "GitHub".bold(), "unavailable".dimmed()),
+    }
+}
+
 /// Display detailed information about a formula or cask
-pub async fn info(api: &BrewApi, formula: &str, json: bool) -> Result<()> {
+///
+/// With `github`, projects whose homepage is a GitHub repository are enriched with
+/// star count and latest release date; other projects get no GitHub line. This is
+/// best-effort: failed lookups are reported as unavailable rather than failing the
+/// command.
+///
+/// Names that are both a formula and a cask (e.g. `docker`) show both, formula
+/// first, unless `formula_only` or `cask_only` picks one. `--json` goes through
+/// [`info_json_array`] instead.
+///
+/// Returns [`Outcome::NotFound`] if there is no such formula or cask.
+pub async fn info(
+    api: &BrewApi,
+    formula: &str,
+    github: bool,
+    variations: bool,
+    formula_only: bool,
+    cask_only: bool,
+) -> Result<Outcome> {
     let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
 
+    // `user/repo/name` looks in that tap; `homebrew/core/name` is a plain core name
+    let qualified = crate::tap::split_qualified(formula);
+    let formula = qualified.as_ref().map_or(formula, |(_, name)| *name);
+
+    // Reject names that can't exist before spending a round-trip on them
+    if !crate::api::validate_name(formula) {
+        let message = format!("'{}' is not a valid formula or cask name", formula);
+        println!("{} {}", "".red(), message);
+        return Ok(Outcome::NotFound);
+    }
+
     // Spinner for API fetching (will be shown only if we reach API call)
-    let spinner = if !json && is_tty {
+    let spinner = if is_tty {
         let pb = ProgressBar::new_spinner();
         pb.set_style(
             ProgressStyle::default_spinner()
@@ -121,12 +435,18 @@ pub async fn info(api: &BrewApi, formula: &str, json: bool) -> Result<()> {
         ProgressBar::hidden()
     };
 
-    // Check if this is an installed tap formula
-    if let Ok(versions) = cellar::get_installed_versions(formula)
-        && let Some(installed_version) = versions.first()
-        && let Ok(Some((tap_name, formula_path, _))) =
-            crate::tap::get_package_tap_info(&installed_version.path)
-    {
+    // Tap formulae, named with their tap or installed from one, are read from the
+    // tap's Ruby file
+    let tap_formula = match tap_formula_file(qualified.as_ref(), formula, cask_only) {
+        Ok(tap_formula) => tap_formula,
+        Err(message) => {
+            spinner.finish_and_clear();
+            println!("{} {}", "".red(), message);
+            return Ok(Outcome::NotFound);
+        }
+    };
+    if let Some((tap_name, formula_path)) = tap_formula {
+        let versions = cellar::get_installed_versions(formula).unwrap_or_default();
         // For tap formulae, parse the Ruby file natively
         match crate::tap::parse_formula_info(&formula_path, formula) {
             Ok(tap_info) => {
@@ -151,8 +471,9 @@ pub async fn info(api: &BrewApi, formula: &str, json: bool) -> Result<()> {
                     "Installed".bold(),
                     versions.len()
                 );
+                let linked = crate::symlink::get_linked_version(formula).ok().flatten();
                 for v in &versions {
-                    let marker = if v.version == installed_version.version {
+                    let marker = if linked.as_ref() == Some(&v.version) {
                         "*"
                     } else {
                         ""
@@ -166,7 +487,7 @@ pub async fn info(api: &BrewApi, formula: &str, json: bool) -> Result<()> {
                     formula_path.display().to_string().dimmed()
                 );
                 spinner.finish_and_clear();
-                return Ok(());
+                return Ok(Outcome::Done);
             }
             Err(e) => {
                 spinner.finish_and_clear();
@@ -184,112 +505,574 @@ pub async fn info(api: &BrewApi, formula: &str, json: bool) -> Result<()> {
         }
     }
 
-    // Try formula first, then cask
-    match api.fetch_formula(formula).await {
+    // Try formula first, then cask. A name can be both (e.g. `docker`), so unless a
+    // flag picks one, the cask is looked up too and shown after the formula
+    let (fetched, also_cask) =
+        lookup_info(api, formula, cask_only, !formula_only && !cask_only).await;
+    let label = |kind: &'static str| if also_cask.is_some() { kind } else { "" };
+    match fetched {
         Ok(formula) => {
-            spinner.finish_and_clear();
-            if json {
-                // Output as JSON
-                let json_str = serde_json::to_string_pretty(&formula)?;
-                println!("{}", json_str);
+            let github_repo = fetch_github_info(api, formula.homepage.as_deref(), github).await;
+            let status = InstallStatus::read(&formula.name, formula.pkg_version().as_deref());
+            let bottles = if variations {
+                Some(bottle_variations(api, &formula).await)
             } else {
-                // Pretty print format
-                println!("{}", format!("==> {}", formula.name).bold().green());
-                if let Some(desc) = &formula.desc {
-                    println!("{}", desc);
-                }
-                if let Some(homepage) = &formula.homepage {
-                    println!("{}: {}", "Homepage".bold(), homepage);
-                }
-                if let Some(version) = &formula.versions.stable {
+                None
+            };
+            spinner.finish_and_clear();
+            // Caveat paths point at the installed keg, or the one a fresh install makes
+            let keg_version = status
+                .versions
+                .first()
+                .or(formula.versions.stable.as_ref())
+                .map_or("", String::as_str);
+            let render = |text: &str| cellar::render_caveats(text, &formula.name, keg_version);
+            println!(
+                "{}",
+                format!("==> {}{}", formula.name, label(" (formula)"))
+                    .bold()
+                    .green()
+            );
+            if let Some(desc) = &formula.desc {
+                println!("{}", desc);
+            }
+            if let Some(homepage) = &formula.homepage {
+                println!("{}: {}", "Homepage".bold(), homepage);
+            }
+            if let Some(license) = &formula.license {
+                println!("{}: {}", "License".bold(), license);
+            }
+            if let Some(version) = formula.pkg_version() {
+                if status.outdated {
+                    println!(
+                        "{}: {} {}",
+                        "Version".bold(),
+                        version,
+                        "(outdated)".yellow()
+                    );
+                } else {
                     println!("{}: {}", "Version".bold(), version);
                 }
+            }
+            if status.versions.is_empty() {
+                println!("{}: {}", "Installed".bold(), "no".dimmed());
+            } else {
+                println!("{}: {}", "Installed".bold(), status.display());
+            }
+            if let Some(repo) = &github_repo {
+                print_github_info(repo);
+            }
 
-                if formula.keg_only {
-                    if let Some(reason) = &formula.keg_only_reason {
-                        let reason_display = match reason.reason.as_str() {
-                            ":provided_by_macos" => "provided by macOS",
-                            ":shadowed_by_macos" => "shadowed by macOS",
-                            ":versioned_formula" => "versioned formula",
-                            _ => &reason.reason,
-                        };
-                        println!("{}: {}", "Keg-only".bold().yellow(), reason_display);
-                        if !reason.explanation.is_empty() {
-                            println!("  {}", reason.explanation.dimmed());
-                        }
-                    } else {
-                        println!("{}: yes", "Keg-only".bold().yellow());
+            if formula.keg_only {
+                if let Some(reason) = &formula.keg_only_reason {
+                    let reason_display = match reason.reason.as_str() {
+                        ":provided_by_macos" => "provided by macOS",
+                        ":shadowed_by_macos" => "shadowed by macOS",
+                        ":versioned_formula" => "versioned formula",
+                        _ => &reason.reason,
+                    };
+                    println!("{}: {}", "Keg-only".bold().yellow(), reason_display);
+                    if !reason.explanation.is_empty() {
+                        println!("  {}", render(&reason.explanation).dimmed());
                     }
+                } else {
+                    println!("{}: yes", "Keg-only".bold().yellow());
                 }
 
-                if !formula.dependencies.is_empty() {
-                    println!(
-                        "{}: {}",
-                        "Dependencies".bold(),
-                        formula.dependencies.join(", ")
-                    );
+                // How to build against it, from the installed keg's layout
+                if let Some(keg) = cellar::get_installed_versions(&formula.name)
+                    .ok()
+                    .and_then(|versions| versions.into_iter().next())
+                {
+                    let hints = cellar::build_env_hints(&formula.name, &keg.version);
+                    if !hints.is_empty() {
+                        println!();
+                        println!("{}", hints);
+                        println!();
+                    }
                 }
+            }
 
-                if !formula.build_dependencies.is_empty() {
-                    println!(
-                        "{}: {}",
-                        "Build dependencies".bold(),
-                        formula.build_dependencies.join(", ")
-                    );
+            if !formula.dependencies.is_empty() {
+                println!(
+                    "{}: {}",
+                    "Dependencies".bold(),
+                    formula.dependencies.join(", ")
+                );
+            }
+
+            if !formula.build_dependencies.is_empty() {
+                println!(
+                    "{}: {}",
+                    "Build dependencies".bold(),
+                    formula.build_dependencies.join(", ")
+                );
+            }
+
+            if !formula.recommended_dependencies.is_empty() {
+                println!(
+                    "{}: {}",
+                    "Recommended dependencies".bold(),
+                    formula.recommended_dependencies.join(", ")
+                );
+            }
+
+            if !formula.optional_dependencies.is_empty() {
+                println!(
+                    "{}: {}",
+                    "Optional dependencies".bold(),
+                    formula.optional_dependencies.join(", ")
+                );
+            }
+
+            if !formula.requirements.is_empty() {
+                let requirements: Vec<_> =
+                    formula.requirements.iter().map(|r| r.to_string()).collect();
+                println!("{}: {}", "Requirements".bold(), requirements.join(", "));
+            }
+
+            let conflicts = formula.conflicts();
+            if !conflicts.is_empty() {
+                println!("{}:", "Conflicts with".bold());
+                for (name, reason) in conflicts {
+                    match reason {
+                        Some(reason) => {
+                            println!("  {} {}", name, format!("(because {})", reason).dimmed())
+                        }
+                        None => println!("  {}", name),
+                    }
                 }
             }
+
+            if let Some(caveats) = formula.caveats.as_deref().filter(|c| !c.trim().is_empty()) {
+                println!("{}", "==> Caveats".bold().green());
+                println!("{}", render(caveats).trim_end());
+            }
+
+            if let Some(bottles) = &bottles {
+                print_variations(bottles);
+            }
+
+            if let Some(cask) = &also_cask {
+                let github_repo = fetch_github_info(api, cask.homepage.as_deref(), github).await;
+                println!();
+                print_cask_info(cask, github_repo.as_ref(), label(" (cask)"));
+            }
         }
-        Err(_) => {
+        // Network and parse failures are reported as such, not as a missing package
+        Err(err) if !matches!(err, BruError::FormulaNotFound(_)) => {
+            spinner.finish_and_clear();
+            return Err(err);
+        }
+        Err(err) => {
             // Try as cask
-            match api.fetch_cask(formula).await {
+            let fetched = match also_cask {
+                _ if formula_only => Err(err),
+                Some(cask) => Ok(cask),
+                None => api.fetch_cask(formula).await,
+            };
+            match fetched {
                 Ok(cask) => {
+                    let github_repo =
+                        fetch_github_info(api, cask.homepage.as_deref(), github).await;
                     spinner.finish_and_clear();
-                    if json {
-                        let json_str = serde_json::to_string_pretty(&cask)?;
-                        println!("{}", json_str);
-                    } else {
-                        println!("{}", format!("==> {}", cask.token).bold().cyan());
-                        if !cask.name.is_empty() {
-                            println!("{}: {}", "Name".bold(), cask.name.join(", "));
-                        }
-                        if let Some(desc) = &cask.desc {
-                            println!("{}", desc);
-                        }
-                        if let Some(homepage) = &cask.homepage {
-                            println!("{}: {}", "Homepage".bold(), homepage);
-                        }
-                        if let Some(version) = &cask.version {
-                            println!("{}: {}", "Version".bold(), version);
-                        }
-                    }
+                    print_cask_info(&cask, github_repo.as_ref(), "");
                 }
-                Err(_) => {
+                Err(BruError::FormulaNotFound(_) | BruError::CaskNotFound(_)) => {
                     spinner.finish_and_clear();
-                    if json {
-                        println!(
-                            "{{\"error\": \"No formula or cask found for '{}'\"}}",
-                            formula
-                        );
-                    } else {
-                        println!("\n {} No formula or cask found for '{}'", "".red(), formula);
-                    }
+                    let message = match (formula_only, cask_only) {
+                        (true, _) => format!("No formula found for '{}'", formula),
+                        (_, true) => format!("No cask found for '{}'", formula),
+                        _ => format!("No formula or cask found for '{}'", formula),
+                    };
+                    println!("\n {} {}", "".red(), message);
+                    super::utils::print_suggestions(formula);
+                    return Ok(Outcome::NotFound);
+                }
+                Err(err) => {
+                    spinner.finish_and_clear();
+                    return Err(err);
                 }
             }
         }
     }
 
+    Ok(Outcome::Done)
+}
+
+/// `info --json`: one array with an entry per name, looked up in parallel
+///
+/// The output is an array even for a single name. Each entry is the formula or cask
+/// object, or `{"name", "error"}` when the name can't be shown; names that don't exist
+/// also carry `suggestions`.
+///
+/// Returns [`Outcome::NotFound`] if any name gave an error.
+pub async fn info_json_array(
+    api: &BrewApi,
+    names: &[String],
+    github: bool,
+    variations: bool,
+    formula_only: bool,
+    cask_only: bool,
+) -> Result<Outcome> {
+    let entries = futures::future::join_all(
+        names
+            .iter()
+            .map(|name| info_json_entry(api, name, github, variations, formula_only, cask_only)),
+    )
+    .await;
+
+    let mut outcome = Outcome::Done;
+    let values: Vec<_> = entries
+        .into_iter()
+        .map(|entry| {
+            entry.unwrap_or_else(|error| {
+                outcome = Outcome::NotFound;
+                error
+            })
+        })
+        .collect();
+    output::print_json(&values)?;
+    Ok(outcome)
+}
+
+/// One element of [`info_json_array`]; `Err` holds the error entry for `name`
+async fn info_json_entry(
+    api: &BrewApi,
+    name: &str,
+    github: bool,
+    variations: bool,
+    formula_only: bool,
+    cask_only: bool,
+) -> std::result::Result<serde_json::Value, serde_json::Value> {
+    let requested = name;
+    let error = |message: String| serde_json::json!({ "name": requested, "error": message });
+    let qualified = crate::tap::split_qualified(name);
+    let name = qualified.as_ref().map_or(name, |(_, short)| *short);
+    let not_found = |message: String| {
+        serde_json::json!({
+            "name": requested,
+            "error": message,
+            "suggestions": crate::api::suggest(name, 3),
+        })
+    };
+    if !crate::api::validate_name(name) {
+        return Err(error(format!(
+            "'{}' is not a valid formula or cask name",
+            name
+        )));
+    }
+
+    if let Some((tap_name, formula_path)) =
+        tap_formula_file(qualified.as_ref(), name, cask_only).map_err(error)?
+    {
+        let versions = cellar::get_installed_versions(name).unwrap_or_default();
+        let tap_info = crate::tap::parse_formula_info(&formula_path, name)
+            .map_err(|e| error(e.to_string()))?;
+        return Ok(tap_info_json(&tap_name, &tap_info, &versions));
+    }
+
+    let (fetched, _) = lookup_info(api, name, cask_only, false).await;
+    let value = match fetched {
+        Ok(formula) => {
+            let github_repo = fetch_github_info(api, formula.homepage.as_deref(), github).await;
+            let status = InstallStatus::read(&formula.name, formula.pkg_version().as_deref());
+            let mut value = info_json(&formula, github_repo.as_ref(), Some(&status))
+                .map_err(|e| error(e.to_string()))?;
+            if variations && let Some(obj) = value.as_object_mut() {
+                let bottles = bottle_variations(api, &formula).await;
+                obj.insert("variations".to_string(), serde_json::json!(bottles));
+            }
+            value
+        }
+        Err(BruError::FormulaNotFound(_)) if !formula_only => match api.fetch_cask(name).await {
+            Ok(cask) => {
+                let github_repo = fetch_github_info(api, cask.homepage.as_deref(), github).await;
+                info_json(&cask, github_repo.as_ref(), None).map_err(|e| error(e.to_string()))?
+            }
+            Err(BruError::CaskNotFound(_)) if cask_only => {
+                return Err(not_found(format!("No cask found for '{}'", name)));
+            }
+            Err(BruError::CaskNotFound(_)) => {
+                return Err(not_found(format!(
+                    "No formula or cask found for '{}'",
+                    name
+                )));
+            }
+            Err(err) => return Err(error(err.to_string())),
+        },
+        Err(BruError::FormulaNotFound(_)) => {
+            return Err(not_found(format!("No formula found for '{}'", name)));
+        }
+        Err(err) => return Err(error(err.to_string())),
+    };
+    Ok(value)
+}
+
+/// The tap and Ruby file [`info`] reads `name` from, if it is a tap formula
+///
+/// That is the case when it is qualified with a tap other than homebrew/core, or was
+/// installed from one. `Err` holds the message for a tap that has no such formula.
+fn tap_formula_file(
+    qualified: Option<&(crate::tap::TapName, &str)>,
+    name: &str,
+    cask_only: bool,
+) -> std::result::Result<Option<(String, std::path::PathBuf)>, String> {
+    match qualified {
+        Some((tap, short)) if !tap.is_core() => {
+            crate::tap::formula_file_in(&cellar::detect_prefix(), tap, short)
+                .map(|path| Some((tap.to_string(), path)))
+                .ok_or_else(|| format!("No formula {} in tap {} (is it tapped?)", short, tap))
+        }
+        Some(_) => Ok(None),
+        None if cask_only => Ok(None),
+        None => Ok(cellar::get_installed_versions(name)
+            .ok()
+            .and_then(|versions| versions.into_iter().next())
+            .and_then(|keg| crate::tap::get_package_tap_info(&keg.path).ok().flatten())
+            .map(|(tap_name, path, _)| (tap_name, path))),
+    }
+}
+
+/// `info --json` for a formula read from a tap's Ruby file
+fn tap_info_json(
+    tap_name: &str,
+    tap_info: &crate::tap::TapFormulaInfo,
+    versions: &[cellar::InstalledPackage],
+) -> serde_json::Value {
+    serde_json::json!({
+        "name": tap_info.name,
+        "full_name": format!("{}/{}", tap_name, tap_info.name),
+        "tap": tap_name,
+        "desc": tap_info.desc,
+        "homepage": tap_info.homepage,
+        "versions": { "stable": tap_info.resolved_version() },
+        "installed": versions
+            .iter()
+            .map(|v| serde_json::json!({ "version": v.version }))
+            .collect::<Vec<_>>(),
+    })
+}
+
+/// GitHub metadata for `info --github`: `None` without the flag or when the homepage
+/// isn't a GitHub repository, `Some(None)` when the lookup failed
+async fn fetch_github_info(
+    api: &BrewApi,
+    homepage: Option<&str>,
+    github: bool,
+) -> Option<Option<crate::api::GitHubRepo>> {
+    let homepage = homepage.filter(|url| github && crate::api::github_repo_slug(url).is_some())?;
+    Some(api.fetch_github_repo(homepage).await.ok().flatten())
+}
+
+/// Look `name` up as a formula for [`info`], and as a cask too if `with_cask`
+///
+/// Both lookups run at once. With `cask_only` the formula lookup is skipped and
+/// reports not found. The cask lookup is best-effort: any failure gives `None`.
+async fn lookup_info(
+    source: &dyn FormulaSource,
+    name: &str,
+    cask_only: bool,
+    with_cask: bool,
+) -> (Result<crate::api::Formula>, Option<crate::api::Cask>) {
+    tokio::join!(
+        async {
+            if cask_only {
+                Err(BruError::FormulaNotFound(name.to_string()))
+            } else {
+                source.formula(name).await
+            }
+        },
+        async {
+            if with_cask {
+                source.cask(name).await.ok()
+            } else {
+                None
+            }
+        }
+    )
+}
+
+/// The human-readable part of [`info`] for a cask; `label` follows the token in the
+/// header
+fn print_cask_info(
+    cask: &crate::api::Cask,
+    github_repo: Option<&Option<crate::api::GitHubRepo>>,
+    label: &str,
+) {
+    println!("{}", format!("==> {}{}", cask.token, label).bold().cyan());
+    if !cask.name.is_empty() {
+        println!("{}: {}", "Name".bold(), cask.name.join(", "));
+    }
+    if let Some(desc) = &cask.desc {
+        println!("{}", desc);
+    }
+    if let Some(homepage) = &cask.homepage {
+        println!("{}: {}", "Homepage".bold(), homepage);
+    }
+    if let Some(version) = &cask.version {
+        println!("{}: {}", "Version".bold(), version);
+    }
+    if let Some(repo) = github_repo {
+        print_github_info(repo);
+    }
+}
+
+/// A dependency with the ways it is declared, as printed by `deps --annotate --json`
+#[derive(serde::Serialize)]
+struct AnnotatedDependency<'a> {
+    name: &'a str,
+    tags: &'a [DependencyTag],
+}
+
+/// ` [build, test]` suffix for a dependency; plain runtime dependencies get none
+fn annotation(tags: &[DependencyTag]) -> String {
+    if tags == [DependencyTag::Runtime] {
+        return String::new();
+    }
+    let tags: Vec<_> = tags.iter().map(|t| t.to_string()).collect();
+    format!(" [{}]", tags.join(", "))
+}
+
+/// Print each dependency once with its tags (`deps --annotate`)
+fn print_annotated(
+    deps: &[(&str, Vec<DependencyTag>)],
+    tree: bool,
+    is_tty: bool,
+    json: bool,
+) -> Result<()> {
+    if json {
+        let deps: Vec<_> = deps
+            .iter()
+            .map(|(name, tags)| AnnotatedDependency { name, tags })
+            .collect();
+        output::print_json(&deps)?;
+        return Ok(());
+    }
+
+    if deps.is_empty() {
+        if is_tty {
+            println!("{} No dependencies", "".green());
+        }
+        return Ok(());
+    }
+
+    let len = deps.len();
+    for (i, (dep, tags)) in deps.iter().enumerate() {
+        let annotation = annotation(tags);
+        if is_tty {
+            let prefix = if !tree {
+                ""
+            } else if i == len - 1 {
+                "└─ "
+            } else {
+                "├─ "
+            };
+            println!("{}{}{}", prefix, dep.cyan(), annotation.dimmed());
+        } else {
+            println!("{}{}", dep, annotation);
+        }
+    }
     Ok(())
 }
 
 /// Show dependencies for a formula
+///
+/// With `json`, prints the runtime dependency names as an array (`--tree` only
+/// affects the human-readable layout). With `annotate`, each dependency is listed
+/// once with its tags; `--direct` then merges runtime, recommended, optional, build
+/// and test dependencies into a single list. With `include_requirements`, the
+/// formula's non-formula requirements (macOS, Xcode, arch) are listed after the
+/// dependencies; main doesn't allow it with `json`. With `topo`, the formula and its dependencies are printed
+/// one per line in the order they'd be installed (see [`install_order`]).
+///
+/// [`install_order`]: super::install_order
+#[allow(clippy::too_many_arguments)]
 pub async fn deps(
     api: &BrewApi,
     formula: &str,
     tree: bool,
     installed_only: bool,
     direct: bool,
+    annotate: bool,
+    include_requirements: bool,
+    topo: bool,
+    json: bool,
 ) -> Result<()> {
-    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+    // Tap-qualified names (`user/repo/name`) are read from the locally cloned tap
+    let source = crate::tap::TapSource::new(api);
+
+    if topo {
+        let mut order = super::install_order(&source, &[formula.to_string()]).await?;
+        if installed_only {
+            let installed: HashSet<String> = cellar::list_installed()?
+                .into_iter()
+                .map(|p| p.name)
+                .collect();
+            order.retain(|name| installed.contains(name));
+        }
+        if json {
+            output::print_json(&order)?;
+        } else {
+            for name in &order {
+                println!("{}", name);
+            }
+        }
+        return Ok(());
+    }
+
+    // Requirements are reported after the dependency list, whichever mode printed it
+    if include_requirements {
+        deps_listing(
+            &source,
+            formula,
+            tree,
+            installed_only,
+            direct,
+            annotate,
+            json,
+        )
+        .await?;
+        let requirements = source.formula(formula).await?.requirements;
+        if !requirements.is_empty() {
+            let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
+            if is_tty {
+                println!("{}", "Requirements:".bold().green());
+            }
+            for requirement in &requirements {
+                if is_tty {
+                    println!("  {}", requirement.to_string().cyan());
+                } else {
+                    println!("{}", requirement);
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    deps_listing(
+        &source,
+        formula,
+        tree,
+        installed_only,
+        direct,
+        annotate,
+        json,
+    )
+    .await
+}
+
+/// The dependency list part of [`deps`]
+async fn deps_listing(
+    source: &dyn FormulaSource,
+    formula: &str,
+    tree: bool,
+    installed_only: bool,
+    direct: bool,
+    annotate: bool,
+    json: bool,
+) -> Result<()> {
+    let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout()) && !json;
 
     // If filtering by installed, get the list of installed packages
     let installed_names: HashSet<String> = if installed_only {
@@ -317,9 +1100,26 @@ pub async fn deps(
             ProgressBar::hidden()
         };
 
-        let formula_data = api.fetch_formula(formula).await?;
+        let formula_data = source.formula(formula).await?;
         spinner.finish_and_clear();
 
+        if annotate {
+            let mut deps = formula_data.tagged_dependencies();
+            if installed_only {
+                deps.retain(|(dep, _)| installed_names.contains(*dep));
+            }
+            return print_annotated(&deps, tree, is_tty, json);
+        }
+
+        if json {
+            let mut deps: Vec<_> = formula_data.dependencies.iter().collect();
+            if installed_only {
+                deps.retain(|dep| installed_names.contains(*dep));
+            }
+            output::print_json(&deps)?;
+            return Ok(());
+        }
+
         if formula_data.dependencies.is_empty() && formula_data.build_dependencies.is_empty() {
             if is_tty {
                 println!("{} No dependencies", "".green());
@@ -369,18 +1169,50 @@ pub async fn deps(
         unsafe {
             std::env::set_var("BRU_QUIET", "1");
         }
-        let (_all_formulae, dep_order) =
-            super::install::resolve_dependencies(api, &[formula.to_string()]).await?;
+        let (all_formulae, dep_order) = super::install::resolve_dependencies(
+            source,
+            &[formula.to_string()],
+            &crate::api::DependencyPolicy::default(),
+        )
+        .await?;
         // SAFETY: Removing the temporary BRU_QUIET variable set above. Safe for same reasons.
         unsafe {
             std::env::remove_var("BRU_QUIET");
         }
 
-        // Remove the root formula from the dependency list
-        let mut deps: Vec<_> = dep_order
-            .into_iter()
-            .filter(|name| name != formula)
-            .collect();
+        // Remove the root formula (keyed by its short name) from the dependency list
+        let root = crate::tap::split_qualified(formula).map_or(formula, |(_, name)| name);
+        let mut deps: Vec<_> = dep_order.into_iter().filter(|name| name != root).collect();
+
+        if annotate {
+            if installed_only {
+                deps.retain(|dep| installed_names.contains(dep));
+            }
+            // A dependency's tags are every way any formula in the tree declares it
+            let mut tags: HashMap<&str, Vec<DependencyTag>> = HashMap::new();
+            for (dep, dep_tags) in all_formulae.values().flat_map(|f| f.tagged_dependencies()) {
+                let entry = tags.entry(dep).or_default();
+                entry.extend(dep_tags);
+                entry.sort();
+                entry.dedup();
+            }
+            let deps: Vec<_> = deps
+                .iter()
+                .map(|dep| {
+                    let dep_tags = tags.get(dep.as_str()).cloned().unwrap_or_default();
+                    (dep.as_str(), dep_tags)
+                })
+                .collect();
+            return print_annotated(&deps, tree, is_tty, json);
+        }
+
+        if json {
+            if installed_only {
+                deps.retain(|dep| installed_names.contains(dep));
+            }
+            output::print_json(&deps)?;
+            return Ok(());
+        }
 
         if deps.is_empty() {
             if is_tty {
@@ -419,8 +1251,16 @@ pub async fn deps(
     Ok(())
 }
 
-/// Show formulae that depend on a given formula
-pub async fn uses(api: &BrewApi, formula: &str, installed_only: bool) -> Result<()> {
+/// Show formulae (and optionally casks) that depend on a given formula
+///
+/// With `include_casks` (`--cask`/`--eval-all`), casks whose `depends_on formula:`
+/// lists the formula are reported too.
+pub async fn uses(
+    api: &BrewApi,
+    formula: &str,
+    installed_only: bool,
+    include_casks: bool,
+) -> Result<()> {
     let is_tty = std::io::IsTerminal::is_terminal(&std::io::stdout());
 
     let spinner = if is_tty {
@@ -439,16 +1279,32 @@ pub async fn uses(api: &BrewApi, formula: &str, installed_only: bool) -> Result<
 
     // If filtering by installed, get the list of installed packages
     let installed_names: HashSet<String> = if installed_only {
-        cellar::list_installed()?
+        let mut names: HashSet<String> = cellar::list_installed()?
             .into_iter()
             .map(|p| p.name)
-            .collect()
+            .collect();
+        if include_casks {
+            names.extend(
+                crate::cask::list_installed_casks()?
+                    .into_iter()
+                    .map(|(token, _)| token),
+            );
+        }
+        names
     } else {
         HashSet::new()
     };
 
-    // Fetch all formulae
-    let all_formulae = api.fetch_all_formulae().await?;
+    // Fetch all formulae (only names, descriptions, and dependencies are needed),
+    // and all casks in parallel when requested
+    let (all_formulae, all_casks) = tokio::join!(api.fetch_all_formulae_brief(), async {
+        if include_casks {
+            api.fetch_all_casks().await
+        } else {
+            Ok(Vec::new())
+        }
+    });
+    let (all_formulae, all_casks) = (all_formulae?, all_casks?);
     spinner.finish_and_clear();
 
     // Find formulae that depend on the target
@@ -460,19 +1316,31 @@ pub async fn uses(api: &BrewApi, formula: &str, installed_only: bool) -> Result<
         })
         .collect();
 
+    // Find casks that declare `depends_on formula:` on the target
+    let mut dependent_casks: Vec<_> = all_casks
+        .into_iter()
+        .filter(|c| c.depends_on.formula.iter().any(|f| f == formula))
+        .collect();
+
     // Filter to installed only if requested
     if installed_only {
         dependent_formulae.retain(|f| installed_names.contains(&f.name));
+        dependent_casks.retain(|c| installed_names.contains(&c.token));
     }
 
-    if dependent_formulae.is_empty() {
+    if dependent_formulae.is_empty() && dependent_casks.is_empty() {
         if is_tty {
-            println!("{} No formulae depend on '{}'", "".green(), formula);
+            let kind = if include_casks {
+                "formulae or casks"
+            } else {
+                "formulae"
+            };
+            println!("{} No {} depend on '{}'", "".green(), kind, formula);
         }
         return Ok(());
     }
 
-    if is_tty {
+    if is_tty && !dependent_formulae.is_empty() {
         println!(
             "{} Found {} formulae that depend on {}:",
             "".green(),
@@ -495,6 +1363,29 @@ pub async fn uses(api: &BrewApi, formula: &str, installed_only: bool) -> Result<
         }
     }
 
+    if is_tty && !dependent_casks.is_empty() {
+        println!(
+            "{} Found {} casks that depend on {}:",
+            "".green(),
+            dependent_casks.len().to_string().bold(),
+            formula.cyan()
+        );
+    }
+
+    for c in dependent_casks {
+        if is_tty {
+            print!("{}", c.token.bold());
+            if let Some(desc) = &c.desc
+                && !desc.is_empty()
+            {
+                print!(" {}", format!("({})", desc).dimmed());
+            }
+            println!();
+        } else {
+            println!("{}", c.token);
+        }
+    }
+
     Ok(())
 }
 
@@ -561,8 +1452,18 @@ pub async fn desc(api: &BrewApi, formula_names: &[String]) -> Result<()> {
     Ok(())
 }
 
-/// Display the contents of a formula or cask as JSON
-pub async fn cat(api: &BrewApi, formula_names: &[String]) -> Result<()> {
+/// Display the contents of a formula or cask
+///
+/// Formulae are printed as their API JSON. Casks print the Ruby source from a local
+/// tap when one has it (see [`tap::find_cask_file`](crate::tap::find_cask_file)), and
+/// their API JSON otherwise. `formula_only` and `cask_only` restrict the lookup to
+/// one kind; by default a formula of the same name wins.
+pub async fn cat(
+    api: &BrewApi,
+    formula_names: &[String],
+    formula_only: bool,
+    cask_only: bool,
+) -> Result<()> {
     if formula_names.is_empty() {
         println!("{} No formulae specified", "".red());
         return Ok(());
@@ -577,28 +1478,37 @@ pub async fn cat(api: &BrewApi, formula_names: &[String]) -> Result<()> {
         println!();
 
         // Try to fetch formula from API
-        match api.fetch_formula(formula_name).await {
+        let fetched = if cask_only {
+            Err(BruError::FormulaNotFound(formula_name.clone()))
+        } else {
+            api.fetch_formula(formula_name).await
+        };
+        match fetched {
             Ok(formula) => {
                 // Print formula as JSON (since we don't have Ruby source)
                 let json = serde_json::to_string_pretty(&formula)?;
                 println!("{}", json);
             }
-            Err(_) => {
-                // Try as cask
+            Err(_) if !formula_only => {
+                // Try as cask, preferring its Ruby source from a local tap
+                if let Some(path) = crate::tap::find_cask_file(formula_name) {
+                    print!("{}", std::fs::read_to_string(&path)?);
+                    continue;
+                }
                 match api.fetch_cask(formula_name).await {
                     Ok(cask) => {
                         let json = serde_json::to_string_pretty(&cask)?;
                         println!("{}", json);
                     }
                     Err(_) => {
-                        println!(
-                            "{} No formula or cask found for '{}'",
-                            "".red(),
-                            formula_name
-                        );
+                        let kind = if cask_only { "cask" } else { "formula or cask" };
+                        println!("{} No {} found for '{}'", "".red(), kind, formula_name);
                     }
                 }
             }
+            Err(_) => {
+                println!("{} No formula found for '{}'", "".red(), formula_name);
+            }
         }
     }
 
@@ -696,7 +1606,7 @@ pub fn formula(formula_name: &str) -> anyhow::Result<()> {
 pub async fn formulae(api: &BrewApi) -> Result<()> {
     println!("Fetching all available formulae...");
 
-    let all_formulae = api.fetch_all_formulae().await?;
+    let all_formulae = api.fetch_all_formulae_brief().await?;
 
     println!(
         "{} {} formulae available",
@@ -857,3 +1767,68 @@ pub async fn formula_info(api: &BrewApi, formula_name: &str) -> anyhow::Result<(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::MockSource;
+
+    #[test]
+    fn test_revision_bump_makes_installed_formula_outdated() {
+        let installed =
+            |versions: &[&str]| -> Vec<String> { versions.iter().map(|v| v.to_string()).collect() };
+
+        assert!(!is_outdated(&installed(&["14.1.0_1"]), Some("14.1.0_1")));
+        assert!(is_outdated(&installed(&["14.1.0"]), Some("14.1.0_1")));
+        assert!(!is_outdated(
+            &installed(&["14.1.0_1", "14.1.0"]),
+            Some("14.1.0_1")
+        ));
+        assert!(is_outdated(&installed(&["13.0.0"]), Some("14.1.0")));
+        assert!(!is_outdated(&[], Some("14.1.0")));
+        assert!(!is_outdated(&installed(&["14.1.0"]), None));
+    }
+
+    #[tokio::test]
+    async fn test_lookup_info_finds_formula_and_cask_of_same_name() {
+        let named = |name: &str| serde_json::from_value(serde_json::json!({ "name": name }));
+        let source = MockSource::new(
+            vec![named("docker").unwrap(), named("jq").unwrap()],
+            &["docker", "firefox"],
+        );
+
+        let (formula, cask) = lookup_info(&source, "docker", false, true).await;
+        assert_eq!(formula.unwrap().name, "docker");
+        assert_eq!(cask.unwrap().token, "docker");
+
+        let (formula, cask) = lookup_info(&source, "jq", false, true).await;
+        assert!(formula.is_ok());
+        assert!(cask.is_none());
+
+        // --cask skips the formula (`info` then fetches the cask on its own), --formula
+        // and --json skip the cask
+        let (formula, cask) = lookup_info(&source, "docker", true, false).await;
+        assert!(matches!(formula, Err(BruError::FormulaNotFound(_))));
+        assert!(cask.is_none());
+        let (formula, cask) = lookup_info(&source, "docker", false, false).await;
+        assert!(formula.is_ok());
+        assert!(cask.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_github_info_only_for_github_homepages() {
+        let api = BrewApi::new().unwrap();
+        // None of these asks GitHub, so none prints a GitHub line
+        assert!(
+            fetch_github_info(&api, Some("https://www.gnu.org/software/wget/"), true)
+                .await
+                .is_none()
+        );
+        assert!(fetch_github_info(&api, None, true).await.is_none());
+        assert!(
+            fetch_github_info(&api, Some("https://github.com/jqlang/jq"), false)
+                .await
+                .is_none()
+        );
+    }
+}
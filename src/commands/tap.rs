@@ -3,10 +3,8 @@
 //! Taps are third-party repositories that extend Homebrew with additional formulae
 //! and casks. This module provides commands for managing taps and their lifecycle.
 
-use crate::cellar;
 use crate::error::Result;
 use colored::Colorize;
-use std::path::Path;
 
 /// List all installed taps, or add a new tap
 pub fn tap(tap_name: Option<&str>) -> Result<()> {
@@ -44,7 +42,10 @@ pub fn tap(tap_name: Option<&str>) -> Result<()> {
 }
 
 /// Remove an installed tap
-pub fn untap(tap_name: &str) -> Result<()> {
+///
+/// Refuses while installed formulae came from the tap, since they could no longer be
+/// upgraded or reinstalled; `force` removes it anyway.
+pub fn untap(tap_name: &str, force: bool) -> Result<()> {
     println!("Untapping {}...", tap_name.cyan());
 
     if !crate::tap::is_tapped(tap_name)? {
@@ -52,6 +53,33 @@ pub fn untap(tap_name: &str) -> Result<()> {
         return Ok(());
     }
 
+    let installed = crate::tap::installed_formulae_from_tap(tap_name)?;
+    if !installed.is_empty() {
+        if !force {
+            println!(
+                "  {} Installed formulae came from {}: {}",
+                "".red(),
+                tap_name.bold(),
+                installed.join(", ").cyan()
+            );
+            println!(
+                "    Uninstall them first, or use {} to untap anyway",
+                "--force".dimmed()
+            );
+            return Err(anyhow::anyhow!(
+                "Refusing to untap {} while {} installed formulae come from it",
+                tap_name,
+                installed.len()
+            )
+            .into());
+        }
+        println!(
+            "  {} Untapping anyway; these formulae can no longer be upgraded: {}",
+            "".yellow(),
+            installed.join(", ").cyan()
+        );
+    }
+
     crate::tap::untap(tap_name)?;
 
     println!(
@@ -77,85 +105,40 @@ pub fn tap_info(tap_name: &str) -> Result<()> {
         return Ok(());
     }
 
-    let tap_dir = crate::tap::tap_directory(tap_name)?;
+    let info = crate::tap::info(tap_name)?;
 
     println!("{}", "Location:".bold());
-    println!("  {}", tap_dir.display().to_string().cyan());
+    println!("  {}", info.path.display().to_string().cyan());
+    if let Some(remote) = &info.remote {
+        println!("  {}: {}", "Remote".dimmed(), remote);
+    }
+    if let Some(head) = &info.head {
+        println!("  {}: {}", "Head".dimmed(), head);
+    }
+    if info.pinned {
+        println!("  {}", "Pinned (not updated)".yellow());
+    }
     println!();
 
-    // Count formulae in the tap (recursively for letter-organized directories)
-    let formula_dir = tap_dir.join("Formula");
-    let formula_count = if formula_dir.exists() {
-        count_rb_files(&formula_dir, 0)
-    } else {
-        0
-    };
-
-    // Count casks in the tap
-    let casks_dir = tap_dir.join("Casks");
-    let cask_count = if casks_dir.exists() {
-        count_rb_files(&casks_dir, 0)
-    } else {
-        0
-    };
-
     println!("{}", "Contents:".bold());
     println!(
         "  {}: {}",
         "Formulae".dimmed(),
-        formula_count.to_string().cyan()
+        info.formula_count.to_string().cyan()
+    );
+    println!(
+        "  {}: {}",
+        "Casks".dimmed(),
+        info.cask_count.to_string().cyan()
     );
-    println!("  {}: {}", "Casks".dimmed(), cask_count.to_string().cyan());
 
     Ok(())
 }
 
-/// Recursively count .rb files in a directory (helper for tap_info)
-/// Limits recursion depth to prevent infinite loops
-fn count_rb_files(dir: &Path, depth: usize) -> usize {
-    const MAX_DEPTH: usize = 10;
-    if depth > MAX_DEPTH {
-        return 0;
-    }
-
-    let mut count = 0;
-    if let Ok(entries) = std::fs::read_dir(dir) {
-        for entry in entries.flatten() {
-            let path = entry.path();
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("rb") {
-                count += 1;
-            } else if path.is_dir() {
-                count += count_rb_files(&path, depth + 1);
-            }
-        }
-    }
-    count
-}
-
 /// Create a new tap with standard directory structure
 pub fn tap_new(tap_name: &str) -> Result<()> {
-    // Validate tap name format (should be user/repo)
-    let parts: Vec<&str> = tap_name.split('/').collect();
-    if parts.len() != 2 {
-        println!(
-            "{} Invalid tap name. Format: {}",
-            "".red(),
-            "user/repo".cyan()
-        );
-        return Ok(());
-    }
-
-    let user = parts[0];
-    let repo = parts[1];
-
-    // Ensure repo name has "homebrew-" prefix
-    let full_repo_name = if repo.starts_with("homebrew-") {
-        String::from(repo)
-    } else {
-        format!("homebrew-{}", repo)
-    };
-
-    let tap_path = crate::tap::taps_path().join(user).join(&full_repo_name);
+    let tap = crate::tap::normalize(tap_name)?;
+    let tap_path = tap.dir_in(&crate::cellar::detect_prefix());
 
     if tap_path.exists() {
         println!(
@@ -166,7 +149,7 @@ pub fn tap_new(tap_name: &str) -> Result<()> {
         return Ok(());
     }
 
-    println!("Creating new tap: {}", tap_name.cyan());
+    println!("Creating new tap: {}", tap.to_string().cyan());
 
     // Create directory structure
     std::fs::create_dir_all(&tap_path)?;
@@ -176,7 +159,7 @@ pub fn tap_new(tap_name: &str) -> Result<()> {
     // Create README with usage instructions
     let readme_content = format!(
         "# {}/{}\n\nHomebrew tap for custom formulae and casks.\n\n## Usage\n\n```bash\nbrew tap {}\n```\n",
-        user, full_repo_name, tap_name
+        tap.user, tap.full_repo, tap
     );
     std::fs::write(tap_path.join("README.md"), readme_content)?;
 
@@ -218,13 +201,10 @@ pub fn tap_pin(tap_name: &str) -> anyhow::Result<()> {
         return Ok(());
     }
 
-    let prefix = cellar::detect_prefix();
-    let pinned_dir = prefix.join("Library/PinnedTaps");
-
-    std::fs::create_dir_all(&pinned_dir)?;
-
-    // Use normalized filename for pinned taps (replace / with --)
-    let pin_file = pinned_dir.join(tap_name.replace('/', "--"));
+    let pin_file = crate::tap::pin_file(tap_name)?;
+    if let Some(pinned_dir) = pin_file.parent() {
+        std::fs::create_dir_all(pinned_dir)?;
+    }
 
     if pin_file.exists() {
         println!("Tap already pinned");
@@ -247,9 +227,7 @@ pub fn tap_pin(tap_name: &str) -> anyhow::Result<()> {
 pub fn tap_unpin(tap_name: &str) -> anyhow::Result<()> {
     println!("Unpinning tap: {}", tap_name.cyan());
 
-    let prefix = cellar::detect_prefix();
-    let pinned_dir = prefix.join("Library/PinnedTaps");
-    let pin_file = pinned_dir.join(tap_name.replace('/', "--"));
+    let pin_file = crate::tap::pin_file(tap_name)?;
 
     if !pin_file.exists() {
         println!("Tap is not pinned");
@@ -4,6 +4,7 @@
 //! finding which formula provides a command, managing aliases, and
 //! accessing documentation.
 
+use super::Outcome;
 use crate::api::BrewApi;
 use crate::cellar;
 use crate::error::Result;
@@ -302,87 +303,170 @@ pub fn man() -> anyhow::Result<()> {
 
 /// Generate shell integration for command-not-found handler
 ///
-/// Outputs shell-specific configuration for integrating Homebrew's
-/// command-not-found handler, which suggests packages to install
-/// when a command is not found.
+/// Outputs a self-contained handler for bash, zsh or fish that, when a command
+/// isn't found, asks `bru command <name>` which formula provides it and prints
+/// the suggested install command. Meant to be evaluated from the shell's rc file
+/// (e.g., `eval "$(bru command-not-found-init)"`).
 ///
 /// # Arguments
 /// * `shell` - Optional shell name (defaults to $SHELL)
 pub fn command_not_found_init(shell: Option<&str>) -> Result<()> {
-    let detected_shell = shell.map(String::from).unwrap_or_else(|| {
-        std::env::var("SHELL")
-            .ok()
-            .and_then(|s| s.split('/').next_back().map(String::from))
-            .unwrap_or_else(|| "bash".to_string())
-    });
+    let detected_shell = super::paths::resolve_shell(shell);
+
+    // Call this bru by absolute path, so the handler can't recurse into itself
+    // when bru isn't on PATH
+    let exe = std::env::current_exe()
+        .map(|path| path.display().to_string())
+        .unwrap_or_else(|_| String::from("bru"));
 
     println!("# bru command-not-found hook for {}", detected_shell);
-    println!();
 
-    match detected_shell.as_str() {
-        "bash" => {
-            println!("# Add this to your ~/.bashrc:");
-            println!(
-                "HB_CNF_HANDLER=\"$(brew --repository)/Library/Taps/homebrew/homebrew-command-not-found/handler.sh\""
-            );
-            println!("if [ -f \"$HB_CNF_HANDLER\" ]; then");
-            println!("  source \"$HB_CNF_HANDLER\"");
-            println!("fi");
+    let Some(shell_type) = super::paths::Shell::from_name(&detected_shell) else {
+        println!("# Shell '{}' not directly supported", detected_shell);
+        println!("# Use bash or zsh configuration as a starting point");
+        return Ok(());
+    };
+    let bru = super::paths::escape(&exe, shell_type.quoted_specials());
+
+    match shell_type {
+        super::paths::Shell::Bash => {
+            println!("# Add this to your ~/.bashrc: eval \"$(bru command-not-found-init)\"");
+            println!("command_not_found_handle() {{");
+            println!("  if [ -x \"{bru}\" ] && \"{bru}\" command \"$1\" >&2; then");
+            println!("    return 127");
+            println!("  fi");
+            println!("  printf '%s: %s: command not found\\n' \"${{0##*/}}\" \"$1\" >&2");
+            println!("  return 127");
+            println!("}}");
         }
-        "zsh" => {
-            println!("# Add this to your ~/.zshrc:");
-            println!(
-                "HB_CNF_HANDLER=\"$(brew --repository)/Library/Taps/homebrew/homebrew-command-not-found/handler.sh\""
-            );
-            println!("if [ -f \"$HB_CNF_HANDLER\" ]; then");
-            println!("  source \"$HB_CNF_HANDLER\"");
-            println!("fi");
+        super::paths::Shell::Zsh => {
+            println!("# Add this to your ~/.zshrc: eval \"$(bru command-not-found-init)\"");
+            println!("command_not_found_handler() {{");
+            println!("  if [ -x \"{bru}\" ] && \"{bru}\" command \"$1\" >&2; then");
+            println!("    return 127");
+            println!("  fi");
+            println!("  printf 'zsh: command not found: %s\\n' \"$1\" >&2");
+            println!("  return 127");
+            println!("}}");
         }
-        "fish" => {
-            println!("# Add this to your ~/.config/fish/config.fish:");
+        super::paths::Shell::Fish => {
             println!(
-                "set HB_CNF_HANDLER (brew --repository)/Library/Taps/homebrew/homebrew-command-not-found/handler.fish"
+                "# Add this to your ~/.config/fish/config.fish: bru command-not-found-init | source"
             );
-            println!("if test -f $HB_CNF_HANDLER");
-            println!("  source $HB_CNF_HANDLER");
+            println!("function fish_command_not_found");
+            println!("  if test -x \"{bru}\"; and \"{bru}\" command $argv[1] >&2");
+            println!("    return 127");
+            println!("  end");
+            println!("  __fish_default_command_not_found_handler $argv");
             println!("end");
         }
-        _ => {
-            println!("# Shell '{}' not directly supported", detected_shell);
-            println!("# Use bash or zsh configuration as a starting point");
-        }
     }
 
     Ok(())
 }
 
-/// Execute an external Homebrew command
+/// Suggest how to get the command `name`
 ///
-/// Runs external Homebrew commands or scripts (typically those prefixed
-/// with "brew-" in the PATH).
+/// Backs the `command-not-found-init` handler. An installed keg that ships `name`
+/// comes first (it's just not linked, or the prefix isn't on `PATH`); otherwise the
+/// formulae that provide it are suggested for install, even when their name differs
+/// from the command's (`rg` comes from `ripgrep`).
+///
+/// Returns [`Outcome::NotFound`] when nothing provides `name`, so the handler can
+/// fall back to the shell's usual message.
 ///
 /// # Arguments
-/// * `subcommand` - The command name to execute
-/// * `args` - Arguments to pass to the command
-pub fn command(subcommand: &str, args: &[String]) -> anyhow::Result<()> {
-    println!("Running Homebrew sub-command: {}", subcommand.cyan());
+/// * `api` - The Homebrew API client
+/// * `name` - The command that wasn't found
+pub async fn command(api: &BrewApi, name: &str) -> Result<Outcome> {
+    if name.is_empty() || name.contains('/') {
+        return Ok(Outcome::NotFound);
+    }
 
-    if !args.is_empty() {
-        println!("  Arguments: {}", args.join(" ").dimmed());
+    let prefix = cellar::detect_prefix();
+    let provides = |keg: &std::path::Path| {
+        ["bin", "sbin"]
+            .iter()
+            .any(|dir| keg.join(dir).join(name).exists())
+    };
+    if let Some(installed) = cellar::list_installed()?
+        .into_iter()
+        .find(|pkg| provides(&pkg.path))
+    {
+        if provides(&prefix) {
+            println!(
+                "The program '{}' is installed by '{}' in {}, which is not on your PATH.",
+                name,
+                installed.name,
+                prefix.join("bin").display()
+            );
+            println!("Add it with:");
+            println!("  eval \"$(bru shellenv)\"");
+        } else {
+            println!(
+                "The program '{}' is installed by '{}', which is not linked. Link it with:",
+                name, installed.name
+            );
+            println!("  bru link {}", installed.name);
+        }
+        return Ok(Outcome::Done);
     }
 
-    println!(" Sub-command execution");
-    println!("  Runs external Homebrew commands or scripts");
+    let mut formulae = formulae_providing(api, name).await?;
+    // The formula named after the command first, then the rest alphabetically
+    formulae.sort_by(|a, b| (a != name).cmp(&(b != name)).then_with(|| a.cmp(b)));
+    formulae.dedup();
 
-    println!("  Would execute:");
-    if args.is_empty() {
-        println!("    brew-{}", subcommand);
-    } else {
-        println!("    brew-{} {}", subcommand, args.join(" "));
+    match formulae.as_slice() {
+        [] => return Ok(Outcome::NotFound),
+        [formula] => {
+            println!(
+                "The program '{}' is currently not installed. You can install it by typing:",
+                name
+            );
+            println!("  bru install {}", formula);
+        }
+        _ => {
+            println!(
+                "The program '{}' can be found in the following formulae:",
+                name
+            );
+            for formula in &formulae {
+                println!("  * {}", formula);
+            }
+            println!("Try: bru install <selected formula>");
+        }
     }
 
-    println!(" This is an internal command");
-    println!("  Used by Homebrew to run external commands");
+    Ok(Outcome::Done)
+}
+
+/// Formulae that install the executable `name`, installed or not
+///
+/// Looks `name` up in Homebrew's executables index, falling back to a stale copy when
+/// it can't be refreshed (e.g. offline). Without any index, formulae named after the
+/// command (including versioned ones like `python@3.13`) are the best guess, taken
+/// from the cached formula names.
+async fn formulae_providing(api: &BrewApi, name: &str) -> Result<Vec<String>> {
+    let index = match api.fetch_executables_index().await {
+        Ok(index) => Some(index),
+        Err(_) => crate::cache::get_cached_executables_any_age(),
+    };
+    if let Some(index) = index {
+        return Ok(index
+            .into_iter()
+            .filter(|f| f.executables.iter().any(|e| e == name))
+            .map(|f| f.name)
+            .collect());
+    }
 
-    Ok(())
+    let names = match crate::cache::get_cached_formulae_brief_any_age() {
+        Some(index) => index,
+        None => api.fetch_all_formulae_brief().await?,
+    };
+    Ok(names
+        .into_iter()
+        .map(|f| f.name)
+        .filter(|f| f == name || f.split_once('@').is_some_and(|(base, _)| base == name))
+        .collect())
 }
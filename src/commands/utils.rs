@@ -43,6 +43,25 @@ pub(super) fn fallback_to_brew_with_reason(
     command: &str,
     formula_name: &str,
     reason: Option<&str>,
+) -> Result<()> {
+    delegate_to_brew(command, formula_name, reason, false)
+}
+
+/// Fallback to brew with `--build-from-source`, for formulae without a usable bottle
+/// and kegs that were built from source, so brew doesn't pour a bottle instead
+pub(super) fn fallback_to_brew_from_source(
+    command: &str,
+    formula_name: &str,
+    reason: Option<&str>,
+) -> Result<()> {
+    delegate_to_brew(command, formula_name, reason, true)
+}
+
+fn delegate_to_brew(
+    command: &str,
+    formula_name: &str,
+    reason: Option<&str>,
+    build_from_source: bool,
 ) -> Result<()> {
     if let Some(msg) = reason {
         println!("  {}", msg);
@@ -60,10 +79,12 @@ pub(super) fn fallback_to_brew_with_reason(
 
     println!("  Delegating to {}...", format!("brew {}", command).cyan());
 
-    let status = Command::new("brew")
-        .arg(command)
-        .arg(formula_name)
-        .status()?;
+    let mut brew = Command::new("brew");
+    brew.arg(command);
+    if build_from_source {
+        brew.arg("--build-from-source");
+    }
+    let status = brew.arg(formula_name).status()?;
 
     if status.success() {
         println!(
@@ -107,9 +128,19 @@ pub(super) fn cleanup_specific_version(formula_name: &str, old_version: &str) ->
     Ok(())
 }
 
-/// Read the list of pinned formulae from the tracking file
-pub(super) fn read_pinned() -> Result<Vec<String>> {
-    let path = cellar::detect_prefix().join("var/homebrew/pinned_formulae");
+/// Returns the path to the pinned formulae (or, with `cask`, pinned casks) tracking file
+pub(super) fn pinned_file_path(cask: bool) -> std::path::PathBuf {
+    let file = if cask {
+        "pinned_casks"
+    } else {
+        "pinned_formulae"
+    };
+    cellar::detect_prefix().join("var/homebrew").join(file)
+}
+
+/// Read the list of pinned formulae (or casks) from the tracking file
+pub(super) fn read_pinned(cask: bool) -> Result<Vec<String>> {
+    let path = pinned_file_path(cask);
     if !path.exists() {
         return Ok(Vec::new());
     }
@@ -118,3 +149,71 @@ pub(super) fn read_pinned() -> Result<Vec<String>> {
     // Convert lines to owned strings
     Ok(content.lines().map(String::from).collect())
 }
+
+/// Format byte size as human-readable string
+pub(super) fn format_size(bytes: u64) -> String {
+    const KB: u64 = 1024;
+    const MB: u64 = KB * 1024;
+    const GB: u64 = MB * 1024;
+
+    if bytes >= GB {
+        format!("{:.2} GB", bytes as f64 / GB as f64)
+    } else if bytes >= MB {
+        format!("{:.2} MB", bytes as f64 / MB as f64)
+    } else if bytes >= KB {
+        format!("{:.2} KB", bytes as f64 / KB as f64)
+    } else {
+        format!("{} bytes", bytes)
+    }
+}
+
+/// Outcome of a command run over many arguments.
+///
+/// With `keep_going`, the arguments that failed are listed once at the end, so the
+/// summary isn't lost among per-package output.
+pub(super) fn batch_outcome(verb: &str, failures: &[String], keep_going: bool) -> super::Outcome {
+    if keep_going && !failures.is_empty() {
+        println!();
+        println!(
+            "{} {} failed to {}: {}",
+            "".red(),
+            if failures.len() == 1 {
+                "1 formula".to_string()
+            } else {
+                format!("{} formulae", failures.len())
+            },
+            verb,
+            failures.join(", ").bold()
+        );
+    }
+    super::Outcome::from_failures(failures.len())
+}
+
+/// Ask a yes/no question on the terminal, defaulting to yes on an empty answer.
+///
+/// Returns `Ok(true)` without prompting when stdin or stdout is not a terminal, so
+/// piped and scripted runs are never blocked.
+pub(super) fn confirm(question: &str) -> Result<bool> {
+    use std::io::{BufRead, IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() || !std::io::stdout().is_terminal() {
+        return Ok(true);
+    }
+
+    print!("{} [Y/n] ", question);
+    std::io::stdout().flush()?;
+
+    let mut answer = String::new();
+    std::io::stdin().lock().read_line(&mut answer)?;
+    let answer = answer.trim().to_ascii_lowercase();
+
+    Ok(answer.is_empty() || answer == "y" || answer == "yes")
+}
+
+/// Print a "Did you mean" hint for an unknown name, if the cached index has close matches
+pub fn print_suggestions(name: &str) {
+    let suggestions = crate::api::suggest(name, 3);
+    if !suggestions.is_empty() {
+        println!("Did you mean: {}?", suggestions.join(", ").bold());
+    }
+}
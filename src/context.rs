@@ -0,0 +1,178 @@
+//! Shared context for library operations - prefix, cache, HTTP client, and configuration.
+//!
+//! A [`BrewContext`] bundles everything an install/upgrade/reinstall needs so it can be
+//! created once and passed down instead of being re-derived by each call:
+//! - **Prefix**: The Homebrew root (`/opt/homebrew`, `/usr/local`, or a custom path)
+//! - **Cache directory**: Where downloaded bottles are stored
+//! - **HTTP client**: One connection pool shared by API lookups and bottle downloads
+//! - **API client**: The [`BrewApi`] instance, built on the shared HTTP client
+//! - **Configuration**: Tunables such as the request timeout, proxy and API rate limit
+//!
+//! The free functions in [`cellar`](crate::cellar), [`download`](crate::download),
+//! [`extract`](crate::extract) and [`symlink`](crate::symlink) keep working on the
+//! detected prefix; they are thin wrappers over the `_in`/`_to` variants that a
+//! context drives.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! use kombrucha::{BrewContext, PackageManager};
+//!
+//! #[tokio::main]
+//! async fn main() -> anyhow::Result<()> {
+//!     // Manage an alternate prefix without touching HOMEBREW_PREFIX
+//!     let ctx = BrewContext::with_prefix("/tmp/brew")?;
+//!     let pm = PackageManager::with_context(ctx);
+//!
+//!     let result = pm.install("jq").await?;
+//!     println!("Installed to {}", result.path.display());
+//!
+//!     Ok(())
+//! }
+//! ```
+
+use crate::api::{self, BrewApi, RateLimit};
+use crate::cellar;
+use crate::download;
+use crate::error::Result;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Tunable options shared by operations that run under a [`BrewContext`].
+#[derive(Debug, Clone)]
+pub struct BrewConfig {
+    /// Timeout applied to every HTTP request (API lookups and bottle downloads)
+    pub request_timeout: Duration,
+    /// Proxy URL for all requests, taking precedence over proxy environment variables
+    pub proxy: Option<String>,
+    /// Concurrency and request-rate limits for Homebrew API lookups
+    pub rate_limit: RateLimit,
+}
+
+impl BrewConfig {
+    /// Route all requests through `url` (e.g. `http://proxy.corp:3128`).
+    ///
+    /// An explicit proxy overrides `HOMEBREW_*_PROXY` and the standard `HTTPS_PROXY`,
+    /// `HTTP_PROXY` and `ALL_PROXY` variables. Hosts in `HOMEBREW_NO_PROXY`/`NO_PROXY`
+    /// still bypass it.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::{BrewConfig, BrewContext};
+    ///
+    /// let config = BrewConfig::default().with_proxy("http://proxy.corp:3128");
+    /// let ctx = BrewContext::with_config("/opt/homebrew", config)?;
+    /// # Ok::<(), kombrucha::BruError>(())
+    /// ```
+    pub fn with_proxy(mut self, url: impl Into<String>) -> Self {
+        self.proxy = Some(url.into());
+        self
+    }
+
+    /// Limit how many Homebrew API requests run at once and how fast they start.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::{BrewConfig, BrewContext, RateLimit};
+    ///
+    /// let config = BrewConfig::default().with_rate_limit(RateLimit {
+    ///     max_concurrent: 2,
+    ///     requests_per_second: Some(5.0),
+    /// });
+    /// let ctx = BrewContext::with_config("/opt/homebrew", config)?;
+    /// # Ok::<(), kombrucha::BruError>(())
+    /// ```
+    pub fn with_rate_limit(mut self, limit: RateLimit) -> Self {
+        self.rate_limit = limit;
+        self
+    }
+}
+
+impl Default for BrewConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: api::REQUEST_TIMEOUT,
+            proxy: None,
+            rate_limit: RateLimit::default(),
+        }
+    }
+}
+
+/// Prefix, cache, HTTP client, and configuration for library operations.
+///
+/// Cloning is cheap: the HTTP client is reference-counted, so clones share the same
+/// connection pool.
+#[derive(Clone)]
+pub struct BrewContext {
+    /// Homebrew prefix (e.g. `/opt/homebrew`)
+    pub prefix: PathBuf,
+    /// Directory where downloaded bottles are cached
+    pub cache_dir: PathBuf,
+    /// HTTP client shared by API requests and bottle downloads
+    pub http: reqwest::Client,
+    /// Homebrew API client, built on `http`
+    pub api: BrewApi,
+    /// Configuration the context was built with
+    pub config: BrewConfig,
+}
+
+impl BrewContext {
+    /// Create a context for the detected Homebrew prefix with default configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::BrewContext;
+    ///
+    /// let ctx = BrewContext::new()?;
+    /// println!("Prefix: {}", ctx.prefix.display());
+    /// # Ok::<(), kombrucha::BruError>(())
+    /// ```
+    pub fn new() -> Result<Self> {
+        Self::with_config(cellar::detect_prefix(), BrewConfig::default())
+    }
+
+    /// Create a context for an explicit prefix with default configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created.
+    pub fn with_prefix(prefix: impl Into<PathBuf>) -> Result<Self> {
+        Self::with_config(prefix, BrewConfig::default())
+    }
+
+    /// Create a context for an explicit prefix and configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the HTTP client cannot be created or the proxy URL is invalid.
+    pub fn with_config(prefix: impl Into<PathBuf>, config: BrewConfig) -> Result<Self> {
+        let http = api::build_http_client(config.request_timeout, config.proxy.as_deref())?;
+        let api = BrewApi::with_client(http.clone()).with_rate_limit(config.rate_limit);
+
+        Ok(Self {
+            prefix: prefix.into(),
+            cache_dir: download::cache_dir(),
+            http,
+            api,
+            config,
+        })
+    }
+
+    /// Use a different bottle cache directory.
+    pub fn with_cache_dir(mut self, cache_dir: impl Into<PathBuf>) -> Self {
+        self.cache_dir = cache_dir.into();
+        self
+    }
+
+    /// Get the Cellar directory (`{prefix}/Cellar`).
+    pub fn cellar(&self) -> PathBuf {
+        self.prefix.join("Cellar")
+    }
+}
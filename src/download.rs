@@ -5,7 +5,10 @@
 //! - **Parallel downloads**: Up to 8 concurrent downloads with semaphore control
 //! - **Progress tracking**: Optional visual progress bars during downloads
 //! - **Checksum verification**: SHA256 validation of downloaded files
+//! - **Response checks**: HTML error pages and empty bodies are rejected before saving
 //! - **Caching**: Avoids re-downloading bottles that already exist with correct checksum
+//! - **Resume and retry**: Interrupted downloads continue from a `.part` file; transient
+//!   network and server errors are retried with backoff
 //! - **GHCR authentication**: Automatic bearer token acquisition for public packages
 //!
 //! # Architecture
@@ -14,7 +17,7 @@
 //! ```text
 //! ~/.cache/bru/downloads/
 //!   formula-name--1.0.0.arm64_sonoma.bottle.tar.gz
-//!   other-package--2.1.0.x86_64_ventura.bottle.tar.gz
+//!   other-package--2.1.0.ventura.bottle.tar.gz
 //! ```
 //!
 //! The download process:
@@ -42,7 +45,7 @@
 //! }
 //! ```
 
-use crate::api::{BrewApi, Formula};
+use crate::api::{BottleFile, BrewApi, Formula};
 use crate::platform;
 use anyhow::{Context, Result, anyhow};
 use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
@@ -58,13 +61,12 @@ struct GhcrToken {
 }
 
 /// Get anonymous bearer token for GHCR
-async fn get_ghcr_token(repository: &str) -> Result<String> {
+async fn get_ghcr_token(client: &reqwest::Client, repository: &str) -> Result<String> {
     let url = format!(
         "https://ghcr.io/token?service=ghcr.io&scope=repository:{}:pull",
         repository
     );
 
-    let client = reqwest::Client::new();
     let response: GhcrToken = client.get(&url).send().await?.json().await?;
 
     Ok(response.token)
@@ -117,6 +119,141 @@ async fn verify_checksum(file_path: &Path, expected: &str) -> Result<bool> {
     Ok(checksum == expected)
 }
 
+/// Pick the bottle file for the current platform and its cache filename.
+fn resolve_bottle(formula: &Formula) -> Result<(&BottleFile, String)> {
+    // Get bottle info
+    let bottle = formula
+        .bottle
+        .as_ref()
+        .and_then(|b| b.stable.as_ref())
+        .ok_or_else(|| anyhow!("No bottle available for {}", formula.name))?;
+
+    // Detect platform (or use the --bottle-tag/HOMEBREW_BOTTLE_TAG override)
+    let platform_tag = platform::bottle_tag()?;
+
+    let bottle_file = bottle.file_for_tag(&platform_tag)?;
+
+    // Determine filename
+    let version = formula.install_version()?;
+    let filename = format!(
+        "{}--{}.{}.bottle.tar.gz",
+        formula.name, version, platform_tag
+    );
+
+    Ok((bottle_file, filename))
+}
+
+/// Formula name, version and platform tag encoded in a bottle's filename
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BottleFilename {
+    pub name: String,
+    /// Version including any revision suffix, e.g. `1.7.1_1`
+    pub version: String,
+    /// Platform tag such as `arm64_sonoma`, `x86_64_linux`, or `all`
+    pub tag: String,
+}
+
+impl BottleFilename {
+    /// Parse Homebrew's `<name>--<version>.<tag>.bottle[.<rebuild>].tar.gz` naming.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the expected format if `filename` doesn't follow it.
+    pub fn parse(filename: &str) -> Result<Self> {
+        let invalid = || {
+            anyhow!(
+                "'{}' is not a bottle filename (expected <name>--<version>.<tag>.bottle.tar.gz)",
+                filename
+            )
+        };
+
+        let stem = filename.strip_suffix(".tar.gz").ok_or_else(invalid)?;
+        // Rebuilt bottles carry a number after `.bottle`
+        let stem = match stem.rsplit_once(".bottle") {
+            Some((head, "")) => head,
+            Some((head, rebuild))
+                if rebuild
+                    .strip_prefix('.')
+                    .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit())) =>
+            {
+                head
+            }
+            _ => return Err(invalid()),
+        };
+        let (name, rest) = stem.split_once("--").ok_or_else(invalid)?;
+        let (version, tag) = rest.rsplit_once('.').ok_or_else(invalid)?;
+
+        let tag_valid = !tag.is_empty()
+            && tag
+                .bytes()
+                .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_');
+        if !crate::api::validate_name(name) || version.is_empty() || !tag_valid {
+            return Err(invalid());
+        }
+
+        Ok(Self {
+            name: name.to_string(),
+            version: version.to_string(),
+            tag: tag.to_string(),
+        })
+    }
+}
+
+/// The SHA256 the current platform's bottle for `formula` must match.
+///
+/// # Errors
+///
+/// Returns an error if the formula has no bottle for the current platform.
+pub fn bottle_sha256(formula: &Formula) -> Result<&str> {
+    Ok(&resolve_bottle(formula)?.0.sha256)
+}
+
+/// Find an already-downloaded bottle without touching the network.
+///
+/// Returns the cached bottle path if it exists and its SHA256 matches the formula's
+/// bottle checksum, or `None` if the bottle isn't cached or fails verification.
+///
+/// # Errors
+///
+/// Returns an error if the formula has no bottle for the current platform or the
+/// cached file cannot be read.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::{BrewApi, download};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let api = BrewApi::new()?;
+///     let formula = api.fetch_formula("jq").await?;
+///
+///     match download::cached_bottle(&formula).await? {
+///         Some(path) => println!("Cached: {}", path.display()),
+///         None => println!("Not cached"),
+///     }
+///
+///     Ok(())
+/// }
+/// ```
+pub async fn cached_bottle(formula: &Formula) -> Result<Option<PathBuf>> {
+    cached_bottle_in(formula, &cache_dir()).await
+}
+
+/// Find an already-downloaded bottle in an explicit cache directory.
+///
+/// Same as [`cached_bottle`], but looks in `cache` instead of the default download cache.
+pub async fn cached_bottle_in(formula: &Formula, cache: &Path) -> Result<Option<PathBuf>> {
+    let (bottle_file, filename) = resolve_bottle(formula)?;
+    let path = cache.join(filename);
+
+    if path.exists() && verify_checksum(&path, &bottle_file.sha256).await? {
+        Ok(Some(path))
+    } else {
+        Ok(None)
+    }
+}
+
 /// Download a single bottle from GitHub Container Registry (GHCR).
 ///
 /// Downloads a precompiled bottle for the current platform and verifies it via SHA256 checksum.
@@ -152,8 +289,7 @@ async fn verify_checksum(file_path: &Path, expected: &str) -> Result<bool> {
 ///     let api = BrewApi::new()?;
 ///     let formula = api.fetch_formula("ripgrep").await?;
 ///
-///     let client = reqwest::Client::new();
-///     let bottle_path = download::download_bottle(&formula, None, &client).await?;
+///     let bottle_path = download::download_bottle(&formula, None, api.client()).await?;
 ///     println!("Downloaded to: {}", bottle_path.display());
 ///
 ///     Ok(())
@@ -170,7 +306,7 @@ async fn verify_checksum(file_path: &Path, expected: &str) -> Result<bool> {
 /// # Platform Selection
 ///
 /// Bottles are matched to the current platform:
-/// - macOS: `arm64_sequoia`, `x86_64_ventura`, etc.
+/// - macOS: `arm64_sequoia`, `ventura` (Intel), etc.
 /// - Linux: `arm64_linux`, `x86_64_linux`
 /// - Falls back to universal `all` bottle if platform-specific unavailable
 pub async fn download_bottle(
@@ -178,45 +314,26 @@ pub async fn download_bottle(
     progress: Option<&MultiProgress>,
     client: &reqwest::Client,
 ) -> Result<PathBuf> {
-    // Get bottle info
-    let bottle = formula
-        .bottle
-        .as_ref()
-        .and_then(|b| b.stable.as_ref())
-        .ok_or_else(|| anyhow!("No bottle available for {}", formula.name))?;
-
-    // Detect platform
-    let platform_tag = platform::detect_bottle_tag()?;
+    download_bottle_to(formula, progress, client, &cache_dir()).await
+}
 
-    // Get bottle file for this platform, with fallback to "all" (universal)
-    // Matches Homebrew's fallback logic: exact platform first, then universal
-    let bottle_file = bottle
-        .files
-        .get(&platform_tag)
-        .or_else(|| bottle.files.get("all"))
-        .ok_or_else(|| {
-            anyhow!(
-                "No bottle for platform: {} (no universal bottle available)",
-                platform_tag
-            )
-        })?;
+/// Download a bottle into an explicit cache directory.
+///
+/// Same as [`download_bottle`], but stores the bottle in `cache` instead of the
+/// default download cache.
+pub async fn download_bottle_to(
+    formula: &Formula,
+    progress: Option<&MultiProgress>,
+    client: &reqwest::Client,
+    cache: &Path,
+) -> Result<PathBuf> {
+    let (bottle_file, filename) = resolve_bottle(formula)?;
 
     // Create cache directory
-    let cache = cache_dir();
-    fs::create_dir_all(&cache)
+    fs::create_dir_all(cache)
         .await
         .context("Failed to create cache directory")?;
 
-    // Determine filename
-    let version = formula
-        .versions
-        .stable
-        .as_ref()
-        .ok_or_else(|| anyhow!("No stable version"))?;
-    let filename = format!(
-        "{}--{}.{}.bottle.tar.gz",
-        formula.name, version, platform_tag
-    );
     let output_path = cache.join(&filename);
 
     // Check if already downloaded and verified
@@ -251,50 +368,290 @@ pub async fn download_bottle(
         .and_then(|s| s.split("/blobs/").next())
         .ok_or_else(|| anyhow!("Invalid GHCR URL format: {}", bottle_file.url))?;
 
-    let token = get_ghcr_token(repository)
+    let token = get_ghcr_token(client, repository)
         .await
         .context("Failed to get GHCR token")?;
 
-    // Download with authentication
-    let mut response = client
-        .get(&bottle_file.url)
-        .header("Authorization", format!("Bearer {}", token))
-        .send()
-        .await
-        .context("Failed to send request")?;
+    // Download with authentication, resuming a partial download if one is left over
+    fetch_resumable(
+        client,
+        &bottle_file.url,
+        Some(&token),
+        &output_path,
+        pb.as_ref(),
+    )
+    .await?;
+
+    if let Some(pb) = &pb {
+        pb.finish_with_message(format!("✓ {}", formula.name));
+    }
+
+    // Verify checksum
+    if !verify_checksum(&output_path, &bottle_file.sha256).await? {
+        fs::remove_file(&output_path).await?;
+        anyhow::bail!("Checksum verification failed for {}", formula.name);
+    }
+
+    Ok(output_path)
+}
+
+/// Download a file and verify its SHA256 before handing it out.
+///
+/// Used for downloads that aren't bottles (e.g. cask DMG/PKG/ZIP artifacts). Like
+/// [`download_bottle`], an existing `dest` that passes verification is reused, an
+/// interrupted download is resumed, and transient failures are retried. A file whose
+/// checksum doesn't match is deleted and an error is returned, so a corrupt or
+/// half-written file is never used. With `sha256` set to `None` the file is not verified.
+///
+/// # Errors
+///
+/// Returns an error if the download fails after retries or the checksum doesn't match.
+pub async fn download_verified(
+    client: &reqwest::Client,
+    url: &str,
+    dest: &Path,
+    sha256: Option<&str>,
+) -> Result<PathBuf> {
+    if dest.exists() {
+        match sha256 {
+            None => return Ok(dest.to_path_buf()),
+            Some(expected) if verify_checksum(dest, expected).await? => {
+                return Ok(dest.to_path_buf());
+            }
+            // Checksum failed, re-download
+            Some(_) => fs::remove_file(dest).await?,
+        }
+    }
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .context("Failed to create cache directory")?;
+    }
 
-    if let Some(pb) = &pb
-        && let Some(total) = response.content_length()
+    fetch_resumable(client, url, None, dest, None).await?;
+
+    if let Some(expected) = sha256
+        && !verify_checksum(dest, expected).await?
     {
-        pb.set_length(total);
+        fs::remove_file(dest).await?;
+        anyhow::bail!(
+            "Checksum mismatch for {} (expected {})",
+            dest.file_name().unwrap_or_default().to_string_lossy(),
+            expected
+        );
     }
 
-    let mut file = fs::File::create(&output_path)
+    Ok(dest.to_path_buf())
+}
+
+/// Attempts made for a download before giving up on transient failures
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+
+/// Download `url` to `dest` through a `<dest>.part` file.
+///
+/// A `.part` file left by an interrupted download is resumed with an HTTP range request
+/// (restarting from scratch if the server ignores the range). Network errors and 5xx/429
+/// responses are retried with exponential backoff, resuming from what already arrived.
+/// HTML pages and empty bodies fail with [`BruError::InvalidDownload`] without being
+/// retried. `dest` only appears once the whole body has been written.
+///
+/// [`BruError::InvalidDownload`]: crate::error::BruError::InvalidDownload
+async fn fetch_resumable(
+    client: &reqwest::Client,
+    url: &str,
+    bearer: Option<&str>,
+    dest: &Path,
+    pb: Option<&ProgressBar>,
+) -> Result<()> {
+    let mut part = dest.as_os_str().to_owned();
+    part.push(".part");
+    let part = PathBuf::from(part);
+
+    let mut attempt = 1;
+    loop {
+        match fetch_into_part(client, url, bearer, &part, pb).await {
+            Ok(()) => break,
+            Err(e) if attempt < DOWNLOAD_ATTEMPTS && is_transient(&e) => {
+                tokio::time::sleep(std::time::Duration::from_millis(500 << (attempt - 1))).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+
+    fs::rename(&part, dest)
         .await
-        .context("Failed to create output file")?;
-    let mut downloaded: u64 = 0;
+        .context("Failed to move download into place")?;
+    Ok(())
+}
+
+/// Make one request for `url`, appending to `part` when the server honors a resume
+async fn fetch_into_part(
+    client: &reqwest::Client,
+    url: &str,
+    bearer: Option<&str>,
+    part: &Path,
+    pb: Option<&ProgressBar>,
+) -> Result<()> {
+    use reqwest::StatusCode;
+    use reqwest::header::{AUTHORIZATION, RANGE};
+
+    let offset = fs::metadata(part).await.map(|m| m.len()).unwrap_or(0);
+
+    let mut request = client.get(url);
+    if let Some(token) = bearer {
+        request = request.header(AUTHORIZATION, format!("Bearer {}", token));
+    }
+    if offset > 0 {
+        request = request.header(RANGE, format!("bytes={}-", offset));
+    }
+
+    let response = request.send().await.context("Failed to send request")?;
+
+    // The part file already holds the whole body (interrupted before the rename);
+    // checksum verification catches it if it's actually stale
+    if offset > 0 && response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        return Ok(());
+    }
+
+    let mut response = response.error_for_status()?;
+    check_response(url, &response)?;
+    let resumed = offset > 0 && response.status() == StatusCode::PARTIAL_CONTENT;
+
+    let mut file = if resumed {
+        fs::OpenOptions::new().append(true).open(part).await?
+    } else {
+        fs::File::create(part)
+            .await
+            .context("Failed to create output file")?
+    };
+    let mut downloaded = if resumed { offset } else { 0 };
+
+    if let Some(pb) = pb {
+        if let Some(remaining) = response.content_length() {
+            pb.set_length(downloaded + remaining);
+        }
+        pb.set_position(downloaded);
+    }
 
     while let Some(chunk) = response.chunk().await? {
         file.write_all(&chunk).await?;
         downloaded += chunk.len() as u64;
-        if let Some(pb) = &pb {
+        if let Some(pb) = pb {
             pb.set_position(downloaded);
         }
     }
 
     file.flush().await?;
+    Ok(())
+}
 
-    if let Some(pb) = &pb {
-        pb.finish_with_message(format!("✓ {}", formula.name));
-    }
+/// Reject a successful response that can't be the file: an HTML page (registries
+/// sometimes send error pages with a `200`) or an empty body
+///
+/// Runs before anything is written, so the partial download is left untouched.
+fn check_response(url: &str, response: &reqwest::Response) -> Result<()> {
+    let invalid = |reason: String| crate::error::BruError::InvalidDownload {
+        url: url.to_string(),
+        reason,
+    };
 
-    // Verify checksum
-    if !verify_checksum(&output_path, &bottle_file.sha256).await? {
-        fs::remove_file(&output_path).await?;
-        anyhow::bail!("Checksum verification failed for {}", formula.name);
+    let content_type = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    if content_type.starts_with("text/html") || content_type.starts_with("application/xhtml") {
+        return Err(invalid(format!(
+            "the server sent a web page ({}) instead of the file",
+            content_type
+        ))
+        .into());
     }
+    if response.content_length() == Some(0) {
+        return Err(invalid("the server sent an empty response".to_string()).into());
+    }
+    Ok(())
+}
 
-    Ok(output_path)
+/// Whether a failed download is worth retrying (connection problems, 5xx, 429)
+fn is_transient(err: &anyhow::Error) -> bool {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<reqwest::Error>())
+        .is_some_and(|e| {
+            e.status().is_none_or(|status| {
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            })
+        })
+}
+
+/// Total bytes that downloading bottles for `formulae` would transfer.
+///
+/// Bottles already in the cache (with a matching checksum) count as zero. Sizes come
+/// from `HEAD` requests against GHCR, sent in parallel, so nothing is downloaded.
+///
+/// # Errors
+///
+/// Returns an error if any formula has no bottle for this platform or a size cannot be
+/// determined (network failure, missing `Content-Length`).
+pub async fn download_size(api: &BrewApi, formulae: &[Formula]) -> Result<u64> {
+    let client = api.client();
+
+    let sizes = futures::future::join_all(formulae.iter().map(|formula| async move {
+        if cached_bottle(formula).await?.is_some() {
+            return Ok(0);
+        }
+        bottle_size(formula, client).await
+    }))
+    .await;
+
+    sizes.into_iter().sum()
+}
+
+/// Size in bytes of a formula's bottle on GHCR, without downloading it.
+async fn bottle_size(formula: &Formula, client: &reqwest::Client) -> Result<u64> {
+    let (bottle_file, _) = resolve_bottle(formula)?;
+    bottle_file_size(client, &bottle_file.url)
+        .await
+        .with_context(|| format!("No size reported for {} bottle", formula.name))
+}
+
+/// Size in bytes of the bottle at a GHCR blob `url`, from a `HEAD` request.
+///
+/// # Errors
+///
+/// Returns an error if the URL isn't a GHCR blob, the request fails, or the registry
+/// doesn't report a `Content-Length`.
+pub async fn bottle_file_size(client: &reqwest::Client, url: &str) -> Result<u64> {
+    let repository = url
+        .strip_prefix("https://ghcr.io/v2/")
+        .and_then(|s| s.split("/blobs/").next())
+        .ok_or_else(|| anyhow!("Invalid GHCR URL format: {}", url))?;
+    let token = get_ghcr_token(client, repository)
+        .await
+        .context("Failed to get GHCR token")?;
+
+    let response = client
+        .head(url)
+        .header("Authorization", format!("Bearer {}", token))
+        .send()
+        .await?
+        .error_for_status()?;
+
+    response
+        .content_length()
+        .ok_or_else(|| anyhow!("No size reported for {}", url))
+}
+
+/// Result of [`download_bottles`]: the bottles that downloaded and the ones that didn't.
+#[derive(Debug, Default)]
+pub struct BatchResult {
+    /// `(formula_name, bottle_path)` for each bottle that downloaded (or was already cached)
+    pub downloaded: Vec<(String, PathBuf)>,
+    /// `(formula_name, error)` for each bottle that could not be downloaded
+    pub failed: Vec<(String, anyhow::Error)>,
 }
 
 /// Download multiple bottles in parallel with automatic concurrency control.
@@ -304,17 +661,14 @@ pub async fn download_bottle(
 ///
 /// # Arguments
 ///
-/// * `_api` - Homebrew API client (currently unused, kept for future extension)
+/// * `api` - Homebrew API client whose HTTP client (and connection pool) is used for downloads
 /// * `formulae` - Slice of formulas to download bottles for
 ///
 /// # Returns
 ///
-/// A vector of `(formula_name, bottle_path)` tuples for successfully downloaded bottles.
-///
-/// # Errors
-///
-/// Returns an error if any bottle fails to download (stops on first error).
-/// Use with error recovery in production to skip failed packages.
+/// A [`BatchResult`] with every successful download and every failure. One failed bottle
+/// doesn't discard the others, so callers can go ahead with what was fetched and report
+/// the rest.
 ///
 /// # Examples
 ///
@@ -329,10 +683,13 @@ pub async fn download_bottle(
 ///         api.fetch_formula("bat").await?,
 ///     ];
 ///
-///     let bottles = download::download_bottles(&api, &formulae).await?;
-///     for (name, path) in bottles {
+///     let batch = download::download_bottles(&api, &formulae).await;
+///     for (name, path) in batch.downloaded {
 ///         println!("Downloaded {} to {}", name, path.display());
 ///     }
+///     for (name, err) in batch.failed {
+///         eprintln!("Failed to download {}: {}", name, err);
+///     }
 ///
 ///     Ok(())
 /// }
@@ -347,18 +704,15 @@ pub async fn download_bottle(
 /// # Performance
 ///
 /// On a 500 Mbps connection, downloading 10 bottles takes ~5-10 seconds.
-pub async fn download_bottles(
-    _api: &BrewApi,
-    formulae: &[Formula],
-) -> Result<Vec<(String, PathBuf)>> {
+pub async fn download_bottles(api: &BrewApi, formulae: &[Formula]) -> BatchResult {
     use std::sync::Arc;
     use tokio::sync::Semaphore;
 
     let mp = MultiProgress::new();
     let mut tasks = Vec::new();
 
-    // Create shared HTTP client (reused across all downloads)
-    let client = Arc::new(reqwest::Client::new());
+    // Reuse the API's HTTP client so every download shares one connection pool
+    let client = api.client();
 
     // Limit concurrent downloads to prevent resource exhaustion
     // Reduced from 16 to 8 to be more conservative with file descriptors
@@ -384,17 +738,104 @@ pub async fn download_bottles(
             (formula_clone.name.clone(), result)
         });
 
-        tasks.push(task);
+        tasks.push((formula.name.clone(), task));
     }
 
-    let mut results = Vec::new();
-    for task in tasks {
-        let (name, result) = task.await?;
-        match result {
-            Ok(path) => results.push((name, path)),
-            Err(e) => return Err(e),
+    let mut batch = BatchResult::default();
+    for (name, task) in tasks {
+        match task.await {
+            Ok((name, Ok(path))) => batch.downloaded.push((name, path)),
+            Ok((name, Err(e))) => batch.failed.push((name, e)),
+            Err(e) => batch
+                .failed
+                .push((name, anyhow!("Download task failed: {}", e))),
         }
     }
 
-    Ok(results)
+    batch
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_bottle_filename() {
+        let bottle = BottleFilename::parse("ripgrep--14.1.0.arm64_sonoma.bottle.tar.gz").unwrap();
+        assert_eq!(bottle.name, "ripgrep");
+        assert_eq!(bottle.version, "14.1.0");
+        assert_eq!(bottle.tag, "arm64_sonoma");
+
+        let bottle =
+            BottleFilename::parse("openssl@3--3.4.0_1.x86_64_linux.bottle.2.tar.gz").unwrap();
+        assert_eq!(bottle.name, "openssl@3");
+        assert_eq!(bottle.version, "3.4.0_1");
+        assert_eq!(bottle.tag, "x86_64_linux");
+
+        for bad in [
+            "ripgrep-14.1.0.arm64_sonoma.bottle.tar.gz",
+            "ripgrep--14.1.0.arm64_sonoma.tar.gz",
+            "ripgrep--14.1.0.arm64_sonoma.bottle.x.tar.gz",
+            "ripgrep--arm64_sonoma.bottle.tar.gz",
+            "ripgrep--14.1.0.arm64_sonoma.bottle.zip",
+        ] {
+            let err = BottleFilename::parse(bad).unwrap_err();
+            assert!(
+                err.to_string().contains("expected <name>--<version>"),
+                "{}",
+                bad
+            );
+        }
+    }
+
+    fn bottled(sha256: &str) -> Formula {
+        serde_json::from_value(serde_json::json!({
+            "name": "jq",
+            "versions": {"stable": "1.7.1", "bottle": true},
+            // Not a GHCR URL, so a download attempt fails before touching the network
+            "bottle": {"stable": {"rebuild": 0, "files": {"all": {
+                "cellar": ":any",
+                "url": "https://example.invalid/jq.tar.gz",
+                "sha256": sha256,
+            }}}},
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_cached_bottle_is_only_reused_when_its_checksum_matches() {
+        use sha2::{Digest, Sha256};
+
+        let cache = tempfile::TempDir::new().unwrap();
+        let client = reqwest::Client::new();
+        let contents = b"bottle contents";
+        let sha256 = format!("{:x}", Sha256::digest(contents));
+
+        let formula = bottled(&sha256);
+        let (_, filename) = resolve_bottle(&formula).unwrap();
+        let cached = cache.path().join(filename);
+        std::fs::write(&cached, contents).unwrap();
+        let path = download_bottle_to(&formula, None, &client, cache.path())
+            .await
+            .unwrap();
+        assert_eq!(path, cached);
+
+        // A mismatching cached file is thrown away and fetched again, never handed out
+        std::fs::write(&cached, b"corrupt").unwrap();
+        let err = download_bottle_to(&formula, None, &client, cache.path())
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Invalid GHCR URL"), "{}", err);
+        assert!(!cached.exists());
+
+        // Without a checksum nothing can verify, so the cached file isn't trusted either
+        let formula = bottled("");
+        std::fs::write(&cached, contents).unwrap();
+        assert!(
+            download_bottle_to(&formula, None, &client, cache.path())
+                .await
+                .is_err()
+        );
+        assert!(!cached.exists());
+    }
 }
@@ -44,7 +44,13 @@ use thiserror::Error;
 /// - [`FormulaNotFound`](BruError::FormulaNotFound): The requested formula doesn't exist
 ///   in Homebrew
 /// - [`CaskNotFound`](BruError::CaskNotFound): The requested cask doesn't exist in Homebrew
+/// - [`InvalidName`](BruError::InvalidName): The name can't be a formula or cask, so no
+///   request was made
+/// - [`NoStableVersion`](BruError::NoStableVersion): The formula is HEAD-only, so there
+///   is no bottle to install
 /// - [`NetworkError`](BruError::NetworkError): Generic network connectivity error
+/// - [`InvalidDownload`](BruError::InvalidDownload): A server answered a download with
+///   something that can't be the file (an HTML page, an empty body)
 /// - [`IoError`](BruError::IoError): File system operation failed (Cellar access, cache, etc.)
 /// - [`Other`](BruError::Other): Miscellaneous error with rich context from `anyhow`
 ///
@@ -91,6 +97,20 @@ pub enum BruError {
     #[error("Cask not found: {0}")]
     CaskNotFound(String),
 
+    /// The name isn't a valid formula name or cask token.
+    ///
+    /// Returned before any network request, so a typo such as `rip grep` fails fast.
+    /// See [`validate_name`](crate::api::validate_name) for the accepted characters.
+    #[error("Invalid formula or cask name: {0}")]
+    InvalidName(String),
+
+    /// The formula has no stable version, so there is nothing to install.
+    ///
+    /// Bottles only exist for stable versions; HEAD-only formulae have to be built from
+    /// source by Homebrew. See [`Formula::install_version`](crate::api::Formula::install_version).
+    #[error("{0} has no stable version (HEAD-only formulae need `brew install --HEAD`)")]
+    NoStableVersion(String),
+
     /// Generic network error with custom message.
     ///
     /// Used for network-related errors that don't fit other categories. Prefer
@@ -99,6 +119,14 @@ pub enum BruError {
     #[allow(dead_code)]
     NetworkError(String),
 
+    /// A download returned something that can't be the requested file.
+    ///
+    /// Registries occasionally answer with an HTML error page or an empty body and a
+    /// `200` status. This is reported before anything is saved, instead of surfacing
+    /// later as a checksum mismatch or a "not in gzip format" extraction error.
+    #[error("Invalid download from {url}: {reason}")]
+    InvalidDownload { url: String, reason: String },
+
     /// File system operation failed (reading Cellar, cache, taps, etc.).
     ///
     /// This wraps `std::io::Error` and typically indicates permission issues, missing
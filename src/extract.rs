@@ -5,6 +5,7 @@
 //! - **Decompresses** GZIP-compressed tar archives
 //! - **Extracts** to the correct Cellar location
 //! - **Handles bottle revisions** (e.g., `1.0.0_1`, `1.0.0_2`)
+//! - **Remaps** bottles whose top-level `{formula}/{version}` directory doesn't match
 //! - **Verifies** extraction succeeded
 //!
 //! # Architecture
@@ -95,59 +96,97 @@ use tar::Archive;
 /// suffixes like `1.0.0_1`, `1.0.0_2`. This function automatically detects and handles these:
 /// - Extracts to whichever revision directory exists in the tar.gz
 /// - Returns the path to that directory
+///
+/// Any other top-level directory (a different version string or formula name) is
+/// moved to `Cellar/{formula}/{version}`, so the keg never ends up double-nested or
+/// beside the one the caller expects.
+#[allow(dead_code)]
 pub fn extract_bottle(bottle_path: &Path, formula_name: &str, version: &str) -> Result<PathBuf> {
-    let cellar = cellar::cellar_path();
+    extract_bottle_with_manifest(bottle_path, formula_name, version).map(|(path, _)| path)
+}
+
+/// Extract a bottle and return the files it contained.
+///
+/// Same as [`extract_bottle`], but also returns every file and symlink that was
+/// unpacked into the keg, relative to the keg directory (e.g. `bin/rg`). Relocation
+/// and linking can consume this list instead of walking the keg again.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::extract;
+/// use std::path::Path;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let bottle_path = Path::new("/path/to/ripgrep--13.0.0.arm64_sonoma.bottle.tar.gz");
+///     let (keg, files) = extract::extract_bottle_with_manifest(bottle_path, "ripgrep", "13.0.0")?;
+///     println!("Extracted {} files to {}", files.len(), keg.display());
+///
+///     Ok(())
+/// }
+/// ```
+pub fn extract_bottle_with_manifest(
+    bottle_path: &Path,
+    formula_name: &str,
+    version: &str,
+) -> Result<(PathBuf, Vec<PathBuf>)> {
+    extract_bottle_with_manifest_in(&cellar::cellar_path(), bottle_path, formula_name, version)
+}
 
+/// Extract a bottle into an explicit Cellar and return the files it contained.
+///
+/// Same as [`extract_bottle_with_manifest`], but unpacks into `cellar` instead of the
+/// detected Homebrew Cellar.
+pub fn extract_bottle_with_manifest_in(
+    cellar: &Path,
+    bottle_path: &Path,
+    formula_name: &str,
+    version: &str,
+) -> Result<(PathBuf, Vec<PathBuf>)> {
     // Ensure Cellar exists
     if !cellar.exists() {
-        fs::create_dir_all(&cellar)
+        fs::create_dir_all(cellar)
             .with_context(|| format!("Failed to create Cellar directory: {}", cellar.display()))?;
     }
 
-    // Open and decompress the bottle
-    let file = fs::File::open(bottle_path)
-        .with_context(|| format!("Failed to open bottle: {}", bottle_path.display()))?;
-    let decompressor = GzDecoder::new(file);
-    let mut archive = Archive::new(decompressor);
-
-    // Extract to Cellar
     // Archive contains: {formula}/{version}/* or {formula}/{version}_N/*
-    // Should go to: /opt/homebrew/Cellar/{formula}/{version}/*
-    archive
-        .unpack(&cellar)
-        .with_context(|| format!("Failed to extract bottle to: {}", cellar.display()))?;
-
-    // Find the extracted directory - it may have a bottle revision suffix (e.g., 3.13.9_1)
-    let formula_dir = cellar.join(formula_name);
-    let extracted_path = if formula_dir.join(version).exists() {
-        // Exact version match (no bottle revision)
-        formula_dir.join(version)
-    } else {
-        // Look for version with bottle revision suffix (version_N)
-        let version_with_revision = fs::read_dir(&formula_dir)
-            .with_context(|| {
-                format!(
-                    "Failed to read formula directory: {}",
-                    formula_dir.display()
-                )
-            })?
-            .filter_map(|entry| entry.ok())
-            .map(|entry| entry.file_name())
-            .find(|name| {
-                let name_str = name.to_string_lossy();
-                name_str.starts_with(version)
-                    && (name_str == version || name_str.starts_with(&format!("{}_", version)))
-            })
-            .ok_or_else(|| {
-                anyhow::anyhow!(
-                    "Extraction failed: no directory found matching version {} in {}",
-                    version,
-                    formula_dir.display()
-                )
-            })?;
-
-        formula_dir.join(version_with_revision)
-    };
+    // Should go to: /opt/homebrew/Cellar/{formula}/{version}/* (keeping any _N suffix)
+    let top = bottle_keg_dir(bottle_path)?;
+    let target = keg_dir_for(&top, formula_name, version);
+
+    // A bottle whose top directory doesn't match is unpacked into a staging directory
+    // and moved into place, so it can't land beside (or inside) the intended keg
+    let staging = (top != target).then(|| cellar.join(format!(".{}.extract", formula_name)));
+    if let Some(staging) = &staging {
+        if staging.exists() {
+            fs::remove_dir_all(staging)
+                .with_context(|| format!("Failed to clear {}", staging.display()))?;
+        }
+        fs::create_dir_all(staging)
+            .with_context(|| format!("Failed to create {}", staging.display()))?;
+    }
+    let dest = staging.as_deref().unwrap_or(cellar);
+    let unpacked = unpack_bottle(bottle_path, dest)?;
+
+    let extracted_path = cellar.join(&target);
+    if let Some(staging) = &staging {
+        if extracted_path.exists() {
+            fs::remove_dir_all(&extracted_path)
+                .with_context(|| format!("Failed to replace {}", extracted_path.display()))?;
+        }
+        if let Some(parent) = extracted_path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        fs::rename(staging.join(&top), &extracted_path).with_context(|| {
+            format!(
+                "Failed to move extracted bottle to {}",
+                extracted_path.display()
+            )
+        })?;
+        fs::remove_dir_all(staging)
+            .with_context(|| format!("Failed to clean up {}", staging.display()))?;
+    }
 
     // Verify extraction
     if !extracted_path.exists() {
@@ -157,7 +196,193 @@ pub fn extract_bottle(bottle_path: &Path, formula_name: &str, version: &str) ->
         );
     }
 
-    Ok(extracted_path)
+    // Archive paths start with the bottle's own {formula}/{version}
+    let mut manifest: Vec<PathBuf> = unpacked
+        .into_iter()
+        .filter_map(|path| path.strip_prefix(&top).ok().map(Path::to_path_buf))
+        .filter(|path| !path.as_os_str().is_empty())
+        .collect();
+    manifest.sort();
+
+    Ok((extracted_path, manifest))
+}
+
+/// Unpack every entry of a bottle under `dest`, returning the archive paths of the
+/// files and symlinks (directories aren't listed).
+fn unpack_bottle(bottle_path: &Path, dest: &Path) -> Result<Vec<PathBuf>> {
+    let file = fs::File::open(bottle_path)
+        .with_context(|| format!("Failed to open bottle: {}", bottle_path.display()))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+
+    // Entries are unpacked one at a time so the manifest is recorded in the same pass.
+    // Directories are applied last, as `Archive::unpack` does, so read-only directory
+    // modes can't block extraction of their contents.
+    let mut unpacked = Vec::new();
+    let mut directories = Vec::new();
+    for entry in archive
+        .entries()
+        .with_context(|| format!("Failed to read bottle: {}", bottle_path.display()))?
+    {
+        let mut entry =
+            entry.with_context(|| format!("Failed to read bottle: {}", bottle_path.display()))?;
+        if entry.header().entry_type().is_dir() {
+            directories.push(entry);
+            continue;
+        }
+
+        let entry_path = entry.path()?.into_owned();
+        entry
+            .unpack_in(dest)
+            .with_context(|| format!("Failed to extract bottle to: {}", dest.display()))?;
+        unpacked.push(entry_path);
+    }
+
+    directories.sort_by(|a, b| b.path_bytes().cmp(&a.path_bytes()));
+    for mut dir in directories {
+        dir.unpack_in(dest)
+            .with_context(|| format!("Failed to extract bottle to: {}", dest.display()))?;
+    }
+
+    Ok(unpacked)
+}
+
+/// The `{formula}/{version}` directory a bottle unpacks into, read from its first
+/// entry with a two-level path.
+fn bottle_keg_dir(bottle_path: &Path) -> Result<PathBuf> {
+    let file = fs::File::open(bottle_path)
+        .with_context(|| format!("Failed to open bottle: {}", bottle_path.display()))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+
+    for entry in archive
+        .entries()
+        .with_context(|| format!("Failed to read bottle: {}", bottle_path.display()))?
+    {
+        let entry =
+            entry.with_context(|| format!("Failed to read bottle: {}", bottle_path.display()))?;
+        let path = entry.path()?;
+        let mut components = path.components().filter_map(|c| match c {
+            std::path::Component::Normal(part) => Some(part),
+            _ => None,
+        });
+        if let (Some(name), Some(version)) = (components.next(), components.next()) {
+            return Ok(Path::new(name).join(version));
+        }
+    }
+
+    anyhow::bail!(
+        "Invalid bottle: no {{formula}}/{{version}} directory in {}",
+        bottle_path.display()
+    )
+}
+
+/// Where a bottle whose top directory is `top` belongs, relative to the Cellar.
+///
+/// `top` is kept when it is `{formula}/{version}` or carries a bottle revision
+/// (`{formula}/{version}_N`); anything else is remapped to `{formula}/{version}`.
+fn keg_dir_for(top: &Path, formula_name: &str, version: &str) -> PathBuf {
+    let expected = Path::new(formula_name).join(version);
+    let keeps_layout = top.parent() == Some(Path::new(formula_name))
+        && top.file_name().is_some_and(|dir| {
+            let dir = dir.to_string_lossy();
+            dir == version
+                || dir
+                    .strip_prefix(version)
+                    .and_then(|rest| rest.strip_prefix('_'))
+                    .is_some_and(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        });
+
+    if keeps_layout {
+        top.to_path_buf()
+    } else {
+        expected
+    }
+}
+
+/// The formula source a bottle ships at `<name>/<version>/.brew/<name>.rb`, read
+/// straight from the archive without unpacking it.
+///
+/// Returns `None` for bottles built without one.
+///
+/// # Errors
+///
+/// Returns an error if the bottle can't be read or isn't a valid tar.gz archive.
+pub fn bottle_formula_source(bottle_path: &Path, formula_name: &str) -> Result<Option<String>> {
+    use std::io::Read;
+
+    let file = fs::File::open(bottle_path)
+        .with_context(|| format!("Failed to open bottle {}", bottle_path.display()))?;
+    let mut archive = Archive::new(GzDecoder::new(file));
+    let wanted = Path::new(".brew").join(format!("{}.rb", formula_name));
+
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        if entry.path()?.ends_with(&wanted) {
+            let mut source = String::new();
+            entry.read_to_string(&mut source)?;
+            return Ok(Some(source));
+        }
+    }
+    Ok(None)
+}
+
+/// Estimated bytes a bottle takes up once extracted.
+///
+/// Reads the uncompressed size from the gzip trailer. That field wraps at 4 GiB, so a
+/// value smaller than the archive itself falls back to three times the compressed size.
+///
+/// # Errors
+///
+/// Returns an error if the bottle can't be opened or is too short to be a gzip file.
+pub fn unpacked_size(bottle_path: &Path) -> Result<u64> {
+    use std::io::{Read, Seek, SeekFrom};
+
+    let mut file = fs::File::open(bottle_path)
+        .with_context(|| format!("Failed to open bottle {}", bottle_path.display()))?;
+    let compressed = file.metadata()?.len();
+    if compressed < 18 {
+        anyhow::bail!("Not a gzip archive: {}", bottle_path.display());
+    }
+
+    let mut trailer = [0u8; 4];
+    file.seek(SeekFrom::End(-4))?;
+    file.read_exact(&mut trailer)?;
+    let isize = u64::from(u32::from_le_bytes(trailer));
+
+    Ok(if isize >= compressed {
+        isize
+    } else {
+        compressed * 3
+    })
+}
+
+/// Bytes available to the current user on the filesystem holding `path`.
+///
+/// `path` doesn't have to exist yet; its nearest existing ancestor is checked.
+///
+/// # Errors
+///
+/// Returns an error if no ancestor of `path` exists or `statvfs` fails.
+pub fn available_space(path: &Path) -> Result<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let existing = path
+        .ancestors()
+        .find(|dir| dir.exists())
+        .with_context(|| format!("No existing directory for {}", path.display()))?;
+    let c_path = std::ffi::CString::new(existing.as_os_str().as_bytes())?;
+
+    // SAFETY: statvfs is a plain C struct of integers, for which all-zero bytes are a
+    // valid value.
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: c_path is a NUL-terminated string and stat a live, writable statvfs, both
+    // valid for the duration of the call; statvfs doesn't keep either pointer.
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error())
+            .with_context(|| format!("Failed to read free space for {}", existing.display()));
+    }
+
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
 }
 
 #[cfg(test)]
@@ -169,4 +394,71 @@ mod tests {
         let path = cellar::cellar_path();
         assert!(path.ends_with("Cellar"));
     }
+
+    /// Write a bottle whose entries live under `top` (e.g. `jq/1.7`)
+    fn write_bottle(path: &Path, top: &str) {
+        let entry = format!("{}/bin/jq", top);
+        let bottle = crate::test_support::bottle(&[(&entry, b"#!/bin/sh\n", 0o755)]);
+        fs::write(path, bottle).unwrap();
+    }
+
+    #[test]
+    fn test_keg_dir_for() {
+        let keg = |top: &str| keg_dir_for(Path::new(top), "jq", "1.7.1");
+        assert_eq!(keg("jq/1.7.1"), Path::new("jq/1.7.1"));
+        assert_eq!(keg("jq/1.7.1_2"), Path::new("jq/1.7.1_2"));
+        assert_eq!(keg("jq/1.7"), Path::new("jq/1.7.1"));
+        assert_eq!(keg("jq/1.7.1-rc"), Path::new("jq/1.7.1"));
+        assert_eq!(keg("jq/1.7.1_"), Path::new("jq/1.7.1"));
+        assert_eq!(keg("jq-legacy/1.7.1"), Path::new("jq/1.7.1"));
+    }
+
+    #[test]
+    fn test_extract_remaps_mismatched_version_dir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let cellar = temp.path().join("Cellar");
+        let bottle = temp.path().join("jq--1.7.1.all.bottle.tar.gz");
+        write_bottle(&bottle, "jq/1.7");
+
+        let (keg, manifest) =
+            extract_bottle_with_manifest_in(&cellar, &bottle, "jq", "1.7.1").unwrap();
+
+        assert_eq!(keg, cellar.join("jq/1.7.1"));
+        assert!(keg.join("bin/jq").is_file());
+        assert!(!cellar.join("jq/1.7").exists());
+        assert!(!cellar.join(".jq.extract").exists());
+        assert_eq!(manifest, vec![PathBuf::from("bin/jq")]);
+    }
+
+    #[test]
+    fn test_extract_keeps_bottle_revision() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let cellar = temp.path().join("Cellar");
+        let bottle = temp.path().join("jq--1.7.1_1.all.bottle.tar.gz");
+        write_bottle(&bottle, "jq/1.7.1_1");
+
+        let (keg, manifest) =
+            extract_bottle_with_manifest_in(&cellar, &bottle, "jq", "1.7.1").unwrap();
+
+        assert_eq!(keg, cellar.join("jq/1.7.1_1"));
+        assert_eq!(manifest, vec![PathBuf::from("bin/jq")]);
+    }
+
+    #[test]
+    fn test_unpacked_size_reads_gzip_trailer() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let bottle = temp.path().join("jq--1.7.1.all.bottle.tar.gz");
+        write_bottle(&bottle, "jq/1.7.1");
+
+        // A tar archive is padded to whole 512-byte blocks, with two trailing blocks
+        let size = unpacked_size(&bottle).unwrap();
+        assert_eq!(size % 512, 0);
+        assert!(size > fs::metadata(&bottle).unwrap().len());
+    }
+
+    #[test]
+    fn test_available_space_of_missing_dir() {
+        let temp = tempfile::TempDir::new().unwrap();
+        assert!(available_space(&temp.path().join("Cellar/jq")).unwrap() > 0);
+    }
 }
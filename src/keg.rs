@@ -0,0 +1,370 @@
+//! Replacing installed kegs: upgrades and reinstalls.
+//!
+//! An upgrade pours the new version beside the installed one, moves the links over and
+//! removes the old keg; a reinstall removes the keg and pours the formula again.
+//! Pouring ([`pour_keg_in`]) only touches the new keg's own directory, so callers can
+//! pour several bottles in parallel and swap the kegs in one at a time.
+//!
+//! Results are returned as [`UpgradeResult`] and [`ReinstallResult`] so callers can
+//! report them however they like; nothing here prints.
+
+use crate::api::Formula;
+use crate::cellar::{self, RuntimeDependency};
+use crate::error::Result;
+use crate::{extract, receipt, relocate, symlink};
+use anyhow::anyhow;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+/// Result of an upgrade operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpgradeResult {
+    /// Package name
+    pub name: String,
+    /// Previous version
+    pub from_version: String,
+    /// New version
+    pub to_version: String,
+    /// Path in Cellar
+    pub path: PathBuf,
+    /// Number of files linked into the prefix
+    pub linked_files: usize,
+    /// Bytes freed by removing the previous version
+    pub freed_bytes: u64,
+    /// Time taken (milliseconds)
+    pub time_ms: u64,
+}
+
+/// Result of a reinstall operation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReinstallResult {
+    /// Package name
+    pub name: String,
+    /// Installed version
+    pub version: String,
+    /// Path in Cellar
+    pub path: PathBuf,
+    /// Number of files linked into the prefix
+    pub linked_files: usize,
+    /// Time taken (milliseconds)
+    pub time_ms: u64,
+}
+
+/// What to record in the receipt of an upgraded or reinstalled keg, beyond what the
+/// formula itself says
+#[derive(Debug, Clone, Default)]
+pub struct ReceiptOptions {
+    /// Runtime dependencies; when `None`, the installed versions of the formula's
+    /// dependencies are recorded
+    pub runtime_dependencies: Option<Vec<RuntimeDependency>>,
+    /// Options the keg was installed with (`--with-*`/`--without-*`)
+    pub used_options: Vec<String>,
+    /// Options the formula offers that weren't used
+    pub unused_options: Vec<String>,
+}
+
+/// A bottle unpacked and relocated into the Cellar but not yet linked
+#[derive(Debug)]
+pub struct PouredKeg {
+    /// Formula name
+    pub name: String,
+    /// Keg version, including any bottle revision (e.g. `1.7.1_1`)
+    pub version: String,
+    /// Path in Cellar
+    pub path: PathBuf,
+    manifest: Vec<PathBuf>,
+}
+
+/// Unpack `bottle` for `formula` into the Cellar under `prefix` and relocate it.
+///
+/// # Errors
+///
+/// Returns an error if the bottle can't be extracted or relocated; a keg that fails to
+/// relocate is removed again.
+pub fn pour_keg_in(prefix: &Path, formula: &Formula, bottle: &Path) -> Result<PouredKeg> {
+    let version = formula.install_version()?;
+    let (path, manifest) = extract::extract_bottle_with_manifest_in(
+        &prefix.join("Cellar"),
+        bottle,
+        &formula.name,
+        version,
+    )
+    .map_err(|e| anyhow!("failed to extract: {}", e))?;
+
+    if let Err(e) = relocate::relocate_bottle_with_manifest(&path, prefix, &manifest) {
+        let _ = fs::remove_dir_all(&path);
+        return Err(anyhow!("failed to relocate: {}", e).into());
+    }
+
+    // The keg directory carries any bottle revision (e.g. 1.7.1_1)
+    let version = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| anyhow!("invalid extracted path: {}", path.display()))?
+        .to_string();
+    Ok(PouredKeg {
+        name: formula.name.clone(),
+        version,
+        path,
+        manifest,
+    })
+}
+
+/// Swap the installed `from_version` of a formula for a freshly poured keg.
+///
+/// The old keg's links move to the new one, the new keg gets a receipt (keeping
+/// whether the old one was installed on request), and the old keg is removed.
+///
+/// # Errors
+///
+/// Returns an error if the old keg can't be unlinked, or the new one can't be linked
+/// or recorded. Failing to remove the old keg afterwards isn't an error; it shows as
+/// [`UpgradeResult::freed_bytes`] being 0.
+pub fn upgrade_keg_in(
+    prefix: &Path,
+    formula: &Formula,
+    keg: PouredKeg,
+    from_version: &str,
+    options: &ReceiptOptions,
+) -> Result<UpgradeResult> {
+    let start = Instant::now();
+    let old_keg = prefix.join("Cellar").join(&keg.name).join(from_version);
+    let on_request = receipt::InstallReceipt::read(&old_keg)
+        .map(|r| r.installed_on_request)
+        .unwrap_or(true);
+
+    symlink::unlink_formula_in(prefix, &keg.name, from_version)
+        .map_err(|e| anyhow!("failed to unlink the old version: {}", e))?;
+    let linked_files = link_keg(prefix, formula, &keg)?;
+    write_receipt(prefix, formula, &keg.path, on_request, options)?;
+
+    // Best effort, the upgrade itself succeeded
+    let mut freed_bytes = 0;
+    if old_keg != keg.path && old_keg.exists() {
+        let size = cellar::keg_size(&old_keg).unwrap_or(0);
+        if fs::remove_dir_all(&old_keg).is_ok() {
+            freed_bytes = size;
+        }
+    }
+
+    Ok(UpgradeResult {
+        name: keg.name,
+        from_version: from_version.to_string(),
+        to_version: keg.version,
+        path: keg.path,
+        linked_files,
+        freed_bytes,
+        time_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Replace the installed `version` of a formula with a fresh pour of `bottle`.
+///
+/// The old keg is unlinked and removed first, then the bottle is poured, linked and
+/// recorded, keeping whether the old keg was installed on request.
+///
+/// # Errors
+///
+/// Returns an error if any step fails; the old keg is gone by the time the bottle is
+/// poured, so callers should have the bottle downloaded before calling this.
+pub fn reinstall_keg_in(
+    prefix: &Path,
+    formula: &Formula,
+    bottle: &Path,
+    version: &str,
+    options: &ReceiptOptions,
+) -> Result<ReinstallResult> {
+    let start = Instant::now();
+    let old_keg = prefix.join("Cellar").join(&formula.name).join(version);
+    let on_request = receipt::InstallReceipt::read(&old_keg)
+        .map(|r| r.installed_on_request)
+        .unwrap_or(true);
+
+    symlink::unlink_formula_in(prefix, &formula.name, version)
+        .map_err(|e| anyhow!("failed to remove symlinks: {}", e))?;
+    if old_keg.exists() {
+        fs::remove_dir_all(&old_keg)
+            .map_err(|e| anyhow!("failed to remove {}: {}", old_keg.display(), e))?;
+    }
+
+    let keg = pour_keg_in(prefix, formula, bottle)?;
+    let linked_files = link_keg(prefix, formula, &keg)?;
+    write_receipt(prefix, formula, &keg.path, on_request, options)?;
+
+    Ok(ReinstallResult {
+        name: keg.name,
+        version: keg.version,
+        path: keg.path,
+        linked_files,
+        time_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// The installed versions of a formula's dependencies, as receipts record them
+pub(crate) fn installed_runtime_dependencies(
+    cellar: &Path,
+    formula: &Formula,
+) -> Vec<RuntimeDependency> {
+    formula
+        .dependencies
+        .iter()
+        .filter_map(|dep_name| {
+            let installed = cellar::get_installed_versions_in(cellar, dep_name).ok()?;
+            let dep_version = installed.first()?.version.clone();
+            Some(RuntimeDependency {
+                full_name: dep_name.clone(),
+                version: dep_version.clone(),
+                revision: 0,
+                bottle_rebuild: 0,
+                pkg_version: dep_version,
+                declared_directly: true,
+                extra: Default::default(),
+            })
+        })
+        .collect()
+}
+
+/// Link a poured keg into the prefix (keg-only ones get only their config files) and
+/// point `opt/` at it; returns the number of files linked
+fn link_keg(prefix: &Path, formula: &Formula, keg: &PouredKeg) -> Result<usize> {
+    let linked_files = if formula.keg_only {
+        symlink::install_config_files_in(prefix, &keg.name, &keg.version)
+            .map_err(|e| anyhow!("failed to install config files: {}", e))?;
+        0
+    } else {
+        symlink::link_formula_with_manifest_in(prefix, &keg.name, &keg.version, &keg.manifest)
+            .map_err(|e| anyhow!("failed to link: {}", e))?
+            .len()
+    };
+    symlink::optlink_in(prefix, &keg.name, &keg.version)
+        .map_err(|e| anyhow!("failed to create opt link: {}", e))?;
+    Ok(linked_files)
+}
+
+fn write_receipt(
+    prefix: &Path,
+    formula: &Formula,
+    keg: &Path,
+    on_request: bool,
+    options: &ReceiptOptions,
+) -> Result<()> {
+    let runtime_deps = options
+        .runtime_dependencies
+        .clone()
+        .unwrap_or_else(|| installed_runtime_dependencies(&prefix.join("Cellar"), formula));
+    let mut receipt = receipt::InstallReceipt::new_bottle(formula, runtime_deps, on_request);
+    receipt.used_options = options.used_options.clone();
+    receipt.unused_options = options.unused_options.clone();
+    receipt
+        .write(keg)
+        .map_err(|e| anyhow!("failed to write receipt: {}", e))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn formula(version: &str) -> Formula {
+        serde_json::from_value(serde_json::json!({
+            "name": "jq",
+            "versions": {"stable": version, "bottle": true},
+        }))
+        .unwrap()
+    }
+
+    fn write_bottle(dir: &Path, version: &str) -> PathBuf {
+        let path = dir.join(format!("jq--{}.all.bottle.tar.gz", version));
+        let script = b"#!@@HOMEBREW_PREFIX@@/bin/sh\necho jq\n";
+        let entry = format!("jq/{}/bin/jq", version);
+        fs::write(
+            &path,
+            crate::test_support::bottle(&[(&entry, script, 0o755)]),
+        )
+        .unwrap();
+        path
+    }
+
+    /// An installed, linked jq `version` recorded as installed as a dependency
+    fn install_old(prefix: &Path, version: &str) {
+        let keg = prefix.join("Cellar/jq").join(version);
+        fs::create_dir_all(keg.join("bin")).unwrap();
+        fs::write(keg.join("bin/jq"), "old").unwrap();
+        fs::write(
+            keg.join("INSTALL_RECEIPT.json"),
+            r#"{"installed_on_request": false, "installed_as_dependency": true}"#,
+        )
+        .unwrap();
+        symlink::link_formula_in(prefix, "jq", version).unwrap();
+        symlink::optlink_in(prefix, "jq", version).unwrap();
+    }
+
+    #[test]
+    fn test_upgrade_keg_swaps_links_and_removes_old_keg() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path().join("brew");
+        install_old(&prefix, "1.6");
+        let old_size = cellar::keg_size(&prefix.join("Cellar/jq/1.6")).unwrap();
+        let bottle = write_bottle(temp.path(), "1.7.1");
+
+        let keg = pour_keg_in(&prefix, &formula("1.7.1"), &bottle).unwrap();
+        assert_eq!(keg.version, "1.7.1");
+        let options = ReceiptOptions {
+            used_options: vec!["--with-oniguruma".to_string()],
+            ..Default::default()
+        };
+        let result = upgrade_keg_in(&prefix, &formula("1.7.1"), keg, "1.6", &options).unwrap();
+
+        assert_eq!(result.from_version, "1.6");
+        assert_eq!(result.to_version, "1.7.1");
+        assert_eq!(result.linked_files, 1);
+        assert_eq!(result.freed_bytes, old_size);
+        assert!(!prefix.join("Cellar/jq/1.6").exists());
+        assert_eq!(
+            fs::read_link(prefix.join("bin/jq")).unwrap(),
+            PathBuf::from("../Cellar/jq/1.7.1/bin/jq")
+        );
+        assert_eq!(
+            symlink::get_linked_version_in(&prefix, "jq")
+                .unwrap()
+                .as_deref(),
+            Some("1.7.1")
+        );
+        let script = fs::read_to_string(result.path.join("bin/jq")).unwrap();
+        assert!(script.starts_with(&format!("#!{}/bin/sh", prefix.display())));
+
+        // Still recorded as a dependency, with the options it was installed with
+        let receipt = receipt::InstallReceipt::read(&result.path).unwrap();
+        assert!(!receipt.installed_on_request);
+        assert_eq!(receipt.used_options, ["--with-oniguruma"]);
+    }
+
+    #[test]
+    fn test_reinstall_keg_replaces_keg() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path().join("brew");
+        install_old(&prefix, "1.7.1");
+        let bottle = write_bottle(temp.path(), "1.7.1");
+
+        let result = reinstall_keg_in(
+            &prefix,
+            &formula("1.7.1"),
+            &bottle,
+            "1.7.1",
+            &ReceiptOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(result.version, "1.7.1");
+        assert_eq!(result.linked_files, 1);
+        let script = fs::read_to_string(prefix.join("bin/jq")).unwrap();
+        assert!(script.contains("echo jq"));
+        assert!(
+            !receipt::InstallReceipt::read(&result.path)
+                .unwrap()
+                .installed_on_request
+        );
+    }
+}
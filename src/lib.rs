@@ -8,8 +8,10 @@
 //!
 //! - **api.rs**: Homebrew JSON API client with caching
 //! - **cellar.rs**: Local Cellar inspection (installed packages)
+//! - **cleanup.rs**: Removal of old formula and cask versions
 //! - **download.rs**: Parallel bottle downloads from GHCR
 //! - **extract.rs**: Bottle extraction to Cellar
+//! - **keg.rs**: Replacing installed kegs for upgrades and reinstalls
 //! - **symlink.rs**: Symlink management for installed packages
 //! - **tap.rs**: Custom tap management
 //! - **receipt.rs**: Installation receipt generation and metadata
@@ -126,7 +128,7 @@
 //!     let api = BrewApi::new()?;
 //!
 //!     // Search across all formulae and casks
-//!     let results = api.search("python").await?;
+//!     let results = api.search("python", Default::default()).await?;
 //!
 //!     println!("Found {} formulae", results.formulae.len());
 //!     for formula in &results.formulae {
@@ -156,8 +158,8 @@
 //!     println!("Downloaded bottle to: {}", bottle_path.display());
 //!
 //!     // Step 2: Extract to Cellar
-//!     let version = formula.versions.stable.unwrap();
-//!     let cellar_dir = extract::extract_bottle(&bottle_path, "ripgrep", &version)?;
+//!     let version = formula.install_version()?;
+//!     let cellar_dir = extract::extract_bottle(&bottle_path, "ripgrep", version)?;
 //!     println!("Extracted to: {}", cellar_dir.display());
 //!
 //!     // Step 3: Create symlinks
@@ -171,6 +173,9 @@
 //! }
 //! ```
 //!
+//! [`install_bottle`] runs these steps for one formula, along with relocation and the
+//! install receipt.
+//!
 //! ## Read Installation Metadata
 //!
 //! ```no_run
@@ -250,26 +255,50 @@
 pub mod api;
 pub mod cache;
 pub mod cellar;
+pub mod cleanup;
+pub mod context;
 pub mod download;
 pub mod error;
 pub mod extract;
+pub mod keg;
 pub mod package_manager;
 pub mod platform;
 pub mod receipt;
 pub mod symlink;
 pub mod tap;
 
+// Caskroom inspection used by cleanup; the rest of cask handling is CLI-only
+#[allow(dead_code)]
+mod cask;
+// Internal helpers used by install_bottle; not part of the public API
+#[allow(dead_code)]
+mod relocate;
+#[cfg(test)]
+mod test_support;
+
 // Re-export commonly used types and functions
-pub use api::{Bottle, BrewApi, Cask, Formula, SearchResults, Versions};
+pub use api::{
+    Bottle, BottleFileInfo, BrewApi, Cask, Formula, FormulaBrief, FormulaExecutables,
+    FormulaSource, RateLimit, Scored, SearchResults, SearchScope, Versions,
+};
 pub use cache::{get_cached_casks, get_cached_formulae, store_casks, store_formulae};
-pub use cellar::{InstalledPackage, RuntimeDependency, cellar_path, detect_prefix, list_installed};
+pub use cellar::{
+    BuildEnvHints, InstalledPackage, RuntimeDependency, cellar_path, detect_prefix, list_installed,
+};
+pub use context::{BrewConfig, BrewContext};
 pub use download::cache_dir;
 pub use error::{BruError, Result};
-pub use extract::extract_bottle;
+pub use extract::{extract_bottle, extract_bottle_with_manifest};
 pub use package_manager::{
-    CleanupResult, Dependencies, HealthCheck, InstallResult, OutdatedPackage, PackageManager,
-    ReinstallResult, UninstallResult, UpgradeResult,
+    CleanedVersion, CleanupResult, Dependencies, HealthCheck, InstallOptions, InstallResult,
+    OutdatedPackage, PackageManager, ReinstallResult, UninstallResult, UpgradeResult,
+    install_bottle,
 };
 pub use receipt::InstallReceipt;
-pub use symlink::{link_formula, normalize_path, optlink, unlink_formula, unoptlink};
-pub use tap::{get_core_formula_version, list_taps, parse_formula_info, parse_formula_version};
+pub use symlink::{
+    link_formula, link_formula_with_manifest, normalize_path, optlink, unlink_formula, unoptlink,
+};
+pub use tap::{
+    TapInfo, get_core_formula_version, list_casks, list_formulae, list_taps, parse_cask_info,
+    parse_formula_info, parse_formula_version,
+};
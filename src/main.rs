@@ -2,17 +2,22 @@ mod api;
 mod cache;
 mod cask;
 mod cellar;
+mod cleanup;
 mod colors;
 mod commands;
 mod download;
 mod error;
 mod extract;
+mod keg;
+mod output;
 mod platform;
 mod receipt;
 mod relocate;
 mod services;
 mod symlink;
 mod tap;
+#[cfg(test)]
+mod test_support;
 
 use clap::{CommandFactory, Parser, Subcommand};
 use colored::Colorize;
@@ -56,6 +61,15 @@ struct Cli {
     /// Disable colored output (same as NO_COLOR=1)
     #[arg(long, global = true)]
     no_color: bool,
+
+    /// Output JSON (list, info, outdated, deps, leaves); other commands print
+    /// {"unsupported": true} and exit without running
+    #[arg(long, global = true)]
+    json: bool,
+
+    /// Operate on an alternate Homebrew prefix (same as HOMEBREW_PREFIX=PATH)
+    #[arg(long, global = true, value_name = "PATH")]
+    prefix: Option<std::path::PathBuf>,
 }
 
 #[derive(Subcommand)]
@@ -72,16 +86,33 @@ enum Commands {
         /// Only search casks
         #[arg(long)]
         cask: bool,
+
+        /// Search installed formulae and casks instead (works offline)
+        #[arg(long)]
+        installed: bool,
     },
 
     /// Show information about a formula or cask
     Info {
-        /// Formula/cask name
-        formula: String,
+        /// Formula/cask names
+        #[arg(required = true)]
+        formulae: Vec<String>,
+
+        /// Include GitHub stars and latest release (queries the GitHub API)
+        #[arg(long)]
+        github: bool,
 
-        /// Output as JSON
+        /// List every platform's bottle with its size and sha256
         #[arg(long)]
-        json: bool,
+        variations: bool,
+
+        /// Only look the name up as a formula
+        #[arg(long = "formula")]
+        formula_only: bool,
+
+        /// Only look the name up as a cask
+        #[arg(long = "cask", conflicts_with = "formula_only")]
+        cask_only: bool,
     },
 
     /// Show dependencies for a formula
@@ -98,8 +129,24 @@ enum Commands {
         installed: bool,
 
         /// Show only direct dependencies (default: show all transitive dependencies)
-        #[arg(long)]
+        #[arg(long, visible_alias = "1")]
         direct: bool,
+
+        /// List each dependency once, tagged with how it's declared (e.g. [build, test])
+        #[arg(long, visible_alias = "annotate-optional")]
+        annotate: bool,
+
+        /// Also list non-formula requirements (macOS, Xcode, architecture)
+        #[arg(long)]
+        include_requirements: bool,
+
+        /// Print the formula and its dependencies in install order, dependencies first
+        #[arg(
+            long,
+            visible_alias = "topological",
+            conflicts_with_all = ["tree", "direct", "annotate", "include_requirements"]
+        )]
+        topo: bool,
     },
 
     /// Show formulae that depend on a formula
@@ -110,18 +157,21 @@ enum Commands {
         /// Only show formulae that are currently installed
         #[arg(long)]
         installed: bool,
+
+        /// Also check casks that depend on the formula
+        #[arg(long, visible_alias = "eval-all")]
+        cask: bool,
     },
 
     /// List installed packages
     List {
+        /// Formulae to list installed files for
+        formulae: Vec<String>,
+
         /// Show all installed versions
         #[arg(long)]
         versions: bool,
 
-        /// Output as JSON
-        #[arg(long)]
-        json: bool,
-
         /// List formulae only
         #[arg(long)]
         formula: bool,
@@ -137,29 +187,57 @@ enum Commands {
         /// Display packages in columns (names only, no versions)
         #[arg(short = 'C', long)]
         columns: bool,
+
+        /// With --json, add size, link state, request state and dependencies per
+        /// formula (walks every keg, so slower on large installs)
+        #[arg(long)]
+        detailed: bool,
+
+        /// Order formulae by name, install date (newest first) or size (largest first)
+        #[arg(long, value_enum, default_value_t = commands::ListSort::Name)]
+        sort: commands::ListSort,
     },
 
     /// Show outdated installed packages
     Outdated {
-        /// Check outdated casks instead of formulae
+        /// Check outdated formulae only
+        #[arg(long)]
+        formula: bool,
+
+        /// Check outdated casks only
         #[arg(long)]
         cask: bool,
 
         /// Show only names (one per line, no versions or headers)
         #[arg(short, long)]
         quiet: bool,
+
+        /// Exit with status 1 if any packages are outdated (pinned formulae excluded)
+        #[arg(long)]
+        exit_code: bool,
     },
 
     /// Download bottles for formulae
     Fetch {
         /// Formula names
         formulae: Vec<String>,
+
+        /// Check every bottle's SHA256 (downloading it or re-reading the cache) and
+        /// report pass/fail per formula; exits non-zero on any failure
+        #[arg(long)]
+        verify: bool,
+
+        /// Also count formulae that can't be looked up or have no bottle as failures,
+        /// and list every failure at the end. Every bottle is always attempted, but
+        /// by default only failed downloads make the command exit non-zero
+        #[arg(long)]
+        keep_going: bool,
     },
 
     /// Install formulae from bottles
     #[command(alias = "i")]
     Install {
-        /// Formula/cask names
+        /// Formula/cask names, `.rb` formula files, or paths/URLs of `.bottle.tar.gz` files
         formulae: Vec<String>,
 
         /// Skip installing dependencies
@@ -170,13 +248,55 @@ enum Commands {
         #[arg(long)]
         cask: bool,
 
+        /// Treat names as formulae even when a cask has the same name
+        #[arg(long, visible_alias = "formulae-only", conflicts_with = "cask")]
+        formula: bool,
+
         /// Show what would be installed without actually installing
         #[arg(short = 'n', long)]
         dry_run: bool,
 
-        /// Install even if already installed
+        /// Install even if already installed or the macOS version is unsupported
         #[arg(short, long)]
         force: bool,
+
+        /// Don't ask for confirmation before large installs
+        #[arg(short, long)]
+        yes: bool,
+
+        /// Always ask for confirmation before downloading
+        #[arg(long, conflicts_with = "yes")]
+        interactive: bool,
+
+        /// Install bottles for this platform tag instead of the detected one (e.g. arm64_sonoma)
+        #[arg(long, value_name = "TAG")]
+        bottle_tag: Option<String>,
+
+        /// Install cask apps into this directory instead of /Applications
+        #[arg(long, value_name = "DIR")]
+        appdir: Option<std::path::PathBuf>,
+
+        /// Install this optional dependency (also accepted as --with-<DEP>)
+        #[arg(long = "with", value_name = "DEP")]
+        with: Vec<String>,
+
+        /// Skip this recommended dependency (also accepted as --without-<DEP>)
+        #[arg(long = "without", value_name = "DEP")]
+        without: Vec<String>,
+
+        /// Don't install recommended dependencies
+        #[arg(long)]
+        ignore_recommended: bool,
+
+        /// Install all optional dependencies
+        #[arg(long)]
+        include_optional: bool,
+
+        /// Install the rest when one formula fails, and list every failure at the end.
+        /// Unknown formulae and failed downloads are always skipped, but by default a
+        /// failed pour rolls back the whole run
+        #[arg(long)]
+        keep_going: bool,
     },
 
     /// Upgrade installed formulae
@@ -196,6 +316,26 @@ enum Commands {
         /// Upgrade even if already at latest version
         #[arg(short, long)]
         force: bool,
+
+        /// Upgrade the rest when dependency lookup fails for some formulae, and list
+        /// every failure at the end. A failed upgrade never stops the others and always
+        /// makes the command exit non-zero, but by default a failed lookup stops the run
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Upgrade only the named formulae, not their outdated or newly added
+        /// dependencies
+        #[arg(long, conflicts_with = "dependents")]
+        ignore_dependencies: bool,
+
+        /// Reinstall installed formulae that depend on the upgraded ones, so they link
+        /// against the new versions
+        #[arg(long, conflicts_with = "cask")]
+        dependents: bool,
+
+        /// Install cask apps into this directory instead of /Applications
+        #[arg(long, value_name = "DIR")]
+        appdir: Option<std::path::PathBuf>,
     },
 
     /// Reinstall formulae
@@ -207,6 +347,20 @@ enum Commands {
         /// Reinstall casks instead of formulae
         #[arg(long)]
         cask: bool,
+
+        /// Reuse the cached bottle and metadata without touching the network
+        #[arg(long, conflicts_with = "cask")]
+        from_cache: bool,
+
+        /// Reinstall the rest when one formula fails, and list every failure at the
+        /// end. By default the first failed lookup or pour stops the run; any failure
+        /// makes the command exit non-zero
+        #[arg(long)]
+        keep_going: bool,
+
+        /// Install cask apps into this directory instead of /Applications
+        #[arg(long, value_name = "DIR")]
+        appdir: Option<std::path::PathBuf>,
     },
 
     /// Uninstall formulae
@@ -222,6 +376,10 @@ enum Commands {
         /// Uninstall cask instead of formula
         #[arg(long)]
         cask: bool,
+
+        /// Also remove the files listed in the cask's zap stanza (settings, caches, ...)
+        #[arg(long, requires = "cask")]
+        zap: bool,
     },
 
     /// Remove unused dependencies
@@ -241,6 +399,10 @@ enum Commands {
     Untap {
         /// Tap name (user/repo format)
         tap: String,
+
+        /// Untap even if installed formulae came from this tap
+        #[arg(long)]
+        force: bool,
     },
 
     /// Show tap information
@@ -264,6 +426,14 @@ enum Commands {
         /// Clean up casks instead of formulae
         #[arg(long)]
         cask: bool,
+
+        /// Only remove broken symlinks and empty directories from the prefix
+        #[arg(long)]
+        prune_prefix: bool,
+
+        /// Also remove all downloaded bottles from the cache
+        #[arg(short, long)]
+        scrub: bool,
     },
 
     /// Manage download cache
@@ -280,7 +450,15 @@ enum Commands {
     Doctor,
 
     /// Show Homebrew environment variables
-    Env,
+    Env {
+        /// Print sourceable commands for this shell (bash, zsh, fish)
+        #[arg(long, conflicts_with = "plain")]
+        shell: Option<String>,
+
+        /// Print unquoted NAME=value lines
+        #[arg(long)]
+        plain: bool,
+    },
 
     /// Open formula homepage in browser
     Home {
@@ -295,12 +473,20 @@ enum Commands {
     Pin {
         /// Formula names
         formulae: Vec<String>,
+
+        /// Pin casks instead of formulae
+        #[arg(long)]
+        cask: bool,
     },
 
     /// Unpin formulae to allow upgrades
     Unpin {
         /// Formula names
         formulae: Vec<String>,
+
+        /// Unpin casks instead of formulae
+        #[arg(long)]
+        cask: bool,
     },
 
     /// Show formula description
@@ -313,6 +499,18 @@ enum Commands {
     Link {
         /// Formula names
         formulae: Vec<String>,
+
+        /// Delete files that already exist in the prefix and conflict with the links
+        #[arg(long)]
+        overwrite: bool,
+
+        /// List the links that would be created without linking anything
+        #[arg(short = 'n', long)]
+        dry_run: bool,
+
+        /// Link keg-only formulae as well
+        #[arg(short, long)]
+        force: bool,
     },
 
     /// Unlink a formula
@@ -348,6 +546,14 @@ enum Commands {
     Cat {
         /// Formula names
         formulae: Vec<String>,
+
+        /// Only look the names up as formulae
+        #[arg(long = "formula")]
+        formula_only: bool,
+
+        /// Only look the names up as casks
+        #[arg(long = "cask", conflicts_with = "formula_only")]
+        cask_only: bool,
     },
 
     /// Print shell configuration
@@ -361,6 +567,10 @@ enum Commands {
     GistLogs {
         /// Formula name (optional)
         formula: Option<String>,
+
+        /// Write the report to this file instead of uploading it
+        #[arg(short, long)]
+        output: Option<std::path::PathBuf>,
     },
 
     /// Show formula aliases
@@ -393,6 +603,10 @@ enum Commands {
         #[arg(long)]
         dump: bool,
 
+        /// With --dump, add each entry's description as a comment
+        #[arg(long, requires = "dump")]
+        describe: bool,
+
         /// Path to Brewfile (default: ./Brewfile)
         #[arg(long)]
         file: Option<String>,
@@ -501,9 +715,13 @@ enum Commands {
         /// Formula names (or all if empty)
         formulae: Vec<String>,
 
-        /// Show all files
+        /// List every linked library, not just missing ones
         #[arg(long)]
         all: bool,
+
+        /// Reinstall formulae that link against missing libraries
+        #[arg(long)]
+        rebuild: bool,
     },
 
     /// Read and validate all formulae in a tap
@@ -814,12 +1032,10 @@ enum Commands {
         target: Option<String>,
     },
 
-    /// Run a Homebrew sub-command
+    /// Suggest the formula that provides a command
     Command {
-        /// Sub-command to run
-        subcommand: String,
-        /// Arguments to pass
-        args: Vec<String>,
+        /// Command name
+        command: String,
     },
 
     /// Sync nodenv shims
@@ -859,6 +1075,29 @@ enum Commands {
     UpdateIfNeeded,
 }
 
+/// Process exit status reported by `bru`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ExitCode {
+    Success = 0,
+    /// The command failed, a package was not found, or `outdated --exit-code` found updates
+    Failure = 1,
+    /// Some of the requested packages failed while the rest succeeded
+    PartialFailure = 2,
+    /// Ctrl-C stopped the command (see [`commands::interrupt`])
+    Interrupted = commands::interrupt::INTERRUPTED_EXIT_CODE as isize,
+}
+
+impl From<commands::Outcome> for ExitCode {
+    fn from(outcome: commands::Outcome) -> Self {
+        match outcome {
+            commands::Outcome::Done => Self::Success,
+            commands::Outcome::NotFound | commands::Outcome::UpdatesAvailable => Self::Failure,
+            commands::Outcome::PartialFailure => Self::PartialFailure,
+            commands::Outcome::Interrupted => Self::Interrupted,
+        }
+    }
+}
+
 fn main() {
     let runtime = tokio::runtime::Builder::new_multi_thread()
         .enable_all()
@@ -873,10 +1112,41 @@ fn main() {
     // Explicitly shutdown runtime with timeout
     runtime.shutdown_timeout(std::time::Duration::from_secs(1));
 
-    if let Err(e) = result {
-        eprintln!("{}", e);
-        std::process::exit(1);
+    match result {
+        Ok(ExitCode::Success) => {}
+        Ok(code) => std::process::exit(code as i32),
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(ExitCode::Failure as i32);
+        }
+    }
+}
+
+/// Rewrite Homebrew's `--with-<dep>`/`--without-<dep>` into `--with=<dep>`/`--without=<dep>`
+/// so clap can parse them.
+///
+/// Only arguments after an `install` subcommand are rewritten; every other command
+/// gets its arguments unchanged.
+fn expand_dependency_flags(args: impl Iterator<Item = String>) -> Vec<String> {
+    let mut args: Vec<String> = args.collect();
+
+    // The subcommand is the first argument that isn't a global flag or its value
+    let mut index = 1;
+    while index < args.len() && args[index].starts_with('-') {
+        index += if args[index] == "--prefix" { 2 } else { 1 };
+    }
+    if !matches!(args.get(index).map(String::as_str), Some("install" | "i")) {
+        return args;
+    }
+
+    for arg in &mut args[index + 1..] {
+        if let Some(dep) = arg.strip_prefix("--with-") {
+            *arg = format!("--with={}", dep);
+        } else if let Some(dep) = arg.strip_prefix("--without-") {
+            *arg = format!("--without={}", dep);
+        }
     }
+    args
 }
 
 /// Display a user-friendly error and exit
@@ -889,7 +1159,7 @@ fn error_exit(message: &str, usage: &str) -> ! {
     std::process::exit(1);
 }
 
-async fn run() -> anyhow::Result<()> {
+async fn run() -> anyhow::Result<ExitCode> {
     // Initialize logging
     if std::env::var("RUST_LOG").is_err() {
         // SAFETY: Setting RUST_LOG before any threads spawn is safe. This is the entry point
@@ -924,7 +1194,10 @@ async fn run() -> anyhow::Result<()> {
         default_panic(panic_info);
     }));
 
-    let cli = match Cli::try_parse() {
+    // Ctrl-C exits immediately unless a command defers it to clean up first
+    commands::interrupt::install_handler();
+
+    let cli = match Cli::try_parse_from(expand_dependency_flags(std::env::args())) {
         Ok(cli) => cli,
         Err(err) => {
             // Check if this is an unrecognized command error
@@ -950,8 +1223,8 @@ async fn run() -> anyhow::Result<()> {
         }
     };
 
-    // Set NO_COLOR if --no-color flag is set
-    if cli.no_color {
+    // Set NO_COLOR if --no-color flag is set (JSON output is never colored)
+    if cli.no_color || cli.json {
         // SAFETY: Setting NO_COLOR early in main before colors are initialized is safe.
         // This happens before any concurrent access and is only read by colors::init_colors().
         unsafe {
@@ -962,6 +1235,51 @@ async fn run() -> anyhow::Result<()> {
     // Initialize color support (respects NO_COLOR, CLICOLOR, TTY)
     colors::init_colors();
 
+    // Route every prefix lookup (cellar::detect_prefix) to the requested prefix; the
+    // environment variable carries it on to brew when a command delegates
+    if let Some(prefix) = &cli.prefix {
+        // Symlink targets are computed relative to the prefix, so it must be absolute
+        let prefix = std::path::absolute(prefix)?;
+        // SAFETY: Setting HOMEBREW_PREFIX early in main is safe. This happens in the main
+        // thread before command execution and is only read (not modified) afterwards.
+        unsafe {
+            std::env::set_var("HOMEBREW_PREFIX", &prefix);
+        }
+        cellar::set_prefix(Some(prefix));
+    }
+
+    // Route bottle selection (platform::bottle_tag) to the requested tag
+    if let Some(Commands::Install {
+        bottle_tag: Some(tag),
+        ..
+    }) = &cli.command
+    {
+        // SAFETY: Same as HOMEBREW_PREFIX above - set once before command execution.
+        unsafe {
+            std::env::set_var("HOMEBREW_BOTTLE_TAG", tag);
+        }
+    }
+
+    // Route cask app installs (cask::appdir) to the requested directory
+    if let Some(
+        Commands::Install {
+            appdir: Some(appdir),
+            ..
+        }
+        | Commands::Upgrade {
+            appdir: Some(appdir),
+            ..
+        }
+        | Commands::Reinstall {
+            appdir: Some(appdir),
+            ..
+        },
+    ) = &cli.command
+    {
+        let appdir = cask::expand_cask_path(&appdir.to_string_lossy());
+        cask::set_appdir(Some(std::path::absolute(appdir)?));
+    }
+
     // Set quiet mode environment variable for commands to check
     if cli.quiet {
         // SAFETY: Setting BRU_QUIET early in main is safe. This happens in the main thread
@@ -971,56 +1289,175 @@ async fn run() -> anyhow::Result<()> {
         }
     }
 
+    // Commands without a JSON schema don't run under --json
+    if cli.json
+        && !matches!(
+            cli.command,
+            Some(
+                Commands::List { .. }
+                    | Commands::Search {
+                        installed: false,
+                        ..
+                    }
+                    | Commands::Info { .. }
+                    | Commands::Outdated { .. }
+                    | Commands::Deps {
+                        include_requirements: false,
+                        ..
+                    }
+                    | Commands::Leaves
+                    | Commands::Config
+                    | Commands::Linkage { rebuild: false, .. }
+                    | Commands::Autoremove { dry_run: true }
+                    | Commands::Upgrade {
+                        dry_run: true,
+                        cask: false,
+                        ..
+                    }
+            )
+        )
+    {
+        output::print_unsupported();
+        return Ok(ExitCode::Failure);
+    }
+
     // Create API client
     let api = api::BrewApi::new()?;
 
+    // Commands that only succeed or fail through `?` leave this as `Done`
+    let mut outcome = commands::Outcome::Done;
+
     match cli.command {
         Some(Commands::Search {
             query,
             formula,
             cask,
+            installed,
         }) => {
-            commands::search(&api, &query, formula, cask).await?;
+            if installed {
+                commands::search_installed(&query, formula, cask)?;
+            } else {
+                commands::search(&api, &query, formula, cask, cli.json).await?;
+            }
         }
-        Some(Commands::Info { formula, json }) => {
-            commands::info(&api, &formula, json).await?;
+        Some(Commands::Info {
+            formulae,
+            github,
+            variations,
+            formula_only,
+            cask_only,
+        }) => {
+            if cli.json {
+                outcome = commands::info_json_array(
+                    &api,
+                    &formulae,
+                    github,
+                    variations,
+                    formula_only,
+                    cask_only,
+                )
+                .await?;
+            } else {
+                for (i, formula) in formulae.iter().enumerate() {
+                    if i > 0 {
+                        println!();
+                    }
+                    let result =
+                        commands::info(&api, formula, github, variations, formula_only, cask_only)
+                            .await?;
+                    if result != commands::Outcome::Done {
+                        outcome = result;
+                    }
+                }
+            }
         }
         Some(Commands::Deps {
             formula,
             tree,
             installed,
             direct,
+            annotate,
+            include_requirements,
+            topo,
         }) => {
-            commands::deps(&api, &formula, tree, installed, direct).await?;
-        }
-        Some(Commands::Uses { formula, installed }) => {
-            commands::uses(&api, &formula, installed).await?;
+            commands::deps(
+                &api,
+                &formula,
+                tree,
+                installed,
+                direct,
+                annotate,
+                include_requirements,
+                topo,
+                cli.json,
+            )
+            .await?;
+        }
+        Some(Commands::Uses {
+            formula,
+            installed,
+            cask,
+        }) => {
+            commands::uses(&api, &formula, installed, cask).await?;
         }
         Some(Commands::List {
+            formulae,
             versions,
-            json,
             formula,
             cask,
             quiet,
             columns,
+            detailed,
+            sort,
         }) => {
-            commands::list(&api, versions, json, formula, cask, quiet, columns).await?;
+            if formulae.is_empty() {
+                commands::list(
+                    &api, versions, cli.json, detailed, formula, cask, quiet, columns, sort,
+                )
+                .await?;
+            } else {
+                commands::list_files(&formulae, cli.json)?;
+            }
         }
-        Some(Commands::Outdated { cask, quiet }) => {
-            commands::outdated(&api, cask, quiet).await?;
+        Some(Commands::Outdated {
+            formula,
+            cask,
+            quiet,
+            exit_code,
+        }) => {
+            let found = commands::outdated(&api, formula, cask, quiet, cli.json).await?;
+            if exit_code {
+                outcome = found;
+            }
         }
-        Some(Commands::Fetch { formulae }) => {
+        Some(Commands::Fetch {
+            formulae,
+            verify,
+            keep_going,
+        }) => {
             if formulae.is_empty() {
                 error_exit("No formulae specified", "bru fetch [FORMULAE]...");
             }
-            commands::fetch(&api, &formulae).await?;
+            outcome = commands::fetch(&api, &formulae, verify, keep_going).await?;
         }
         Some(Commands::Install {
             formulae,
-            only_dependencies,
+            // Accepted for brew compatibility; install doesn't act on it yet
+            only_dependencies: _,
             cask,
+            formula,
             dry_run,
             force,
+            yes,
+            interactive,
+            with,
+            without,
+            ignore_recommended,
+            include_optional,
+            keep_going,
+            // bottle_tag is applied through HOMEBREW_BOTTLE_TAG and appdir through
+            // cask::set_appdir before dispatch
+            ..
         }) => {
             if formulae.is_empty() {
                 error_exit(
@@ -1031,7 +1468,21 @@ async fn run() -> anyhow::Result<()> {
             if cask {
                 commands::cask::install_cask(&api, &formulae).await?;
             } else {
-                commands::install(&api, &formulae, only_dependencies, dry_run, force).await?;
+                let flags = commands::InstallFlags {
+                    dry_run,
+                    force,
+                    formula_only: formula,
+                    yes,
+                    interactive,
+                    dependency_policy: api::DependencyPolicy {
+                        ignore_recommended,
+                        include_optional,
+                        with,
+                        without,
+                    },
+                    keep_going,
+                };
+                outcome = commands::install(&api, &formulae, &flags).await?;
             }
         }
         Some(Commands::Upgrade {
@@ -1039,22 +1490,46 @@ async fn run() -> anyhow::Result<()> {
             cask,
             dry_run,
             force,
+            keep_going,
+            ignore_dependencies,
+            dependents,
+            // appdir is applied through cask::set_appdir before dispatch
+            ..
+        }) => {
+            outcome = commands::upgrade(
+                &api,
+                &formulae,
+                cask,
+                dry_run,
+                force,
+                cli.json,
+                keep_going,
+                ignore_dependencies,
+                dependents,
+            )
+            .await?;
+        }
+        Some(Commands::Reinstall {
+            formulae,
+            cask,
+            from_cache,
+            keep_going,
+            // appdir is applied through cask::set_appdir before dispatch
+            ..
         }) => {
-            commands::upgrade(&api, &formulae, cask, dry_run, force).await?;
-        }
-        Some(Commands::Reinstall { formulae, cask }) => {
             if formulae.is_empty() {
                 error_exit(
                     "No formulae specified",
                     "bru reinstall [OPTIONS] [FORMULAE]...",
                 );
             }
-            commands::reinstall(&api, &formulae, cask).await?;
+            outcome = commands::reinstall(&api, &formulae, cask, from_cache, keep_going).await?;
         }
         Some(Commands::Uninstall {
             formulae,
             force,
             cask,
+            zap,
         }) => {
             if formulae.is_empty() {
                 error_exit(
@@ -1063,19 +1538,19 @@ async fn run() -> anyhow::Result<()> {
                 );
             }
             if cask {
-                commands::cask::uninstall_cask(&formulae)?;
+                commands::cask::uninstall_cask(&formulae, zap)?;
             } else {
                 commands::uninstall(&api, &formulae, force).await?;
             }
         }
         Some(Commands::Autoremove { dry_run }) => {
-            commands::maintenance::autoremove(dry_run)?;
+            commands::maintenance::autoremove(dry_run, cli.json)?;
         }
         Some(Commands::Tap { tap }) => {
             commands::tap::tap(tap.as_deref())?;
         }
-        Some(Commands::Untap { tap }) => {
-            commands::tap::untap(&tap)?;
+        Some(Commands::Untap { tap, force }) => {
+            commands::tap::untap(&tap, force)?;
         }
         Some(Commands::TapInfo { tap }) => {
             commands::tap::tap_info(&tap)?;
@@ -1087,38 +1562,40 @@ async fn run() -> anyhow::Result<()> {
             formulae,
             dry_run,
             cask,
+            prune_prefix,
+            scrub,
         }) => {
-            commands::maintenance::cleanup(&formulae, dry_run, cask)?;
+            commands::maintenance::cleanup(&formulae, dry_run, cask, prune_prefix, scrub)?;
         }
         Some(Commands::Cache { clean }) => {
             commands::maintenance::cache(clean)?;
         }
         Some(Commands::Config) => {
-            commands::config()?;
+            commands::config(cli.json)?;
         }
         Some(Commands::Doctor) => {
             commands::maintenance::doctor()?;
         }
-        Some(Commands::Env) => {
-            commands::env()?;
+        Some(Commands::Env { shell, plain }) => {
+            commands::env(shell.as_deref(), plain)?;
         }
         Some(Commands::Home { formula }) => {
             commands::query::home(&api, &formula).await?;
         }
         Some(Commands::Leaves) => {
-            commands::leaves()?;
+            commands::leaves(cli.json)?;
         }
-        Some(Commands::Pin { formulae }) => {
+        Some(Commands::Pin { formulae, cask }) => {
             if formulae.is_empty() {
                 error_exit("No formulae specified", "bru pin [FORMULAE]...");
             }
-            commands::pin(&formulae)?;
+            commands::pin(&formulae, cask)?;
         }
-        Some(Commands::Unpin { formulae }) => {
+        Some(Commands::Unpin { formulae, cask }) => {
             if formulae.is_empty() {
                 error_exit("No formulae specified", "bru unpin [FORMULAE]...");
             }
-            commands::unpin(&formulae)?;
+            commands::unpin(&formulae, cask)?;
         }
         Some(Commands::Desc { formulae }) => {
             if formulae.is_empty() {
@@ -1126,11 +1603,16 @@ async fn run() -> anyhow::Result<()> {
             }
             commands::desc(&api, &formulae).await?;
         }
-        Some(Commands::Link { formulae }) => {
+        Some(Commands::Link {
+            formulae,
+            overwrite,
+            dry_run,
+            force,
+        }) => {
             if formulae.is_empty() {
                 error_exit("No formulae specified", "bru link [FORMULAE]...");
             }
-            commands::link(&api, &formulae).await?;
+            commands::link(&api, &formulae, overwrite, dry_run, force).await?;
         }
         Some(Commands::Unlink { formulae }) => {
             if formulae.is_empty() {
@@ -1151,14 +1633,18 @@ async fn run() -> anyhow::Result<()> {
         Some(Commands::Analytics { action }) => {
             commands::analytics(action.as_deref())?;
         }
-        Some(Commands::Cat { formulae }) => {
-            commands::cat(&api, &formulae).await?;
+        Some(Commands::Cat {
+            formulae,
+            formula_only,
+            cask_only,
+        }) => {
+            commands::cat(&api, &formulae, formula_only, cask_only).await?;
         }
         Some(Commands::Shellenv { shell }) => {
             commands::shellenv(shell.as_deref())?;
         }
-        Some(Commands::GistLogs { formula }) => {
-            commands::gist_logs(&api, formula.as_deref()).await?;
+        Some(Commands::GistLogs { formula, output }) => {
+            commands::gist_logs(&api, formula.as_deref(), output.as_deref()).await?;
         }
         Some(Commands::Alias { formula }) => {
             commands::alias(&api, formula.as_deref()).await?;
@@ -1172,8 +1658,12 @@ async fn run() -> anyhow::Result<()> {
         Some(Commands::Options { formula }) => {
             commands::options(&api, &formula).await?;
         }
-        Some(Commands::Bundle { dump, file }) => {
-            commands::bundle(&api, dump, file.as_deref()).await?;
+        Some(Commands::Bundle {
+            dump,
+            describe,
+            file,
+        }) => {
+            commands::bundle(&api, dump, describe, file.as_deref()).await?;
         }
         Some(Commands::Services { action, formula }) => {
             commands::services(action.as_deref(), formula.as_deref())?;
@@ -1223,8 +1713,12 @@ async fn run() -> anyhow::Result<()> {
         Some(Commands::Migrate { formula, tap }) => {
             commands::migrate(&formula, tap.as_deref())?;
         }
-        Some(Commands::Linkage { formulae, all }) => {
-            commands::linkage(&formulae, all)?;
+        Some(Commands::Linkage {
+            formulae,
+            all,
+            rebuild,
+        }) => {
+            outcome = commands::linkage(&api, &formulae, all, rebuild, cli.json).await?;
         }
         Some(Commands::Readall { tap }) => {
             commands::readall(tap.as_deref())?;
@@ -1377,8 +1871,8 @@ async fn run() -> anyhow::Result<()> {
         Some(Commands::Sponsor { target }) => {
             commands::sponsor(target.as_deref())?;
         }
-        Some(Commands::Command { subcommand, args }) => {
-            commands::command(&subcommand, &args)?;
+        Some(Commands::Command { command }) => {
+            outcome = commands::command(&api, &command).await?;
         }
         Some(Commands::NodeenvSync) => {
             commands::nodenv_sync()?;
@@ -1450,5 +1944,5 @@ async fn run() -> anyhow::Result<()> {
         }
     }
 
-    Ok(())
+    Ok(outcome.into())
 }
@@ -0,0 +1,20 @@
+//! Machine-readable output for the global `--json` flag.
+//!
+//! Commands with a JSON schema render it through [`print_json`]; every other command
+//! prints the [`print_unsupported`] sentinel instead of running, so `--json` output is
+//! always parseable.
+//!
+//! JSON mode also disables colors and spinners (see `main`).
+
+use serde::Serialize;
+
+/// Pretty-print `value` as JSON on stdout
+pub fn print_json<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Print `{"unsupported": true}` for commands without a JSON schema
+pub fn print_unsupported() {
+    println!("{}", serde_json::json!({ "unsupported": true }));
+}
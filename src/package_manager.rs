@@ -45,17 +45,21 @@
 //! ```
 
 use crate::api::{BrewApi, Formula};
-use crate::cellar::{
-    self, InstalledPackage, RuntimeDependency, cellar_path, detect_prefix, list_installed,
-};
+use crate::cellar::{self, InstalledPackage};
+use crate::cleanup;
+use crate::context::BrewContext;
 use crate::error::Result;
-use crate::{download, extract, receipt, symlink};
+use crate::keg::{self, ReceiptOptions};
+use crate::{download, extract, receipt, relocate, symlink};
 use anyhow::anyhow;
 use serde::{Deserialize, Serialize};
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
+pub use crate::cleanup::{CleanedVersion, CleanupResult};
+pub use crate::keg::{ReinstallResult, UpgradeResult};
+
 /// Result of an install operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct InstallResult {
@@ -73,6 +77,27 @@ pub struct InstallResult {
     pub time_ms: u64,
 }
 
+/// Options for [`install_bottle`]
+#[derive(Debug, Clone)]
+pub struct InstallOptions {
+    /// Bottle to pour; when `None` it is downloaded into the context's cache
+    pub bottle: Option<PathBuf>,
+    /// Record the formula as installed on request rather than as a dependency
+    pub on_request: bool,
+    /// Link the keg into the prefix (keg-only formulae are never linked)
+    pub link: bool,
+}
+
+impl Default for InstallOptions {
+    fn default() -> Self {
+        Self {
+            bottle: None,
+            on_request: true,
+            link: true,
+        }
+    }
+}
+
 /// Result of an uninstall operation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct UninstallResult {
@@ -86,34 +111,6 @@ pub struct UninstallResult {
     pub time_ms: u64,
 }
 
-/// Result of an upgrade operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct UpgradeResult {
-    /// Package name
-    pub name: String,
-    /// Previous version
-    pub from_version: String,
-    /// New version
-    pub to_version: String,
-    /// Path in Cellar
-    pub path: PathBuf,
-    /// Time taken (milliseconds)
-    pub time_ms: u64,
-}
-
-/// Result of a reinstall operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ReinstallResult {
-    /// Package name
-    pub name: String,
-    /// Installed version
-    pub version: String,
-    /// Path in Cellar
-    pub path: PathBuf,
-    /// Time taken (milliseconds)
-    pub time_ms: u64,
-}
-
 /// Information about an outdated package
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OutdatedPackage {
@@ -127,17 +124,6 @@ pub struct OutdatedPackage {
     pub changeable: bool,
 }
 
-/// Result of a cleanup operation
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct CleanupResult {
-    /// Removed versions (formula/version)
-    pub removed: Vec<String>,
-    /// Space freed in MB
-    pub space_freed_mb: f64,
-    /// Errors encountered (formula, error message)
-    pub errors: Vec<(String, String)>,
-}
-
 /// Health check result
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HealthCheck {
@@ -191,8 +177,7 @@ pub struct Dependencies {
 /// }
 /// ```
 pub struct PackageManager {
-    api: BrewApi,
-    client: reqwest::Client,
+    ctx: BrewContext,
 }
 
 impl PackageManager {
@@ -214,14 +199,29 @@ impl PackageManager {
     /// }
     /// ```
     pub fn new() -> Result<Self> {
-        let api = BrewApi::new()?;
-        let client = reqwest::Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .pool_idle_timeout(std::time::Duration::from_secs(90))
-            .pool_max_idle_per_host(10)
-            .build()?;
-
-        Ok(Self { api, client })
+        Ok(Self::with_context(BrewContext::new()?))
+    }
+
+    /// Create a PackageManager that operates on an existing context.
+    ///
+    /// Use this to target a different prefix or cache directory, or to share one
+    /// HTTP client with other code.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use kombrucha::{BrewContext, PackageManager};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> anyhow::Result<()> {
+    ///     let ctx = BrewContext::with_prefix("/tmp/brew")?;
+    ///     let pm = PackageManager::with_context(ctx);
+    ///     println!("Cellar: {}", pm.cellar().display());
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn with_context(ctx: BrewContext) -> Self {
+        Self { ctx }
     }
 
     /// Install a package from a bottle.
@@ -258,69 +258,14 @@ impl PackageManager {
     /// }
     /// ```
     pub async fn install(&self, name: &str) -> Result<InstallResult> {
-        let start = Instant::now();
-
-        // Fetch formula
         let formula = self
+            .ctx
             .api
             .fetch_formula(name)
             .await
             .map_err(|_| anyhow!("Formula '{}' not found", name))?;
 
-        let version = formula
-            .versions
-            .stable
-            .as_ref()
-            .ok_or_else(|| anyhow!("No stable version available for {}", name))?
-            .clone();
-
-        // Step 1: Download bottle
-        let bottle_path = download::download_bottle(&formula, None, &self.client)
-            .await
-            .map_err(|e| anyhow!("Failed to download bottle: {}", e))?;
-
-        // Step 2: Extract to Cellar
-        let cellar_dir = extract::extract_bottle(&bottle_path, &formula.name, &version)
-            .map_err(|e| anyhow!("Failed to extract bottle: {}", e))?;
-
-        // Step 3: Generate runtime dependencies from formula metadata
-        // In a real scenario, we'd query the API for each dependency to get full info
-        // For now, use formula dependencies as fallback
-        let runtime_deps: Vec<RuntimeDependency> = formula
-            .dependencies
-            .iter()
-            .enumerate()
-            .map(|(idx, dep_name)| RuntimeDependency {
-                full_name: dep_name.clone(),
-                version: "0.0.0".to_string(), // Placeholder - would fetch from API in production
-                revision: 0,
-                bottle_rebuild: 0,
-                pkg_version: "0.0.0".to_string(),
-                declared_directly: idx == 0,
-            })
-            .collect();
-
-        // Step 4: Create installation receipt
-        let install_receipt = receipt::InstallReceipt::new_bottle(&formula, runtime_deps, true);
-        install_receipt
-            .write(&cellar_dir)
-            .map_err(|e| anyhow!("Failed to write installation receipt: {}", e))?;
-
-        // Step 5: Create symlinks
-        let _linked_files = symlink::link_formula(&formula.name, &version)
-            .map_err(|e| anyhow!("Failed to create symlinks: {}", e))?;
-
-        symlink::optlink(&formula.name, &version)
-            .map_err(|e| anyhow!("Failed to create opt symlink: {}", e))?;
-
-        Ok(InstallResult {
-            name: formula.name.clone(),
-            version,
-            path: cellar_dir,
-            dependencies: formula.dependencies.clone(),
-            linked: true,
-            time_ms: start.elapsed().as_millis() as u64,
-        })
+        install_bottle(&self.ctx, &formula, &InstallOptions::default()).await
     }
 
     /// Uninstall a package.
@@ -353,7 +298,7 @@ impl PackageManager {
     pub async fn uninstall(&self, name: &str) -> Result<UninstallResult> {
         let start = Instant::now();
 
-        let versions = crate::cellar::get_installed_versions(name)?;
+        let versions = cellar::get_installed_versions_in(&self.ctx.cellar(), name)?;
         let version = versions
             .first()
             .ok_or_else(|| anyhow!("Package '{}' not installed", name))?
@@ -361,13 +306,14 @@ impl PackageManager {
             .clone();
 
         // Step 1: Remove symlinks
-        symlink::unlink_formula(name, &version)
+        symlink::unlink_formula_in(&self.ctx.prefix, name, &version)
             .map_err(|e| anyhow!("Failed to remove symlinks: {}", e))?;
 
-        symlink::unoptlink(name).map_err(|e| anyhow!("Failed to remove opt symlink: {}", e))?;
+        symlink::unoptlink_in(&self.ctx.prefix, name)
+            .map_err(|e| anyhow!("Failed to remove opt symlink: {}", e))?;
 
         // Step 2: Remove from Cellar
-        let cellar = cellar_path();
+        let cellar = self.ctx.cellar();
         let formula_path = cellar.join(name).join(&version);
         fs::remove_dir_all(&formula_path)
             .map_err(|e| anyhow!("Failed to remove Cellar directory: {}", e))?;
@@ -404,13 +350,14 @@ impl PackageManager {
     ///     let pm = PackageManager::new()?;
     ///     let result = pm.upgrade("ripgrep").await?;
     ///     println!("Upgraded {} {} → {}", result.name, result.from_version, result.to_version);
+    ///     println!("Linked {} files, freed {} bytes", result.linked_files, result.freed_bytes);
     ///     Ok(())
     /// }
     /// ```
     pub async fn upgrade(&self, name: &str) -> Result<UpgradeResult> {
         let start = Instant::now();
 
-        let installed = crate::cellar::get_installed_versions(name)?;
+        let installed = cellar::get_installed_versions_in(&self.ctx.cellar(), name)?;
         let from_version = installed
             .first()
             .ok_or_else(|| anyhow!("Package '{}' not installed", name))?
@@ -418,17 +365,13 @@ impl PackageManager {
             .clone();
 
         let formula = self
+            .ctx
             .api
             .fetch_formula(name)
             .await
             .map_err(|_| anyhow!("Formula '{}' not found", name))?;
 
-        let to_version = formula
-            .versions
-            .stable
-            .as_ref()
-            .ok_or_else(|| anyhow!("No stable version available for {}", name))?
-            .clone();
+        let to_version = formula.install_version()?.to_string();
 
         // If already at latest version, nothing to do
         if from_version == to_version {
@@ -436,62 +379,28 @@ impl PackageManager {
                 name: formula.name.clone(),
                 from_version,
                 to_version: to_version.clone(),
-                path: cellar_path().join(&formula.name).join(&to_version),
+                path: self.ctx.cellar().join(&formula.name).join(&to_version),
+                linked_files: 0,
+                freed_bytes: 0,
                 time_ms: start.elapsed().as_millis() as u64,
             });
         }
 
-        // Step 1: Download new bottle
-        let bottle_path = download::download_bottle(&formula, None, &self.client)
-            .await
-            .map_err(|e| anyhow!("Failed to download bottle: {}", e))?;
-
-        // Step 2: Extract to Cellar
-        let cellar_dir = extract::extract_bottle(&bottle_path, &formula.name, &to_version)
-            .map_err(|e| anyhow!("Failed to extract bottle: {}", e))?;
-
-        // Step 3: Generate runtime dependencies
-        let runtime_deps: Vec<RuntimeDependency> = formula
-            .dependencies
-            .iter()
-            .enumerate()
-            .map(|(idx, dep_name)| RuntimeDependency {
-                full_name: dep_name.clone(),
-                version: "0.0.0".to_string(),
-                revision: 0,
-                bottle_rebuild: 0,
-                pkg_version: "0.0.0".to_string(),
-                declared_directly: idx == 0,
-            })
-            .collect();
-
-        // Step 4: Create installation receipt for new version
-        let install_receipt = receipt::InstallReceipt::new_bottle(&formula, runtime_deps, true);
-        install_receipt
-            .write(&cellar_dir)
-            .map_err(|e| anyhow!("Failed to write installation receipt: {}", e))?;
-
-        // Step 5: Update symlinks to new version
-        let _linked_files = symlink::link_formula(&formula.name, &to_version)
-            .map_err(|e| anyhow!("Failed to create symlinks: {}", e))?;
-
-        symlink::optlink(&formula.name, &to_version)
-            .map_err(|e| anyhow!("Failed to create opt symlink: {}", e))?;
-
-        // Step 6: Remove old version from Cellar
-        let cellar = cellar_path();
-        let old_formula_path = cellar.join(&formula.name).join(&from_version);
-        if old_formula_path.exists() {
-            let _ = fs::remove_dir_all(&old_formula_path);
-        }
-
-        Ok(UpgradeResult {
-            name: formula.name.clone(),
-            from_version,
-            to_version,
-            path: cellar_dir,
-            time_ms: start.elapsed().as_millis() as u64,
-        })
+        // Download and pour the new version beside the old one, then swap them
+        let bottle_path =
+            download::download_bottle_to(&formula, None, &self.ctx.http, &self.ctx.cache_dir)
+                .await
+                .map_err(|e| anyhow!("Failed to download bottle: {}", e))?;
+        let keg = keg::pour_keg_in(&self.ctx.prefix, &formula, &bottle_path)?;
+        let mut result = keg::upgrade_keg_in(
+            &self.ctx.prefix,
+            &formula,
+            keg,
+            &from_version,
+            &ReceiptOptions::default(),
+        )?;
+        result.time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
     }
 
     /// Reinstall a package (same version, fresh).
@@ -524,7 +433,7 @@ impl PackageManager {
     pub async fn reinstall(&self, name: &str) -> Result<ReinstallResult> {
         let start = Instant::now();
 
-        let versions = crate::cellar::get_installed_versions(name)?;
+        let versions = cellar::get_installed_versions_in(&self.ctx.cellar(), name)?;
         let version = versions
             .first()
             .ok_or_else(|| anyhow!("Package '{}' not installed", name))?
@@ -532,65 +441,28 @@ impl PackageManager {
             .clone();
 
         let formula = self
+            .ctx
             .api
             .fetch_formula(name)
             .await
             .map_err(|_| anyhow!("Formula '{}' not found", name))?;
 
-        // Step 1: Remove old installation
-        symlink::unlink_formula(name, &version)
-            .map_err(|e| anyhow!("Failed to remove symlinks: {}", e))?;
-
-        symlink::unoptlink(name).map_err(|e| anyhow!("Failed to remove opt symlink: {}", e))?;
-
-        let cellar = cellar_path();
-        let formula_path = cellar.join(name).join(&version);
-        fs::remove_dir_all(&formula_path)
-            .map_err(|e| anyhow!("Failed to remove Cellar directory: {}", e))?;
-
-        // Step 2: Download fresh bottle
-        let bottle_path = download::download_bottle(&formula, None, &self.client)
-            .await
-            .map_err(|e| anyhow!("Failed to download bottle: {}", e))?;
-
-        // Step 3: Extract to Cellar
-        let cellar_dir = extract::extract_bottle(&bottle_path, &formula.name, &version)
-            .map_err(|e| anyhow!("Failed to extract bottle: {}", e))?;
-
-        // Step 4: Generate runtime dependencies
-        let runtime_deps: Vec<RuntimeDependency> = formula
-            .dependencies
-            .iter()
-            .enumerate()
-            .map(|(idx, dep_name)| RuntimeDependency {
-                full_name: dep_name.clone(),
-                version: "0.0.0".to_string(),
-                revision: 0,
-                bottle_rebuild: 0,
-                pkg_version: "0.0.0".to_string(),
-                declared_directly: idx == 0,
-            })
-            .collect();
-
-        // Step 5: Create installation receipt
-        let install_receipt = receipt::InstallReceipt::new_bottle(&formula, runtime_deps, true);
-        install_receipt
-            .write(&cellar_dir)
-            .map_err(|e| anyhow!("Failed to write installation receipt: {}", e))?;
-
-        // Step 6: Create symlinks
-        let _linked_files = symlink::link_formula(&formula.name, &version)
-            .map_err(|e| anyhow!("Failed to create symlinks: {}", e))?;
-
-        symlink::optlink(&formula.name, &version)
-            .map_err(|e| anyhow!("Failed to create opt symlink: {}", e))?;
-
-        Ok(ReinstallResult {
-            name: name.to_string(),
-            version,
-            path: cellar_dir,
-            time_ms: start.elapsed().as_millis() as u64,
-        })
+        // Download the bottle before touching the installed keg, so a failed download
+        // leaves it in place
+        let bottle_path =
+            download::download_bottle_to(&formula, None, &self.ctx.http, &self.ctx.cache_dir)
+                .await
+                .map_err(|e| anyhow!("Failed to download bottle: {}", e))?;
+
+        let mut result = keg::reinstall_keg_in(
+            &self.ctx.prefix,
+            &formula,
+            &bottle_path,
+            &version,
+            &ReceiptOptions::default(),
+        )?;
+        result.time_ms = start.elapsed().as_millis() as u64;
+        Ok(result)
     }
 
     /// Search for packages matching a query.
@@ -620,7 +492,10 @@ impl PackageManager {
     /// }
     /// ```
     pub async fn search(&self, query: &str) -> Result<crate::api::SearchResults> {
-        self.api.search(query).await
+        self.ctx
+            .api
+            .search(query, crate::api::SearchScope::default())
+            .await
     }
 
     /// Get information about a package.
@@ -649,7 +524,7 @@ impl PackageManager {
     /// }
     /// ```
     pub async fn info(&self, name: &str) -> Result<Formula> {
-        self.api.fetch_formula(name).await
+        self.ctx.api.fetch_formula(name).await
     }
 
     /// Get dependencies of a package.
@@ -677,7 +552,7 @@ impl PackageManager {
     /// }
     /// ```
     pub async fn dependencies(&self, name: &str) -> Result<Dependencies> {
-        let formula = self.api.fetch_formula(name).await?;
+        let formula = self.ctx.api.fetch_formula(name).await?;
         Ok(Dependencies {
             name: formula.name.clone(),
             runtime: formula.dependencies.clone(),
@@ -696,10 +571,10 @@ impl PackageManager {
     /// List of formula names that depend on this one.
     pub async fn uses(&self, name: &str) -> Result<Vec<String>> {
         // Validate package exists
-        let _formula = self.api.fetch_formula(name).await?;
+        let _formula = self.ctx.api.fetch_formula(name).await?;
 
         // Fetch all formulae and filter
-        let all = self.api.fetch_all_formulae().await?;
+        let all = self.ctx.api.fetch_all_formulae_brief().await?;
         let dependents: Vec<String> = all
             .into_iter()
             .filter(|f| {
@@ -734,7 +609,7 @@ impl PackageManager {
     /// }
     /// ```
     pub fn list(&self) -> Result<Vec<InstalledPackage>> {
-        Ok(list_installed()?)
+        Ok(cellar::list_installed_in(&self.ctx.cellar())?)
     }
 
     /// Find outdated packages.
@@ -761,14 +636,14 @@ impl PackageManager {
     /// }
     /// ```
     pub async fn outdated(&self) -> Result<Vec<OutdatedPackage>> {
-        let installed = list_installed()?;
+        let installed = cellar::list_current_in(&self.ctx.prefix)?;
 
         let mut result = Vec::new();
         for pkg in installed {
-            match self.api.fetch_formula(&pkg.name).await {
+            match self.ctx.api.fetch_formula(&pkg.name).await {
                 Ok(formula) => {
                     if let Some(latest) = formula.versions.stable
-                        && latest > pkg.version
+                        && cellar::compare_versions(&latest, &pkg.version).is_gt()
                     {
                         result.push(OutdatedPackage {
                             name: pkg.name,
@@ -787,18 +662,19 @@ impl PackageManager {
         Ok(result)
     }
 
-    /// Clean up old package versions.
+    /// Clean up old formula and cask versions.
     ///
-    /// Removes all but the most recent version of each installed package.
-    /// This frees up disk space from older versions that are no longer needed.
+    /// Keeps the newest version of each formula (plus the linked one, if older) and the
+    /// most recently installed version of each cask; everything else is removed.
     ///
     /// # Arguments
     ///
+    /// * `names` - Formulae and casks to clean up, or an empty slice for everything installed
     /// * `dry_run` - If true, only report what would be removed without actually removing
     ///
     /// # Returns
     ///
-    /// Cleanup result with removed packages and space freed.
+    /// Cleanup result with the removed versions and the space freed per package and in total.
     ///
     /// # Examples
     ///
@@ -809,87 +685,28 @@ impl PackageManager {
     ///     let pm = PackageManager::new()?;
     ///
     ///     // Dry run: see what would be removed
-    ///     let result = pm.cleanup(true)?;
+    ///     let result = pm.cleanup(&[], true)?;
     ///     println!("Would remove {} versions", result.removed.len());
-    ///     println!("Would free {:.1} MB", result.space_freed_mb);
+    ///     println!("Would free {} bytes", result.total_freed_bytes);
     ///
     ///     // Actually clean up
-    ///     let result = pm.cleanup(false)?;
-    ///     println!("Removed {} versions", result.removed.len());
-    ///     println!("Freed {:.1} MB", result.space_freed_mb);
+    ///     let result = pm.cleanup(&[], false)?;
+    ///     for (name, bytes) in &result.freed_bytes {
+    ///         println!("{}: freed {} bytes", name, bytes);
+    ///     }
     ///
     ///     Ok(())
     /// }
     /// ```
-    pub fn cleanup(&self, dry_run: bool) -> Result<CleanupResult> {
-        let mut removed = Vec::new();
-        let mut space_freed_mb = 0.0;
-        let mut errors = Vec::new();
-
-        let installed = list_installed()?;
-        let cellar = cellar_path();
-
-        // Group by formula name
-        let mut by_formula: std::collections::HashMap<String, Vec<InstalledPackage>> =
-            std::collections::HashMap::new();
-        for pkg in installed {
-            by_formula.entry(pkg.name.clone()).or_default().push(pkg);
-        }
-
-        // For each formula, remove all but the newest version
-        for (formula_name, mut versions) in by_formula {
-            if versions.len() <= 1 {
-                continue;
-            }
-
-            // Sort by version (newest first)
-            versions.sort_by(|a, b| cellar::compare_versions(&b.version, &a.version));
-
-            // Remove all but the first (newest)
-            for pkg in &versions[1..] {
-                let path = cellar.join(&formula_name).join(&pkg.version);
-                let size_mb = Self::dir_size(&path)? as f64 / (1024.0 * 1024.0);
-
-                if !dry_run {
-                    match fs::remove_dir_all(&path) {
-                        Ok(_) => {
-                            removed.push(format!("{}/{}", formula_name, pkg.version));
-                            space_freed_mb += size_mb;
-                        }
-                        Err(e) => {
-                            errors
-                                .push((format!("{}/{}", formula_name, pkg.version), e.to_string()));
-                        }
-                    }
-                } else {
-                    removed.push(format!("{}/{}", formula_name, pkg.version));
-                    space_freed_mb += size_mb;
-                }
-            }
-        }
-
-        Ok(CleanupResult {
-            removed,
-            space_freed_mb,
-            errors,
-        })
+    pub fn cleanup(&self, names: &[String], dry_run: bool) -> Result<CleanupResult> {
+        cleanup::cleanup_in(&self.ctx.prefix, names, dry_run)
     }
 
-    /// Calculate directory size in bytes
-    fn dir_size(path: &PathBuf) -> Result<u64> {
-        let mut size = 0u64;
-        if path.is_dir() {
-            for entry in fs::read_dir(path)? {
-                let entry = entry?;
-                let metadata = entry.metadata()?;
-                size += if metadata.is_dir() {
-                    Self::dir_size(&entry.path())?
-                } else {
-                    metadata.len()
-                };
-            }
-        }
-        Ok(size)
+    /// Clean up old cask versions only.
+    ///
+    /// Same as [`cleanup`](Self::cleanup), but leaves the Cellar untouched.
+    pub fn cleanup_casks(&self, names: &[String], dry_run: bool) -> Result<CleanupResult> {
+        cleanup::cleanup_casks_in(&self.ctx.prefix, names, dry_run)
     }
 
     /// Check system health.
@@ -898,8 +715,8 @@ impl PackageManager {
     ///
     /// Health check result with issues if any.
     pub fn check(&self) -> Result<HealthCheck> {
-        let prefix = detect_prefix();
-        let cellar = cellar_path();
+        let prefix = &self.ctx.prefix;
+        let cellar = self.ctx.cellar();
 
         let mut issues = Vec::new();
 
@@ -925,29 +742,158 @@ impl PackageManager {
 
     /// Get the Homebrew prefix path.
     pub fn prefix(&self) -> PathBuf {
-        detect_prefix()
+        self.ctx.prefix.clone()
     }
 
     /// Get the Cellar path.
     pub fn cellar(&self) -> PathBuf {
-        cellar_path()
+        self.ctx.cellar()
     }
 
     /// Get reference to the underlying API client.
     ///
     /// For advanced use cases that need direct API access.
     pub fn api(&self) -> &BrewApi {
-        &self.api
+        &self.ctx.api
     }
 
     /// Get reference to the underlying HTTP client.
     ///
     /// For advanced use cases that need direct HTTP access.
     pub fn client(&self) -> &reqwest::Client {
-        &self.client
+        &self.ctx.http
+    }
+
+    /// Get reference to the context this manager operates on.
+    pub fn context(&self) -> &BrewContext {
+        &self.ctx
     }
 }
 
+/// Pour the bottle of an already-fetched formula into the context's prefix.
+///
+/// Extracts the bottle into the Cellar, relocates its placeholders, writes the install
+/// receipt, and links the keg. Dependencies are not resolved or installed; this is the
+/// single-formula step that [`PackageManager::install`] and multi-formula installers
+/// build on. The receipt records the direct dependencies that are installed in the
+/// same Cellar.
+///
+/// # Errors
+///
+/// Returns an error if the formula has no stable version, or the download, extraction,
+/// relocation, receipt, or linking fails. A keg that was extracted is left in place.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::{BrewContext, InstallOptions, install_bottle};
+///
+/// #[tokio::main]
+/// async fn main() -> anyhow::Result<()> {
+///     let ctx = BrewContext::new()?;
+///     let formula = ctx.api.fetch_formula("jq").await?;
+///     let options = InstallOptions {
+///         bottle: Some("jq--1.7.1.arm64_sonoma.bottle.tar.gz".into()),
+///         ..Default::default()
+///     };
+///     let result = install_bottle(&ctx, &formula, &options).await?;
+///     println!("Installed {} {} to {}", result.name, result.version, result.path.display());
+///     Ok(())
+/// }
+/// ```
+pub async fn install_bottle(
+    ctx: &BrewContext,
+    formula: &Formula,
+    options: &InstallOptions,
+) -> Result<InstallResult> {
+    let start = Instant::now();
+    let version = formula.install_version()?;
+    let cellar = ctx.cellar();
+
+    // Step 1: Download bottle (unless one was given)
+    let bottle_path = match &options.bottle {
+        Some(path) => path.clone(),
+        None => download::download_bottle_to(formula, None, &ctx.http, &ctx.cache_dir)
+            .await
+            .map_err(|e| anyhow!("Failed to download bottle: {}", e))?,
+    };
+
+    // Step 2: Extract to Cellar, noting the kegs that were already there
+    let kegs_before: Vec<PathBuf> = fs::read_dir(cellar.join(&formula.name))
+        .map(|entries| entries.flatten().map(|entry| entry.path()).collect())
+        .unwrap_or_default();
+    let (cellar_dir, manifest) =
+        extract::extract_bottle_with_manifest_in(&cellar, &bottle_path, &formula.name, version)
+            .map_err(|e| anyhow!("Failed to extract bottle: {}", e))?;
+    // The keg directory carries any bottle revision (e.g. 1.7.1_1)
+    let version = cellar_dir
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(version)
+        .to_string();
+
+    // Steps 3-5; a keg this call created and left half-finished by a failure is
+    // removed again, while one that was already installed stays
+    let linked = match finish_keg(ctx, formula, options, &cellar_dir, &version, &manifest) {
+        Ok(linked) => linked,
+        Err(e) if kegs_before.contains(&cellar_dir) => return Err(e),
+        Err(e) => {
+            let _ = symlink::unlink_formula_in(&ctx.prefix, &formula.name, &version);
+            let _ = fs::remove_dir_all(&cellar_dir);
+            // Only succeeds once no other version is left
+            if let Some(formula_dir) = cellar_dir.parent() {
+                let _ = fs::remove_dir(formula_dir);
+            }
+            return Err(e);
+        }
+    };
+
+    Ok(InstallResult {
+        name: formula.name.clone(),
+        version,
+        path: cellar_dir,
+        dependencies: formula.dependencies.clone(),
+        linked,
+        time_ms: start.elapsed().as_millis() as u64,
+    })
+}
+
+/// Relocate, record and link a freshly extracted keg; returns whether it was linked
+fn finish_keg(
+    ctx: &BrewContext,
+    formula: &Formula,
+    options: &InstallOptions,
+    cellar_dir: &Path,
+    version: &str,
+    manifest: &[PathBuf],
+) -> Result<bool> {
+    let cellar = ctx.cellar();
+
+    // Step 3: Replace @@HOMEBREW_PREFIX@@/@@HOMEBREW_CELLAR@@ placeholders
+    relocate::relocate_bottle_with_manifest(cellar_dir, &ctx.prefix, manifest)
+        .map_err(|e| anyhow!("Failed to relocate bottle: {}", e))?;
+
+    // Step 4: Create installation receipt
+    let runtime_deps = keg::installed_runtime_dependencies(&cellar, formula);
+    receipt::InstallReceipt::new_bottle(formula, runtime_deps, options.on_request)
+        .write(cellar_dir)
+        .map_err(|e| anyhow!("Failed to write installation receipt: {}", e))?;
+
+    // Step 5: Create symlinks
+    let linked = options.link && !formula.keg_only;
+    if linked {
+        symlink::link_formula_with_manifest_in(&ctx.prefix, &formula.name, version, manifest)
+            .map_err(|e| anyhow!("Failed to create symlinks: {}", e))?;
+    } else {
+        symlink::install_config_files_in(&ctx.prefix, &formula.name, version)
+            .map_err(|e| anyhow!("Failed to install config files: {}", e))?;
+    }
+    symlink::optlink_in(&ctx.prefix, &formula.name, version)
+        .map_err(|e| anyhow!("Failed to create opt symlink: {}", e))?;
+
+    Ok(linked)
+}
+
 impl Default for PackageManager {
     fn default() -> Self {
         Self::new().expect("Failed to create PackageManager")
@@ -8,9 +8,10 @@
 //!
 //! # Bottle Tag Format
 //!
-//! Bottles are named with platform tags like `arm64_sequoia` or `x86_64_sonoma`:
-//! - `<arch>_<os_version>`
-//! - Examples: `arm64_sequoia`, `x86_64_ventura`, `arm64_linux`
+//! Bottles are named with platform tags like `arm64_sequoia` or `sonoma`:
+//! - `arm64_<macos_name>` on Apple Silicon, the bare `<macos_name>` on Intel Macs
+//! - `<arch>_linux` on Linux
+//! - Examples: `arm64_sequoia`, `ventura`, `x86_64_linux`
 //!
 //! If an exact platform bottle isn't available, Homebrew falls back to universal
 //! bottles tagged as `all`.
@@ -24,7 +25,7 @@
 //!     let bottle_tag = platform::detect_bottle_tag()?;
 //!     println!("This system needs: {}", bottle_tag);
 //!     // Output: "arm64_sequoia" on M3 Mac with macOS 15
-//!     // Output: "x86_64_ventura" on Intel Mac with macOS 13
+//!     // Output: "ventura" on Intel Mac with macOS 13
 //!
 //!     Ok(())
 //! }
@@ -45,7 +46,7 @@ use std::process::Command;
 ///
 /// Examples of returned tags:
 /// - `arm64_sequoia` - Apple Silicon (M1+) on macOS 15
-/// - `x86_64_ventura` - Intel on macOS 13
+/// - `ventura` - Intel on macOS 13
 /// - `arm64_linux` - ARM64 Linux
 /// - `x86_64_linux` - x86_64 Linux
 ///
@@ -68,7 +69,7 @@ use std::process::Command;
 ///     let tag = platform::detect_bottle_tag()?;
 ///     println!("Bottle tag: {}", tag);
 ///     // Output: "arm64_sequoia" (on M3 Mac)
-///     // Output: "x86_64_ventura" (on Intel Mac)
+///     // Output: "ventura" (on Intel Mac)
 ///
 ///     Ok(())
 /// }
@@ -80,30 +81,147 @@ use std::process::Command;
 /// tagged as `all`. This function returns the preferred tag, not the fallback.
 pub fn detect_bottle_tag() -> Result<String> {
     #[cfg(target_os = "macos")]
-    {
-        // Homebrew uses "arm64" not "aarch64"
-        let arch = match std::env::consts::ARCH {
-            "aarch64" => "arm64",
-            other => other,
-        };
-        let os_version = macos_version()?;
-        let os_name = macos_name(&os_version);
-
-        Ok(format!("{}_{}", arch, os_name))
+    let os_version = Some(macos_version()?);
+    #[cfg(not(target_os = "macos"))]
+    let os_version: Option<String> = None;
+
+    bottle_tag_for(
+        std::env::consts::OS,
+        std::env::consts::ARCH,
+        os_version.as_deref(),
+    )
+}
+
+/// Get the bottle tag to install, honoring a `HOMEBREW_BOTTLE_TAG` override.
+///
+/// Returns the override when it is set (`bru install --bottle-tag <tag>` sets it), and
+/// the [detected](detect_bottle_tag) tag otherwise. The override is meant for testing
+/// bottles for other platforms and for working around a misdetected macOS release.
+///
+/// # Errors
+///
+/// Returns an error if there is no override and the platform cannot be detected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::platform;
+///
+/// fn main() -> anyhow::Result<()> {
+///     // HOMEBREW_BOTTLE_TAG=arm64_sonoma -> "arm64_sonoma"
+///     println!("Installing {} bottles", platform::bottle_tag()?);
+///     Ok(())
+/// }
+/// ```
+pub fn bottle_tag() -> Result<String> {
+    match std::env::var("HOMEBREW_BOTTLE_TAG") {
+        Ok(tag) if !tag.trim().is_empty() => Ok(tag.trim().to_string()),
+        _ => detect_bottle_tag(),
+    }
+}
+
+/// Compute the bottle tag for an OS, architecture, and (on macOS) product version.
+///
+/// `os` and `arch` use Rust's [`std::env::consts`] names (`"macos"`, `"linux"`,
+/// `"aarch64"`, `"x86_64"`); `macos_version` is the `sw_vers -productVersion` string.
+///
+/// # Errors
+///
+/// Returns an error for operating systems Homebrew has no bottles for.
+///
+/// # Examples
+///
+/// ```
+/// use kombrucha::platform;
+///
+/// assert_eq!(
+///     platform::bottle_tag_for("macos", "aarch64", Some("15.1")).unwrap(),
+///     "arm64_sequoia"
+/// );
+/// assert_eq!(
+///     platform::bottle_tag_for("macos", "x86_64", Some("15.1")).unwrap(),
+///     "sequoia"
+/// );
+/// assert_eq!(
+///     platform::bottle_tag_for("linux", "x86_64", None).unwrap(),
+///     "x86_64_linux"
+/// );
+/// ```
+pub fn bottle_tag_for(os: &str, arch: &str, macos_version: Option<&str>) -> Result<String> {
+    // Homebrew uses "arm64" not "aarch64"
+    let arch = match arch {
+        "aarch64" => "arm64",
+        other => other,
+    };
+
+    match os {
+        // Intel Mac bottles are tagged with the bare release name
+        "macos" if arch == "arm64" => Ok(format!(
+            "arm64_{}",
+            macos_name(macos_version.unwrap_or_default())
+        )),
+        "macos" => Ok(macos_name(macos_version.unwrap_or_default()).to_string()),
+        "linux" => Ok(format!("{}_linux", arch)),
+        _ => anyhow::bail!("Unsupported platform"),
+    }
+}
+
+/// Whether a bottle built for `bottle_tag` runs on a system that installs `tag` bottles.
+///
+/// Besides an exact match and universal `all` bottles, a macOS bottle runs on the same
+/// architecture's later releases, as Homebrew falls back to bottles for older macOS.
+///
+/// # Examples
+///
+/// ```
+/// use kombrucha::platform;
+///
+/// assert!(platform::bottle_runs_on("arm64_ventura", "arm64_sequoia"));
+/// assert!(platform::bottle_runs_on("all", "x86_64_linux"));
+/// assert!(!platform::bottle_runs_on("arm64_sequoia", "arm64_ventura"));
+/// assert!(!platform::bottle_runs_on("ventura", "arm64_sequoia"));
+/// ```
+pub fn bottle_runs_on(bottle_tag: &str, tag: &str) -> bool {
+    if bottle_tag == tag || bottle_tag == "all" {
+        return true;
     }
+    match (macos_tag(bottle_tag), macos_tag(tag)) {
+        (Some((bottle_arch, bottle_major)), Some((arch, major))) => {
+            bottle_arch == arch && bottle_major <= major
+        }
+        _ => false,
+    }
+}
+
+/// Split a macOS bottle tag into its architecture and major release
+/// (`arm64_sonoma` → `("arm64", 14)`, `ventura` → `("x86_64", 13)`)
+fn macos_tag(tag: &str) -> Option<(&'static str, u32)> {
+    let (arch, name) = match tag.strip_prefix("arm64_") {
+        Some(name) => ("arm64", name),
+        None => ("x86_64", tag),
+    };
+    let major = match name {
+        "tahoe" => 26,
+        "sequoia" => 15,
+        "sonoma" => 14,
+        "ventura" => 13,
+        "monterey" => 12,
+        "big_sur" => 11,
+        _ => return None,
+    };
+    Some((arch, major))
+}
 
-    #[cfg(target_os = "linux")]
+/// Get the running macOS product version (e.g. `"15.1"`), or `None` on other platforms.
+pub fn current_macos_version() -> Option<String> {
+    #[cfg(target_os = "macos")]
     {
-        let arch = match std::env::consts::ARCH {
-            "aarch64" => "arm64",
-            other => other,
-        };
-        Ok(format!("{}_linux", arch))
+        macos_version().ok()
     }
 
-    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    #[cfg(not(target_os = "macos"))]
     {
-        anyhow::bail!("Unsupported platform")
+        None
     }
 }
 
@@ -122,7 +240,6 @@ fn macos_version() -> Result<String> {
     Ok(version)
 }
 
-#[cfg(target_os = "macos")]
 fn macos_name(version: &str) -> &'static str {
     // Parse major version
     let major: u32 = version
@@ -153,11 +270,24 @@ mod tests {
         assert!(!tag.is_empty());
         #[cfg(target_arch = "aarch64")]
         assert!(tag.starts_with("arm64_"));
-        #[cfg(target_arch = "x86_64")]
-        assert!(tag.starts_with("x86_64_"));
+        #[cfg(all(target_arch = "x86_64", target_os = "linux"))]
+        assert_eq!(tag, "x86_64_linux");
+        #[cfg(all(target_arch = "x86_64", target_os = "macos"))]
+        assert!(!tag.starts_with("x86_64_"));
+    }
+
+    #[test]
+    fn test_bottle_runs_on_older_macos() {
+        assert!(bottle_runs_on("arm64_sonoma", "arm64_sonoma"));
+        assert!(bottle_runs_on("arm64_big_sur", "arm64_tahoe"));
+        assert!(bottle_runs_on("monterey", "sonoma"));
+        assert!(bottle_runs_on("all", "arm64_sonoma"));
+        assert!(!bottle_runs_on("sequoia", "sonoma"));
+        assert!(!bottle_runs_on("arm64_sonoma", "sonoma"));
+        assert!(!bottle_runs_on("arm64_sonoma", "arm64_linux"));
+        assert!(!bottle_runs_on("x86_64_linux", "arm64_linux"));
     }
 
-    #[cfg(target_os = "macos")]
     #[test]
     fn test_macos_names() {
         assert_eq!(macos_name("15.1"), "sequoia");
@@ -165,4 +295,49 @@ mod tests {
         assert_eq!(macos_name("13.0"), "ventura");
         assert_eq!(macos_name("12.0"), "monterey");
     }
+
+    #[test]
+    fn test_bottle_tag_matrix() {
+        let cases = [
+            ("macos", "aarch64", Some("26.0"), "arm64_tahoe"),
+            ("macos", "aarch64", Some("15.1"), "arm64_sequoia"),
+            ("macos", "aarch64", Some("14.7.1"), "arm64_sonoma"),
+            ("macos", "aarch64", Some("13.6"), "arm64_ventura"),
+            ("macos", "aarch64", Some("12.7"), "arm64_monterey"),
+            ("macos", "aarch64", Some("11.7"), "arm64_big_sur"),
+            ("macos", "x86_64", Some("15.1"), "sequoia"),
+            ("macos", "x86_64", Some("14.7.1"), "sonoma"),
+            ("macos", "x86_64", Some("13.6"), "ventura"),
+            ("macos", "x86_64", Some("12.7"), "monterey"),
+            ("macos", "x86_64", Some("11.7"), "big_sur"),
+            ("linux", "x86_64", None, "x86_64_linux"),
+            ("linux", "aarch64", None, "arm64_linux"),
+        ];
+
+        for (os, arch, version, expected) in cases {
+            assert_eq!(
+                bottle_tag_for(os, arch, version).unwrap(),
+                expected,
+                "{os} {arch} {version:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_bottle_tag_for_unknown_macos_release() {
+        // Unrecognized or unreadable versions fall back to a recent compatible release
+        assert_eq!(
+            bottle_tag_for("macos", "aarch64", Some("99.0")).unwrap(),
+            "arm64_sonoma"
+        );
+        assert_eq!(
+            bottle_tag_for("macos", "aarch64", None).unwrap(),
+            "arm64_sonoma"
+        );
+    }
+
+    #[test]
+    fn test_bottle_tag_for_unsupported_os() {
+        assert!(bottle_tag_for("windows", "x86_64", None).is_err());
+    }
 }
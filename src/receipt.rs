@@ -49,64 +49,63 @@ use std::fs;
 use std::path::Path;
 
 /// Install receipt compatible with Homebrew
-#[derive(Debug, Serialize, Deserialize)]
+///
+/// Every field is optional when reading, and fields bru doesn't model are kept in
+/// `extra`, so any receipt brew writes can be read and written back unchanged. Keys
+/// brew always writes are written as `null` when unset, like brew does.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct InstallReceipt {
     pub homebrew_version: String,
-    #[serde(default)]
     pub used_options: Vec<String>,
-    #[serde(default)]
     pub unused_options: Vec<String>,
     pub built_as_bottle: bool,
+    /// A receipt that doesn't say counts as poured from a bottle
+    #[serde(default = "poured_by_default")]
     pub poured_from_bottle: bool,
     pub loaded_from_api: bool,
     pub installed_as_dependency: bool,
     pub installed_on_request: bool,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub changed_files: Option<Vec<String>>,
     pub time: i64,
-    #[serde(default)]
     pub source_modified_time: i64,
-    #[serde(default)]
     pub compiler: Option<String>,
-    #[serde(default)]
     pub aliases: Vec<String>,
-    #[serde(default)]
     pub runtime_dependencies: Vec<RuntimeDependency>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub source: Option<SourceInfo>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub arch: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub built_on: Option<BuiltOn>,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub stdlib: Option<String>,
+    /// Fields from newer or older Homebrew versions that bru doesn't model
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SourceInfo {
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub path: Option<String>,
-    pub tap: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    /// `None` for formula and bottle files that aren't in any tap
+    pub tap: Option<String>,
     pub tap_git_head: Option<String>,
     pub spec: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub versions: Option<SourceVersions>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SourceVersions {
-    #[serde(skip_serializing_if = "Option::is_none")]
     pub stable: Option<String>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub head: Option<String>,
-    #[serde(default)]
     pub version_scheme: u32,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub compatibility_version: Option<String>,
+    /// `head`, `compatibility_version` and other fields not every brew version writes
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BuiltOn {
     pub os: String,
     pub os_version: String,
@@ -117,6 +116,12 @@ pub struct BuiltOn {
     pub clt: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub preferred_perl: Option<String>,
+    #[serde(flatten)]
+    pub extra: serde_json::Map<String, serde_json::Value>,
+}
+
+fn poured_by_default() -> bool {
+    true
 }
 
 impl InstallReceipt {
@@ -140,7 +145,7 @@ impl InstallReceipt {
     /// Returns an error if:
     /// - The `INSTALL_RECEIPT.json` file doesn't exist in the cellar directory
     /// - The file cannot be read (permission denied, etc.)
-    /// - The JSON is malformed or incompatible with the receipt schema
+    /// - The file isn't valid JSON (missing or unknown fields are fine)
     ///
     /// # Examples
     ///
@@ -160,7 +165,7 @@ impl InstallReceipt {
     ///
     ///     // Check source information
     ///     if let Some(source) = &receipt.source {
-    ///         println!("From tap: {}", source.tap);
+    ///         println!("From tap: {}", source.tap.as_deref().unwrap_or("(none)"));
     ///         if let Some(stable) = &source.versions {
     ///             println!("Stable version: {:?}", stable.stable);
     ///         }
@@ -216,7 +221,8 @@ impl InstallReceipt {
     /// - Build environment detected from the system
     /// - Architecture detected from current system
     /// - Provided runtime dependencies
-    /// - Source information (tap: "homebrew/core", spec: "stable")
+    /// - Source information (spec: "stable"; tap from [`Formula::tap`], with the tap's
+    ///   formula file as `path` for formulae outside homebrew/core)
     ///
     /// # Examples
     ///
@@ -240,6 +246,7 @@ impl InstallReceipt {
     ///             bottle_rebuild: 0,
     ///             pkg_version: "1.0.0".to_string(),
     ///             declared_directly: idx == 0,
+    ///             extra: Default::default(),
     ///         })
     ///         .collect();
     ///
@@ -260,7 +267,7 @@ impl InstallReceipt {
     /// - `homebrew_version`: Set to `"bru/{version}"` (Kombrucha version)
     /// - `built_as_bottle`: Always `true` (this is for bottle installations)
     /// - `poured_from_bottle`: Always `true` (indicates precompiled installation)
-    /// - `loaded_from_api`: Always `true` (metadata came from Homebrew API)
+    /// - `loaded_from_api`: `true` unless the formula came from a tap other than homebrew/core
     /// - `installed_as_dependency`: Set to the opposite of `installed_on_request`
     /// - `time`: Current Unix timestamp
     /// - `arch`: Detected system architecture ("arm64" or "x86_64")
@@ -275,13 +282,28 @@ impl InstallReceipt {
             .unwrap()
             .as_secs() as i64;
 
+        // Tap formulae are read from the tap's Ruby file, core ones from the API
+        let from_tap = formula.tap() != "homebrew/core";
+        let source_path = if from_tap {
+            crate::tap::split_qualified(&formula.full_name)
+                .and_then(|(tap, name)| {
+                    crate::tap::formula_file_in(&crate::cellar::detect_prefix(), &tap, name)
+                })
+                .map(|path| path.display().to_string())
+        } else {
+            Some(format!(
+                "{}/Library/Caches/Homebrew/api/formula.jws.json",
+                std::env::var("HOME").unwrap_or_else(|_| "/Users/USER".to_string())
+            ))
+        };
+
         Self {
             homebrew_version: format!("bru/{}", env!("CARGO_PKG_VERSION")),
             used_options: vec![],
             unused_options: vec![],
             built_as_bottle: true,
             poured_from_bottle: true,
-            loaded_from_api: true,
+            loaded_from_api: !from_tap,
             installed_as_dependency: !installed_on_request,
             installed_on_request,
             changed_files: Some(vec![]),
@@ -291,23 +313,21 @@ impl InstallReceipt {
             aliases: vec![],
             runtime_dependencies: runtime_deps,
             source: Some(SourceInfo {
-                path: Some(format!(
-                    "{}/Library/Caches/Homebrew/api/formula.jws.json",
-                    std::env::var("HOME").unwrap_or_else(|_| "/Users/USER".to_string())
-                )),
-                tap: "homebrew/core".to_string(),
+                path: source_path,
+                tap: Some(formula.tap().to_string()),
                 tap_git_head: None,
                 spec: "stable".to_string(),
                 versions: Some(SourceVersions {
                     stable: formula.versions.stable.clone(),
-                    head: None,
                     version_scheme: 0,
-                    compatibility_version: None,
+                    extra: Default::default(),
                 }),
+                extra: Default::default(),
             }),
             arch: Some(homebrew_arch().to_string()),
             built_on: detect_build_environment(),
             stdlib: Some("libc++".to_string()),
+            extra: Default::default(),
         }
     }
 
@@ -396,6 +416,45 @@ impl InstallReceipt {
 
         Ok(())
     }
+
+    /// Whether the keg was built from source instead of poured from a bottle.
+    ///
+    /// Reinstalls and upgrades keep that choice by going through `brew`. A receipt
+    /// that doesn't record how the keg was installed counts as a bottle.
+    pub fn built_from_source(&self) -> bool {
+        !self.poured_from_bottle
+    }
+
+    /// Record that `brew` built the keg at `cellar_path` from source on bru's behalf.
+    ///
+    /// `brew install` marks everything it installs as requested, so the dependency flags
+    /// are rewritten to match how bru actually pulled the formula in.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receipt cannot be read or written.
+    pub fn mark_built_from_source(cellar_path: &Path, installed_on_request: bool) -> Result<()> {
+        let mut receipt = Self::read(cellar_path)?;
+        receipt.poured_from_bottle = false;
+        receipt.installed_on_request = installed_on_request;
+        receipt.installed_as_dependency = !installed_on_request;
+        receipt.write(cellar_path)
+    }
+
+    /// Record that the keg at `cellar_path` came from a bottle or formula file outside
+    /// any tap (`formula_path`, when it was a formula file), not from the API.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the receipt cannot be read or written.
+    pub fn mark_untapped(cellar_path: &Path, formula_path: Option<&Path>) -> Result<()> {
+        let mut receipt = Self::read(cellar_path)?;
+        receipt.loaded_from_api = false;
+        let source = receipt.source.get_or_insert_with(SourceInfo::default);
+        source.tap = None;
+        source.path = formula_path.map(|path| path.display().to_string());
+        receipt.write(cellar_path)
+    }
 }
 
 /// Convert Rust target architecture to Homebrew platform name
@@ -428,6 +487,7 @@ fn detect_build_environment() -> Option<BuiltOn> {
             xcode: None,
             clt: None,
             preferred_perl: Some("5.34".to_string()),
+            extra: Default::default(),
         })
     }
 
@@ -436,3 +496,128 @@ fn detect_build_environment() -> Option<BuiltOn> {
         None
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::BREW_RECEIPT;
+
+    #[test]
+    fn test_read_brew_written_receipt() {
+        let keg = tempfile::TempDir::new().unwrap();
+        fs::write(keg.path().join("INSTALL_RECEIPT.json"), BREW_RECEIPT).unwrap();
+
+        let receipt = InstallReceipt::read(keg.path()).unwrap();
+        assert_eq!(receipt.homebrew_version, "4.4.4-76-g40f4ab2");
+        assert!(receipt.installed_on_request);
+        assert_eq!(receipt.compiler.as_deref(), Some("clang"));
+        assert_eq!(receipt.runtime_dependencies[1].full_name, "gettext");
+        let source = receipt.source.as_ref().unwrap();
+        assert_eq!(source.tap.as_deref(), Some("homebrew/core"));
+        assert_eq!(
+            source.versions.as_ref().unwrap().stable.as_deref(),
+            Some("1.25.0")
+        );
+        let built_on = receipt.built_on.as_ref().unwrap();
+        assert_eq!(built_on.xcode.as_deref(), Some("16.1"));
+
+        // Written back unchanged, except that dependencies gain a `bottle_rebuild`
+        receipt.write(keg.path()).unwrap();
+        let written: serde_json::Value = serde_json::from_str(
+            &fs::read_to_string(keg.path().join("INSTALL_RECEIPT.json")).unwrap(),
+        )
+        .unwrap();
+        let original: serde_json::Value = serde_json::from_str(BREW_RECEIPT).unwrap();
+        for (key, value) in original.as_object().unwrap() {
+            if key != "runtime_dependencies" {
+                assert_eq!(&written[key], value, "{}", key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unknown_and_null_fields_round_trip() {
+        // Fields a newer (or older) brew writes that bru doesn't model, at every level
+        let mut receipt: serde_json::Value = serde_json::from_str(BREW_RECEIPT).unwrap();
+        receipt["bottle_manifest_digest"] = "sha256:0c1d2e3f".into();
+        receipt["stdlib"] = serde_json::Value::Null;
+        receipt["runtime_dependencies"][0]["bottle_rebuild"] = 1.into();
+        receipt["runtime_dependencies"][0]["build_flags"] = serde_json::json!([]);
+        receipt["source"]["tap_git_head"] = serde_json::Value::Null;
+        receipt["source"]["versions"]["head"] = serde_json::Value::Null;
+        receipt["source"]["versions"]["compatibility_version"] = serde_json::Value::Null;
+        receipt["built_on"]["glibc_version"] = serde_json::Value::Null;
+
+        let keg = tempfile::TempDir::new().unwrap();
+        let path = keg.path().join("INSTALL_RECEIPT.json");
+        fs::write(&path, receipt.to_string()).unwrap();
+        InstallReceipt::read(keg.path())
+            .unwrap()
+            .write(keg.path())
+            .unwrap();
+        let written: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+
+        assert_eq!(written["bottle_manifest_digest"], "sha256:0c1d2e3f");
+        assert_eq!(written["runtime_dependencies"][0]["bottle_rebuild"], 1);
+        assert_eq!(
+            written["runtime_dependencies"][0]["build_flags"],
+            serde_json::json!([])
+        );
+        for (object, key) in [
+            (&written, "stdlib"),
+            (&written["source"], "tap_git_head"),
+            (&written["source"]["versions"], "head"),
+            (&written["source"]["versions"], "compatibility_version"),
+            (&written["built_on"], "glibc_version"),
+        ] {
+            assert_eq!(
+                object.as_object().unwrap().get(key),
+                Some(&serde_json::Value::Null),
+                "{}",
+                key
+            );
+        }
+    }
+
+    #[test]
+    fn test_mark_built_from_source() {
+        let keg = tempfile::TempDir::new().unwrap();
+        fs::write(keg.path().join("INSTALL_RECEIPT.json"), BREW_RECEIPT).unwrap();
+        assert!(
+            !InstallReceipt::read(keg.path())
+                .unwrap()
+                .built_from_source()
+        );
+
+        // Pulled in as a dependency, but brew recorded it as requested
+        InstallReceipt::mark_built_from_source(keg.path(), false).unwrap();
+
+        let receipt = InstallReceipt::read(keg.path()).unwrap();
+        assert!(receipt.built_from_source());
+        assert!(!receipt.poured_from_bottle);
+        assert!(receipt.installed_as_dependency);
+        assert!(!receipt.installed_on_request);
+        assert_eq!(receipt.runtime_dependencies[0].full_name, "libunistring");
+    }
+
+    #[test]
+    fn test_read_minimal_receipt() {
+        // Old or hand-written receipts may carry only a few fields
+        let keg = tempfile::TempDir::new().unwrap();
+        fs::write(
+            keg.path().join("INSTALL_RECEIPT.json"),
+            r#"{"installed_on_request": true, "runtime_dependencies": [{"full_name": "pcre2", "version": "10.44"}]}"#,
+        )
+        .unwrap();
+
+        let receipt = InstallReceipt::read(keg.path()).unwrap();
+        assert!(receipt.installed_on_request);
+        assert_eq!(receipt.homebrew_version, "");
+        assert_eq!(receipt.runtime_dependencies[0].version, "10.44");
+        assert!(receipt.source.is_none());
+        // Nothing says it was built from source
+        assert!(receipt.poured_from_bottle);
+        assert!(!receipt.built_from_source());
+    }
+}
@@ -14,7 +14,30 @@ use walkdir::WalkDir;
 ///
 /// This replaces placeholder paths in binaries, libraries, and scripts with actual paths.
 /// Required for bottles to work correctly.
+#[allow(dead_code)]
 pub fn relocate_bottle(cellar_path: &Path, prefix: &Path) -> Result<()> {
+    let all_files = collect_files(cellar_path);
+    relocate_files(cellar_path, prefix, all_files)
+}
+
+/// Relocate a bottle using the file list recorded during extraction
+///
+/// Equivalent to [`relocate_bottle`] but skips walking the keg: `manifest` holds
+/// paths relative to `cellar_path`, as returned by `extract_bottle_with_manifest`.
+pub fn relocate_bottle_with_manifest(
+    cellar_path: &Path,
+    prefix: &Path,
+    manifest: &[PathBuf],
+) -> Result<()> {
+    let all_files = manifest
+        .iter()
+        .map(|relative| cellar_path.join(relative))
+        .filter(|path| path.is_file())
+        .collect();
+    relocate_files(cellar_path, prefix, all_files)
+}
+
+fn relocate_files(cellar_path: &Path, prefix: &Path, all_files: Vec<PathBuf>) -> Result<()> {
     let prefix_str = prefix
         .to_str()
         .ok_or_else(|| anyhow::anyhow!("Invalid prefix path"))?;
@@ -27,7 +50,7 @@ pub fn relocate_bottle(cellar_path: &Path, prefix: &Path) -> Result<()> {
         .ok_or_else(|| anyhow::anyhow!("Invalid cellar path"))?;
 
     // Find all Mach-O binaries and libraries
-    let mach_o_files = find_mach_o_files(cellar_path)?;
+    let mach_o_files = find_mach_o_files(&all_files);
 
     // Process Mach-O files in parallel (relocate placeholders)
     let mach_o_results: Vec<Result<()>> = mach_o_files
@@ -55,7 +78,7 @@ pub fn relocate_bottle(cellar_path: &Path, prefix: &Path) -> Result<()> {
     }
 
     // Find and process scripts with unreplaced shebangs (only in bin/ directories)
-    let script_files = find_scripts_with_placeholders(cellar_path)?;
+    let script_files = find_scripts_with_placeholders(cellar_path, &all_files);
 
     // Process scripts in parallel
     let script_results: Vec<Result<()>> = script_files
@@ -70,8 +93,8 @@ pub fn relocate_bottle(cellar_path: &Path, prefix: &Path) -> Result<()> {
     Ok(())
 }
 
-/// Find all Mach-O binaries and libraries in a directory
-fn find_mach_o_files(dir: &Path) -> Result<Vec<PathBuf>> {
+/// Collect all regular files in a keg
+fn collect_files(dir: &Path) -> Vec<PathBuf> {
     // Collect all file paths first without checking if they're Mach-O
     // WalkDir keeps directory handles open, so we process in batches
     let mut all_files = Vec::new();
@@ -88,13 +111,22 @@ fn find_mach_o_files(dir: &Path) -> Result<Vec<PathBuf>> {
         }
     }
 
-    // Now check which ones are Mach-O files in parallel (file handles closed between checks)
-    let mach_o_files: Vec<PathBuf> = all_files
-        .into_par_iter()
-        .filter(|path| is_mach_o(path).unwrap_or(false))
-        .collect();
+    all_files
+}
 
-    Ok(mach_o_files)
+/// Find all Mach-O binaries and libraries in a keg
+pub fn mach_o_files(keg: &Path) -> Vec<PathBuf> {
+    find_mach_o_files(&collect_files(keg))
+}
+
+/// Find all Mach-O binaries and libraries among a keg's files
+fn find_mach_o_files(all_files: &[PathBuf]) -> Vec<PathBuf> {
+    // Check which ones are Mach-O files in parallel (file handles closed between checks)
+    all_files
+        .par_iter()
+        .filter(|path| is_mach_o(path).unwrap_or(false))
+        .cloned()
+        .collect()
 }
 
 /// Check if a file is a Mach-O binary
@@ -244,35 +276,25 @@ fn fix_library_id(path: &Path, prefix: &str, cellar: &str) -> Result<()> {
 }
 
 /// Find executable scripts in bin/ directories with unreplaced shebang placeholders
-fn find_scripts_with_placeholders(dir: &Path) -> Result<Vec<PathBuf>> {
+fn find_scripts_with_placeholders(dir: &Path, all_files: &[PathBuf]) -> Vec<PathBuf> {
     use std::io::Read;
     use std::os::unix::fs::PermissionsExt;
 
     let mut candidates = Vec::new();
 
     // Only look in bin/ subdirectories
-    for entry in WalkDir::new(dir)
-        .follow_links(false)
-        .max_depth(3) // Don't go too deep
-        .into_iter()
-        .filter_map(|e| e.ok())
-    {
-        let path = entry.path();
-
-        // Only process files in directories named "bin"
-        if let Some(parent) = path.parent() {
-            if let Some(dir_name) = parent.file_name() {
-                if dir_name != "bin" {
-                    continue;
-                }
-            } else {
-                continue;
-            }
-        } else {
+    for path in all_files {
+        // Don't go too deep (bin/ or one level below the keg root, e.g. libexec/bin/)
+        let depth = path
+            .strip_prefix(dir)
+            .map(|relative| relative.components().count())
+            .unwrap_or(usize::MAX);
+        if depth > 3 {
             continue;
         }
 
-        if !path.is_file() {
+        // Only process files in directories named "bin"
+        if path.parent().and_then(|parent| parent.file_name()) != Some("bin".as_ref()) {
             continue;
         }
 
@@ -285,11 +307,11 @@ fn find_scripts_with_placeholders(dir: &Path) -> Result<Vec<PathBuf>> {
             continue;
         }
 
-        candidates.push(path.to_path_buf());
+        candidates.push(path.clone());
     }
 
     // Check candidates in parallel for placeholder shebangs
-    let scripts: Vec<PathBuf> = candidates
+    candidates
         .into_par_iter()
         .filter(|path| {
             // Skip Mach-O binaries (already handled)
@@ -320,9 +342,7 @@ fn find_scripts_with_placeholders(dir: &Path) -> Result<Vec<PathBuf>> {
 
             false
         })
-        .collect();
-
-    Ok(scripts)
+        .collect()
 }
 
 /// Replace placeholders in a script's shebang line
@@ -6,6 +6,7 @@
 //! - **Version-agnostic symlinks**: `/opt/homebrew/opt/<formula>` links to current version
 //! - **Relative symlinks**: Safe relocatable symlinks without absolute paths
 //! - **Conflict resolution**: Overwrites symlinks but preserves regular files
+//! - **Config files**: `etc/` is copied rather than linked, so user edits survive upgrades
 //! - **Cleanup**: Removal of symlinks when packages are uninstalled
 //!
 //! # Architecture
@@ -21,8 +22,13 @@
 //! ### Option Links (version-agnostic)
 //! ```text
 //! /opt/homebrew/opt/ripgrep -> ../Cellar/ripgrep/13.0.0
+//! ```
+//!
+//! ### Linked-Keg Records
+//! ```text
 //! /opt/homebrew/var/homebrew/linked/ripgrep -> ../../../Cellar/ripgrep/13.0.0
 //! ```
+//! Written when a keg is linked and removed when it is unlinked, matching `brew link`.
 //!
 //! # Examples
 //!
@@ -63,6 +69,41 @@ const LINKABLE_DIRS: &[&str] = &[
     "Frameworks",
 ];
 
+/// What linking does with one keg file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LinkRule {
+    /// Symlink it into the prefix
+    Symlink,
+    /// Copy it, keeping any edited copy already in the prefix (`etc/` config files)
+    Copy,
+    /// Leave it out: files many kegs ship that would only conflict
+    Skip,
+}
+
+/// Homebrew's link rule for a file, given its path relative to the keg.
+///
+/// Directories (`share/man`, `share/info`, `lib/pkgconfig`, ...) are always created
+/// for real and filled file by file, so several kegs can share them.
+fn link_rule(relative: &Path) -> LinkRule {
+    let mut components = relative.components().map(|c| c.as_os_str());
+    let first = components.next();
+    let rest: PathBuf = components.collect();
+
+    match first.and_then(|c| c.to_str()) {
+        Some("etc") => LinkRule::Copy,
+        Some("share")
+            if rest == Path::new("info/dir")
+                || rest == Path::new("locale/locale.alias")
+                || (rest.starts_with("icons")
+                    && rest.file_name() == Some("icon-theme.cache".as_ref())) =>
+        {
+            LinkRule::Skip
+        }
+        Some("lib") if rest == Path::new("charset.alias") => LinkRule::Skip,
+        _ => LinkRule::Symlink,
+    }
+}
+
 /// Create symlinks for an installed formula into the Homebrew prefix.
 ///
 /// Creates symlinks from the installed formula's files (bin/, lib/, include/, etc.) to the
@@ -101,19 +142,30 @@ const LINKABLE_DIRS: &[&str] = &[
 ///
 /// # Linkable Directories
 ///
-/// The following directories are symlinked if they exist:
+/// The following directories are linked file by file if they exist:
 /// - `bin/`, `sbin/` - Executable binaries
-/// - `lib/`, `include/` - Libraries and headers
-/// - `share/`, `etc/` - Data and configuration files
+/// - `lib/`, `include/` - Libraries and headers (including `lib/pkgconfig/`)
+/// - `share/` - Data files, man pages (`share/man/`) and info pages (`share/info/`)
 /// - `Frameworks/` - macOS frameworks
 ///
+/// `etc/` is copied instead: an existing file in the prefix is left alone, and a
+/// changed default is written next to it as `<file>.default`, as Homebrew does.
+/// `share/info/dir`, `lib/charset.alias` and similar shared index files are skipped.
+///
 /// # Relative Symlinks
 ///
 /// All symlinks are relative (not absolute) for portability. A linked binary at
 /// `/opt/homebrew/bin/ripgrep` points to `../Cellar/ripgrep/13.0.0/bin/ripgrep`.
 pub fn link_formula(formula_name: &str, version: &str) -> Result<Vec<PathBuf>> {
-    let prefix = cellar::detect_prefix();
-    let cellar_path = cellar::cellar_path();
+    link_formula_in(&cellar::detect_prefix(), formula_name, version)
+}
+
+/// Link a formula's files into an explicit prefix.
+///
+/// Same as [`link_formula`], but operates on `prefix` (and its `Cellar/`) instead of
+/// the detected Homebrew prefix.
+pub fn link_formula_in(prefix: &Path, formula_name: &str, version: &str) -> Result<Vec<PathBuf>> {
+    let cellar_path = prefix.join("Cellar");
     let formula_path = cellar_path.join(formula_name).join(version);
 
     let mut linked_files = Vec::new();
@@ -137,22 +189,229 @@ pub fn link_formula(formula_name: &str, version: &str) -> Result<Vec<PathBuf>> {
         }
 
         // Link all files in this directory
-        link_directory(&source_dir, &target_dir, &cellar_path, &mut linked_files)?;
+        link_directory(
+            &source_dir,
+            &target_dir,
+            &formula_path,
+            &cellar_path,
+            &mut linked_files,
+        )?;
     }
 
+    write_linked_record(prefix, formula_name, version)?;
+
     Ok(linked_files)
 }
 
+/// Link a formula using the file list recorded during extraction.
+///
+/// Equivalent to [`link_formula`] but skips walking the keg: `manifest` holds paths
+/// relative to the keg (as returned by `extract_bottle_with_manifest`). Only entries
+/// under the linkable directories are linked.
+///
+/// # Errors
+///
+/// Returns an error if a target directory or symlink cannot be created.
+#[allow(dead_code)]
+pub fn link_formula_with_manifest(
+    formula_name: &str,
+    version: &str,
+    manifest: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    link_formula_with_manifest_in(&cellar::detect_prefix(), formula_name, version, manifest)
+}
+
+/// Link a formula from its extraction manifest into an explicit prefix.
+///
+/// Same as [`link_formula_with_manifest`], but operates on `prefix` instead of the
+/// detected Homebrew prefix.
+pub fn link_formula_with_manifest_in(
+    prefix: &Path,
+    formula_name: &str,
+    version: &str,
+    manifest: &[PathBuf],
+) -> Result<Vec<PathBuf>> {
+    let cellar_path = prefix.join("Cellar");
+    let formula_path = cellar_path.join(formula_name).join(version);
+
+    let mut operations = Vec::new();
+    let mut copies = Vec::new();
+    let mut target_dirs = std::collections::BTreeSet::new();
+
+    for relative in manifest {
+        let is_linkable = relative
+            .components()
+            .next()
+            .is_some_and(|first| LINKABLE_DIRS.iter().any(|dir| first.as_os_str() == *dir));
+        let rule = link_rule(relative);
+        if !is_linkable || rule == LinkRule::Skip {
+            continue;
+        }
+
+        let target_path = prefix.join(relative);
+        if let Some(parent) = target_path.parent() {
+            target_dirs.insert(parent.to_path_buf());
+        }
+        if rule == LinkRule::Copy {
+            copies.push((formula_path.join(relative), target_path));
+        } else {
+            operations.push((formula_path.join(relative), target_path));
+        }
+    }
+
+    for target_dir in &target_dirs {
+        create_directory_if_needed(target_dir)?;
+    }
+    for (source_path, target_path) in &copies {
+        install_config_file(source_path, target_path)?;
+    }
+
+    let linked = operations
+        .into_par_iter()
+        .map(|(source_path, target_path)| {
+            create_symlink_operation(source_path, target_path, &cellar_path)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    write_linked_record(prefix, formula_name, version)?;
+
+    Ok(linked)
+}
+
+/// List the symlinks that linking a formula would create, without touching the prefix.
+///
+/// Returns `(source, target)` pairs: `source` is the file in the keg and `target` is
+/// where its symlink would be created in the prefix. Used for `bru link --dry-run` and
+/// to find conflicting files before `--overwrite`.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::symlink;
+///
+/// fn main() -> anyhow::Result<()> {
+///     for (_, target) in symlink::link_targets("ripgrep", "13.0.0")? {
+///         println!("Would link {}", target.display());
+///     }
+///     Ok(())
+/// }
+/// ```
+pub fn link_targets(formula_name: &str, version: &str) -> Result<Vec<(PathBuf, PathBuf)>> {
+    link_targets_in(&cellar::detect_prefix(), formula_name, version)
+}
+
+/// List the symlinks that linking a formula into an explicit prefix would create.
+///
+/// Same as [`link_targets`], but operates on `prefix` instead of the detected Homebrew prefix.
+pub fn link_targets_in(
+    prefix: &Path,
+    formula_name: &str,
+    version: &str,
+) -> Result<Vec<(PathBuf, PathBuf)>> {
+    let cellar_path = prefix.join("Cellar");
+    let formula_path = cellar_path.join(formula_name).join(version);
+
+    let mut operations = Vec::new();
+    for dir_name in LINKABLE_DIRS {
+        let source_dir = formula_path.join(dir_name);
+        if source_dir.is_dir() {
+            collect_link_operations(
+                &source_dir,
+                &prefix.join(dir_name),
+                &formula_path,
+                &mut operations,
+            )?;
+        }
+    }
+
+    Ok(operations
+        .into_iter()
+        .filter_map(|op| match op {
+            LinkOperation::CreateSymlink {
+                source_path,
+                target_path,
+            } => Some((source_path, target_path)),
+            LinkOperation::CreateDirectory { .. } | LinkOperation::CopyFile { .. } => None,
+        })
+        .collect())
+}
+
+/// A prefix path that linking a formula would have to replace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkConflict {
+    /// Where the formula's symlink would go
+    pub target: PathBuf,
+    /// Formula whose keg the existing symlink points into, or `None` if the existing
+    /// entry is a file rather than a symlink
+    pub owner: Option<String>,
+}
+
+/// Find the files and other kegs' symlinks in the way of linking a formula.
+///
+/// Symlinks into another version of the same formula are not conflicts; relinking
+/// replaces them. Used by `bru link`, which only replaces conflicts with `--overwrite`.
+pub fn link_conflicts(formula_name: &str, version: &str) -> Result<Vec<LinkConflict>> {
+    link_conflicts_in(&cellar::detect_prefix(), formula_name, version)
+}
+
+/// Find the conflicts for linking a formula into an explicit prefix.
+///
+/// Same as [`link_conflicts`], but operates on `prefix` instead of the detected Homebrew prefix.
+pub fn link_conflicts_in(
+    prefix: &Path,
+    formula_name: &str,
+    version: &str,
+) -> Result<Vec<LinkConflict>> {
+    let cellar_path = prefix.join("Cellar");
+    let mut conflicts = Vec::new();
+
+    for (_, target) in link_targets_in(prefix, formula_name, version)? {
+        let Ok(metadata) = target.symlink_metadata() else {
+            continue;
+        };
+        if !metadata.is_symlink() {
+            if !metadata.is_dir() {
+                conflicts.push(LinkConflict {
+                    target,
+                    owner: None,
+                });
+            }
+            continue;
+        }
+
+        let Ok(destination) = fs::read_link(&target) else {
+            continue;
+        };
+        let resolved = normalize_path(&target.parent().unwrap_or(prefix).join(destination));
+        let owner = resolved
+            .strip_prefix(&cellar_path)
+            .ok()
+            .and_then(|keg| keg.components().next())
+            .map(|name| name.as_os_str().to_string_lossy().into_owned());
+        if let Some(owner) = owner
+            && owner != formula_name
+        {
+            conflicts.push(LinkConflict {
+                target,
+                owner: Some(owner),
+            });
+        }
+    }
+
+    Ok(conflicts)
+}
+
 /// Recursively link files from source to target
 fn link_directory(
     source: &Path,
     target: &Path,
+    keg_root: &Path,
     cellar_root: &Path,
     linked_files: &mut Vec<PathBuf>,
 ) -> Result<()> {
     // Collect all files and directories that need linking
     let mut operations = Vec::new();
-    collect_link_operations(source, target, cellar_root, &mut operations)?;
+    collect_link_operations(source, target, keg_root, &mut operations)?;
 
     // Create all target directories first (can be done in parallel)
     let dir_results: Vec<Result<()>> = operations
@@ -170,6 +429,17 @@ fn link_directory(
         result?;
     }
 
+    // Config files are copied (few and small, so sequentially)
+    for op in &operations {
+        if let LinkOperation::CopyFile {
+            source_path,
+            target_path,
+        } = op
+        {
+            install_config_file(source_path, target_path)?;
+        }
+    }
+
     // Create symlinks in parallel
     let symlink_results: Vec<Result<PathBuf>> = operations
         .into_par_iter()
@@ -207,13 +477,17 @@ enum LinkOperation {
         source_path: PathBuf,
         target_path: PathBuf,
     },
+    CopyFile {
+        source_path: PathBuf,
+        target_path: PathBuf,
+    },
 }
 
 /// Collect all linking operations needed (files and directories)
 fn collect_link_operations(
     source: &Path,
     target: &Path,
-    _cellar_root: &Path,
+    keg_root: &Path,
     operations: &mut Vec<LinkOperation>,
 ) -> Result<()> {
     for entry in fs::read_dir(source)? {
@@ -228,16 +502,106 @@ fn collect_link_operations(
                 target_dir: target_path.clone(),
             });
             // Recursively collect operations for contents
-            collect_link_operations(&source_path, &target_path, _cellar_root, operations)?;
+            collect_link_operations(&source_path, &target_path, keg_root, operations)?;
         } else {
-            // Need to create symlink
-            operations.push(LinkOperation::CreateSymlink {
-                source_path,
-                target_path,
-            });
+            let relative = source_path.strip_prefix(keg_root).unwrap_or(&source_path);
+            match link_rule(relative) {
+                LinkRule::Symlink => operations.push(LinkOperation::CreateSymlink {
+                    source_path,
+                    target_path,
+                }),
+                LinkRule::Copy => operations.push(LinkOperation::CopyFile {
+                    source_path,
+                    target_path,
+                }),
+                LinkRule::Skip => {}
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Copy a formula's `etc/` config files into the Homebrew prefix without linking it.
+///
+/// Keg-only formulae are never linked, but their configuration still belongs in the
+/// prefix's `etc/`, as Homebrew installs it for every keg. Files are copied the way
+/// [`link_formula`] copies them, so user edits are kept.
+///
+/// Returns the prefix paths of the config files.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be created or a file cannot be copied.
+#[allow(dead_code)]
+pub fn install_config_files(formula_name: &str, version: &str) -> Result<Vec<PathBuf>> {
+    install_config_files_in(&cellar::detect_prefix(), formula_name, version)
+}
+
+/// Copy a formula's `etc/` config files into an explicit prefix.
+///
+/// Same as [`install_config_files`], but operates on `prefix` instead of the detected
+/// Homebrew prefix.
+pub fn install_config_files_in(
+    prefix: &Path,
+    formula_name: &str,
+    version: &str,
+) -> Result<Vec<PathBuf>> {
+    let source = prefix
+        .join("Cellar")
+        .join(formula_name)
+        .join(version)
+        .join("etc");
+    let mut installed = Vec::new();
+    if source.is_dir() {
+        copy_config_dir(&source, &prefix.join("etc"), &mut installed)?;
+    }
+    Ok(installed)
+}
+
+/// Copy every file under `source` to the same place under `target`
+fn copy_config_dir(source: &Path, target: &Path, installed: &mut Vec<PathBuf>) -> Result<()> {
+    create_directory_if_needed(target)?;
+    for entry in fs::read_dir(source)? {
+        let source_path = entry?.path();
+        let Some(file_name) = source_path.file_name() else {
+            continue;
+        };
+        let target_path = target.join(file_name);
+        if source_path.is_dir() {
+            copy_config_dir(&source_path, &target_path, installed)?;
+        } else {
+            install_config_file(&source_path, &target_path)?;
+            installed.push(target_path);
+        }
+    }
+    Ok(())
+}
+
+/// Copy a config file from the keg into the prefix without clobbering user edits.
+///
+/// A missing file is copied; an identical one is left as is. A file that differs
+/// keeps its contents, and the keg's version is written beside it as
+/// `<file>.default`. A symlink (from linking `etc/` in older versions) is replaced.
+fn install_config_file(source: &Path, target: &Path) -> Result<()> {
+    if let Ok(metadata) = target.symlink_metadata() {
+        if metadata.is_symlink() {
+            fs::remove_file(target)
+                .with_context(|| format!("Failed to remove symlink: {}", target.display()))?;
+        } else {
+            let unchanged = fs::read(source).ok() == fs::read(target).ok();
+            if !unchanged {
+                let mut default = target.as_os_str().to_owned();
+                default.push(".default");
+                fs::copy(source, &default)
+                    .with_context(|| format!("Failed to copy config file to: {:?}", default))?;
+            }
+            return Ok(());
         }
     }
 
+    fs::copy(source, target)
+        .with_context(|| format!("Failed to copy config file to: {}", target.display()))?;
     Ok(())
 }
 
@@ -397,8 +761,15 @@ fn create_relative_symlink(source: &Path, target: &Path, cellar_root: &Path) ->
 /// }
 /// ```
 pub fn unlink_formula(formula_name: &str, version: &str) -> Result<Vec<PathBuf>> {
-    let prefix = cellar::detect_prefix();
-    let cellar_path = cellar::cellar_path();
+    unlink_formula_in(&cellar::detect_prefix(), formula_name, version)
+}
+
+/// Unlink a formula's files from an explicit prefix.
+///
+/// Same as [`unlink_formula`], but operates on `prefix` instead of the detected
+/// Homebrew prefix.
+pub fn unlink_formula_in(prefix: &Path, formula_name: &str, version: &str) -> Result<Vec<PathBuf>> {
+    let cellar_path = prefix.join("Cellar");
     let formula_path = cellar_path.join(formula_name).join(version);
 
     let mut unlinked_files = Vec::new();
@@ -415,9 +786,103 @@ pub fn unlink_formula(formula_name: &str, version: &str) -> Result<Vec<PathBuf>>
         unlink_symlinks_in_directory(&target_dir, &formula_path, &mut unlinked_files)?;
     }
 
+    remove_linked_record(prefix, formula_name, version)?;
+
     Ok(unlinked_files)
 }
 
+/// Path of Homebrew's linked-keg record for a formula (`var/homebrew/linked/<formula>`)
+fn linked_record_path(prefix: &Path, formula_name: &str) -> PathBuf {
+    prefix
+        .join("var")
+        .join("homebrew")
+        .join("linked")
+        .join(formula_name)
+}
+
+/// Record a keg as linked, as `brew link` does:
+/// `var/homebrew/linked/<formula>` -> `../../../Cellar/<formula>/<version>`
+fn write_linked_record(prefix: &Path, formula_name: &str, version: &str) -> Result<()> {
+    let linked_record = linked_record_path(prefix, formula_name);
+
+    // Ensure linked directory exists
+    if let Some(linked_dir) = linked_record.parent() {
+        fs::create_dir_all(linked_dir).with_context(|| {
+            format!(
+                "Failed to create linked directory: {}",
+                linked_dir.display()
+            )
+        })?;
+    }
+
+    // Remove existing symlink if present
+    if linked_record.symlink_metadata().is_ok() {
+        fs::remove_file(&linked_record).with_context(|| {
+            format!(
+                "Failed to remove existing linked symlink: {}",
+                linked_record.display()
+            )
+        })?;
+    }
+
+    // var/homebrew/linked/<formula> is 3 levels deep, so need 3 ".."
+    let relative_path = PathBuf::from("../../../Cellar")
+        .join(formula_name)
+        .join(version);
+
+    unix_fs::symlink(&relative_path, &linked_record).with_context(|| {
+        format!(
+            "Failed to create linked symlink: {} -> {}",
+            linked_record.display(),
+            relative_path.display()
+        )
+    })
+}
+
+/// The version a formula's linked-keg record points at under `prefix`, or `None` when
+/// it isn't linked.
+///
+/// Unlike [`get_linked_version_in`], which follows `opt/` (kept by `brew unlink`), this
+/// reads `var/homebrew/linked/<formula>`, which only exists while the keg is linked.
+///
+/// # Errors
+///
+/// Returns an error if the record exists but cannot be read.
+pub fn linked_keg_version_in(prefix: &Path, formula_name: &str) -> Result<Option<String>> {
+    let linked_record = linked_record_path(prefix, formula_name);
+    if linked_record.symlink_metadata().is_err() {
+        return Ok(None);
+    }
+    let target = fs::read_link(&linked_record)
+        .with_context(|| format!("Failed to read linked record: {}", linked_record.display()))?;
+    Ok(target
+        .file_name()
+        .and_then(|version| version.to_str())
+        .map(str::to_string))
+}
+
+/// Remove the linked-keg record, but only if it records `version`.
+///
+/// Unlinking an old version (e.g. during cleanup or after an upgrade relinked the new
+/// one) must not drop the record of the version that is actually linked.
+fn remove_linked_record(prefix: &Path, formula_name: &str, version: &str) -> Result<()> {
+    let linked_record = linked_record_path(prefix, formula_name);
+
+    let Ok(target) = fs::read_link(&linked_record) else {
+        return Ok(());
+    };
+    if target.file_name().is_some_and(|name| name == version) {
+        fs::remove_file(&linked_record).with_context(|| {
+            format!(
+                "Failed to remove linked symlink: {}",
+                linked_record.display()
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
 /// Recursively scan target directory and remove symlinks pointing to formula_path
 fn unlink_symlinks_in_directory(
     target: &Path,
@@ -484,9 +949,9 @@ pub fn normalize_path(path: &Path) -> PathBuf {
 /// Creates symlinks that point to a formula regardless of its version. This is essential
 /// for tools that need to track the "currently installed" version of a package.
 ///
-/// Creates two symlinks:
-/// - `/opt/homebrew/opt/<formula>` → `../Cellar/<formula>/<version>`
-/// - `/opt/homebrew/var/homebrew/linked/<formula>` → `../../../Cellar/<formula>/<version>`
+/// Creates `/opt/homebrew/opt/<formula>` → `../Cellar/<formula>/<version>`. The
+/// `var/homebrew/linked/<formula>` record is maintained by [`link_formula`] and
+/// [`unlink_formula`] instead, since it tracks whether the keg is linked into the prefix.
 ///
 /// # Arguments
 ///
@@ -515,8 +980,13 @@ pub fn normalize_path(path: &Path) -> PathBuf {
 /// When upgrading, call `optlink()` with the new version to switch the symlink.
 /// This allows multiple versions to coexist, with only one being "active".
 pub fn optlink(formula_name: &str, version: &str) -> Result<()> {
-    let prefix = cellar::detect_prefix();
+    optlink_in(&cellar::detect_prefix(), formula_name, version)
+}
 
+/// Create the `opt/` symlink under an explicit prefix.
+///
+/// Same as [`optlink`], but operates on `prefix` instead of the detected Homebrew prefix.
+pub fn optlink_in(prefix: &Path, formula_name: &str, version: &str) -> Result<()> {
     // Create opt/ symlink: /opt/homebrew/opt/<formula> -> ../Cellar/<formula>/<version>
     let opt_record = prefix.join("opt").join(formula_name);
 
@@ -549,54 +1019,12 @@ pub fn optlink(formula_name: &str, version: &str) -> Result<()> {
         )
     })?;
 
-    // Create linked/ symlink: /opt/homebrew/var/homebrew/linked/<formula> -> ../../../Cellar/<formula>/<version>
-    let linked_record = prefix
-        .join("var")
-        .join("homebrew")
-        .join("linked")
-        .join(formula_name);
-
-    // Ensure linked directory exists
-    if let Some(linked_dir) = linked_record.parent() {
-        fs::create_dir_all(linked_dir).with_context(|| {
-            format!(
-                "Failed to create linked directory: {}",
-                linked_dir.display()
-            )
-        })?;
-    }
-
-    // Remove existing symlink if present
-    if linked_record.symlink_metadata().is_ok() {
-        fs::remove_file(&linked_record).with_context(|| {
-            format!(
-                "Failed to remove existing linked symlink: {}",
-                linked_record.display()
-            )
-        })?;
-    }
-
-    // Calculate relative path from linked_record to formula_path
-    // var/homebrew/linked/<formula> is 3 levels deep, so need 3 ".."
-    let relative_path = PathBuf::from("../../../Cellar")
-        .join(formula_name)
-        .join(version);
-
-    // Create the symlink
-    unix_fs::symlink(&relative_path, &linked_record).with_context(|| {
-        format!(
-            "Failed to create linked symlink: {} -> {}",
-            linked_record.display(),
-            relative_path.display()
-        )
-    })?;
-
     Ok(())
 }
 
 /// Remove version-agnostic symlinks for a formula.
 ///
-/// Removes the symlinks created by `optlink()`:
+/// Removes the symlink created by `optlink()` and any linked-keg record left behind:
 /// - `/opt/homebrew/opt/<formula>`
 /// - `/opt/homebrew/var/homebrew/linked/<formula>`
 ///
@@ -623,8 +1051,13 @@ pub fn optlink(formula_name: &str, version: &str) -> Result<()> {
 /// }
 /// ```
 pub fn unoptlink(formula_name: &str) -> Result<()> {
-    let prefix = cellar::detect_prefix();
+    unoptlink_in(&cellar::detect_prefix(), formula_name)
+}
 
+/// Remove the `opt/` and `var/homebrew/linked/` symlinks under an explicit prefix.
+///
+/// Same as [`unoptlink`], but operates on `prefix` instead of the detected Homebrew prefix.
+pub fn unoptlink_in(prefix: &Path, formula_name: &str) -> Result<()> {
     // Remove opt/ symlink
     let opt_record = prefix.join("opt").join(formula_name);
     if opt_record.symlink_metadata().is_ok() {
@@ -650,6 +1083,171 @@ pub fn unoptlink(formula_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// Remove dangling Homebrew symlinks from the prefix.
+///
+/// Scans the linkable directories (`bin/`, `lib/`, `share/`, ...), `opt/`, and
+/// `var/homebrew/linked/` for symlinks whose target no longer exists. Only links that
+/// point into the Cellar, Caskroom, or `opt/` are removed; dangling links created by
+/// anything else are left alone.
+///
+/// With `dry_run`, nothing is removed. Returns the links that were (or would be) removed.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read or a link cannot be removed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::symlink;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let pruned = symlink::prune_broken(false)?;
+///     println!("Removed {} broken symlinks", pruned.len());
+///     Ok(())
+/// }
+/// ```
+pub fn prune_broken(dry_run: bool) -> Result<Vec<PathBuf>> {
+    prune_broken_in(&cellar::detect_prefix(), dry_run)
+}
+
+/// Remove dangling Homebrew symlinks under an explicit prefix.
+///
+/// Same as [`prune_broken`], but operates on `prefix` instead of the detected Homebrew prefix.
+pub fn prune_broken_in(prefix: &Path, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let mut pruned = Vec::new();
+
+    let dirs = LINKABLE_DIRS
+        .iter()
+        .copied()
+        .chain(["opt", "var/homebrew/linked"]);
+    for dir_name in dirs {
+        let dir = prefix.join(dir_name);
+        if dir.is_dir() {
+            collect_broken_links(prefix, &dir, &mut pruned)?;
+        }
+    }
+
+    if !dry_run {
+        for link in &pruned {
+            fs::remove_file(link)
+                .with_context(|| format!("Failed to remove symlink: {}", link.display()))?;
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Remove empty directories left under the prefix's linkable directories.
+///
+/// The linkable directories themselves (`bin/`, `share/`, ...) are kept, and `etc/` is
+/// never pruned since it holds configuration. Directories that only contain dangling
+/// Homebrew symlinks count as empty, so a dry run reports what [`prune_broken`]
+/// followed by this function would remove.
+///
+/// Returns the directories that were (or would be) removed, deepest first.
+///
+/// # Errors
+///
+/// Returns an error if a directory cannot be read or removed.
+pub fn prune_empty_dirs(dry_run: bool) -> Result<Vec<PathBuf>> {
+    prune_empty_dirs_in(&cellar::detect_prefix(), dry_run)
+}
+
+/// Remove empty directories under an explicit prefix.
+///
+/// Same as [`prune_empty_dirs`], but operates on `prefix` instead of the detected
+/// Homebrew prefix.
+pub fn prune_empty_dirs_in(prefix: &Path, dry_run: bool) -> Result<Vec<PathBuf>> {
+    let mut pruned = Vec::new();
+
+    for dir_name in LINKABLE_DIRS.iter().filter(|d| **d != "etc") {
+        let dir = prefix.join(dir_name);
+        if !dir.is_dir() || dir.is_symlink() {
+            continue;
+        }
+        for entry in fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if !path.is_symlink() && path.is_dir() {
+                collect_empty_dirs(prefix, &path, &mut pruned)?;
+            }
+        }
+    }
+
+    if !dry_run {
+        for dir in &pruned {
+            // Leftover dangling links were skipped above; clear them so the dir is empty
+            for entry in fs::read_dir(dir)? {
+                fs::remove_file(entry?.path())?;
+            }
+            fs::remove_dir(dir)
+                .with_context(|| format!("Failed to remove directory: {}", dir.display()))?;
+        }
+    }
+
+    Ok(pruned)
+}
+
+/// Check whether `path` is a dangling symlink into the Cellar, Caskroom, or `opt/`
+fn is_broken_homebrew_link(prefix: &Path, path: &Path) -> bool {
+    let Ok(link_target) = fs::read_link(path) else {
+        return false;
+    };
+    if path.exists() {
+        return false;
+    }
+
+    let resolved = if link_target.is_relative() {
+        path.parent().unwrap_or(prefix).join(&link_target)
+    } else {
+        link_target
+    };
+    let normalized = normalize_path(&resolved);
+
+    ["Cellar", "Caskroom", "opt"]
+        .iter()
+        .any(|owned| normalized.starts_with(prefix.join(owned)))
+}
+
+/// Recursively collect dangling Homebrew symlinks under `dir` (without following links)
+fn collect_broken_links(prefix: &Path, dir: &Path, broken: &mut Vec<PathBuf>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_symlink() {
+            if is_broken_homebrew_link(prefix, &path) {
+                broken.push(path);
+            }
+        } else if path.is_dir() {
+            collect_broken_links(prefix, &path, broken)?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Collect `dir` and its subdirectories that are empty (or would be once broken links
+/// are pruned), children before parents. Returns whether `dir` itself was collected.
+fn collect_empty_dirs(prefix: &Path, dir: &Path, empty: &mut Vec<PathBuf>) -> Result<bool> {
+    let mut is_empty = true;
+
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_symlink() {
+            is_empty &= is_broken_homebrew_link(prefix, &path);
+        } else if path.is_dir() {
+            is_empty &= collect_empty_dirs(prefix, &path, empty)?;
+        } else {
+            is_empty = false;
+        }
+    }
+
+    if is_empty {
+        empty.push(dir.to_path_buf());
+    }
+
+    Ok(is_empty)
+}
+
 /// Get the currently linked version of a formula.
 ///
 /// Returns the version that is currently linked via `/opt/homebrew/opt/<formula>`.
@@ -694,7 +1292,14 @@ pub fn unoptlink(formula_name: &str) -> Result<()> {
 /// 3. Update symlinks with `link_formula()` and `optlink()`
 /// 4. If an error occurs, you can read this to determine what was partially installed
 pub fn get_linked_version(formula_name: &str) -> Result<Option<String>> {
-    let prefix = cellar::detect_prefix();
+    get_linked_version_in(&cellar::detect_prefix(), formula_name)
+}
+
+/// Get the currently linked version of a formula under a specific prefix.
+///
+/// Same as [`get_linked_version`], but operates on `prefix` instead of the detected
+/// Homebrew prefix.
+pub fn get_linked_version_in(prefix: &Path, formula_name: &str) -> Result<Option<String>> {
     let opt_link = prefix.join("opt").join(formula_name);
 
     // Check if opt symlink exists
@@ -4,7 +4,8 @@
 //! - **List taps**: Find all installed custom taps
 //! - **Add taps**: Clone git repositories as new taps
 //! - **Remove taps**: Delete tap directories and cleanup
-//! - **Parse tap formulae**: Extract metadata from Ruby formula files
+//! - **Parse tap formulae and casks**: Extract metadata from Ruby files
+//! - **Inspect taps**: Remote, revision, contents, and pin state ([`info`])
 //! - **Detect tap sources**: Identify which tap a package came from
 //!
 //! # What are Taps?
@@ -52,8 +53,10 @@
 //! }
 //! ```
 
+use crate::api::{Cask, Formula, FormulaSource};
 use crate::cellar;
 use anyhow::{Context, Result, anyhow};
+use futures::future::BoxFuture;
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -80,32 +83,168 @@ pub fn extract_formula_name(tap_formula: &str) -> String {
     }
 }
 
-/// Parse a tap name into (user, repo) components
-/// Input: "user/repo" → Output: ("user", "homebrew-repo")
-fn parse_tap_name(tap: &str) -> Result<(String, String)> {
-    let parts: Vec<&str> = tap.split('/').collect();
-    if parts.len() != 2 {
-        return Err(anyhow!(
-            "Invalid tap name format. Expected 'user/repo', got '{}'",
-            tap
-        ));
+/// A validated tap name, split into its GitHub user and repository
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TapName {
+    /// GitHub user or organization, lowercased
+    pub user: String,
+    /// Repository name without the `homebrew-` prefix, lowercased
+    pub repo: String,
+    /// Repository name as on GitHub and on disk (`homebrew-<repo>`)
+    pub full_repo: String,
+}
+
+impl TapName {
+    /// Tap directory under `prefix` (`Library/Taps/user/homebrew-repo`)
+    pub fn dir_in(&self, prefix: &Path) -> PathBuf {
+        prefix
+            .join("Library/Taps")
+            .join(&self.user)
+            .join(&self.full_repo)
+    }
+
+    /// GitHub URL the tap is cloned from
+    pub fn remote_url(&self) -> String {
+        format!("https://github.com/{}/{}.git", self.user, self.full_repo)
     }
 
-    let user = parts[0].to_string();
-    let mut repo = parts[1].to_string();
+    /// Whether this is homebrew/core, whose formulae come from the API
+    pub fn is_core(&self) -> bool {
+        self.user == "homebrew" && self.repo == "core"
+    }
+}
+
+impl std::fmt::Display for TapName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.user, self.repo)
+    }
+}
 
-    // Add homebrew- prefix if not present
-    if !repo.starts_with("homebrew-") {
-        repo = format!("homebrew-{}", repo);
+/// Parse and validate a tap name.
+///
+/// Accepts `user/repo` or `user/homebrew-repo`; both normalize to the same tap, and the
+/// `homebrew-` prefix is never added twice. Names are lowercased, as Homebrew does.
+///
+/// # Errors
+///
+/// Returns an error unless the name has exactly one `/`, both parts are non-empty, and
+/// they contain only ASCII letters, digits, `-`, `_` and `.`.
+///
+/// # Examples
+///
+/// ```
+/// use kombrucha::tap;
+///
+/// let tap = tap::normalize("User/homebrew-Tools").unwrap();
+/// assert_eq!(tap.to_string(), "user/tools");
+/// assert_eq!(tap.full_repo, "homebrew-tools");
+/// assert!(tap::normalize("user/repo/extra").is_err());
+/// ```
+pub fn normalize(name: &str) -> Result<TapName> {
+    let invalid = || anyhow!("Invalid tap name '{}'. Expected 'user/repo'", name);
+
+    let lower = name.to_lowercase();
+    let (user, repo) = lower.split_once('/').ok_or_else(invalid)?;
+    let repo = repo.strip_prefix("homebrew-").unwrap_or(repo);
+
+    let valid_part = |part: &str| {
+        !part.is_empty()
+            && part != "."
+            && part != ".."
+            && part
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    };
+    if !valid_part(user) || !valid_part(repo) {
+        return Err(invalid());
     }
 
-    Ok((user, repo))
+    Ok(TapName {
+        user: user.to_string(),
+        repo: repo.to_string(),
+        full_repo: format!("homebrew-{}", repo),
+    })
 }
 
 /// Get the directory path for a tap
 pub fn tap_directory(tap: &str) -> Result<PathBuf> {
-    let (user, repo) = parse_tap_name(tap)?;
-    Ok(taps_path().join(user).join(repo))
+    Ok(normalize(tap)?.dir_in(&cellar::detect_prefix()))
+}
+
+/// Split a tap-qualified formula name (`user/repo/name`) into its tap and short name.
+///
+/// Returns `None` for plain names and for names whose tap part isn't a valid tap name.
+///
+/// # Examples
+///
+/// ```
+/// use kombrucha::tap;
+///
+/// let (tap, name) = tap::split_qualified("user/repo/foo").unwrap();
+/// assert_eq!((tap.to_string().as_str(), name), ("user/repo", "foo"));
+/// assert!(tap::split_qualified("foo").is_none());
+/// ```
+pub fn split_qualified(name: &str) -> Option<(TapName, &str)> {
+    let (tap, short) = name.rsplit_once('/')?;
+    Some((normalize(tap).ok()?, short))
+}
+
+/// Ruby file of a formula in a tap under `prefix`, flat (`Formula/foo.rb`) or
+/// letter-organized (`Formula/f/foo.rb`), if the tap has one
+pub fn formula_file_in(prefix: &Path, tap: &TapName, name: &str) -> Option<PathBuf> {
+    rb_file(&tap.dir_in(prefix).join("Formula"), name)
+}
+
+/// Ruby file of a cask in a tap under `prefix`, flat (`Casks/foo.rb`) or
+/// letter-organized (`Casks/f/foo.rb`), if the tap has one
+pub fn cask_file_in(prefix: &Path, tap: &TapName, token: &str) -> Option<PathBuf> {
+    rb_file(&tap.dir_in(prefix).join("Casks"), token)
+}
+
+/// `dir/<name>.rb` or `dir/<first letter>/<name>.rb`, whichever exists
+fn rb_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let letter = name.chars().next()?.to_ascii_lowercase().to_string();
+    let file = format!("{}.rb", name);
+    [dir.join(&file), dir.join(letter).join(&file)]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Find the Ruby source of a cask in the installed taps.
+///
+/// `user/repo/token` only looks in that tap. A plain token is looked up in
+/// homebrew/cask first, then in the other taps in name order.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::tap;
+///
+/// if let Some(path) = tap::find_cask_file("firefox") {
+///     println!("{}", std::fs::read_to_string(path).unwrap());
+/// }
+/// ```
+pub fn find_cask_file(token: &str) -> Option<PathBuf> {
+    find_cask_file_in(&crate::cellar::detect_prefix(), token)
+}
+
+/// Find the Ruby source of a cask in the taps under an explicit prefix.
+///
+/// See [`find_cask_file`].
+pub fn find_cask_file_in(prefix: &Path, token: &str) -> Option<PathBuf> {
+    if let Some((tap, token)) = split_qualified(token) {
+        return cask_file_in(prefix, &tap, token);
+    }
+
+    let cask_tap = normalize("homebrew/cask").ok()?;
+    cask_file_in(prefix, &cask_tap, token).or_else(|| {
+        list_taps_in(prefix)
+            .ok()?
+            .iter()
+            .filter_map(|tap| normalize(tap).ok())
+            .filter(|tap| *tap != cask_tap)
+            .find_map(|tap| cask_file_in(prefix, &tap, token))
+    })
 }
 
 /// List all installed Homebrew taps.
@@ -136,7 +275,14 @@ pub fn tap_directory(tap: &str) -> Result<PathBuf> {
 /// }
 /// ```
 pub fn list_taps() -> Result<Vec<String>> {
-    let taps_dir = taps_path();
+    list_taps_in(&crate::cellar::detect_prefix())
+}
+
+/// List the taps installed under an explicit prefix.
+///
+/// See [`list_taps`].
+pub fn list_taps_in(prefix: &Path) -> Result<Vec<String>> {
+    let taps_dir = prefix.join("Library/Taps");
 
     if !taps_dir.exists() {
         return Ok(vec![]);
@@ -175,6 +321,195 @@ pub fn list_taps() -> Result<Vec<String>> {
     Ok(taps)
 }
 
+/// Summary of an installed tap, as shown by `bru tap-info`
+#[derive(Debug, Clone)]
+pub struct TapInfo {
+    /// Tap name as given (`user/repo`)
+    #[allow(dead_code)]
+    pub name: String,
+    /// Tap repository directory
+    pub path: PathBuf,
+    /// URL of the `origin` remote, if the repository has one
+    pub remote: Option<String>,
+    /// Commit the tap is checked out at
+    pub head: Option<String>,
+    /// Number of `.rb` files under `Formula/`
+    pub formula_count: usize,
+    /// Number of `.rb` files under `Casks/`
+    pub cask_count: usize,
+    /// Whether the tap is pinned (excluded from updates)
+    pub pinned: bool,
+}
+
+/// Get location, revision, contents, and pin state of an installed tap.
+///
+/// Remote and revision are read with `git` and are `None` if it is unavailable.
+///
+/// # Errors
+///
+/// Returns an error if the tap name is invalid or the tap is not installed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::tap;
+///
+/// fn main() -> anyhow::Result<()> {
+///     let info = tap::info("user/repo")?;
+///     println!("{}: {} formulae, {} casks", info.name, info.formula_count, info.cask_count);
+///     Ok(())
+/// }
+/// ```
+pub fn info(tap: &str) -> Result<TapInfo> {
+    info_in(&cellar::detect_prefix(), tap)
+}
+
+/// Get information about a tap installed under an explicit prefix.
+///
+/// See [`info`].
+pub fn info_in(prefix: &Path, tap: &str) -> Result<TapInfo> {
+    let path = installed_tap_dir(prefix, tap)?;
+
+    let git = |args: &[&str]| {
+        Command::new("git")
+            .arg("-C")
+            .arg(&path)
+            .args(args)
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    Ok(TapInfo {
+        name: tap.to_string(),
+        remote: git(&["remote", "get-url", "origin"]),
+        head: git(&["rev-parse", "HEAD"]),
+        formula_count: rb_names(&path.join("Formula")).len(),
+        cask_count: rb_names(&path.join("Casks")).len(),
+        pinned: pin_file_in(prefix, tap)?.exists(),
+        path,
+    })
+}
+
+/// Path of the marker file that pins a tap (`Library/PinnedTaps/user--repo`)
+///
+/// # Errors
+///
+/// Returns an error if the tap name is invalid.
+pub fn pin_file(tap: &str) -> Result<PathBuf> {
+    pin_file_in(&cellar::detect_prefix(), tap)
+}
+
+fn pin_file_in(prefix: &Path, tap: &str) -> Result<PathBuf> {
+    let tap = normalize(tap)?;
+    Ok(prefix
+        .join("Library/PinnedTaps")
+        .join(format!("{}--{}", tap.user, tap.repo)))
+}
+
+/// List the formula names a tap provides, sorted.
+///
+/// Names come from the `.rb` files under `Formula/`, including letter subdirectories
+/// (`Formula/r/ripgrep.rb`) as used by homebrew/core.
+///
+/// # Errors
+///
+/// Returns an error if the tap name is invalid or the tap is not installed.
+///
+/// # Examples
+///
+/// ```no_run
+/// use kombrucha::tap;
+///
+/// fn main() -> anyhow::Result<()> {
+///     for name in tap::list_formulae("user/repo")? {
+///         println!("user/repo/{}", name);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[allow(dead_code)]
+pub fn list_formulae(tap: &str) -> Result<Vec<String>> {
+    list_formulae_in(&cellar::detect_prefix(), tap)
+}
+
+/// List the formula names of a tap installed under an explicit prefix.
+///
+/// See [`list_formulae`].
+#[allow(dead_code)]
+pub fn list_formulae_in(prefix: &Path, tap: &str) -> Result<Vec<String>> {
+    Ok(rb_names(&installed_tap_dir(prefix, tap)?.join("Formula")))
+}
+
+/// List the cask tokens a tap provides, sorted.
+///
+/// The cask counterpart of [`list_formulae`], reading `Casks/`.
+///
+/// # Errors
+///
+/// Returns an error if the tap name is invalid or the tap is not installed.
+#[allow(dead_code)]
+pub fn list_casks(tap: &str) -> Result<Vec<String>> {
+    list_casks_in(&cellar::detect_prefix(), tap)
+}
+
+/// List the cask tokens of a tap installed under an explicit prefix.
+///
+/// See [`list_casks`].
+#[allow(dead_code)]
+pub fn list_casks_in(prefix: &Path, tap: &str) -> Result<Vec<String>> {
+    Ok(rb_names(&installed_tap_dir(prefix, tap)?.join("Casks")))
+}
+
+/// Repository directory of a tap under `prefix`, or an error if it isn't tapped
+fn installed_tap_dir(prefix: &Path, tap: &str) -> Result<PathBuf> {
+    let path = normalize(tap)?.dir_in(prefix);
+    if !path.join(".git").exists() {
+        return Err(anyhow!("Tap {} is not installed", tap));
+    }
+    Ok(path)
+}
+
+/// Sorted stems of the .rb files in a directory, flat or letter-organized
+fn rb_names(dir: &Path) -> Vec<String> {
+    let mut names: Vec<String> = rb_files(dir)
+        .iter()
+        .filter_map(|path| path.file_stem()?.to_str().map(String::from))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Paths of the .rb files in a directory, flat or letter-organized
+pub(crate) fn rb_files(dir: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    collect_rb_files(dir, 0, &mut files);
+    files
+}
+
+/// Recursively collect .rb files (helper for rb_files)
+/// Limits recursion depth to prevent infinite loops
+fn collect_rb_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    const MAX_DEPTH: usize = 10;
+    if depth > MAX_DEPTH {
+        return;
+    }
+
+    if let Ok(entries) = fs::read_dir(dir) {
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("rb") {
+                files.push(path);
+            } else if path.is_dir() {
+                collect_rb_files(&path, depth + 1, files);
+            }
+        }
+    }
+}
+
 /// Check if a tap is installed
 pub fn is_tapped(tap: &str) -> Result<bool> {
     let tap_dir = tap_directory(tap)?;
@@ -219,22 +554,22 @@ pub fn is_tapped(tap: &str) -> Result<bool> {
 /// - Cloned from: `https://github.com/user/homebrew-repo.git`
 /// - Stored in: `/opt/homebrew/Library/Taps/user/homebrew-repo/`
 pub fn tap(tap_name: &str) -> Result<()> {
-    let (user, repo) = parse_tap_name(tap_name)?;
-    let tap_dir = tap_directory(tap_name)?;
+    let name = normalize(tap_name)?;
+    let tap_dir = name.dir_in(&cellar::detect_prefix());
 
     if tap_dir.exists() {
-        return Err(anyhow!("Tap {}/{} already exists", user, repo));
+        return Err(anyhow!("Tap {} already exists", name));
     }
 
     // Create user directory if it doesn't exist
-    let user_dir = taps_path().join(&user);
+    let user_dir = taps_path().join(&name.user);
     if !user_dir.exists() {
         fs::create_dir_all(&user_dir)
             .with_context(|| format!("Failed to create directory: {}", user_dir.display()))?;
     }
 
     // Clone the repository
-    let git_url = format!("https://github.com/{}/{}.git", user, repo);
+    let git_url = name.remote_url();
 
     let tap_dir_str = tap_dir
         .to_str()
@@ -302,6 +637,32 @@ pub fn untap(tap_name: &str) -> Result<()> {
     Ok(())
 }
 
+/// List installed formulae whose install receipt says they came from `tap_name`.
+///
+/// Removing such a tap leaves the kegs installed but without a formula to upgrade or
+/// reinstall from, so `untap` checks this first. Tap names compare case-insensitively
+/// and with or without the `homebrew-` repo prefix. Each formula is listed once, however many versions are installed.
+///
+/// # Errors
+///
+/// Returns an error if the tap name format is invalid or the Cellar can't be read.
+pub fn installed_formulae_from_tap(tap_name: &str) -> Result<Vec<String>> {
+    let wanted = normalize(tap_name)?;
+
+    let mut formulae: Vec<String> = cellar::list_installed()?
+        .into_iter()
+        .filter(|pkg| {
+            pkg.tap()
+                .is_some_and(|tap| normalize(tap).is_ok_and(|tap| tap == wanted))
+        })
+        .map(|pkg| pkg.name)
+        .collect();
+    formulae.sort();
+    formulae.dedup();
+
+    Ok(formulae)
+}
+
 /// Get the formula file path for a package in a tap
 /// Returns the path even if the file doesn't exist
 pub fn formula_path(tap_name: &str, formula_name: &str) -> Result<PathBuf> {
@@ -365,105 +726,146 @@ pub fn parse_formula_version(formula_path: &Path) -> Result<Option<String>> {
         }
 
         // Look for: url ".../{version}.tar.gz"
-        // Common patterns:
-        //   url "https://github.com/user/repo/archive/refs/tags/v1.2.3.tar.gz"
-        //   url "https://example.com/download/1.2.3/file.tar.gz"
-        if line.starts_with("url ")
-            && line.contains('"')
-            && let Some(start) = line.find('"')
-            && let Some(end) = line[start + 1..].find('"')
+        if let Some(url) = quoted_arg(line, "url", false)
+            && let Some(version) = version_from_url(&url)
         {
-            let url = &line[start + 1..start + 1 + end];
-
-            // Extract version from common URL patterns
-            // Pattern 1: /tags/v1.2.3.tar.gz or /tags/1.2.3.tar.gz
-            // Also handles: /tags/@scope/package@1.2.3.tar.gz
-            if let Some(tags_idx) = url.rfind("/tags/") {
-                let after_tags = &url[tags_idx + 6..];
-                if let Some(dot_tar) = after_tags.find(".tar") {
-                    let mut version_str = &after_tags[..dot_tar];
-                    // Handle npm-style tags: @scope/package@1.2.3 -> extract 1.2.3
-                    if let Some(at_idx) = version_str.rfind('@') {
-                        version_str = &version_str[at_idx + 1..];
-                    }
-                    // Strip leading 'v' if present
-                    let version = version_str.strip_prefix('v').unwrap_or(version_str);
-                    return Ok(Some(version.to_string()));
-                }
+            return Ok(Some(version));
+        }
+
+        // Look for: url "....git", tag: "v1.2.3" (possibly on its own line)
+        if (line.starts_with("url ") || line.starts_with("tag:"))
+            && let Some(version) = tag_arg(line).as_deref().and_then(version_from_tag)
+        {
+            return Ok(Some(version));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Infer a version from a source URL
+///
+/// Common patterns:
+///   url "https://github.com/user/repo/archive/refs/tags/v1.2.3.tar.gz"
+///   url "https://example.com/download/1.2.3/file.tar.gz"
+///   url "https://example.com/pool/tool_1.2.3.orig.tar.gz"
+fn version_from_url(url: &str) -> Option<String> {
+    // Extract version from common URL patterns
+    // Pattern 1: /tags/v1.2.3.tar.gz or /tags/1.2.3.tar.gz
+    // Also handles: /tags/@scope/package@1.2.3.tar.gz
+    if let Some(tags_idx) = url.rfind("/tags/") {
+        let after_tags = &url[tags_idx + 6..];
+        if let Some(stem) = archive_stem(after_tags) {
+            let mut version_str = stem;
+            // Handle npm-style tags: @scope/package@1.2.3 -> extract 1.2.3
+            if let Some(at_idx) = version_str.rfind('@') {
+                version_str = &version_str[at_idx + 1..];
             }
+            // Strip leading 'v' if present
+            let version = version_str.strip_prefix('v').unwrap_or(version_str);
+            return Some(version.to_string());
+        }
+    }
 
-            // Pattern 2: /archive/v1.2.3.tar.gz or /archive/1.2.3.tar.gz
-            if let Some(archive_idx) = url.rfind("/archive/") {
-                let after_archive = &url[archive_idx + 9..];
-                if let Some(dot_tar) = after_archive.find(".tar") {
-                    let version_str = &after_archive[..dot_tar];
-                    // Strip leading 'v' and 'refs/tags/' if present
-                    let version = version_str
+    // Pattern 2: /archive/v1.2.3.tar.gz or /archive/1.2.3.tar.gz
+    if let Some(archive_idx) = url.rfind("/archive/") {
+        let after_archive = &url[archive_idx + 9..];
+        if let Some(version_str) = archive_stem(after_archive) {
+            // Strip leading 'v' and 'refs/tags/' if present
+            let version = version_str
+                .strip_prefix("refs/tags/")
+                .unwrap_or(version_str)
+                .strip_prefix('v')
+                .unwrap_or(
+                    version_str
                         .strip_prefix("refs/tags/")
-                        .unwrap_or(version_str)
-                        .strip_prefix('v')
-                        .unwrap_or(
-                            version_str
-                                .strip_prefix("refs/tags/")
-                                .unwrap_or(version_str),
-                        );
-                    return Ok(Some(version.to_string()));
-                }
-            }
+                        .unwrap_or(version_str),
+                );
+            return Some(version.to_string());
+        }
+    }
 
-            // Pattern 3: /{name}-{version}.tar.gz (e.g., git-2.52.0.tar.xz)
-            // Extract the last path component
-            if let Some(last_slash) = url.rfind('/') {
-                let filename = &url[last_slash + 1..];
-                // Look for pattern: name-version.tar.*
-                if let Some(tar_idx) = filename.find(".tar") {
-                    let name_version = &filename[..tar_idx];
-                    // Find the last dash to split name from version
-                    if let Some(last_dash) = name_version.rfind('-') {
-                        let potential_version = &name_version[last_dash + 1..];
-                        // Check if it looks like a version (starts with digit)
-                        if potential_version
-                            .chars()
-                            .next()
-                            .is_some_and(|c| c.is_ascii_digit())
-                        {
-                            let version = potential_version
-                                .strip_prefix('v')
-                                .unwrap_or(potential_version);
-                            return Ok(Some(version.to_string()));
-                        }
+    // Pattern 3: /{name}-{version}.tar.gz (e.g., git-2.52.0.tar.xz), or Debian's
+    // /{name}_{version}.orig.tar.gz
+    // Extract the last path component
+    if let Some(last_slash) = url.rfind('/') {
+        let filename = &url[last_slash + 1..];
+        // Look for pattern: name-version.tar.*
+        if let Some(name_version) = archive_stem(filename) {
+            let name_version = name_version.strip_suffix(".orig").unwrap_or(name_version);
+            // Split name from version at the last dash, or the last underscore
+            for separator in ['-', '_'] {
+                if let Some(idx) = name_version.rfind(separator) {
+                    let potential_version = &name_version[idx + 1..];
+                    // Check if it looks like a version (starts with digit)
+                    if potential_version
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_ascii_digit())
+                    {
+                        return Some(potential_version.to_string());
                     }
                 }
             }
+        }
+    }
 
-            // Pattern 4: npm registry URLs
-            // E.g., https://registry.npmjs.org/@google/gemini-cli/-/gemini-cli-0.16.0.tgz
-            // E.g., https://registry.npmjs.org/opencode-ai/-/opencode-ai-1.0.74.tgz
-            if url.contains("registry.npmjs.org") && url.ends_with(".tgz") {
-                // Find the last path component (e.g., "gemini-cli-0.16.0.tgz")
-                if let Some(last_slash) = url.rfind('/') {
-                    let filename = &url[last_slash + 1..];
-                    // Remove .tgz extension
-                    if let Some(name_version) = filename.strip_suffix(".tgz") {
-                        // Find the last dash to split name from version
-                        if let Some(last_dash) = name_version.rfind('-') {
-                            let potential_version = &name_version[last_dash + 1..];
-                            // Check if it looks like a version
-                            if potential_version
-                                .chars()
-                                .next()
-                                .is_some_and(|c| c.is_ascii_digit())
-                            {
-                                return Ok(Some(potential_version.to_string()));
-                            }
-                        }
+    // Pattern 4: npm registry URLs
+    // E.g., https://registry.npmjs.org/@google/gemini-cli/-/gemini-cli-0.16.0.tgz
+    // E.g., https://registry.npmjs.org/opencode-ai/-/opencode-ai-1.0.74.tgz
+    if url.contains("registry.npmjs.org") && url.ends_with(".tgz") {
+        // Find the last path component (e.g., "gemini-cli-0.16.0.tgz")
+        if let Some(last_slash) = url.rfind('/') {
+            let filename = &url[last_slash + 1..];
+            // Remove .tgz extension
+            if let Some(name_version) = filename.strip_suffix(".tgz") {
+                // Find the last dash to split name from version
+                if let Some(last_dash) = name_version.rfind('-') {
+                    let potential_version = &name_version[last_dash + 1..];
+                    // Check if it looks like a version
+                    if potential_version
+                        .chars()
+                        .next()
+                        .is_some_and(|c| c.is_ascii_digit())
+                    {
+                        return Some(potential_version.to_string());
                     }
                 }
             }
         }
     }
 
-    Ok(None)
+    None
+}
+
+/// The part of a URL path before its archive extension (`.tar.*`, `.tgz` or `.zip`)
+fn archive_stem(path: &str) -> Option<&str> {
+    path.find(".tar")
+        .map(|idx| &path[..idx])
+        .or_else(|| path.strip_suffix(".tgz"))
+        .or_else(|| path.strip_suffix(".zip"))
+}
+
+/// Infer a version from the `tag:` of a git source URL (`v1.2.3`, `release-1.2.3`,
+/// `@scope/package@1.2.3`)
+fn version_from_tag(tag: &str) -> Option<String> {
+    let tag = tag.rsplit('@').next().unwrap_or(tag);
+    let starts_with_digit = |s: &str| s.chars().next().is_some_and(|c| c.is_ascii_digit());
+    let version = tag.strip_prefix('v').unwrap_or(tag);
+    if starts_with_digit(version) {
+        return Some(version.to_string());
+    }
+    tag.rsplit(['-', '_'])
+        .next()
+        .filter(|version| starts_with_digit(version))
+        .map(str::to_string)
+}
+
+/// The `tag: "..."` argument of a `url` line or one of its continuation lines
+fn tag_arg(line: &str) -> Option<String> {
+    let rest = &line[line.find("tag:")? + 4..];
+    let value = &rest[rest.find('"')? + 1..];
+    Some(value[..value.find('"')?].to_string())
 }
 
 /// Get the latest version for a tap formula
@@ -521,9 +923,84 @@ pub fn get_core_formula_version(formula_name: &str) -> Result<Option<String>> {
 #[derive(Debug, Clone)]
 pub struct TapFormulaInfo {
     pub name: String,
+    /// Ruby class from the `class Foo < Formula` declaration
+    pub class_name: Option<String>,
     pub desc: Option<String>,
     pub homepage: Option<String>,
+    /// Explicit `version "..."`; see [`TapFormulaInfo::resolved_version`]
     pub version: Option<String>,
+    /// `revision N`, bumped when the formula changes without a new version (0 if absent)
+    pub revision: u32,
+    /// Stable source URL (the first `url` stanza)
+    pub url: Option<String>,
+    /// `tag:` of a git stable source URL
+    pub tag: Option<String>,
+    /// `head` source URL, from `head "..."` or a `head do` block
+    pub head: Option<String>,
+    /// The `bottle do` block, if the formula has one
+    pub bottle: Option<TapBottleInfo>,
+    /// Runtime dependencies; see [`runtime_dependencies`]
+    pub dependencies: Vec<String>,
+    /// Whether the formula declares `keg_only`
+    pub keg_only: bool,
+}
+
+/// Bottles declared in a formula's `bottle do` block
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TapBottleInfo {
+    /// Explicit `root_url`; bottles live on Homebrew's GHCR registry without one
+    pub root_url: Option<String>,
+    /// `rebuild N`, bumped when bottles are rebuilt for the same version (0 if absent)
+    pub rebuild: u32,
+    /// Checksum for each platform tag (`arm64_sonoma`, `x86_64_linux`, `all`, ...)
+    pub sha256: std::collections::BTreeMap<String, String>,
+}
+
+impl TapFormulaInfo {
+    /// The explicit version, or one inferred from the git tag or source URL like
+    /// [`parse_formula_version`] does.
+    pub fn resolved_version(&self) -> Option<String> {
+        self.version
+            .clone()
+            .or_else(|| self.tag.as_deref().and_then(version_from_tag))
+            .or_else(|| self.url.as_deref().and_then(version_from_url))
+    }
+
+    /// Whether the formula only has a `head` source, with no stable one to version
+    pub fn head_only(&self) -> bool {
+        self.url.is_none() && self.head.is_some()
+    }
+
+    /// The version as Homebrew names kegs and bottles: [`resolved_version`] with a
+    /// `_N` suffix when the formula has a revision.
+    ///
+    /// [`resolved_version`]: TapFormulaInfo::resolved_version
+    pub fn pkg_version(&self) -> Option<String> {
+        let version = self.resolved_version()?;
+        Some(match self.revision {
+            0 => version,
+            revision => format!("{}_{}", version, revision),
+        })
+    }
+
+    /// Metadata problems that make the formula unusable: a missing class declaration,
+    /// source URL, or version. Head-only formulae need neither of the latter.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.class_name.is_none() {
+            problems.push("missing `class ... < Formula` declaration".to_string());
+        }
+        if self.head_only() {
+            return problems;
+        }
+        if self.url.is_none() {
+            problems.push("missing url".to_string());
+        }
+        if self.resolved_version().is_none() {
+            problems.push("no version (none declared and none in the url)".to_string());
+        }
+        problems
+    }
 }
 
 /// Parse complete metadata from a Ruby formula file.
@@ -564,9 +1041,16 @@ pub struct TapFormulaInfo {
 /// # Parsed Fields
 ///
 /// Looks for these patterns in the Ruby file:
+/// - `class Foo < Formula` - Class declaration
 /// - `desc "..."` - Package description
 /// - `homepage "https://..."` - Project homepage
 /// - `version "X.Y.Z"` - Package version
+/// - `revision N` - Formula revision
+/// - `url "https://..."` - Stable source URL, and its `tag:` for git URLs
+/// - `head "https://..."` or `head do ... end` - Head source URL
+/// - `bottle do ... end` - `root_url`, `rebuild` and per-platform `sha256` lines
+/// - `depends_on "..."` - Runtime dependencies
+/// - `keg_only ...` - Keg-only declaration
 pub fn parse_formula_info(formula_path: &Path, formula_name: &str) -> Result<TapFormulaInfo> {
     if !formula_path.exists() {
         return Err(anyhow::anyhow!(
@@ -578,49 +1062,282 @@ pub fn parse_formula_info(formula_path: &Path, formula_name: &str) -> Result<Tap
     let contents = fs::read_to_string(formula_path)
         .with_context(|| format!("Failed to read formula: {}", formula_path.display()))?;
 
+    let mut class_name = None;
     let mut desc = None;
     let mut homepage = None;
     let mut version = None;
+    let mut revision = 0;
+    let mut url = None;
+    let mut tag = None;
+    let mut url_continues = false;
+    let mut head = None;
+    let mut in_head = false;
+    let mut bottle: Option<TapBottleInfo> = None;
+    let mut in_bottle = false;
 
     for line in contents.lines() {
         let line = line.trim();
 
-        // Parse: desc "Description text"
-        if line.starts_with("desc ")
-            && line.contains('"')
-            && let Some(start) = line.find('"')
-            && let Some(end) = line[start + 1..].rfind('"')
+        // Parse: bottle do / root_url "..." / sha256 cellar: :any, arm64_sonoma: "..." / end
+        if line == "bottle do" {
+            in_bottle = true;
+            bottle.get_or_insert_with(TapBottleInfo::default);
+            continue;
+        }
+        if in_bottle && let Some(bottle) = bottle.as_mut() {
+            if line == "end" {
+                in_bottle = false;
+            } else if let Some(value) = quoted_arg(line, "root_url", false) {
+                bottle.root_url = Some(value);
+            } else if let Some(rebuild) = line
+                .strip_prefix("rebuild ")
+                .and_then(|n| n.trim().parse().ok())
+            {
+                bottle.rebuild = rebuild;
+            } else if let Some(rest) = line.strip_prefix("sha256 ") {
+                for (tag, sha256) in rest.split(',').filter_map(|field| field.split_once(':')) {
+                    let tag = tag.trim();
+                    if tag != "cellar" {
+                        bottle
+                            .sha256
+                            .insert(tag.to_string(), sha256.trim().trim_matches('"').to_string());
+                    }
+                }
+            }
+            continue;
+        }
+
+        // Parse: tag: "v1.2.3" continuing a git url "...", line
+        if url_continues {
+            if tag.is_none() {
+                tag = tag_arg(line);
+            }
+            url_continues = line.ends_with(',');
+            continue;
+        }
+
+        // Parse: head "https://..." / head do / url "..." / end
+        if line == "head do" {
+            in_head = true;
+            continue;
+        }
+        if in_head {
+            if line == "end" {
+                in_head = false;
+            } else if head.is_none() {
+                head = quoted_arg(line, "url", false);
+            }
+            continue;
+        }
+        if let Some(value) = quoted_arg(line, "head", false) {
+            head = Some(value);
+        }
+
+        // Parse: class Foo < Formula
+        if class_name.is_none()
+            && let Some((class, parent)) = line
+                .strip_prefix("class ")
+                .and_then(|rest| rest.split_once('<'))
+            && parent.trim() == "Formula"
         {
-            desc = Some(line[start + 1..start + 1 + end].to_string());
+            class_name = Some(class.trim().to_string());
+        }
+
+        // Parse: desc "Description text"
+        if let Some(value) = quoted_arg(line, "desc", true) {
+            desc = Some(value);
         }
 
         // Parse: homepage "https://..."
-        if line.starts_with("homepage ")
-            && line.contains('"')
-            && let Some(start) = line.find('"')
-            && let Some(end) = line[start + 1..].rfind('"')
-        {
-            homepage = Some(line[start + 1..start + 1 + end].to_string());
+        if let Some(value) = quoted_arg(line, "homepage", true) {
+            homepage = Some(value);
         }
 
         // Parse: version "X.Y.Z"
-        if line.starts_with("version ")
-            && line.contains('"')
-            && let Some(start) = line.find('"')
-            && let Some(end) = line[start + 1..].find('"')
+        if let Some(value) = quoted_arg(line, "version", false) {
+            version = Some(value);
+        }
+
+        // Parse: revision N
+        if let Some(n) = line
+            .strip_prefix("revision ")
+            .and_then(|n| n.trim().parse().ok())
+        {
+            revision = n;
+        }
+
+        // Parse: url "https://..." (resource and head URLs come later)
+        if url.is_none()
+            && let Some(value) = quoted_arg(line, "url", false)
         {
-            version = Some(line[start + 1..start + 1 + end].to_string());
+            url = Some(value);
+            tag = tag_arg(line);
+            url_continues = line.ends_with(',');
         }
     }
 
     Ok(TapFormulaInfo {
         name: formula_name.to_string(),
+        class_name,
         desc,
         homepage,
         version,
+        revision,
+        url,
+        tag,
+        head,
+        bottle,
+        dependencies: runtime_dependencies(&contents),
+        keg_only: contents
+            .lines()
+            .any(|line| line.trim_start().starts_with("keg_only")),
     })
 }
 
+/// Runtime `depends_on "<formula>"` entries in a formula's Ruby source, plus
+/// `uses_from_macos "<formula>"` ones on Linux
+///
+/// Build, test and optional dependencies are left out, and so are those in
+/// `on_macos`/`on_linux`/`on_arm`/`on_intel` blocks for another platform than the one
+/// bottles are installed for (see [`crate::platform::bottle_tag`]).
+pub fn runtime_dependencies(source: &str) -> Vec<String> {
+    let tag = crate::platform::bottle_tag().unwrap_or_default();
+    runtime_dependencies_for(source, &tag)
+}
+
+/// [`runtime_dependencies`] for the platform a bottle tag (`arm64_sonoma`,
+/// `x86_64_linux`, ...) names
+pub fn runtime_dependencies_for(source: &str, tag: &str) -> Vec<String> {
+    let linux = tag.ends_with("_linux");
+    let arm = tag.starts_with("arm64");
+
+    // Whether each enclosing `do ... end` block applies to this platform
+    let mut blocks: Vec<bool> = Vec::new();
+    let mut dependencies = Vec::new();
+    for line in source.lines().map(str::trim) {
+        if line == "end" {
+            blocks.pop();
+            continue;
+        }
+        if line.ends_with(" do") || (line.contains(" do |") && line.ends_with('|')) {
+            let applies = match line.split_whitespace().next().unwrap_or_default() {
+                "on_macos" => !linux,
+                "on_linux" => linux,
+                "on_arm" => arm,
+                "on_intel" => !arm,
+                // HEAD-only dependencies don't apply to bottles
+                "head" => false,
+                // Other on_* blocks name macOS releases (on_sonoma :or_newer, ...)
+                block => !(block.starts_with("on_") && linux),
+            };
+            blocks.push(applies);
+            continue;
+        }
+        // Anything else closed by `end` applies wherever its enclosing block does
+        let keyword = line.split_whitespace().next().unwrap_or_default();
+        if [
+            "class", "module", "def", "if", "unless", "case", "begin", "while", "until",
+        ]
+        .contains(&keyword)
+        {
+            blocks.push(true);
+            continue;
+        }
+        if blocks.contains(&false) {
+            continue;
+        }
+
+        // `uses_from_macos` names a formula only needed where macOS doesn't provide it
+        let Some((name, tail)) = line
+            .strip_prefix("depends_on \"")
+            .or_else(|| line.strip_prefix("uses_from_macos \"").filter(|_| linux))
+            .and_then(|rest| rest.split_once('"'))
+        else {
+            continue;
+        };
+        let skipped = [":build", ":test", ":optional"]
+            .iter()
+            .any(|tag| tail.contains(tag));
+        if !skipped {
+            dependencies.push(name.to_string());
+        }
+    }
+    dependencies
+}
+
+/// Tap cask metadata extracted from Ruby file
+#[allow(dead_code)]
+#[derive(Debug, Clone)]
+pub struct TapCaskInfo {
+    pub token: String,
+    /// Display names from `name "..."` stanzas
+    pub name: Vec<String>,
+    pub desc: Option<String>,
+    pub homepage: Option<String>,
+    pub version: Option<String>,
+}
+
+/// Parse metadata from a Ruby cask file.
+///
+/// The cask counterpart of [`parse_formula_info`]: extracts display names, description,
+/// homepage, and version from a tap's `Casks/<token>.rb`.
+///
+/// # Errors
+///
+/// Returns an error if the file doesn't exist or cannot be read.
+#[allow(dead_code)]
+pub fn parse_cask_info(cask_path: &Path, token: &str) -> Result<TapCaskInfo> {
+    if !cask_path.exists() {
+        return Err(anyhow::anyhow!(
+            "Cask file not found: {}",
+            cask_path.display()
+        ));
+    }
+
+    let contents = fs::read_to_string(cask_path)
+        .with_context(|| format!("Failed to read cask: {}", cask_path.display()))?;
+
+    let mut info = TapCaskInfo {
+        token: token.to_string(),
+        name: Vec::new(),
+        desc: None,
+        homepage: None,
+        version: None,
+    };
+
+    for line in contents.lines() {
+        let line = line.trim();
+
+        if let Some(value) = quoted_arg(line, "name", true) {
+            info.name.push(value);
+        } else if let Some(value) = quoted_arg(line, "desc", true) {
+            info.desc = Some(value);
+        } else if let Some(value) = quoted_arg(line, "homepage", true) {
+            info.homepage = Some(value);
+        } else if let Some(value) = quoted_arg(line, "version", false) {
+            info.version = Some(value);
+        }
+    }
+
+    Ok(info)
+}
+
+/// Extract the string argument of a `keyword "value"` line.
+///
+/// With `to_last_quote`, the value runs to the last quote on the line, which keeps
+/// escaped quotes in descriptions intact.
+fn quoted_arg(line: &str, keyword: &str, to_last_quote: bool) -> Option<String> {
+    let rest = line.strip_prefix(keyword)?.strip_prefix(' ')?;
+    let start = rest.find('"')?;
+    let value = &rest[start + 1..];
+    let end = if to_last_quote {
+        value.rfind('"')?
+    } else {
+        value.find('"')?
+    };
+    Some(value[..end].to_string())
+}
+
 /// Check if an installed package is from a tap (based on receipt)
 /// Returns (tap_name, formula_path, installed_version) if from a tap, None otherwise
 pub fn get_package_tap_info(cellar_path: &Path) -> Result<Option<(String, PathBuf, String)>> {
@@ -633,10 +1350,12 @@ pub fn get_package_tap_info(cellar_path: &Path) -> Result<Option<(String, PathBu
         None => return Ok(None),
     };
 
-    // homebrew/core is not considered a "tap" for upgrade purposes
-    if source.tap == "homebrew/core" {
-        return Ok(None);
-    }
+    // homebrew/core is not considered a "tap" for upgrade purposes, nor is a formula
+    // file outside any tap
+    let tap = match source.tap {
+        Some(tap) if tap != "homebrew/core" => tap,
+        _ => return Ok(None),
+    };
 
     let path = match source.path {
         Some(p) => PathBuf::from(p),
@@ -645,28 +1364,517 @@ pub fn get_package_tap_info(cellar_path: &Path) -> Result<Option<(String, PathBu
 
     let installed_version = source.versions.and_then(|v| v.stable).unwrap_or_default();
 
-    Ok(Some((source.tap, path, installed_version)))
+    Ok(Some((tap, path, installed_version)))
+}
+
+/// A [`FormulaSource`] that looks up tap-qualified names (`user/repo/name`) in the tap's
+/// local Ruby files and hands everything else to `inner`, normally the API.
+///
+/// `homebrew/core/name` and `homebrew/cask/token` are passed on unqualified.
+pub struct TapSource<'a> {
+    inner: &'a dyn FormulaSource,
+    prefix: PathBuf,
+}
+
+impl<'a> TapSource<'a> {
+    /// Look up tap formulae under the detected prefix
+    pub fn new(inner: &'a dyn FormulaSource) -> Self {
+        Self::with_prefix(inner, cellar::detect_prefix())
+    }
+
+    /// Look up tap formulae under an explicit prefix
+    pub fn with_prefix(inner: &'a dyn FormulaSource, prefix: PathBuf) -> Self {
+        Self { inner, prefix }
+    }
+
+    /// Formula metadata from the tap's Ruby file
+    ///
+    /// Only what the file declares is filled in: version, description, homepage,
+    /// runtime dependencies and keg-only status. `full_name` keeps the tap.
+    fn tap_formula(&self, tap: &TapName, name: &str) -> crate::error::Result<Formula> {
+        let full_name = format!("{}/{}", tap, name);
+        let path = formula_file_in(&self.prefix, tap, name)
+            .ok_or_else(|| crate::error::BruError::FormulaNotFound(full_name.clone()))?;
+        let info = parse_formula_info(&path, name)?;
+        let formula = serde_json::from_value(serde_json::json!({
+            "name": name,
+            "full_name": full_name,
+            "desc": info.desc,
+            "homepage": info.homepage,
+            "versions": {"stable": info.resolved_version(), "bottle": info.bottle.is_some()},
+            "dependencies": info.dependencies,
+            "keg_only": info.keg_only,
+        }))
+        .map_err(anyhow::Error::from)?;
+        Ok(formula)
+    }
+}
+
+impl FormulaSource for TapSource<'_> {
+    fn formula<'a>(&'a self, name: &'a str) -> BoxFuture<'a, crate::error::Result<Formula>> {
+        match split_qualified(name) {
+            Some((tap, short)) if tap.is_core() => self.inner.formula(short),
+            Some((tap, short)) => {
+                let formula = self.tap_formula(&tap, short);
+                Box::pin(async move { formula })
+            }
+            None => self.inner.formula(name),
+        }
+    }
+
+    fn cask<'a>(&'a self, token: &'a str) -> BoxFuture<'a, crate::error::Result<Cask>> {
+        match split_qualified(token) {
+            Some((tap, short)) if tap.user == "homebrew" && tap.repo == "cask" => {
+                self.inner.cask(short)
+            }
+            Some(_) => {
+                let token = token.to_string();
+                Box::pin(async move { Err(crate::error::BruError::CaskNotFound(token)) })
+            }
+            None => self.inner.cask(token),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::MockSource;
+
+    #[test]
+    fn test_normalize_tap_name() {
+        let tap = normalize("user/repo").unwrap();
+        assert_eq!(tap.user, "user");
+        assert_eq!(tap.repo, "repo");
+        assert_eq!(tap.full_repo, "homebrew-repo");
+
+        assert_eq!(normalize("user/homebrew-repo").unwrap(), tap);
+        assert_eq!(normalize("User/Homebrew-Repo").unwrap(), tap);
+        assert_eq!(tap.to_string(), "user/repo");
+    }
+
+    #[test]
+    fn test_normalize_tap_name_invalid() {
+        assert!(normalize("invalid").is_err());
+        assert!(normalize("too/many/slashes").is_err());
+        assert!(normalize("/repo").is_err());
+        assert!(normalize("user/").is_err());
+        assert!(normalize("user/homebrew-").is_err());
+        assert!(normalize("user/re po").is_err());
+        assert!(normalize("../repo").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_tap_source_routes_qualified_names() {
+        let prefix = tempfile::TempDir::new().unwrap();
+        let formula_dir = prefix
+            .path()
+            .join("Library/Taps/user/homebrew-repo/Formula/f");
+        fs::create_dir_all(&formula_dir).unwrap();
+        fs::write(
+            formula_dir.join("foo.rb"),
+            "class Foo < Formula\n  url \"https://example.com/foo-1.2.tar.gz\"\n  \
+             keg_only :provided_by_macos\n  depends_on \"bar\"\n  \
+             depends_on \"cmake\" => :build\nend\n",
+        )
+        .unwrap();
+
+        // Stands in for the API; its jq says where it came from
+        let api = MockSource::new(
+            vec![
+                serde_json::from_value(serde_json::json!({ "name": "jq", "desc": "from the API" }))
+                    .unwrap(),
+            ],
+            &[],
+        );
+        let source = TapSource::with_prefix(&api, prefix.path().to_path_buf());
+        let foo = source.formula("User/homebrew-repo/foo").await.unwrap();
+        assert_eq!(foo.name, "foo");
+        assert_eq!(foo.full_name, "user/repo/foo");
+        assert_eq!(foo.tap(), "user/repo");
+        assert_eq!(foo.versions.stable.as_deref(), Some("1.2"));
+        assert_eq!(foo.dependencies, vec!["bar"]);
+        assert!(foo.keg_only);
+
+        let not_found = |result: crate::error::Result<Formula>| match result {
+            Err(crate::error::BruError::FormulaNotFound(name)) => name,
+            other => panic!("expected FormulaNotFound, got {:?}", other.map(|f| f.name)),
+        };
+        assert_eq!(
+            not_found(source.formula("user/repo/bar").await),
+            "user/repo/bar"
+        );
+        for name in ["homebrew/core/jq", "jq"] {
+            let jq = source.formula(name).await.unwrap();
+            assert_eq!(jq.desc.as_deref(), Some("from the API"), "{}", name);
+        }
+        assert_eq!(not_found(source.formula("wget").await), "wget");
+    }
+
+    #[test]
+    fn test_tap_info_counts_contents_and_pin() {
+        let prefix = tempfile::TempDir::new().unwrap();
+        let tap_dir = prefix.path().join("Library/Taps/user/homebrew-repo");
+        fs::create_dir_all(tap_dir.join(".git")).unwrap();
+        fs::create_dir_all(tap_dir.join("Formula/a")).unwrap();
+        fs::create_dir_all(tap_dir.join("Casks")).unwrap();
+        fs::write(tap_dir.join("Formula/foo.rb"), "").unwrap();
+        fs::write(tap_dir.join("Formula/a/abc.rb"), "").unwrap();
+        fs::write(tap_dir.join("Formula/README.md"), "").unwrap();
+        fs::write(tap_dir.join("Casks/app.rb"), "").unwrap();
+
+        let info = info_in(prefix.path(), "user/repo").unwrap();
+        assert_eq!(info.path, tap_dir);
+        assert_eq!(info.formula_count, 2);
+        assert_eq!(info.cask_count, 1);
+        assert!(!info.pinned);
+
+        let pin = pin_file_in(prefix.path(), "user/repo").unwrap();
+        assert_eq!(
+            pin,
+            pin_file_in(prefix.path(), "user/homebrew-repo").unwrap()
+        );
+        fs::create_dir_all(pin.parent().unwrap()).unwrap();
+        fs::write(&pin, "").unwrap();
+        assert!(info_in(prefix.path(), "user/repo").unwrap().pinned);
+
+        assert!(info_in(prefix.path(), "user/other").is_err());
+    }
 
     #[test]
-    fn test_parse_tap_name() {
-        let (user, repo) = parse_tap_name("user/repo").unwrap();
-        assert_eq!(user, "user");
-        assert_eq!(repo, "homebrew-repo");
+    fn test_runtime_dependencies_follow_platform_blocks() {
+        let source = r#"class Widget < Formula
+  depends_on "pkgconf" => :build
+  depends_on "openssl@3"
+  uses_from_macos "m4" => :build
+  uses_from_macos "curl"
+
+  on_macos do
+    depends_on "gettext"
+  end
+
+  on_linux do
+    depends_on "zlib"
+    on_intel do
+      depends_on "libunwind"
+    end
+  end
+
+  head do
+    depends_on "autoconf"
+  end
+
+  def install
+    system "make"
+  end
+end
+"#;
+        assert_eq!(
+            runtime_dependencies_for(source, "arm64_sonoma"),
+            ["openssl@3", "gettext"]
+        );
+        assert_eq!(
+            runtime_dependencies_for(source, "x86_64_linux"),
+            ["openssl@3", "curl", "zlib", "libunwind"]
+        );
+        assert_eq!(
+            runtime_dependencies_for(source, "arm64_linux"),
+            ["openssl@3", "curl", "zlib"]
+        );
+        // A Linux-only dependency after a method is still skipped on macOS
+        let source = "def caveats\n  \"\"\nend\non_linux do\n  depends_on \"zlib\"\nend\n";
+        assert!(runtime_dependencies_for(source, "arm64_sonoma").is_empty());
+    }
+
+    #[test]
+    fn test_parse_formula_bottle_block() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("widget.rb");
+        fs::write(
+            &path,
+            r#"class Widget < Formula
+  desc "Widget tool"
+  url "https://example.com/widget-1.2.0.tar.gz"
+  sha256 "0000000000000000000000000000000000000000000000000000000000000000"
+  revision 2
+
+  bottle do
+    root_url "https://example.com/bottles"
+    rebuild 1
+    sha256 cellar: :any_skip_relocation, arm64_sonoma: "aaa"
+    sha256 x86_64_linux: "bbb"
+  end
+end
+"#,
+        )
+        .unwrap();
+
+        let info = parse_formula_info(&path, "widget").unwrap();
+        assert_eq!(info.resolved_version().as_deref(), Some("1.2.0"));
+        assert_eq!(info.pkg_version().as_deref(), Some("1.2.0_2"));
+        let bottle = info.bottle.unwrap();
+        assert_eq!(bottle.rebuild, 1);
+        assert_eq!(
+            bottle.root_url.as_deref(),
+            Some("https://example.com/bottles")
+        );
+        assert_eq!(
+            bottle.sha256.into_iter().collect::<Vec<_>>(),
+            vec![
+                ("arm64_sonoma".to_string(), "aaa".to_string()),
+                ("x86_64_linux".to_string(), "bbb".to_string())
+            ]
+        );
+
+        fs::write(&path, "class Widget < Formula\nend\n").unwrap();
+        assert!(
+            parse_formula_info(&path, "widget")
+                .unwrap()
+                .bottle
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_find_cask_file() {
+        let prefix = tempfile::TempDir::new().unwrap();
+        let taps = prefix.path().join("Library/Taps");
+        let cask_tap = taps.join("homebrew/homebrew-cask");
+        fs::create_dir_all(cask_tap.join("Casks/f")).unwrap();
+        fs::write(cask_tap.join("Casks/f/firefox.rb"), "").unwrap();
+        let third = taps.join("user/homebrew-repo");
+        fs::create_dir_all(third.join("Casks")).unwrap();
+        fs::write(third.join("Casks/firefox.rb"), "").unwrap();
+        fs::write(third.join("Casks/app.rb"), "").unwrap();
+
+        // homebrew/cask wins for plain tokens; qualified tokens pick their tap
+        assert_eq!(
+            find_cask_file_in(prefix.path(), "firefox"),
+            Some(cask_tap.join("Casks/f/firefox.rb"))
+        );
+        assert_eq!(
+            find_cask_file_in(prefix.path(), "user/repo/firefox"),
+            Some(third.join("Casks/firefox.rb"))
+        );
+        assert_eq!(
+            find_cask_file_in(prefix.path(), "app"),
+            Some(third.join("Casks/app.rb"))
+        );
+        assert!(find_cask_file_in(prefix.path(), "homebrew/cask/app").is_none());
+        assert!(find_cask_file_in(prefix.path(), "missing").is_none());
+    }
+
+    #[test]
+    fn test_list_formulae_and_casks() {
+        let prefix = tempfile::TempDir::new().unwrap();
+
+        // homebrew/core letter layout
+        let core = prefix.path().join("Library/Taps/homebrew/homebrew-core");
+        fs::create_dir_all(core.join(".git")).unwrap();
+        fs::create_dir_all(core.join("Formula/r")).unwrap();
+        fs::create_dir_all(core.join("Formula/j")).unwrap();
+        fs::write(core.join("Formula/r/ripgrep.rb"), "").unwrap();
+        fs::write(core.join("Formula/j/jq.rb"), "").unwrap();
+
+        // Flat third-party layout
+        let third = prefix.path().join("Library/Taps/user/homebrew-repo");
+        fs::create_dir_all(third.join(".git")).unwrap();
+        fs::create_dir_all(third.join("Formula")).unwrap();
+        fs::create_dir_all(third.join("Casks")).unwrap();
+        fs::write(third.join("Formula/tool.rb"), "").unwrap();
+        fs::write(third.join("Formula/notes.txt"), "").unwrap();
+        fs::write(third.join("Casks/app.rb"), "").unwrap();
+
+        assert_eq!(
+            list_formulae_in(prefix.path(), "homebrew/core").unwrap(),
+            vec!["jq", "ripgrep"]
+        );
+        assert!(
+            list_casks_in(prefix.path(), "homebrew/core")
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(
+            list_formulae_in(prefix.path(), "user/repo").unwrap(),
+            vec!["tool"]
+        );
+        assert_eq!(
+            list_casks_in(prefix.path(), "user/repo").unwrap(),
+            vec!["app"]
+        );
+        assert!(list_formulae_in(prefix.path(), "user/missing").is_err());
+    }
 
-        let (user, repo) = parse_tap_name("user/homebrew-repo").unwrap();
-        assert_eq!(user, "user");
-        assert_eq!(repo, "homebrew-repo");
+    #[test]
+    fn test_formula_info_problems() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, contents: &str| {
+            let path = dir.path().join(format!("{}.rb", name));
+            fs::write(&path, contents).unwrap();
+            parse_formula_info(&path, name).unwrap()
+        };
+
+        let good = write(
+            "tool",
+            r#"class Tool < Formula
+  desc "A tool"
+  url "https://example.com/tool-1.2.3.tar.gz"
+  sha256 "abc"
+
+  resource "extra" do
+    url "https://example.com/extra-9.9.tar.gz"
+  end
+end
+"#,
+        );
+        assert_eq!(good.class_name.as_deref(), Some("Tool"));
+        assert_eq!(
+            good.url.as_deref(),
+            Some("https://example.com/tool-1.2.3.tar.gz")
+        );
+        assert_eq!(good.resolved_version().as_deref(), Some("1.2.3"));
+        assert!(good.problems().is_empty());
+
+        let bad = write(
+            "broken",
+            "module Broken\n  url \"https://example.com/download/latest.zip\"\nend\n",
+        );
+        assert_eq!(
+            bad.problems(),
+            vec![
+                "missing `class ... < Formula` declaration",
+                "no version (none declared and none in the url)"
+            ]
+        );
+
+        let empty = write("empty", "");
+        assert_eq!(empty.problems().len(), 3);
+    }
+
+    #[test]
+    fn test_formula_info_version_from_git_tag() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("gitool.rb");
+        fs::write(
+            &path,
+            r#"class Gitool < Formula
+  url "https://github.com/user/gitool.git",
+      tag:      "v2.4.1",
+      revision: "0123456789abcdef0123456789abcdef01234567"
+  head "https://github.com/user/gitool.git", branch: "main"
+end
+"#,
+        )
+        .unwrap();
+
+        let info = parse_formula_info(&path, "gitool").unwrap();
+        assert_eq!(info.tag.as_deref(), Some("v2.4.1"));
+        assert_eq!(info.resolved_version().as_deref(), Some("2.4.1"));
+        assert!(info.problems().is_empty());
+        assert_eq!(
+            parse_formula_version(&path).unwrap().as_deref(),
+            Some("2.4.1")
+        );
+
+        fs::write(
+            &path,
+            "class Gitool < Formula\n  url \"https://example.com/gitool.git\", tag: \"release-3.0\"\nend\n",
+        )
+        .unwrap();
+        let info = parse_formula_info(&path, "gitool").unwrap();
+        assert_eq!(info.resolved_version().as_deref(), Some("3.0"));
+    }
+
+    #[test]
+    fn test_formula_info_version_from_zip_and_orig_urls() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let write = |name: &str, url: &str| {
+            let path = dir.path().join(format!("{}.rb", name));
+            let class = format!("class {} < Formula\n  url \"{}\"\nend\n", name, url);
+            fs::write(&path, class).unwrap();
+            parse_formula_info(&path, name).unwrap()
+        };
+
+        let zip = write("Zipped", "https://example.com/dl/zipped-1.4.0.zip");
+        assert_eq!(zip.resolved_version().as_deref(), Some("1.4.0"));
+        assert!(zip.problems().is_empty());
+
+        let tagged = write(
+            "Tagged",
+            "https://github.com/user/tagged/archive/refs/tags/v0.9.2.zip",
+        );
+        assert_eq!(tagged.resolved_version().as_deref(), Some("0.9.2"));
+
+        let orig = write(
+            "Debian",
+            "https://deb.debian.org/debian/pool/main/d/debian/debian_1.2.orig.tar.gz",
+        );
+        assert_eq!(orig.resolved_version().as_deref(), Some("1.2"));
+        assert!(orig.problems().is_empty());
+    }
+
+    #[test]
+    fn test_formula_info_head_only() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("nightly.rb");
+        fs::write(
+            &path,
+            r#"class Nightly < Formula
+  desc "Only built from the main branch"
+
+  head do
+    url "https://github.com/user/nightly.git", branch: "main"
+  end
+end
+"#,
+        )
+        .unwrap();
+
+        let info = parse_formula_info(&path, "nightly").unwrap();
+        assert_eq!(info.url, None);
+        assert_eq!(
+            info.head.as_deref(),
+            Some("https://github.com/user/nightly.git")
+        );
+        assert!(info.head_only());
+        assert!(info.problems().is_empty());
+
+        // Without a head source it is still missing its url and version
+        fs::write(&path, "class Nightly < Formula\nend\n").unwrap();
+        let info = parse_formula_info(&path, "nightly").unwrap();
+        assert!(!info.head_only());
+        assert_eq!(info.problems().len(), 2);
     }
 
     #[test]
-    fn test_parse_tap_name_invalid() {
-        assert!(parse_tap_name("invalid").is_err());
-        assert!(parse_tap_name("too/many/slashes").is_err());
+    fn test_parse_cask_info() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let path = dir.path().join("myapp.rb");
+        fs::write(
+            &path,
+            r#"cask "myapp" do
+  version "1.2.3"
+  sha256 "abc"
+
+  url "https://example.com/MyApp-#{version}.dmg"
+  name "MyApp"
+  name "My App"
+  desc "Does \"things\""
+  homepage "https://example.com/"
+
+  app "MyApp.app"
+end
+"#,
+        )
+        .unwrap();
+
+        let info = parse_cask_info(&path, "myapp").unwrap();
+        assert_eq!(info.token, "myapp");
+        assert_eq!(info.name, vec!["MyApp", "My App"]);
+        assert_eq!(info.desc.as_deref(), Some(r#"Does \"things\""#));
+        assert_eq!(info.homepage.as_deref(), Some("https://example.com/"));
+        assert_eq!(info.version.as_deref(), Some("1.2.3"));
+        assert!(parse_cask_info(&dir.path().join("missing.rb"), "missing").is_err());
     }
 
     #[test]
@@ -0,0 +1,136 @@
+//! Fixtures shared by unit tests across modules
+
+use crate::api::{Cask, Formula, FormulaSource};
+use crate::error::{BruError, Result};
+use futures::future::BoxFuture;
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Write};
+use std::thread::JoinHandle;
+
+/// A gzipped bottle tarball holding `files` as `(path, contents, mode)`
+pub(crate) fn bottle(files: &[(&str, &[u8], u32)]) -> Vec<u8> {
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(
+        Vec::new(),
+        flate2::Compression::default(),
+    ));
+    for (path, contents, mode) in files {
+        let mut header = tar::Header::new_gnu();
+        header.set_size(contents.len() as u64);
+        header.set_mode(*mode);
+        header.set_cksum();
+        builder.append_data(&mut header, path, *contents).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap()
+}
+
+/// Answer one HTTP request on a local port with `body`
+///
+/// Returns the port and a handle that yields the request the server received.
+pub(crate) fn serve_once(body: Vec<u8>) -> (u16, JoinHandle<String>) {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let server = std::thread::spawn(move || {
+        let (mut stream, _) = listener.accept().unwrap();
+        let mut request = [0u8; 4096];
+        let len = stream.read(&mut request).unwrap();
+        let request = String::from_utf8_lossy(&request[..len]).to_string();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )
+        .unwrap();
+        stream.write_all(&body).unwrap();
+        request
+    });
+    (port, server)
+}
+
+/// Formulae and casks served from memory, so lookups run without the network
+pub(crate) struct MockSource {
+    formulae: HashMap<String, Formula>,
+    casks: HashSet<String>,
+}
+
+impl MockSource {
+    pub(crate) fn new(formulae: Vec<Formula>, casks: &[&str]) -> Self {
+        Self {
+            formulae: formulae.into_iter().map(|f| (f.name.clone(), f)).collect(),
+            casks: casks.iter().map(|c| c.to_string()).collect(),
+        }
+    }
+}
+
+impl FormulaSource for MockSource {
+    fn formula<'a>(&'a self, name: &'a str) -> BoxFuture<'a, Result<Formula>> {
+        let found = self
+            .formulae
+            .get(name)
+            .cloned()
+            .ok_or_else(|| BruError::FormulaNotFound(name.to_string()));
+        Box::pin(async move { found })
+    }
+
+    fn cask<'a>(&'a self, token: &'a str) -> BoxFuture<'a, Result<Cask>> {
+        let found = if self.casks.contains(token) {
+            Ok(serde_json::from_value(serde_json::json!({ "token": token })).unwrap())
+        } else {
+            Err(BruError::CaskNotFound(token.to_string()))
+        };
+        Box::pin(async move { found })
+    }
+}
+
+/// Receipt brew 4.4.4 wrote for `brew install wget` on Apple Silicon, as recorded
+/// in ai/research/homebrew-compatibility.md
+pub(crate) const BREW_RECEIPT: &str = r#"{
+  "homebrew_version": "4.4.4-76-g40f4ab2",
+  "used_options": [],
+  "unused_options": [],
+  "built_as_bottle": true,
+  "poured_from_bottle": true,
+  "loaded_from_api": true,
+  "installed_as_dependency": false,
+  "installed_on_request": true,
+  "changed_files": [],
+  "time": 1731462018,
+  "source_modified_time": 1731274298,
+  "compiler": "clang",
+  "aliases": [],
+  "runtime_dependencies": [
+    {
+      "full_name": "libunistring",
+      "version": "1.3",
+      "revision": 0,
+      "pkg_version": "1.3",
+      "declared_directly": true
+    },
+    {
+      "full_name": "gettext",
+      "version": "0.22.5",
+      "revision": 0,
+      "pkg_version": "0.22.5",
+      "declared_directly": true
+    }
+  ],
+  "source": {
+    "path": "/opt/homebrew/Library/Taps/homebrew/homebrew-core/Formula/w/wget.rb",
+    "tap": "homebrew/core",
+    "tap_git_head": "abc123...",
+    "spec": "stable",
+    "versions": {
+      "stable": "1.25.0",
+      "version_scheme": 0
+    }
+  },
+  "arch": "arm64",
+  "built_on": {
+    "os": "Macintosh",
+    "os_version": "macOS 15.1",
+    "cpu_family": "arm",
+    "xcode": "16.1",
+    "clt": "16.1.0.0.1.1729049160",
+    "preferred_perl": "5.34"
+  },
+  "stdlib": "libc++"
+}"#;
@@ -5,6 +5,8 @@
 
 use std::process::Command;
 
+mod common;
+
 /// Get the bru binary path for testing
 fn bru_bin() -> String {
     // Use the release build for integration tests
@@ -677,6 +679,39 @@ fn test_fetch_validation() {
     );
 }
 
+#[test]
+fn test_fetch_keep_going_reports_every_failure() {
+    // TEST: fetch --keep-going doesn't stop at the first failed formula; every
+    // failure is listed together at the end and the run still exits non-zero
+
+    let output = Command::new(bru_bin())
+        .args([
+            "fetch",
+            "--keep-going",
+            "nonexistent-formula-xyz-123",
+            "nonexistent-formula-xyz-456",
+        ])
+        .output()
+        .expect("Failed to run bru fetch --keep-going");
+
+    assert!(
+        !output.status.success(),
+        "Failed fetches should exit non-zero"
+    );
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let (_, summary) = stdout
+        .split_once("Failed to fetch:")
+        .unwrap_or_else(|| panic!("Should list failures at the end. Got:\n{}", stdout));
+    assert!(
+        summary.contains("nonexistent-formula-xyz-123")
+            && summary.contains("nonexistent-formula-xyz-456")
+            && summary.contains("2 formulae failed to fetch"),
+        "Should report both failures at the end. Got:\n{}",
+        stdout
+    );
+}
+
 #[test]
 fn test_help_command() {
     // TEST: Help should always work and show all commands
@@ -784,3 +819,1342 @@ fn test_regression_leaves_duplicates() {
         );
     }
 }
+
+#[test]
+fn test_prefix_flag_targets_alternate_prefix() {
+    // TEST: --prefix must redirect every prefix lookup, not just `bru prefix`
+    // Enables hermetic tests against a throwaway Cellar
+    let temp = tempfile::TempDir::new().unwrap();
+    let keg_bin = temp.path().join("Cellar/hello/1.0/bin");
+    std::fs::create_dir_all(&keg_bin).unwrap();
+    std::fs::write(keg_bin.join("hello"), "").unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap(), "prefix"])
+        .env_remove("HOMEBREW_PREFIX")
+        .output()
+        .expect("Failed to run bru prefix");
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        temp.path().to_str().unwrap()
+    );
+
+    let output = Command::new(bru_bin())
+        .args([
+            "--prefix",
+            temp.path().to_str().unwrap(),
+            "list",
+            "--formula",
+        ])
+        .env_remove("HOMEBREW_PREFIX")
+        .output()
+        .expect("Failed to run bru list");
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "hello");
+}
+
+#[test]
+fn test_doctor_reports_prefix_bin_missing_from_path() {
+    // TEST: doctor must flag <prefix>/bin missing from PATH and print the shellenv fix
+    // Most "installed tool isn't found" reports come down to this
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("bin")).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap(), "doctor"])
+        .env("PATH", "/usr/bin:/bin")
+        .env("SHELL", "/bin/zsh")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru doctor");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("bin is not in your PATH"));
+    assert!(stdout.contains("Add to ~/.zshrc: eval \"$(bru shellenv)\""));
+}
+
+#[test]
+fn test_env_shell_output_is_sourceable() {
+    // TEST: eval "$(bru env --shell bash)" must reproduce the exact paths, even ones with
+    // quotes and dollar signs; --plain prints them unquoted
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("we\"ird $dir");
+    std::fs::create_dir_all(&prefix).unwrap();
+
+    let env = |args: &[&str]| {
+        let output = Command::new(bru_bin())
+            .args(["--prefix", prefix.to_str().unwrap(), "env"])
+            .args(args)
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("Failed to run bru env");
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+        )
+    };
+
+    let (ok, script) = env(&["--shell", "bash"]);
+    assert!(ok);
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(format!("{}\nprintf '%s' \"$HOMEBREW_CELLAR\"", script))
+        .output()
+        .expect("Failed to run bash");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        prefix.join("Cellar").to_str().unwrap()
+    );
+
+    let (ok, plain) = env(&["--plain"]);
+    assert!(ok);
+    assert!(plain.contains(&format!("HOMEBREW_PREFIX={}\n", prefix.display())));
+
+    let (ok, _) = env(&["--shell", "tcsh"]);
+    assert!(!ok, "unsupported shells must fail");
+}
+
+#[test]
+fn test_shellenv_is_idempotent() {
+    // TEST: sourcing `bru shellenv` twice must not add the prefix to PATH a second time
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("pre fix");
+    std::fs::create_dir_all(&prefix).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args([
+            "--prefix",
+            prefix.to_str().unwrap(),
+            "shellenv",
+            "--shell",
+            "bash",
+        ])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru shellenv");
+    assert!(output.status.success());
+    let script = String::from_utf8_lossy(&output.stdout).to_string();
+
+    let output = Command::new("bash")
+        .arg("-c")
+        .arg(format!("{0}\n{0}\nprintf '%s' \"$PATH\"", script))
+        .env("PATH", "/usr/bin:/bin")
+        .output()
+        .expect("Failed to run bash");
+    let path = String::from_utf8_lossy(&output.stdout).to_string();
+    let bin = prefix.join("bin");
+    let sbin = prefix.join("sbin");
+    assert_eq!(
+        path,
+        format!("{}:{}:/usr/bin:/bin", bin.display(), sbin.display())
+    );
+}
+
+#[test]
+fn test_command_suggests_formula() {
+    // TEST: `bru command <cmd>` backs the command-not-found hook: unlinked kegs get a
+    // link hint, formulae from the executables index an install hint (even when named
+    // differently from the command), and unknown commands exit non-zero
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    std::fs::create_dir_all(prefix.join("Cellar/foo/1.0/bin")).unwrap();
+    std::fs::write(prefix.join("Cellar/foo/1.0/bin/foo"), "").unwrap();
+    let cache = temp.path().join("cache");
+    std::fs::create_dir_all(cache.join("bru")).unwrap();
+    std::fs::write(
+        cache.join("bru/executables.json"),
+        r#"[
+            {"name":"ripgrep","executables":["rg"]},
+            {"name":"python@3.13","executables":["python3","pip3"]},
+            {"name":"python@3.12","executables":["python3"]}
+        ]"#,
+    )
+    .unwrap();
+
+    let command = |name: &str| {
+        let output = Command::new(bru_bin())
+            .args(["--prefix", prefix.to_str().unwrap(), "command", name])
+            .env("NO_COLOR", "1")
+            .env("XDG_CACHE_HOME", &cache)
+            .output()
+            .expect("Failed to run bru command");
+        (
+            output.status.success(),
+            String::from_utf8_lossy(&output.stdout).to_string(),
+        )
+    };
+
+    let (ok, out) = command("foo");
+    assert!(ok);
+    assert!(out.contains("bru link foo"), "{}", out);
+
+    let (ok, out) = command("rg");
+    assert!(ok);
+    assert!(out.contains("bru install ripgrep"), "{}", out);
+
+    let (ok, out) = command("python3");
+    assert!(ok);
+    assert!(
+        out.find("* python@3.12") < out.find("* python@3.13"),
+        "{}",
+        out
+    );
+
+    let (ok, out) = command("no-such-command");
+    assert!(!ok);
+    assert!(out.is_empty());
+}
+
+#[test]
+fn test_cat_cask_prints_tap_source() {
+    // TEST: `bru cat --cask` prints the cask's Ruby source from a local tap, without
+    // touching the API
+    let temp = tempfile::TempDir::new().unwrap();
+    let casks = temp.path().join("Library/Taps/user/homebrew-repo/Casks");
+    std::fs::create_dir_all(&casks).unwrap();
+    let source = "cask \"app\" do\n  version \"1.0\"\nend\n";
+    std::fs::write(casks.join("app.rb"), source).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args([
+            "--prefix",
+            temp.path().to_str().unwrap(),
+            "cat",
+            "--cask",
+            "app",
+        ])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru cat");
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains(source));
+}
+
+#[test]
+fn test_info_json_multiple_names_prints_array() {
+    // TEST: `bru info --json a b` prints one array in argument order, with an
+    // {"name", "error"} entry for a name that can't be shown; one name is an array too
+    let temp = tempfile::TempDir::new().unwrap();
+    let formulae = temp.path().join("Library/Taps/user/homebrew-repo/Formula");
+    std::fs::create_dir_all(&formulae).unwrap();
+    std::fs::write(
+        formulae.join("foo.rb"),
+        "class Foo < Formula\n  desc \"Foo tool\"\n  homepage \"https://example.com\"\n  \
+         url \"https://example.com/foo-1.2.tar.gz\"\nend\n",
+    )
+    .unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap(), "--json", "info"])
+        .args(["user/repo/foo", "not a name"])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru info");
+    assert!(!output.status.success());
+
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["full_name"], "user/repo/foo");
+    assert_eq!(entries[0]["desc"], "Foo tool");
+    assert_eq!(entries[1]["name"], "not a name");
+    assert!(
+        entries[1]["error"]
+            .as_str()
+            .unwrap()
+            .contains("not a valid")
+    );
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap(), "--json", "info"])
+        .arg("user/repo/foo")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru info");
+    assert!(output.status.success());
+    let entries: Vec<serde_json::Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["full_name"], "user/repo/foo");
+}
+
+#[test]
+fn test_search_json_separates_formulae_and_casks() {
+    // TEST: `bru search --json` prints formulae and casks with descriptions and match
+    // scores, best match first, and --formula leaves the casks out
+    let temp = tempfile::TempDir::new().unwrap();
+    let cache = temp.path().join("bru");
+    std::fs::create_dir_all(&cache).unwrap();
+    std::fs::write(
+        cache.join("formulae.json"),
+        serde_json::json!([
+            {"name": "ripgrep-all", "versions": {"stable": "0.10.6"}},
+            {"name": "ripgrep", "desc": "Search tool", "versions": {"stable": "14.1.1"}},
+            {"name": "jq", "versions": {"stable": "1.7.1"}},
+        ])
+        .to_string(),
+    )
+    .unwrap();
+    std::fs::write(
+        cache.join("casks.json"),
+        serde_json::json!([{"token": "ripgrep-ui", "desc": "Search GUI"}]).to_string(),
+    )
+    .unwrap();
+
+    let search = |args: &[&str]| {
+        let output = Command::new(bru_bin())
+            .args(["--json", "search", "ripgrep"])
+            .args(args)
+            .env("NO_COLOR", "1")
+            .env("XDG_CACHE_HOME", temp.path())
+            .output()
+            .expect("Failed to run bru search");
+        assert!(output.status.success());
+        serde_json::from_slice::<serde_json::Value>(&output.stdout).unwrap()
+    };
+
+    let results = search(&[]);
+    let formulae = results["formulae"].as_array().unwrap();
+    assert_eq!(formulae.len(), 2);
+    assert_eq!(formulae[0]["name"], "ripgrep");
+    assert_eq!(formulae[0]["desc"], "Search tool");
+    assert_eq!(formulae[0]["score"], 1.0);
+    assert_eq!(formulae[1]["name"], "ripgrep-all");
+    assert!(formulae[1]["score"].as_f64().unwrap() < 1.0);
+    assert_eq!(results["casks"][0]["token"], "ripgrep-ui");
+    assert_eq!(results["casks"][0]["desc"], "Search GUI");
+
+    let results = search(&["--formula"]);
+    assert_eq!(results["formulae"].as_array().unwrap().len(), 2);
+    assert!(results["casks"].as_array().unwrap().is_empty());
+}
+
+#[test]
+fn test_doctor_reports_unlinked_kegs() {
+    // TEST: doctor must flag installed-but-unlinked formulae, including ones `brew unlink`
+    // left an opt/ link for, but not linked or keg-only ones
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar/hello/1.0/bin")).unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar/tree/2.1/bin")).unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar/wget/1.25.0/bin")).unwrap();
+    std::fs::create_dir_all(temp.path().join("opt")).unwrap();
+    std::os::unix::fs::symlink("../Cellar/tree/2.1", temp.path().join("opt/tree")).unwrap();
+    std::os::unix::fs::symlink("../Cellar/wget/1.25.0", temp.path().join("opt/wget")).unwrap();
+    std::fs::create_dir_all(temp.path().join("var/homebrew/linked")).unwrap();
+    std::os::unix::fs::symlink(
+        "../../../Cellar/wget/1.25.0",
+        temp.path().join("var/homebrew/linked/wget"),
+    )
+    .unwrap();
+    let keg_only = temp.path().join("Cellar/openssl@3/3.4.0/.brew");
+    std::fs::create_dir_all(&keg_only).unwrap();
+    std::fs::write(
+        keg_only.join("openssl@3.rb"),
+        "class OpensslAT3 < Formula\n  keg_only :shadowed_by_macos\nend\n",
+    )
+    .unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap(), "doctor"])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru doctor");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Found 2 installed but unlinked formulae"));
+    assert!(stdout.contains("Run: bru link hello"));
+    assert!(stdout.contains("Run: bru link tree"));
+    assert!(!stdout.contains("bru link wget"));
+    assert!(!stdout.contains("bru link openssl@3"));
+}
+
+#[test]
+fn test_doctor_reports_foreign_owned_prefix_dirs() {
+    // TEST: doctor flags prefix directories owned by another user (e.g. after a
+    // `sudo` install) and prints the chown fix
+    let temp = tempfile::TempDir::new().unwrap();
+    let cellar = temp.path().join("Cellar");
+    std::fs::create_dir_all(&cellar).unwrap();
+    std::fs::create_dir_all(temp.path().join("var/homebrew")).unwrap();
+
+    let run_doctor = || {
+        let output = Command::new(bru_bin())
+            .args(["--prefix", temp.path().to_str().unwrap(), "doctor"])
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("Failed to run bru doctor");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).to_string()
+    };
+
+    let stdout = run_doctor();
+    assert!(stdout.contains("Prefix directories are owned by the current user"));
+    assert!(stdout.contains("var/homebrew is writable"));
+
+    // Handing the Cellar to another user needs root; nothing more to check otherwise
+    if std::os::unix::fs::chown(&cellar, Some(65534), None).is_err() {
+        return;
+    }
+    let stdout = run_doctor();
+    assert!(stdout.contains(&format!("{} is owned by uid 65534", cellar.display())));
+    assert!(stdout.contains(&format!(
+        "Run: sudo chown -R \"$(whoami)\" {}",
+        cellar.display()
+    )));
+}
+
+#[test]
+fn test_outdated_accepts_formula_and_cask_filters() {
+    // TEST: outdated checks formulae and casks together by default, and accepts
+    // --formula/--cask (alone or combined) like brew
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+    std::fs::create_dir_all(temp.path().join("Caskroom")).unwrap();
+
+    for args in [
+        &[][..],
+        &["--formula"][..],
+        &["--cask"][..],
+        &["--formula", "--cask"][..],
+    ] {
+        let output = Command::new(bru_bin())
+            .args(["--prefix", temp.path().to_str().unwrap(), "outdated"])
+            .args(args)
+            .output()
+            .expect("Failed to run bru outdated");
+        assert!(output.status.success(), "bru outdated {:?} failed", args);
+        // Nothing installed: piped output must be empty (no headers)
+        assert!(output.stdout.is_empty(), "unexpected output for {:?}", args);
+    }
+}
+
+#[test]
+fn test_reinstall_from_cache_works_offline() {
+    // TEST: reinstall --from-cache must rebuild a keg from a cached, checksum-verified
+    // bottle and cached metadata without any network access
+    use sha2::{Digest, Sha256};
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    let home = temp.path().join("home");
+
+    // Corrupted keg
+    let keg_bin = prefix.join("Cellar/hello/1.0/bin");
+    std::fs::create_dir_all(&keg_bin).unwrap();
+    std::fs::write(keg_bin.join("hello"), "corrupted").unwrap();
+
+    // Cached bottle containing hello/1.0/bin/hello
+    let script = b"#!/bin/sh\necho hello\n";
+    let bottle = common::bottle(&[("hello/1.0/bin/hello", script, 0o755)]);
+    let sha256 = format!("{:x}", Sha256::digest(&bottle));
+
+    let downloads = home.join(".cache/bru/downloads");
+    std::fs::create_dir_all(&downloads).unwrap();
+    let tag = kombrucha::platform::detect_bottle_tag().unwrap();
+    std::fs::write(
+        downloads.join(format!("hello--1.0.{}.bottle.tar.gz", tag)),
+        &bottle,
+    )
+    .unwrap();
+
+    // Cached formula metadata
+    let formulae = serde_json::json!([{
+        "name": "hello",
+        "versions": {"stable": "1.0", "bottle": true},
+        "bottle": {"stable": {"rebuild": 0, "files": {"all": {
+            "cellar": ":any_skip_relocation",
+            "url": "https://ghcr.io/v2/homebrew/core/hello/blobs/sha256:unused",
+            "sha256": sha256
+        }}}}
+    }]);
+    std::fs::write(home.join(".cache/bru/formulae.json"), formulae.to_string()).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", prefix.to_str().unwrap()])
+        .args(["reinstall", "--from-cache", "hello"])
+        .env("HOME", &home)
+        .env_remove("XDG_CACHE_HOME")
+        // Any network access would fail through this proxy
+        .env("HTTPS_PROXY", "http://127.0.0.1:9")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru reinstall");
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "reinstall failed: {}", stdout);
+    assert!(stdout.contains("Reinstalled 1 package"), "{}", stdout);
+    assert_eq!(
+        std::fs::read(keg_bin.join("hello")).unwrap(),
+        script.to_vec()
+    );
+}
+
+#[test]
+fn test_install_local_bottle_file_offline() {
+    // TEST: install ./<name>--<version>.<tag>.bottle.tar.gz pours the bottle without
+    // touching the API: keg, links, and a receipt listing installed dependencies for
+    // this platform and no tap. Bottles for another platform are refused.
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    std::fs::create_dir_all(prefix.join("Cellar/oniguruma/6.9.9/lib")).unwrap();
+
+    let files: [(&str, &[u8], u32); 2] = [
+        ("jq/1.7.1/bin/jq", b"#!/bin/sh\necho jq\n", 0o755),
+        (
+            "jq/1.7.1/.brew/jq.rb",
+            b"class Jq < Formula\n  depends_on \"autoconf\" => :build\n  depends_on \"oniguruma\"\n  \
+              on_linux do\n    depends_on \"zlib\"\n  end\nend\n",
+            0o644,
+        ),
+    ];
+    let bottle = temp.path().join("jq--1.7.1.arm64_sonoma.bottle.tar.gz");
+    std::fs::write(&bottle, common::bottle(&files)).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", prefix.to_str().unwrap(), "install"])
+        .arg(&bottle)
+        .env("HOMEBREW_BOTTLE_TAG", "arm64_sonoma")
+        // Any network access would fail through this proxy
+        .env("HTTPS_PROXY", "http://127.0.0.1:9")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru install");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "install failed: {}", stdout);
+    // The Linux-only dependency doesn't apply to a macOS bottle
+    assert!(!stdout.contains("zlib"), "{}", stdout);
+
+    assert!(prefix.join("Cellar/jq/1.7.1/bin/jq").is_file());
+    assert!(prefix.join("bin/jq").exists());
+    let receipt: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(prefix.join("Cellar/jq/1.7.1/INSTALL_RECEIPT.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(receipt["installed_on_request"], true);
+    let deps: Vec<_> = receipt["runtime_dependencies"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|dep| dep["full_name"].as_str().unwrap())
+        .collect();
+    assert_eq!(deps, ["oniguruma"]);
+    assert_eq!(receipt["loaded_from_api"], false);
+    assert!(receipt["source"]["tap"].is_null());
+
+    // Already installed: skipped, unless --force
+    let install = |extra: &[&str], tag: &str| {
+        Command::new(bru_bin())
+            .args(["--prefix", prefix.to_str().unwrap(), "install"])
+            .args(extra)
+            .arg(&bottle)
+            .env("HOMEBREW_BOTTLE_TAG", tag)
+            .env("HTTPS_PROXY", "http://127.0.0.1:9")
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("Failed to run bru install")
+    };
+    let output = install(&[], "arm64_sonoma");
+    assert!(String::from_utf8_lossy(&output.stdout).contains("is already installed"));
+    let output = install(&["--force"], "arm64_sonoma");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "reinstall failed: {}", stdout);
+    assert!(stdout.contains("Installed jq"), "{}", stdout);
+
+    // A bottle for another platform isn't poured
+    std::fs::remove_dir_all(prefix.join("Cellar/jq")).unwrap();
+    let output = install(&[], "x86_64_linux");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("is a bottle for arm64_sonoma, not x86_64_linux")
+    );
+    assert!(!prefix.join("Cellar/jq").exists());
+
+    // Names that don't follow the bottle naming convention are refused up front
+    let bad = temp.path().join("jq.tar.gz");
+    std::fs::copy(&bottle, &bad).unwrap();
+    let output = Command::new(bru_bin())
+        .args(["--prefix", prefix.to_str().unwrap(), "install"])
+        .arg(&bad)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru install");
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stdout)
+            .contains("expected <name>--<version>.<tag>.bottle.tar.gz")
+    );
+}
+
+#[test]
+fn test_install_local_formula_file() {
+    // TEST: install ./<name>.rb downloads the bottle from the formula's own bottle
+    // block (root_url + sha256) and pours it; incomplete formula files are refused
+    use sha2::{Digest, Sha256};
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    let home = temp.path().join("home");
+    std::fs::create_dir_all(prefix.join("Cellar")).unwrap();
+
+    let bottle = common::bottle(&[(
+        "widget/1.2.0_1/bin/widget",
+        b"#!/bin/sh\necho widget\n",
+        0o755,
+    )]);
+    let sha256 = format!("{:x}", Sha256::digest(&bottle));
+
+    // Serve the bottle once over plain HTTP
+    let (port, server) = common::serve_once(bottle);
+
+    let formula = temp.path().join("widget.rb");
+    std::fs::write(
+        &formula,
+        format!(
+            "class Widget < Formula\n  url \"https://example.com/widget-1.2.0.tar.gz\"\n  \
+             revision 1\n\n  bottle do\n    root_url \"http://127.0.0.1:{}/bottles\"\n    \
+             rebuild 2\n    sha256 cellar: :any_skip_relocation, all: \"{}\"\n  end\nend\n",
+            port, sha256
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", prefix.to_str().unwrap(), "install"])
+        .arg(&formula)
+        .env("HOME", &home)
+        .env_remove("XDG_CACHE_HOME")
+        .env("NO_PROXY", "127.0.0.1")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru install");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "install failed: {}", stdout);
+
+    let request = server.join().unwrap();
+    // The revision is part of the version, the rebuild follows `.bottle`
+    assert!(request.starts_with("GET /bottles/widget--1.2.0_1.all.bottle.2.tar.gz "));
+    assert!(prefix.join("Cellar/widget/1.2.0_1/bin/widget").is_file());
+    let receipt: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(prefix.join("Cellar/widget/1.2.0_1/INSTALL_RECEIPT.json"))
+            .unwrap(),
+    )
+    .unwrap();
+    assert!(receipt["source"]["tap"].is_null());
+    assert_eq!(receipt["source"]["path"], formula.to_str().unwrap());
+    assert!(prefix.join("bin/widget").exists());
+
+    // A formula without a url or version can't be installed
+    let broken = temp.path().join("broken.rb");
+    std::fs::write(&broken, "class Broken < Formula\nend\n").unwrap();
+    let output = Command::new(bru_bin())
+        .args(["--prefix", prefix.to_str().unwrap(), "install"])
+        .arg(&broken)
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru install");
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("can't be installed: missing url"));
+}
+
+#[test]
+fn test_install_qualified_tap_formula_records_tap() {
+    // TEST: install user/repo/foo reads the formula from the locally cloned tap, pours
+    // its bottle, and records user/repo (not homebrew/core) as the receipt's tap
+    use sha2::{Digest, Sha256};
+
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    let home = temp.path().join("home");
+    std::fs::create_dir_all(prefix.join("Cellar")).unwrap();
+
+    let bottle = common::bottle(&[("foo/2.0/bin/foo", b"#!/bin/sh\necho foo\n", 0o755)]);
+    let sha256 = format!("{:x}", Sha256::digest(&bottle));
+    let (port, server) = common::serve_once(bottle);
+
+    let tap_dir = prefix.join("Library/Taps/user/homebrew-repo");
+    std::fs::create_dir_all(tap_dir.join(".git")).unwrap();
+    std::fs::create_dir_all(tap_dir.join("Formula")).unwrap();
+    let formula_file = tap_dir.join("Formula/foo.rb");
+    std::fs::write(
+        &formula_file,
+        format!(
+            "class Foo < Formula\n  url \"https://example.com/foo-2.0.tar.gz\"\n\n  \
+             bottle do\n    root_url \"http://127.0.0.1:{}/bottles\"\n    \
+             sha256 cellar: :any_skip_relocation, all: \"{}\"\n  end\nend\n",
+            port, sha256
+        ),
+    )
+    .unwrap();
+
+    let output = Command::new(bru_bin())
+        .args([
+            "--prefix",
+            prefix.to_str().unwrap(),
+            "install",
+            "user/repo/foo",
+        ])
+        .env("HOME", &home)
+        .env_remove("XDG_CACHE_HOME")
+        .env("NO_PROXY", "127.0.0.1")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru install");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "install failed: {}", stdout);
+    assert!(!stdout.contains("Delegating to"), "{}", stdout);
+
+    let request = server.join().unwrap();
+    assert!(request.starts_with("GET /bottles/foo--2.0.all.bottle.tar.gz "));
+    assert!(prefix.join("bin/foo").exists());
+
+    let receipt: serde_json::Value = serde_json::from_str(
+        &std::fs::read_to_string(prefix.join("Cellar/foo/2.0/INSTALL_RECEIPT.json")).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(receipt["source"]["tap"], "user/repo");
+    assert_eq!(
+        receipt["source"]["path"].as_str(),
+        formula_file.to_str(),
+        "receipt should point at the tap's formula file"
+    );
+}
+
+#[test]
+#[ignore] // Requires network access to the Homebrew API
+fn test_install_warns_when_formula_and_cask_share_a_name() {
+    // TEST: docker exists as both a formula and a cask. Piped installs must keep
+    // picking the formula but say so; --formula makes the choice explicit and silent
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap()])
+        .args(["install", "--dry-run", "docker"])
+        .output()
+        .expect("Failed to run bru install");
+    assert!(output.status.success());
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(stderr.contains("docker exists as both a formula and a cask"));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("docker"));
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap()])
+        .args(["install", "--dry-run", "--formula", "docker"])
+        .output()
+        .expect("Failed to run bru install");
+    assert!(output.status.success());
+    assert!(!String::from_utf8_lossy(&output.stderr).contains("both a formula and a cask"));
+}
+
+#[test]
+fn test_cleanup_scrub_clears_download_cache() {
+    // TEST: cleanup -s must also remove cached bottles (like brew cleanup -s),
+    // and --dry-run must leave them in place
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    std::fs::create_dir_all(prefix.join("Cellar")).unwrap();
+    let downloads = temp.path().join("home/.cache/bru/downloads");
+    std::fs::create_dir_all(&downloads).unwrap();
+    let bottle = downloads.join("hello--1.0.x86_64_linux.bottle.tar.gz");
+    std::fs::write(&bottle, vec![0u8; 2048]).unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(bru_bin())
+            .args(["--prefix", prefix.to_str().unwrap(), "cleanup"])
+            .args(args)
+            .env("HOME", temp.path().join("home"))
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("Failed to run bru cleanup")
+    };
+
+    let output = run(&["--scrub", "--dry-run"]);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Would remove 1 cached bottles"));
+    assert!(bottle.exists());
+
+    let output = run(&["-s"]);
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Removed 1 cached bottles, freed 2.00 KB"),
+        "{}",
+        stdout
+    );
+    assert!(stdout.contains("Freed 2.00 KB in total"), "{}", stdout);
+    assert!(!bottle.exists());
+}
+
+#[test]
+fn test_search_installed_works_offline() {
+    // TEST: search --installed must match installed names (and cached descriptions)
+    // without any network access
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    let home = temp.path().join("home");
+
+    for (name, version) in [("ripgrep", "14.1.0"), ("jq", "1.7.1")] {
+        std::fs::create_dir_all(prefix.join("Cellar").join(name).join(version).join("bin"))
+            .unwrap();
+    }
+    std::fs::create_dir_all(home.join(".cache/bru")).unwrap();
+    let index = serde_json::json!([
+        {"name": "ripgrep", "desc": "Search tool like grep and The Silver Searcher"},
+        {"name": "silver-searcher", "desc": "Code-search similar to ack"}
+    ]);
+    std::fs::write(
+        home.join(".cache/bru/formulae-brief.json"),
+        index.to_string(),
+    )
+    .unwrap();
+
+    let search = |query: &str| {
+        let output = Command::new(bru_bin())
+            .args(["--prefix", prefix.to_str().unwrap()])
+            .args(["search", "--installed", query])
+            .env("HOME", &home)
+            .env_remove("XDG_CACHE_HOME")
+            // Any network access would fail through this proxy
+            .env("HTTPS_PROXY", "http://127.0.0.1:9")
+            .output()
+            .expect("Failed to run bru search");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).into_owned()
+    };
+
+    assert_eq!(search("jq"), "jq\n");
+    // Description match; silver-searcher is not installed
+    assert_eq!(search("silver"), "ripgrep\n");
+    assert_eq!(search("python"), "");
+}
+
+#[test]
+fn test_global_json_flag() {
+    // TEST: --json works on any supported command (before or after the subcommand) and
+    // unsupported commands print a parseable sentinel instead of running
+    let temp = tempfile::TempDir::new().unwrap();
+    let keg = temp.path().join("Cellar/jq/1.7.1");
+    std::fs::create_dir_all(keg.join("bin")).unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar/oniguruma/6.9.9/lib")).unwrap();
+    let receipt = serde_json::json!({
+        "homebrew_version": "bru/test",
+        "built_as_bottle": true,
+        "poured_from_bottle": true,
+        "loaded_from_api": true,
+        "installed_as_dependency": false,
+        "installed_on_request": true,
+        "time": 0,
+        "runtime_dependencies": [{
+            "full_name": "oniguruma",
+            "version": "6.9.9",
+            "revision": 0,
+            "bottle_rebuild": 0,
+            "pkg_version": "6.9.9",
+            "declared_directly": true
+        }, {
+            // Pulled in through oniguruma; --detailed lists only direct dependencies
+            "full_name": "libyaml",
+            "version": "0.2.5",
+            "revision": 0,
+            "bottle_rebuild": 0,
+            "pkg_version": "0.2.5",
+            "declared_directly": false
+        }]
+    });
+    std::fs::write(keg.join("INSTALL_RECEIPT.json"), receipt.to_string()).unwrap();
+
+    let run = |args: &[&str]| {
+        let output = Command::new(bru_bin())
+            .args(["--prefix", temp.path().to_str().unwrap()])
+            .args(args)
+            .output()
+            .expect("Failed to run bru");
+        let json: serde_json::Value = serde_json::from_slice(&output.stdout)
+            .unwrap_or_else(|e| panic!("{:?} printed invalid JSON: {}", args, e));
+        (output.status.success(), json)
+    };
+
+    let (ok, leaves) = run(&["--json", "leaves"]);
+    assert!(ok);
+    assert_eq!(
+        leaves,
+        serde_json::json!([{"name": "jq", "version": "1.7.1", "installed_on_request": true}])
+    );
+
+    let (ok, list) = run(&["list", "--json"]);
+    assert!(ok);
+    assert_eq!(list["formulae"][0]["name"], "jq");
+    assert_eq!(list["formulae"][1]["versions"][0], "6.9.9");
+    assert_eq!(list["casks"], serde_json::json!([]));
+    // The default listing stays lightweight; --detailed adds receipt and disk data
+    assert!(list["formulae"][0].get("size_bytes").is_none());
+
+    let (ok, detailed) = run(&["list", "--json", "--detailed", "--formula"]);
+    assert!(ok);
+    assert_eq!(detailed[0]["name"], "jq");
+    assert_eq!(detailed[0]["installed_on_request"], true);
+    assert_eq!(detailed[0]["linked"], false);
+    assert_eq!(
+        detailed[0]["dependencies"],
+        serde_json::json!(["oniguruma"])
+    );
+    assert!(detailed[0]["size_bytes"].as_u64().unwrap() > 0);
+
+    let (ok, config) = run(&["--json", "config"]);
+    assert!(ok);
+    assert_eq!(config["prefix"], temp.path().to_str().unwrap());
+    assert_eq!(config["installed_packages"], 2);
+    assert!(config["version"].is_string());
+    assert!(config["git_sha"].is_string());
+    assert!(!config["target"].as_str().unwrap().is_empty());
+
+    let (ok, sentinel) = run(&["--json", "uninstall", "jq"]);
+    assert!(!ok);
+    assert_eq!(sentinel, serde_json::json!({"unsupported": true}));
+    assert!(keg.exists());
+
+    // Requirements have no JSON form, so they aren't silently dropped from it
+    let (ok, sentinel) = run(&["--json", "deps", "--include-requirements", "jq"]);
+    assert!(!ok);
+    assert_eq!(sentinel, serde_json::json!({"unsupported": true}));
+}
+
+#[test]
+fn test_outdated_exit_code_when_up_to_date() {
+    // TEST: outdated --exit-code exits 0 when nothing is outdated (and 1 only when
+    // updates exist), so scripts can branch on the status
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap()])
+        .args(["outdated", "--exit-code"])
+        .output()
+        .expect("Failed to run bru outdated");
+
+    assert_eq!(
+        output.status.code(),
+        Some(0),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+#[ignore] // Requires network access to the Homebrew API and bottle downloads
+fn test_ctrl_c_during_install_rolls_back_partial_keg() {
+    // TEST: Ctrl-C while kegs are being poured must stop the install and leave only
+    // complete kegs (with a receipt) behind - no half-extracted or half-linked ones
+    use std::io::{BufRead, BufReader};
+    use std::process::Stdio;
+
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+
+    let mut child = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap()])
+        .args(["install", "--yes", "wget"])
+        .stdout(Stdio::piped())
+        .spawn()
+        .expect("Failed to run bru install");
+
+    let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+    for line in lines.by_ref() {
+        if line.unwrap().contains("Installing packages...") {
+            break;
+        }
+    }
+    Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("Failed to send SIGINT");
+    let stdout: Vec<String> = lines.map_while(|line| line.ok()).collect();
+    let status = child.wait().unwrap();
+
+    // The install may have finished before the signal arrived
+    if status.code() == Some(130) {
+        assert!(
+            stdout.iter().any(|line| line.contains("Interrupted:")),
+            "stdout: {:?}",
+            stdout
+        );
+    } else {
+        assert!(status.success());
+    }
+
+    for formula in std::fs::read_dir(temp.path().join("Cellar")).unwrap() {
+        for keg in std::fs::read_dir(formula.unwrap().path()).unwrap() {
+            let keg = keg.unwrap().path();
+            assert!(
+                keg.join("INSTALL_RECEIPT.json").exists(),
+                "partial keg left behind: {}",
+                keg.display()
+            );
+        }
+    }
+}
+
+#[test]
+fn test_info_rejects_invalid_name_offline() {
+    // TEST: A name that can't be a formula or cask fails immediately with a clear
+    // message instead of a generic API error after a round-trip
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap()])
+        .args(["info", "rip grep"])
+        .output()
+        .expect("Failed to run bru info");
+
+    assert_eq!(output.status.code(), Some(1));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("not a valid formula or cask name"),
+        "stdout: {}",
+        stdout
+    );
+}
+
+#[test]
+fn test_gist_logs_writes_report_file() {
+    // TEST: gist-logs --output writes the diagnostics to disk (no upload, no network)
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar/jq/1.7.1/bin")).unwrap();
+    let report = temp.path().join("report.txt");
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap()])
+        .args(["gist-logs", "--output", report.to_str().unwrap()])
+        .output()
+        .expect("Failed to run bru gist-logs");
+
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let contents = std::fs::read_to_string(&report).unwrap();
+    assert!(contents.contains("=== System Information ==="));
+    assert!(contents.contains("jq 1.7.1"));
+}
+
+#[test]
+fn test_no_color_disables_ansi_codes() {
+    // TEST: NO_COLOR and --no-color strip colors even when CLICOLOR_FORCE would
+    // otherwise color piped output
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+
+    let run = |no_color_env: bool, flag: bool| {
+        let mut cmd = Command::new(bru_bin());
+        cmd.args(["--prefix", temp.path().to_str().unwrap()])
+            .arg("config")
+            .env("CLICOLOR_FORCE", "1")
+            .env_remove("NO_COLOR");
+        if no_color_env {
+            cmd.env("NO_COLOR", "1");
+        }
+        if flag {
+            cmd.arg("--no-color");
+        }
+        let output = cmd.output().expect("Failed to run bru config");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout).contains('\x1b')
+    };
+
+    assert!(run(false, false), "CLICOLOR_FORCE should color output");
+    assert!(!run(true, false), "NO_COLOR should disable colors");
+    assert!(!run(false, true), "--no-color should disable colors");
+}
+
+#[test]
+fn test_untap_refuses_while_formulae_from_tap_are_installed() {
+    // TEST: untap must not orphan kegs installed from the tap unless --force is given
+    let temp = tempfile::TempDir::new().unwrap();
+    let tap_dir = temp.path().join("Library/Taps/acme/homebrew-tools");
+    std::fs::create_dir_all(tap_dir.join(".git")).unwrap();
+    let keg = temp.path().join("Cellar/widget/1.0");
+    std::fs::create_dir_all(&keg).unwrap();
+    std::fs::write(
+        keg.join("INSTALL_RECEIPT.json"),
+        r#"{"homebrew_version": "4.4.0", "source": {"tap": "acme/tools"}}"#,
+    )
+    .unwrap();
+
+    let untap = |force: bool| {
+        let mut cmd = Command::new(bru_bin());
+        cmd.args([
+            "--prefix",
+            temp.path().to_str().unwrap(),
+            "untap",
+            "acme/tools",
+        ])
+        .env("NO_COLOR", "1");
+        if force {
+            cmd.arg("--force");
+        }
+        cmd.output().expect("Failed to run bru untap")
+    };
+
+    let output = untap(false);
+    assert!(!output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("widget"));
+    assert!(tap_dir.exists(), "tap must survive a refused untap");
+
+    let output = untap(true);
+    assert!(
+        output.status.success(),
+        "stderr: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!tap_dir.exists());
+}
+
+#[test]
+fn test_doctor_reports_unlinked_pkgconfig_files() {
+    // TEST: doctor flags linked formulae whose lib/pkgconfig/*.pc isn't linked, but not
+    // keg-only ones, which are never linked
+    let temp = tempfile::TempDir::new().unwrap();
+    let keg = temp.path().join("Cellar/libfoo/1.0");
+    std::fs::create_dir_all(keg.join("lib/pkgconfig")).unwrap();
+    std::fs::write(keg.join("lib/pkgconfig/foo.pc"), "Name: foo\n").unwrap();
+    std::fs::write(keg.join("lib/pkgconfig/foo-extra.pc"), "Name: foo-extra\n").unwrap();
+    std::fs::create_dir_all(temp.path().join("var/homebrew/linked")).unwrap();
+    std::os::unix::fs::symlink(
+        "../../../Cellar/libfoo/1.0",
+        temp.path().join("var/homebrew/linked/libfoo"),
+    )
+    .unwrap();
+    std::fs::create_dir_all(temp.path().join("opt")).unwrap();
+    let keg_only = temp.path().join("Cellar/libbar/2.0");
+    std::fs::create_dir_all(keg_only.join("lib/pkgconfig")).unwrap();
+    std::fs::write(keg_only.join("lib/pkgconfig/bar.pc"), "Name: bar\n").unwrap();
+    std::fs::create_dir_all(keg_only.join(".brew")).unwrap();
+    std::fs::write(
+        keg_only.join(".brew/libbar.rb"),
+        "class Libbar < Formula\n  keg_only :provided_by_macos\nend\n",
+    )
+    .unwrap();
+    std::os::unix::fs::symlink("../Cellar/libbar/2.0", temp.path().join("opt/libbar")).unwrap();
+    std::fs::create_dir_all(temp.path().join("lib/pkgconfig")).unwrap();
+    std::os::unix::fs::symlink(
+        "../../Cellar/libfoo/1.0/lib/pkgconfig/foo.pc",
+        temp.path().join("lib/pkgconfig/foo.pc"),
+    )
+    .unwrap();
+
+    let output = Command::new(bru_bin())
+        .args(["--prefix", temp.path().to_str().unwrap(), "doctor"])
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru doctor");
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("Found 1 formulae with unlinked pkg-config files"),
+        "stdout: {}",
+        stdout
+    );
+    assert!(stdout.contains("libfoo (foo-extra.pc)"));
+    assert!(!stdout.contains("libbar"));
+}
+
+#[test]
+fn test_list_sort_by_date_and_size() {
+    // TEST: list --sort orders formulae by receipt time (newest first) or size (largest first)
+    let temp = tempfile::TempDir::new().unwrap();
+    for (name, time, size) in [
+        ("aaa", 1_700_000_000, 10),
+        ("bbb", 1_730_000_000, 5000),
+        ("ccc", 1_600_000_000, 100),
+    ] {
+        let keg = temp.path().join("Cellar").join(name).join("1.0");
+        std::fs::create_dir_all(keg.join("bin")).unwrap();
+        std::fs::write(keg.join("bin").join(name), vec![0u8; size]).unwrap();
+        std::fs::write(
+            keg.join("INSTALL_RECEIPT.json"),
+            format!(r#"{{"homebrew_version": "4.4.6", "time": {}}}"#, time),
+        )
+        .unwrap();
+    }
+
+    let list = |sort: &str| {
+        let output = Command::new(bru_bin())
+            .args([
+                "--prefix",
+                temp.path().to_str().unwrap(),
+                "list",
+                "--formula",
+                "-1",
+                "--sort",
+                sort,
+            ])
+            .output()
+            .expect("Failed to run bru list");
+        assert!(output.status.success());
+        String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(list("name"), vec!["aaa", "bbb", "ccc"]);
+    assert_eq!(list("date"), vec!["bbb", "aaa", "ccc"]);
+    assert_eq!(list("size"), vec!["bbb", "ccc", "aaa"]);
+}
+
+#[test]
+fn test_autoremove_keeps_cask_formula_dependencies() {
+    // TEST: autoremove doesn't remove a formula an installed cask depends on, and
+    // --json --dry-run lists the remaining candidates with sizes
+    let temp = tempfile::TempDir::new().unwrap();
+    for name in ["libpcap", "orphan"] {
+        let keg = temp.path().join("Cellar").join(name).join("1.0");
+        std::fs::create_dir_all(keg.join("lib")).unwrap();
+        std::fs::write(keg.join("lib/data"), vec![0u8; 100]).unwrap();
+        std::fs::write(
+            keg.join("INSTALL_RECEIPT.json"),
+            r#"{"homebrew_version": "4.4.6", "installed_as_dependency": true, "installed_on_request": false}"#,
+        )
+        .unwrap();
+    }
+    let cask = temp.path().join("Caskroom/wireshark/4.4.0");
+    std::fs::create_dir_all(&cask).unwrap();
+    std::fs::write(
+        cask.join(".metadata.json"),
+        r#"{"token": "wireshark", "version": "4.4.0", "depends_on": {"formula": ["libpcap"]}}"#,
+    )
+    .unwrap();
+
+    let output = Command::new(bru_bin())
+        .args([
+            "--prefix",
+            temp.path().to_str().unwrap(),
+            "--json",
+            "autoremove",
+            "--dry-run",
+        ])
+        .output()
+        .expect("Failed to run bru autoremove");
+    assert!(output.status.success());
+
+    let candidates: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(candidates.as_array().unwrap().len(), 1);
+    assert_eq!(candidates[0]["name"], "orphan");
+    assert_eq!(candidates[0]["version"], "1.0");
+    assert!(candidates[0]["size_bytes"].as_u64().unwrap() >= 100);
+    assert!(temp.path().join("Cellar/orphan/1.0").exists());
+}
+
+#[test]
+fn test_upgrade_dry_run_json() {
+    // TEST: upgrade --dry-run --json prints a parseable plan; without --dry-run, --json
+    // refuses to run rather than upgrading with unparseable output
+    let temp = tempfile::TempDir::new().unwrap();
+    std::fs::create_dir_all(temp.path().join("Cellar")).unwrap();
+
+    let run = |args: &[&str]| {
+        Command::new(bru_bin())
+            .args(["--prefix", temp.path().to_str().unwrap(), "--json"])
+            .args(args)
+            .output()
+            .expect("Failed to run bru upgrade")
+    };
+
+    let output = run(&["upgrade", "--dry-run"]);
+    assert!(output.status.success());
+    let plan: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(plan, serde_json::json!([]));
+
+    let output = run(&["upgrade"]);
+    assert!(!output.status.success());
+    let sentinel: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(sentinel, serde_json::json!({"unsupported": true}));
+}
+
+#[test]
+fn test_install_keep_going_reports_failures() {
+    // TEST: --keep-going installs what it can, summarizes the failures, and exits non-zero
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+
+    // Tagged `all` so it installs on any platform
+    let bottle = temp.path().join("hello--2.12.all.bottle.tar.gz");
+    std::fs::write(
+        &bottle,
+        common::bottle(&[("hello/2.12/bin/hello", b"#!/bin/sh\necho hello\n", 0o755)]),
+    )
+    .unwrap();
+    let bad = temp.path().join("broken.tar.gz");
+    std::fs::copy(&bottle, &bad).unwrap();
+
+    let output = Command::new(bru_bin())
+        .args([
+            "--prefix",
+            prefix.to_str().unwrap(),
+            "install",
+            "--keep-going",
+        ])
+        .arg(&bad)
+        .arg(&bottle)
+        .env("HTTPS_PROXY", "http://127.0.0.1:9")
+        .env("NO_COLOR", "1")
+        .output()
+        .expect("Failed to run bru install");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(
+        !output.status.success(),
+        "partial failure should fail the run"
+    );
+    assert!(prefix.join("Cellar/hello/2.12/bin/hello").is_file());
+    assert!(
+        stdout.contains("1 formula failed to install"),
+        "missing summary: {}",
+        stdout
+    );
+    assert!(stdout.contains("broken.tar.gz"));
+}
+
+#[test]
+fn test_pin_cask_holds_it_back_from_upgrade() {
+    // TEST: pin --cask records the cask in var/homebrew/pinned_casks, upgrade --cask skips
+    // it (without any network access), and unpin --cask removes it again
+    let temp = tempfile::TempDir::new().unwrap();
+    let prefix = temp.path().join("prefix");
+    std::fs::create_dir_all(prefix.join("Caskroom/firefox/120.0")).unwrap();
+    let pinned_casks = prefix.join("var/homebrew/pinned_casks");
+
+    let run = |args: &[&str]| {
+        Command::new(bru_bin())
+            .args(["--prefix", prefix.to_str().unwrap()])
+            .args(args)
+            .env("HTTPS_PROXY", "http://127.0.0.1:9")
+            .env("NO_COLOR", "1")
+            .output()
+            .expect("Failed to run bru")
+    };
+
+    let output = run(&["pin", "--cask", "firefox"]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&pinned_casks).unwrap(), "firefox");
+    assert!(!prefix.join("var/homebrew/pinned_formulae").exists());
+
+    let output = run(&["upgrade", "--cask", "firefox"]);
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(output.status.success(), "upgrade failed: {}", stdout);
+    assert!(
+        stdout.contains("Not upgrading 1 pinned cask: firefox"),
+        "{}",
+        stdout
+    );
+    assert!(prefix.join("Caskroom/firefox/120.0").exists());
+
+    let output = run(&["unpin", "--cask", "firefox"]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read_to_string(&pinned_casks).unwrap(), "");
+}
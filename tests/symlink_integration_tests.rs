@@ -339,15 +339,11 @@ fn test_skip_already_correct_symlink() -> Result<()> {
 }
 
 #[test]
-#[ignore] // Requires setting HOMEBREW_PREFIX which conflicts with parallel test execution
+#[ignore] // Sets the process-wide prefix, which conflicts with parallel test execution
 fn test_optlink_creates_version_agnostic_symlinks() -> Result<()> {
     let temp_dir = TempDir::new()?;
     let prefix = temp_dir.path();
-
-    // Set HOMEBREW_PREFIX to temp directory for testing
-    unsafe {
-        std::env::set_var("HOMEBREW_PREFIX", prefix);
-    }
+    kombrucha::cellar::set_prefix(Some(prefix.to_path_buf()));
 
     create_mock_cellar(prefix, "testpkg", "1.0.0")?;
 
@@ -364,32 +360,23 @@ fn test_optlink_creates_version_agnostic_symlinks() -> Result<()> {
     let opt_target = fs::read_link(&opt_symlink)?;
     assert_eq!(opt_target, PathBuf::from("../Cellar/testpkg/1.0.0"));
 
-    // Verify linked/ symlink
+    // The linked/ record belongs to link_formula, not optlink
     let linked_symlink = prefix
         .join("var")
         .join("homebrew")
         .join("linked")
         .join("testpkg");
-    assert!(linked_symlink.symlink_metadata()?.is_symlink());
-    let linked_target = fs::read_link(&linked_symlink)?;
-    assert_eq!(
-        linked_target,
-        PathBuf::from("../../../Cellar/testpkg/1.0.0")
-    );
+    assert!(linked_symlink.symlink_metadata().is_err());
 
     Ok(())
 }
 
 #[test]
-#[ignore] // Requires setting HOMEBREW_PREFIX which conflicts with parallel test execution
+#[ignore] // Sets the process-wide prefix, which conflicts with parallel test execution
 fn test_optlink_updates_existing_symlinks() -> Result<()> {
     let temp_dir = TempDir::new()?;
     let prefix = temp_dir.path();
-
-    // Set HOMEBREW_PREFIX to temp directory for testing
-    unsafe {
-        std::env::set_var("HOMEBREW_PREFIX", prefix);
-    }
+    kombrucha::cellar::set_prefix(Some(prefix.to_path_buf()));
 
     create_mock_cellar(prefix, "testpkg", "1.0.0")?;
     create_mock_cellar(prefix, "testpkg", "2.0.0")?;
@@ -412,39 +399,24 @@ fn test_optlink_updates_existing_symlinks() -> Result<()> {
     let opt_target = fs::read_link(&opt_symlink)?;
     assert_eq!(opt_target, PathBuf::from("../Cellar/testpkg/2.0.0"));
 
-    // Verify linked/ also points to v2.0.0
-    let linked_symlink = prefix
-        .join("var")
-        .join("homebrew")
-        .join("linked")
-        .join("testpkg");
-    let linked_target = fs::read_link(&linked_symlink)?;
-    assert_eq!(
-        linked_target,
-        PathBuf::from("../../../Cellar/testpkg/2.0.0")
-    );
-
     Ok(())
 }
 
 #[test]
-#[ignore] // Requires setting HOMEBREW_PREFIX which conflicts with parallel test execution
+#[ignore] // Sets the process-wide prefix, which conflicts with parallel test execution
 fn test_unoptlink_removes_symlinks() -> Result<()> {
     let temp_dir = TempDir::new()?;
     let prefix = temp_dir.path();
-
-    // Set HOMEBREW_PREFIX to temp directory for testing
-    unsafe {
-        std::env::set_var("HOMEBREW_PREFIX", prefix);
-    }
+    kombrucha::cellar::set_prefix(Some(prefix.to_path_buf()));
 
     create_mock_cellar(prefix, "testpkg", "1.0.0")?;
 
     fs::create_dir_all(prefix.join("opt"))?;
     fs::create_dir_all(prefix.join("var").join("homebrew").join("linked"))?;
 
-    // Create symlinks
+    // Create symlinks; linking the keg records it under linked/
     kombrucha::optlink("testpkg", "1.0.0")?;
+    kombrucha::link_formula("testpkg", "1.0.0")?;
 
     // Verify they exist
     let opt_symlink = prefix.join("opt").join("testpkg");
@@ -465,3 +437,307 @@ fn test_unoptlink_removes_symlinks() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_link_and_optlink_in_explicit_prefix() -> Result<()> {
+    use kombrucha::symlink;
+
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+
+    // Explicit-prefix variants don't touch HOMEBREW_PREFIX, so this runs in parallel
+    create_mock_cellar(prefix, "testpkg", "1.0.0")?;
+
+    let linked = symlink::link_formula_in(prefix, "testpkg", "1.0.0")?;
+    assert_eq!(linked.len(), 3);
+    symlink::optlink_in(prefix, "testpkg", "1.0.0")?;
+
+    let target_bin = prefix.join("bin").join("test-binary");
+    assert_eq!(
+        fs::read_link(&target_bin)?,
+        PathBuf::from("../Cellar/testpkg/1.0.0/bin/test-binary")
+    );
+    assert!(prefix.join("opt").join("testpkg").exists());
+
+    symlink::unlink_formula_in(prefix, "testpkg", "1.0.0")?;
+    symlink::unoptlink_in(prefix, "testpkg")?;
+
+    assert!(target_bin.symlink_metadata().is_err());
+    assert!(
+        prefix
+            .join("opt")
+            .join("testpkg")
+            .symlink_metadata()
+            .is_err()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_link_targets_lists_links_without_creating_them() -> Result<()> {
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+    create_mock_cellar(prefix, "testpkg", "1.0.0")?;
+
+    let mut targets: Vec<PathBuf> =
+        kombrucha::symlink::link_targets_in(prefix, "testpkg", "1.0.0")?
+            .into_iter()
+            .map(|(_, target)| target)
+            .collect();
+    targets.sort();
+
+    assert_eq!(
+        targets,
+        vec![
+            prefix.join("bin/test-binary"),
+            prefix.join("lib/libtest.so"),
+            prefix.join("share/man/man1/test.1"),
+        ]
+    );
+    assert!(!prefix.join("bin").exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_prune_prefix_removes_only_homebrew_leftovers() -> Result<()> {
+    use kombrucha::symlink::{prune_broken_in, prune_empty_dirs_in};
+
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+    create_mock_cellar(prefix, "testpkg", "1.0.0")?;
+    fs::create_dir_all(prefix.join("bin"))?;
+    fs::create_dir_all(prefix.join("share/doc/gone/html"))?;
+    fs::create_dir_all(prefix.join("share/doc/kept"))?;
+    fs::create_dir_all(prefix.join("etc/empty.d"))?;
+    fs::create_dir_all(prefix.join("opt"))?;
+
+    // Live link, dangling Homebrew links, and a dangling link owned by someone else
+    unix_fs::symlink(
+        "../Cellar/testpkg/1.0.0/bin/test-binary",
+        prefix.join("bin/live"),
+    )?;
+    unix_fs::symlink("../Cellar/removed/2.0/bin/tool", prefix.join("bin/stale"))?;
+    unix_fs::symlink(
+        "../../../../Cellar/removed/2.0/share/doc/x",
+        prefix.join("share/doc/gone/html/x"),
+    )?;
+    unix_fs::symlink("../Cellar/removed/2.0", prefix.join("opt/removed"))?;
+    unix_fs::symlink("/nonexistent/tool", prefix.join("bin/foreign"))?;
+    fs::write(prefix.join("share/doc/kept/README"), "user file")?;
+
+    // Dry run reports without removing
+    let links = prune_broken_in(prefix, true)?;
+    let dirs = prune_empty_dirs_in(prefix, true)?;
+    assert_eq!(links.len(), 3);
+    assert_eq!(
+        dirs,
+        vec![
+            prefix.join("share/doc/gone/html"),
+            prefix.join("share/doc/gone")
+        ]
+    );
+    assert!(prefix.join("bin/stale").symlink_metadata().is_ok());
+
+    // Real run
+    let mut links = prune_broken_in(prefix, false)?;
+    links.sort();
+    assert_eq!(
+        links,
+        vec![
+            prefix.join("bin/stale"),
+            prefix.join("opt/removed"),
+            prefix.join("share/doc/gone/html/x"),
+        ]
+    );
+    assert_eq!(prune_empty_dirs_in(prefix, false)?.len(), 2);
+
+    assert!(!prefix.join("share/doc/gone").exists());
+    assert!(prefix.join("bin/live").exists());
+    assert!(prefix.join("bin/foreign").symlink_metadata().is_ok());
+    assert!(prefix.join("share/doc/kept/README").exists());
+    assert!(prefix.join("etc/empty.d").is_dir());
+    assert!(prefix.join("opt").is_dir());
+
+    Ok(())
+}
+
+#[test]
+fn test_link_maintains_linked_keg_record() -> Result<()> {
+    use kombrucha::symlink::{link_formula_in, link_formula_with_manifest_in, unlink_formula_in};
+
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+    create_mock_cellar(prefix, "testpkg", "1.0.0")?;
+    create_mock_cellar(prefix, "testpkg", "2.0.0")?;
+    let record = prefix.join("var/homebrew/linked/testpkg");
+
+    link_formula_in(prefix, "testpkg", "1.0.0")?;
+    assert_eq!(
+        fs::read_link(&record)?,
+        PathBuf::from("../../../Cellar/testpkg/1.0.0")
+    );
+    assert!(record.exists(), "record must resolve to the keg");
+
+    unlink_formula_in(prefix, "testpkg", "1.0.0")?;
+    assert!(record.symlink_metadata().is_err());
+
+    // Upgrade-style relink: unlinking the old version keeps the new version's record
+    let manifest = vec![PathBuf::from("bin/test-binary")];
+    link_formula_with_manifest_in(prefix, "testpkg", "2.0.0", &manifest)?;
+    unlink_formula_in(prefix, "testpkg", "1.0.0")?;
+    assert_eq!(
+        fs::read_link(&record)?,
+        PathBuf::from("../../../Cellar/testpkg/2.0.0")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_link_man_pages_and_copies_etc_files() -> Result<()> {
+    use kombrucha::symlink::{link_formula_in, link_formula_with_manifest_in, unlink_formula_in};
+
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+    let keg = create_mock_cellar(prefix, "testpkg", "1.0.0")?;
+    fs::create_dir_all(keg.join("etc/testpkg"))?;
+    fs::write(keg.join("etc/testpkg/testpkg.conf"), "port = 80\n")?;
+    fs::create_dir_all(keg.join("lib/pkgconfig"))?;
+    fs::write(keg.join("lib/pkgconfig/testpkg.pc"), "Name: testpkg\n")?;
+    fs::create_dir_all(keg.join("share/info"))?;
+    fs::write(keg.join("share/info/testpkg.info"), "info page")?;
+    fs::write(keg.join("share/info/dir"), "shared index")?;
+
+    let linked = link_formula_in(prefix, "testpkg", "1.0.0")?;
+
+    // Man, info and pkg-config files are symlinked into real directories
+    let man_page = prefix.join("share/man/man1/test.1");
+    assert_eq!(
+        fs::read_link(&man_page)?,
+        PathBuf::from("../../../Cellar/testpkg/1.0.0/share/man/man1/test.1")
+    );
+    assert!(!prefix.join("share/man").symlink_metadata()?.is_symlink());
+    assert!(
+        prefix
+            .join("lib/pkgconfig/testpkg.pc")
+            .symlink_metadata()?
+            .is_symlink()
+    );
+    assert!(
+        prefix
+            .join("share/info/testpkg.info")
+            .symlink_metadata()?
+            .is_symlink()
+    );
+    assert!(prefix.join("share/info/dir").symlink_metadata().is_err());
+
+    // Config files are real copies, not links, and aren't reported as linked
+    let conf = prefix.join("etc/testpkg/testpkg.conf");
+    assert!(!conf.symlink_metadata()?.is_symlink());
+    assert_eq!(fs::read_to_string(&conf)?, "port = 80\n");
+    assert!(!linked.contains(&conf));
+
+    // User edits survive relinking; the new default is written beside them
+    fs::write(&conf, "port = 8080\n")?;
+    fs::write(keg.join("etc/testpkg/testpkg.conf"), "port = 81\n")?;
+    let manifest = vec![PathBuf::from("etc/testpkg/testpkg.conf")];
+    link_formula_with_manifest_in(prefix, "testpkg", "1.0.0", &manifest)?;
+    assert_eq!(fs::read_to_string(&conf)?, "port = 8080\n");
+    assert_eq!(
+        fs::read_to_string(prefix.join("etc/testpkg/testpkg.conf.default"))?,
+        "port = 81\n"
+    );
+
+    // Unlinking removes the symlinks but keeps the config
+    unlink_formula_in(prefix, "testpkg", "1.0.0")?;
+    assert!(man_page.symlink_metadata().is_err());
+    assert!(conf.exists());
+
+    Ok(())
+}
+
+#[test]
+fn test_install_config_files_for_unlinked_keg() -> Result<()> {
+    use kombrucha::symlink::install_config_files_in;
+
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+    let keg = create_mock_cellar(prefix, "kegonly", "1.0.0")?;
+    fs::create_dir_all(keg.join("etc/kegonly"))?;
+    fs::write(keg.join("etc/kegonly/kegonly.conf"), "port = 80\n")?;
+
+    // A keg-only formula gets its config, but nothing else is linked
+    let conf = prefix.join("etc/kegonly/kegonly.conf");
+    assert_eq!(
+        install_config_files_in(prefix, "kegonly", "1.0.0")?,
+        std::slice::from_ref(&conf)
+    );
+    assert!(!conf.symlink_metadata()?.is_symlink());
+    assert_eq!(fs::read_to_string(&conf)?, "port = 80\n");
+    assert!(prefix.join("bin/test-binary").symlink_metadata().is_err());
+
+    // User edits are kept, as when linking
+    fs::write(&conf, "port = 8080\n")?;
+    fs::write(keg.join("etc/kegonly/kegonly.conf"), "port = 81\n")?;
+    install_config_files_in(prefix, "kegonly", "1.0.0")?;
+    assert_eq!(fs::read_to_string(&conf)?, "port = 8080\n");
+    assert_eq!(
+        fs::read_to_string(prefix.join("etc/kegonly/kegonly.conf.default"))?,
+        "port = 81\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_link_conflicts_name_the_owning_keg() -> Result<()> {
+    use kombrucha::symlink::{LinkConflict, link_conflicts_in, link_formula_in};
+
+    let temp_dir = TempDir::new()?;
+    let prefix = temp_dir.path();
+    create_mock_cellar(prefix, "testpkg", "1.0.0")?;
+    create_mock_cellar(prefix, "testpkg", "2.0.0")?;
+    create_mock_cellar(prefix, "otherpkg", "1.0.0")?;
+
+    // Another version of the same formula is relinked, not a conflict
+    link_formula_in(prefix, "testpkg", "1.0.0")?;
+    assert!(link_conflicts_in(prefix, "testpkg", "2.0.0")?.is_empty());
+
+    // otherpkg ships the same files; a real file is in the way too
+    fs::remove_file(prefix.join("lib/libtest.so"))?;
+    fs::write(prefix.join("lib/libtest.so"), "not a link")?;
+    let mut conflicts = link_conflicts_in(prefix, "otherpkg", "1.0.0")?;
+    conflicts.sort_by(|a, b| a.target.cmp(&b.target));
+    assert_eq!(
+        conflicts,
+        vec![
+            LinkConflict {
+                target: prefix.join("bin/test-binary"),
+                owner: Some("testpkg".to_string()),
+            },
+            LinkConflict {
+                target: prefix.join("lib/libtest.so"),
+                owner: None,
+            },
+            LinkConflict {
+                target: prefix.join("share/man/man1/test.1"),
+                owner: Some("testpkg".to_string()),
+            },
+        ]
+    );
+
+    // What `link --overwrite` does: remove the conflicts, then link
+    for conflict in &conflicts {
+        fs::remove_file(&conflict.target)?;
+    }
+    link_formula_in(prefix, "otherpkg", "1.0.0")?;
+    assert_eq!(
+        fs::read_link(prefix.join("bin/test-binary"))?,
+        PathBuf::from("../Cellar/otherpkg/1.0.0/bin/test-binary")
+    );
+    assert!(link_conflicts_in(prefix, "otherpkg", "1.0.0")?.is_empty());
+
+    Ok(())
+}
@@ -1,5 +1,7 @@
 // Unit tests for core functionality
 
+mod common;
+
 #[cfg(test)]
 mod version_tests {
     // Test strip_bottle_revision function from commands.rs
@@ -282,6 +284,51 @@ mod api_parsing_tests {
         );
     }
 
+    #[test]
+    fn test_parse_formula_brief_skips_unused_fields() {
+        let json = r#"[
+            {
+                "name": "wget",
+                "desc": "Internet file retriever",
+                "versions": {"stable": "1.21.4", "bottle": true},
+                "dependencies": ["openssl@3", "libidn2"],
+                "bottle": {"stable": {"rebuild": 0, "files": {}}},
+                "caveats": null
+            },
+            {"name": "minimal"}
+        ]"#;
+
+        let formulae: Vec<kombrucha::FormulaBrief> =
+            serde_json::from_reader(std::io::Cursor::new(json)).unwrap();
+
+        assert_eq!(formulae.len(), 2);
+        assert_eq!(formulae[0].name, "wget");
+        assert_eq!(formulae[0].desc.as_deref(), Some("Internet file retriever"));
+        assert_eq!(formulae[0].dependencies, vec!["openssl@3", "libidn2"]);
+        assert!(formulae[1].desc.is_none());
+        assert!(formulae[1].build_dependencies.is_empty());
+    }
+
+    #[test]
+    fn test_parse_cask_depends_on() {
+        let json = json!([
+            {
+                "token": "wireshark",
+                "depends_on": {
+                    "formula": ["libpcap"],
+                    "macos": {">=": ["10.15"]}
+                }
+            },
+            {"token": "firefox"}
+        ]);
+
+        let casks: Vec<kombrucha::api::Cask> = serde_json::from_value(json).unwrap();
+
+        assert_eq!(casks[0].depends_on.formula, vec!["libpcap"]);
+        assert!(casks[0].depends_on.cask.is_empty());
+        assert!(casks[1].depends_on.formula.is_empty());
+    }
+
     #[test]
     fn test_parse_formula_with_missing_optional_fields() {
         let json = json!({
@@ -572,6 +619,133 @@ mod download_tests {
         let repository = format!("homebrew/core/{}", formula_name);
         assert_eq!(repository, "homebrew/core/python@3.11");
     }
+
+    /// Serve `body` over HTTP, honoring `Range: bytes=N-`; returns the URL and the
+    /// Range headers received
+    fn serve(body: &'static [u8]) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        serve_as(body, "application/octet-stream")
+    }
+
+    /// [`serve`] with an explicit `Content-Type`
+    fn serve_as(
+        body: &'static [u8],
+        content_type: &'static str,
+    ) -> (String, std::sync::Arc<std::sync::Mutex<Vec<String>>>) {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/artifact.dmg", listener.local_addr().unwrap());
+        let ranges = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = ranges.clone();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut offset = 0;
+                loop {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                        break;
+                    }
+                    if let Some(range) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        offset = range.trim().trim_end_matches('-').parse().unwrap();
+                        seen.lock().unwrap().push(range.trim().to_string());
+                    }
+                }
+                let status = if offset > 0 {
+                    "206 Partial Content"
+                } else {
+                    "200 OK"
+                };
+                let rest = &body[offset..];
+                let head = format!(
+                    "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    content_type,
+                    rest.len()
+                );
+                stream.write_all(head.as_bytes()).unwrap();
+                stream.write_all(rest).unwrap();
+            }
+        });
+
+        (url, ranges)
+    }
+
+    fn sha256(data: &[u8]) -> String {
+        use sha2::{Digest, Sha256};
+        format!("{:x}", Sha256::digest(data))
+    }
+
+    #[tokio::test]
+    async fn test_download_verified_rejects_checksum_mismatch() {
+        let (url, _) = serve(b"corrupted dmg contents");
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest = temp.path().join("artifact.dmg");
+        let client = reqwest::Client::new();
+
+        let expected = sha256(b"the real dmg");
+        let err = kombrucha::download::download_verified(&client, &url, &dest, Some(&expected))
+            .await
+            .unwrap_err();
+
+        assert!(err.to_string().contains("Checksum mismatch"), "{err}");
+        // Nothing usable (or mountable) is left behind
+        assert!(!dest.exists());
+        assert!(!temp.path().join("artifact.dmg.part").exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_rejects_html_and_empty_responses() {
+        use kombrucha::BruError;
+
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest = temp.path().join("artifact.dmg");
+        let client = reqwest::Client::new();
+
+        // An error page with a 200 is rejected before it is saved, even unverified
+        let (url, _) = serve_as(b"<html>rate limited</html>", "text/html; charset=utf-8");
+        let err = kombrucha::download::download_verified(&client, &url, &dest, None)
+            .await
+            .unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<BruError>(),
+                Some(BruError::InvalidDownload { reason, .. }) if reason.contains("web page")
+            ),
+            "{err}"
+        );
+        assert!(!dest.exists());
+        assert!(!temp.path().join("artifact.dmg.part").exists());
+
+        let (url, _) = serve(b"");
+        let err = kombrucha::download::download_verified(&client, &url, &dest, None)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("empty response"), "{err}");
+        assert!(!dest.exists());
+    }
+
+    #[tokio::test]
+    async fn test_download_verified_resumes_partial_download() {
+        let body = b"hello world, this is a cask artifact";
+        let (url, ranges) = serve(body);
+        let temp = tempfile::TempDir::new().unwrap();
+        let dest = temp.path().join("artifact.dmg");
+        std::fs::write(temp.path().join("artifact.dmg.part"), &body[..6]).unwrap();
+        let client = reqwest::Client::new();
+
+        let path =
+            kombrucha::download::download_verified(&client, &url, &dest, Some(&sha256(body)))
+                .await
+                .unwrap();
+
+        assert_eq!(path, dest);
+        assert_eq!(std::fs::read(&dest).unwrap(), body);
+        assert_eq!(*ranges.lock().unwrap(), vec!["6-"]);
+    }
 }
 
 #[cfg(test)]
@@ -837,6 +1011,84 @@ mod api_tests {
         assert_eq!(url, "https://formulae.brew.sh/api/formula/python@3.11.json");
     }
 
+    #[test]
+    fn test_github_repo_slug_from_homepage() {
+        use kombrucha::api::github_repo_slug;
+
+        assert_eq!(
+            github_repo_slug("https://github.com/BurntSushi/ripgrep"),
+            Some(("BurntSushi".to_string(), "ripgrep".to_string()))
+        );
+        assert_eq!(
+            github_repo_slug("https://github.com/jqlang/jq.git"),
+            Some(("jqlang".to_string(), "jq".to_string()))
+        );
+        assert_eq!(
+            github_repo_slug("https://github.com/cli/cli#readme"),
+            Some(("cli".to_string(), "cli".to_string()))
+        );
+        assert_eq!(github_repo_slug("https://github.com/nijaru"), None);
+        assert_eq!(github_repo_slug("https://www.gnu.org/software/wget/"), None);
+    }
+
+    #[tokio::test]
+    async fn test_shared_client_reuses_connections() {
+        use kombrucha::BrewApi;
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpListener;
+        use std::sync::Arc;
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Minimal keep-alive HTTP server that counts accepted connections
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                std::thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                            return;
+                        }
+                        if line == "\r\n" {
+                            let response = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                            stream.write_all(response.as_bytes()).unwrap();
+                        }
+                    }
+                });
+            }
+        });
+
+        let api = BrewApi::new().unwrap();
+        let url = format!("http://{}/", addr);
+        for _ in 0..3 {
+            let body = api.client().get(&url).send().await.unwrap().text().await;
+            assert_eq!(body.unwrap(), "ok");
+        }
+
+        // Bottle and cask downloads go through the same client and pool
+        let temp = tempfile::TempDir::new().unwrap();
+        for file in ["one.tar.gz", "two.dmg"] {
+            let dest = temp.path().join(file);
+            kombrucha::download::download_verified(
+                api.client(),
+                &format!("{}{}", url, file),
+                &dest,
+                None,
+            )
+            .await
+            .unwrap();
+            assert_eq!(std::fs::read_to_string(dest).unwrap(), "ok");
+        }
+
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+    }
+
     #[test]
     fn test_cache_size_constants() {
         let formula_cache_size = 1000;
@@ -1159,3 +1411,109 @@ mod edge_case_tests {
         assert!(result4.is_none());
     }
 }
+
+mod package_manager_tests {
+    use kombrucha::{BrewContext, Formula, InstallOptions, install_bottle};
+    use std::path::Path;
+
+    /// A bottle holding `jq/1.7.1/bin/jq`, a script whose shebang needs relocating
+    fn write_bottle(path: &Path) {
+        let script = b"#!@@HOMEBREW_PREFIX@@/bin/sh\necho jq\n";
+        let bottle = crate::common::bottle(&[("jq/1.7.1/bin/jq", script, 0o755)]);
+        std::fs::write(path, bottle).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_install_bottle_pours_local_bottle() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path().join("brew");
+        let bottle = temp.path().join("jq--1.7.1.all.bottle.tar.gz");
+        write_bottle(&bottle);
+        // oniguruma is installed; the receipt should record it
+        std::fs::create_dir_all(prefix.join("Cellar/oniguruma/6.9.9/lib")).unwrap();
+
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "jq",
+            "versions": {"stable": "1.7.1", "bottle": true},
+            "dependencies": ["oniguruma"],
+        }))
+        .unwrap();
+        let ctx = BrewContext::with_prefix(&prefix).unwrap();
+        let options = InstallOptions {
+            bottle: Some(bottle),
+            on_request: false,
+            ..Default::default()
+        };
+
+        let result = install_bottle(&ctx, &formula, &options).await.unwrap();
+        assert_eq!(result.version, "1.7.1");
+        assert_eq!(result.path, prefix.join("Cellar/jq/1.7.1"));
+        assert!(result.linked);
+
+        // Relocated, linked, and opt-linked into the prefix
+        let script = std::fs::read_to_string(result.path.join("bin/jq")).unwrap();
+        assert!(script.starts_with(&format!("#!{}/bin/sh", prefix.display())));
+        assert!(prefix.join("bin/jq").symlink_metadata().is_ok());
+        assert!(prefix.join("opt/jq").symlink_metadata().is_ok());
+
+        let receipt = kombrucha::InstallReceipt::read(&result.path).unwrap();
+        assert!(!receipt.installed_on_request);
+        assert!(receipt.installed_as_dependency);
+        assert_eq!(receipt.runtime_dependencies.len(), 1);
+        assert_eq!(receipt.runtime_dependencies[0].full_name, "oniguruma");
+        assert_eq!(receipt.runtime_dependencies[0].version, "6.9.9");
+    }
+
+    #[tokio::test]
+    async fn test_install_bottle_removes_keg_when_linking_fails() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path().join("brew");
+        let bottle = temp.path().join("jq--1.7.1.all.bottle.tar.gz");
+        write_bottle(&bottle);
+        // A file where bin/ should be makes linking fail
+        std::fs::create_dir_all(&prefix).unwrap();
+        std::fs::write(prefix.join("bin"), "").unwrap();
+
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "jq",
+            "versions": {"stable": "1.7.1", "bottle": true},
+        }))
+        .unwrap();
+        let ctx = BrewContext::with_prefix(&prefix).unwrap();
+        let options = InstallOptions {
+            bottle: Some(bottle),
+            ..Default::default()
+        };
+
+        assert!(install_bottle(&ctx, &formula, &options).await.is_err());
+        assert!(!prefix.join("Cellar/jq").exists());
+        assert!(prefix.join("opt/jq").symlink_metadata().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_install_bottle_keeps_existing_keg_when_linking_fails() {
+        let temp = tempfile::TempDir::new().unwrap();
+        let prefix = temp.path().join("brew");
+        let bottle = temp.path().join("jq--1.7.1.all.bottle.tar.gz");
+        write_bottle(&bottle);
+        // jq 1.7.1 is already installed when the reinstall's linking fails
+        let keg = prefix.join("Cellar/jq/1.7.1");
+        std::fs::create_dir_all(keg.join("share")).unwrap();
+        std::fs::write(keg.join("share/README"), "jq").unwrap();
+        std::fs::write(prefix.join("bin"), "").unwrap();
+
+        let formula: Formula = serde_json::from_value(serde_json::json!({
+            "name": "jq",
+            "versions": {"stable": "1.7.1", "bottle": true},
+        }))
+        .unwrap();
+        let ctx = BrewContext::with_prefix(&prefix).unwrap();
+        let options = InstallOptions {
+            bottle: Some(bottle),
+            ..Default::default()
+        };
+
+        assert!(install_bottle(&ctx, &formula, &options).await.is_err());
+        assert!(keg.join("share/README").exists());
+    }
+}